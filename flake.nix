@@ -27,6 +27,7 @@
           buildInputs = [
             pkgs.fontconfig
             pkgs.sqlite
+            pkgs.sqlcipher
           ];
           doCheck = true;
           checkInputs = [
@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
 
 use crate::metrics::{MetricKind, MetricSample};
-use serde_json::json;
+use serde_json::Value;
 
 fn sum_or_none(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
     let mut total = 0.0;
@@ -108,14 +108,20 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
         let sum_energy_full = sum_or_none(energy_full.iter().map(|m| m.value));
         let sum_energy_full_design = sum_or_none(energy_full_design.iter().map(|m| m.value));
 
-        let mut sources: Vec<&str> = group.iter().map(|m| m.source.as_str()).collect();
+        let battery_group: Vec<&MetricSample> = group
+            .iter()
+            .filter(|m| battery_kinds.contains(&m.kind))
+            .copied()
+            .collect();
+
+        let mut sources: Vec<&str> = battery_group.iter().map(|m| m.source.as_str()).collect();
         sources.sort();
         sources.dedup();
         let combined_source = sources.join("+");
 
         let mut statuses = std::collections::BTreeSet::new();
-        for metric in &group {
-            if let Some(status) = metric.details.get("status").and_then(|v| v.as_str()) {
+        for metric in &battery_group {
+            if let Some(status) = metric.status.as_deref() {
                 statuses.insert(status);
             }
         }
@@ -127,8 +133,6 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
             Some("mixed".to_string())
         };
 
-        let details = json!({ "status": status });
-
         let mut computed_percentage = percent(sum_energy_now, sum_energy_full);
         if computed_percentage.is_none() {
             computed_percentage = avg_or_none(percentages.iter().map(|m| m.value));
@@ -141,6 +145,7 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
 
         let avg_capacity = avg_or_none(capacity.iter().map(|m| m.value));
 
+        let combined_from = aggregated.len();
         if let Some(pct) = computed_percentage {
             aggregated.push(MetricSample::new(
                 ts,
@@ -148,7 +153,7 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
                 &combined_source,
                 Some(pct),
                 Some("%"),
-                details.clone(),
+                Value::Null,
             ));
         }
 
@@ -159,7 +164,7 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
                 &combined_source,
                 Some(cap),
                 Some("%"),
-                details.clone(),
+                Value::Null,
             ));
         }
 
@@ -170,7 +175,7 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
                 &combined_source,
                 Some(h),
                 Some("%"),
-                details.clone(),
+                Value::Null,
             ));
         }
 
@@ -181,7 +186,7 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
                 &combined_source,
                 Some(e),
                 Some("Wh"),
-                details.clone(),
+                Value::Null,
             ));
         }
 
@@ -192,7 +197,7 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
                 &combined_source,
                 Some(e),
                 Some("Wh"),
-                details.clone(),
+                Value::Null,
             ));
         }
 
@@ -203,9 +208,18 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
                 &combined_source,
                 Some(e),
                 Some("Wh"),
-                details.clone(),
+                Value::Null,
             ));
         }
+
+        // The packs were read in one collection run, on one host and boot
+        if let Some(first) = battery_group.first() {
+            for sample in &mut aggregated[combined_from..] {
+                sample.status = status.clone();
+                sample.host = first.host.clone();
+                sample.boot = first.boot.clone();
+            }
+        }
     }
 
     aggregated
@@ -214,7 +228,6 @@ pub fn aggregate_multi_device_metrics(metrics: &[MetricSample]) -> Vec<MetricSam
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json::json;
 
     fn battery_metric(
         ts: f64,
@@ -234,7 +247,15 @@ mod tests {
                 | MetricKind::BatteryHealth => Some("%".to_string()),
                 _ => Some("Wh".to_string()),
             },
-            details: json!({"status": status}),
+            details: Value::Null,
+            rx_bytes: None,
+            tx_bytes: None,
+            total_bytes: None,
+            status: Some(status.to_string()),
+            histogram: None,
+            label: None,
+            host: None,
+            boot: None,
         }
     }
 
@@ -320,10 +341,38 @@ mod tests {
             .unwrap();
         assert_eq!(capacity.value, Some(92.5));
 
-        let status = percentage.details.get("status").unwrap().as_str();
+        let status = percentage.status.as_deref();
         assert_eq!(status, Some("mixed"));
     }
 
+    #[test]
+    fn aggregate_ignores_peripheral_status_and_source() {
+        let metrics = vec![
+            battery_metric(1.0, MetricKind::BatteryEnergyNow, "BAT0", 10.0, "Charging"),
+            battery_metric(1.0, MetricKind::BatteryEnergyFull, "BAT0", 20.0, "Charging"),
+            MetricSample::new(
+                1.0,
+                MetricKind::PeripheralBattery,
+                "Mouse",
+                Some(40.0),
+                Some("%"),
+                Value::Null,
+            )
+            .with_status("Discharging"),
+        ];
+
+        let aggregated = aggregate_multi_device_metrics(&metrics);
+        let percentage = aggregated
+            .iter()
+            .find(|m| m.kind == MetricKind::BatteryPercentage)
+            .unwrap();
+        assert_eq!(percentage.source, "BAT0");
+        assert_eq!(percentage.status.as_deref(), Some("Charging"));
+        assert!(aggregated
+            .iter()
+            .any(|m| m.kind == MetricKind::PeripheralBattery && m.source == "Mouse"));
+    }
+
     #[test]
     fn aggregate_multi_device_metrics_groups_by_timestamp() {
         let metrics = vec![
@@ -0,0 +1,277 @@
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use log::warn;
+use rusqlite::Connection;
+
+use crate::config::AlertsConfig;
+use crate::db;
+
+/// Title of the daily summary sent in `[alerts] digest` mode.
+const DIGEST_TITLE: &str = "symmetri daily digest";
+const DIGEST_SECONDS: f64 = 86400.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn urgency(self) -> &'static str {
+        match self {
+            Severity::Warning => "normal",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn from_label(raw: &str) -> Self {
+        if raw == "critical" {
+            Severity::Critical
+        } else {
+            Severity::Warning
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+}
+
+pub trait AlertSink {
+    fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+struct LogSink;
+
+impl AlertSink for LogSink {
+    fn send(&self, alert: &Alert) -> Result<()> {
+        warn!("{}: {}", alert.title, alert.message);
+        Ok(())
+    }
+}
+
+/// Runs a command (e.g. `notify-send`) with the alert title and message appended.
+/// `SYMMETRI_ALERT_SEVERITY` is set to `normal` or `critical` for the child process.
+struct CommandSink {
+    program: String,
+    args: Vec<String>,
+}
+
+impl AlertSink for CommandSink {
+    fn send(&self, alert: &Alert) -> Result<()> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(&alert.title)
+            .arg(&alert.message)
+            .env("SYMMETRI_ALERT_SEVERITY", alert.severity.urgency())
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!(
+                "alert command {} exited with {status}",
+                self.program
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn sinks_from_config(config: &AlertsConfig) -> Vec<Box<dyn AlertSink>> {
+    let mut sinks: Vec<Box<dyn AlertSink>> = Vec::new();
+    if config.log {
+        sinks.push(Box::new(LogSink));
+    }
+    if let Some((program, args)) = config.command.split_first() {
+        sinks.push(Box::new(CommandSink {
+            program: program.clone(),
+            args: args.to_vec(),
+        }));
+    }
+    sinks
+}
+
+/// Deliver an alert to every sink; a failing sink is logged and does not stop the others.
+pub fn dispatch(sinks: &[Box<dyn AlertSink>], alert: &Alert) {
+    for sink in sinks {
+        if let Err(err) = sink.send(alert) {
+            warn!("Failed to deliver alert `{}`: {err}", alert.title);
+        }
+    }
+}
+
+/// One alert summing up `held`, grouped by title in the order they were first raised.
+fn digest(held: &[Alert]) -> Alert {
+    let mut groups: Vec<(&Alert, usize)> = Vec::new();
+    for alert in held {
+        match groups
+            .iter_mut()
+            .find(|(first, _)| first.title == alert.title)
+        {
+            // Keep the latest message of each title
+            Some(group) => {
+                group.0 = alert;
+                group.1 += 1;
+            }
+            None => groups.push((alert, 1)),
+        }
+    }
+    let lines: Vec<String> = groups
+        .iter()
+        .map(|(alert, count)| match count {
+            1 => format!("{}: {}", alert.title, alert.message),
+            _ => format!("{}: {} (x{count})", alert.title, alert.message),
+        })
+        .collect();
+    Alert {
+        severity: if held.iter().any(|a| a.severity == Severity::Critical) {
+            Severity::Critical
+        } else {
+            Severity::Warning
+        },
+        title: DIGEST_TITLE.to_string(),
+        message: lines.join("\n"),
+    }
+}
+
+/// Deliver `alerts` raised at `now` within the `[alerts]` limits: an alert repeating within
+/// `cooldown` or beyond `max_per_hour` is held back and counted in the next delivery of its
+/// title, and in digest mode everything is held for one summary a day. The log in the
+/// database keeps the limits across runs.
+pub fn dispatch_limited(
+    conn: &Connection,
+    sinks: &[Box<dyn AlertSink>],
+    config: &AlertsConfig,
+    alerts: &[Alert],
+    now: f64,
+) -> Result<()> {
+    for alert in alerts {
+        let last_sent = db::last_alert_sent(conn, &alert.title)?;
+        let cooling = last_sent.is_some_and(|ts| now - ts < config.cooldown as f64);
+        let over_limit = db::alerts_sent_since(conn, now - 3600.0)? >= config.max_per_hour as usize;
+        if config.digest || cooling || over_limit {
+            db::record_alert(conn, alert, now, false)?;
+            continue;
+        }
+        let held = db::held_alerts_since(conn, last_sent, Some(&alert.title))?.len();
+        let mut delivered = alert.clone();
+        if held > 0 {
+            delivered.message = format!("{} ({held} more held back)", alert.message);
+        }
+        dispatch(sinks, &delivered);
+        db::record_alert(conn, alert, now, true)?;
+    }
+
+    if config.digest {
+        let last_digest = db::last_alert_sent(conn, DIGEST_TITLE)?;
+        if last_digest.is_none_or(|ts| now - ts >= DIGEST_SECONDS) {
+            let held = db::held_alerts_since(conn, last_digest, None)?;
+            if !held.is_empty() {
+                let summary = digest(&held);
+                dispatch(sinks, &summary);
+                db::record_alert(conn, &summary, now, true)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn sinks_follow_config() {
+        let none = AlertsConfig {
+            log: false,
+            ..AlertsConfig::default()
+        };
+        assert!(sinks_from_config(&none).is_empty());
+
+        let both = AlertsConfig {
+            log: true,
+            command: vec!["true".to_string()],
+            ..AlertsConfig::default()
+        };
+        let sinks = sinks_from_config(&both);
+        assert_eq!(sinks.len(), 2);
+        let alert = Alert {
+            severity: Severity::Warning,
+            title: "test".to_string(),
+            message: "message".to_string(),
+        };
+        for sink in &sinks {
+            sink.send(&alert).unwrap();
+        }
+    }
+
+    /// Keeps delivered alerts for inspection.
+    struct Recorder(Arc<Mutex<Vec<Alert>>>);
+
+    impl AlertSink for Recorder {
+        fn send(&self, alert: &Alert) -> Result<()> {
+            self.0.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn repeats_are_held_back_and_counted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let conn = db::init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sinks: Vec<Box<dyn AlertSink>> = vec![Box::new(Recorder(delivered.clone()))];
+        let alert = |title: &str, severity| Alert {
+            severity,
+            title: title.to_string(),
+            message: "hot".to_string(),
+        };
+        let config = AlertsConfig {
+            cooldown: 600,
+            max_per_hour: 2,
+            ..AlertsConfig::default()
+        };
+        let hot = alert("acpitz", Severity::Warning);
+        for ts in [0.0, 60.0, 120.0, 700.0] {
+            dispatch_limited(&conn, &sinks, &config, std::slice::from_ref(&hot), ts).unwrap();
+        }
+        let messages: Vec<String> = delivered
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|a| a.message.clone())
+            .collect();
+        assert_eq!(messages, ["hot", "hot (2 more held back)"]);
+
+        // The hourly budget is spent until the first delivery is an hour old
+        let other = alert("nvme0", Severity::Critical);
+        dispatch_limited(&conn, &sinks, &config, std::slice::from_ref(&other), 800.0).unwrap();
+        assert_eq!(delivered.lock().unwrap().len(), 2);
+        dispatch_limited(&conn, &sinks, &config, std::slice::from_ref(&other), 3700.0).unwrap();
+        assert_eq!(delivered.lock().unwrap().len(), 3);
+
+        let digest_config = AlertsConfig {
+            digest: true,
+            ..AlertsConfig::default()
+        };
+        delivered.lock().unwrap().clear();
+        let raised = [hot.clone(), other.clone(), hot.clone()];
+        dispatch_limited(&conn, &sinks, &digest_config, &raised, 4000.0).unwrap();
+        dispatch_limited(&conn, &sinks, &digest_config, &raised, 5000.0).unwrap();
+        let delivered = delivered.lock().unwrap();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].title, DIGEST_TITLE);
+        assert_eq!(delivered[0].severity, Severity::Critical);
+        assert!(delivered[0].message.starts_with("acpitz: hot (x"));
+    }
+}
@@ -0,0 +1,880 @@
+use std::collections::BTreeMap;
+
+use ordered_float::OrderedFloat;
+
+use crate::cli_helpers::{is_charging, is_discharging};
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Consecutive samples further apart than this end a session.
+const MAX_INTERVAL_SECONDS: f64 = 10.0 * 60.0;
+/// Charge power below this share of the session peak, at high SoC, is the CV (taper) phase.
+const CV_POWER_RATIO: f64 = 0.7;
+const CV_MIN_SOC: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Charging,
+    Discharging,
+}
+
+impl Direction {
+    fn matches(self, sample: &MetricSample) -> bool {
+        match self {
+            Direction::Charging => is_charging(sample),
+            Direction::Discharging => is_discharging(sample),
+        }
+    }
+}
+
+/// Average power between two consecutive energy readings of the same direction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateInterval {
+    pub start_ts: f64,
+    pub end_ts: f64,
+    /// State of charge at the start of the interval
+    pub soc_pct: Option<f64>,
+    pub watts: f64,
+}
+
+impl RateInterval {
+    pub fn seconds(&self) -> f64 {
+        self.end_ts - self.start_ts
+    }
+}
+
+/// Split battery history into uninterrupted charging or discharging sessions, each a list
+/// of rate intervals built from `BatteryEnergyNow` samples.
+pub fn rate_sessions(metrics: &[MetricSample], direction: Direction) -> Vec<Vec<RateInterval>> {
+    let soc: BTreeMap<OrderedFloat<f64>, f64> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::BatteryPercentage)
+        .filter_map(|m| m.value.map(|v| (OrderedFloat(m.ts), v)))
+        .collect();
+    let mut energy: Vec<&MetricSample> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::BatteryEnergyNow && m.value.is_some())
+        .collect();
+    energy.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+
+    let mut sessions = Vec::new();
+    let mut current: Vec<RateInterval> = Vec::new();
+    for window in energy.windows(2) {
+        let (prev, next) = (window[0], window[1]);
+        let dt = next.ts - prev.ts;
+        let continues = dt > 0.0
+            && dt <= MAX_INTERVAL_SECONDS
+            && direction.matches(prev)
+            && direction.matches(next);
+        if !continues {
+            if !current.is_empty() {
+                sessions.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        let delta_wh = next.value.unwrap() - prev.value.unwrap();
+        let watts = match direction {
+            Direction::Charging => delta_wh,
+            Direction::Discharging => -delta_wh,
+        } / (dt / 3600.0);
+        current.push(RateInterval {
+            start_ts: prev.ts,
+            end_ts: next.ts,
+            soc_pct: soc.get(&OrderedFloat(prev.ts)).copied(),
+            watts: watts.max(0.0),
+        });
+    }
+    if !current.is_empty() {
+        sessions.push(current);
+    }
+    sessions
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecileRate {
+    /// Lower bound of the 10% state-of-charge band (0, 10, ..., 90)
+    pub band_start: u8,
+    pub avg_watts: f64,
+    pub samples: usize,
+}
+
+/// Average power per 10% state-of-charge band, weighted by interval duration.
+pub fn soc_decile_rates(sessions: &[Vec<RateInterval>]) -> Vec<DecileRate> {
+    let mut bands: BTreeMap<u8, (f64, f64, usize)> = BTreeMap::new();
+    for interval in sessions.iter().flatten() {
+        let Some(soc) = interval.soc_pct else {
+            continue;
+        };
+        let band = ((soc.clamp(0.0, 99.999) / 10.0).floor() as u8) * 10;
+        let entry = bands.entry(band).or_default();
+        entry.0 += interval.watts * interval.seconds();
+        entry.1 += interval.seconds();
+        entry.2 += 1;
+    }
+    bands
+        .into_iter()
+        .filter(|(_, (_, seconds, _))| *seconds > 0.0)
+        .map(|(band_start, (weighted, seconds, samples))| DecileRate {
+            band_start,
+            avg_watts: weighted / seconds,
+            samples,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChargeCurve {
+    pub start_ts: f64,
+    pub end_ts: f64,
+    pub start_soc: Option<f64>,
+    pub end_soc: Option<f64>,
+    pub avg_watts: f64,
+    pub peak_watts: f64,
+    /// Constant-current phase: power near the session peak
+    pub cc_seconds: f64,
+    /// Constant-voltage phase: power tapering off at high state of charge
+    pub cv_seconds: f64,
+    pub cv_start_soc: Option<f64>,
+}
+
+fn is_cv_phase(interval: &RateInterval, peak_watts: f64) -> bool {
+    interval.watts < peak_watts * CV_POWER_RATIO
+        && interval
+            .soc_pct
+            .map(|soc| soc >= CV_MIN_SOC)
+            .unwrap_or(true)
+}
+
+/// Characterize each charging session by its CC/CV split and power levels.
+pub fn charge_curves(metrics: &[MetricSample]) -> Vec<ChargeCurve> {
+    rate_sessions(metrics, Direction::Charging)
+        .into_iter()
+        .map(|session| {
+            let first = &session[0];
+            let last = &session[session.len() - 1];
+            let peak_watts = session.iter().map(|i| i.watts).fold(0.0, f64::max);
+            let total_seconds: f64 = session.iter().map(RateInterval::seconds).sum();
+            let energy_wh: f64 = session.iter().map(|i| i.watts * i.seconds() / 3600.0).sum();
+            let cv_seconds: f64 = session
+                .iter()
+                .filter(|i| is_cv_phase(i, peak_watts))
+                .map(RateInterval::seconds)
+                .sum();
+            let cv_start_soc = session
+                .iter()
+                .find(|i| is_cv_phase(i, peak_watts))
+                .and_then(|i| i.soc_pct);
+            let end_soc = metrics
+                .iter()
+                .find(|m| m.kind == MetricKind::BatteryPercentage && m.ts == last.end_ts)
+                .and_then(|m| m.value);
+            ChargeCurve {
+                start_ts: first.start_ts,
+                end_ts: last.end_ts,
+                start_soc: first.soc_pct,
+                end_soc,
+                avg_watts: energy_wh / (total_seconds / 3600.0),
+                peak_watts,
+                cc_seconds: total_seconds - cv_seconds,
+                cv_seconds,
+                cv_start_soc,
+            }
+        })
+        .collect()
+}
+
+/// One uninterrupted charge or discharge, ended by a status change or a collection gap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatterySession {
+    pub direction: Direction,
+    pub start_ts: f64,
+    pub end_ts: f64,
+    pub start_soc: Option<f64>,
+    pub end_soc: Option<f64>,
+    pub avg_watts: f64,
+}
+
+impl BatterySession {
+    pub fn seconds(&self) -> f64 {
+        self.end_ts - self.start_ts
+    }
+
+    /// Change in state of charge per hour, negative while discharging.
+    pub fn percent_per_hour(&self) -> Option<f64> {
+        let change = self.end_soc? - self.start_soc?;
+        (self.seconds() > 0.0).then(|| change / (self.seconds() / 3600.0))
+    }
+
+    /// Percentage points drained, for discharge sessions.
+    pub fn depth_of_discharge(&self) -> Option<f64> {
+        match self.direction {
+            Direction::Discharging => Some(self.start_soc? - self.end_soc?),
+            Direction::Charging => None,
+        }
+    }
+}
+
+/// Every charge and discharge session, oldest first.
+pub fn battery_sessions(metrics: &[MetricSample]) -> Vec<BatterySession> {
+    let soc_at = |ts: f64| {
+        metrics
+            .iter()
+            .find(|m| m.kind == MetricKind::BatteryPercentage && m.ts == ts)
+            .and_then(|m| m.value)
+    };
+    let mut sessions: Vec<BatterySession> = [Direction::Charging, Direction::Discharging]
+        .into_iter()
+        .flat_map(|direction| {
+            rate_sessions(metrics, direction)
+                .into_iter()
+                .map(move |session| (direction, session))
+        })
+        .map(|(direction, session)| {
+            let first = &session[0];
+            let last = &session[session.len() - 1];
+            let total_seconds: f64 = session.iter().map(RateInterval::seconds).sum();
+            let energy_wh: f64 = session.iter().map(|i| i.watts * i.seconds() / 3600.0).sum();
+            BatterySession {
+                direction,
+                start_ts: first.start_ts,
+                end_ts: last.end_ts,
+                start_soc: first.soc_pct,
+                end_soc: soc_at(last.end_ts),
+                avg_watts: energy_wh / (total_seconds / 3600.0),
+            }
+        })
+        .collect();
+    sessions.sort_by(|a, b| a.start_ts.total_cmp(&b.start_ts));
+    sessions
+}
+
+/// The latest stretch on battery, from the first discharging reading after the machine was
+/// last seen on AC to the latest discharging one. Collection gaps such as a suspend don't
+/// end it, so its duration is wall-clock time off the charger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastDischarge {
+    /// `avg_watts` only covers the intervals that were measured
+    pub session: BatterySession,
+    /// Still on battery at the latest reading
+    pub ongoing: bool,
+    /// Already discharging at the first reading, so it was unplugged earlier
+    pub started_before: bool,
+}
+
+pub fn last_discharge(metrics: &[MetricSample]) -> Option<LastDischarge> {
+    let mut energy: Vec<&MetricSample> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::BatteryEnergyNow && m.value.is_some())
+        .collect();
+    energy.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    let last = energy.iter().rposition(|m| is_discharging(m))?;
+    let first = energy[..last]
+        .iter()
+        .rposition(|m| !is_discharging(m))
+        .map_or(0, |index| index + 1);
+    let (start, end) = (energy[first], energy[last]);
+    if start.ts >= end.ts {
+        return None;
+    }
+
+    let intervals: Vec<RateInterval> = rate_sessions(metrics, Direction::Discharging)
+        .into_iter()
+        .flatten()
+        .filter(|i| i.start_ts >= start.ts && i.end_ts <= end.ts)
+        .collect();
+    let seconds: f64 = intervals.iter().map(RateInterval::seconds).sum();
+    let energy_wh: f64 = intervals
+        .iter()
+        .map(|i| i.watts * i.seconds() / 3600.0)
+        .sum();
+    let avg_watts = if seconds > 0.0 {
+        energy_wh / (seconds / 3600.0)
+    } else {
+        energy_used(start, end) / ((end.ts - start.ts) / 3600.0)
+    };
+    let soc_at = |ts: f64| {
+        metrics
+            .iter()
+            .find(|m| m.kind == MetricKind::BatteryPercentage && m.ts == ts)
+            .and_then(|m| m.value)
+    };
+    Some(LastDischarge {
+        session: BatterySession {
+            direction: Direction::Discharging,
+            start_ts: start.ts,
+            end_ts: end.ts,
+            start_soc: soc_at(start.ts),
+            end_soc: soc_at(end.ts),
+            avg_watts,
+        },
+        ongoing: last == energy.len() - 1,
+        started_before: first == 0,
+    })
+}
+
+/// Charge level read as a full charge from batteries that never report `Full`.
+const FULL_CHARGE_PCT: f64 = 99.0;
+/// Charge level read as a full discharge; most machines shut down around here.
+const EMPTY_CHARGE_PCT: f64 = 5.0;
+/// How often a full charge and discharge keeps the fuel gauge honest.
+pub const CALIBRATION_INTERVAL_DAYS: f64 = 30.0;
+
+/// When the battery was last seen at either end of its range, the two points the fuel
+/// gauge recalibrates on.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChargeExtremes {
+    /// Latest reading with status `Full` or at 99% and above
+    pub last_full: Option<f64>,
+    /// Latest reading at 5% or below
+    pub last_empty: Option<f64>,
+}
+
+impl ChargeExtremes {
+    /// No full discharge in the [`CALIBRATION_INTERVAL_DAYS`] before `now`.
+    pub fn calibration_due(&self, now: f64) -> bool {
+        self.last_empty
+            .is_none_or(|ts| now - ts > CALIBRATION_INTERVAL_DAYS * 86400.0)
+    }
+}
+
+pub fn charge_extremes(metrics: &[MetricSample]) -> ChargeExtremes {
+    let mut extremes = ChargeExtremes::default();
+    for sample in metrics.iter().filter(|m| m.kind.is_battery()) {
+        let reported_full = sample
+            .status
+            .as_deref()
+            .is_some_and(|s| s.eq_ignore_ascii_case("full"));
+        let pct = sample
+            .value
+            .filter(|_| sample.kind == MetricKind::BatteryPercentage);
+        if reported_full || pct.is_some_and(|pct| pct >= FULL_CHARGE_PCT) {
+            extremes.last_full = Some(extremes.last_full.map_or(sample.ts, |ts| ts.max(sample.ts)));
+        }
+        if pct.is_some_and(|pct| pct <= EMPTY_CHARGE_PCT) {
+            extremes.last_empty = Some(
+                extremes
+                    .last_empty
+                    .map_or(sample.ts, |ts| ts.max(sample.ts)),
+            );
+        }
+    }
+    extremes
+}
+
+/// Charging done behind one charger wattage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChargerRate {
+    /// Negotiated charger wattage, rounded to the watt
+    pub charger_watts: u32,
+    /// Active USB type (`PD`, `C`, ...) when the charger reports one
+    pub usb_type: Option<String>,
+    pub seconds: f64,
+    pub energy_wh: f64,
+}
+
+impl ChargerRate {
+    pub fn avg_watts(&self) -> f64 {
+        self.energy_wh / (self.seconds / 3600.0)
+    }
+}
+
+/// Split charging time and energy by the wattage the charger negotiated, so a 65W brick
+/// and a 20W phone charger are told apart. Intervals without a recent charger reading
+/// are left out; with several chargers online the strongest one counts.
+pub fn charge_by_charger(metrics: &[MetricSample]) -> Vec<ChargerRate> {
+    let mut chargers: BTreeMap<OrderedFloat<f64>, (u32, Option<String>)> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::ChargerPower)
+    {
+        let Some(watts) = sample.value else {
+            continue;
+        };
+        let usb_type = sample.details.get("usb_type").and_then(|v| v.as_str());
+        let charger = (watts.round() as u32, usb_type.map(str::to_string));
+        let entry = chargers
+            .entry(OrderedFloat(sample.ts))
+            .or_insert_with(|| charger.clone());
+        if charger.0 > entry.0 {
+            *entry = charger;
+        }
+    }
+    if chargers.is_empty() {
+        return Vec::new();
+    }
+
+    let mut totals: BTreeMap<(u32, Option<String>), (f64, f64)> = BTreeMap::new();
+    for interval in rate_sessions(metrics, Direction::Charging).iter().flatten() {
+        let Some(charger) = chargers
+            .range(..=OrderedFloat(interval.start_ts))
+            .next_back()
+            .filter(|(ts, _)| interval.start_ts - ts.0 <= MAX_INTERVAL_SECONDS)
+            .map(|(_, charger)| charger.clone())
+        else {
+            continue;
+        };
+        let entry = totals.entry(charger).or_default();
+        entry.0 += interval.seconds();
+        entry.1 += interval.watts * interval.seconds() / 3600.0;
+    }
+    totals
+        .into_iter()
+        .rev()
+        .map(
+            |((charger_watts, usb_type), (seconds, energy_wh))| ChargerRate {
+                charger_watts,
+                usb_type,
+                seconds,
+                energy_wh,
+            },
+        )
+        .collect()
+}
+
+/// What the machine was doing while the battery drained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Activity {
+    Active,
+    Idle,
+    LidClosed,
+    /// No samples between two readings of the same boot, or taken with the lid closed
+    Suspended,
+}
+
+impl Activity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Activity::Active => "Active",
+            Activity::Idle => "Idle",
+            Activity::LidClosed => "Lid closed",
+            Activity::Suspended => "Suspended",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityDrain {
+    pub activity: Activity,
+    pub seconds: f64,
+    pub energy_wh: f64,
+}
+
+impl ActivityDrain {
+    pub fn avg_watts(&self) -> f64 {
+        self.energy_wh / (self.seconds / 3600.0)
+    }
+}
+
+/// Values of one kind keyed by timestamp.
+fn timeline<T>(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    value: impl Fn(&MetricSample) -> Option<T>,
+) -> BTreeMap<OrderedFloat<f64>, T> {
+    metrics
+        .iter()
+        .filter(|m| m.kind == kind)
+        .filter_map(|m| value(m).map(|v| (OrderedFloat(m.ts), v)))
+        .collect()
+}
+
+/// Latest value of a boolean state kind, keyed by timestamp.
+fn state_timeline(metrics: &[MetricSample], kind: MetricKind) -> BTreeMap<OrderedFloat<f64>, bool> {
+    timeline(metrics, kind, |m| m.value.map(|v| v > 0.5))
+}
+
+fn state_at<T: Copy>(timeline: &BTreeMap<OrderedFloat<f64>, T>, ts: f64) -> Option<T> {
+    timeline
+        .range(..=OrderedFloat(ts))
+        .next_back()
+        .filter(|(sample_ts, _)| ts - sample_ts.0 <= MAX_INTERVAL_SECONDS)
+        .map(|(_, state)| *state)
+}
+
+/// Consecutive energy readings taken while discharging, oldest first.
+pub(crate) fn discharge_intervals(metrics: &[MetricSample]) -> Vec<(&MetricSample, &MetricSample)> {
+    let mut energy: Vec<&MetricSample> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::BatteryEnergyNow && m.value.is_some())
+        .collect();
+    energy.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+    energy
+        .windows(2)
+        .map(|window| (window[0], window[1]))
+        .filter(|(prev, next)| next.ts > prev.ts && is_discharging(prev) && is_discharging(next))
+        .collect()
+}
+
+pub(crate) fn energy_used(prev: &MetricSample, next: &MetricSample) -> f64 {
+    (prev.value.unwrap() - next.value.unwrap()).max(0.0)
+}
+
+/// Split discharge time and energy by logind activity state. Intervals without a recent
+/// idle or lid sample are left out.
+pub fn discharge_by_activity(metrics: &[MetricSample]) -> Vec<ActivityDrain> {
+    let idle = state_timeline(metrics, MetricKind::UserIdle);
+    let lid = state_timeline(metrics, MetricKind::LidClosed);
+    if idle.is_empty() && lid.is_empty() {
+        return Vec::new();
+    }
+
+    let mut totals: BTreeMap<Activity, (f64, f64)> = BTreeMap::new();
+    for (prev, next) in discharge_intervals(metrics) {
+        let dt = next.ts - prev.ts;
+        let lid_closed = state_at(&lid, prev.ts) == Some(true);
+        let activity = if dt > MAX_INTERVAL_SECONDS {
+            // Readings from one kernel boot mean the machine slept rather than shut down;
+            // without boot ids, a lid closed at both ends is the best hint
+            let suspended = match (&prev.boot, &next.boot) {
+                (Some(before), Some(after)) => before == after,
+                _ => lid_closed && state_at(&lid, next.ts) == Some(true),
+            };
+            if !suspended {
+                continue;
+            }
+            Activity::Suspended
+        } else if lid_closed {
+            Activity::LidClosed
+        } else {
+            match state_at(&idle, prev.ts) {
+                Some(true) => Activity::Idle,
+                Some(false) => Activity::Active,
+                None if state_at(&lid, prev.ts).is_some() => Activity::Active,
+                None => continue,
+            }
+        };
+        let entry = totals.entry(activity).or_default();
+        entry.0 += dt;
+        entry.1 += energy_used(prev, next);
+    }
+    totals
+        .into_iter()
+        .map(|(activity, (seconds, energy_wh))| ActivityDrain {
+            activity,
+            seconds,
+            energy_wh,
+        })
+        .collect()
+}
+
+/// Discharge spent at one keyboard backlight brightness step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacklightDrain {
+    /// Raw brightness step, 0 when off
+    pub level: u32,
+    pub max_level: u32,
+    pub seconds: f64,
+    pub energy_wh: f64,
+}
+
+impl BacklightDrain {
+    pub fn avg_watts(&self) -> f64 {
+        self.energy_wh / (self.seconds / 3600.0)
+    }
+}
+
+/// Split discharge time and energy by keyboard backlight brightness (the brightest
+/// backlight when there are several), skipping intervals without a recent reading.
+pub fn discharge_by_keyboard_backlight(metrics: &[MetricSample]) -> Vec<BacklightDrain> {
+    let mut levels: BTreeMap<OrderedFloat<f64>, (u32, u32)> = BTreeMap::new();
+    for (ts, level) in timeline(metrics, MetricKind::KeyboardBacklight, |m| {
+        let step = |key| m.details.get(key)?.as_u64().map(|v| v as u32);
+        Some((step("brightness")?, step("max_brightness")?))
+    }) {
+        let entry = levels.entry(ts).or_insert(level);
+        *entry = (*entry).max(level);
+    }
+    if levels.is_empty() {
+        return Vec::new();
+    }
+
+    let mut totals: BTreeMap<(u32, u32), (f64, f64)> = BTreeMap::new();
+    for (prev, next) in discharge_intervals(metrics) {
+        let dt = next.ts - prev.ts;
+        let Some(level) = state_at(&levels, prev.ts).filter(|_| dt <= MAX_INTERVAL_SECONDS) else {
+            continue;
+        };
+        let entry = totals.entry(level).or_default();
+        entry.0 += dt;
+        entry.1 += energy_used(prev, next);
+    }
+    totals
+        .into_iter()
+        .map(
+            |((level, max_level), (seconds, energy_wh))| BacklightDrain {
+                level,
+                max_level,
+                seconds,
+                energy_wh,
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// Energy readings for a 100 Wh battery every 5 minutes with the given status.
+    fn battery_history(start_ts: f64, energies: &[f64], status: &str) -> Vec<MetricSample> {
+        let mut metrics = Vec::new();
+        for (i, energy) in energies.iter().enumerate() {
+            let ts = start_ts + i as f64 * 300.0;
+            metrics.push(
+                MetricSample::new(
+                    ts,
+                    MetricKind::BatteryEnergyNow,
+                    "BAT0",
+                    Some(*energy),
+                    Some("Wh"),
+                    Value::Null,
+                )
+                .with_status(status),
+            );
+            metrics.push(
+                MetricSample::new(
+                    ts,
+                    MetricKind::BatteryPercentage,
+                    "BAT0",
+                    Some(*energy),
+                    Some("%"),
+                    Value::Null,
+                )
+                .with_status(status),
+            );
+        }
+        metrics
+    }
+
+    #[test]
+    fn charge_and_discharge_sessions_alternate() {
+        let mut metrics = battery_history(0.0, &[80.0, 75.0, 70.0, 65.0], "Discharging");
+        metrics.extend(battery_history(1200.0, &[66.0, 76.0, 86.0], "Charging"));
+
+        let sessions = battery_sessions(&metrics);
+        assert_eq!(sessions.len(), 2);
+        let discharge = &sessions[0];
+        assert_eq!(discharge.direction, Direction::Discharging);
+        assert_eq!((discharge.start_ts, discharge.end_ts), (0.0, 900.0));
+        assert_eq!(discharge.depth_of_discharge(), Some(15.0));
+        assert_eq!(discharge.percent_per_hour(), Some(-60.0));
+        assert!((discharge.avg_watts - 60.0).abs() < 1e-9);
+
+        let charge = &sessions[1];
+        assert_eq!(charge.direction, Direction::Charging);
+        assert_eq!((charge.start_soc, charge.end_soc), (Some(66.0), Some(86.0)));
+        assert_eq!(charge.depth_of_discharge(), None);
+        assert_eq!(charge.percent_per_hour(), Some(120.0));
+    }
+
+    #[test]
+    fn sessions_split_on_status_and_gaps() {
+        let mut metrics = battery_history(0.0, &[50.0, 45.0, 40.0], "Discharging");
+        metrics.extend(battery_history(900.0, &[40.0, 45.0], "Charging"));
+        metrics.extend(battery_history(10_000.0, &[60.0, 55.0], "Discharging"));
+
+        let discharging = rate_sessions(&metrics, Direction::Discharging);
+        assert_eq!(discharging.len(), 2);
+        assert_eq!(discharging[0].len(), 2);
+        assert!((discharging[0][0].watts - 60.0).abs() < 1e-9);
+        assert_eq!(discharging[0][0].soc_pct, Some(50.0));
+
+        let charging = rate_sessions(&metrics, Direction::Charging);
+        assert_eq!(charging.len(), 1);
+        assert!((charging[0][0].watts - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn last_discharge_spans_gaps_until_the_charger() {
+        let mut metrics = battery_history(0.0, &[90.0, 85.0], "Discharging");
+        metrics.extend(battery_history(600.0, &[85.0, 95.0], "Charging"));
+        // Unplugged at 95%, then suspended for an hour in the middle
+        metrics.extend(battery_history(1200.0, &[95.0, 90.0, 85.0], "Discharging"));
+        metrics.extend(battery_history(5400.0, &[80.0, 75.0], "Discharging"));
+
+        let last = last_discharge(&metrics).unwrap();
+        assert_eq!(
+            (last.session.start_ts, last.session.end_ts),
+            (1200.0, 5700.0)
+        );
+        assert_eq!(
+            (last.session.start_soc, last.session.end_soc),
+            (Some(95.0), Some(75.0))
+        );
+        assert!((last.session.avg_watts - 60.0).abs() < 1e-9);
+        assert_eq!(last.session.percent_per_hour(), Some(-16.0));
+        assert!(last.ongoing && !last.started_before);
+
+        metrics.extend(battery_history(6000.0, &[76.0], "Charging"));
+        assert!(!last_discharge(&metrics).unwrap().ongoing);
+        assert_eq!(
+            last_discharge(&battery_history(0.0, &[50.0], "Charging")),
+            None
+        );
+    }
+
+    #[test]
+    fn charge_extremes_find_full_and_empty() {
+        let mut metrics = battery_history(0.0, &[99.5, 80.0, 40.0, 4.0], "Discharging");
+        metrics.extend(battery_history(1200.0, &[3.0, 60.0], "Charging"));
+        metrics.extend(battery_history(1800.0, &[97.0], "Full"));
+
+        let extremes = charge_extremes(&metrics);
+        assert_eq!(extremes.last_full, Some(1800.0));
+        assert_eq!(extremes.last_empty, Some(1200.0));
+        assert!(!extremes.calibration_due(1800.0));
+        assert!(extremes.calibration_due(1200.0 + 31.0 * 86400.0));
+        assert!(ChargeExtremes::default().calibration_due(0.0));
+    }
+
+    #[test]
+    fn decile_rates_group_by_soc_band() {
+        let metrics = battery_history(0.0, &[35.0, 33.0, 29.0, 23.0, 17.0], "Discharging");
+        let rates = soc_decile_rates(&rate_sessions(&metrics, Direction::Discharging));
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].band_start, 20);
+        assert!((rates[0].avg_watts - 72.0).abs() < 1e-9);
+        assert_eq!(rates[1].band_start, 30);
+        assert_eq!(rates[1].samples, 2);
+        assert!((rates[1].avg_watts - 36.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn charge_curve_splits_cc_and_cv_phases() {
+        // 5 Wh per 5 min (60 W) up to 80%, then tapering.
+        let metrics = battery_history(
+            0.0,
+            &[60.0, 65.0, 70.0, 75.0, 80.0, 82.0, 83.0, 83.5],
+            "Charging",
+        );
+        let curves = charge_curves(&metrics);
+        assert_eq!(curves.len(), 1);
+        let curve = &curves[0];
+        assert!((curve.peak_watts - 60.0).abs() < 1e-9);
+        assert_eq!(curve.cc_seconds, 4.0 * 300.0);
+        assert_eq!(curve.cv_seconds, 3.0 * 300.0);
+        assert_eq!(curve.cv_start_soc, Some(80.0));
+        assert_eq!(curve.start_soc, Some(60.0));
+        assert_eq!(curve.end_soc, Some(83.5));
+    }
+
+    #[test]
+    fn charging_is_split_by_charger_wattage() {
+        // A 65W brick for two intervals (60 W), then a 20W one adding 1 Wh per 5 min (12 W)
+        let mut metrics = battery_history(0.0, &[40.0, 45.0, 50.0, 51.0, 52.0], "Charging");
+        let charger = |ts: f64, watts: f64, usb_type: &str| {
+            MetricSample::new(
+                ts,
+                MetricKind::ChargerPower,
+                "ucsi-source-psy-USBC000:001",
+                Some(watts),
+                Some("W"),
+                json!({ "usb_type": usb_type }),
+            )
+        };
+        metrics.push(charger(0.0, 65.0, "PD"));
+        metrics.push(charger(0.0, 15.0, "C"));
+        metrics.push(charger(600.0, 20.0, "PD"));
+
+        let chargers = charge_by_charger(&metrics);
+        assert_eq!(chargers.len(), 2);
+        assert_eq!(chargers[0].charger_watts, 65);
+        assert_eq!(chargers[0].usb_type.as_deref(), Some("PD"));
+        assert!((chargers[0].avg_watts() - 60.0).abs() < 1e-9);
+        assert_eq!(chargers[1].charger_watts, 20);
+        assert_eq!(chargers[1].seconds, 600.0);
+        assert!((chargers[1].avg_watts() - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn discharge_is_split_by_activity() {
+        let mut metrics = battery_history(0.0, &[50.0, 49.0, 48.0, 47.5], "Discharging");
+        // Resumed two hours later, lid still closed at wake-up.
+        metrics.extend(battery_history(8_100.0, &[47.0], "Discharging"));
+        let state = |ts: f64, kind: MetricKind, value: f64| {
+            MetricSample::new(ts, kind, "logind", Some(value), Some("bool"), json!(null))
+        };
+        metrics.extend([
+            state(0.0, MetricKind::UserIdle, 0.0),
+            state(300.0, MetricKind::UserIdle, 1.0),
+            state(600.0, MetricKind::UserIdle, 1.0),
+            state(600.0, MetricKind::LidClosed, 1.0),
+            state(900.0, MetricKind::LidClosed, 1.0),
+            state(8_100.0, MetricKind::LidClosed, 1.0),
+        ]);
+
+        let drains = discharge_by_activity(&metrics);
+        let activities: Vec<Activity> = drains.iter().map(|d| d.activity).collect();
+        assert_eq!(
+            activities,
+            vec![
+                Activity::Active,
+                Activity::Idle,
+                Activity::LidClosed,
+                Activity::Suspended
+            ]
+        );
+        assert!((drains[0].avg_watts() - 12.0).abs() < 1e-9);
+        assert_eq!(drains[3].seconds, 7_200.0);
+        assert!((drains[3].energy_wh - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn boot_ids_tell_suspends_from_shutdowns() {
+        let with_boot = |mut samples: Vec<MetricSample>, boot: &str| {
+            for sample in &mut samples {
+                sample.boot = Some(boot.to_string());
+            }
+            samples
+        };
+        // Slept an hour with the lid open, then shut down overnight
+        let mut metrics = with_boot(battery_history(0.0, &[50.0, 49.0], "Discharging"), "a");
+        metrics.extend(with_boot(
+            battery_history(3_900.0, &[48.0], "Discharging"),
+            "a",
+        ));
+        metrics.extend(with_boot(
+            battery_history(40_000.0, &[45.0], "Discharging"),
+            "b",
+        ));
+        metrics.push(MetricSample::new(
+            0.0,
+            MetricKind::LidClosed,
+            "logind",
+            Some(0.0),
+            Some("bool"),
+            json!(null),
+        ));
+
+        let drains = discharge_by_activity(&metrics);
+        let suspended: Vec<f64> = drains
+            .iter()
+            .filter(|d| d.activity == Activity::Suspended)
+            .map(|d| d.seconds)
+            .collect();
+        assert_eq!(suspended, [3_600.0]);
+    }
+
+    #[test]
+    fn discharge_is_split_by_keyboard_backlight() {
+        let mut metrics = battery_history(0.0, &[50.0, 49.0, 47.0, 46.0], "Discharging");
+        let backlight = |ts: f64, brightness: u64| {
+            MetricSample::new(
+                ts,
+                MetricKind::KeyboardBacklight,
+                "tpacpi::kbd_backlight",
+                Some(brightness as f64 * 50.0),
+                Some("%"),
+                json!({ "brightness": brightness, "max_brightness": 2 }),
+            )
+        };
+        metrics.extend([backlight(0.0, 0), backlight(300.0, 2), backlight(600.0, 0)]);
+
+        let drains = discharge_by_keyboard_backlight(&metrics);
+        assert_eq!(drains.len(), 2);
+        assert_eq!((drains[0].level, drains[0].seconds), (0, 600.0));
+        assert!((drains[0].energy_wh - 2.0).abs() < 1e-9);
+        assert_eq!((drains[1].level, drains[1].max_level), (2, 2));
+        assert!((drains[1].avg_watts() - 24.0).abs() < 1e-9);
+    }
+}
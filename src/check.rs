@@ -0,0 +1,242 @@
+//! `symmetri check`: alert conditions such as `temperature.max > 90` evaluated against the
+//! latest samples or a recent window, for cron jobs and monitoring scripts.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+/// How the samples of one source in the window reduce to the value compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    Last,
+    Min,
+    Max,
+    Avg,
+}
+
+impl Stat {
+    fn reduce(self, values: &[f64]) -> Option<f64> {
+        match self {
+            Stat::Last => values.last().copied(),
+            Stat::Min => values.iter().copied().reduce(f64::min),
+            Stat::Max => values.iter().copied().reduce(f64::max),
+            Stat::Avg => {
+                (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    /// Operators longest first, so `>=` is not read as `>`.
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+    ];
+
+    fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Greater => value > threshold,
+            Comparison::GreaterOrEqual => value >= threshold,
+            Comparison::Less => value < threshold,
+            Comparison::LessOrEqual => value <= threshold,
+            Comparison::Equal => value == threshold,
+            Comparison::NotEqual => value != threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RuleError {
+    #[error("rule `{0}` has no comparison; expected e.g. `temperature.max > 90`")]
+    MissingComparison(String),
+    #[error("unknown metric kind `{0}`")]
+    UnknownKind(String),
+    #[error("unknown statistic `{0}`; expected last, min, max or avg")]
+    UnknownStat(String),
+    #[error("invalid threshold `{0}`")]
+    InvalidThreshold(String),
+}
+
+/// `kind[source].stat <op> threshold`, violated when the comparison holds for a source.
+/// Without a source every source of the kind is checked; the statistic defaults to `last`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub kind: MetricKind,
+    pub source: Option<String>,
+    pub stat: Stat,
+    pub comparison: Comparison,
+    pub threshold: f64,
+    raw: String,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+        let (at, op, comparison) = Comparison::OPERATORS
+            .iter()
+            .filter_map(|(op, comparison)| raw.find(op).map(|at| (at, *op, *comparison)))
+            .min_by_key(|(at, op, _)| (*at, std::cmp::Reverse(op.len())))
+            .ok_or_else(|| RuleError::MissingComparison(raw.to_string()))?;
+        let (metric, threshold) = (raw[..at].trim(), raw[at + op.len()..].trim());
+        let threshold: f64 = threshold
+            .parse()
+            .map_err(|_| RuleError::InvalidThreshold(threshold.to_string()))?;
+
+        let (metric, stat) = match metric.rsplit_once('.') {
+            Some((metric, stat)) if !stat.contains(']') => {
+                let stat = match stat {
+                    "last" => Stat::Last,
+                    "min" => Stat::Min,
+                    "max" => Stat::Max,
+                    "avg" => Stat::Avg,
+                    _ => return Err(RuleError::UnknownStat(stat.to_string())),
+                };
+                (metric, stat)
+            }
+            _ => (metric, Stat::Last),
+        };
+        let (name, source) = match metric.split_once('[') {
+            Some((name, source)) => (name, Some(source.trim_end_matches(']').to_string())),
+            None => (metric, None),
+        };
+        let kind =
+            MetricKind::from_label(name).ok_or_else(|| RuleError::UnknownKind(name.to_string()))?;
+        Ok(Rule {
+            kind,
+            source,
+            stat,
+            comparison,
+            threshold,
+            raw: raw.to_string(),
+        })
+    }
+}
+
+/// A rule's value per checked source, and whether any of them violates it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleOutcome {
+    pub values: Vec<(String, f64)>,
+    pub violations: Vec<(String, f64)>,
+}
+
+impl RuleOutcome {
+    pub fn passed(&self) -> bool {
+        !self.values.is_empty() && self.violations.is_empty()
+    }
+}
+
+/// Evaluate `rule` per source over `samples` (oldest first).
+pub fn evaluate(rule: &Rule, samples: &[MetricSample]) -> RuleOutcome {
+    let mut by_source: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for sample in samples {
+        if sample.kind != rule.kind
+            || rule
+                .source
+                .as_ref()
+                .is_some_and(|source| source != &sample.source)
+        {
+            continue;
+        }
+        if let Some(value) = sample.value {
+            by_source.entry(&sample.source).or_default().push(value);
+        }
+    }
+    let values: Vec<(String, f64)> = by_source
+        .into_iter()
+        .filter_map(|(source, values)| Some((source.to_string(), rule.stat.reduce(&values)?)))
+        .collect();
+    let violations = values
+        .iter()
+        .filter(|(_, value)| rule.comparison.holds(*value, rule.threshold))
+        .cloned()
+        .collect();
+    RuleOutcome { values, violations }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample(ts: f64, kind: MetricKind, source: &str, value: f64) -> MetricSample {
+        MetricSample::new(ts, kind, source, Some(value), None, json!({}))
+    }
+
+    #[test]
+    fn rules_parse() {
+        let rule: Rule = "temperature[coretemp:Package id 0].max >= 90.5"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.kind, MetricKind::Temperature);
+        assert_eq!(rule.source.as_deref(), Some("coretemp:Package id 0"));
+        assert_eq!(rule.stat, Stat::Max);
+        assert_eq!(rule.comparison, Comparison::GreaterOrEqual);
+        assert_eq!(rule.threshold, 90.5);
+
+        let rule: Rule = "battery_health<80".parse().unwrap();
+        assert_eq!((rule.stat, rule.comparison), (Stat::Last, Comparison::Less));
+        assert_eq!(rule.to_string(), "battery_health<80");
+
+        assert!(matches!(
+            "cpu_usage".parse::<Rule>(),
+            Err(RuleError::MissingComparison(_))
+        ));
+        assert!(matches!(
+            "cpu_use > 5".parse::<Rule>(),
+            Err(RuleError::UnknownKind(_))
+        ));
+        assert!(matches!(
+            "cpu_usage.p99 > 5".parse::<Rule>(),
+            Err(RuleError::UnknownStat(_))
+        ));
+        assert!(matches!(
+            "cpu_usage > hot".parse::<Rule>(),
+            Err(RuleError::InvalidThreshold(_))
+        ));
+    }
+
+    #[test]
+    fn any_violating_source_fails_the_rule() {
+        let samples = vec![
+            sample(0.0, MetricKind::Temperature, "coretemp", 70.0),
+            sample(0.0, MetricKind::Temperature, "nvme0", 50.0),
+            sample(60.0, MetricKind::Temperature, "coretemp", 95.0),
+            sample(120.0, MetricKind::Temperature, "coretemp", 72.0),
+        ];
+        let hot = evaluate(&"temperature.max > 90".parse().unwrap(), &samples);
+        assert!(!hot.passed());
+        assert_eq!(hot.violations, [("coretemp".to_string(), 95.0)]);
+
+        let latest = evaluate(&"temperature > 90".parse().unwrap(), &samples);
+        assert!(latest.passed());
+        assert_eq!(latest.values.len(), 2);
+
+        let missing = evaluate(&"battery_health < 80".parse().unwrap(), &samples);
+        assert!(!missing.passed() && missing.violations.is_empty());
+    }
+}
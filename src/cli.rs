@@ -1,25 +1,65 @@
 use std::collections::BTreeMap;
 use std::ffi::OsString;
+use std::fmt;
 use std::io::Write;
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
-use clap::{Parser, Subcommand, ValueEnum};
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
-use comfy_table::presets::UTF8_FULL_CONDENSED;
-use comfy_table::{Attribute, Cell, CellAlignment, Color, ContentArrangement, Table};
+use comfy_table::presets::{ASCII_FULL_CONDENSED, NOTHING, UTF8_FULL_CONDENSED};
+use comfy_table::{
+    Attribute, Cell, CellAlignment, Color, ColumnConstraint, ContentArrangement, Table,
+};
+use log::warn;
 
 use chrono::{DateTime, Local};
 
+use crate::alert;
+use crate::battery_analysis::{
+    ActivityDrain, BacklightDrain, BatterySession, ChargeCurve, ChargerRate, DecileRate, Direction,
+};
+use crate::check::{self, Rule};
 use crate::cli_helpers::{
-    average_rates, bucket_span_seconds, bucket_start, default_graph_path, estimate_runtime_hours,
-    format_runtime, is_charging, is_discharging,
+    average_rates, bucket_start, default_graph_path, format_bytes, format_runtime, local_datetime,
+    SourceCounterBuckets,
 };
-use crate::collector::{collect_loop, collect_once, resolve_db_path};
+use crate::collector::{collect_live, collect_loop, collect_once, resolve_db_path};
+use crate::config::{self, Config, DataCapConfig, Panel, ReportConfig, TableTheme};
+use crate::correlate::{self, ThrottleSummary};
+use crate::data_cap::{self, cap_percent, MonthlyUsage};
 use crate::db;
-use crate::graph;
-use crate::metrics::{MetricKind, MetricSample};
-use crate::timeframe::{build_timeframe, Timeframe};
+use crate::doctor::{self, Diagnosis};
+use crate::export::{self, ExportFormat};
+use crate::fleet::{self, FleetRow};
+use crate::forecast::DiskForecast;
+use crate::graph::{self, PlotOptions};
+use crate::inline_image;
+use crate::leak::LeakPeriod;
+use crate::live;
+use crate::metrics::{Collector, MetricKind, MetricSample};
+use crate::overview::{self, OverviewColumn};
+pub use crate::report::ReportPreset;
+use crate::report::{
+    bucket_stats_for_kind_by_source, dominant_governor, filter_metrics_by_host,
+    filter_metrics_by_source, BatteryPack, BootSummary, CategorySummary, ChargeExtremes,
+    CollectorCost, CpuDetail, DailySummary, DiskMount, DistributionSummary, GovernorBuckets,
+    GpuLimitSummary, HealthBucket, LastDischarge, NumberStats, PeripheralSummary, ReportBuilder,
+    ReportResult, ReportSection, SamplingSummary, SourceBuckets, StateSummary, TimeBuckets,
+    TransferStats, UsageStats, ValueHistogram, WakeupSourceSummary, CALIBRATION_INTERVAL_DAYS,
+    DEFAULT_DISK_FULL_DAYS, DEFAULT_THROTTLE_TEMP_C,
+};
+use crate::schema::{self, SchemaName};
+use crate::setup;
+use crate::status::{self, StatusFormat, StatusMetric, StatusOptions};
+use crate::storage;
+use crate::system_info::SystemSnapshot;
+use crate::timeframe::{
+    bound_timeframe, build_timeframe, parse_timestamp, parse_window, Timeframe,
+};
 
 #[derive(Parser)]
 #[command(name = "symmetri", version)]
@@ -27,22 +67,56 @@ use crate::timeframe::{build_timeframe, Timeframe};
     about = "System metrics collection for Linux/NixOS (battery, CPU, GPU, network, RAM, disk, thermals)"
 )]
 pub struct Cli {
+    /// Path to the TOML config file (or set SYMMETRI_CONFIG)
+    #[arg(long = "config", global = true)]
+    pub config_path: Option<PathBuf>,
+    /// ASCII-only output without colors, borders or column alignment, for screen readers
+    /// and log files
+    #[arg(long = "plain", global = true)]
+    pub plain: bool,
+    /// Color tables and logs: auto (only on a terminal, unless NO_COLOR is set), always or never
+    #[arg(
+        long = "color",
+        value_name = "WHEN",
+        global = true,
+        default_value = "auto"
+    )]
+    pub color: ColorChoice,
     #[command(subcommand)]
     pub command: Commands,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-pub enum ReportPreset {
-    All,
-    Battery,
-    Cpu,
-    Gpu,
-    Memory,
-    Network,
-    Temperature,
-    Disk,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// `--plain` and a non-empty `NO_COLOR` turn `auto` off; an explicit `always` still wins
+    /// over `NO_COLOR`.
+    fn resolve(self, plain: bool, no_color: Option<OsString>) -> ColorChoice {
+        match self {
+            _ if plain => ColorChoice::Never,
+            ColorChoice::Auto if no_color.is_some_and(|value| !value.is_empty()) => {
+                ColorChoice::Never
+            }
+            choice => choice,
+        }
+    }
+}
+
+fn parse_metric_kind(raw: &str) -> Result<MetricKind, String> {
+    MetricKind::from_label(raw).ok_or_else(|| {
+        let known: Vec<&str> = MetricKind::all_kinds().map(|k| k.as_str()).collect();
+        format!("unknown metric kind `{raw}` (known: {})", known.join(", "))
+    })
 }
 
+// Parsed once per run, so the size of the report arguments does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Collect system metrics once (or repeatedly with --interval)
@@ -57,49 +131,355 @@ pub enum Commands {
         #[arg(short, long)]
         verbose: bool,
     },
-    /// Render a timeframe report (optionally save a graph image)
-    Report {
-        /// Window in hours (used when days/months are zero)
-        #[arg(long = "hours", default_value_t = 6)]
+    /// Plot two metric kinds on twin y-axes in one chart
+    Graph {
+        /// Two metric kinds, e.g. `power_draw,cpu_usage`
+        #[arg(
+            long = "overlay",
+            value_delimiter = ',',
+            required = true,
+            value_parser = parse_metric_kind
+        )]
+        overlay: Vec<MetricKind>,
+        /// Window such as 6h, 7d, 2w, 3mo or all
+        #[arg(long = "last", default_value = "24h")]
+        last: String,
+        /// Draw these kinds as step lines, holding each value until the next sample
+        #[arg(long = "step", value_delimiter = ',', value_parser = parse_metric_kind)]
+        step_kinds: Vec<MetricKind>,
+        /// Path to SQLite database
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Custom path for the graph image (`-` writes PNG to stdout); defaults to an auto-generated name
+        #[arg(long = "graph-path")]
+        graph_path: Option<PathBuf>,
+        /// Enable debug logging
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Correlate two metric kinds over time buckets and save a scatter plot
+    Correlate {
+        /// Metric kind on the x axis, e.g. `cpu_usage`
+        #[arg(long = "x", value_parser = parse_metric_kind)]
+        x: MetricKind,
+        /// Metric kind on the y axis, e.g. `power_draw`
+        #[arg(long = "y", value_parser = parse_metric_kind)]
+        y: MetricKind,
+        /// Window in hours (used when days is zero)
+        #[arg(long = "hours", default_value_t = 24)]
         hours: u64,
         /// Window in days (overrides hours when non-zero)
         #[arg(long = "days", default_value_t = 0)]
         days: u64,
+        /// Bucket width in minutes; each bucket averages both kinds into one point
+        #[arg(long = "bucket", default_value_t = 5)]
+        bucket_minutes: u64,
+        /// Path to SQLite database
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Custom path for the scatter plot (`-` writes PNG to stdout); defaults to an auto-generated name
+        #[arg(long = "graph-path")]
+        graph_path: Option<PathBuf>,
+        /// Enable debug logging
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Render a timeframe report (optionally save a graph image)
+    Report {
+        /// Window in hours (used when days/months are zero) [default: 6, or report.timeframe]
+        #[arg(long = "hours")]
+        hours: Option<u64>,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days")]
+        days: Option<u64>,
         /// Window in months (~30d each; overrides days/hours when non-zero)
-        #[arg(long = "months", default_value_t = 0)]
-        months: u64,
+        #[arg(long = "months")]
+        months: Option<u64>,
         /// Ignore timeframe limits and use the entire history
         #[arg(long = "all")]
         all_time: bool,
-        /// Path to SQLite database
+        /// Start of the window: RFC3339, `YYYY-MM-DD HH:MM` or `YYYY-MM-DD` in local time
+        #[arg(long = "since", conflicts_with_all = ["hours", "days", "months", "all_time"])]
+        since: Option<String>,
+        /// End of the window, in the same formats (a bare date includes that day); the
+        /// window flags then count back from it
+        #[arg(long = "until")]
+        until: Option<String>,
+        /// SQLite database; repeat to report on several at once, e.g. per-year or per-host
+        /// files (or set SYMMETRI_DB)
         #[arg(long = "db")]
-        db_path: Option<PathBuf>,
+        db_paths: Vec<PathBuf>,
         /// Save a graph image with an auto-generated name
         #[arg(long = "graph", short = 'g')]
         graph: bool,
-        /// Custom path for the graph image (png/pdf/etc); overrides --graph name
+        /// Custom path for the graph image (png/pdf/etc, `-` for PNG on stdout); overrides --graph name
         #[arg(long = "graph-path")]
         graph_path: Option<PathBuf>,
-        /// Limit metrics to specific sensor names (repeatable)
+        /// Show the chart inline after the report (kitty, iTerm2/WezTerm or sixel terminals)
+        #[arg(long = "graph-inline")]
+        graph_inline: bool,
+        /// Limit metrics to specific sensor names (repeatable, `*` matches any text)
         #[arg(long = "sensor", value_name = "NAME", num_args = 0..)]
         sensor_filters: Vec<String>,
-        /// Which report presets to render (repeatable)
-        #[arg(
-            long = "preset",
-            value_enum,
-            num_args = 0..,
-            default_values_t = [ReportPreset::Battery]
-        )]
+        /// Use the defaults of `[report.profiles.<NAME>]` instead of `[report]`
+        #[arg(long = "profile", value_name = "NAME")]
+        profile: Option<String>,
+        /// Only samples collected on this host, in databases shared by several (repeatable)
+        #[arg(long = "host", value_name = "HOST", num_args = 0..)]
+        hosts: Vec<String>,
+        /// Only show temperature sensors matching these names, e.g. `coretemp:*` (repeatable) [default: report.temperature_sensors]
+        #[arg(long = "temp-sensor", value_name = "PATTERN", num_args = 0..)]
+        temperature_sensors: Vec<String>,
+        /// Which report presets to render (repeatable) [default: battery, or report.presets]
+        #[arg(long = "preset", value_enum, num_args = 0..)]
         presets: Vec<ReportPreset>,
+        /// Report blocks to print, in this order, e.g. `summary` or `events,summary`
+        /// [default: all of them per preset, or report.sections]
+        #[arg(long = "sections", value_enum, value_delimiter = ',')]
+        sections: Vec<ReportSection>,
+        /// Keep only the N temperature sensors with the highest peak, in tables and graphs
+        #[arg(long = "top", value_name = "N")]
+        top: Option<usize>,
+        /// Group temperature sensors by device (nvme0, coretemp, ...) in tables and charts
+        #[arg(long = "temp-by-device")]
+        temp_by_device: bool,
+        /// Show only the `cpu` total (core frequencies averaged) or only cpu0..cpuN in the
+        /// CPU tables and graph [default: both, or report.cpu_detail]
+        #[arg(long = "cpu-detail", value_enum)]
+        cpu_detail: Option<CpuDetail>,
+        /// Report each battery (BAT0, BAT1, ...) separately instead of as one combined battery
+        #[arg(long = "per-battery")]
+        per_battery: bool,
+        /// Temperature (C) above which CPU frequency drops count as likely thermal throttling
+        #[arg(long = "throttle-temp", default_value_t = DEFAULT_THROTTLE_TEMP_C)]
+        throttle_temp: f64,
+        /// Highlight mountpoints predicted to fill within this many days
+        #[arg(long = "disk-full-days", value_name = "DAYS", default_value_t = DEFAULT_DISK_FULL_DAYS)]
+        disk_full_days: f64,
+        /// Draw these kinds as step lines in graphs, e.g. `disk_usage` [default: report.step_kinds]
+        #[arg(long = "step", value_delimiter = ',', value_parser = parse_metric_kind)]
+        step_kinds: Vec<MetricKind>,
+        /// Shade the min–max range around graph lines that average several sources
+        #[arg(long = "bands")]
+        bands: bool,
+        /// Show how values spread over the timeframe as binned bar tables, per source, for
+        /// these kinds [default: cpu_usage, power_draw, temperature, gpu_usage]
+        #[arg(long = "histogram", value_name = "KINDS", value_delimiter = ',', num_args = 0.., value_parser = parse_metric_kind)]
+        histogram: Option<Vec<MetricKind>>,
+        /// List every battery charge and discharge session (duration, %/h, power, depth)
+        #[arg(long = "sessions")]
+        sessions: bool,
+        /// One row per calendar day instead of the usual blocks: screen time, energy used,
+        /// temperatures and data transferred [default window: 7 days]
+        #[arg(long = "daily")]
+        daily: bool,
+        /// Show per-interface monthly totals against the configured data cap
+        #[arg(long = "data-cap")]
+        data_cap: bool,
+        /// Print the tables as text, or as JSON, CSV or Markdown for scripts and dashboards
+        #[arg(long = "format", value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+        /// Print `key=value` lines instead of tables, the summary blocks only unless
+        /// `--sections` says otherwise (same as `--format key-value`)
+        #[arg(long = "no-table", conflicts_with = "format")]
+        no_table: bool,
+        /// Statistics columns to show, e.g. `min,avg,max` (matched against the header
+        /// words; the first column of each table always stays)
+        #[arg(long = "columns", value_delimiter = ',', value_name = "NAMES")]
+        columns: Vec<String>,
+        /// Drop columns to fit thin terminals (auto), always (narrow) or never (wide)
+        #[arg(long = "layout", value_enum, default_value_t = TableLayout::Auto)]
+        layout: TableLayout,
+        /// Only the report itself: no log lines and no notes between the tables
+        #[arg(short, long, conflicts_with = "verbose")]
+        quiet: bool,
         /// Enable debug logging
         #[arg(short, long)]
         verbose: bool,
     },
+    /// Collect every few seconds without storing and redraw CPU, memory, power and
+    /// temperatures in place
+    Live {
+        /// Seconds between refreshes (default 2)
+        #[arg(long = "interval")]
+        interval: Option<u64>,
+    },
+    /// Key metrics over the last 24h, 7d and 30d in one table
+    Overview {
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// One health row per machine (last seen, battery, fullest disk, 7d temperature and power)
+    Fleet {
+        /// SQLite databases, per machine or shared (or set SYMMETRI_DB for a single one)
+        #[arg(long = "db")]
+        db_paths: Vec<PathBuf>,
+    },
+    /// Exit non-zero when an alert condition holds, e.g. `--rule 'temperature.max > 90'`,
+    /// for cron jobs and monitoring scripts
+    Check {
+        /// Alert condition `kind[source].stat <op> threshold` (stat: last, min, max or avg;
+        /// op: >, >=, <, <=, == or !=); repeat for several
+        #[arg(long = "rule", required = true)]
+        rules: Vec<Rule>,
+        /// Evaluate over a window such as 1h or 7d instead of each source's latest sample
+        #[arg(long = "last")]
+        last: Option<String>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Print stored samples for other tools, e.g. InfluxDB line protocol
+    Export {
+        /// Output format
+        #[arg(long = "format", value_enum, default_value_t = ExportFormat::Influx)]
+        format: ExportFormat,
+        /// Window such as 6h, 7d, 2w, 3mo or all
+        #[arg(long = "last", default_value = "all")]
+        last: String,
+        /// Only these kinds, e.g. `cpu_usage,temperature` (all when omitted)
+        #[arg(long = "kind", value_delimiter = ',', value_parser = parse_metric_kind)]
+        kinds: Vec<MetricKind>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Stream raw samples as JSON lines, oldest first and without bucketing, e.g. into jq
+    Dump {
+        /// Only these kinds, e.g. `cpu_usage,temperature` (all when omitted)
+        #[arg(long = "kind", value_delimiter = ',', value_parser = parse_metric_kind)]
+        kinds: Vec<MetricKind>,
+        /// A window back from now such as 2h, 7d or all, or a date/time such as
+        /// `2025-03-01` or `2025-03-01 14:00`
+        #[arg(long = "since", default_value = "all")]
+        since: String,
+        /// Last date/time to include
+        #[arg(long = "until")]
+        until: Option<String>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Bring the hourly and daily rollups that long reports read up to date
+    Compact {
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Database maintenance: backups and corruption checks
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// Print every metric kind in the database (unit, sources, time span, rows) as JSON
+    Catalog {
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Print the JSON on a single line
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Run a read-only SQL statement against the database and print its rows
+    Query {
+        /// e.g. "SELECT source, MAX(value) FROM metric_samples WHERE kind = 'temperature'
+        /// GROUP BY source"
+        sql: String,
+        #[arg(long = "format", value_enum, default_value_t = QueryFormat::Table)]
+        format: QueryFormat,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Print the JSON Schema of a JSON output (all of them when no name is given)
+    Schema {
+        #[arg(value_enum)]
+        name: Option<SchemaName>,
+    },
+    /// Detect hardware, choose collectors and write the config (and optionally a systemd timer)
+    Setup,
+    /// Probe every collector group and show what will and won't be collected here, and why
+    Doctor,
+    /// List every source a collection finds (batteries, sensors, interfaces, cards,
+    /// mountpoints) under the exact names used in rules, transforms and derived metrics
+    Sensors,
+    /// Print a one-line status of the latest samples for i3blocks, polybar and other bars
+    Status {
+        /// Print the latest sample of every kind and source as a table, with the battery
+        /// time remaining
+        #[arg(long = "all", conflicts_with_all = ["metric", "format", "tmux", "click", "watch"])]
+        all: bool,
+        /// Metric to show instead of the one selected by clicking
+        #[arg(long = "metric", value_enum)]
+        metric: Option<StatusMetric>,
+        /// Output protocol
+        #[arg(long = "format", value_enum, default_value_t = StatusFormat::Plain)]
+        format: StatusFormat,
+        /// Colored tmux string for `status-right` (same as `--format tmux`)
+        #[arg(long = "tmux", conflicts_with = "format")]
+        tmux: bool,
+        /// Handle a click first: 1-5 or left, right, up, down (i3blocks sets BLOCK_BUTTON)
+        #[arg(long = "click")]
+        click: Option<String>,
+        /// Keep running, printing a line every SECONDS (`[viewer] refresh`, else 5) and
+        /// reading click events on stdin
+        #[arg(long = "watch", value_name = "SECONDS", num_args = 0..=1)]
+        watch: Option<Option<u64>>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Copy the database to a new file, safely while the collector is writing
+    Backup {
+        /// File to create
+        path: PathBuf,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Check the database for corruption (`PRAGMA integrity_check`)
+    Check {
+        /// Also salvage every readable row into this new file, to `db restore` afterwards
+        #[arg(long = "recover", value_name = "PATH")]
+        recover: Option<PathBuf>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Replace the database with a backup
+    Restore {
+        /// Backup to restore
+        path: PathBuf,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
 }
 
+/// Kinds binned by a bare `report --histogram`.
+const DEFAULT_HISTOGRAM_KINDS: [MetricKind; 4] = [
+    MetricKind::CpuUsage,
+    MetricKind::PowerDraw,
+    MetricKind::Temperature,
+    MetricKind::GpuUsage,
+];
+
+/// Seconds between `status --watch` lines when neither the flag nor `[viewer]` sets it.
+const DEFAULT_WATCH_SECONDS: u64 = 5;
+
 fn configure_logging(verbose: bool) {
     let mut builder = env_logger::Builder::from_env(env_logger::Env::default());
     builder.format(|buf, record| writeln!(buf, "{}", record.args()));
+    builder.write_style(match COLOR.get().copied().unwrap_or_default() {
+        ColorChoice::Auto => env_logger::WriteStyle::Auto,
+        ColorChoice::Always => env_logger::WriteStyle::Always,
+        ColorChoice::Never => env_logger::WriteStyle::Never,
+    });
     if verbose {
         builder.filter_level(log::LevelFilter::Debug);
     } else {
@@ -108,63 +488,113 @@ fn configure_logging(verbose: bool) {
     let _ = builder.try_init();
 }
 
-fn preset_kinds(preset: ReportPreset) -> &'static [MetricKind] {
-    match preset {
-        ReportPreset::Battery => &[
-            MetricKind::BatteryPercentage,
-            MetricKind::BatteryCapacity,
-            MetricKind::BatteryHealth,
-            MetricKind::BatteryEnergyNow,
-            MetricKind::BatteryEnergyFull,
-            MetricKind::BatteryEnergyFullDesign,
-            MetricKind::PowerDraw,
-        ],
-        ReportPreset::Cpu => &[MetricKind::CpuUsage, MetricKind::CpuFrequency],
-        ReportPreset::Gpu => &[MetricKind::GpuUsage, MetricKind::GpuFrequency],
-        ReportPreset::Memory => &[MetricKind::MemoryUsage],
-        ReportPreset::Network => &[MetricKind::NetworkBytes],
-        ReportPreset::Temperature => &[MetricKind::Temperature],
-        ReportPreset::Disk => &[MetricKind::DiskUsage],
-        ReportPreset::All => &[],
-    }
-}
-
-fn normalize_presets(mut presets: Vec<ReportPreset>) -> Vec<ReportPreset> {
-    if presets.is_empty() {
-        return vec![ReportPreset::Battery];
-    }
-    if presets.contains(&ReportPreset::All) {
-        return vec![
-            ReportPreset::Battery,
-            ReportPreset::Cpu,
-            ReportPreset::Gpu,
-            ReportPreset::Memory,
-            ReportPreset::Network,
-            ReportPreset::Temperature,
-            ReportPreset::Disk,
-        ];
+/// Timeframe from the CLI flags, falling back to `report.timeframe` and then the last 6 hours.
+/// Database file of a command that works on a local SQLite database only.
+fn local_db_path(db_path: Option<&Path>, command: &str) -> Result<PathBuf> {
+    let resolved = resolve_db_path(db_path);
+    if storage::is_remote(&resolved) {
+        anyhow::bail!(
+            "`symmetri {command}` is not supported on PostgreSQL; it works on a local SQLite \
+             database only"
+        );
+    }
+    Ok(resolved)
+}
+
+fn resolve_report_timeframe(
+    hours: Option<u64>,
+    days: Option<u64>,
+    months: Option<u64>,
+    all_time: bool,
+    config: &ReportConfig,
+) -> Result<Timeframe> {
+    let flags_given = hours.is_some() || days.is_some() || months.is_some() || all_time;
+    if let (false, Some(window)) = (flags_given, config.timeframe.as_deref()) {
+        return parse_window(window).context("invalid report.timeframe in config");
     }
-    presets.sort();
-    presets.dedup();
-    presets
+    Ok(build_timeframe(
+        hours.unwrap_or(6) as i64,
+        days.unwrap_or(0) as i64,
+        months.unwrap_or(0) as i64,
+        all_time,
+    )?)
 }
 
-fn metric_kinds_for_presets(presets: &[ReportPreset]) -> Vec<MetricKind> {
-    let mut kinds = Vec::new();
-    for preset in presets {
-        kinds.extend_from_slice(preset_kinds(*preset));
+/// Value of the global `--config` option, looked up before clap parses the arguments so
+/// aliases can be expanded.
+fn config_path_arg(args: &[OsString]) -> Option<PathBuf> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--config" {
+            return iter.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
     }
-    kinds.sort_by(|a, b| a.as_str().cmp(b.as_str()));
-    kinds.dedup();
-    kinds
+    None
+}
+
+/// Every global flag as `--name` and whether it takes a value, so the pre-parse steps over
+/// them the way clap does.
+fn global_flags() -> Vec<(String, bool)> {
+    Cli::command()
+        .get_arguments()
+        .filter(|arg| arg.is_global_set())
+        .filter_map(|arg| {
+            let long = arg.get_long()?;
+            Some((format!("--{long}"), arg.get_action().takes_values()))
+        })
+        .collect()
 }
 
-fn has_data_for_preset(preset: ReportPreset, metrics: &[MetricSample]) -> bool {
-    if preset == ReportPreset::All {
-        return !metrics.is_empty();
+/// Replace a configured alias with its arguments, or insert the default command when no
+/// subcommand is given. Built-in subcommands always take precedence over aliases.
+fn expand_command_aliases(mut args: Vec<OsString>, config: &Config) -> Vec<OsString> {
+    let globals = global_flags();
+    let mut idx = 1;
+    while idx < args.len() {
+        let arg = args[idx].to_string_lossy();
+        let global = globals.iter().find(|(flag, _)| {
+            arg == flag.as_str()
+                || arg
+                    .strip_prefix(flag.as_str())
+                    .is_some_and(|rest| rest.starts_with('='))
+        });
+        match global {
+            Some((flag, true)) if arg == flag.as_str() => idx += 2,
+            Some(_) => idx += 1,
+            // --help, --version, ... are left to clap.
+            None if arg.starts_with('-') => return args,
+            None => break,
+        }
+    }
+
+    if idx >= args.len() {
+        match &config.default_command {
+            Some(default) => args.extend(default.iter().map(OsString::from)),
+            None => return args,
+        }
+    }
+    let name = args[idx].to_string_lossy().to_string();
+    let builtin = name == "help"
+        || Cli::command()
+            .get_subcommands()
+            .any(|cmd| cmd.get_name() == name || cmd.get_all_aliases().any(|a| a == name));
+    if builtin {
+        return args;
+    }
+    match config.aliases.get(&name) {
+        Some(expansion) => {
+            let rest = args.split_off(idx + 1);
+            args.truncate(idx);
+            args.extend(expansion.iter().map(OsString::from));
+            args.extend(rest);
+            args
+        }
+        None => args,
     }
-    let kinds = preset_kinds(preset);
-    metrics.iter().any(|m| kinds.contains(&m.kind))
 }
 
 pub fn run<I, T>(args: I) -> Result<()>
@@ -172,7 +602,26 @@ where
     I: IntoIterator<Item = T>,
     T: Into<OsString> + Clone,
 {
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    // A broken config must not hide `--help`; its error is reported after parsing.
+    let loaded = config::load_config(config_path_arg(&args).as_deref());
+    let args = match &loaded {
+        Ok(config) => expand_command_aliases(args, config),
+        Err(_) => args,
+    };
     let cli = Cli::parse_from(args);
+    let config = match loaded {
+        Ok(config) => config,
+        // The wizard writes a fresh config, so an invalid existing one must not stop it.
+        Err(_) if matches!(cli.command, Commands::Setup) => Config::default(),
+        Err(err) => return Err(err),
+    };
+    let _ = COLOR.set(cli.color.resolve(cli.plain, std::env::var_os("NO_COLOR")));
+    let _ = TABLE_THEME.set(if cli.plain {
+        TableTheme::Plain
+    } else {
+        config.viewer.theme
+    });
     match cli.command {
         Commands::Collect {
             db_path,
@@ -181,571 +630,1841 @@ where
         } => {
             configure_logging(verbose);
             if let Some(interval) = interval {
-                collect_loop(interval, db_path.as_deref(), None)?;
+                collect_loop(interval, db_path.as_deref(), None, &config)?;
             } else {
-                let code = collect_once(db_path.as_deref(), None)?;
+                let code = collect_once(db_path.as_deref(), None, &config)?;
                 if code != 0 {
                     return Err(anyhow::anyhow!("Collection failed with exit code {code}"));
                 }
             }
         }
+        Commands::Graph {
+            overlay,
+            last,
+            step_kinds,
+            db_path,
+            graph_path,
+            verbose,
+        } => {
+            configure_logging(verbose);
+            if overlay.len() != 2 {
+                return Err(anyhow::anyhow!(
+                    "--overlay takes exactly two metric kinds, e.g. power_draw,cpu_usage"
+                ));
+            }
+            let timeframe = parse_window(&last)?;
+            let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+            let raw_metrics =
+                store.fetch_samples(timeframe.since_timestamp(None), None, Some(&overlay))?;
+            let metrics = crate::aggregate::aggregate_multi_device_metrics(&raw_metrics);
+            let path = graph_path.unwrap_or_else(|| {
+                let label = format!("{}_{}_{}", overlay[0], overlay[1], timeframe.label);
+                default_graph_path(&label, None, Some(Local::now()))
+            });
+            graph::render_overlay(
+                &metrics,
+                (&overlay[0], &overlay[1]),
+                &timeframe,
+                &step_kinds,
+                &path,
+            )?;
+        }
+        Commands::Correlate {
+            x,
+            y,
+            hours,
+            days,
+            bucket_minutes,
+            db_path,
+            graph_path,
+            verbose,
+        } => {
+            configure_logging(verbose);
+            let timeframe = build_timeframe(hours as i64, days as i64, 0, false)?;
+            let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+            let raw_metrics = store.fetch_samples(
+                timeframe.since_timestamp(None),
+                None,
+                Some(&[x.clone(), y.clone()]),
+            )?;
+            // The overall CPU line, not its mean with the per-core ones
+            let metrics = crate::report::apply_cpu_detail(
+                crate::aggregate::aggregate_multi_device_metrics(&raw_metrics),
+                CpuDetail::Aggregate,
+            );
+            let aligned =
+                correlate::align_by_bucket(&metrics, &x, &y, bucket_minutes.max(1) as f64 * 60.0);
+            let points: Vec<(f64, f64)> = aligned.iter().map(|(_, x, y)| (*x, *y)).collect();
+            let fit = crate::forecast::linear_fit(&points);
+            match correlate::pearson(&points) {
+                Some(r) => println!(
+                    "{y} vs {x} over {}: r = {r:.2} ({} {} correlation, {} buckets of {bucket_minutes} min)",
+                    timeframe.label.replace('_', " "),
+                    correlate::correlation_strength(r),
+                    if r < 0.0 { "negative" } else { "positive" },
+                    points.len(),
+                ),
+                None => println!(
+                    "{y} vs {x} over {}: not enough varying data to correlate ({} buckets)",
+                    timeframe.label.replace('_', " "),
+                    points.len(),
+                ),
+            }
+            if let Some((slope, _)) = fit {
+                println!("{y} changes by {slope:.3} per unit of {x}");
+            }
+            let path = graph_path.unwrap_or_else(|| {
+                let label = format!("{y}_vs_{x}_{}", timeframe.label);
+                default_graph_path(&label, None, Some(Local::now()))
+            });
+            graph::render_scatter(&metrics, (&x, &y), &points, fit, &timeframe, &path)?;
+        }
+        Commands::Live { interval } => {
+            // No logger: the collector's per-run messages would scroll the view away
+            let interval = interval
+                .or(config.viewer.refresh)
+                .unwrap_or(live::DEFAULT_LIVE_SECONDS)
+                .max(1);
+            let redraw = !matches!(TABLE_THEME.get(), Some(TableTheme::Plain));
+            collect_live(interval, None, &config, |samples| {
+                let rows = live::live_rows(samples, &config.viewer.panels);
+                let when = rows
+                    .first()
+                    .map(|sample| local_datetime(sample.ts).format("%H:%M:%S").to_string())
+                    .unwrap_or_default();
+                if redraw {
+                    // Home the cursor and clear the screen, so the table stays in place
+                    print!("\x1b[H\x1b[2J");
+                }
+                println!("symmetri live {when}, every {interval}s (Ctrl-C to quit)");
+                println!("{}", live_table(&rows));
+                std::io::stdout().flush()?;
+                Ok(())
+            })?;
+        }
+        Commands::Overview { db_path } => {
+            let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let columns = overview::overview(store.as_ref(), now)?;
+            println!("{}", overview_table(&columns, &config.viewer.panels));
+        }
+        Commands::Check {
+            rules,
+            last,
+            db_path,
+        } => {
+            let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+            let mut kinds: Vec<MetricKind> = rules.iter().map(|rule| rule.kind.clone()).collect();
+            kinds.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+            kinds.dedup();
+            let samples = match last {
+                Some(window) => {
+                    let since = parse_window(&window)?.since_timestamp(None);
+                    store.fetch_samples(since, None, Some(&kinds))?
+                }
+                None => store.fetch_latest(Some(&kinds))?,
+            };
+            let mut failed = 0;
+            for rule in &rules {
+                let outcome = check::evaluate(rule, &samples);
+                let values = if outcome.violations.is_empty() {
+                    &outcome.values
+                } else {
+                    &outcome.violations
+                };
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|(source, value)| format!("{source} {value:.1}"))
+                    .collect();
+                let status = match (outcome.passed(), outcome.values.is_empty()) {
+                    (true, _) => "ok",
+                    (false, true) => "NO DATA",
+                    (false, false) => "FAIL",
+                };
+                if !outcome.passed() {
+                    failed += 1;
+                }
+                println!("{status}: {rule} ({})", values.join(", "));
+            }
+            if failed > 0 {
+                return Err(anyhow::anyhow!(
+                    "{failed} of {} rules failed or had no data",
+                    rules.len()
+                ));
+            }
+        }
+        Commands::Fleet { db_paths } => {
+            let db_paths = if db_paths.is_empty() {
+                vec![resolve_db_path(None)]
+            } else {
+                db_paths
+            };
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let mut rows = Vec::new();
+            for db_path in &db_paths {
+                let store = storage::open(db_path)?;
+                let untagged_host = store.database_host()?;
+                let samples = store.fetch_samples(
+                    Some(now - fleet::FLEET_WINDOW_SECONDS),
+                    None,
+                    Some(&fleet::FLEET_KINDS),
+                )?;
+                let last_seen = store.last_seen_by_host()?;
+                rows.extend(fleet::fleet_rows(&untagged_host, &last_seen, &samples));
+            }
+            println!("{}", fleet_table(&rows));
+        }
+        Commands::Export {
+            format,
+            last,
+            kinds,
+            db_path,
+        } => {
+            let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+            let since = parse_window(&last)?.since_timestamp(None);
+            let kinds = (!kinds.is_empty()).then_some(kinds.as_slice());
+            let samples = store.fetch_samples(since, None, kinds)?;
+            export::write_samples(&mut std::io::stdout().lock(), &samples, format)?;
+        }
+        Commands::Dump {
+            kinds,
+            since,
+            until,
+            db_path,
+        } => {
+            let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+            let since = match parse_window(&since) {
+                Ok(window) => window.since_timestamp(None),
+                Err(_) => Some(parse_timestamp(&since, false)?),
+            };
+            let until = until
+                .as_deref()
+                .map(|raw| parse_timestamp(raw, true))
+                .transpose()?;
+            let kinds = (!kinds.is_empty()).then_some(kinds.as_slice());
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            let streamed = store
+                .for_each_sample(since, until, kinds, &mut |sample| {
+                    writeln!(out, "{}", serde_json::to_string(&sample)?)?;
+                    Ok(())
+                })
+                .and_then(|()| Ok(out.flush()?));
+            match streamed {
+                // The reader (`head`, a closed jq) has seen enough
+                Err(err)
+                    if err
+                        .downcast_ref::<std::io::Error>()
+                        .is_some_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe) => {}
+                streamed => streamed?,
+            }
+        }
+        Commands::Compact { db_path } => {
+            let mut conn = db::init_db_connection(&local_db_path(db_path.as_deref(), "compact")?)?;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let written = db::update_rollups(&mut conn, now)?;
+            println!("Wrote {written} rollup rows");
+        }
+        Commands::Db { command } => match command {
+            DbCommand::Backup { path, db_path } => {
+                let conn =
+                    db::init_db_connection(&local_db_path(db_path.as_deref(), "db backup")?)?;
+                db::backup_db(&conn, &path)?;
+                println!("Backed up to {}", path.display());
+            }
+            DbCommand::Check { recover, db_path } => {
+                let resolved = local_db_path(db_path.as_deref(), "db check")?;
+                let problems = db::integrity_check(&resolved)?;
+                if problems.is_empty() {
+                    println!("{}: ok", resolved.display());
+                } else {
+                    for problem in &problems {
+                        println!("{problem}");
+                    }
+                }
+                if let Some(dest) = &recover {
+                    let recovery = db::recover_db(&resolved, dest)?;
+                    println!(
+                        "Recovered {} rows from {} tables into {}{}",
+                        recovery.rows,
+                        recovery.tables,
+                        dest.display(),
+                        if recovery.unreadable > 0 {
+                            format!(" ({} rows unreadable)", recovery.unreadable)
+                        } else {
+                            String::new()
+                        }
+                    );
+                } else if !problems.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "{} is damaged; salvage it with `symmetri db check --recover NEW.db`, \
+                         then `symmetri db restore NEW.db`",
+                        resolved.display()
+                    ));
+                }
+            }
+            DbCommand::Restore { path, db_path } => {
+                let resolved = local_db_path(db_path.as_deref(), "db restore")?;
+                let mut conn = db::init_db_connection(&resolved)?;
+                db::restore_db(&mut conn, &path)?;
+                println!("Restored {} from {}", resolved.display(), path.display());
+            }
+        },
+        Commands::Catalog { db_path, compact } => {
+            let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+            let catalog = store.catalog()?;
+            let json = if compact {
+                serde_json::to_string(&catalog)?
+            } else {
+                serde_json::to_string_pretty(&catalog)?
+            };
+            println!("{json}");
+        }
+        Commands::Query {
+            sql,
+            format,
+            db_path,
+        } => {
+            let result = db::query_read_only(&local_db_path(db_path.as_deref(), "query")?, &sql)?;
+            match format {
+                QueryFormat::Table => {
+                    let mut table = themed_table();
+                    table.set_header(header_cells(
+                        &result
+                            .columns
+                            .iter()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>(),
+                    ));
+                    for row in &result.rows {
+                        table.add_row(row.iter().map(|value| {
+                            if value.parse::<f64>().is_ok() {
+                                value_cell(value)
+                            } else {
+                                Cell::new(value)
+                            }
+                        }));
+                    }
+                    println!("{table}");
+                }
+                QueryFormat::Csv => {
+                    for row in std::iter::once(&result.columns).chain(&result.rows) {
+                        let fields: Vec<String> = row.iter().map(|v| csv_field(v)).collect();
+                        println!("{}", fields.join(","));
+                    }
+                }
+            }
+        }
+        Commands::Schema { name } => {
+            let json = match name {
+                Some(name) => serde_json::to_value(schema::schema(name))?,
+                None => schema::all_schemas(),
+            };
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        Commands::Setup => setup::run(cli.config_path.as_deref())?,
+        Commands::Doctor => println!("{}", doctor_table(&doctor::diagnose(&config))),
+        Commands::Sensors => println!(
+            "{}",
+            sensors_table(&doctor::trial_collection(&config), &config)
+        ),
+        Commands::Status {
+            all,
+            metric,
+            format,
+            tmux,
+            click,
+            watch,
+            db_path,
+        } => {
+            configure_logging(false);
+            if all {
+                let store = storage::open(&resolve_db_path(db_path.as_deref()))?;
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                let mut latest = store.fetch_latest(None)?;
+                if latest.is_empty() {
+                    println!("No samples recorded yet.");
+                    return Ok(());
+                }
+                latest.sort_by(|a, b| {
+                    (a.kind.as_str(), &a.source).cmp(&(b.kind.as_str(), &b.source))
+                });
+                println!("{}", latest_table(&latest, now));
+                let battery = store.fetch_samples(
+                    Some(now - status::BATTERY_RATE_WINDOW_SECS),
+                    None,
+                    Some(&[MetricKind::BatteryEnergyNow, MetricKind::BatteryEnergyFull]),
+                )?;
+                if let Some(eta) = status::battery_eta(&battery) {
+                    let what = if eta.charging { "to full" } else { "remaining" };
+                    println!("Battery: {} {what}", format_runtime(Some(eta.hours)));
+                }
+                return Ok(());
+            }
+            let options = StatusOptions {
+                metric,
+                format: if tmux { StatusFormat::Tmux } else { format },
+                click,
+                plain: cli.plain,
+                watch: watch.map(|seconds| {
+                    seconds
+                        .or(config.viewer.refresh)
+                        .unwrap_or(DEFAULT_WATCH_SECONDS)
+                }),
+            };
+            status::run(
+                &resolve_db_path(db_path.as_deref()),
+                &config.status,
+                options,
+            )?;
+        }
         Commands::Report {
             hours,
             days,
             months,
             all_time,
-            db_path,
+            since,
+            until,
+            db_paths,
             graph: graph_flag,
             graph_path,
+            graph_inline,
             presets,
+            sections,
             sensor_filters,
+            profile,
+            hosts,
+            temperature_sensors,
+            top,
+            temp_by_device,
+            cpu_detail,
+            per_battery,
+            throttle_temp,
+            disk_full_days,
+            step_kinds,
+            bands,
+            histogram,
+            sessions,
+            daily,
+            data_cap,
+            format,
+            no_table,
+            columns,
+            layout,
+            quiet,
             verbose,
         } => {
             configure_logging(verbose);
-            let timeframe = build_timeframe(hours as i64, days as i64, months as i64, all_time)?;
-            let resolved = resolve_db_path(db_path.as_deref());
-            let presets = normalize_presets(presets);
-            let metric_kinds = metric_kinds_for_presets(&presets);
-
-            let conn = db::init_db_connection(&resolved)?;
-            let metric_total = db::count_metric_samples_with_conn(&conn, None)?;
-            if metric_total == 0 {
-                return Err(anyhow::anyhow!("No records available; collect data first."));
+            if quiet {
+                log::set_max_level(log::LevelFilter::Warn);
             }
-
-            let since_ts = timeframe.since_timestamp(None);
-            let raw_metrics =
-                db::fetch_metric_samples_with_conn(&conn, since_ts, Some(&metric_kinds))?;
-
-            let aggregated_metrics = crate::aggregate::aggregate_multi_device_metrics(&raw_metrics);
-            let metric_samples = filter_metrics_by_source(&aggregated_metrics, &sensor_filters);
-            let timeframe_record_count = raw_metrics.len();
-
-            let has_selected_data = presets
-                .iter()
-                .any(|preset| has_data_for_preset(*preset, &metric_samples));
-            if !has_selected_data {
-                return Err(anyhow::anyhow!(
-                    "No records for the selected presets in {}; try a broader timeframe or enable those collectors.",
-                    timeframe.label.replace('_', " ")
-                ));
+            let format = if no_table {
+                ReportFormat::KeyValue
+            } else {
+                format
+            };
+            let report_config = &match profile {
+                Some(name) => config.report.with_profile(&name)?,
+                None => config.report.clone(),
+            };
+            // A daily review covers a week unless a window is given
+            let days = match days {
+                None if daily
+                    && hours.is_none()
+                    && months.is_none()
+                    && !all_time
+                    && report_config.timeframe.is_none() =>
+                {
+                    Some(7)
+                }
+                days => days,
+            };
+            let timeframe = resolve_report_timeframe(hours, days, months, all_time, report_config)?;
+            let timeframe = if since.is_some() || until.is_some() {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                bound_timeframe(
+                    timeframe,
+                    since
+                        .as_deref()
+                        .map(|raw| parse_timestamp(raw, false))
+                        .transpose()?,
+                    until
+                        .as_deref()
+                        .map(|raw| parse_timestamp(raw, true))
+                        .transpose()?,
+                    now,
+                )?
+            } else {
+                timeframe
+            };
+            let db_paths = if db_paths.is_empty() {
+                vec![resolve_db_path(None)]
+            } else {
+                db_paths
+            };
+            let presets = if presets.is_empty() {
+                report_config.presets.clone()
+            } else {
+                presets
+            };
+            let plot_options = PlotOptions {
+                step_kinds: if step_kinds.is_empty() {
+                    report_config.step_kinds.clone()
+                } else {
+                    step_kinds
+                },
+                bands: bands || report_config.bands,
+                per_battery,
+            };
+            let sections = match (sections.is_empty(), format) {
+                (false, _) => sections,
+                (true, ReportFormat::KeyValue) if report_config.sections.is_empty() => {
+                    vec![ReportSection::Summary]
+                }
+                (true, _) => report_config.sections.clone(),
+            };
+            let sensor_filters = if sensor_filters.is_empty() {
+                report_config.sources.clone()
+            } else {
+                sensor_filters
+            };
+            let temperature_sensors = if temperature_sensors.is_empty() {
+                report_config.temperature_sensors.clone()
+            } else {
+                temperature_sensors
+            };
+            let graph_flag = graph_flag || report_config.graph;
+            // Plain output goes to logs and screen readers, and structured output to other
+            // programs, so only an explicit flag draws images into them
+            let graph_inline = graph_inline
+                || (report_config.graph_inline && !cli.plain && format == ReportFormat::Text);
+            let mut databases = Vec::new();
+            for db_path in &db_paths {
+                let store = storage::open(db_path)?;
+                let host = store.database_host()?;
+                databases.push((store, host));
             }
+            let report = ReportBuilder::new(timeframe)
+                .presets(presets)
+                .source_filters(sensor_filters.iter().cloned())
+                .hosts(hosts.iter().cloned())
+                .temperature_sensors(temperature_sensors)
+                .sections(sections.iter().copied())
+                .temp_by_device(temp_by_device)
+                .temperature_top(top)
+                .cpu_detail(cpu_detail.or(report_config.cpu_detail))
+                .per_battery(per_battery)
+                .sessions(sessions)
+                .daily(daily)
+                .keep_samples(graph_path.is_some() || graph_flag || graph_inline)
+                .histograms(match histogram {
+                    Some(kinds) if kinds.is_empty() => DEFAULT_HISTOGRAM_KINDS.to_vec(),
+                    Some(kinds) => kinds,
+                    None => Vec::new(),
+                })
+                .throttle_temp(throttle_temp)
+                .disk_full_days(disk_full_days)
+                .bucket_seconds(report_config.bucket)
+                .derived(config.derived.iter().cloned())
+                .build_merged(&databases)?;
+            let (timeframe, presets) = (&report.timeframe, &report.presets);
 
             let output_path = match (graph_path, graph_flag) {
                 (Some(path), _) => Some(path),
                 (None, true) => Some(default_graph_path(
                     &timeframe.label,
-                    None,
+                    report_config
+                        .graph_dir
+                        .as_deref()
+                        .map(config::expand_home)
+                        .as_deref(),
                     Some(Local::now()),
                 )),
                 _ => None,
             };
 
             if let Some(path) = output_path {
-                if metric_samples.is_empty() {
-                    println!("Skipping graph output; no data in timeframe.");
+                if graph::is_stdout(&path) {
+                    // Stdout carries the image; the textual report would corrupt it.
+                    return graph::render_plot(
+                        &report.samples,
+                        presets,
+                        timeframe,
+                        &plot_options,
+                        &path,
+                    );
+                }
+                if report.samples.is_empty() {
+                    if !quiet {
+                        println!("Skipping graph output; no data in timeframe.");
+                    }
                 } else {
-                    graph::render_plot(&metric_samples, &presets, &timeframe, &path)?;
+                    graph::render_plot(&report.samples, presets, timeframe, &plot_options, &path)?;
                 }
             }
 
-            summarize(
-                &metric_samples,
-                &timeframe,
-                timeframe_record_count,
-                &presets,
-            );
+            let mut out = ReportOutput::new(format)
+                .quiet(quiet)
+                .columns(columns, layout);
+            if daily {
+                out.table(
+                    format!("Daily summary ({})", timeframe.label.replace('_', " ")),
+                    daily_table(&report.daily),
+                );
+            } else {
+                print_report(&report, &sections, &mut out);
+            }
+
+            if data_cap {
+                let since = timeframe.since_timestamp(None);
+                let mut network_samples = Vec::new();
+                for (store, host) in &databases {
+                    let samples = data_cap::month_samples(store.as_ref(), since)?;
+                    network_samples.extend(samples.into_iter().map(|mut sample| {
+                        if databases.len() > 1 {
+                            sample.host.get_or_insert_with(|| host.clone());
+                        }
+                        sample
+                    }));
+                }
+                let network_samples = filter_metrics_by_host(&network_samples, &hosts);
+                let network_samples = filter_metrics_by_source(&network_samples, &sensor_filters);
+                let usage = data_cap::monthly_network_usage(&network_samples);
+                if usage.is_empty() {
+                    out.note("\nNo network samples available for monthly data usage.".to_string());
+                } else {
+                    let cap_config = &config.network.data_cap;
+                    out.table(
+                        "Monthly data usage".to_string(),
+                        data_cap_table(&usage, cap_config),
+                    );
+                    let current_month = Local::now().format("%Y-%m").to_string();
+                    let sinks = alert::sinks_from_config(&config.alerts);
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs_f64();
+                    // Alerts are rate-limited through the first local database's log
+                    match databases.iter().find_map(|(store, _)| store.sqlite()) {
+                        Some(conn) => alert::dispatch_limited(
+                            conn,
+                            &sinks,
+                            &config.alerts,
+                            &data_cap::data_cap_alerts(&usage, cap_config, &current_month),
+                            now,
+                        )?,
+                        None => warn!("Data cap alerts need a local database to log them in"),
+                    }
+                }
+            }
+
+            out.finish(&report);
+
+            if graph_inline {
+                match inline_image::detect_protocol() {
+                    Some(protocol) => {
+                        println!();
+                        graph::display_plot_inline(
+                            &report.samples,
+                            presets,
+                            timeframe,
+                            &plot_options,
+                            protocol,
+                        )?;
+                    }
+                    None => warn!(
+                        "--graph-inline: no kitty, iTerm2 or sixel support detected in this terminal"
+                    ),
+                }
+            }
         }
     }
     Ok(())
 }
 
-fn summarize(
-    metrics: &[MetricSample],
-    timeframe: &Timeframe,
-    timeframe_records: usize,
-    presets: &[ReportPreset],
-) {
-    let timeframe_label = timeframe.label.replace('_', " ");
-    let bucket_seconds = bucket_span_seconds(timeframe, data_span_seconds(metrics));
-
-    let battery_metrics: Vec<MetricSample> = metrics
-        .iter()
-        .filter(|m| {
-            matches!(
-                m.kind,
-                MetricKind::BatteryPercentage
-                    | MetricKind::BatteryCapacity
-                    | MetricKind::BatteryHealth
-                    | MetricKind::BatteryEnergyNow
-                    | MetricKind::BatteryEnergyFull
-                    | MetricKind::BatteryEnergyFullDesign
-            )
-        })
-        .cloned()
-        .collect();
+/// Per-block printers in the default report order; each prints its part of one section.
+const REPORT_BLOCKS: [fn(&ReportResult, ReportSection, &str, &mut ReportOutput); 12] = [
+    print_battery,
+    print_cpu,
+    print_gpu,
+    print_memory,
+    print_disk,
+    print_network,
+    print_containers,
+    print_system,
+    print_temperature,
+    print_derived,
+    print_collector,
+    print_general,
+];
 
-    let battery_rates = average_rates(&battery_metrics);
-    let power_draw_stats = average_for_kind(metrics, MetricKind::PowerDraw);
-    let avg_discharge_w = power_draw_stats.average().or(battery_rates.discharge_w);
-    let est_runtime_hours = estimate_runtime_hours(avg_discharge_w, &battery_metrics);
-    let power_draw_by_bucket =
-        bucket_stats_for_kind(metrics, MetricKind::PowerDraw, bucket_seconds);
-
-    if presets.contains(&ReportPreset::Battery) {
-        println!(
-            "\nBattery summary ({})\n{}",
-            timeframe_label,
-            battery_summary_table(
-                timeframe_records,
-                avg_discharge_w,
-                battery_rates.charge_w,
-                est_runtime_hours
-            )
-        );
-
-        if battery_metrics.is_empty() {
-            println!("\nNo battery samples available for buckets in {timeframe_label}.");
-        } else {
-            let (discharge_rates, charge_rates) =
-                battery_rate_buckets(&battery_metrics, bucket_seconds);
-            println!(
-                "\nBattery stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                battery_stats_table(
-                    &battery_metrics,
-                    &power_draw_by_bucket,
-                    &discharge_rates,
-                    &charge_rates,
-                    bucket_seconds
-                )
-            );
+/// Print the selected sections in the given order. Without a selection, every section of a
+/// preset is printed before moving on to the next preset.
+fn print_report(report: &ReportResult, sections: &[ReportSection], out: &mut ReportOutput) {
+    let timeframe_label = report.timeframe.label.replace('_', " ");
+    if sections.is_empty() {
+        print_machine(report, out);
+        for print in REPORT_BLOCKS {
+            for section in ReportSection::value_variants() {
+                print(report, *section, &timeframe_label, out);
+            }
+        }
+    } else {
+        for section in sections {
+            if *section == ReportSection::Machine {
+                print_machine(report, out);
+            }
+            for print in REPORT_BLOCKS {
+                print(report, *section, &timeframe_label, out);
+            }
         }
     }
+}
 
-    if presets.contains(&ReportPreset::Cpu) {
-        let usage_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::CpuUsage, bucket_seconds);
-        let freq_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::CpuFrequency, bucket_seconds);
-        if usage_buckets.is_empty() && freq_buckets.is_empty() {
-            println!("\nNo CPU samples available for {timeframe_label}.");
-        } else {
-            println!(
-                "\nCPU stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                cpu_stats_table(bucket_seconds, &usage_buckets, &freq_buckets)
-            );
-        }
+fn print_machine(report: &ReportResult, out: &mut ReportOutput) {
+    if !report.machines.is_empty() {
+        out.table("Machine".to_string(), machine_table(&report.machines));
     }
+}
 
-    if presets.contains(&ReportPreset::Gpu) {
-        let usage_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::GpuUsage, bucket_seconds);
-        let freq_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::GpuFrequency, bucket_seconds);
-        if usage_buckets.is_empty() && freq_buckets.is_empty() {
-            println!("\nNo GPU samples available for {timeframe_label}.");
-        } else {
-            println!(
-                "\nGPU stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                gpu_stats_table(bucket_seconds, &usage_buckets, &freq_buckets)
+fn print_battery(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let Some(battery) = &report.battery else {
+        return;
+    };
+    match section {
+        ReportSection::Summary => {
+            out.table(
+                format!("Battery summary ({timeframe_label})"),
+                battery_summary_table(
+                    report.record_count,
+                    battery.avg_discharge_w,
+                    battery.avg_charge_w,
+                    battery.est_runtime_hours,
+                ),
             );
+            if let Some(last) = &battery.last_discharge {
+                out.table(
+                    format!("Last discharge ({timeframe_label})"),
+                    last_discharge_table(last),
+                );
+            }
+            if !battery.samples.is_empty() {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                let extremes = &battery.charge_extremes;
+                out.table(
+                    format!("Full charge and discharge ({timeframe_label})"),
+                    charge_extremes_table(extremes, now),
+                );
+                // A shorter window can't tell whether the battery was emptied this month
+                let covers_interval = report.timeframe.until.is_none()
+                    && report
+                        .timeframe
+                        .seconds
+                        .is_none_or(|seconds| seconds >= CALIBRATION_INTERVAL_DAYS * 86400.0);
+                if covers_interval && extremes.calibration_due(now) {
+                    out.note(format!(
+                        "\nNo full discharge in the last {CALIBRATION_INTERVAL_DAYS} days: running the battery from full down to a few percent now and then recalibrates its fuel gauge, keeping the percentage and runtime estimates accurate."
+                    ));
+                }
+            }
+            if !report.battery_packs.is_empty() {
+                out.table(
+                    format!("Battery packs ({timeframe_label})"),
+                    battery_pack_table(&report.battery_packs),
+                );
+            }
         }
-    }
+        ReportSection::Buckets if !report.battery_packs.is_empty() => {
+            for pack in &report.battery_packs {
+                out.table(
+                    format!("Battery stats, {} ({})", pack.source, timeframe_label),
+                    battery_stats_table(
+                        &pack.samples,
+                        &BTreeMap::new(),
+                        &pack.discharge_rates,
+                        &pack.charge_rates,
+                        report.bucket_seconds,
+                        None,
+                    ),
+                );
+            }
+        }
+        ReportSection::Buckets => {
+            if battery.samples.is_empty() {
+                out.note(format!(
+                    "\nNo battery samples available for buckets in {timeframe_label}."
+                ));
+            } else {
+                out.table(
+                    format!("Battery stats ({timeframe_label})"),
+                    battery_stats_table(
+                        &battery.samples,
+                        &battery.power_draw,
+                        &battery.discharge_rates,
+                        &battery.charge_rates,
+                        report.bucket_seconds,
+                        report
+                            .previous
+                            .as_ref()
+                            .map(|previous| (previous.label, &previous.discharge_w)),
+                    ),
+                );
+            }
+        }
+        ReportSection::Breakdowns => {
+            if !battery.sessions.is_empty() {
+                out.table(
+                    format!("Battery sessions ({timeframe_label})"),
+                    battery_session_table(&battery.sessions),
+                );
+            }
 
-    if presets.contains(&ReportPreset::Memory) {
-        let memory_buckets = bucket_usage_stats(metrics, MetricKind::MemoryUsage, bucket_seconds);
-        if memory_buckets.is_empty() {
-            println!("\nNo memory samples available for {timeframe_label}.");
-        } else {
-            println!(
-                "\nMemory stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                memory_stats_table(bucket_seconds, &memory_buckets)
-            );
+            if !battery.discharge_deciles.is_empty() {
+                out.table(
+                    format!("Discharge power by state of charge ({timeframe_label})"),
+                    soc_decile_table("Avg discharge", &battery.discharge_deciles),
+                );
+            }
+
+            if !battery.activity.is_empty() {
+                out.table(
+                    format!("Discharge by activity ({timeframe_label})"),
+                    activity_drain_table(&battery.activity),
+                );
+            }
+
+            if !battery.keyboard_backlight.is_empty() {
+                out.table(
+                    format!("Discharge by keyboard backlight ({timeframe_label})"),
+                    backlight_drain_table(&battery.keyboard_backlight),
+                );
+            }
+
+            if !battery.charge_curves.is_empty() {
+                out.table(
+                    format!("Charging curve ({timeframe_label})"),
+                    charge_curve_table(&battery.charge_curves),
+                );
+                if !battery.charge_deciles.is_empty() {
+                    out.table(
+                        format!("Charge power by state of charge ({timeframe_label})"),
+                        soc_decile_table("Avg charge", &battery.charge_deciles),
+                    );
+                }
+            }
+
+            if !battery.chargers.is_empty() {
+                out.table(
+                    format!("Charging by charger ({timeframe_label})"),
+                    charger_rate_table(&battery.chargers),
+                );
+            }
+
+            if !battery.peripherals.is_empty() {
+                out.table(
+                    format!("Peripheral batteries ({timeframe_label})"),
+                    peripheral_battery_table(&battery.peripherals),
+                );
+            }
+
+            if !battery.wakeup_sources.is_empty() {
+                out.table(
+                    format!("Top wakeup sources ({timeframe_label})"),
+                    wakeup_source_table(&battery.wakeup_sources),
+                );
+            }
         }
+        ReportSection::Events | ReportSection::Machine => {}
     }
+}
 
-    if presets.contains(&ReportPreset::Disk) {
-        let disk_buckets = bucket_usage_stats(metrics, MetricKind::DiskUsage, bucket_seconds);
-        if disk_buckets.is_empty() {
-            println!("\nNo disk samples available for {timeframe_label}.");
-        } else {
-            println!(
-                "\nDisk stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                disk_stats_table(bucket_seconds, &disk_buckets)
-            );
+fn print_cpu(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let Some(cpu) = &report.cpu else {
+        return;
+    };
+    let bucket_seconds = report.bucket_seconds;
+    match section {
+        ReportSection::Buckets => {
+            if cpu.usage.is_empty() && cpu.frequency.is_empty() {
+                out.note(format!("\nNo CPU samples available for {timeframe_label}."));
+            } else {
+                out.table(
+                    format!("CPU stats ({timeframe_label})"),
+                    cpu_stats_table(bucket_seconds, &cpu.usage, &cpu.frequency, &cpu.governors),
+                );
+            }
+
+            if has_nonzero_deltas(&cpu.throttle_events) {
+                out.table(
+                    format!("CPU throttling ({timeframe_label})"),
+                    counter_delta_table(bucket_seconds, "Throttle events", &cpu.throttle_events),
+                );
+            }
+
+            if !cpu.context_switches.is_empty() || !cpu.interrupts.is_empty() {
+                out.table(
+                    format!("Scheduler activity ({timeframe_label})"),
+                    scheduler_rates_table(bucket_seconds, &cpu.context_switches, &cpu.interrupts),
+                );
+            }
+
+            if !cpu.iowait.is_empty() || !cpu.steal.is_empty() {
+                out.table(
+                    format!("CPU iowait and steal ({timeframe_label})"),
+                    cpu_wait_table(bucket_seconds, &cpu.iowait, &cpu.steal),
+                );
+            }
+
+            if !cpu.idle_residency.is_empty() {
+                out.table(
+                    format!("CPU idle state residency ({timeframe_label})"),
+                    idle_residency_table(bucket_seconds, &cpu.idle_residency),
+                );
+            }
+        }
+        ReportSection::Events => {
+            if let Some(summary) = &cpu.thermal_throttle {
+                out.note(throttle_summary_line(summary, timeframe_label));
+            }
         }
+        ReportSection::Summary | ReportSection::Breakdowns | ReportSection::Machine => {}
     }
+}
 
-    if presets.contains(&ReportPreset::Network) {
-        let network_buckets = bucket_network_totals(metrics, bucket_seconds);
-        if network_buckets.is_empty() {
-            println!("\nNo network samples available for {timeframe_label}.");
-        } else {
-            println!(
-                "\nNetwork stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                network_totals_table(bucket_seconds, &network_buckets)
-            );
-        }
+fn print_gpu(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let Some(gpu) = &report.gpu else {
+        return;
+    };
+    if section == ReportSection::Summary && !gpu.limits.is_empty() {
+        out.table(
+            format!("GPU limits ({timeframe_label})"),
+            gpu_limit_table(&gpu.limits),
+        );
+    }
+    if section != ReportSection::Buckets {
+        return;
+    }
+    if gpu.usage.is_empty() && gpu.frequency.is_empty() {
+        out.note(format!("\nNo GPU samples available for {timeframe_label}."));
+    } else {
+        out.table(
+            format!("GPU stats ({timeframe_label})"),
+            gpu_stats_table(report.bucket_seconds, &gpu.usage, &gpu.frequency),
+        );
     }
+}
 
-    if presets.contains(&ReportPreset::Temperature) {
-        let temp_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::Temperature, bucket_seconds);
-        if temp_buckets.is_empty() {
-            println!("\nNo temperature samples available for {timeframe_label}.");
-        } else {
-            println!(
-                "\nTemperature stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                temperature_stats_table(bucket_seconds, &temp_buckets)
-            );
+fn print_memory(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let Some(memory) = &report.memory else {
+        return;
+    };
+    match section {
+        ReportSection::Buckets => {
+            if memory.buckets.is_empty() {
+                out.note(format!(
+                    "\nNo memory samples available for {timeframe_label}."
+                ));
+            } else {
+                out.table(
+                    format!("Memory stats ({timeframe_label})"),
+                    memory_stats_table(report.bucket_seconds, &memory.buckets),
+                );
+            }
         }
+        ReportSection::Events => {
+            if !memory.leaks.is_empty() {
+                out.table(
+                    format!("Possible memory leaks ({timeframe_label})"),
+                    memory_leak_table(&memory.leaks),
+                );
+            }
+        }
+        ReportSection::Summary | ReportSection::Breakdowns | ReportSection::Machine => {}
     }
 }
 
-fn format_power(value: Option<f64>) -> String {
-    match value {
-        Some(v) => format!("{v:.2}W"),
-        None => "--".to_string(),
+fn print_disk(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let Some(disk) = &report.disk else {
+        return;
+    };
+    match section {
+        ReportSection::Summary => {
+            if !disk.mounts.is_empty() {
+                out.table(
+                    format!("Disk mountpoints ({timeframe_label})"),
+                    disk_mounts_table(&disk.mounts),
+                );
+            }
+        }
+        ReportSection::Buckets => {
+            if disk.buckets.is_empty() {
+                out.note(format!(
+                    "\nNo disk samples available for {timeframe_label}."
+                ));
+            } else {
+                out.table(
+                    format!("Disk stats ({timeframe_label})"),
+                    disk_stats_table(report.bucket_seconds, &disk.buckets),
+                );
+            }
+        }
+        ReportSection::Events => {
+            for mount in &disk.mounts {
+                out.note(
+                    match disk.forecasts.iter().find(|f| f.source == mount.source) {
+                        Some(forecast) => disk_forecast_line(forecast),
+                        None => format!("{}: not enough history for a forecast", mount.source),
+                    },
+                );
+            }
+        }
+        ReportSection::Breakdowns | ReportSection::Machine => {}
     }
 }
 
-fn themed_table() -> Table {
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL_CONDENSED)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_content_arrangement(ContentArrangement::Dynamic);
-    table
-}
+fn print_network(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let (Some(network), ReportSection::Buckets) = (&report.network, section) else {
+        return;
+    };
+    let bucket_seconds = report.bucket_seconds;
+    if network.totals.is_empty() {
+        out.note(format!(
+            "\nNo network samples available for {timeframe_label}."
+        ));
+    } else {
+        out.table(
+            format!("Network stats ({timeframe_label})"),
+            network_totals_table(bucket_seconds, &network.totals),
+        );
+    }
 
-fn header_cells(labels: &[&str]) -> Vec<Cell> {
-    labels
-        .iter()
-        .map(|label| {
-            Cell::new(*label)
-                .add_attribute(Attribute::Bold)
-                .fg(Color::Cyan)
-        })
-        .collect()
-}
+    if has_nonzero_deltas(&network.wakeups) {
+        out.table(
+            format!("Network wakeups ({timeframe_label})"),
+            counter_delta_table(bucket_seconds, "Wakeups", &network.wakeups),
+        );
+    }
 
-fn label_cell(text: &str) -> Cell {
-    Cell::new(text).add_attribute(Attribute::Bold)
+    if !network.tcp.is_empty() {
+        out.table(
+            format!("TCP connections ({timeframe_label})"),
+            tcp_connections_table(bucket_seconds, &network.tcp),
+        );
+    }
 }
 
-fn value_cell<T: std::fmt::Display>(value: T) -> Cell {
-    Cell::new(value.to_string()).set_alignment(CellAlignment::Right)
+fn print_containers(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let (Some(containers), ReportSection::Buckets) = (&report.containers, section) else {
+        return;
+    };
+    if containers.cpu.is_empty() && containers.memory.is_empty() {
+        out.note(format!(
+            "\nNo container samples available for {timeframe_label}."
+        ));
+    } else {
+        out.table(
+            format!("Container stats ({timeframe_label})"),
+            container_stats_table(report.bucket_seconds, &containers.cpu, &containers.memory),
+        );
+    }
 }
 
-fn status_cell(status: Option<&str>) -> Cell {
-    let status_text = status.unwrap_or("unknown");
-    let lower = status_text.to_ascii_lowercase();
-    let color = if lower.starts_with("discharging") {
-        Color::Yellow
-    } else if lower.starts_with("charging") {
-        Color::Green
-    } else if lower.starts_with("full") {
-        Color::Blue
-    } else {
-        Color::White
+fn print_system(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let (Some(system), ReportSection::Buckets) = (&report.system, section) else {
+        return;
     };
-    Cell::new(status_text).fg(color)
+    if system.health.is_empty() {
+        out.note(format!(
+            "\nNo systemd health samples available for {timeframe_label}."
+        ));
+    } else {
+        out.table(
+            format!("System health ({timeframe_label})"),
+            system_health_table(report.bucket_seconds, &system.health),
+        );
+    }
 }
 
-fn format_percent(value: Option<f64>) -> String {
-    value
-        .map(|v| format!("{v:.1}%"))
-        .unwrap_or_else(|| "--".to_string())
+fn print_temperature(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let Some(temperature) = &report.temperature else {
+        return;
+    };
+    if section == ReportSection::Summary && !temperature.sensors.is_empty() {
+        out.table(
+            format!("Temperature sensors ({timeframe_label})"),
+            temperature_sensors_table(&temperature.sensors),
+        );
+    }
+    if section != ReportSection::Buckets {
+        return;
+    }
+    if temperature.buckets.is_empty() {
+        out.note(format!(
+            "\nNo temperature samples available for {timeframe_label}."
+        ));
+    } else {
+        out.table(
+            format!("Temperature stats ({timeframe_label})"),
+            temperature_stats_table(
+                report.bucket_seconds,
+                &temperature.buckets,
+                report
+                    .previous
+                    .as_ref()
+                    .map(|previous| (previous.label, &previous.temperature)),
+            ),
+        );
+    }
+    for (title, buckets, unit) in [
+        ("Voltage rails", &temperature.voltage, "V"),
+        ("Current sensors", &temperature.current, "A"),
+    ] {
+        if buckets.is_empty() {
+            continue;
+        }
+        let units = buckets
+            .keys()
+            .map(|source| (source.clone(), unit.to_string()))
+            .collect();
+        out.table(
+            format!("{title} ({timeframe_label})"),
+            source_stats_table("Sensor", report.bucket_seconds, buckets, &units),
+        );
+    }
 }
 
-#[derive(Default, Clone)]
-struct NumberStats {
-    total: f64,
-    count: usize,
-    min: f64,
-    max: f64,
+fn print_derived(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let (Some(derived), ReportSection::Buckets) = (&report.derived, section) else {
+        return;
+    };
+    if !derived.buckets.is_empty() {
+        out.table(
+            format!("Derived metrics ({timeframe_label})"),
+            source_stats_table(
+                "Metric",
+                report.bucket_seconds,
+                &derived.buckets,
+                &derived.units,
+            ),
+        );
+    }
 }
 
-impl NumberStats {
-    fn record(&mut self, value: f64) {
-        if self.count == 0 {
-            self.min = value;
-            self.max = value;
-        } else {
-            self.min = self.min.min(value);
-            self.max = self.max.max(value);
+fn print_collector(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    let Some(collector) = &report.collector else {
+        return;
+    };
+    match section {
+        ReportSection::Summary if collector.costs.is_empty() => {
+            out.note(format!(
+                "\nNo collector stats available for {timeframe_label}."
+            ));
         }
-        self.total += value;
-        self.count += 1;
-    }
-
-    fn record_opt(&mut self, value: Option<f64>) {
-        if let Some(v) = value {
-            self.record(v);
+        ReportSection::Summary => {
+            out.table(
+                format!("Collector cost ({timeframe_label})"),
+                collector_cost_table(&collector.costs),
+            );
+        }
+        ReportSection::Buckets if !collector.buckets.is_empty() => {
+            out.table(
+                format!("Collection time ({timeframe_label})"),
+                collector_stats_table(report.bucket_seconds, &collector.buckets),
+            );
         }
+        _ => {}
     }
+}
 
-    fn average(&self) -> Option<f64> {
-        (self.count > 0).then_some(self.total / self.count as f64)
-    }
+/// Summaries computed for every kind regardless of the selected presets.
+fn print_general(
+    report: &ReportResult,
+    section: ReportSection,
+    timeframe_label: &str,
+    out: &mut ReportOutput,
+) {
+    match section {
+        ReportSection::Summary => {
+            if !report.distributions.is_empty() {
+                out.table(
+                    format!("Distributions ({timeframe_label})"),
+                    distribution_table(&report.distributions),
+                );
+            }
 
-    fn min(&self) -> Option<f64> {
-        (self.count > 0).then_some(self.min)
-    }
+            for histogram in &report.histograms {
+                out.table(
+                    format!(
+                        "Histogram, {} {} ({timeframe_label})",
+                        histogram.kind.as_str(),
+                        histogram.source
+                    ),
+                    value_histogram_table(histogram),
+                );
+            }
 
-    fn max(&self) -> Option<f64> {
-        (self.count > 0).then_some(self.max)
-    }
-}
+            if !report.states.is_empty() {
+                out.table(
+                    format!("States ({timeframe_label})"),
+                    state_table(&report.states),
+                );
+            }
 
-#[derive(Default, Clone)]
-struct UsageStats {
-    used: NumberStats,
-    percent: NumberStats,
-}
+            if !report.categories.is_empty() {
+                out.table(
+                    format!("Time in state ({timeframe_label})"),
+                    category_table(&report.categories),
+                );
+            }
 
-impl UsageStats {
-    fn record(&mut self, used: Option<f64>, total: Option<f64>) {
-        if let Some(used_bytes) = used {
-            self.used.record(used_bytes);
-            if let Some(total_bytes) = total {
-                if total_bytes > 0.0 {
-                    self.percent.record((used_bytes / total_bytes) * 100.0);
-                }
+            if !report.boots.is_empty() {
+                out.table(
+                    format!("Boots ({timeframe_label})"),
+                    boot_table(&report.boots),
+                );
+            }
+        }
+        ReportSection::Events => {
+            if !report.undersampled.is_empty() {
+                out.table(
+                    format!(
+                        "Low confidence: sparsely sampled series ({})",
+                        timeframe_label
+                    ),
+                    sampling_table(&report.undersampled),
+                );
             }
         }
+        ReportSection::Buckets | ReportSection::Breakdowns | ReportSection::Machine => {}
+    }
+}
+
+fn format_power(value: Option<f64>) -> String {
+    match value {
+        Some(v) => format!("{v:.2}W"),
+        None => "--".to_string(),
     }
 }
 
-#[derive(Default, Clone)]
-struct TransferStats {
-    rx_total: f64,
-    tx_total: f64,
+/// Border style from `[viewer] theme`, set once the config is loaded.
+static TABLE_THEME: OnceLock<TableTheme> = OnceLock::new();
+
+/// Resolved `--color`, set once the arguments are parsed.
+static COLOR: OnceLock<ColorChoice> = OnceLock::new();
+
+/// Width of the terminal on stdout, if it is one.
+fn terminal_columns() -> Option<u16> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes into `size`, which outlives the call.
+    let ok = unsafe {
+        libc::ioctl(
+            libc::STDOUT_FILENO,
+            libc::TIOCGWINSZ,
+            &mut size as *mut libc::winsize,
+        )
+    } == 0;
+    (ok && size.ws_col > 0).then_some(size.ws_col)
 }
 
-impl TransferStats {
-    fn record(&mut self, rx: f64, tx: f64) {
-        self.rx_total += rx;
-        self.tx_total += tx;
-    }
+/// Headers of the columns that, following the first one, tell the rows of a table apart
+/// (per-window rows of each source, say), so `--columns` never hides them.
+const LABEL_HEADERS: [&str; 8] = [
+    "Window",
+    "Source",
+    "State",
+    "Interface",
+    "Host",
+    "Metric",
+    "Device",
+    "Sensor",
+];
+
+/// A report table that prints its cells tab-separated, one row per line, in the plain theme.
+struct ThemedTable {
+    table: Table,
+    plain: bool,
 }
 
-fn average_for_kind(metrics: &[MetricSample], kind: MetricKind) -> NumberStats {
-    let mut stats = NumberStats::default();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        stats.record_opt(sample.value);
+impl Deref for ThemedTable {
+    type Target = Table;
+
+    fn deref(&self) -> &Table {
+        &self.table
     }
-    stats
 }
 
-fn filter_metrics_by_source(
-    metrics: &[MetricSample],
-    sensor_filters: &[String],
-) -> Vec<MetricSample> {
-    if sensor_filters.is_empty() {
-        return metrics.to_vec();
+impl DerefMut for ThemedTable {
+    fn deref_mut(&mut self) -> &mut Table {
+        &mut self.table
     }
-    metrics
-        .iter()
-        .filter(|m| sensor_filters.iter().any(|f| f == &m.source))
-        .cloned()
-        .collect()
 }
 
-type SourceBuckets = BTreeMap<String, BTreeMap<DateTime<Local>, NumberStats>>;
+impl ThemedTable {
+    /// Whether each column is printed, in order.
+    fn shown_columns(&self) -> Vec<bool> {
+        let count = self
+            .table
+            .header()
+            .into_iter()
+            .chain(self.table.row_iter())
+            .map(|row| row.cell_count())
+            .max()
+            .unwrap_or(0);
+        (0..count)
+            .map(|index| {
+                self.table
+                    .column(index)
+                    .is_none_or(|column| !column.is_hidden())
+            })
+            .collect()
+    }
 
-fn bucket_stats_for_kind_by_source(
-    metrics: &[MetricSample],
-    kind: MetricKind,
-    bucket_seconds: i64,
-) -> SourceBuckets {
-    let mut buckets: SourceBuckets = BTreeMap::new();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        if let Some(value) = sample.value {
-            let bucket = bucket_start(sample.ts, bucket_seconds);
-            buckets
-                .entry(sample.source.clone())
-                .or_default()
-                .entry(bucket)
-                .or_default()
-                .record(value);
+    fn hide_column(&mut self, index: usize) {
+        if let Some(column) = self.table.column_mut(index) {
+            column.set_constraint(ColumnConstraint::Hidden);
         }
     }
-    buckets
-}
 
-fn data_span_seconds(metrics: &[MetricSample]) -> Option<f64> {
-    let mut min_ts = f64::INFINITY;
-    let mut max_ts = f64::NEG_INFINITY;
-
-    for ts in metrics.iter().map(|s| s.ts) {
-        min_ts = min_ts.min(ts);
-        max_ts = max_ts.max(ts);
+    /// Hide the columns whose header does not name one of `wanted`, e.g. `avg` keeps
+    /// "Avg %" and "Avg discharge W". The first column labels the rows and always stays, as
+    /// do the [`LABEL_HEADERS`] columns right after it; a table without any wanted column is
+    /// left whole.
+    fn select_columns(&mut self, wanted: &[String]) {
+        let Some(header) = self.table.header() else {
+            return;
+        };
+        if wanted.is_empty() {
+            return;
+        }
+        let labels = 1 + header
+            .cell_iter()
+            .skip(1)
+            .take_while(|cell| LABEL_HEADERS.contains(&cell.content().as_str()))
+            .count();
+        let wanted: Vec<String> = wanted.iter().map(|name| key_part(name)).collect();
+        let keep: Vec<bool> = header
+            .cell_iter()
+            .map(|cell| {
+                let slug = key_part(&cell.content());
+                wanted
+                    .iter()
+                    .any(|name| slug == *name || slug.split('_').any(|word| word == name.as_str()))
+            })
+            .collect();
+        if !keep.iter().skip(labels).any(|keep| *keep) {
+            return;
+        }
+        for (index, keep) in keep.into_iter().enumerate().skip(labels) {
+            if !keep {
+                self.hide_column(index);
+            }
+        }
     }
 
-    if max_ts.is_finite() && min_ts.is_finite() && max_ts >= min_ts {
-        Some(max_ts - min_ts)
-    } else {
-        None
+    /// Hide columns from the right until the table fits in `width` characters, keeping at
+    /// least two. Returns how many were hidden.
+    fn fit_width(&mut self, width: u16) -> usize {
+        if self.plain {
+            return 0;
+        }
+        let widths = self.table.column_max_content_widths();
+        let mut shown = self.shown_columns();
+        let mut dropped = 0;
+        loop {
+            let visible: Vec<usize> = (0..shown.len()).filter(|index| shown[*index]).collect();
+            // One space of padding on each side of a cell, and a border between cells
+            let needed: usize = visible
+                .iter()
+                .map(|index| widths.get(*index).copied().unwrap_or(0) as usize + 3)
+                .sum::<usize>()
+                + 1;
+            if needed <= width as usize || visible.len() <= 2 {
+                return dropped;
+            }
+            let last = *visible.last().unwrap();
+            self.hide_column(last);
+            shown[last] = false;
+            dropped += 1;
+        }
     }
 }
 
-fn bucket_stats_for_kind(
-    metrics: &[MetricSample],
-    kind: MetricKind,
-    bucket_seconds: i64,
-) -> BTreeMap<DateTime<Local>, NumberStats> {
-    let mut buckets: BTreeMap<DateTime<Local>, NumberStats> = BTreeMap::new();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        if let Some(value) = sample.value {
-            let bucket = bucket_start(sample.ts, bucket_seconds);
-            buckets.entry(bucket).or_default().record(value);
+impl fmt::Display for ThemedTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.plain {
+            return self.table.fmt(f);
         }
+        let shown = self.shown_columns();
+        let rows = self.table.header().into_iter().chain(self.table.row_iter());
+        let lines: Vec<String> = rows
+            .map(|row| {
+                let cells: Vec<String> = row
+                    .cell_iter()
+                    .zip(&shown)
+                    .filter(|(_, shown)| **shown)
+                    .map(|(cell, _)| cell.content())
+                    .collect();
+                cells.join("\t")
+            })
+            .collect();
+        f.write_str(&lines.join("\n"))
     }
-    buckets
 }
 
-#[cfg(test)]
-fn usage_stats_for_kind(metrics: &[MetricSample], kind: MetricKind) -> UsageStats {
-    let mut stats = UsageStats::default();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        let total = number_from_details(sample, "total_bytes");
-        stats.record(sample.value, total);
-    }
-    stats
+/// How `report` prints its blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Tables in the `[viewer] theme`
+    Text,
+    /// One object with every table's columns and rows
+    Json,
+    /// Long format: one `table,row,column,value` line per cell
+    Csv,
+    /// A heading and pipe table per block
+    Markdown,
+    /// One `block.row.column=value` line per cell, for shell scripts and status bars
+    KeyValue,
 }
 
-fn bucket_usage_stats(
-    metrics: &[MetricSample],
-    kind: MetricKind,
-    bucket_seconds: i64,
-) -> BTreeMap<DateTime<Local>, UsageStats> {
-    let mut buckets: BTreeMap<DateTime<Local>, UsageStats> = BTreeMap::new();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        let bucket = bucket_start(sample.ts, bucket_seconds);
-        let total = number_from_details(sample, "total_bytes");
-        buckets
-            .entry(bucket)
-            .or_default()
-            .record(sample.value, total);
+/// Whether report tables drop columns to fit the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TableLayout {
+    /// Narrow on a terminal too thin for a table, wide otherwise
+    #[default]
+    Auto,
+    /// Every column, wrapped to the terminal when needed
+    Wide,
+    /// Drop columns from the right until the table fits the terminal (80 columns when
+    /// not printing to one)
+    Narrow,
+}
+
+impl TableLayout {
+    /// Width tables are narrowed to, if any.
+    fn fit_width(self) -> Option<u16> {
+        match self {
+            TableLayout::Auto => terminal_columns(),
+            TableLayout::Wide => None,
+            TableLayout::Narrow => terminal_columns().or(Some(NARROW_TABLE_WIDTH)),
+        }
     }
-    buckets
 }
 
-#[cfg(test)]
-struct NetworkRateSample {
-    rx_rate: Option<f64>,
-    tx_rate: Option<f64>,
+/// Width of a narrow table when stdout is not a terminal.
+const NARROW_TABLE_WIDTH: u16 = 80;
+
+/// How `query` prints its rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum QueryFormat {
+    /// A table in the `[viewer] theme`
+    Table,
+    /// A header line, then one line per row
+    Csv,
 }
 
-#[cfg(test)]
-fn rate_from_counters(previous: Option<f64>, current: Option<f64>, dt: f64) -> Option<f64> {
-    match (previous, current) {
-        (Some(prev), Some(next)) if next >= prev && dt > 0.0 => Some((next - prev) / dt),
-        _ => None,
-    }
+/// One report table with its cells as printed.
+struct ReportBlock {
+    title: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
 }
 
-#[cfg(test)]
-fn compute_network_rates(metrics: &[MetricSample]) -> Vec<NetworkRateSample> {
-    let mut by_iface: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
-    for sample in metrics
-        .iter()
-        .filter(|s| s.kind == MetricKind::NetworkBytes)
-    {
-        by_iface.entry(&sample.source).or_default().push(sample);
-    }
-
-    let mut rates = Vec::new();
-    for (_iface, mut samples) in by_iface {
-        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
-        for window in samples.windows(2) {
-            let prev = window[0];
-            let next = window[1];
-            let dt = next.ts - prev.ts;
-            if dt <= 0.0 {
-                continue;
-            }
-            let rx_rate = rate_from_counters(
-                number_from_details(prev, "rx_bytes"),
-                number_from_details(next, "rx_bytes"),
-                dt,
-            );
-            let tx_rate = rate_from_counters(
-                number_from_details(prev, "tx_bytes"),
-                number_from_details(next, "tx_bytes"),
-                dt,
-            );
-            if rx_rate.is_none() && tx_rate.is_none() {
-                continue;
-            }
-            rates.push(NetworkRateSample { rx_rate, tx_rate });
-        }
-    }
-    rates
+/// Where report blocks go: printed as they come in the text format, else collected and
+/// written as one document by [`ReportOutput::finish`].
+struct ReportOutput {
+    format: ReportFormat,
+    /// Drop the notes
+    quiet: bool,
+    /// `--columns`: statistics columns to keep (all when empty)
+    columns: Vec<String>,
+    layout: TableLayout,
+    blocks: Vec<ReportBlock>,
+    notes: Vec<String>,
 }
 
-fn compute_counter_delta(prev: Option<f64>, next: Option<f64>) -> f64 {
-    match (prev, next) {
-        (Some(prev_val), Some(next_val)) if next_val >= prev_val => next_val - prev_val,
-        _ => 0.0,
+impl ReportOutput {
+    fn new(format: ReportFormat) -> Self {
+        Self {
+            format,
+            quiet: false,
+            columns: Vec::new(),
+            layout: TableLayout::Auto,
+            blocks: Vec::new(),
+            notes: Vec::new(),
+        }
     }
-}
 
-fn bucket_network_totals(
-    metrics: &[MetricSample],
-    bucket_seconds: i64,
-) -> BTreeMap<DateTime<Local>, TransferStats> {
-    let mut by_iface: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
-    for sample in metrics
-        .iter()
-        .filter(|s| s.kind == MetricKind::NetworkBytes)
-    {
-        by_iface.entry(&sample.source).or_default().push(sample);
-    }
-
-    let mut buckets: BTreeMap<DateTime<Local>, TransferStats> = BTreeMap::new();
-    for (_iface, mut samples) in by_iface {
-        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
-        for window in samples.windows(2) {
-            let prev = window[0];
-            let next = window[1];
-            let dt = next.ts - prev.ts;
-            if dt <= 0.0 {
-                continue;
-            }
+    fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
 
-            let rx_delta = compute_counter_delta(
-                number_from_details(prev, "rx_bytes"),
-                number_from_details(next, "rx_bytes"),
-            );
-            let tx_delta = compute_counter_delta(
-                number_from_details(prev, "tx_bytes"),
-                number_from_details(next, "tx_bytes"),
-            );
+    fn columns(mut self, columns: Vec<String>, layout: TableLayout) -> Self {
+        self.columns = columns;
+        self.layout = layout;
+        self
+    }
 
-            if rx_delta > 0.0 || tx_delta > 0.0 {
-                let bucket = bucket_start(next.ts, bucket_seconds);
-                buckets
-                    .entry(bucket)
-                    .or_default()
-                    .record(rx_delta, tx_delta);
+    fn table(&mut self, title: String, mut table: ThemedTable) {
+        table.select_columns(&self.columns);
+        if self.format == ReportFormat::Text {
+            let dropped = self
+                .layout
+                .fit_width()
+                .map_or(0, |width| table.fit_width(width));
+            println!("\n{title}\n{table}");
+            if dropped > 0 && !self.quiet {
+                println!(
+                    "({dropped} more column{} hidden to fit; see --layout wide or --columns)",
+                    if dropped == 1 { "" } else { "s" }
+                );
             }
+            return;
         }
+        let shown = table.shown_columns();
+        let cells = |row: &comfy_table::Row| -> Vec<String> {
+            row.cell_iter()
+                .zip(&shown)
+                .filter(|(_, shown)| **shown)
+                .map(|(cell, _)| cell.content())
+                .collect()
+        };
+        self.blocks.push(ReportBlock {
+            title,
+            columns: table.header().map(cells).unwrap_or_default(),
+            rows: table.row_iter().map(cells).collect(),
+        });
     }
-    buckets
-}
 
-fn format_freq(value: Option<f64>) -> String {
-    value
-        .map(|v| format!("{v:.0}MHz"))
+    /// A message outside any table, printed as is in the text format.
+    fn note(&mut self, text: String) {
+        match self.format {
+            _ if self.quiet => {}
+            ReportFormat::Text => println!("{text}"),
+            _ => self.notes.push(text.trim().to_string()),
+        }
+    }
+
+    /// Print the collected blocks in the structured formats.
+    fn finish(self, report: &ReportResult) {
+        match self.format {
+            ReportFormat::Text => {}
+            ReportFormat::Json => {
+                let tables: Vec<serde_json::Value> = self
+                    .blocks
+                    .iter()
+                    .map(|block| {
+                        serde_json::json!({
+                            "title": block.title,
+                            "columns": block.columns,
+                            "rows": block.rows,
+                        })
+                    })
+                    .collect();
+                let document = serde_json::json!({
+                    "timeframe": report.timeframe.label,
+                    "bucket_seconds": report.bucket_seconds,
+                    "record_count": report.record_count,
+                    "tables": tables,
+                    "notes": self.notes,
+                });
+                println!("{document:#}");
+            }
+            ReportFormat::Csv => {
+                println!("table,row,column,value");
+                for block in &self.blocks {
+                    for (index, row) in block.rows.iter().enumerate() {
+                        for (column, value) in block.columns.iter().zip(row) {
+                            println!(
+                                "{},{index},{},{}",
+                                csv_field(&block.title),
+                                csv_field(column),
+                                csv_field(value)
+                            );
+                        }
+                    }
+                }
+                for (index, note) in self.notes.iter().enumerate() {
+                    println!("Notes,{index},note,{}", csv_field(note));
+                }
+            }
+            ReportFormat::Markdown => {
+                let row = |cells: &[String]| -> String {
+                    let cells: Vec<String> = cells
+                        .iter()
+                        .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+                        .collect();
+                    format!("| {} |", cells.join(" | "))
+                };
+                for block in &self.blocks {
+                    println!("### {}\n", block.title);
+                    println!("{}", row(&block.columns));
+                    println!("|{}", "---|".repeat(block.columns.len()));
+                    for cells in &block.rows {
+                        println!("{}", row(cells));
+                    }
+                    println!();
+                }
+                for note in &self.notes {
+                    println!("{note}\n");
+                }
+            }
+            ReportFormat::KeyValue => {
+                println!("timeframe={}", report.timeframe.label);
+                for block in &self.blocks {
+                    for line in block.key_value_lines() {
+                        println!("{line}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ReportBlock {
+    /// `block.row=value` for two-column tables such as the battery summary, else
+    /// `block.row.column=value`, each part lowercased with `_` for anything else than
+    /// letters and digits. The timeframe in parentheses is left out of the block name.
+    fn key_value_lines(&self) -> Vec<String> {
+        let title = match self.title.rsplit_once(" (") {
+            Some((title, _)) if self.title.ends_with(')') => title,
+            _ => &self.title,
+        };
+        let block = key_part(title);
+        let mut lines = Vec::new();
+        for (index, row) in self.rows.iter().enumerate() {
+            let label = match row.first().map(|label| key_part(label)) {
+                Some(label) if !label.is_empty() => label,
+                _ => index.to_string(),
+            };
+            for (column, value) in self.columns.iter().zip(row).skip(1) {
+                let value = value.trim();
+                if value.is_empty() {
+                    continue;
+                }
+                if self.columns.len() == 2 {
+                    lines.push(format!("{block}.{label}={value}"));
+                } else {
+                    lines.push(format!("{block}.{label}.{}={value}", key_part(column)));
+                }
+            }
+        }
+        lines
+    }
+}
+
+fn key_part(raw: &str) -> String {
+    raw.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn themed_table() -> ThemedTable {
+    themed_table_with(TABLE_THEME.get().copied().unwrap_or_default())
+}
+
+fn themed_table_with(theme: TableTheme) -> ThemedTable {
+    let mut table = Table::new();
+    match theme {
+        TableTheme::Rounded => {
+            table
+                .load_preset(UTF8_FULL_CONDENSED)
+                .apply_modifier(UTF8_ROUND_CORNERS);
+        }
+        TableTheme::Ascii => {
+            table.load_preset(ASCII_FULL_CONDENSED);
+        }
+        TableTheme::Borderless | TableTheme::Plain => {
+            table.load_preset(NOTHING);
+        }
+    }
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    // comfy-table colors only on a terminal by itself; `auto` leaves that as is
+    match COLOR.get().copied().unwrap_or_default() {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => {
+            table.enforce_styling();
+        }
+        ColorChoice::Never => {
+            // Without a tty comfy-table no longer looks up the width to wrap to
+            if let Some(columns) = terminal_columns() {
+                table.set_width(columns);
+            }
+            table.force_no_tty();
+        }
+    }
+    ThemedTable {
+        table,
+        plain: theme == TableTheme::Plain,
+    }
+}
+
+fn header_cells(labels: &[&str]) -> Vec<Cell> {
+    labels
+        .iter()
+        .map(|label| {
+            Cell::new(*label)
+                .add_attribute(Attribute::Bold)
+                .fg(Color::Cyan)
+        })
+        .collect()
+}
+
+fn label_cell(text: &str) -> Cell {
+    Cell::new(text).add_attribute(Attribute::Bold)
+}
+
+fn value_cell<T: std::fmt::Display>(value: T) -> Cell {
+    Cell::new(value.to_string()).set_alignment(CellAlignment::Right)
+}
+
+fn status_cell(status: Option<&str>) -> Cell {
+    let status_text = status.unwrap_or("unknown");
+    let lower = status_text.to_ascii_lowercase();
+    let color = if lower.starts_with("discharging") {
+        Color::Yellow
+    } else if lower.starts_with("charging") {
+        Color::Green
+    } else if lower.starts_with("full") {
+        Color::Blue
+    } else {
+        Color::White
+    };
+    Cell::new(status_text).fg(color)
+}
+
+fn format_percent(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{v:.1}%"))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+fn data_cap_table(usage: &MonthlyUsage, caps: &DataCapConfig) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Month",
+        "Interface",
+        "Down",
+        "Up",
+        "Total",
+        "Cap",
+        "Used of cap",
+    ]));
+
+    for ((month, iface), stats) in usage {
+        let cap = caps.cap_for(iface);
+        let used_pct = cap.and_then(|c| cap_percent(stats, c));
+        let color = match used_pct {
+            Some(pct) if pct >= 100.0 => Color::Red,
+            Some(pct) if pct >= caps.warn_percent => Color::Yellow,
+            _ => Color::White,
+        };
+        report.add_row(vec![
+            Cell::new(month)
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            label_cell(iface),
+            value_cell(format_bytes(stats.rx_total)),
+            value_cell(format_bytes(stats.tx_total)),
+            value_cell(format_bytes(stats.rx_total + stats.tx_total)),
+            value_cell(format_opt_bytes(cap.map(|c| c as f64))),
+            value_cell(format_percent(used_pct)).fg(color),
+        ]);
+    }
+    report
+}
+
+fn format_freq(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{v:.0}MHz"))
         .unwrap_or_else(|| "--".to_string())
 }
 
@@ -754,7 +2473,7 @@ fn battery_summary_table(
     avg_discharge_w: Option<f64>,
     avg_charge_w: Option<f64>,
     est_runtime_hours: Option<f64>,
-) -> Table {
+) -> ThemedTable {
     let mut table = themed_table();
     table.set_header(header_cells(&["Metric", "Value"]));
     table.add_row(vec![
@@ -776,68 +2495,14 @@ fn battery_summary_table(
     table
 }
 
-fn battery_rate_buckets(
-    battery_metrics: &[MetricSample],
-    bucket_seconds: i64,
-) -> (
-    BTreeMap<DateTime<Local>, NumberStats>,
-    BTreeMap<DateTime<Local>, NumberStats>,
-) {
-    const MAX_GAP_HOURS: f64 = 5.0 / 60.0;
-
-    let mut discharge = BTreeMap::new();
-    let mut charge = BTreeMap::new();
-
-    let energy_now_samples: Vec<_> = battery_metrics
-        .iter()
-        .filter(|m| m.kind == MetricKind::BatteryEnergyNow && m.value.is_some())
-        .collect();
-
-    let mut iter = energy_now_samples.iter();
-    let mut previous = match iter.next() {
-        Some(sample) => sample,
-        None => return (discharge, charge),
-    };
-
-    for current in iter {
-        if current.ts < previous.ts {
-            previous = current;
-            continue;
-        }
-        let (Some(prev_now), Some(curr_now)) = (previous.value, current.value) else {
-            previous = current;
-            continue;
-        };
-        let dt_hours = (current.ts - previous.ts) / 3600.0;
-        if dt_hours <= 0.0 || dt_hours > MAX_GAP_HOURS {
-            previous = current;
-            continue;
-        }
-        let bucket = bucket_start(current.ts, bucket_seconds);
-        if curr_now > prev_now && is_charging(previous) && is_charging(current) {
-            charge
-                .entry(bucket)
-                .or_default()
-                .record((curr_now - prev_now) / dt_hours);
-        } else if curr_now < prev_now && is_discharging(previous) && is_discharging(current) {
-            discharge
-                .entry(bucket)
-                .or_default()
-                .record((prev_now - curr_now) / dt_hours);
-        }
-        previous = current;
-    }
-
-    (discharge, charge)
-}
-
 fn battery_stats_table(
     battery_metrics: &[MetricSample],
     power_draw: &BTreeMap<DateTime<Local>, NumberStats>,
     discharge_rates: &BTreeMap<DateTime<Local>, NumberStats>,
     charge_rates: &BTreeMap<DateTime<Local>, NumberStats>,
     bucket_seconds: i64,
-) -> Table {
+    previous: Option<(&str, &TimeBuckets<f64>)>,
+) -> ThemedTable {
     let mut buckets: BTreeMap<DateTime<Local>, Vec<&MetricSample>> = BTreeMap::new();
     for sample in battery_metrics {
         let bucket_key = bucket_start(sample.ts, bucket_seconds);
@@ -845,16 +2510,18 @@ fn battery_stats_table(
     }
 
     let mut report = themed_table();
-    report.set_header(header_cells(&[
+    let delta_header = previous.map(|(label, _)| format!("vs {label}"));
+    let mut header = vec![
         "Window",
         "Records",
         "Min %",
         "Avg %",
         "Max %",
         "Avg discharge W",
-        "Avg charge W",
-        "Latest status",
-    ]));
+    ];
+    header.extend(delta_header.as_deref());
+    header.extend(["Avg charge W", "Latest status"]);
+    report.set_header(header_cells(&header));
 
     for (bucket_start_dt, bucket_samples) in buckets {
         let pct_values: Vec<f64> = bucket_samples
@@ -865,8 +2532,7 @@ fn battery_stats_table(
         let (min_pct, avg_pct, max_pct) = pct_stats(&pct_values);
         let latest_status = bucket_samples
             .last()
-            .and_then(|s| s.details.get("status"))
-            .and_then(|v| v.as_str())
+            .and_then(|s| s.status.as_deref())
             .unwrap_or("unknown");
         let rates = average_rates(bucket_samples.iter().copied());
         let discharge_power = discharge_rates
@@ -882,147 +2548,1389 @@ fn battery_stats_table(
             .get(&bucket_start_dt)
             .and_then(NumberStats::average)
             .or(rates.charge_w);
+        let mut row = vec![
+            Cell::new(format_bucket(bucket_start_dt, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(bucket_samples.len()),
+            value_cell(min_pct),
+            value_cell(avg_pct),
+            value_cell(max_pct),
+            value_cell(format_power(discharge_power)),
+        ];
+        if let Some((_, before)) = previous {
+            row.push(delta_cell(
+                discharge_power,
+                before.get(&bucket_start_dt).copied(),
+                "W",
+                2,
+            ));
+        }
+        row.extend([
+            value_cell(format_power(charge_power)),
+            status_cell(Some(latest_status)),
+        ]);
+        report.add_row(row);
+    }
+    report
+}
+
+fn gpu_limit_table(limits: &[GpuLimitSummary]) -> ThemedTable {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "GPU",
+        "At power cap",
+        "Near thermal limit",
+        "Avg fan",
+        "Max temp",
+    ]));
+    for limit in limits {
+        table.add_row(vec![
+            label_cell(&limit.card),
+            value_cell(format_percent(Some(limit.power_limited_percent()))).fg(Color::Yellow),
+            value_cell(format_percent(Some(limit.thermal_limited_percent()))).fg(Color::Red),
+            value_cell(format_percent(limit.avg_fan_percent)),
+            value_cell(
+                limit
+                    .max_temp_c
+                    .map(|t| format!("{t:.1}C"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+        ]);
+    }
+    table
+}
+
+/// Overview row of each panel, in the default order; label and value of a column.
+type OverviewRow = (Panel, &'static str, fn(&OverviewColumn) -> String);
+
+const OVERVIEW_ROWS: [OverviewRow; 4] = [
+    (Panel::Power, "Avg power", |c| format_power(c.avg_power_w)),
+    (Panel::Cpu, "Avg CPU", |c| format_percent(c.avg_cpu_percent)),
+    (Panel::Network, "Transferred", |c| {
+        format_opt_bytes(c.transferred_bytes)
+    }),
+    (Panel::Battery, "Min battery health", |c| {
+        format_percent(c.min_battery_health)
+    }),
+];
+
+/// The rows of `panels` in that order (all of them when empty), one column per window.
+fn overview_table(columns: &[OverviewColumn], panels: &[Panel]) -> ThemedTable {
+    let mut table = themed_table();
+    let mut header = vec![""];
+    header.extend(columns.iter().map(|column| column.label));
+    table.set_header(header_cells(&header));
+    let rows: Vec<&OverviewRow> = if panels.is_empty() {
+        OVERVIEW_ROWS.iter().collect()
+    } else {
+        panels
+            .iter()
+            .filter_map(|panel| OVERVIEW_ROWS.iter().find(|(row, ..)| row == panel))
+            .collect()
+    };
+    for (_, label, value) in rows {
+        let mut row = vec![label_cell(label)];
+        row.extend(columns.iter().map(|column| value_cell(value(column))));
+        table.add_row(row);
+    }
+    table
+}
+
+fn fleet_table(rows: &[FleetRow]) -> ThemedTable {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Host",
+        "Last seen",
+        "Battery health",
+        "Fullest disk",
+        "Max temp 7d",
+        "Avg power 7d",
+    ]));
+    for row in rows {
+        table.add_row(vec![
+            label_cell(&row.host),
+            Cell::new(
+                row.last_seen
+                    .map(|ts| local_datetime(ts).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            value_cell(format_percent(row.battery_health)),
+            value_cell(
+                row.fullest_mount
+                    .as_ref()
+                    .map(|(mount, percent)| format!("{mount} {percent:.0}%"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            value_cell(
+                row.max_temp_c
+                    .map(|temp| format!("{temp:.1}C"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            value_cell(format_power(row.avg_power_w)),
+        ]);
+    }
+    table
+}
+
+fn battery_pack_table(packs: &[BatteryPack]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Battery",
+        "Latest %",
+        "Health %",
+        "Full Wh",
+        "Design Wh",
+        "Avg discharge W",
+        "Avg charge W",
+    ]));
+    let energy = |value: Option<f64>| {
+        value
+            .map(|v| format!("{v:.1}"))
+            .unwrap_or_else(|| "--".to_string())
+    };
+    for pack in packs {
+        report.add_row(vec![
+            label_cell(&pack.source),
+            value_cell(format_percent(pack.latest_percent)),
+            value_cell(format_percent(pack.health)),
+            value_cell(energy(pack.energy_full_wh)),
+            value_cell(energy(pack.energy_full_design_wh)),
+            value_cell(format_power(pack.avg_discharge_w)),
+            value_cell(format_power(pack.avg_charge_w)),
+        ]);
+    }
+    report
+}
+
+fn peripheral_battery_table(peripherals: &[PeripheralSummary]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Device",
+        "Latest %",
+        "Min %",
+        "Status",
+        "Last seen",
+    ]));
+    for peripheral in peripherals {
+        report.add_row(vec![
+            label_cell(&peripheral.device),
+            value_cell(format_percent(peripheral.latest_percent)),
+            value_cell(format_percent(peripheral.min_percent)),
+            status_cell(peripheral.status.as_deref()),
+            value_cell(
+                local_datetime(peripheral.last_seen)
+                    .format("%m-%d %H:%M")
+                    .to_string(),
+            ),
+        ]);
+    }
+    report
+}
+
+fn throttle_summary_line(summary: &ThrottleSummary, timeframe_label: &str) -> String {
+    format!(
+        "Likely thermal throttling: {} of {} observed in {} (>= {:.1}C while at or below {})",
+        format_runtime(Some(summary.throttled_seconds / 3600.0)),
+        format_runtime(Some(summary.observed_seconds / 3600.0)),
+        timeframe_label,
+        summary.temp_threshold_c,
+        format_freq(Some(summary.freq_threshold_mhz)),
+    )
+}
+
+fn freq_usage_stats_table(
+    bucket_seconds: i64,
+    usage_label: &'static str,
+    freq_label: &'static str,
+    usage: &SourceBuckets,
+    freq: &SourceBuckets,
+    governors: Option<&GovernorBuckets>,
+) -> ThemedTable {
+    let mut report = themed_table();
+    let min_usage = format!("Min {usage_label}");
+    let avg_usage = format!("Avg {usage_label}");
+    let peak_usage = format!("Peak {usage_label}");
+    let min_freq = format!("Min {freq_label}");
+    let avg_freq = format!("Avg {freq_label}");
+    let peak_freq = format!("Peak {freq_label}");
+    let mut header = vec![
+        "Source",
+        "Window",
+        "Samples",
+        &min_usage,
+        &avg_usage,
+        &peak_usage,
+        &min_freq,
+        &avg_freq,
+        &peak_freq,
+    ];
+    if governors.is_some() {
+        header.push("Governor");
+    }
+    report.set_header(header_cells(&header));
+
+    let mut sources: Vec<&String> = usage.keys().chain(freq.keys()).collect();
+    sources.sort();
+    sources.dedup();
+
+    for source in sources {
+        let usage_buckets = usage.get(source);
+        let freq_buckets = freq.get(source);
+        let mut keys: Vec<DateTime<Local>> = usage_buckets
+            .into_iter()
+            .flat_map(|m| m.keys().copied())
+            .chain(freq_buckets.into_iter().flat_map(|m| m.keys().copied()))
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        for key in keys {
+            let usage_stats = usage_buckets
+                .and_then(|map| map.get(&key).cloned())
+                .unwrap_or_default();
+            let freq_stats = freq_buckets
+                .and_then(|map| map.get(&key).cloned())
+                .unwrap_or_default();
+            let samples = usage_stats.count.max(freq_stats.count);
+            let mut row = vec![
+                label_cell(source),
+                Cell::new(format_bucket(key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(samples),
+                value_cell(format_percent(usage_stats.min())),
+                value_cell(format_percent(usage_stats.average())),
+                value_cell(format_percent(usage_stats.max())),
+                value_cell(format_freq(freq_stats.min())),
+                value_cell(format_freq(freq_stats.average())),
+                value_cell(format_freq(freq_stats.max())),
+            ];
+            if let Some(governors) = governors {
+                let governor = governors
+                    .get(source)
+                    .and_then(|buckets| buckets.get(&key))
+                    .and_then(dominant_governor);
+                row.push(label_cell(governor.unwrap_or("-")));
+            }
+            report.add_row(row);
+        }
+    }
+    report
+}
+
+fn cpu_stats_table(
+    bucket_seconds: i64,
+    usage: &SourceBuckets,
+    freq: &SourceBuckets,
+    governors: &GovernorBuckets,
+) -> ThemedTable {
+    let governors = (!governors.is_empty()).then_some(governors);
+    freq_usage_stats_table(bucket_seconds, "usage", "freq", usage, freq, governors)
+}
+
+fn gpu_stats_table(
+    bucket_seconds: i64,
+    usage: &SourceBuckets,
+    freq: &SourceBuckets,
+) -> ThemedTable {
+    freq_usage_stats_table(bucket_seconds, "usage", "freq", usage, freq, None)
+}
+
+fn usage_stats_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<DateTime<Local>, UsageStats>,
+) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Window",
+        "Samples",
+        "Min used",
+        "Avg used",
+        "Min used %",
+        "Avg used %",
+        "Peak used %",
+    ]));
+
+    for (key, stats) in buckets {
+        report.add_row(vec![
+            Cell::new(format_bucket(*key, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(stats.used.count),
+            value_cell(format_opt_bytes(stats.used.min())),
+            value_cell(format_opt_bytes(stats.used.average())),
+            value_cell(format_percent(stats.percent.min())),
+            value_cell(format_percent(stats.percent.average())),
+            value_cell(format_percent(stats.percent.max())),
+        ]);
+    }
+    report
+}
+
+fn memory_stats_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<DateTime<Local>, UsageStats>,
+) -> ThemedTable {
+    usage_stats_table(bucket_seconds, buckets)
+}
+
+fn disk_stats_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<DateTime<Local>, UsageStats>,
+) -> ThemedTable {
+    usage_stats_table(bucket_seconds, buckets)
+}
+
+/// One row per mountpoint; those predicted to fill soon have their forecast in red.
+fn disk_mounts_table(mounts: &[DiskMount]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Mountpoint",
+        "Used",
+        "Total",
+        "Used %",
+        "Growth",
+        "Full in",
+    ]));
+    for mount in mounts {
+        let growth =
+            mount
+                .growth_bytes_per_day
+                .map_or("not enough history".to_string(), |growth| {
+                    let sign = if growth >= 0.0 { "+" } else { "-" };
+                    format!("{sign}{}/day", format_bytes(growth.abs()))
+                });
+        let full_in = value_cell(
+            mount
+                .days_until_full
+                .map_or("--".to_string(), |days| format!("~{days:.0} days")),
+        );
+        report.add_row(vec![
+            label_cell(&mount.source),
+            value_cell(format_bytes(mount.used_bytes)),
+            value_cell(mount.total_bytes.map_or("--".to_string(), format_bytes)),
+            value_cell(format_percent(mount.used_percent())),
+            value_cell(growth),
+            if mount.filling {
+                full_in.fg(Color::Red).add_attribute(Attribute::Bold)
+            } else {
+                full_in
+            },
+        ]);
+    }
+    report
+}
+
+fn disk_forecast_line(forecast: &DiskForecast) -> String {
+    let growth = forecast.growth_bytes_per_day;
+    let sign = if growth >= 0.0 { "+" } else { "-" };
+    let rate = format!("{sign}{}/day", format_bytes(growth.abs()));
+    match forecast.days_until_full() {
+        Some(days) => format!(
+            "At current growth ({rate}), {} will be full in ~{days:.0} days",
+            forecast.source
+        ),
+        None => format!("{} is not growing ({rate})", forecast.source),
+    }
+}
+
+fn format_soc(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{v:.0}%"))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+fn battery_session_table(sessions: &[BatterySession]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Session",
+        "Start",
+        "Duration",
+        "SoC",
+        "Rate",
+        "Avg power",
+        "Depth",
+    ]));
+    for session in sessions {
+        let label = match session.direction {
+            Direction::Charging => "Charge",
+            Direction::Discharging => "Discharge",
+        };
+        report.add_row(vec![
+            label_cell(label),
+            Cell::new(local_datetime(session.start_ts).format("%m-%d %H:%M")),
+            value_cell(format_runtime(Some(session.seconds() / 3600.0))),
+            value_cell(format!(
+                "{} -> {}",
+                format_soc(session.start_soc),
+                format_soc(session.end_soc)
+            )),
+            value_cell(
+                session
+                    .percent_per_hour()
+                    .map(|rate| format!("{rate:+.1}%/h"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            value_cell(format_power(Some(session.avg_watts))),
+            value_cell(format_soc(session.depth_of_discharge())),
+        ]);
+    }
+    report
+}
+
+fn daily_table(days: &[DailySummary]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Day",
+        "Screen on",
+        "Energy used",
+        "Avg temp",
+        "Max temp",
+        "Transferred",
+    ]));
+    let temp = |value: Option<f64>| {
+        value
+            .map(|v| format!("{v:.1}C"))
+            .unwrap_or_else(|| "--".to_string())
+    };
+    for day in days {
+        report.add_row(vec![
+            label_cell(&day.day.format("%a %Y-%m-%d").to_string()),
+            value_cell(format_runtime(
+                day.screen_on_seconds.map(|seconds| seconds / 3600.0),
+            )),
+            value_cell(
+                day.energy_wh
+                    .map(|wh| format!("{wh:.1} Wh"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            value_cell(temp(day.avg_temp)),
+            value_cell(temp(day.max_temp)),
+            value_cell(
+                day.transferred_bytes
+                    .map(format_bytes)
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+        ]);
+    }
+    report
+}
+
+fn last_discharge_table(last: &LastDischarge) -> ThemedTable {
+    let session = &last.session;
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Unplugged",
+        "Ran for",
+        "SoC",
+        "Rate",
+        "Avg power",
+        "Now",
+    ]));
+    let unplugged = local_datetime(session.start_ts).format("%m-%d %H:%M");
+    report.add_row(vec![
+        label_cell(&if last.started_before {
+            format!("{unplugged} or earlier")
+        } else {
+            unplugged.to_string()
+        }),
+        value_cell(format_runtime(Some(session.seconds() / 3600.0))),
+        value_cell(format!(
+            "{} -> {}",
+            format_soc(session.start_soc),
+            format_soc(session.end_soc)
+        )),
+        value_cell(
+            session
+                .percent_per_hour()
+                .map(|rate| format!("{rate:+.1}%/h"))
+                .unwrap_or_else(|| "--".to_string()),
+        ),
+        value_cell(format_power(Some(session.avg_watts))),
+        status_cell(Some(if last.ongoing {
+            "Discharging"
+        } else {
+            "Plugged in"
+        })),
+    ]);
+    report
+}
+
+/// `10-16 22:56 (3h ago)`, or `not in window`.
+fn format_seen(ts: Option<f64>, now: f64) -> String {
+    let Some(ts) = ts else {
+        return "not in window".to_string();
+    };
+    let hours = ((now - ts) / 3600.0).max(0.0);
+    let ago = if hours < 1.0 {
+        format!("{}m", (hours * 60.0).floor())
+    } else if hours < 48.0 {
+        format!("{}h", hours.floor())
+    } else {
+        format!("{}d", (hours / 24.0).floor())
+    };
+    format!("{} ({ago} ago)", local_datetime(ts).format("%m-%d %H:%M"))
+}
+
+fn charge_extremes_table(extremes: &ChargeExtremes, now: f64) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Last full charge", "Last full discharge"]));
+    report.add_row(vec![
+        value_cell(format_seen(extremes.last_full, now)),
+        value_cell(format_seen(extremes.last_empty, now)),
+    ]);
+    report
+}
+
+fn charge_curve_table(curves: &[ChargeCurve]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Start",
+        "Duration",
+        "SoC",
+        "Avg power",
+        "Peak power",
+        "CC phase",
+        "CV phase",
+        "CV from",
+    ]));
+    for curve in curves {
+        report.add_row(vec![
+            Cell::new(local_datetime(curve.start_ts).format("%m-%d %H:%M"))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(format_runtime(Some(
+                (curve.end_ts - curve.start_ts) / 3600.0,
+            ))),
+            value_cell(format!(
+                "{} -> {}",
+                format_soc(curve.start_soc),
+                format_soc(curve.end_soc)
+            )),
+            value_cell(format_power(Some(curve.avg_watts))),
+            value_cell(format_power(Some(curve.peak_watts))).fg(Color::Yellow),
+            value_cell(format_runtime(Some(curve.cc_seconds / 3600.0))),
+            value_cell(format_runtime(Some(curve.cv_seconds / 3600.0))),
+            value_cell(format_soc(curve.cv_start_soc)),
+        ]);
+    }
+    report
+}
+
+fn activity_drain_table(drains: &[ActivityDrain]) -> ThemedTable {
+    let total_wh: f64 = drains.iter().map(|d| d.energy_wh).sum();
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "State",
+        "Time",
+        "Energy",
+        "Share",
+        "Avg discharge",
+    ]));
+    for drain in drains {
+        let share = (total_wh > 0.0).then(|| drain.energy_wh / total_wh * 100.0);
+        report.add_row(vec![
+            label_cell(drain.activity.label()),
+            value_cell(format_runtime(Some(drain.seconds / 3600.0))),
+            value_cell(format!("{:.2} Wh", drain.energy_wh)),
+            value_cell(format_percent(share)),
+            value_cell(format_power(Some(drain.avg_watts()))).fg(Color::Yellow),
+        ]);
+    }
+    report
+}
+
+fn wakeup_source_table(sources: &[WakeupSourceSummary]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Wakeup source", "Events", "Per hour"]));
+    for source in sources {
+        report.add_row(vec![
+            label_cell(&source.source),
+            value_cell(format!("{:.0}", source.events)),
+            value_cell(
+                source
+                    .per_hour
+                    .map(|rate| format!("{rate:.1}"))
+                    .unwrap_or_else(|| "--".to_string()),
+            )
+            .fg(Color::Yellow),
+        ]);
+    }
+    report
+}
+
+fn backlight_drain_table(drains: &[BacklightDrain]) -> ThemedTable {
+    let total_wh: f64 = drains.iter().map(|d| d.energy_wh).sum();
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Backlight",
+        "Time",
+        "Energy",
+        "Share",
+        "Avg discharge",
+    ]));
+    for drain in drains {
+        let label = match drain.level {
+            0 => "Off".to_string(),
+            level => format!("{level}/{}", drain.max_level),
+        };
+        let share = (total_wh > 0.0).then(|| drain.energy_wh / total_wh * 100.0);
+        report.add_row(vec![
+            label_cell(&label),
+            value_cell(format_runtime(Some(drain.seconds / 3600.0))),
+            value_cell(format!("{:.2} Wh", drain.energy_wh)),
+            value_cell(format_percent(share)),
+            value_cell(format_power(Some(drain.avg_watts()))).fg(Color::Yellow),
+        ]);
+    }
+    report
+}
+
+fn charger_rate_table(chargers: &[ChargerRate]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Charger", "Time", "Energy", "Avg charge"]));
+    for charger in chargers {
+        let label = match &charger.usb_type {
+            Some(usb_type) => format!("{}W ({usb_type})", charger.charger_watts),
+            None => format!("{}W", charger.charger_watts),
+        };
+        report.add_row(vec![
+            label_cell(&label),
+            value_cell(format_runtime(Some(charger.seconds / 3600.0))),
+            value_cell(format!("{:.2} Wh", charger.energy_wh)),
+            value_cell(format_power(Some(charger.avg_watts()))).fg(Color::Green),
+        ]);
+    }
+    report
+}
+
+fn soc_decile_table(label: &str, deciles: &[DecileRate]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["State of charge", label, "Samples"]));
+    for decile in deciles {
+        report.add_row(vec![
+            label_cell(&format!(
+                "{}-{}%",
+                decile.band_start,
+                u16::from(decile.band_start) + 10
+            )),
+            value_cell(format_power(Some(decile.avg_watts))),
+            value_cell(decile.samples),
+        ]);
+    }
+    report
+}
+
+fn memory_leak_table(leaks: &[LeakPeriod]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Start",
+        "End",
+        "Duration",
+        "Growth",
+        "Rate",
+        "Non-decreasing",
+    ]));
+    for leak in leaks {
+        report.add_row(vec![
+            Cell::new(local_datetime(leak.start_ts).format("%m-%d %H:%M"))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            Cell::new(local_datetime(leak.end_ts).format("%m-%d %H:%M")),
+            value_cell(format_runtime(Some((leak.end_ts - leak.start_ts) / 3600.0))),
+            value_cell(format_bytes(leak.growth_bytes)).fg(Color::Yellow),
+            value_cell(format!("{}/h", format_bytes(leak.bytes_per_hour))),
+            value_cell(format_percent(Some(leak.monotonic_ratio * 100.0))),
+        ]);
+    }
+    report
+}
+
+fn format_rate(value: Option<f64>) -> String {
+    match value {
+        Some(v) if v >= 1000.0 => format!("{:.1}k/s", v / 1000.0),
+        Some(v) => format!("{v:.0}/s"),
+        None => "--".to_string(),
+    }
+}
+
+fn scheduler_rates_table(
+    bucket_seconds: i64,
+    switches: &BTreeMap<DateTime<Local>, NumberStats>,
+    interrupts: &BTreeMap<DateTime<Local>, NumberStats>,
+) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Window",
+        "Avg ctx switches",
+        "Peak ctx switches",
+        "Avg interrupts",
+        "Peak interrupts",
+    ]));
+    let mut keys: Vec<&DateTime<Local>> = switches.keys().chain(interrupts.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let switch_stats = switches.get(key);
+        let interrupt_stats = interrupts.get(key);
+        report.add_row(vec![
+            Cell::new(format_bucket(*key, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(format_rate(switch_stats.and_then(NumberStats::average))),
+            value_cell(format_rate(switch_stats.and_then(NumberStats::max))).fg(Color::Yellow),
+            value_cell(format_rate(interrupt_stats.and_then(NumberStats::average))),
+            value_cell(format_rate(interrupt_stats.and_then(NumberStats::max))).fg(Color::Yellow),
+        ]);
+    }
+    report
+}
+
+fn cpu_wait_table(
+    bucket_seconds: i64,
+    iowait: &BTreeMap<DateTime<Local>, NumberStats>,
+    steal: &BTreeMap<DateTime<Local>, NumberStats>,
+) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Window",
+        "Avg iowait",
+        "Peak iowait",
+        "Avg steal",
+        "Peak steal",
+    ]));
+    let mut keys: Vec<&DateTime<Local>> = iowait.keys().chain(steal.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let iowait_stats = iowait.get(key);
+        let steal_stats = steal.get(key);
+        report.add_row(vec![
+            Cell::new(format_bucket(*key, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(format_percent(iowait_stats.and_then(NumberStats::average))),
+            value_cell(format_percent(iowait_stats.and_then(NumberStats::max))).fg(Color::Yellow),
+            value_cell(format_percent(steal_stats.and_then(NumberStats::average))),
+            value_cell(format_percent(steal_stats.and_then(NumberStats::max))).fg(Color::Yellow),
+        ]);
+    }
+    report
+}
+
+/// Average share of CPU time per idle state and bucket, states ordered shallow to deep.
+fn idle_residency_table(bucket_seconds: i64, residency: &[MetricSample]) -> ThemedTable {
+    let mut states: Vec<(u64, &str)> = residency
+        .iter()
+        .map(|s| {
+            let index = s.details.get("index").and_then(|v| v.as_u64());
+            (index.unwrap_or(u64::MAX), s.source.as_str())
+        })
+        .collect();
+    states.sort();
+    states.dedup_by(|a, b| a.1 == b.1);
+    let buckets =
+        bucket_stats_for_kind_by_source(residency, MetricKind::CpuIdleResidency, bucket_seconds);
+
+    let mut report = themed_table();
+    let mut header = vec!["Window"];
+    header.extend(states.iter().map(|(_, name)| *name));
+    report.set_header(header_cells(&header));
+    let mut keys: Vec<&DateTime<Local>> = buckets.values().flat_map(BTreeMap::keys).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let mut row = vec![Cell::new(format_bucket(*key, bucket_seconds))
+            .fg(Color::Magenta)
+            .add_attribute(Attribute::Bold)];
+        row.extend(states.iter().map(|(_, name)| {
+            let stats = buckets.get(*name).and_then(|b| b.get(key));
+            value_cell(format_percent(stats.and_then(NumberStats::average)))
+        }));
+        report.add_row(row);
+    }
+    report
+}
+
+fn container_stats_table(
+    bucket_seconds: i64,
+    cpu: &SourceBuckets,
+    memory: &SourceBuckets,
+) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Container",
+        "Window",
+        "Avg CPU",
+        "Peak CPU",
+        "Avg memory",
+        "Peak memory",
+    ]));
+
+    let mut keys: Vec<(&String, &DateTime<Local>)> = cpu
+        .iter()
+        .chain(memory.iter())
+        .flat_map(|(source, buckets)| buckets.keys().map(move |key| (source, key)))
+        .collect();
+    keys.sort();
+    keys.dedup();
+    for (source, key) in keys {
+        let cpu_stats = cpu.get(source).and_then(|b| b.get(key));
+        let memory_stats = memory.get(source).and_then(|b| b.get(key));
+        report.add_row(vec![
+            label_cell(source),
+            Cell::new(format_bucket(*key, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(format_percent(cpu_stats.and_then(NumberStats::average))),
+            value_cell(format_percent(cpu_stats.and_then(NumberStats::max))).fg(Color::Yellow),
+            value_cell(format_opt_bytes(
+                memory_stats.and_then(NumberStats::average),
+            )),
+            value_cell(format_opt_bytes(memory_stats.and_then(NumberStats::max))),
+        ]);
+    }
+    report
+}
+
+fn system_health_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<DateTime<Local>, HealthBucket>,
+) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Window",
+        "Samples",
+        "Not running",
+        "Peak failed",
+        "Failed units",
+    ]));
+    for (key, bucket) in buckets {
+        let failing = bucket.peak_failed > 0.0 || bucket.degraded > 0;
+        let units: Vec<&str> = bucket.failed_units.iter().map(String::as_str).collect();
+        report.add_row(vec![
+            Cell::new(format_bucket(*key, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(bucket.samples),
+            value_cell(format_percent(Some(
+                bucket.degraded as f64 / bucket.samples as f64 * 100.0,
+            ))),
+            value_cell(format!("{:.0}", bucket.peak_failed)).fg(if failing {
+                Color::Red
+            } else {
+                Color::Green
+            }),
+            Cell::new(if units.is_empty() {
+                "--".to_string()
+            } else {
+                units.join(", ")
+            }),
+        ]);
+    }
+    report
+}
+
+fn tcp_connections_table(bucket_seconds: i64, buckets: &SourceBuckets) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["State", "Window", "Min", "Avg", "Peak"]));
+    for (state, readings) in buckets {
+        for (key, stats) in readings {
+            report.add_row(vec![
+                label_cell(state),
+                Cell::new(format_bucket(*key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(format_count(stats.min())),
+                value_cell(format_count(stats.average())),
+                value_cell(format_count(stats.max())).fg(Color::Yellow),
+            ]);
+        }
+    }
+    report
+}
+
+/// Min/avg/max per source and window, each source with its unit.
+fn source_stats_table(
+    label: &str,
+    bucket_seconds: i64,
+    buckets: &SourceBuckets,
+    units: &BTreeMap<String, String>,
+) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[label, "Window", "Min", "Avg", "Max"]));
+    for (name, readings) in buckets {
+        let unit = units.get(name).map(String::as_str).unwrap_or("");
+        let format = |value: Option<f64>| {
+            value
+                .map(|v| format!("{v:.2}{unit}"))
+                .unwrap_or_else(|| "--".to_string())
+        };
+        for (key, stats) in readings {
+            report.add_row(vec![
+                label_cell(name),
+                Cell::new(format_bucket(*key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(format(stats.min())),
+                value_cell(format(stats.average())),
+                value_cell(format(stats.max())).fg(Color::Yellow),
+            ]);
+        }
+    }
+    report
+}
+
+fn state_table(states: &[StateSummary]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "State", "Source", "Uptime", "On", "Observed", "Changes", "Now",
+    ]));
+    for state in states {
+        report.add_row(vec![
+            label_cell(state.kind.as_str()),
+            label_cell(&state.source),
+            value_cell(format_percent(state.on_percent())).fg(Color::Yellow),
+            value_cell(format_runtime(Some(state.on_seconds / 3600.0))),
+            value_cell(format_runtime(Some(state.observed_seconds / 3600.0))),
+            value_cell(state.transitions),
+            value_cell(if state.latest { "on" } else { "off" }),
+        ]);
+    }
+    report
+}
+
+/// The reading of one sample: its label for categorical kinds, else value and unit.
+fn format_sample_value(sample: &MetricSample) -> String {
+    match (&sample.label, sample.value, sample.unit.as_deref()) {
+        (Some(label), _, _) => label.clone(),
+        (None, Some(value), Some("bytes")) => format_bytes(value),
+        (None, Some(value), Some(unit)) => format!("{value:.1} {unit}"),
+        (None, Some(value), None) => format!("{value:.1}"),
+        (None, None, _) => "--".to_string(),
+    }
+}
+
+fn live_table(samples: &[MetricSample]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Metric", "Source", "Value"]));
+    for sample in samples {
+        report.add_row(vec![
+            label_cell(sample.kind.as_str()),
+            label_cell(&sample.source),
+            value_cell(format_sample_value(sample)),
+        ]);
+    }
+    report
+}
+
+fn latest_table(samples: &[MetricSample], now: f64) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Metric", "Source", "Value", "Age"]));
+    for sample in samples {
+        report.add_row(vec![
+            label_cell(sample.kind.as_str()),
+            label_cell(&sample.source),
+            value_cell(format_sample_value(sample)),
+            value_cell(format_runtime(Some((now - sample.ts).max(0.0) / 3600.0))),
+        ]);
+    }
+    report
+}
+
+/// One row per probed path; the collector, its verdict and sample count on its first row.
+fn doctor_table(diagnoses: &[Diagnosis]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Collector",
+        "Verdict",
+        "Samples",
+        "Path",
+        "State",
+        "Provides",
+    ]));
+    for diagnosis in diagnoses {
+        let lead = [
+            label_cell(&diagnosis.collector.to_string()),
+            Cell::new(diagnosis.verdict()),
+            value_cell(diagnosis.samples),
+        ];
+        if diagnosis.findings.is_empty() {
+            report.add_row(lead.into_iter().chain(["", "", ""].map(Cell::new)));
+            continue;
+        }
+        for (index, finding) in diagnosis.findings.iter().enumerate() {
+            let row = if index == 0 {
+                lead.clone()
+            } else {
+                ["", "", ""].map(Cell::new)
+            };
+            report.add_row(row.into_iter().chain([
+                Cell::new(finding.path.display()),
+                Cell::new(finding.state.label()),
+                Cell::new(&finding.provides),
+            ]));
+        }
+    }
+    report
+}
+
+/// One row per kind and source, with the group that collects it and a fresh reading.
+fn sensors_table(trial: &[(Collector, Vec<MetricSample>)], config: &Config) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Collector", "Kind", "Source", "Value"]));
+    for (collector, samples) in trial {
+        let mut seen = std::collections::HashSet::new();
+        for sample in samples {
+            if !seen.insert((&sample.kind, sample.source.as_str())) {
+                continue;
+            }
+            let group = if config.collect.is_enabled(*collector) {
+                collector.to_string()
+            } else {
+                format!("{collector} (disabled)")
+            };
+            report.add_row(vec![
+                label_cell(&group),
+                Cell::new(sample.kind.as_str()),
+                Cell::new(&sample.source),
+                value_cell(format_sample_value(sample)),
+            ]);
+        }
+    }
+    report
+}
+
+fn boot_table(boots: &[BootSummary]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Boot",
+        "First run",
+        "Last run",
+        "Runs",
+        "Awake",
+        "Suspends",
+        "Suspended",
+    ]));
+    for boot in boots {
+        // The first group of the UUID is enough to tell boots apart
+        let short = boot.boot.split('-').next().unwrap_or(&boot.boot);
+        report.add_row(vec![
+            label_cell(short),
+            Cell::new(local_datetime(boot.start_ts).format("%Y-%m-%d %H:%M")),
+            Cell::new(local_datetime(boot.end_ts).format("%Y-%m-%d %H:%M")),
+            value_cell(boot.runs),
+            value_cell(format_runtime(Some(boot.awake_seconds() / 3600.0))),
+            value_cell(boot.suspends),
+            value_cell(format_runtime(Some(boot.suspended_seconds / 3600.0))),
+        ]);
+    }
+    report
+}
+
+fn category_table(categories: &[CategorySummary]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Metric", "Source", "State", "Time", "Share",
+    ]));
+    for category in categories {
+        let observed = category.observed_seconds();
+        for (label, seconds) in &category.seconds {
+            let share = (observed > 0.0).then(|| seconds / observed * 100.0);
+            let name = if *label == category.latest {
+                format!("{label} (now)")
+            } else {
+                label.clone()
+            };
+            report.add_row(vec![
+                label_cell(category.kind.as_str()),
+                label_cell(&category.source),
+                label_cell(&name),
+                value_cell(format_runtime(Some(seconds / 3600.0))),
+                value_cell(format_percent(share)).fg(Color::Yellow),
+            ]);
+        }
+    }
+    report
+}
+
+/// Machine snapshots in effect during the report; several rows mean the kernel, battery or
+/// machine changed within the window.
+fn machine_table(snapshots: &[SystemSnapshot]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Since", "Host", "Kernel", "CPU", "RAM", "Battery", "Serial",
+    ]));
+    let text = |value: &Option<String>| label_cell(value.as_deref().unwrap_or("--"));
+    for snapshot in snapshots {
+        let info = &snapshot.info;
         report.add_row(vec![
-            Cell::new(format_bucket(bucket_start_dt, bucket_seconds))
-                .fg(Color::Magenta)
-                .add_attribute(Attribute::Bold),
-            value_cell(bucket_samples.len()),
-            value_cell(min_pct),
-            value_cell(avg_pct),
-            value_cell(max_pct),
-            value_cell(format_power(discharge_power)),
-            value_cell(format_power(charge_power)),
-            status_cell(Some(latest_status)),
+            Cell::new(local_datetime(snapshot.ts).format("%Y-%m-%d %H:%M")),
+            text(&info.hostname),
+            text(&info.kernel),
+            text(&info.cpu_model),
+            value_cell(
+                info.memory_bytes
+                    .map(|bytes| format_bytes(bytes as f64))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            text(&info.battery_model),
+            text(&info.battery_serial),
         ]);
     }
     report
 }
 
-fn freq_usage_stats_table(
-    bucket_seconds: i64,
-    usage_label: &'static str,
-    freq_label: &'static str,
-    usage: &SourceBuckets,
-    freq: &SourceBuckets,
-) -> Table {
+fn collector_cost_table(costs: &[CollectorCost]) -> ThemedTable {
     let mut report = themed_table();
     report.set_header(header_cells(&[
-        "Source",
-        "Window",
-        "Samples",
-        &format!("Min {usage_label}"),
-        &format!("Avg {usage_label}"),
-        &format!("Peak {usage_label}"),
-        &format!("Min {freq_label}"),
-        &format!("Avg {freq_label}"),
-        &format!("Peak {freq_label}"),
+        "Collector",
+        "Runs",
+        "Avg time",
+        "Max time",
+        "CPU time",
+        "Samples/run",
     ]));
+    let ms = |value: Option<f64>| {
+        value
+            .map(|v| format!("{v:.1}ms"))
+            .unwrap_or_else(|| "--".to_string())
+    };
+    for cost in costs {
+        report.add_row(vec![
+            label_cell(&cost.collector),
+            value_cell(cost.duration.count),
+            value_cell(ms(cost.duration.average())),
+            value_cell(ms(cost.duration.max())).fg(Color::Yellow),
+            value_cell(ms(cost.cpu.average())),
+            value_cell(
+                cost.samples
+                    .average()
+                    .map(|v| format!("{v:.0}"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+        ]);
+    }
+    report
+}
 
-    let mut sources: Vec<&String> = usage.keys().chain(freq.keys()).collect();
-    sources.sort();
-    sources.dedup();
-
-    for source in sources {
-        let usage_buckets = usage.get(source);
-        let freq_buckets = freq.get(source);
-        let mut keys: Vec<DateTime<Local>> = usage_buckets
-            .into_iter()
-            .flat_map(|m| m.keys().copied())
-            .chain(freq_buckets.into_iter().flat_map(|m| m.keys().copied()))
-            .collect();
-        keys.sort();
-        keys.dedup();
-
-        for key in keys {
-            let usage_stats = usage_buckets
-                .and_then(|map| map.get(&key).cloned())
-                .unwrap_or_default();
-            let freq_stats = freq_buckets
-                .and_then(|map| map.get(&key).cloned())
-                .unwrap_or_default();
-            let samples = usage_stats.count.max(freq_stats.count);
+fn collector_stats_table(bucket_seconds: i64, buckets: &SourceBuckets) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Collector",
+        "Window",
+        "Runs",
+        "Min time",
+        "Avg time",
+        "Max time",
+    ]));
+    let ms = |value: Option<f64>| {
+        value
+            .map(|v| format!("{v:.1}ms"))
+            .unwrap_or_else(|| "--".to_string())
+    };
+    for (collector, readings) in buckets {
+        for (key, stats) in readings {
             report.add_row(vec![
-                label_cell(source),
-                Cell::new(format_bucket(key, bucket_seconds))
+                label_cell(collector),
+                Cell::new(format_bucket(*key, bucket_seconds))
                     .fg(Color::Magenta)
                     .add_attribute(Attribute::Bold),
-                value_cell(samples),
-                value_cell(format_percent(usage_stats.min())),
-                value_cell(format_percent(usage_stats.average())),
-                value_cell(format_percent(usage_stats.max())),
-                value_cell(format_freq(freq_stats.min())),
-                value_cell(format_freq(freq_stats.average())),
-                value_cell(format_freq(freq_stats.max())),
+                value_cell(stats.count),
+                value_cell(ms(stats.min())),
+                value_cell(ms(stats.average())),
+                value_cell(ms(stats.max())).fg(Color::Yellow),
             ]);
         }
     }
     report
 }
 
-fn cpu_stats_table(bucket_seconds: i64, usage: &SourceBuckets, freq: &SourceBuckets) -> Table {
-    freq_usage_stats_table(bucket_seconds, "usage", "freq", usage, freq)
-}
-
-fn gpu_stats_table(bucket_seconds: i64, usage: &SourceBuckets, freq: &SourceBuckets) -> Table {
-    freq_usage_stats_table(bucket_seconds, "usage", "freq", usage, freq)
+fn sampling_table(summaries: &[SamplingSummary]) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Metric", "Source", "Interval", "Samples", "Expected", "Coverage",
+    ]));
+    for summary in summaries {
+        report.add_row(vec![
+            label_cell(summary.kind.as_str()),
+            label_cell(&summary.source),
+            value_cell(format!("{}s", summary.interval)),
+            value_cell(summary.samples),
+            value_cell(summary.expected),
+            value_cell(format_percent(Some(summary.coverage() * 100.0))).fg(Color::Red),
+        ]);
+    }
+    report
 }
 
-fn usage_stats_table(
-    bucket_seconds: i64,
-    buckets: &BTreeMap<DateTime<Local>, UsageStats>,
-) -> Table {
+fn distribution_table(summaries: &[DistributionSummary]) -> ThemedTable {
     let mut report = themed_table();
     report.set_header(header_cells(&[
-        "Window",
+        "Metric",
+        "Source",
         "Samples",
-        "Min used",
-        "Avg used",
-        "Min used %",
-        "Avg used %",
-        "Peak used %",
+        "Observations",
+        "p50",
+        "p90",
+        "p99",
     ]));
-
-    for (key, stats) in buckets {
+    for summary in summaries {
+        let unit = summary.unit.as_deref().unwrap_or("");
+        let format = |value: Option<f64>| {
+            value
+                .map(|v| format!("{v:.2}{unit}"))
+                .unwrap_or_else(|| "--".to_string())
+        };
         report.add_row(vec![
-            Cell::new(format_bucket(*key, bucket_seconds))
-                .fg(Color::Magenta)
-                .add_attribute(Attribute::Bold),
-            value_cell(stats.used.count),
-            value_cell(format_opt_bytes(stats.used.min())),
-            value_cell(format_opt_bytes(stats.used.average())),
-            value_cell(format_percent(stats.percent.min())),
-            value_cell(format_percent(stats.percent.average())),
-            value_cell(format_percent(stats.percent.max())),
+            label_cell(summary.kind.as_str()),
+            label_cell(&summary.source),
+            value_cell(summary.samples.to_string()),
+            value_cell(summary.observations.to_string()),
+            value_cell(format(summary.p50)),
+            value_cell(format(summary.p90)),
+            value_cell(format(summary.p99)).fg(Color::Yellow),
         ]);
     }
     report
 }
 
-fn memory_stats_table(
-    bucket_seconds: i64,
-    buckets: &BTreeMap<DateTime<Local>, UsageStats>,
-) -> Table {
-    usage_stats_table(bucket_seconds, buckets)
+/// Width in characters of the fullest histogram bar.
+const HISTOGRAM_BAR_WIDTH: usize = 30;
+
+/// A bar of `fraction` × `width` characters, in eighths of a block, or in whole `#`
+/// characters for `--plain`.
+fn histogram_bar(fraction: f64, width: usize, ascii: bool) -> String {
+    const EIGHTHS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+    let fraction = fraction.clamp(0.0, 1.0);
+    if ascii {
+        return "#".repeat((fraction * width as f64).round() as usize);
+    }
+    let eighths = (fraction * width as f64 * 8.0).round() as usize;
+    format!("{}{}", "█".repeat(eighths / 8), EIGHTHS[eighths % 8])
 }
 
-fn disk_stats_table(bucket_seconds: i64, buckets: &BTreeMap<DateTime<Local>, UsageStats>) -> Table {
-    usage_stats_table(bucket_seconds, buckets)
+fn value_histogram_table(histogram: &ValueHistogram) -> ThemedTable {
+    fill_value_histogram_table(themed_table(), histogram)
+}
+
+fn fill_value_histogram_table(mut report: ThemedTable, histogram: &ValueHistogram) -> ThemedTable {
+    let (dash, ascii) = if report.plain {
+        ("-", true)
+    } else {
+        ("–", false)
+    };
+    report.set_header(header_cells(&["Range", "Samples", "Share", "Distribution"]));
+    let unit = match histogram.unit.as_deref() {
+        Some("%") => "%".to_string(),
+        Some(unit) => format!(" {unit}"),
+        None => String::new(),
+    };
+    let total = histogram.total().max(1) as f64;
+    let fullest = histogram
+        .bins
+        .iter()
+        .map(|(_, _, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    for (lower, upper, count) in &histogram.bins {
+        report.add_row(vec![
+            label_cell(&format!("{lower:.1}{dash}{upper:.1}{unit}")),
+            value_cell(count),
+            value_cell(format_percent(Some(*count as f64 / total * 100.0))),
+            Cell::new(histogram_bar(
+                *count as f64 / fullest,
+                HISTOGRAM_BAR_WIDTH,
+                ascii,
+            ))
+            .fg(Color::Green),
+        ]);
+    }
+    report
+}
+
+fn format_count(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{v:.0}"))
+        .unwrap_or_else(|| "--".to_string())
 }
 
-fn temperature_stats_table(bucket_seconds: i64, buckets: &SourceBuckets) -> Table {
+/// One row per sensor over the whole timeframe, hottest first.
+fn temperature_sensors_table(sensors: &[(String, NumberStats)]) -> ThemedTable {
     let mut report = themed_table();
     report.set_header(header_cells(&[
-        "Source",
-        "Window",
+        "Sensor",
         "Samples",
         "Min temp",
         "Avg temp",
         "Peak temp",
     ]));
+    let celsius = |value: Option<f64>| {
+        value
+            .map(|v| format!("{v:.1}C"))
+            .unwrap_or_else(|| "--".to_string())
+    };
+    for (source, stats) in sensors {
+        report.add_row(vec![
+            label_cell(source),
+            value_cell(stats.count),
+            value_cell(celsius(stats.min())),
+            value_cell(celsius(stats.average())),
+            value_cell(celsius(stats.max())),
+        ]);
+    }
+    report
+}
+
+/// Rise or fall against the previous period's average; red when it rose by more than
+/// [`DELTA_HIGHLIGHT_RATIO`] of it, green when it fell by as much.
+fn delta_cell(current: Option<f64>, previous: Option<f64>, unit: &str, decimals: usize) -> Cell {
+    let (Some(current), Some(previous)) = (current, previous) else {
+        return value_cell("--");
+    };
+    let delta = current - previous;
+    let cell = value_cell(format!("{delta:+.decimals$}{unit}"));
+    let threshold = previous.abs() * DELTA_HIGHLIGHT_RATIO;
+    if delta > threshold {
+        cell.fg(Color::Red)
+    } else if delta < -threshold {
+        cell.fg(Color::Green)
+    } else {
+        cell
+    }
+}
+
+/// Share of the previous average a bucket must rise or fall by to be highlighted.
+const DELTA_HIGHLIGHT_RATIO: f64 = 0.05;
+
+fn temperature_stats_table(
+    bucket_seconds: i64,
+    buckets: &SourceBuckets,
+    previous: Option<(&str, &BTreeMap<String, TimeBuckets<f64>>)>,
+) -> ThemedTable {
+    let mut report = themed_table();
+    let delta_header = previous.map(|(label, _)| format!("vs {label}"));
+    let mut header = vec!["Source", "Window", "Samples", "Min temp", "Avg temp"];
+    header.extend(delta_header.as_deref());
+    header.push("Peak temp");
+    report.set_header(header_cells(&header));
 
     for (source, readings) in buckets {
         for (key, stats) in readings {
-            report.add_row(vec![
+            let delta = previous.map(|(_, before)| {
+                delta_cell(
+                    stats.average(),
+                    before
+                        .get(source)
+                        .and_then(|buckets| buckets.get(key))
+                        .copied(),
+                    "C",
+                    1,
+                )
+            });
+            let mut row = vec![
                 label_cell(source),
                 Cell::new(format_bucket(*key, bucket_seconds))
                     .fg(Color::Magenta)
@@ -1046,7 +3954,11 @@ fn temperature_stats_table(bucket_seconds: i64, buckets: &SourceBuckets) -> Tabl
                         .map(|v| format!("{v:.1}C"))
                         .unwrap_or_else(|| "--".to_string()),
                 ),
-            ]);
+            ];
+            if let Some(delta) = delta {
+                row.insert(5, delta);
+            }
+            report.add_row(row);
         }
     }
     report
@@ -1055,7 +3967,7 @@ fn temperature_stats_table(bucket_seconds: i64, buckets: &SourceBuckets) -> Tabl
 fn network_totals_table(
     bucket_seconds: i64,
     buckets: &BTreeMap<DateTime<Local>, TransferStats>,
-) -> Table {
+) -> ThemedTable {
     let mut report = themed_table();
     report.set_header(header_cells(&["Window", "Total down", "Total up"]));
 
@@ -1071,35 +3983,39 @@ fn network_totals_table(
     report
 }
 
-fn format_bytes(value: f64) -> String {
-    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
-    let mut val = value;
-    let mut unit = "B";
-    for next in &UNITS {
-        unit = next;
-        if val.abs() < 1024.0 || *next == "TiB" {
-            break;
+fn has_nonzero_deltas(buckets: &SourceCounterBuckets) -> bool {
+    buckets
+        .values()
+        .any(|deltas| deltas.values().any(|delta| *delta > 0.0))
+}
+
+/// Table of counter increases per source and bucket; buckets without events are omitted.
+fn counter_delta_table(
+    bucket_seconds: i64,
+    label: &str,
+    buckets: &SourceCounterBuckets,
+) -> ThemedTable {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Source", "Window", label]));
+
+    for (source, deltas) in buckets {
+        for (key, delta) in deltas.iter().filter(|(_, delta)| **delta > 0.0) {
+            report.add_row(vec![
+                label_cell(source),
+                Cell::new(format_bucket(*key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(format!("{delta:.0}")),
+            ]);
         }
-        val /= 1024.0;
-    }
-    if unit == "B" {
-        format!("{val:.0}{unit}")
-    } else {
-        format!("{val:.1}{unit}")
     }
+    report
 }
 
 fn format_opt_bytes(value: Option<f64>) -> String {
     value.map(format_bytes).unwrap_or_else(|| "--".to_string())
 }
 
-fn number_from_details(sample: &MetricSample, key: &str) -> Option<f64> {
-    sample
-        .details
-        .get(key)
-        .and_then(|v| v.as_f64().or_else(|| v.as_i64().map(|i| i as f64)))
-}
-
 fn pct_stats(values: &[f64]) -> (String, String, String) {
     if values.is_empty() {
         return ("--".to_string(), "--".to_string(), "--".to_string());
@@ -1127,8 +4043,102 @@ fn format_bucket(dt: DateTime<Local>, bucket_seconds: i64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli_helpers::bucket_counter_deltas;
+    use crate::forecast::disk_forecasts;
+    use chrono::TimeZone;
     use serde_json::json;
-
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+    #[test]
+    fn local_only_commands_refuse_a_server_store() {
+        let err = local_db_path(
+            Some(Path::new("postgres://metrics.lan/symmetri")),
+            "compact",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`symmetri compact` is not supported on PostgreSQL; it works on a local SQLite \
+             database only"
+        );
+        let file = Path::new("/var/lib/symmetri/metrics.db");
+        assert_eq!(local_db_path(Some(file), "query").unwrap(), file);
+    }
+    #[test]
+    fn aliases_and_default_command_expand() {
+        let config = config::parse_config(
+            r#"
+            default_command = "week"
+            [aliases]
+            week = "report --days 7 --preset all"
+            collect = "report"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            expand_command_aliases(os_args(&["symmetri", "week", "--graph"]), &config),
+            os_args(&["symmetri", "report", "--days", "7", "--preset", "all", "--graph"])
+        );
+        assert_eq!(
+            expand_command_aliases(os_args(&["symmetri", "--config", "c.toml"]), &config),
+            os_args(&[
+                "symmetri", "--config", "c.toml", "report", "--days", "7", "--preset", "all"
+            ])
+        );
+        // Global flags before the alias or in place of a subcommand are stepped over
+        assert_eq!(
+            expand_command_aliases(
+                os_args(&["symmetri", "--config", "a.toml", "--plain", "week"]),
+                &config
+            ),
+            os_args(&[
+                "symmetri", "--config", "a.toml", "--plain", "report", "--days", "7", "--preset",
+                "all"
+            ])
+        );
+        assert_eq!(
+            expand_command_aliases(
+                os_args(&["symmetri", "--plain", "--config", "a.toml"]),
+                &config
+            ),
+            os_args(&[
+                "symmetri", "--plain", "--config", "a.toml", "report", "--days", "7", "--preset",
+                "all"
+            ])
+        );
+        for color in [&["--color", "never"][..], &["--color=never"]] {
+            let args: Vec<&str> = ["symmetri"]
+                .into_iter()
+                .chain(color.iter().copied())
+                .collect();
+            let expected: Vec<&str> = args
+                .iter()
+                .copied()
+                .chain(["report", "--days", "7", "--preset", "all"])
+                .collect();
+            let week: Vec<&str> = args.iter().copied().chain(["week"]).collect();
+            assert_eq!(
+                expand_command_aliases(os_args(&week), &config),
+                os_args(&expected)
+            );
+            assert_eq!(
+                expand_command_aliases(os_args(&args), &config),
+                os_args(&expected)
+            );
+        }
+        // Built-in subcommands and flags are never rewritten.
+        for args in [&["symmetri", "collect"][..], &["symmetri", "--help"]] {
+            assert_eq!(
+                expand_command_aliases(os_args(args), &config),
+                os_args(args)
+            );
+        }
+        assert_eq!(
+            config_path_arg(&os_args(&["symmetri", "--config=x.toml"])),
+            Some(PathBuf::from("x.toml"))
+        );
+    }
     fn metric_sample_with_source(
         kind: MetricKind,
         source: &str,
@@ -1143,187 +4153,314 @@ mod tests {
             value,
             unit: None,
             details,
+            rx_bytes: None,
+            tx_bytes: None,
+            total_bytes: None,
+            status: None,
+            histogram: None,
+            label: None,
+            host: None,
+            boot: None,
         }
     }
 
-    fn metric_sample(
-        kind: MetricKind,
-        ts: f64,
-        value: Option<f64>,
-        details: serde_json::Value,
-    ) -> MetricSample {
-        metric_sample_with_source(kind, "test", ts, value, details)
+    #[test]
+    fn color_choice_follows_no_color_and_plain() {
+        let no_color = || Some(OsString::from("1"));
+        assert_eq!(ColorChoice::Auto.resolve(false, None), ColorChoice::Auto);
+        assert_eq!(
+            ColorChoice::Auto.resolve(false, no_color()),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            ColorChoice::Auto.resolve(false, Some(OsString::new())),
+            ColorChoice::Auto
+        );
+        assert_eq!(
+            ColorChoice::Always.resolve(false, no_color()),
+            ColorChoice::Always
+        );
+        assert_eq!(ColorChoice::Always.resolve(true, None), ColorChoice::Never);
     }
 
-    fn battery_metric(ts: f64, kind: MetricKind, value: f64, status: &str) -> MetricSample {
-        MetricSample {
-            ts,
-            kind: kind.clone(),
-            source: "BAT0".to_string(),
-            value: Some(value),
-            unit: match kind {
-                MetricKind::BatteryPercentage
-                | MetricKind::BatteryCapacity
-                | MetricKind::BatteryHealth => Some("%".to_string()),
-                _ => Some("Wh".to_string()),
-            },
-            details: json!({"status": status}),
-        }
+    #[test]
+    fn report_timeframe_flags_override_config() {
+        let config = ReportConfig {
+            timeframe: Some("7d".to_string()),
+            ..ReportConfig::default()
+        };
+        let from_config = resolve_report_timeframe(None, None, None, false, &config).unwrap();
+        assert_eq!(from_config.label, "last_7_days");
+        let from_flags = resolve_report_timeframe(Some(3), None, None, false, &config).unwrap();
+        assert_eq!(from_flags.label, "last_3_hours");
+        let fallback =
+            resolve_report_timeframe(None, None, None, false, &ReportConfig::default()).unwrap();
+        assert_eq!(fallback.label, "last_6_hours");
     }
-
     #[test]
-    fn network_rates_compute_per_second() {
+    fn counter_deltas_skip_resets() {
         let metrics = vec![
-            metric_sample(
-                MetricKind::NetworkBytes,
+            metric_sample_with_source(
+                MetricKind::NetworkWakeups,
+                "wlan0",
                 0.0,
-                Some(1000.0),
-                json!({"rx_bytes": 1_000.0, "tx_bytes": 500.0}),
+                Some(5.0),
+                json!({}),
+            ),
+            metric_sample_with_source(
+                MetricKind::NetworkWakeups,
+                "wlan0",
+                60.0,
+                Some(9.0),
+                json!({}),
             ),
-            metric_sample(
-                MetricKind::NetworkBytes,
-                10.0,
-                Some(4000.0),
-                json!({"rx_bytes": 3_000.0, "tx_bytes": 1_500.0}),
+            metric_sample_with_source(
+                MetricKind::NetworkWakeups,
+                "wlan0",
+                120.0,
+                Some(2.0),
+                json!({}),
+            ),
+            metric_sample_with_source(
+                MetricKind::NetworkWakeups,
+                "wlan0",
+                180.0,
+                Some(3.0),
+                json!({}),
             ),
         ];
 
-        let rates = compute_network_rates(&metrics);
-        assert_eq!(rates.len(), 1);
-        let rate = &rates[0];
-        assert!((rate.rx_rate.unwrap() - 200.0).abs() < 1e-6);
-        assert!((rate.tx_rate.unwrap() - 100.0).abs() < 1e-6);
+        let deltas = bucket_counter_deltas(&metrics, MetricKind::NetworkWakeups, 3600);
+        let total: f64 = deltas["wlan0"].values().sum();
+        assert_eq!(total, 5.0);
+    }
+    #[test]
+    fn structured_report_output_keeps_the_table_cells() {
+        let mut out = ReportOutput::new(ReportFormat::Json);
+        let mut table = themed_table();
+        table.set_header(header_cells(&["Source", "Avg"]));
+        table.add_row(vec![label_cell("cpu"), value_cell("12.5%")]);
+        out.table("CPU stats (last 6 hours)".to_string(), table);
+        out.note("\nNo GPU samples available for last 6 hours.".to_string());
+
+        let block = &out.blocks[0];
+        assert_eq!(block.title, "CPU stats (last 6 hours)");
+        assert_eq!(block.columns, ["Source", "Avg"]);
+        assert_eq!(block.rows, [["cpu", "12.5%"]]);
+        assert_eq!(out.notes, ["No GPU samples available for last 6 hours."]);
+        assert_eq!(csv_field("coretemp:Package id 0"), "coretemp:Package id 0");
+        assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
     }
 
     #[test]
-    fn network_totals_compute_transferred_bytes() {
-        let metrics = vec![
-            metric_sample(
-                MetricKind::NetworkBytes,
-                0.0,
-                Some(1000.0),
-                json!({"rx_bytes": 1_000.0, "tx_bytes": 500.0}),
-            ),
-            metric_sample(
-                MetricKind::NetworkBytes,
-                10.0,
-                Some(4000.0),
-                json!({"rx_bytes": 3_000.0, "tx_bytes": 1_500.0}),
-            ),
-            metric_sample(
-                MetricKind::NetworkBytes,
-                20.0,
-                Some(8000.0),
-                json!({"rx_bytes": 7_000.0, "tx_bytes": 3_000.0}),
-            ),
-        ];
+    fn columns_are_selected_and_dropped_to_fit() {
+        let battery_table = || {
+            let mut table = themed_table();
+            table.set_header(header_cells(&[
+                "Window",
+                "Records",
+                "Min %",
+                "Avg %",
+                "Max %",
+                "Avg charge W",
+            ]));
+            table.add_row(vec![
+                label_cell("10-17 00:00"),
+                value_cell(12),
+                value_cell("40.0"),
+                value_cell("55.0"),
+                value_cell("70.0"),
+                value_cell("25.0W"),
+            ]);
+            table
+        };
 
-        let totals = bucket_network_totals(&metrics, 60);
-        assert!(!totals.is_empty());
+        let mut out = ReportOutput::new(ReportFormat::Json).columns(
+            vec!["avg".to_string(), "Max %".to_string()],
+            TableLayout::Wide,
+        );
+        out.table("Battery".to_string(), battery_table());
+        let block = &out.blocks[0];
+        assert_eq!(block.columns, ["Window", "Avg %", "Max %", "Avg charge W"]);
+        assert_eq!(block.rows, [["10-17 00:00", "55.0", "70.0", "25.0W"]]);
 
-        // Sum up all totals across buckets
-        let total_rx: f64 = totals.values().map(|s| s.rx_total).sum();
-        let total_tx: f64 = totals.values().map(|s| s.tx_total).sum();
+        // Nothing named `p99`, so the table stays whole
+        let mut table = battery_table();
+        table.select_columns(&["p99".to_string()]);
+        assert_eq!(table.shown_columns(), [true; 6]);
 
-        // Total RX: (3000-1000) + (7000-3000) = 2000 + 4000 = 6000
-        assert!((total_rx - 6000.0).abs() < 1e-6);
-        // Total TX: (1500-500) + (3000-1500) = 1000 + 1500 = 2500
-        assert!((total_tx - 2500.0).abs() < 1e-6);
+        let mut table = battery_table();
+        assert_eq!(table.fit_width(200), 0);
+        assert_eq!(table.fit_width(40), 3);
+        assert_eq!(
+            table.shown_columns(),
+            [true, true, true, false, false, false]
+        );
+        assert_eq!(table.fit_width(10), 1);
     }
 
     #[test]
-    fn bucket_stats_are_kept_per_source() {
-        let metrics = vec![
-            metric_sample_with_source(MetricKind::Temperature, "cpu0", 0.0, Some(30.0), json!({})),
-            metric_sample_with_source(MetricKind::Temperature, "cpu1", 0.0, Some(40.0), json!({})),
-            metric_sample_with_source(MetricKind::Temperature, "cpu0", 60.0, Some(50.0), json!({})),
-        ];
-
-        let buckets = bucket_stats_for_kind_by_source(&metrics, MetricKind::Temperature, 60);
-        assert_eq!(buckets.len(), 2);
-        let cpu0_count: usize = buckets
-            .get("cpu0")
-            .unwrap()
-            .values()
-            .map(|stats| stats.count)
-            .sum();
-        let cpu1_count: usize = buckets
-            .get("cpu1")
-            .unwrap()
-            .values()
-            .map(|stats| stats.count)
-            .sum();
-        assert_eq!(cpu0_count, 2);
-        assert_eq!(cpu1_count, 1);
+    fn overview_rows_follow_the_configured_panels() {
+        let columns = [OverviewColumn {
+            label: "24h",
+            avg_power_w: Some(7.5),
+            avg_cpu_percent: Some(12.0),
+            ..OverviewColumn::default()
+        }];
+        let labels = |panels: &[Panel]| -> Vec<String> {
+            overview_table(&columns, panels)
+                .table
+                .row_iter()
+                .map(|row| row.cell_iter().next().unwrap().content())
+                .collect()
+        };
+        assert_eq!(labels(&[]).len(), 4);
+        assert_eq!(
+            labels(&[Panel::Cpu, Panel::Temperature, Panel::Power]),
+            ["Avg CPU", "Avg power"]
+        );
     }
-
     #[test]
-    fn metrics_can_be_filtered_by_source() {
-        let metrics = vec![
-            metric_sample_with_source(MetricKind::CpuUsage, "cpu0", 0.0, Some(10.0), json!({})),
-            metric_sample_with_source(MetricKind::CpuUsage, "cpu1", 0.0, Some(20.0), json!({})),
-        ];
-
-        let filtered = filter_metrics_by_source(&metrics, &["cpu1".to_string()]);
-        assert_eq!(filtered.len(), 1);
-        assert_eq!(filtered[0].source, "cpu1");
+    fn label_columns_survive_column_selection() {
+        let mut stats = NumberStats::default();
+        stats.record_opt(Some(42.0));
+        let hours = [0, 1].map(|hour| Local.with_ymd_and_hms(2025, 10, 17, hour, 0, 0).unwrap());
+        let mut buckets = SourceBuckets::new();
+        buckets.insert(
+            "nvme0:Composite".to_string(),
+            hours.iter().map(|hour| (*hour, stats.clone())).collect(),
+        );
+        let mut table = source_stats_table("Sensor", 3600, &buckets, &BTreeMap::new());
+        table.select_columns(&["avg".to_string()]);
+        assert_eq!(table.shown_columns(), [true, true, false, true, false]);
     }
-
     #[test]
-    fn usage_stats_compute_percentage() {
-        let metrics = vec![metric_sample(
-            MetricKind::MemoryUsage,
-            0.0,
-            Some(2048.0),
-            json!({"total_bytes": 4096.0}),
-        )];
+    fn sections_select_and_order_report_blocks() {
+        let sections = |args: &[&str]| -> Result<Vec<ReportSection>, clap::Error> {
+            let cli = Cli::try_parse_from(["symmetri", "report"].iter().chain(args))?;
+            match cli.command {
+                Commands::Report { sections, .. } => Ok(sections),
+                _ => unreachable!(),
+            }
+        };
+        assert_eq!(
+            sections(&["--sections", "buckets,summary"]).unwrap(),
+            [ReportSection::Buckets, ReportSection::Summary]
+        );
+        assert!(sections(&["--sections", "summary,totals"]).is_err());
 
-        let stats = usage_stats_for_kind(&metrics, MetricKind::MemoryUsage);
-        assert_eq!(stats.used.count, 1);
-        assert!((stats.used.average().unwrap() - 2048.0).abs() < 1e-6);
-        assert!((stats.percent.average().unwrap() - 50.0).abs() < 1e-6);
+        let disk = |ts: f64| {
+            metric_sample_with_source(
+                MetricKind::DiskUsage,
+                "/",
+                ts,
+                Some(50.0),
+                serde_json::Value::Null,
+            )
+            .with_total_bytes(100.0)
+        };
+        let timeframe = crate::timeframe::build_timeframe(0, 0, 0, true).unwrap();
+        let mut report = ReportBuilder::new(timeframe)
+            .presets([ReportPreset::Disk])
+            .build_from_samples(&[disk(0.0), disk(60.0)])
+            .unwrap();
+        report.machines.push(crate::system_info::SystemSnapshot {
+            ts: 0.0,
+            info: crate::system_info::SystemInfo {
+                hostname: Some("laptop".to_string()),
+                ..Default::default()
+            },
+        });
+        let titles = |sections: &[ReportSection]| {
+            let mut out = ReportOutput::new(ReportFormat::Json);
+            print_report(&report, sections, &mut out);
+            out.blocks
+                .into_iter()
+                .map(|block| block.title)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            titles(&[ReportSection::Buckets, ReportSection::Summary]),
+            ["Disk stats (all)", "Disk mountpoints (all)"]
+        );
+        assert_eq!(
+            titles(&[ReportSection::Summary]),
+            ["Disk mountpoints (all)"]
+        );
+        assert_eq!(
+            titles(&[]),
+            ["Machine", "Disk mountpoints (all)", "Disk stats (all)"]
+        );
+        assert_eq!(
+            titles(&[ReportSection::Summary, ReportSection::Machine]),
+            ["Disk mountpoints (all)", "Machine"]
+        );
     }
 
     #[test]
-    fn battery_rate_buckets_capture_charging_segments() {
-        let metrics = vec![
-            battery_metric(0.0, MetricKind::BatteryEnergyNow, 10.0, "Charging"),
-            battery_metric(300.0, MetricKind::BatteryEnergyNow, 11.0, "Charging"),
-            battery_metric(600.0, MetricKind::BatteryEnergyNow, 12.5, "Charging"),
-        ];
-
-        let (discharge, charge) = battery_rate_buckets(&metrics, 300);
-
-        assert!(discharge.is_empty());
-        let first_bucket = bucket_start(metrics[1].ts, 300);
-        let second_bucket = bucket_start(metrics[2].ts, 300);
-        let first_rate = charge
-            .get(&first_bucket)
-            .and_then(NumberStats::average)
-            .unwrap();
-        let second_rate = charge
-            .get(&second_bucket)
-            .and_then(NumberStats::average)
-            .unwrap();
+    fn plain_histograms_are_ascii() {
+        let histogram = ValueHistogram {
+            kind: MetricKind::PowerDraw,
+            source: "BAT0".to_string(),
+            unit: Some("W".to_string()),
+            bins: vec![(4.0, 6.0, 3), (6.0, 8.0, 1), (8.0, 10.0, 0)],
+        };
+        let plain = fill_value_histogram_table(themed_table_with(TableTheme::Plain), &histogram)
+            .to_string();
+        assert!(plain.is_ascii(), "{plain}");
+        assert!(plain.contains("4.0-6.0 W\t3\t75.0%\t##############################"));
+        assert!(plain.contains("6.0-8.0 W\t1\t25.0%\t##########\n"));
 
-        assert!((first_rate - 12.0).abs() < 1e-6);
-        assert!((second_rate - 18.0).abs() < 1e-6);
+        let rounded =
+            fill_value_histogram_table(themed_table_with(TableTheme::Rounded), &histogram)
+                .to_string();
+        assert!(rounded.contains("4.0–6.0 W") && rounded.contains('█'));
     }
 
     #[test]
-    fn battery_rate_buckets_ignores_mixed_mode_segments() {
-        let metrics = vec![
-            battery_metric(0.0, MetricKind::BatteryEnergyNow, 50.0, "Discharging"),
-            battery_metric(300.0, MetricKind::BatteryEnergyNow, 49.0, "Discharging"),
-            battery_metric(600.0, MetricKind::BatteryEnergyNow, 50.0, "Charging"),
-            battery_metric(900.0, MetricKind::BatteryEnergyNow, 50.5, "Charging"),
-        ];
+    fn key_value_lines_name_every_cell() {
+        let summary = ReportBlock {
+            title: "Battery summary (last 6 hours)".to_string(),
+            columns: vec!["Metric".to_string(), "Value".to_string()],
+            rows: vec![vec!["Avg discharge power".to_string(), "7.9 W".to_string()]],
+        };
+        assert_eq!(
+            summary.key_value_lines(),
+            ["battery_summary.avg_discharge_power=7.9 W"]
+        );
+        let stats = ReportBlock {
+            title: "Temperature stats, coretemp (last 6 hours)".to_string(),
+            columns: ["Source", "Avg", "Max"].map(String::from).to_vec(),
+            rows: vec![["Package id 0", "51.0C", ""].map(String::from).to_vec()],
+        };
+        assert_eq!(
+            stats.key_value_lines(),
+            ["temperature_stats_coretemp.package_id_0.avg=51.0C"]
+        );
 
-        let (discharge, charge) = battery_rate_buckets(&metrics, 600);
+        let mut out = ReportOutput::new(ReportFormat::KeyValue).quiet(true);
+        out.note("No GPU samples available.".to_string());
+        assert!(out.notes.is_empty());
+    }
 
-        let discharge_bucket = bucket_start(metrics[1].ts, 600);
-        let charge_bucket = bucket_start(metrics[3].ts, 600);
-        assert!(discharge.contains_key(&discharge_bucket));
-        assert!(charge.contains_key(&charge_bucket));
+    #[test]
+    fn disk_forecast_line_reports_days_until_full() {
+        let day = 24.0 * 3600.0;
+        let gib = 1024.0 * 1024.0 * 1024.0;
+        let disk = |ts: f64, used: f64| {
+            metric_sample_with_source(
+                MetricKind::DiskUsage,
+                "/",
+                ts,
+                Some(used * gib),
+                serde_json::Value::Null,
+            )
+            .with_total_bytes(100.0 * gib)
+        };
+        let forecasts = disk_forecasts(&[disk(0.0, 60.0), disk(day, 61.0), disk(2.0 * day, 62.0)]);
+        assert_eq!(
+            disk_forecast_line(&forecasts[0]),
+            "At current growth (+1.0GiB/day), / will be full in ~38 days"
+        );
     }
 }
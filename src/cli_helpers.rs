@@ -1,7 +1,8 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, Local, TimeZone};
+use chrono::{DateTime, Datelike, Local, TimeZone};
 
 use crate::metrics::{MetricKind, MetricSample};
 use crate::timeframe::Timeframe;
@@ -71,6 +72,131 @@ pub fn bucket_start(ts: f64, bucket_seconds: i64) -> DateTime<Local> {
     Local.timestamp_opt(aligned, 0).unwrap()
 }
 
+pub fn local_datetime(ts: f64) -> DateTime<Local> {
+    Local.timestamp_opt(ts as i64, 0).unwrap()
+}
+
+/// Timestamp of local midnight on the first day of the month containing `ts`.
+pub fn month_start_timestamp(ts: f64) -> f64 {
+    let dt = local_datetime(ts);
+    Local
+        .with_ymd_and_hms(dt.year(), dt.month(), 1, 0, 0, 0)
+        .earliest()
+        .map(|start| start.timestamp() as f64)
+        .unwrap_or(ts)
+}
+
+pub fn format_bytes(value: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut val = value;
+    let mut unit = "B";
+    for next in &UNITS {
+        unit = next;
+        if val.abs() < 1024.0 || *next == "TiB" {
+            break;
+        }
+        val /= 1024.0;
+    }
+    if unit == "B" {
+        format!("{val:.0}{unit}")
+    } else {
+        format!("{val:.1}{unit}")
+    }
+}
+
+pub fn counter_delta(previous: Option<f64>, current: Option<f64>) -> f64 {
+    match (previous, current) {
+        (Some(prev), Some(next)) if next >= prev => next - prev,
+        _ => 0.0,
+    }
+}
+
+pub type SourceCounterBuckets = BTreeMap<String, BTreeMap<DateTime<Local>, f64>>;
+
+/// Increase of a cumulative counter kind per source and bucket; counter resets
+/// (e.g. after a reboot) contribute nothing for that interval.
+pub fn bucket_counter_deltas(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    bucket_seconds: i64,
+) -> SourceCounterBuckets {
+    let mut by_source: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        by_source.entry(&sample.source).or_default().push(sample);
+    }
+
+    let mut buckets: SourceCounterBuckets = BTreeMap::new();
+    for (source, mut samples) in by_source {
+        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+        for window in samples.windows(2) {
+            if window[1].ts <= window[0].ts {
+                continue;
+            }
+            let delta = counter_delta(window[0].value, window[1].value);
+            *buckets
+                .entry(source.to_string())
+                .or_default()
+                .entry(bucket_start(window[1].ts, bucket_seconds))
+                .or_default() += delta;
+        }
+    }
+    buckets
+}
+
+/// Per-second rate of a cumulative counter kind, as samples of the same kind stamped at the
+/// end of each interval; counter resets are skipped.
+pub fn counter_rate_samples(metrics: &[MetricSample], kind: MetricKind) -> Vec<MetricSample> {
+    let mut by_source: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        by_source.entry(&sample.source).or_default().push(sample);
+    }
+
+    let mut rates = Vec::new();
+    for (_, mut samples) in by_source {
+        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+        for window in samples.windows(2) {
+            let (prev, next) = (window[0], window[1]);
+            let (Some(start), Some(end)) = (prev.value, next.value) else {
+                continue;
+            };
+            if next.ts <= prev.ts || end < start {
+                continue;
+            }
+            let mut rate = next.clone();
+            rate.value = Some((end - start) / (next.ts - prev.ts));
+            rates.push(rate);
+        }
+    }
+    rates
+}
+
+/// Container CPU time as a percentage of one core, from cumulative `usage_usec` counters.
+pub fn container_cpu_percent(metrics: &[MetricSample]) -> Vec<MetricSample> {
+    let mut rates = counter_rate_samples(metrics, MetricKind::ContainerCpu);
+    for sample in &mut rates {
+        sample.value = sample.value.map(|usec_per_sec| usec_per_sec / 10_000.0);
+        sample.unit = Some("%".to_string());
+    }
+    rates
+}
+
+/// Share of total CPU time spent in each idle state, from cumulative per-state `time` counters.
+pub fn cpu_idle_residency_percent(metrics: &[MetricSample]) -> Vec<MetricSample> {
+    let mut rates = counter_rate_samples(metrics, MetricKind::CpuIdleResidency);
+    rates.retain_mut(|sample| {
+        let cpus = sample.details.get("cpus").and_then(|v| v.as_f64());
+        let Some(cpus) = cpus.filter(|cpus| *cpus > 0.0) else {
+            return false;
+        };
+        sample.value = sample
+            .value
+            .map(|usec_per_sec| (usec_per_sec / (cpus * 10_000.0)).min(100.0));
+        sample.unit = Some("%".to_string());
+        true
+    });
+    rates
+}
+
 #[derive(Debug, Default, PartialEq)]
 pub struct AverageRates {
     pub discharge_w: Option<f64>,
@@ -142,18 +268,16 @@ pub fn average_rates<'a>(
 
 pub(crate) fn is_discharging(sample: &MetricSample) -> bool {
     sample
-        .details
-        .get("status")
-        .and_then(|v| v.as_str())
+        .status
+        .as_deref()
         .map(|s| s.eq_ignore_ascii_case("discharging"))
         .unwrap_or(true)
 }
 
 pub(crate) fn is_charging(sample: &MetricSample) -> bool {
     sample
-        .details
-        .get("status")
-        .and_then(|v| v.as_str())
+        .status
+        .as_deref()
         .map(|s| s.eq_ignore_ascii_case("charging"))
         .unwrap_or(false)
 }
@@ -222,7 +346,15 @@ mod tests {
             source: "BAT0".to_string(),
             value: Some(energy),
             unit: Some("Wh".to_string()),
-            details: json!({"status": status}),
+            details: serde_json::Value::Null,
+            rx_bytes: None,
+            tx_bytes: None,
+            total_bytes: None,
+            status: status.map(str::to_string),
+            histogram: None,
+            label: None,
+            host: None,
+            boot: None,
         }
     }
 
@@ -384,6 +516,54 @@ mod tests {
         assert_eq!(bucket.second(), 0);
     }
 
+    #[test]
+    fn month_start_is_first_day_midnight() {
+        let mid_month = Local.with_ymd_and_hms(2025, 3, 17, 14, 5, 0).unwrap();
+        let start = local_datetime(month_start_timestamp(mid_month.timestamp() as f64));
+        assert_eq!((start.year(), start.month(), start.day()), (2025, 3, 1));
+        assert_eq!((start.hour(), start.minute()), (0, 0));
+    }
+
+    #[test]
+    fn container_cpu_rates_skip_counter_resets() {
+        let cpu = |ts: f64, usec: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::ContainerCpu,
+                "web",
+                Some(usec),
+                Some("usec"),
+                json!({}),
+            )
+        };
+        let metrics = vec![
+            cpu(0.0, 0.0),
+            cpu(10.0, 5_000_000.0),
+            cpu(20.0, 1_000.0),
+            cpu(30.0, 2_001_000.0),
+        ];
+        let percent = container_cpu_percent(&metrics);
+        let values: Vec<(f64, f64)> = percent.iter().map(|s| (s.ts, s.value.unwrap())).collect();
+        assert_eq!(values, vec![(10.0, 50.0), (30.0, 20.0)]);
+    }
+
+    #[test]
+    fn idle_residency_is_share_of_all_cpus() {
+        let idle = |ts: f64, usec: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::CpuIdleResidency,
+                "C10",
+                Some(usec),
+                Some("usec"),
+                json!({ "cpus": 4 }),
+            )
+        };
+        let percent = cpu_idle_residency_percent(&[idle(0.0, 0.0), idle(10.0, 30_000_000.0)]);
+        assert_eq!(percent.len(), 1);
+        assert_eq!(percent[0].value, Some(75.0));
+    }
+
     #[test]
     fn all_time_uses_data_span_for_buckets() {
         use crate::timeframe::build_timeframe;
@@ -1,13 +1,29 @@
+use std::fs;
+use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::{info, warn};
 
+use crate::aggregate::aggregate_multi_device_metrics;
+use crate::config::{Config, LowPowerConfig};
+use crate::data_cap;
 use crate::db;
-use crate::metrics::{self, MetricSample};
-use crate::sysfs::{create_battery_metrics, find_battery_paths, read_battery};
+use crate::derived;
+use crate::metrics::{self, Collector, MetricKind, MetricSample};
+use crate::power_profile;
+use crate::push;
+use crate::storage::{self, Storage};
+use crate::sysfs::{
+    ac_online_samples, charger_power_samples, create_battery_metrics, find_battery_paths,
+    find_peripheral_battery_paths, peripheral_battery_metric, read_battery,
+};
+use crate::system_info;
+use crate::transform;
 
 pub fn default_db_path() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("~"));
@@ -32,13 +48,71 @@ pub fn resolve_db_path(db_path: Option<&Path>) -> PathBuf {
     default_db_path()
 }
 
-pub fn collect_once(db_path: Option<&Path>, sysfs_root: Option<&Path>) -> Result<i32> {
-    let resolved_db = resolve_db_path(db_path);
-    let mut conn = db::init_db_connection(&resolved_db)?;
+pub fn collect_once(
+    db_path: Option<&Path>,
+    sysfs_root: Option<&Path>,
+    config: &Config,
+) -> Result<i32> {
+    let mut store = storage::open(&resolve_db_path(db_path))?;
+    snapshot_system_info(store.as_mut(), sysfs_root, config);
+    let mut samples = collect(sysfs_root, config, 0)?;
+    write_samples(store.as_mut(), config, &mut samples, None)?;
+    Ok(0)
+}
 
+/// Store the machine identity at collector start. Failing to read it must not stop the
+/// collection, so errors are only logged.
+fn snapshot_system_info(store: &mut dyn Storage, sysfs_root: Option<&Path>, config: &Config) {
     let root = sysfs_root.unwrap_or_else(|| Path::new("/sys/class/power_supply"));
-    let battery_paths = find_battery_paths(root);
-    if battery_paths.is_empty() {
+    let batteries = config
+        .collect
+        .is_enabled(Collector::Battery)
+        .then_some(root);
+    let info = system_info::read_system_info(Path::new("/proc"), batteries);
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    match store.record_system_info(&info, ts) {
+        Ok(true) => info!(
+            "Recorded system info for {}",
+            info.hostname.unwrap_or_default()
+        ),
+        Ok(false) => {}
+        Err(err) => warn!("Failed to record system info: {err}"),
+    }
+}
+
+/// Why the `[collect.low_power]` profile applies to this run, if it does. Battery samples
+/// are collected first, so the current charge is known before the other groups run.
+fn low_power_reason(config: &LowPowerConfig, samples: &[MetricSample], ts: f64) -> Option<String> {
+    let on_ac = samples
+        .iter()
+        .any(|m| m.kind == MetricKind::AcOnline && m.value == Some(1.0));
+    if let (Some(threshold), false) = (config.battery_below, on_ac) {
+        let percent = aggregate_multi_device_metrics(samples)
+            .into_iter()
+            .find(|m| m.kind == MetricKind::BatteryPercentage)
+            .and_then(|m| m.value);
+        if let Some(percent) = percent.filter(|percent| *percent < threshold) {
+            return Some(format!("battery at {percent:.0}%"));
+        }
+    }
+    (config.power_saver && power_profile::power_saver_active(ts))
+        .then(|| "power-saver profile".to_string())
+}
+
+/// One collection, tagged with the host. `run` counts collections of the loop, for groups
+/// the low power profile only runs every few collections.
+fn collect(sysfs_root: Option<&Path>, config: &Config, run: u64) -> Result<Vec<MetricSample>> {
+    let collect = &config.collect;
+    let root = sysfs_root.unwrap_or_else(|| Path::new("/sys/class/power_supply"));
+    let battery_paths = if collect.is_enabled(Collector::Battery) {
+        find_battery_paths(root)
+    } else {
+        Vec::new()
+    };
+    if battery_paths.is_empty() && collect.is_enabled(Collector::Battery) {
         warn!("No batteries found in sysfs; collecting other metrics only");
     }
 
@@ -46,59 +120,358 @@ pub fn collect_once(db_path: Option<&Path>, sysfs_root: Option<&Path>) -> Result
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs_f64();
+    let started = Instant::now();
+    let cpu_before = metrics::process_cpu_time();
+    let mut timings = Vec::new();
 
     let mut metric_samples: Vec<MetricSample> = Vec::new();
-    let mut battery_count = 0;
-    for path in battery_paths {
-        let reading = read_battery(&path);
-        let battery_metrics = create_battery_metrics(&reading, ts);
-        if !battery_metrics.is_empty() {
-            battery_count += 1;
-            let source_name = path
-                .file_name()
-                .map(|p| p.to_string_lossy())
-                .unwrap_or_else(|| path.to_string_lossy());
-            info!(
-                "Logged record for {}: percent={:.2} health={:.2}",
-                source_name,
-                reading.percentage.unwrap_or(0.0),
-                reading.health_pct.unwrap_or(0.0)
-            );
-        }
-        metric_samples.extend(battery_metrics);
+    if collect.is_enabled(Collector::Battery) {
+        let root = root.to_path_buf();
+        metric_samples.extend(metrics::timed(
+            &mut timings,
+            Collector::Battery,
+            move || {
+                let mut samples = Vec::new();
+                for path in battery_paths {
+                    let reading = read_battery(&path);
+                    let battery_metrics = create_battery_metrics(&reading, ts);
+                    if !battery_metrics.is_empty() {
+                        let source_name = path
+                            .file_name()
+                            .map(|p| p.to_string_lossy())
+                            .unwrap_or_else(|| path.to_string_lossy());
+                        info!(
+                            "Logged record for {}: percent={:.2} health={:.2}",
+                            source_name,
+                            reading.percentage.unwrap_or(0.0),
+                            reading.health_pct.unwrap_or(0.0)
+                        );
+                    }
+                    samples.extend(battery_metrics);
+                }
+                samples.extend(ac_online_samples(&root, ts));
+                samples.extend(charger_power_samples(&root, ts));
+                samples
+            },
+        ));
+    }
+    let mut batteries: Vec<&str> = metric_samples
+        .iter()
+        .filter(|m| m.kind.is_battery())
+        .map(|m| m.source.as_str())
+        .collect();
+    batteries.sort_unstable();
+    batteries.dedup();
+    let battery_count = batteries.len();
+    let low_power = low_power_reason(&collect.low_power, &metric_samples, ts);
+    if let Some(reason) = &low_power {
+        info!("Low power ({reason}): holding back the configured collectors");
+    }
+    let enabled = |collector: Collector| {
+        collect.is_enabled(collector)
+            && !(low_power.is_some() && collect.low_power.holds_back(collector, run))
+    };
+    if enabled(Collector::Peripherals) {
+        let root = root.to_path_buf();
+        metric_samples.extend(metrics::timed(
+            &mut timings,
+            Collector::Peripherals,
+            move || {
+                find_peripheral_battery_paths(&root)
+                    .iter()
+                    .filter_map(|path| peripheral_battery_metric(path, ts))
+                    .collect()
+            },
+        ));
     }
 
-    metric_samples.extend(metrics::collect_metrics(ts));
-    db::insert_metric_samples_with_conn(&mut conn, &metric_samples)?;
-
+    metric_samples.extend(metrics::collect_metrics(
+        ts,
+        enabled,
+        &config.network.interfaces,
+        &mut timings,
+    ));
+    metric_samples.extend(metrics::collector_timeout_samples(ts, &timings));
+    let mut metric_samples = transform::apply_transforms(&config.transforms, metric_samples);
+    let materialized = derived::evaluate(
+        config.derived.iter().filter(|metric| metric.materialize),
+        &metric_samples,
+    );
+    metric_samples.extend(materialized);
+    if collect.is_enabled(Collector::Stats) {
+        let cpu = metrics::process_cpu_time().saturating_sub(cpu_before);
+        let written = metric_samples.len();
+        metric_samples.extend(metrics::collector_stats_samples(
+            ts,
+            &timings,
+            started.elapsed(),
+            cpu,
+            written,
+        ));
+    }
+    let host = collect
+        .host
+        .clone()
+        .or_else(|| system_info::local_host(Path::new("/proc"), Path::new("/etc/machine-id")));
+    let boot = system_info::boot_id(Path::new("/proc"));
+    for sample in &mut metric_samples {
+        sample.host = host.clone();
+        sample.boot = boot.clone();
+    }
     if !metric_samples.is_empty() {
         info!(
-            "Logged {} metric records ({} batteries)",
+            "Collected {} metric records ({} batteries)",
             metric_samples.len(),
             battery_count
         );
     }
-    Ok(0)
+    Ok(metric_samples)
+}
+
+/// Write `samples` (one or more collections) in one transaction, then update the rollups,
+/// check the data cap and push them to the configured sinks when storing locally. `samples`
+/// is left empty once they are written, and untouched when writing them fails.
+/// `interval_seconds` is the loop period, recorded per stored kind so reports can tell
+/// undersampled kinds apart.
+fn write_samples(
+    store: &mut dyn Storage,
+    config: &Config,
+    samples: &mut Vec<MetricSample>,
+    interval_seconds: Option<u64>,
+) -> Result<()> {
+    let Some(ts) = samples.iter().map(|m| m.ts).max_by(f64::total_cmp) else {
+        return Ok(());
+    };
+    let skip_repeats = &config.collect.skip_repeats;
+    let stored = store.insert_samples(samples, skip_repeats)?;
+    let samples = std::mem::take(samples);
+    // A server store is the shared destination already, and keeps its own retention
+    if let Some(conn) = store.sqlite_mut() {
+        db::update_rollups(conn, ts)?;
+        if config.collect.archive_monthly {
+            let moved = db::rotate_archives(conn, ts)?;
+            if moved > 0 {
+                info!("Archived {moved} samples from past months");
+            }
+        }
+        let host = samples.iter().find_map(|m| m.host.as_deref());
+        if let Err(err) = data_cap::check(conn, config, host, ts) {
+            warn!("Failed to check the data cap: {err:#}");
+        }
+        if let Err(err) = push::push_influx(conn, &config.export.influx, &samples) {
+            warn!("Failed to push samples to InfluxDB: {err:#}");
+        }
+        #[cfg(feature = "remote-write")]
+        if let Err(err) = push::push_remote_write(conn, &config.export.remote_write, &samples) {
+            warn!("Failed to push samples over remote write: {err:#}");
+        }
+    }
+    if let Some(interval) = interval_seconds {
+        // Kinds with skipped repeats would look undersampled against their interval
+        let mut kinds: Vec<&str> = samples
+            .iter()
+            .filter(|m| !skip_repeats.contains(&m.kind))
+            .map(|m| m.kind.as_str())
+            .collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        store.record_collection_intervals(&kinds, interval as f64, ts)?;
+    }
+    info!("Logged {stored} metric records");
+    Ok(())
+}
+
+/// Set by SIGTERM/SIGINT while the loop buffers samples, so they are written before exiting.
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_stop(_signal: libc::c_int) {
+    STOP_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Sleep `seconds`, waking early when a stop was requested. Returns whether it was.
+fn sleep_unless_stopped(seconds: u64) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    while !STOP_REQUESTED.load(Ordering::SeqCst) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return false;
+        }
+        thread::sleep(left.min(Duration::from_millis(500)));
+    }
+    true
 }
 
 pub fn collect_loop(
     interval_seconds: u64,
     db_path: Option<&Path>,
     sysfs_root: Option<&Path>,
+    config: &Config,
+) -> Result<()> {
+    let location = resolve_db_path(db_path);
+    let mut store = Some(storage::open(&location)?);
+    if let Some(store) = &mut store {
+        snapshot_system_info(store.as_mut(), sysfs_root, config);
+    }
+    let exit = buffered_loop(
+        interval_seconds,
+        config,
+        |run| collect(sysfs_root, config, run),
+        |pending| {
+            // The store stays open across writes; after a failure (say a restarted server)
+            // the next write opens it afresh
+            let open = match store.take() {
+                Some(open) => open,
+                None => storage::open(&location)?,
+            };
+            let written = write_samples(
+                store.insert(open).as_mut(),
+                config,
+                pending,
+                Some(interval_seconds),
+            );
+            if written.is_err() {
+                store = None;
+            }
+            written
+        },
+        resident_memory_bytes,
+    )?;
+    match exit {
+        LoopExit::Stopped => Ok(()),
+        LoopExit::OverRssCap { rss, cap } => Err(restart_over_rss_cap(rss, cap)),
+    }
+}
+
+/// Why [`buffered_loop`] returned; the buffered samples are stored either way.
+#[derive(Debug, PartialEq)]
+enum LoopExit {
+    Stopped,
+    OverRssCap { rss: u64, cap: u64 },
+}
+
+/// Run `collect_run` every `interval_seconds`, handing the buffered samples to `write`
+/// after each full batch of `flush_every` collections, on SIGTERM/SIGINT and before a
+/// restart for going over `max_rss` (as read by `rss`). A batch `write` fails on stays
+/// buffered for the next one.
+fn buffered_loop(
+    interval_seconds: u64,
+    config: &Config,
+    mut collect_run: impl FnMut(u64) -> Result<Vec<MetricSample>>,
+    mut write: impl FnMut(&mut Vec<MetricSample>) -> Result<()>,
+    rss: impl Fn() -> Option<u64>,
+) -> Result<LoopExit> {
+    let mut run = 0;
+    let mut pending = Vec::new();
+    if !config.collect.flushes_after(0) {
+        let handler = request_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // SAFETY: the handler only stores to an atomic
+        unsafe {
+            libc::signal(libc::SIGTERM, handler);
+            libc::signal(libc::SIGINT, handler);
+        }
+    }
+    loop {
+        match collect_run(run) {
+            Ok(samples) => pending.extend(samples),
+            Err(err) => {
+                if let Err(write_err) = write(&mut pending) {
+                    warn!(
+                        "Failed to write {} buffered samples: {write_err:#}",
+                        pending.len()
+                    );
+                }
+                return Err(err);
+            }
+        }
+        if config.collect.flushes_after(run) {
+            if let Err(err) = write(&mut pending) {
+                warn!(
+                    "Failed to write {} buffered samples, retrying with the next batch: {err:#}",
+                    pending.len()
+                );
+            }
+        }
+        if let (Some(cap), Some(rss)) = (config.collect.max_rss, rss()) {
+            if rss > cap {
+                if let Err(err) = write(&mut pending) {
+                    warn!(
+                        "Failed to write {} buffered samples before restarting: {err:#}",
+                        pending.len()
+                    );
+                }
+                return Ok(LoopExit::OverRssCap { rss, cap });
+            }
+        }
+        if sleep_unless_stopped(interval_seconds) {
+            info!("Stopping; writing {} buffered samples", pending.len());
+            write(&mut pending)?;
+            return Ok(LoopExit::Stopped);
+        }
+        run += 1;
+    }
+}
+
+/// Collect every `interval_seconds` without storing anything, handing each collection to
+/// `show`, until SIGTERM/SIGINT.
+pub fn collect_live(
+    interval_seconds: u64,
+    sysfs_root: Option<&Path>,
+    config: &Config,
+    mut show: impl FnMut(Vec<MetricSample>) -> Result<()>,
 ) -> Result<()> {
+    let handler = request_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only stores to an atomic
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+    let mut run = 0;
     loop {
-        let exit_code = collect_once(db_path, sysfs_root)?;
-        if exit_code != 0 {
-            warn!("Collection returned exit code {exit_code}");
+        show(collect(sysfs_root, config, run)?)?;
+        if sleep_unless_stopped(interval_seconds) {
+            return Ok(());
         }
-        thread::sleep(Duration::from_secs(interval_seconds));
+        run += 1;
     }
 }
 
+/// `VmRSS` of a `/proc/<pid>/status` file, in bytes.
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn resident_memory_bytes() -> Option<u64> {
+    parse_vm_rss(&fs::read_to_string("/proc/self/status").ok()?)
+}
+
+/// Replace the process with a fresh copy of itself, so a slow leak cannot grow a
+/// month-long collector without bound. The buffered collections are written before this
+/// point; only returns (with the error) when the exec fails.
+fn restart_over_rss_cap(rss: u64, cap: u64) -> anyhow::Error {
+    warn!(
+        "Collector resident memory {} MiB exceeds max_rss {} MiB; restarting",
+        rss / (1024 * 1024),
+        cap / (1024 * 1024)
+    );
+    log::logger().flush();
+    let program = match std::env::current_exe() {
+        Ok(program) => program,
+        Err(err) => return anyhow!("cannot restart: executable path unknown: {err}"),
+    };
+    let err = Command::new(&program)
+        .args(std::env::args_os().skip(1))
+        .exec();
+    anyhow!("failed to restart {}: {err}", program.display())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
     use std::env;
+    use std::sync::Mutex;
 
     struct EnvGuard {
         key: &'static str,
@@ -123,6 +496,176 @@ mod tests {
         }
     }
 
+    /// The loop tests share the stop flag and the signal handlers.
+    static LOOP_LOCK: Mutex<()> = Mutex::new(());
+
+    fn buffering_config(flush_every: u64, max_rss: Option<u64>) -> Config {
+        let mut config = Config::default();
+        config.collect.flush_every = Some(flush_every);
+        config.collect.max_rss = max_rss;
+        config
+    }
+
+    fn cpu_sample(run: u64) -> Vec<MetricSample> {
+        vec![MetricSample::new(
+            run as f64,
+            MetricKind::CpuUsage,
+            "cpu",
+            Some(5.0),
+            None,
+            serde_json::Value::Null,
+        )]
+    }
+
+    #[test]
+    fn stop_signals_write_the_buffered_samples() {
+        let _lock = LOOP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        for signal in [libc::SIGTERM, libc::SIGINT] {
+            STOP_REQUESTED.store(false, Ordering::SeqCst);
+            let tmp = tempfile::tempdir().unwrap();
+            let db_path = tmp.path().join("metrics.db");
+            let mut store = storage::open(&db_path).unwrap();
+            let config = buffering_config(10, None);
+            let exit = buffered_loop(
+                0,
+                &config,
+                |run| {
+                    if run == 1 {
+                        // SAFETY: the loop installed a handler that only sets the stop flag
+                        unsafe { libc::raise(signal) };
+                    }
+                    Ok(cpu_sample(run))
+                },
+                |pending| write_samples(store.as_mut(), &config, pending, Some(0)),
+                || None,
+            )
+            .unwrap();
+            assert_eq!(exit, LoopExit::Stopped);
+            assert_eq!(db::count_metric_samples(&db_path, None).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn going_over_max_rss_writes_the_buffered_samples_first() {
+        let _lock = LOOP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        STOP_REQUESTED.store(false, Ordering::SeqCst);
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        let mut store = storage::open(&db_path).unwrap();
+        let config = buffering_config(10, Some(64 * 1024 * 1024));
+        let rss = Cell::new(None);
+        let exit = buffered_loop(
+            0,
+            &config,
+            |run| {
+                if run == 2 {
+                    rss.set(Some(128 * 1024 * 1024));
+                }
+                Ok(cpu_sample(run))
+            },
+            |pending| write_samples(store.as_mut(), &config, pending, Some(0)),
+            || rss.get(),
+        )
+        .unwrap();
+        assert_eq!(
+            exit,
+            LoopExit::OverRssCap {
+                rss: 128 * 1024 * 1024,
+                cap: 64 * 1024 * 1024
+            }
+        );
+        assert_eq!(db::count_metric_samples(&db_path, None).unwrap(), 3);
+    }
+
+    #[test]
+    fn full_batches_are_written_without_waiting_for_a_stop() {
+        let _lock = LOOP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        STOP_REQUESTED.store(false, Ordering::SeqCst);
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        let mut store = storage::open(&db_path).unwrap();
+        let config = buffering_config(3, None);
+        let mut stored_before = Vec::new();
+        buffered_loop(
+            0,
+            &config,
+            |run| {
+                stored_before.push(db::count_metric_samples(&db_path, None)?);
+                if run == 4 {
+                    STOP_REQUESTED.store(true, Ordering::SeqCst);
+                }
+                Ok(cpu_sample(run))
+            },
+            |pending| write_samples(store.as_mut(), &config, pending, Some(0)),
+            || None,
+        )
+        .unwrap();
+        assert_eq!(stored_before, [0, 0, 0, 3, 3]);
+        assert_eq!(db::count_metric_samples(&db_path, None).unwrap(), 5);
+    }
+
+    #[test]
+    fn a_failed_write_keeps_the_batch_for_the_next_one() {
+        let _lock = LOOP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        STOP_REQUESTED.store(false, Ordering::SeqCst);
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        let mut store = storage::open(&db_path).unwrap();
+        let config = buffering_config(2, None);
+        let mut writes = 0;
+        let mut stored_before = Vec::new();
+        buffered_loop(
+            0,
+            &config,
+            |run| {
+                stored_before.push(db::count_metric_samples(&db_path, None)?);
+                if run == 4 {
+                    STOP_REQUESTED.store(true, Ordering::SeqCst);
+                }
+                Ok(cpu_sample(run))
+            },
+            |pending| {
+                writes += 1;
+                if writes == 1 {
+                    anyhow::bail!("database is locked");
+                }
+                write_samples(store.as_mut(), &config, pending, Some(0))
+            },
+            || None,
+        )
+        .unwrap();
+        assert_eq!(stored_before, [0, 0, 0, 0, 4]);
+        assert_eq!(db::count_metric_samples(&db_path, None).unwrap(), 5);
+    }
+
+    #[test]
+    fn vm_rss_is_read_from_proc_status() {
+        let status = "Name:\tsymmetri\nVmPeak:\t  20000 kB\nVmRSS:\t   12288 kB\nThreads:\t1\n";
+        assert_eq!(parse_vm_rss(status), Some(12288 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn low_power_applies_on_battery_below_the_threshold() {
+        let config = LowPowerConfig {
+            battery_below: Some(20.0),
+            ..LowPowerConfig::default()
+        };
+        let sample = |kind: MetricKind, source: &str, value: f64| {
+            MetricSample::new(1.0, kind, source, Some(value), None, serde_json::json!({}))
+        };
+        let mut samples = vec![
+            sample(MetricKind::BatteryPercentage, "BAT0", 10.0),
+            sample(MetricKind::BatteryPercentage, "BAT1", 20.0),
+        ];
+        assert_eq!(
+            low_power_reason(&config, &samples, 1.0).as_deref(),
+            Some("battery at 15%")
+        );
+        samples.push(sample(MetricKind::AcOnline, "AC", 1.0));
+        assert_eq!(low_power_reason(&config, &samples, 1.0), None);
+    }
+
     #[test]
     fn resolve_db_path_prefers_argument() {
         let _guard = EnvGuard::set("SYMMETRI_DB", "/tmp/should_not_use.db");
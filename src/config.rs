@@ -0,0 +1,849 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
+
+use crate::derived::DerivedMetric;
+use crate::metrics::{Collector, MetricKind};
+use crate::report::{source_matches, CpuDetail, ReportPreset, ReportSection};
+use crate::status::StatusMetric;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Arguments used when `symmetri` is run without a subcommand
+    #[serde(deserialize_with = "deserialize_opt_command_line")]
+    pub default_command: Option<Vec<String>>,
+    /// Shorthand subcommands, e.g. `week = "report --days 7 --preset all --graph"`
+    #[serde(deserialize_with = "deserialize_command_line_map")]
+    pub aliases: BTreeMap<String, Vec<String>>,
+    pub collect: CollectConfig,
+    /// Metrics computed from the collected kinds, e.g. `net_power = "power_draw - 2"`
+    #[serde(deserialize_with = "deserialize_derived_metrics")]
+    pub derived: Vec<DerivedMetric>,
+    /// `[[transform]]` rules applied to samples before they are stored
+    #[serde(rename = "transform")]
+    pub transforms: Vec<Transform>,
+    pub report: ReportConfig,
+    pub network: NetworkConfig,
+    pub alerts: AlertsConfig,
+    pub status: StatusConfig,
+    pub viewer: ViewerConfig,
+    pub export: ExportConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CollectConfig {
+    /// Collector groups to skip, e.g. `["containers", "gpu"]`
+    pub disabled: Vec<Collector>,
+    /// Resident memory above which `collect --interval` restarts itself, e.g. `"64MiB"`
+    #[serde(deserialize_with = "deserialize_opt_byte_size")]
+    pub max_rss: Option<u64>,
+    pub low_power: LowPowerConfig,
+    /// Name stored with every sample (the hostname, else the machine id, when unset)
+    pub host: Option<String>,
+    /// Kinds whose samples are only stored when they differ from the previous one of the
+    /// same source, e.g. `["disk_usage", "battery_health"]`
+    #[serde(deserialize_with = "deserialize_metric_kinds")]
+    pub skip_repeats: Vec<MetricKind>,
+    /// `collect --interval` writes its samples every this many collections (default 1)
+    pub flush_every: Option<u64>,
+    /// Move samples of past months into `metrics-YYYY-MM.db` files next to the database
+    pub archive_monthly: bool,
+}
+
+impl CollectConfig {
+    pub fn is_enabled(&self, collector: Collector) -> bool {
+        !self.disabled.contains(&collector)
+    }
+
+    /// Whether collection number `run` (from 0) of the loop writes the buffered samples.
+    pub fn flushes_after(&self, run: u64) -> bool {
+        (run + 1).is_multiple_of(self.flush_every.unwrap_or(1).max(1))
+    }
+}
+
+/// `[collect.low_power]`: collector groups to pause or thin out while the battery is low
+/// or the power-saver profile is on, so monitoring costs less when power is scarce.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LowPowerConfig {
+    /// Battery percentage below which the profile applies while on battery
+    pub battery_below: Option<f64>,
+    /// Also apply while the power profile is power-saver
+    pub power_saver: bool,
+    /// Groups skipped entirely
+    pub pause: Vec<Collector>,
+    /// Groups run only every `reduce_every`-th collection
+    pub reduce: Vec<Collector>,
+    /// Defaults to every 5th collection
+    pub reduce_every: Option<u64>,
+}
+
+impl LowPowerConfig {
+    /// Whether `collector` sits out collection number `run` while the profile applies.
+    pub fn holds_back(&self, collector: Collector, run: u64) -> bool {
+        self.pause.contains(&collector)
+            || (self.reduce.contains(&collector)
+                && !run.is_multiple_of(self.reduce_every.unwrap_or(5).max(1)))
+    }
+}
+
+/// Defaults for `symmetri report` when the matching flags are not given.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReportConfig {
+    /// Presets rendered without `--preset`
+    pub presets: Vec<ReportPreset>,
+    /// Blocks printed without `--sections`, in order, e.g. `["summary", "events"]`
+    pub sections: Vec<ReportSection>,
+    /// Window without `--hours/--days/--months/--all`, e.g. `24h`, `7d` or `all`
+    pub timeframe: Option<String>,
+    /// Always save an auto-named graph image
+    pub graph: bool,
+    /// Directory for auto-named graph images (defaults to the current directory)
+    pub graph_dir: Option<PathBuf>,
+    /// Always show the chart inline after the report
+    pub graph_inline: bool,
+    /// Temperature sources to show, e.g. `["coretemp:*", "nvme0:Composite"]` (all when empty)
+    pub temperature_sensors: Vec<String>,
+    /// Kinds drawn as step lines in graphs, e.g. `["disk_usage"]`
+    #[serde(deserialize_with = "deserialize_metric_kinds")]
+    pub step_kinds: Vec<MetricKind>,
+    /// Always shade the min–max band around averaged graph lines
+    pub bands: bool,
+    /// `aggregate` or `per-core` CPU usage without `--cpu-detail` (both when unset)
+    pub cpu_detail: Option<CpuDetail>,
+    /// Width of the bucket tables, e.g. `30m`, `2h` or `1d` (picked from the window when unset)
+    #[serde(deserialize_with = "deserialize_opt_bucket")]
+    pub bucket: Option<i64>,
+    /// Sources to keep without `--sensor`, e.g. `["BAT0", "coretemp:*"]` (all when empty)
+    pub sources: Vec<String>,
+    /// `[report.profiles.<name>]` tables selected with `report --profile <name>`
+    pub profiles: BTreeMap<String, ReportProfile>,
+}
+
+/// A named set of report defaults; each key set here replaces the one from `[report]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReportProfile {
+    pub presets: Option<Vec<ReportPreset>>,
+    pub sections: Option<Vec<ReportSection>>,
+    pub timeframe: Option<String>,
+    #[serde(deserialize_with = "deserialize_opt_bucket")]
+    pub bucket: Option<i64>,
+    pub sources: Option<Vec<String>>,
+    pub graph: Option<bool>,
+    pub graph_dir: Option<PathBuf>,
+    pub graph_inline: Option<bool>,
+    pub temperature_sensors: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_opt_metric_kinds")]
+    pub step_kinds: Option<Vec<MetricKind>>,
+    pub bands: Option<bool>,
+    pub cpu_detail: Option<CpuDetail>,
+}
+
+impl ReportConfig {
+    /// These defaults with the keys of profile `name` applied on top.
+    pub fn with_profile(&self, name: &str) -> Result<ReportConfig> {
+        let Some(profile) = self.profiles.get(name) else {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            anyhow::bail!(
+                "unknown report profile `{name}`; defined profiles: {}",
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            );
+        };
+        let profile = profile.clone();
+        let mut merged = self.clone();
+        merged.presets = profile.presets.unwrap_or(merged.presets);
+        merged.sections = profile.sections.unwrap_or(merged.sections);
+        merged.timeframe = profile.timeframe.or(merged.timeframe);
+        merged.bucket = profile.bucket.or(merged.bucket);
+        merged.sources = profile.sources.unwrap_or(merged.sources);
+        merged.graph = profile.graph.unwrap_or(merged.graph);
+        merged.graph_dir = profile.graph_dir.or(merged.graph_dir);
+        merged.graph_inline = profile.graph_inline.unwrap_or(merged.graph_inline);
+        merged.temperature_sensors = profile
+            .temperature_sensors
+            .unwrap_or(merged.temperature_sensors);
+        merged.step_kinds = profile.step_kinds.unwrap_or(merged.step_kinds);
+        merged.bands = profile.bands.unwrap_or(merged.bands);
+        merged.cpu_detail = profile.cpu_detail.or(merged.cpu_detail);
+        Ok(merged)
+    }
+}
+
+/// `symmetri status` settings for status bars.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StatusConfig {
+    /// Metrics cycled through by clicking, in order (all when empty)
+    pub metrics: Vec<StatusMetric>,
+    /// Metrics shown side by side by `status --tmux` (battery and temperature when empty)
+    pub tmux_metrics: Vec<StatusMetric>,
+    /// Command started on right click, e.g. `"foot -e symmetri report --preset all"`
+    #[serde(deserialize_with = "deserialize_opt_command_line")]
+    pub open_command: Option<Vec<String>>,
+}
+
+/// `[viewer]`: how output meant to be watched looks and refreshes.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ViewerConfig {
+    /// Seconds between lines of `status --watch` and redraws of `live` when no interval is
+    /// given
+    pub refresh: Option<u64>,
+    /// Border style of report tables
+    pub theme: TableTheme,
+    /// What `live` and `overview` show, in this order (everything they can when empty)
+    pub panels: Vec<Panel>,
+}
+
+/// A reading the `live` and `overview` views can show; each view skips the panels it has
+/// nothing for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Panel {
+    /// CPU usage
+    Cpu,
+    /// Memory usage (`live`)
+    Memory,
+    /// Power draw
+    Power,
+    /// Battery charge (`live`) and lowest health (`overview`)
+    Battery,
+    /// Temperatures (`live`)
+    Temperature,
+    /// Data transferred (`overview`)
+    Network,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableTheme {
+    /// Rounded box-drawing borders
+    #[default]
+    Rounded,
+    /// `+`, `-` and `|` borders for terminals without box-drawing glyphs
+    Ascii,
+    /// Column gaps only, no borders
+    Borderless,
+    /// Tab-separated cells without borders, alignment or color, as with `--plain`
+    Plain,
+}
+
+/// Remote sinks the collector pushes every run's samples to.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub influx: InfluxConfig,
+    pub remote_write: RemoteWriteConfig,
+}
+
+/// `[export.influx]`: an InfluxDB write endpoint taking line protocol.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct InfluxConfig {
+    /// Write URL, e.g. `http://localhost:8086/api/v2/write?org=home&bucket=symmetri`
+    pub url: Option<String>,
+    /// API token sent as `Authorization: Token ...`
+    pub token: Option<String>,
+    /// Batches kept while the endpoint is unreachable; the oldest are dropped past this
+    pub max_pending: usize,
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        InfluxConfig {
+            url: None,
+            token: None,
+            max_pending: 1000,
+        }
+    }
+}
+
+/// `[export.remote_write]`: a Prometheus remote-write endpoint, used with the `remote-write`
+/// feature.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RemoteWriteConfig {
+    /// Push URL, e.g. `http://localhost:8428/api/v1/write`
+    pub url: Option<String>,
+    /// Sent as `Authorization: Bearer ...`
+    pub token: Option<String>,
+    /// Batches kept while the endpoint is unreachable; the oldest are dropped past this
+    pub max_pending: usize,
+}
+
+impl Default for RemoteWriteConfig {
+    fn default() -> Self {
+        RemoteWriteConfig {
+            url: None,
+            token: None,
+            max_pending: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub data_cap: DataCapConfig,
+    pub interfaces: InterfaceFilter,
+}
+
+/// `[network.interfaces]`: which interfaces are collected. Physical ones are unless denied;
+/// virtual ones (loopback, container veths and bridges, tun/tap, ifb and other
+/// `/sys/devices/virtual` devices) only when allowed.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct InterfaceFilter {
+    /// Collect only these interfaces, virtual or not (`*` matches any text)
+    pub allow: Vec<String>,
+    /// Never collect these
+    pub deny: Vec<String>,
+    /// Collect virtual interfaces as well
+    pub include_virtual: bool,
+}
+
+impl InterfaceFilter {
+    pub fn includes(&self, iface: &str, is_virtual: bool) -> bool {
+        let matches = |patterns: &[String]| patterns.iter().any(|p| source_matches(p, iface));
+        if matches(&self.deny) {
+            return false;
+        }
+        if !self.allow.is_empty() {
+            return matches(&self.allow);
+        }
+        self.include_virtual || !is_virtual
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DataCapConfig {
+    /// Monthly cap applied to every interface without an explicit entry
+    #[serde(deserialize_with = "deserialize_opt_byte_size")]
+    pub limit: Option<u64>,
+    /// Per-interface monthly caps (e.g. `wwan0 = "20GiB"`)
+    #[serde(deserialize_with = "deserialize_byte_size_map")]
+    pub interfaces: BTreeMap<String, u64>,
+    /// Percentage of the cap that triggers an alert
+    pub warn_percent: f64,
+}
+
+impl Default for DataCapConfig {
+    fn default() -> Self {
+        DataCapConfig {
+            limit: None,
+            interfaces: BTreeMap::new(),
+            warn_percent: 80.0,
+        }
+    }
+}
+
+impl DataCapConfig {
+    pub fn cap_for(&self, iface: &str) -> Option<u64> {
+        self.interfaces.get(iface).copied().or(self.limit)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    /// Log alerts as warnings
+    pub log: bool,
+    /// Command run for each alert, with title and message appended as arguments
+    pub command: Vec<String>,
+    /// Seconds before an alert with the same title is delivered again
+    pub cooldown: u64,
+    /// Alerts delivered per hour at most, over every title
+    pub max_per_hour: u32,
+    /// Hold alerts back and deliver them as one summary a day
+    pub digest: bool,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            log: true,
+            command: Vec::new(),
+            cooldown: 3600,
+            max_per_hour: 10,
+            digest: false,
+        }
+    }
+}
+
+pub fn default_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("~"))
+                .join(".config")
+        })
+        .join("symmetri")
+        .join("config.toml")
+}
+
+/// Expand a leading `~/` to the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Resolve the config path from the argument, `SYMMETRI_CONFIG`, or the default location.
+/// Returns whether the path was requested explicitly, in which case it must exist.
+pub fn resolve_config_path(config_path: Option<&Path>) -> (PathBuf, bool) {
+    if let Some(path) = config_path {
+        return (path.to_path_buf(), true);
+    }
+    if let Ok(env_path) = std::env::var("SYMMETRI_CONFIG") {
+        return (expand_home(Path::new(&env_path)), true);
+    }
+    (default_config_path(), false)
+}
+
+pub fn load_config(config_path: Option<&Path>) -> Result<Config> {
+    let (path, explicit) = resolve_config_path(config_path);
+    if !explicit && !path.exists() {
+        return Ok(Config::default());
+    }
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read config {}", path.display()))?;
+    parse_config(&raw).with_context(|| format!("invalid config {}", path.display()))
+}
+
+pub fn parse_config(raw: &str) -> Result<Config> {
+    Ok(toml::from_str(raw)?)
+}
+
+pub fn parse_byte_size(raw: &str) -> Option<u64> {
+    let trimmed = raw.trim();
+    let split = trimmed
+        .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let value: f64 = number.parse().ok()?;
+    let multiplier: f64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "g" | "gb" => 1e9,
+        "t" | "tb" => 1e12,
+        "kib" => 1024.0,
+        "mib" => 1024.0_f64.powi(2),
+        "gib" => 1024.0_f64.powi(3),
+        "tib" => 1024.0_f64.powi(4),
+        _ => return None,
+    };
+    Some((value * multiplier) as u64)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ByteSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl ByteSize {
+    fn into_bytes<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            ByteSize::Bytes(value) => Ok(value),
+            ByteSize::Text(text) => parse_byte_size(&text)
+                .ok_or_else(|| E::custom(format!("invalid byte size `{text}`"))),
+        }
+    }
+}
+
+fn deserialize_opt_byte_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<ByteSize>::deserialize(deserializer)?
+        .map(ByteSize::into_bytes)
+        .transpose()
+}
+
+fn deserialize_byte_size_map<'de, D>(deserializer: D) -> Result<BTreeMap<String, u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, ByteSize>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, size)| size.into_bytes().map(|bytes| (key, bytes)))
+        .collect()
+}
+
+fn deserialize_metric_kinds<'de, D>(deserializer: D) -> Result<Vec<MetricKind>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|raw| {
+            MetricKind::from_label(&raw)
+                .ok_or_else(|| serde::de::Error::custom(format!("unknown metric kind `{raw}`")))
+        })
+        .collect()
+}
+
+fn deserialize_opt_metric_kinds<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<MetricKind>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_metric_kinds(deserializer).map(Some)
+}
+
+/// Seconds in a bucket width such as `30m`, `2h`, `1d` or `1w`.
+pub fn parse_bucket(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = raw.split_at(split);
+    let value: i64 = number.parse().ok().filter(|value| *value > 0)?;
+    let unit_seconds = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        "w" => 7 * 24 * 3600,
+        _ => return None,
+    };
+    Some(value * unit_seconds)
+}
+
+fn deserialize_opt_bucket<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|raw| {
+            parse_bucket(&raw).ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "invalid bucket `{raw}`; expected e.g. 30m, 2h or 1d"
+                ))
+            })
+        })
+        .transpose()
+}
+
+/// A command given either as one whitespace-separated string or as a list of arguments
+/// (needed when an argument contains spaces).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CommandLine {
+    Text(String),
+    Args(Vec<String>),
+}
+
+impl CommandLine {
+    fn into_args<E: serde::de::Error>(self) -> Result<Vec<String>, E> {
+        let args = match self {
+            CommandLine::Text(text) => text.split_whitespace().map(str::to_string).collect(),
+            CommandLine::Args(args) => args,
+        };
+        if args.is_empty() {
+            return Err(E::custom("command must not be empty"));
+        }
+        Ok(args)
+    }
+}
+
+fn deserialize_opt_command_line<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<CommandLine>::deserialize(deserializer)?
+        .map(CommandLine::into_args)
+        .transpose()
+}
+
+fn deserialize_command_line_map<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, CommandLine>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, command)| command.into_args().map(|args| (name, args)))
+        .collect()
+}
+
+/// A derived metric given as just its expression, or as a table with options.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DerivedEntry {
+    Expr(String),
+    Table {
+        expr: String,
+        #[serde(default)]
+        unit: Option<String>,
+        #[serde(default)]
+        materialize: bool,
+    },
+}
+
+fn deserialize_derived_metrics<'de, D>(deserializer: D) -> Result<Vec<DerivedMetric>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, DerivedEntry>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, entry)| {
+            let (expr, unit, materialize) = match entry {
+                DerivedEntry::Expr(expr) => (expr, None, false),
+                DerivedEntry::Table {
+                    expr,
+                    unit,
+                    materialize,
+                } => (expr, unit, materialize),
+            };
+            let expr = expr.parse().map_err(|err| {
+                serde::de::Error::custom(format!("invalid expression for `{name}`: {err}"))
+            })?;
+            Ok(DerivedMetric {
+                name,
+                expr,
+                unit,
+                materialize,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = parse_config("").unwrap();
+        assert!(config.network.data_cap.limit.is_none());
+        assert_eq!(config.network.data_cap.warn_percent, 80.0);
+        assert!(config.alerts.log);
+        assert!(config.collect.is_enabled(Collector::Battery));
+    }
+
+    #[test]
+    fn data_cap_accepts_human_sizes() {
+        let config = parse_config(
+            r#"
+            [network.data_cap]
+            limit = "20GiB"
+            warn_percent = 90
+
+            [network.data_cap.interfaces]
+            wwan0 = "5GB"
+            eth0 = 1000
+            "#,
+        )
+        .unwrap();
+        let cap = &config.network.data_cap;
+        assert_eq!(cap.limit, Some(20 * 1024 * 1024 * 1024));
+        assert_eq!(cap.cap_for("wwan0"), Some(5_000_000_000));
+        assert_eq!(cap.cap_for("eth0"), Some(1000));
+        assert_eq!(cap.cap_for("wlan0"), cap.limit);
+        assert_eq!(cap.warn_percent, 90.0);
+
+        let config = parse_config("[collect]\nmax_rss = \"64MiB\"").unwrap();
+        assert_eq!(config.collect.max_rss, Some(64 * 1024 * 1024));
+        assert!(config.collect.flushes_after(0));
+
+        let config = parse_config("[collect]\nflush_every = 3").unwrap();
+        let flushes: Vec<u64> = (0..7)
+            .filter(|run| config.collect.flushes_after(*run))
+            .collect();
+        assert_eq!(flushes, [2, 5]);
+
+        let config = parse_config(
+            r#"
+            [collect.low_power]
+            battery_below = 20
+            pause = ["containers"]
+            reduce = ["gpu"]
+            reduce_every = 3
+            "#,
+        )
+        .unwrap();
+        let low_power = &config.collect.low_power;
+        assert_eq!(low_power.battery_below, Some(20.0));
+        assert!(low_power.holds_back(Collector::Containers, 3));
+        assert!(low_power.holds_back(Collector::Gpu, 4));
+        assert!(!low_power.holds_back(Collector::Gpu, 6));
+        assert!(!low_power.holds_back(Collector::Cpu, 1));
+    }
+
+    #[test]
+    fn report_defaults_parse_presets() {
+        let config = parse_config(
+            r#"
+            [report]
+            presets = ["cpu", "disk", "network"]
+            timeframe = "24h"
+            graph = true
+            step_kinds = ["disk_usage"]
+            temperature_sensors = ["coretemp:*"]
+            sections = ["summary", "events"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.report.presets,
+            vec![ReportPreset::Cpu, ReportPreset::Disk, ReportPreset::Network]
+        );
+        assert_eq!(config.report.timeframe.as_deref(), Some("24h"));
+        assert_eq!(config.report.step_kinds, vec![MetricKind::DiskUsage]);
+        assert_eq!(config.report.temperature_sensors, ["coretemp:*"]);
+        assert_eq!(
+            config.report.sections,
+            [ReportSection::Summary, ReportSection::Events]
+        );
+        assert!(config.report.graph);
+        assert!(!config.report.graph_inline);
+        assert!(parse_config("[report]\npresets = [\"bogus\"]").is_err());
+
+        let config = parse_config(
+            "[viewer]\nrefresh = 2\ntheme = \"ascii\"\npanels = [\"power\", \"battery\"]",
+        )
+        .unwrap();
+        assert_eq!(config.viewer.refresh, Some(2));
+        assert_eq!(config.viewer.theme, TableTheme::Ascii);
+        assert_eq!(config.viewer.panels, [Panel::Power, Panel::Battery]);
+        assert!(parse_config("[viewer]\npanels = [\"gpu\"]").is_err());
+        assert_eq!(parse_config("").unwrap().viewer.theme, TableTheme::Rounded);
+    }
+
+    #[test]
+    fn report_profiles_override_the_defaults() {
+        let config = parse_config(
+            r#"
+            [report]
+            presets = ["battery"]
+            timeframe = "24h"
+            graph = true
+
+            [report.profiles.work-laptop]
+            presets = ["cpu", "temperature"]
+            bucket = "30m"
+            sources = ["BAT0"]
+            graph = false
+            "#,
+        )
+        .unwrap();
+        let profile = config.report.with_profile("work-laptop").unwrap();
+        assert_eq!(
+            profile.presets,
+            [ReportPreset::Cpu, ReportPreset::Temperature]
+        );
+        assert_eq!(profile.timeframe.as_deref(), Some("24h"));
+        assert_eq!(profile.bucket, Some(1800));
+        assert_eq!(profile.sources, ["BAT0"]);
+        assert!(!profile.graph);
+        assert!(config
+            .report
+            .with_profile("home")
+            .unwrap_err()
+            .to_string()
+            .contains("work-laptop"));
+        assert!(parse_config("[report]\nbucket = \"5x\"").is_err());
+    }
+
+    #[test]
+    fn aliases_accept_strings_and_lists() {
+        let config = parse_config(
+            r#"
+            default_command = "report --days 1"
+
+            [aliases]
+            week = "report --days 7 --preset all --graph"
+            thermal = ["report", "--preset", "temperature", "--graph-path", "/tmp/my graphs/t.png"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.default_command,
+            Some(vec!["report".into(), "--days".into(), "1".into()])
+        );
+        assert_eq!(config.aliases["week"].len(), 6);
+        assert_eq!(config.aliases["thermal"][4], "/tmp/my graphs/t.png");
+        assert!(parse_config("[aliases]\nnothing = \"\"").is_err());
+
+        let config = parse_config(
+            "[status]\nmetrics = [\"power\", \"battery\"]\nopen_command = \"foot -e symmetri report\"",
+        )
+        .unwrap();
+        assert_eq!(
+            config.status.metrics,
+            [StatusMetric::Power, StatusMetric::Battery]
+        );
+        assert_eq!(config.status.open_command.unwrap()[0], "foot");
+    }
+
+    #[test]
+    fn interface_filter_skips_virtual_unless_allowed() {
+        let config = parse_config(
+            r#"
+            [network.interfaces]
+            deny = ["wwan*"]
+            "#,
+        )
+        .unwrap();
+        let filter = &config.network.interfaces;
+        assert!(filter.includes("wlan0", false));
+        assert!(!filter.includes("docker0", true));
+        assert!(!filter.includes("wwan0", false));
+
+        let allow = InterfaceFilter {
+            allow: vec!["wg*".to_string(), "eth0".to_string()],
+            ..InterfaceFilter::default()
+        };
+        assert!(allow.includes("wg0", true));
+        assert!(!allow.includes("wlan0", false));
+        let all = InterfaceFilter {
+            include_virtual: true,
+            ..InterfaceFilter::default()
+        };
+        assert!(all.includes("veth1a2b", true));
+    }
+
+    #[test]
+    fn invalid_byte_size_is_rejected() {
+        assert!(parse_config("[network.data_cap]\nlimit = \"lots\"").is_err());
+        assert_eq!(parse_byte_size("1.5KiB"), Some(1536));
+    }
+
+    #[test]
+    fn derived_metrics_parse_expressions() {
+        let config = parse_config(
+            r#"
+            [derived]
+            net_power = "power_draw - power_draw[amdgpu]"
+            mem_pressure = { expr = "memory_usage / memory_usage.total_bytes * 100", unit = "%", materialize = true }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.derived.len(), 2);
+        assert_eq!(config.derived[0].name, "mem_pressure");
+        assert!(config.derived[0].materialize);
+        assert_eq!(config.derived[1].unit, None);
+        let err = parse_config(
+            "[derived]
+bad = \"gpu_power * 2\"",
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("unknown metric kind `gpu_power`"));
+    }
+}
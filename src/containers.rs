@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::metrics::{read_numeric, MetricKind, MetricSample};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// Container scopes live a few levels down (e.g. `user.slice/user-1000.slice/user@1000.service/user.slice`).
+const MAX_CGROUP_DEPTH: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ContainerCgroup {
+    runtime: &'static str,
+    id: String,
+    path: PathBuf,
+}
+
+fn is_container_id(raw: &str) -> bool {
+    raw.len() == 64 && raw.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Recognize Docker (systemd and cgroupfs drivers) and Podman container cgroups.
+fn container_from_dir(parent: &str, name: &str) -> Option<(&'static str, String)> {
+    let scoped = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(".scope"))
+            .filter(|id| is_container_id(id))
+            .map(str::to_string)
+    };
+    if let Some(id) = scoped("docker-") {
+        return Some(("docker", id));
+    }
+    if let Some(id) = scoped("libpod-") {
+        return Some(("podman", id));
+    }
+    if parent == "docker" && is_container_id(name) {
+        return Some(("docker", name.to_string()));
+    }
+    None
+}
+
+fn find_container_cgroups(root: &Path) -> Vec<ContainerCgroup> {
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0usize)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let parent = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for entry in entries.flatten() {
+            if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            match container_from_dir(&parent, &name) {
+                Some((runtime, id)) => found.push(ContainerCgroup {
+                    runtime,
+                    id,
+                    path: entry.path(),
+                }),
+                None if depth + 1 < MAX_CGROUP_DEPTH => pending.push((entry.path(), depth + 1)),
+                None => {}
+            }
+        }
+    }
+    found.sort_by(|a, b| a.id.cmp(&b.id));
+    found
+}
+
+fn read_cpu_usage_usec(cgroup: &Path) -> Option<f64> {
+    let content = fs::read_to_string(cgroup.join("cpu.stat")).ok()?;
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+fn read_memory_limit(cgroup: &Path) -> Option<f64> {
+    let raw = fs::read_to_string(cgroup.join("memory.max")).ok()?;
+    raw.trim().parse().ok()
+}
+
+/// Container names from Podman's storage index (`containers.json`), keyed by full id.
+fn podman_names(index: &Path) -> BTreeMap<String, String> {
+    let Ok(raw) = fs::read_to_string(index) else {
+        return BTreeMap::new();
+    };
+    let Ok(Value::Array(entries)) = serde_json::from_str::<Value>(&raw) else {
+        return BTreeMap::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let id = entry.get("id")?.as_str()?;
+            let name = entry.get("names")?.as_array()?.first()?.as_str()?;
+            Some((id.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
+/// Docker keeps the name in the container's `config.v2.json` (readable by root only).
+fn docker_config_name(containers_dir: &Path, id: &str) -> Option<String> {
+    let raw = fs::read_to_string(containers_dir.join(id).join("config.v2.json")).ok()?;
+    let config: Value = serde_json::from_str(&raw).ok()?;
+    let name = config.get("Name")?.as_str()?;
+    Some(name.trim_start_matches('/').to_string())
+}
+
+fn container_names() -> BTreeMap<String, String> {
+    let mut names = BTreeMap::new();
+    let mut podman_indexes = vec![PathBuf::from(
+        "/var/lib/containers/storage/overlay-containers/containers.json",
+    )];
+    if let Some(data) = dirs::data_dir() {
+        podman_indexes.push(data.join("containers/storage/overlay-containers/containers.json"));
+    }
+    for index in podman_indexes {
+        names.extend(podman_names(&index));
+    }
+    #[cfg(feature = "docker")]
+    names.extend(docker_api::container_names());
+    names
+}
+
+fn read_container_samples(
+    containers: Vec<ContainerCgroup>,
+    names: &BTreeMap<String, String>,
+    docker_dir: &Path,
+    ts: f64,
+) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for container in containers {
+        let name = names
+            .get(&container.id)
+            .cloned()
+            .or_else(|| docker_config_name(docker_dir, &container.id))
+            .unwrap_or_else(|| container.id[..12].to_string());
+        let details = json!({ "runtime": container.runtime, "id": container.id });
+        if let Some(usage) = read_cpu_usage_usec(&container.path) {
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::ContainerCpu,
+                name.clone(),
+                Some(usage),
+                Some("usec"),
+                details.clone(),
+            ));
+        }
+        if let Some(current) = read_numeric(&container.path.join("memory.current")) {
+            let mut details = details;
+            if let Some(limit) = read_memory_limit(&container.path) {
+                details["limit_bytes"] = json!(limit);
+            }
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::ContainerMemory,
+                name,
+                Some(current),
+                Some("bytes"),
+                details,
+            ));
+        }
+    }
+    samples
+}
+
+/// Per-container cumulative CPU time and current memory from cgroup v2.
+pub fn container_samples(ts: f64) -> Vec<MetricSample> {
+    let root = Path::new(CGROUP_ROOT);
+    if !root.join("cgroup.controllers").exists() {
+        return Vec::new();
+    }
+    let containers = find_container_cgroups(root);
+    if containers.is_empty() {
+        return Vec::new();
+    }
+    read_container_samples(
+        containers,
+        &container_names(),
+        Path::new("/var/lib/docker/containers"),
+        ts,
+    )
+}
+
+#[cfg(feature = "docker")]
+mod docker_api {
+    use std::collections::BTreeMap;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    use log::debug;
+    use serde_json::Value;
+
+    const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+    /// Names of running containers from the Docker Engine API, keyed by full id.
+    pub fn container_names() -> BTreeMap<String, String> {
+        match list_containers() {
+            Ok(names) => names,
+            Err(err) => {
+                debug!("Docker API unavailable: {err}");
+                BTreeMap::new()
+            }
+        }
+    }
+
+    fn list_containers() -> anyhow::Result<BTreeMap<String, String>> {
+        let mut stream = UnixStream::connect(DOCKER_SOCKET)?;
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        stream.write_all(b"GET /containers/json HTTP/1.0\r\nHost: docker\r\n\r\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body)
+            .ok_or_else(|| anyhow::anyhow!("malformed Docker API response"))?;
+        Ok(parse_container_list(body))
+    }
+
+    fn parse_container_list(body: &str) -> BTreeMap<String, String> {
+        let Ok(Value::Array(entries)) = serde_json::from_str::<Value>(body) else {
+            return BTreeMap::new();
+        };
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let id = entry.get("Id")?.as_str()?;
+                let name = entry.get("Names")?.as_array()?.first()?.as_str()?;
+                Some((id.to_string(), name.trim_start_matches('/').to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_cgroup(dir: &Path, usage_usec: u64, memory: u64) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("cpu.stat"),
+            format!("usage_usec {usage_usec}\nuser_usec 1\nsystem_usec 2\n"),
+        )
+        .unwrap();
+        fs::write(dir.join("memory.current"), format!("{memory}\n")).unwrap();
+        fs::write(dir.join("memory.max"), "max\n").unwrap();
+    }
+
+    #[test]
+    fn cgroup_scopes_become_named_container_samples() {
+        let tmp = tempfile::tempdir().unwrap();
+        let docker_id = "a".repeat(64);
+        let podman_id = "b".repeat(64);
+        write_cgroup(
+            &tmp.path()
+                .join("system.slice")
+                .join(format!("docker-{docker_id}.scope")),
+            5_000_000,
+            1024,
+        );
+        write_cgroup(
+            &tmp.path()
+                .join("user.slice/user-1000.slice/user@1000.service/user.slice")
+                .join(format!("libpod-{podman_id}.scope")),
+            7,
+            2048,
+        );
+        fs::create_dir_all(tmp.path().join("system.slice/sshd.service")).unwrap();
+
+        let names = BTreeMap::from([(podman_id.clone(), "web".to_string())]);
+        let samples =
+            read_container_samples(find_container_cgroups(tmp.path()), &names, tmp.path(), 1.0);
+        assert_eq!(samples.len(), 4);
+
+        let docker_cpu = &samples[0];
+        assert_eq!(docker_cpu.kind, MetricKind::ContainerCpu);
+        assert_eq!(docker_cpu.source, "aaaaaaaaaaaa");
+        assert_eq!(docker_cpu.value, Some(5_000_000.0));
+        assert_eq!(docker_cpu.details["runtime"], "docker");
+
+        let podman_memory = &samples[3];
+        assert_eq!(podman_memory.kind, MetricKind::ContainerMemory);
+        assert_eq!(podman_memory.source, "web");
+        assert_eq!(podman_memory.value, Some(2048.0));
+        assert!(podman_memory.details.get("limit_bytes").is_none());
+    }
+
+    #[test]
+    fn cgroupfs_docker_layout_and_podman_index() {
+        assert_eq!(
+            container_from_dir("docker", &"c".repeat(64)),
+            Some(("docker", "c".repeat(64)))
+        );
+        assert_eq!(container_from_dir("system.slice", &"c".repeat(64)), None);
+        assert_eq!(
+            container_from_dir(
+                "machine.slice",
+                &format!("libpod-conmon-{}.scope", "c".repeat(64))
+            ),
+            None
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        let index = tmp.path().join("containers.json");
+        fs::write(&index, r#"[{"id":"abc","names":["db","alias"]}]"#).unwrap();
+        assert_eq!(
+            podman_names(&index).get("abc").map(String::as_str),
+            Some("db")
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use ordered_float::OrderedFloat;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Intervals longer than this are collection gaps and are not attributed to either sample.
+const MAX_INTERVAL_SECONDS: f64 = 15.0 * 60.0;
+/// Frequency at or below this share of the observed peak counts as a drop.
+const FREQUENCY_DROP_RATIO: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reduce {
+    Mean,
+    Max,
+    Sum,
+}
+
+/// Reduce every source of `kind` to one value per collection timestamp.
+pub fn series_by_timestamp(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    reduce: Reduce,
+) -> BTreeMap<OrderedFloat<f64>, f64> {
+    let mut grouped: BTreeMap<OrderedFloat<f64>, Vec<f64>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == kind) {
+        if let Some(value) = sample.value {
+            grouped
+                .entry(OrderedFloat(sample.ts))
+                .or_default()
+                .push(value);
+        }
+    }
+    grouped
+        .into_iter()
+        .map(|(ts, values)| {
+            let value = match reduce {
+                Reduce::Mean => values.iter().sum::<f64>() / values.len() as f64,
+                Reduce::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                Reduce::Sum => values.iter().sum(),
+            };
+            (ts, value)
+        })
+        .collect()
+}
+
+/// Pair two kinds on the timestamps where both were collected, as `(ts, x, y)`.
+pub fn align_by_timestamp(
+    metrics: &[MetricSample],
+    x: (MetricKind, Reduce),
+    y: (MetricKind, Reduce),
+) -> Vec<(f64, f64, f64)> {
+    let xs = series_by_timestamp(metrics, x.0, x.1);
+    let ys = series_by_timestamp(metrics, y.0, y.1);
+    xs.into_iter()
+        .filter_map(|(ts, xv)| ys.get(&ts).map(|yv| (ts.into_inner(), xv, *yv)))
+        .collect()
+}
+
+/// Mean of every source of `kind` per `bucket_seconds` window, keyed by window start.
+/// Counters are turned into summed per-second rates first.
+fn bucket_means(
+    metrics: &[MetricSample],
+    kind: &MetricKind,
+    bucket_seconds: f64,
+) -> BTreeMap<i64, f64> {
+    let rates;
+    let samples = if kind.is_counter() {
+        rates = crate::cli_helpers::counter_rate_samples(metrics, kind.clone());
+        series_by_timestamp(&rates, kind.clone(), Reduce::Sum)
+    } else {
+        series_by_timestamp(metrics, kind.clone(), Reduce::Mean)
+    };
+    let mut buckets: BTreeMap<i64, (f64, usize)> = BTreeMap::new();
+    for (ts, value) in samples {
+        let bucket = buckets
+            .entry((ts.into_inner() / bucket_seconds).floor() as i64)
+            .or_default();
+        bucket.0 += value;
+        bucket.1 += 1;
+    }
+    buckets
+        .into_iter()
+        .map(|(bucket, (sum, count))| (bucket, sum / count as f64))
+        .collect()
+}
+
+/// Pair the bucket means of two kinds on the windows where both have samples, as
+/// `(window start, x, y)`. Unlike [`align_by_timestamp`] the kinds need not be
+/// collected in the same run.
+pub fn align_by_bucket(
+    metrics: &[MetricSample],
+    x: &MetricKind,
+    y: &MetricKind,
+    bucket_seconds: f64,
+) -> Vec<(f64, f64, f64)> {
+    let xs = bucket_means(metrics, x, bucket_seconds);
+    let ys = bucket_means(metrics, y, bucket_seconds);
+    xs.into_iter()
+        .filter_map(|(bucket, xv)| {
+            ys.get(&bucket)
+                .map(|yv| (bucket as f64 * bucket_seconds, xv, *yv))
+        })
+        .collect()
+}
+
+/// Pearson correlation coefficient of `(x, y)` points, from -1 to 1; `None` with fewer
+/// than two points or when either side never changes.
+pub fn pearson(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut covariance, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (var_x.sqrt() * var_y.sqrt()))
+}
+
+/// Conventional wording for the strength of a correlation coefficient.
+pub fn correlation_strength(r: f64) -> &'static str {
+    match r.abs() {
+        a if a >= 0.7 => "strong",
+        a if a >= 0.4 => "moderate",
+        a if a >= 0.2 => "weak",
+        _ => "no",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrottleSummary {
+    pub throttled_seconds: f64,
+    pub observed_seconds: f64,
+    pub temp_threshold_c: f64,
+    pub freq_threshold_mhz: f64,
+}
+
+/// Time spent with the hottest sensor at or above `temp_threshold_c` while the mean CPU
+/// frequency was at or below 80% of its peak in the window.
+pub fn thermal_throttle_summary(
+    metrics: &[MetricSample],
+    temp_threshold_c: f64,
+) -> Option<ThrottleSummary> {
+    let aligned = align_by_timestamp(
+        metrics,
+        (MetricKind::Temperature, Reduce::Max),
+        (MetricKind::CpuFrequency, Reduce::Mean),
+    );
+    if aligned.len() < 2 {
+        return None;
+    }
+    let peak_freq = aligned
+        .iter()
+        .map(|(_, _, freq)| *freq)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let freq_threshold_mhz = peak_freq * FREQUENCY_DROP_RATIO;
+
+    let mut throttled_seconds = 0.0;
+    let mut observed_seconds = 0.0;
+    for window in aligned.windows(2) {
+        let (ts, temp, freq) = window[0];
+        let dt = window[1].0 - ts;
+        if dt <= 0.0 || dt > MAX_INTERVAL_SECONDS {
+            continue;
+        }
+        observed_seconds += dt;
+        if temp >= temp_threshold_c && freq <= freq_threshold_mhz {
+            throttled_seconds += dt;
+        }
+    }
+
+    Some(ThrottleSummary {
+        throttled_seconds,
+        observed_seconds,
+        temp_threshold_c,
+        freq_threshold_mhz,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn sample(kind: MetricKind, source: &str, ts: f64, value: f64) -> MetricSample {
+        MetricSample::new(ts, kind, source, Some(value), None, Value::Null)
+    }
+
+    #[test]
+    fn alignment_reduces_sources_per_timestamp() {
+        let metrics = vec![
+            sample(MetricKind::Temperature, "a", 0.0, 50.0),
+            sample(MetricKind::Temperature, "b", 0.0, 70.0),
+            sample(MetricKind::CpuFrequency, "cpu0", 0.0, 1000.0),
+            sample(MetricKind::CpuFrequency, "cpu1", 0.0, 3000.0),
+            sample(MetricKind::Temperature, "a", 60.0, 55.0),
+        ];
+        let aligned = align_by_timestamp(
+            &metrics,
+            (MetricKind::Temperature, Reduce::Max),
+            (MetricKind::CpuFrequency, Reduce::Mean),
+        );
+        assert_eq!(aligned, vec![(0.0, 70.0, 2000.0)]);
+    }
+
+    #[test]
+    fn bucketed_series_correlate() {
+        // Power follows CPU usage, collected a few seconds apart in each 5-minute window
+        let mut metrics = Vec::new();
+        for (i, usage) in [10.0, 50.0, 30.0, 90.0].iter().enumerate() {
+            let ts = i as f64 * 300.0;
+            metrics.push(sample(MetricKind::CpuUsage, "cpu", ts + 10.0, *usage));
+            metrics.push(sample(
+                MetricKind::PowerDraw,
+                "BAT0",
+                ts + 40.0,
+                3.0 + usage / 10.0,
+            ));
+        }
+        let aligned = align_by_bucket(
+            &metrics,
+            &MetricKind::CpuUsage,
+            &MetricKind::PowerDraw,
+            300.0,
+        );
+        assert_eq!(aligned.len(), 4);
+        assert_eq!(aligned[1], (300.0, 50.0, 8.0));
+        let points: Vec<(f64, f64)> = aligned.iter().map(|(_, x, y)| (*x, *y)).collect();
+        let r = pearson(&points).unwrap();
+        assert!((r - 1.0).abs() < 1e-9);
+        assert_eq!(correlation_strength(-r), "strong");
+        assert_eq!(pearson(&[(1.0, 2.0), (2.0, 2.0)]), None);
+    }
+
+    #[test]
+    fn throttling_counts_hot_and_slow_intervals() {
+        let points = [
+            (0.0, 60.0, 4000.0),
+            (300.0, 92.0, 2500.0),
+            (600.0, 93.0, 2400.0),
+            (900.0, 92.0, 3900.0),
+            (1200.0, 70.0, 3000.0),
+            // A long gap is ignored.
+            (9000.0, 95.0, 2000.0),
+        ];
+        let mut metrics = Vec::new();
+        for (ts, temp, freq) in points {
+            metrics.push(sample(MetricKind::Temperature, "cpu", ts, temp));
+            metrics.push(sample(MetricKind::CpuFrequency, "cpu0", ts, freq));
+        }
+
+        let summary = thermal_throttle_summary(&metrics, 90.0).unwrap();
+        assert_eq!(summary.freq_threshold_mhz, 3200.0);
+        assert_eq!(summary.throttled_seconds, 600.0);
+        assert_eq!(summary.observed_seconds, 1200.0);
+    }
+}
@@ -0,0 +1,230 @@
+//! Monthly data usage per interface against the `[network.data_cap]` caps: tabled by
+//! `report --data-cap`, and checked by the collector after every write so a metered
+//! connection raises its alert as the threshold is crossed.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::alert::{self, Alert, Severity};
+use crate::cli_helpers::{format_bytes, local_datetime, month_start_timestamp};
+use crate::config::{Config, DataCapConfig};
+use crate::db;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::report::{network_counter_deltas, TransferStats};
+use crate::storage::Storage;
+
+/// How far before a month the last counter reading is looked for, so the traffic between
+/// it and the month's first reading is counted.
+const SEED_SECONDS: f64 = 24.0 * 3600.0;
+
+/// Transferred bytes keyed by calendar month (`YYYY-MM`, local time) and interface.
+pub type MonthlyUsage = BTreeMap<(String, String), TransferStats>;
+
+pub fn monthly_network_usage(metrics: &[MetricSample]) -> MonthlyUsage {
+    let mut usage: MonthlyUsage = BTreeMap::new();
+    for (iface, ts, rx_delta, tx_delta) in network_counter_deltas(metrics) {
+        let month = local_datetime(ts).format("%Y-%m").to_string();
+        usage
+            .entry((month, iface.to_string()))
+            .or_default()
+            .record(rx_delta, tx_delta);
+    }
+    usage
+}
+
+/// Network counter samples from the start of the month holding `since` on (all of them
+/// without `since`), led by each counter's last reading before that month.
+pub fn month_samples(store: &dyn Storage, since: Option<f64>) -> Result<Vec<MetricSample>> {
+    let kinds = [MetricKind::NetworkBytes];
+    let Some(start) = since.map(month_start_timestamp) else {
+        return store.fetch_samples(None, None, Some(&kinds));
+    };
+    let mut seeds = BTreeMap::new();
+    let mut samples = Vec::new();
+    for sample in store.fetch_samples(Some(start - SEED_SECONDS), None, Some(&kinds))? {
+        if sample.ts < start {
+            seeds.insert((sample.source.clone(), sample.host.clone()), sample);
+        } else {
+            samples.push(sample);
+        }
+    }
+    Ok(seeds.into_values().chain(samples).collect())
+}
+
+pub fn cap_percent(stats: &TransferStats, cap: u64) -> Option<f64> {
+    (cap > 0).then(|| (stats.rx_total + stats.tx_total) / cap as f64 * 100.0)
+}
+
+pub fn data_cap_alerts(usage: &MonthlyUsage, caps: &DataCapConfig, month: &str) -> Vec<Alert> {
+    usage
+        .iter()
+        .filter(|((m, _), _)| m == month)
+        .filter_map(|((_, iface), stats)| {
+            let cap = caps.cap_for(iface)?;
+            let pct = cap_percent(stats, cap)?;
+            if pct < caps.warn_percent {
+                return None;
+            }
+            let severity = if pct >= 100.0 {
+                Severity::Critical
+            } else {
+                Severity::Warning
+            };
+            Some(Alert {
+                severity,
+                title: format!("Data cap on {iface}"),
+                message: format!(
+                    "{iface} used {} of {} ({pct:.1}%) in {month}",
+                    format_bytes(stats.rx_total + stats.tx_total),
+                    format_bytes(cap as f64)
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Raise the data cap alerts of the month holding `now`, counting the samples of `host`,
+/// through the `[alerts]` limits. An interface over its cap stays over it for the rest of
+/// the month, so an alert still in its cooldown is not raised again rather than logged as
+/// held back after every write.
+pub fn check(conn: &Connection, config: &Config, host: Option<&str>, now: f64) -> Result<()> {
+    let caps = &config.network.data_cap;
+    if caps.limit.is_none() && caps.interfaces.is_empty() {
+        return Ok(());
+    }
+    let month = local_datetime(now).format("%Y-%m").to_string();
+    let transfer =
+        db::network_transfer_since(conn, month_start_timestamp(now), SEED_SECONDS, host)?;
+    let usage: MonthlyUsage = transfer
+        .into_iter()
+        .map(|(iface, (rx, tx))| {
+            let mut stats = TransferStats::default();
+            stats.record(rx, tx);
+            ((month.clone(), iface), stats)
+        })
+        .collect();
+    let mut alerts = Vec::new();
+    for alert in data_cap_alerts(&usage, caps, &month) {
+        let last_sent = db::last_alert_sent(conn, &alert.title)?;
+        if last_sent.is_none_or(|ts| now - ts >= config.alerts.cooldown as f64) {
+            alerts.push(alert);
+        }
+    }
+    let sinks = alert::sinks_from_config(&config.alerts);
+    alert::dispatch_limited(conn, &sinks, &config.alerts, &alerts, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::storage;
+    use chrono::{Local, TimeZone};
+
+    fn net(source: &str, ts: f64, rx: f64, tx: f64) -> MetricSample {
+        MetricSample::new(
+            ts,
+            MetricKind::NetworkBytes,
+            source,
+            Some(rx + tx),
+            None,
+            serde_json::Value::Null,
+        )
+        .with_network_bytes(Some(rx), Some(tx))
+    }
+
+    fn local_ts(month: u32, day: u32, hour: u32, minute: u32) -> f64 {
+        Local
+            .with_ymd_and_hms(2025, month, day, hour, minute, 0)
+            .unwrap()
+            .timestamp() as f64
+    }
+
+    fn parse(toml: &str) -> Config {
+        config::parse_config(toml).unwrap()
+    }
+
+    #[test]
+    fn monthly_usage_splits_by_month_and_interface() {
+        let jan = local_ts(1, 31, 23, 0);
+        let feb = local_ts(2, 1, 1, 0);
+        let metrics = vec![
+            net("wwan0", jan, 0.0, 0.0),
+            net("wwan0", jan + 1800.0, 600.0, 400.0),
+            net("wwan0", feb, 900.0, 500.0),
+            net("eth0", jan, 0.0, 0.0),
+            net("eth0", jan + 60.0, 10.0, 0.0),
+        ];
+
+        let usage = monthly_network_usage(&metrics);
+        let key = |m: &str, i: &str| (m.to_string(), i.to_string());
+        assert_eq!(usage.len(), 3);
+        assert_eq!(usage[&key("2025-01", "wwan0")].rx_total, 600.0);
+        assert_eq!(usage[&key("2025-02", "wwan0")].tx_total, 100.0);
+        assert_eq!(usage[&key("2025-01", "eth0")].rx_total, 10.0);
+    }
+
+    #[test]
+    fn data_cap_alerts_fire_above_threshold_in_current_month() {
+        let mut usage = MonthlyUsage::new();
+        let mut stats = TransferStats::default();
+        stats.record(850.0, 0.0);
+        usage.insert(("2025-01".to_string(), "wwan0".to_string()), stats.clone());
+        usage.insert(("2025-02".to_string(), "wwan0".to_string()), stats.clone());
+        stats.record(200.0, 0.0);
+        usage.insert(("2025-02".to_string(), "eth0".to_string()), stats);
+
+        let caps =
+            parse("[network.data_cap]\nlimit = 1000\n[network.data_cap.interfaces]\nwlan0 = 1")
+                .network
+                .data_cap;
+        let alerts = data_cap_alerts(&usage, &caps, "2025-02");
+        assert_eq!(alerts.len(), 2);
+        let eth = alerts.iter().find(|a| a.title.contains("eth0")).unwrap();
+        assert_eq!(eth.severity, Severity::Critical);
+        let wwan = alerts.iter().find(|a| a.title.contains("wwan0")).unwrap();
+        assert_eq!(wwan.severity, Severity::Warning);
+        assert!(data_cap_alerts(&usage, &caps, "2025-03").is_empty());
+    }
+
+    #[test]
+    fn the_month_counts_traffic_across_its_start() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = storage::open(&tmp.path().join("metrics.db")).unwrap();
+        store
+            .insert_samples(
+                &[
+                    net("wwan0", local_ts(1, 31, 10, 0), 200.0, 0.0),
+                    net("wwan0", local_ts(1, 31, 23, 30), 1000.0, 0.0),
+                    net("wwan0", local_ts(2, 1, 0, 30), 1500.0, 100.0),
+                    net("wwan0", local_ts(2, 1, 1, 0), 1600.0, 100.0),
+                ],
+                &[],
+            )
+            .unwrap();
+        let now = local_ts(2, 1, 1, 0);
+
+        let usage = monthly_network_usage(&month_samples(store.as_ref(), Some(now)).unwrap());
+        let feb = &usage[&("2025-02".to_string(), "wwan0".to_string())];
+        assert_eq!((feb.rx_total, feb.tx_total), (600.0, 100.0));
+        assert_eq!(usage.len(), 1);
+
+        let conn = store.sqlite().unwrap();
+        let transfer =
+            db::network_transfer_since(conn, month_start_timestamp(now), SEED_SECONDS, None)
+                .unwrap();
+        assert_eq!(transfer["wwan0"], (600.0, 100.0));
+
+        let config = parse("[network.data_cap]\nlimit = 800\n[alerts]\nlog = false");
+        check(conn, &config, None, now).unwrap();
+        assert_eq!(
+            db::last_alert_sent(conn, "Data cap on wwan0").unwrap(),
+            Some(now)
+        );
+        // Still over the cap on the next write, but cooling down: nothing more is logged
+        check(conn, &config, None, now + 60.0).unwrap();
+        assert_eq!(db::held_alerts_since(conn, None, None).unwrap().len(), 0);
+    }
+}
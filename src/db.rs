@@ -1,11 +1,21 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
-use anyhow::Result;
-use rusqlite::{params, Connection, Row};
+use anyhow::{bail, Result};
+use chrono::{Datelike, Local, Months, NaiveDate, TimeZone};
+use rusqlite::backup::Backup;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use schemars::JsonSchema;
+use serde::Serialize;
 
+use crate::alert::{Alert, Severity};
 use crate::metrics::{MetricKind, MetricSample};
+use crate::storage;
+use crate::system_info::{SystemInfo, SystemSnapshot};
 
 const SCHEMA: &str = r#"
 
@@ -21,12 +31,179 @@ CREATE INDEX IF NOT EXISTS idx_metric_samples_ts ON metric_samples (ts);
 CREATE INDEX IF NOT EXISTS idx_metric_samples_kind_ts ON metric_samples (kind, ts);
 "#;
 
+/// Schema changes applied in order on top of `SCHEMA`; `PRAGMA user_version` records how
+/// many have run, so append new steps and never edit existing ones.
+const MIGRATIONS: &[&str] = &[
+    // Bucketed distributions as JSON (`Histogram`)
+    "ALTER TABLE metric_samples ADD COLUMN histogram TEXT;",
+    // Category of labelled kinds (governor, power profile, battery status)
+    "ALTER TABLE metric_samples ADD COLUMN label TEXT;",
+    // Interval the collector loop ran at when it last stored each kind
+    "CREATE TABLE IF NOT EXISTS collection_intervals (
+        kind TEXT PRIMARY KEY,
+        interval REAL NOT NULL,
+        updated REAL NOT NULL
+    );",
+    // Machine identity, one row per change seen at collector start
+    "CREATE TABLE IF NOT EXISTS system_info (
+        ts REAL NOT NULL,
+        hostname TEXT,
+        kernel TEXT,
+        cpu_model TEXT,
+        memory_bytes INTEGER,
+        battery_model TEXT,
+        battery_serial TEXT
+    );",
+    // Hourly and daily min/avg/max per kind and source, see `update_rollups`
+    "CREATE TABLE IF NOT EXISTS metric_rollups (
+        resolution INTEGER NOT NULL,
+        ts REAL NOT NULL,
+        kind TEXT NOT NULL,
+        source TEXT NOT NULL,
+        samples INTEGER NOT NULL,
+        min REAL,
+        avg REAL,
+        max REAL,
+        unit TEXT,
+        PRIMARY KEY (resolution, kind, source, ts)
+    );",
+    // Batches waiting for a push sink, sent oldest first
+    "CREATE TABLE IF NOT EXISTS push_outbox (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        sink TEXT NOT NULL,
+        payload TEXT NOT NULL
+    );",
+    // Every raised alert and whether it was delivered or held back by the rate limits
+    "CREATE TABLE IF NOT EXISTS alert_log (
+        ts REAL NOT NULL,
+        title TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        message TEXT NOT NULL,
+        sent INTEGER NOT NULL
+    );",
+    // Machine that collected each sample, for databases shared by several hosts
+    "ALTER TABLE metric_samples ADD COLUMN host TEXT;",
+    // Rollups per host too; they are derived data, rebuilt by the next `update_rollups`
+    "DROP TABLE IF EXISTS metric_rollups;
+    CREATE TABLE metric_rollups (
+        resolution INTEGER NOT NULL,
+        ts REAL NOT NULL,
+        kind TEXT NOT NULL,
+        source TEXT NOT NULL,
+        host TEXT NOT NULL DEFAULT '',
+        samples INTEGER NOT NULL,
+        min REAL,
+        avg REAL,
+        max REAL,
+        unit TEXT,
+        PRIMARY KEY (resolution, kind, source, host, ts)
+    );",
+    // One row per timestamp, kind, source and host: drop duplicates left by repeated
+    // collect ticks or imports, then let inserts skip them
+    "DELETE FROM metric_samples WHERE rowid NOT IN (
+        SELECT MIN(rowid) FROM metric_samples GROUP BY ts, kind, source, COALESCE(host, '')
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_metric_samples_unique
+        ON metric_samples (ts, kind, source, COALESCE(host, ''));",
+    // Kernel boot each sample was collected in, to tell suspends from reboots
+    "ALTER TABLE metric_samples ADD COLUMN boot TEXT;",
+    // Hot detail keys in their own untyped columns (see `HOT_DETAILS`), so integers stay
+    // integers; rows keep the rest of their details as JSON
+    "ALTER TABLE metric_samples ADD COLUMN rx_bytes;
+    ALTER TABLE metric_samples ADD COLUMN tx_bytes;
+    ALTER TABLE metric_samples ADD COLUMN total_bytes;
+    ALTER TABLE metric_samples ADD COLUMN status;
+    UPDATE metric_samples SET rx_bytes = json_extract(details, '$.rx_bytes'),
+        details = json_remove(details, '$.rx_bytes')
+        WHERE json_type(details, '$.rx_bytes') IN ('integer', 'real', 'text');
+    UPDATE metric_samples SET tx_bytes = json_extract(details, '$.tx_bytes'),
+        details = json_remove(details, '$.tx_bytes')
+        WHERE json_type(details, '$.tx_bytes') IN ('integer', 'real', 'text');
+    UPDATE metric_samples SET total_bytes = json_extract(details, '$.total_bytes'),
+        details = json_remove(details, '$.total_bytes')
+        WHERE json_type(details, '$.total_bytes') IN ('integer', 'real', 'text');
+    UPDATE metric_samples SET status = json_extract(details, '$.status'),
+        details = json_remove(details, '$.status')
+        WHERE json_type(details, '$.status') IN ('integer', 'real', 'text');
+    UPDATE metric_samples SET details = NULL WHERE details = '{}' AND COALESCE(
+        rx_bytes, tx_bytes, total_bytes, status) IS NOT NULL;",
+];
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    migrate_to(conn, MIGRATIONS.len())
+}
+
+/// Apply the migrations up to (not including) step `target`.
+fn migrate_to(conn: &mut Connection, target: usize) -> Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version >= target {
+        return Ok(());
+    }
+    let tx = conn.transaction()?;
+    for step in &MIGRATIONS[version..target] {
+        tx.execute_batch(step)?;
+    }
+    tx.pragma_update(None, "user_version", target)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Environment variable holding the key of an encrypted database.
+pub const DB_KEY_ENV: &str = "SYMMETRI_DB_KEY";
+
+/// Key of an encrypted database: `SYMMETRI_DB_KEY`, else the secret stored in the desktop
+/// keyring under `application symmetri`, looked up once with `secret-tool`.
+#[cfg(feature = "encryption")]
+fn database_key() -> Option<&'static str> {
+    static KEY: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+    KEY.get_or_init(|| {
+        if let Ok(key) = std::env::var(DB_KEY_ENV) {
+            return Some(key);
+        }
+        let output = std::process::Command::new("secret-tool")
+            .args(["lookup", "application", "symmetri"])
+            .output()
+            .ok()?;
+        let key = String::from_utf8(output.stdout).ok()?;
+        (output.status.success() && !key.is_empty()).then_some(key)
+    })
+    .as_deref()
+}
+
+/// Open `db_path`, unlocking it with the database key when built with the `encryption`
+/// feature. Without it, a key in the environment is an error rather than being ignored.
+fn open_connection(db_path: &Path, flags: OpenFlags) -> Result<Connection> {
+    if storage::is_remote(db_path) {
+        bail!("--db is a PostgreSQL URL, but this command only works on a local database");
+    }
+    let conn = Connection::open_with_flags(db_path, flags)?;
+    #[cfg(feature = "encryption")]
+    if let Some(key) = database_key() {
+        conn.pragma_update(None, "key", key)?;
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map_err(|err| {
+            anyhow::anyhow!(
+                "cannot unlock {}: {err} (wrong key, or not encrypted?)",
+                db_path.display()
+            )
+        })?;
+    }
+    #[cfg(not(feature = "encryption"))]
+    if std::env::var_os(DB_KEY_ENV).is_some() {
+        bail!("{DB_KEY_ENV} is set, but symmetri was built without the `encryption` feature");
+    }
+    Ok(conn)
+}
+
 pub fn init_db_connection(db_path: &Path) -> Result<Connection> {
-    if let Some(parent) = db_path.parent() {
+    if let Some(parent) = db_path.parent().filter(|_| !storage::is_remote(db_path)) {
         fs::create_dir_all(parent)?;
     }
-    let conn = Connection::open(db_path)?;
+    let mut conn = open_connection(db_path, OpenFlags::default())?;
     conn.execute_batch(SCHEMA)?;
+    migrate(&mut conn)?;
     Ok(conn)
 }
 
@@ -35,69 +212,538 @@ pub fn init_db(db_path: &Path) -> Result<()> {
     init_db_connection(db_path).map(|_| ())
 }
 
-fn serialize_details(details: &serde_json::Value) -> Option<String> {
-    if details.is_null() {
-        None
+/// Copy the database behind `conn` to the new file `dest` with SQLite's online backup. It
+/// copies a few pages at a time and restarts when the collector writes in between, so
+/// the copy is a consistent snapshot without stopping the collector.
+pub fn backup_db(conn: &Connection, dest: &Path) -> Result<()> {
+    if dest.exists() {
+        bail!("{} already exists", dest.display());
+    }
+    let mut backup_conn = open_connection(dest, OpenFlags::default())?;
+    let backup = Backup::new(conn, &mut backup_conn)?;
+    backup.run_to_completion(100, Duration::from_millis(10), None)?;
+    Ok(())
+}
+
+/// Replace the database behind `conn` with the backup at `src`, migrating it if it was
+/// written by an older version.
+pub fn restore_db(conn: &mut Connection, src: &Path) -> Result<()> {
+    let backup = open_connection(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let tables: i64 = backup.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'metric_samples'",
+        [],
+        |row| row.get(0),
+    )?;
+    if tables == 0 {
+        bail!("{} is not a symmetri database", src.display());
+    }
+    let version: usize = backup.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "{} was written by a newer symmetri (schema {version}, this one knows {})",
+            src.display(),
+            MIGRATIONS.len()
+        );
+    }
+    Backup::new(&backup, conn)?.run_to_completion(100, Duration::from_millis(10), None)?;
+    conn.execute_batch(SCHEMA)?;
+    migrate(conn)
+}
+
+/// Problems `PRAGMA integrity_check` finds in the database at `db_path`, empty when it is
+/// sound. The file is opened read-only, so a damaged database is left as it is.
+pub fn integrity_check(db_path: &Path) -> Result<Vec<String>> {
+    let conn = open_connection(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let checked: rusqlite::Result<Vec<String>> = conn
+        .prepare("PRAGMA integrity_check")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect());
+    // Damage can also stop the check itself
+    Ok(match checked {
+        Ok(problems) if problems == ["ok"] => Vec::new(),
+        Ok(problems) => problems,
+        Err(err) => vec![err.to_string()],
+    })
+}
+
+/// Column names and rows of an ad-hoc query, every value rendered as text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Run one SQL statement against the database at `db_path`, opened read-only so a stray
+/// `DELETE` cannot touch the collected data. NULL reads as an empty string and blobs as
+/// their size.
+pub fn query_read_only(db_path: &Path, sql: &str) -> Result<QueryResult> {
+    if !db_path.exists() && !storage::is_remote(db_path) {
+        bail!("{} does not exist", db_path.display());
+    }
+    let conn = open_connection(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare(sql)?;
+    if !stmt.readonly() {
+        bail!("only read-only statements (SELECT, WITH, read-only PRAGMA) can be run");
+    }
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let width = columns.len();
+    let rows = stmt
+        .query_map([], |row| {
+            (0..width)
+                .map(|index| {
+                    Ok(match row.get::<_, SqlValue>(index)? {
+                        SqlValue::Null => String::new(),
+                        SqlValue::Integer(value) => value.to_string(),
+                        SqlValue::Real(value) => value.to_string(),
+                        SqlValue::Text(value) => value,
+                        SqlValue::Blob(value) => format!("<{} bytes>", value.len()),
+                    })
+                })
+                .collect()
+        })?
+        .collect::<rusqlite::Result<Vec<Vec<String>>>>()?;
+    Ok(QueryResult { columns, rows })
+}
+
+/// Rows read back by [`recover_db`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recovery {
+    pub tables: usize,
+    pub rows: usize,
+    /// Rows whose lookup failed; rows on pages too damaged to list cannot be counted
+    pub unreadable: usize,
+}
+
+/// Rows copied per query by [`recover_db`]; a failing range is retried row by row.
+const RECOVER_CHUNK_ROWS: i64 = 1000;
+
+/// Salvage what can still be read from the damaged database at `src` into the new file
+/// `dest`, like the sqlite3 shell's `.recover`: each table is read in rowid ranges, ranges
+/// that hit a damaged page are retried one rowid at a time, and unreadable rows are
+/// skipped. `dest` gets the schema version of `src` and is then migrated.
+pub fn recover_db(src: &Path, dest: &Path) -> Result<Recovery> {
+    if dest.exists() {
+        bail!("{} already exists", dest.display());
+    }
+    let damaged = open_connection(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let version: usize = damaged.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "{} was written by a newer symmetri (schema {version}, this one knows {})",
+            src.display(),
+            MIGRATIONS.len()
+        );
+    }
+    let mut conn = open_connection(dest, OpenFlags::default())?;
+    conn.execute_batch(SCHEMA)?;
+    migrate_to(&mut conn, version)?;
+
+    let mut recovery = Recovery::default();
+    let tables: Vec<String> = conn
+        .prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for table in tables {
+        // The damaged file may lack the table, or its schema page may be unreadable
+        let Ok(columns) = table_columns(&damaged, &table) else {
+            continue;
+        };
+        if columns.is_empty() {
+            continue;
+        }
+        let tx = conn.transaction()?;
+        recover_table(&damaged, &tx, &table, &columns, &mut recovery)?;
+        tx.commit()?;
+        recovery.tables += 1;
+    }
+    migrate(&mut conn)?;
+    Ok(recovery)
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn recover_table(
+    damaged: &Connection,
+    dest: &Connection,
+    table: &str,
+    columns: &[String],
+    recovery: &mut Recovery,
+) -> Result<()> {
+    let list = columns.join(", ");
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    let mut insert = dest.prepare(&format!(
+        "INSERT OR IGNORE INTO {table} ({list}) VALUES ({placeholders})"
+    ))?;
+    let mut select = damaged.prepare(&format!(
+        "SELECT {list} FROM {table} WHERE rowid BETWEEN ?1 AND ?2"
+    ))?;
+    let mut read = |from: i64, to: i64| -> rusqlite::Result<Vec<Vec<SqlValue>>> {
+        select
+            .query_map(params![from, to], |row| {
+                (0..columns.len()).map(|i| row.get(i)).collect()
+            })?
+            .collect()
+    };
+    // The first or last page may be the damaged one; without a last rowid, reading stops
+    // at the first range that holds nothing
+    let bound = |sql: &str| {
+        damaged
+            .query_row(&format!("SELECT {sql}(rowid) FROM {table}"), [], |row| {
+                row.get::<_, Option<i64>>(0)
+            })
+            .map_err(|_| ())
+    };
+    let (first, last) = match (bound("MIN"), bound("MAX")) {
+        (Ok(None), _) | (_, Ok(None)) => return Ok(()),
+        (first, last) => (first.ok().flatten().unwrap_or(1), last.ok().flatten()),
+    };
+    let mut start = first;
+    while last.is_none_or(|last| start <= last) {
+        let end = start.saturating_add(RECOVER_CHUNK_ROWS - 1);
+        let end = last.map_or(end, |last| end.min(last));
+        let rows = match read(start, end) {
+            Ok(rows) if rows.is_empty() && last.is_none() => break,
+            Ok(rows) => rows,
+            Err(_) => {
+                let mut rows = Vec::new();
+                for rowid in start..=end {
+                    match read(rowid, rowid) {
+                        Ok(row) => rows.extend(row),
+                        Err(_) => recovery.unreadable += 1,
+                    }
+                }
+                rows
+            }
+        };
+        for row in rows {
+            recovery.rows += insert.execute(rusqlite::params_from_iter(row))?;
+        }
+        if end == i64::MAX {
+            break;
+        }
+        start = end + 1;
+    }
+    Ok(())
+}
+
+/// Detail keys read for most rows (network counters, disk and memory totals, battery
+/// status), held in typed [`MetricSample`] fields and stored in columns of the same name
+/// instead of the `details` JSON.
+const HOT_DETAILS: [&str; 4] = ["rx_bytes", "tx_bytes", "total_bytes", "status"];
+
+/// `n` as stored in a hot column: an integer when it is whole.
+fn sql_number(n: f64) -> SqlValue {
+    if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 {
+        SqlValue::Integer(n as i64)
     } else {
-        Some(details.to_string())
+        SqlValue::Real(n)
     }
 }
 
+/// A hot number column as read back; columns are untyped, so text is parsed.
+pub(crate) fn sql_as_number(value: SqlValue) -> Option<f64> {
+    match value {
+        SqlValue::Integer(i) => Some(i as f64),
+        SqlValue::Real(f) => Some(f),
+        SqlValue::Text(text) => text.parse().ok(),
+        SqlValue::Null | SqlValue::Blob(_) => None,
+    }
+}
+
+/// The hot `status` column as read back.
+pub(crate) fn sql_as_text(value: SqlValue) -> Option<String> {
+    match value {
+        SqlValue::Text(text) => Some(text),
+        SqlValue::Integer(i) => Some(i.to_string()),
+        SqlValue::Real(f) => Some(f.to_string()),
+        SqlValue::Null | SqlValue::Blob(_) => None,
+    }
+}
+
+/// The columns of `HOT_DETAILS` and the rest of the details as JSON (`None` when nothing
+/// is left) for storing `sample`.
+pub(crate) fn split_details(sample: &MetricSample) -> ([SqlValue; 4], Option<String>) {
+    let mut hot = [
+        sample.rx_bytes.map(sql_number),
+        sample.tx_bytes.map(sql_number),
+        sample.total_bytes.map(sql_number),
+        sample.status.clone().map(SqlValue::Text),
+    ]
+    .map(|column| column.unwrap_or(SqlValue::Null));
+    let details = &sample.details;
+    let serde_json::Value::Object(map) = details else {
+        return (hot, (!details.is_null()).then(|| details.to_string()));
+    };
+    let mut rest = map.clone();
+    // Samples built by hand or read from older exports may carry the keys in `details`
+    for (column, key) in hot.iter_mut().zip(HOT_DETAILS) {
+        let value = match rest.get(key) {
+            Some(serde_json::Value::Number(n)) => match n.as_i64() {
+                Some(i) => SqlValue::Integer(i),
+                None => n.as_f64().map_or(SqlValue::Null, SqlValue::Real),
+            },
+            Some(serde_json::Value::String(text)) => SqlValue::Text(text.clone()),
+            _ => continue,
+        };
+        rest.remove(key);
+        if *column == SqlValue::Null {
+            *column = value;
+        }
+    }
+    let rest = (!rest.is_empty() || hot.iter().all(|v| *v == SqlValue::Null))
+        .then(|| serde_json::Value::Object(rest).to_string());
+    (hot, rest)
+}
+
+/// Store `samples`, skipping any whose timestamp, kind, source and host are already stored.
+/// Samples of a `skip_repeats` kind whose value, label and details equal the newest stored
+/// row of the same source and host are left out too; returns how many were stored.
 pub fn insert_metric_samples_with_conn(
     conn: &mut Connection,
     samples: &[MetricSample],
-) -> Result<()> {
+    skip_repeats: &[MetricKind],
+) -> Result<usize> {
     if samples.is_empty() {
-        return Ok(());
+        return Ok(0);
     }
     let tx = conn.transaction()?;
+    let mut stored = 0;
     {
         let mut stmt = tx.prepare(
             r#"
-            INSERT INTO metric_samples (
-                ts, kind, source, value, unit, details
-            ) VALUES (?, ?, ?, ?, ?, ?)
+            INSERT OR IGNORE INTO metric_samples (
+                ts, kind, source, value, unit, details, histogram, label, host, boot,
+                rx_bytes, tx_bytes, total_bytes, status
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )?;
+        let mut previous = tx.prepare(
+            "SELECT value, label, details, rx_bytes, tx_bytes, total_bytes, status \
+             FROM metric_samples \
+             WHERE kind = ? AND source = ? AND host IS ? ORDER BY ts DESC LIMIT 1",
+        )?;
         for sample in samples {
-            stmt.execute(params![
+            let (hot, details) = split_details(sample);
+            if skip_repeats.contains(&sample.kind) {
+                type Stored = (Option<f64>, Option<String>, Option<String>, [SqlValue; 4]);
+                let last: Option<Stored> = previous
+                    .query_row(
+                        params![sample.kind.as_str(), sample.source, sample.host],
+                        |row| {
+                            Ok((
+                                row.get(0)?,
+                                row.get(1)?,
+                                row.get(2)?,
+                                [row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?],
+                            ))
+                        },
+                    )
+                    .optional()?;
+                if last.as_ref()
+                    == Some(&(
+                        sample.value,
+                        sample.label.clone(),
+                        details.clone(),
+                        hot.clone(),
+                    ))
+                {
+                    continue;
+                }
+            }
+            let [rx_bytes, tx_bytes, total_bytes, status] = hot;
+            stored += stmt.execute(params![
                 sample.ts,
                 sample.kind.as_str(),
                 sample.source,
                 sample.value,
                 sample.unit,
-                serialize_details(&sample.details),
+                details,
+                sample
+                    .histogram
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+                sample.label,
+                sample.host,
+                sample.boot,
+                rx_bytes,
+                tx_bytes,
+                total_bytes,
+                status,
             ])?;
         }
     }
     tx.commit()?;
-    Ok(())
+    Ok(stored)
 }
 
 #[allow(dead_code)]
 pub fn insert_metric_samples(db_path: &Path, samples: &[MetricSample]) -> Result<()> {
-    let mut conn = Connection::open(db_path)?;
-    insert_metric_samples_with_conn(&mut conn, samples)
+    let mut conn = open_connection(db_path, OpenFlags::default())?;
+    insert_metric_samples_with_conn(&mut conn, samples, &[])?;
+    Ok(())
+}
+
+/// Host of the samples a database holds without a host tag, which came from the machine
+/// the database was made on: its stored hostname, else the file name of `db_path`.
+pub fn database_host(conn: &Connection, db_path: &Path) -> Result<String> {
+    Ok(system_snapshots(conn, None)?
+        .pop()
+        .and_then(|snapshot| snapshot.info.hostname)
+        .unwrap_or_else(|| {
+            db_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        }))
+}
+
+/// Timestamp of the newest sample of each host, untagged samples under `None`, including
+/// hosts only seen in monthly archives.
+pub fn last_seen_by_host(conn: &Connection) -> Result<Vec<(Option<String>, f64)>> {
+    let mut last_seen: BTreeMap<Option<String>, f64> = BTreeMap::new();
+    let mut add = |rows: Vec<(Option<String>, f64)>| {
+        for (host, ts) in rows {
+            let seen = last_seen.entry(host).or_insert(ts);
+            *seen = seen.max(ts);
+        }
+    };
+    for archive in monthly_archives(conn, None) {
+        add(with_archive(conn, &archive, |table| {
+            last_seen_from(conn, table)
+        })?);
+    }
+    add(last_seen_from(conn, "metric_samples")?);
+    Ok(last_seen.into_iter().collect())
+}
+
+fn last_seen_from(conn: &Connection, table: &str) -> Result<Vec<(Option<String>, f64)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT host, MAX(ts) FROM {table} GROUP BY host ORDER BY host"
+    ))?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
 }
 
 #[allow(dead_code)]
 pub fn count_metric_samples(db_path: &Path, since_ts: Option<f64>) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = open_connection(db_path, OpenFlags::default())?;
     count_metric_samples_with_conn(&conn, since_ts)
 }
 
+/// Number of samples since `since_ts`, including those in monthly archives.
 pub fn count_metric_samples_with_conn(conn: &Connection, since_ts: Option<f64>) -> Result<usize> {
-    let count: i64 = match since_ts {
-        Some(ts) => conn.query_row(
-            "SELECT COUNT(*) FROM metric_samples WHERE ts >= ?",
-            params![ts],
-            |row| row.get(0),
-        )?,
-        None => conn.query_row("SELECT COUNT(*) FROM metric_samples", [], |row| row.get(0))?,
-    };
+    let mut count = 0;
+    for archive in monthly_archives(conn, since_ts) {
+        count += with_archive(conn, &archive, |table| {
+            count_samples_from(conn, table, since_ts)
+        })?;
+    }
+    Ok(count + count_samples_from(conn, "metric_samples", since_ts)?)
+}
+
+fn count_samples_from(conn: &Connection, table: &str, since_ts: Option<f64>) -> Result<usize> {
+    let count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {table} WHERE ?1 IS NULL OR ts >= ?1"),
+        params![since_ts],
+        |row| row.get(0),
+    )?;
     Ok(count as usize)
 }
 
+/// Remember the interval `kinds` are being collected at, replacing earlier values.
+pub fn record_collection_intervals<'a>(
+    conn: &mut Connection,
+    kinds: impl IntoIterator<Item = &'a str>,
+    interval: f64,
+    ts: f64,
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO collection_intervals (kind, interval, updated) VALUES (?, ?, ?) \
+             ON CONFLICT(kind) DO UPDATE SET interval = excluded.interval, updated = excluded.updated",
+        )?;
+        for kind in kinds {
+            stmt.execute(params![kind, interval, ts])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Last recorded collection interval in seconds per kind. Kinds only ever collected by
+/// one-shot runs (timers, cron) have none.
+pub fn collection_intervals(conn: &Connection) -> Result<BTreeMap<String, f64>> {
+    let mut stmt = conn.prepare("SELECT kind, interval FROM collection_intervals")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn system_snapshot_from_row(row: &Row) -> rusqlite::Result<SystemSnapshot> {
+    Ok(SystemSnapshot {
+        ts: row.get("ts")?,
+        info: SystemInfo {
+            hostname: row.get("hostname")?,
+            kernel: row.get("kernel")?,
+            cpu_model: row.get("cpu_model")?,
+            memory_bytes: row.get("memory_bytes")?,
+            battery_model: row.get("battery_model")?,
+            battery_serial: row.get("battery_serial")?,
+        },
+    })
+}
+
+const SYSTEM_INFO_COLUMNS: &str =
+    "ts, hostname, kernel, cpu_model, memory_bytes, battery_model, battery_serial";
+
+/// Store the machine identity unless it matches the latest snapshot, so a timer running
+/// the collector every few minutes only adds a row when something changed (a kernel
+/// update, a swapped battery). Returns whether a row was written.
+pub fn record_system_info(conn: &Connection, info: &SystemInfo, ts: f64) -> Result<bool> {
+    let latest = conn
+        .query_row(
+            &format!("SELECT {SYSTEM_INFO_COLUMNS} FROM system_info ORDER BY ts DESC LIMIT 1"),
+            [],
+            system_snapshot_from_row,
+        )
+        .optional()?;
+    if latest.is_some_and(|snapshot| &snapshot.info == info) {
+        return Ok(false);
+    }
+    conn.execute(
+        &format!("INSERT INTO system_info ({SYSTEM_INFO_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?)"),
+        params![
+            ts,
+            info.hostname,
+            info.kernel,
+            info.cpu_model,
+            info.memory_bytes,
+            info.battery_model,
+            info.battery_serial,
+        ],
+    )?;
+    Ok(true)
+}
+
+/// Snapshots in effect during the window starting at `since_ts`: the last one taken
+/// before it and every one taken after, oldest first.
+pub fn system_snapshots(conn: &Connection, since_ts: Option<f64>) -> Result<Vec<SystemSnapshot>> {
+    let since = since_ts.unwrap_or(f64::NEG_INFINITY);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SYSTEM_INFO_COLUMNS} FROM system_info \
+         WHERE ts >= (SELECT COALESCE(MAX(ts), ?1) FROM system_info WHERE ts <= ?1) \
+         ORDER BY ts"
+    ))?;
+    let rows = stmt.query_map(params![since], system_snapshot_from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("invalid metric kind `{raw}` in database row")]
 struct MetricKindParseError {
@@ -115,11 +761,21 @@ fn metric_from_row(row: &Row) -> rusqlite::Result<MetricSample> {
             }),
         )
     })?;
-    let details_raw: Option<String> = row.get("details")?;
-    let details = match details_raw {
+    // Rows holding nothing but hot keys store no JSON, so most need no parsing
+    let details = match row.get::<_, Option<String>>("details")? {
         Some(text) => serde_json::from_str(&text).unwrap_or(serde_json::Value::Null),
         None => serde_json::Value::Null,
     };
+    // Databases opened without `init_db_connection` may predate these columns.
+    let hot = |column: &str| row.get::<_, SqlValue>(column).unwrap_or(SqlValue::Null);
+    let number = |column: &str| sql_as_number(hot(column));
+    let histogram = row
+        .get::<_, Option<String>>("histogram")
+        .unwrap_or(None)
+        .and_then(|text| serde_json::from_str(&text).ok());
+    let label = row.get::<_, Option<String>>("label").unwrap_or(None);
+    let host = row.get::<_, Option<String>>("host").unwrap_or(None);
+    let boot = row.get::<_, Option<String>>("boot").unwrap_or(None);
 
     Ok(MetricSample {
         ts: row.get("ts")?,
@@ -128,6 +784,14 @@ fn metric_from_row(row: &Row) -> rusqlite::Result<MetricSample> {
         value: row.get("value")?,
         unit: row.get::<_, Option<String>>("unit")?,
         details,
+        rx_bytes: number("rx_bytes"),
+        tx_bytes: number("tx_bytes"),
+        total_bytes: number("total_bytes"),
+        status: sql_as_text(hot("status")),
+        histogram,
+        label,
+        host,
+        boot,
     })
 }
 
@@ -137,51 +801,112 @@ pub fn fetch_metric_samples(
     since_ts: Option<f64>,
     kinds: Option<&[MetricKind]>,
 ) -> Result<Vec<MetricSample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = open_connection(db_path, OpenFlags::default())?;
     fetch_metric_samples_with_conn(&conn, since_ts, kinds)
 }
 
+/// Samples since `since_ts` (of `kinds`, when given), oldest first, including those in
+/// monthly archives next to the database.
 pub fn fetch_metric_samples_with_conn(
     conn: &Connection,
     since_ts: Option<f64>,
     kinds: Option<&[MetricKind]>,
 ) -> Result<Vec<MetricSample>> {
-    let kind_placeholders = kinds.map(|k| k.iter().map(|_| "?").collect::<Vec<_>>().join(", "));
+    fetch_metric_samples_between(conn, since_ts, None, kinds)
+}
 
-    let (sql, params_vec): (String, Vec<Box<dyn rusqlite::types::ToSql>>) =
-        match (since_ts, &kind_placeholders) {
-            (Some(_), Some(ph)) => (
-                format!(
-                    "SELECT * FROM metric_samples WHERE ts >= ? AND kind IN ({ph}) ORDER BY ts"
-                ),
-                {
-                    let mut v: Vec<Box<dyn rusqlite::types::ToSql>> =
-                        vec![Box::new(since_ts.unwrap())];
-                    for k in kinds.unwrap() {
-                        v.push(Box::new(k.as_str().to_string()));
-                    }
-                    v
-                },
-            ),
-            (Some(ts), None) => (
-                "SELECT * FROM metric_samples WHERE ts >= ? ORDER BY ts".to_string(),
-                vec![Box::new(ts)],
-            ),
-            (None, Some(ph)) => (
-                format!("SELECT * FROM metric_samples WHERE kind IN ({ph}) ORDER BY ts"),
-                {
-                    let mut v: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-                    for k in kinds.unwrap() {
-                        v.push(Box::new(k.as_str().to_string()));
-                    }
-                    v
-                },
-            ),
-            (None, None) => (
-                "SELECT * FROM metric_samples ORDER BY ts".to_string(),
-                Vec::new(),
-            ),
-        };
+/// Samples from `since_ts` up to and including `until_ts`, like
+/// [`fetch_metric_samples_with_conn`].
+pub fn fetch_metric_samples_between(
+    conn: &Connection,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    kinds: Option<&[MetricKind]>,
+) -> Result<Vec<MetricSample>> {
+    let mut samples = Vec::new();
+    for archive in monthly_archives(conn, since_ts) {
+        samples.extend(with_archive(conn, &archive, |table| {
+            fetch_samples_from(conn, table, since_ts, until_ts, kinds)
+        })?);
+    }
+    let archived = !samples.is_empty();
+    samples.extend(fetch_samples_from(
+        conn,
+        "metric_samples",
+        since_ts,
+        until_ts,
+        kinds,
+    )?);
+    if archived {
+        samples.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    }
+    Ok(samples)
+}
+
+/// Call `each` with every sample from `since_ts` up to `until_ts`, oldest first, reading
+/// row by row instead of collecting, so a long window can be piped out without holding
+/// it in memory. Monthly archives come first, each in order of time.
+pub fn for_each_metric_sample(
+    conn: &Connection,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    kinds: Option<&[MetricKind]>,
+    mut each: impl FnMut(MetricSample) -> Result<()>,
+) -> Result<()> {
+    for archive in monthly_archives(conn, since_ts) {
+        with_archive(conn, &archive, |table| {
+            for_each_sample_from(conn, table, since_ts, until_ts, kinds, &mut each)
+        })?;
+    }
+    for_each_sample_from(conn, "metric_samples", since_ts, until_ts, kinds, &mut each)
+}
+
+fn fetch_samples_from(
+    conn: &Connection,
+    table: &str,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    kinds: Option<&[MetricKind]>,
+) -> Result<Vec<MetricSample>> {
+    let mut samples = Vec::new();
+    for_each_sample_from(conn, table, since_ts, until_ts, kinds, &mut |sample| {
+        samples.push(sample);
+        Ok(())
+    })?;
+    Ok(samples)
+}
+
+fn for_each_sample_from(
+    conn: &Connection,
+    table: &str,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    kinds: Option<&[MetricKind]>,
+    each: &mut impl FnMut(MetricSample) -> Result<()>,
+) -> Result<()> {
+    let mut conditions = Vec::new();
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    if let Some(ts) = since_ts {
+        conditions.push("ts >= ?".to_string());
+        params_vec.push(Box::new(ts));
+    }
+    if let Some(ts) = until_ts {
+        conditions.push("ts <= ?".to_string());
+        params_vec.push(Box::new(ts));
+    }
+    if let Some(kinds) = kinds {
+        let placeholders = kinds.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+        conditions.push(format!("kind IN ({placeholders})"));
+        for kind in kinds {
+            params_vec.push(Box::new(kind.as_str().to_string()));
+        }
+    }
+    let filter = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    let sql = format!("SELECT * FROM {table}{filter} ORDER BY ts");
 
     let to_sql_refs: Vec<&dyn rusqlite::types::ToSql> =
         params_vec.iter().map(|b| b.as_ref()).collect();
@@ -191,11 +916,10 @@ pub fn fetch_metric_samples_with_conn(
         rusqlite::params_from_iter(to_sql_refs.iter()),
         metric_from_row,
     )?;
-    let mut samples = Vec::new();
     for row in rows {
-        samples.push(row?);
+        each(row?)?;
     }
-    Ok(samples)
+    Ok(())
 }
 
 #[allow(dead_code)]
@@ -203,11 +927,10 @@ pub fn fetch_latest_metric_samples(
     db_path: &Path,
     kinds: Option<&[MetricKind]>,
 ) -> Result<Vec<MetricSample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = open_connection(db_path, OpenFlags::default())?;
     fetch_latest_metric_samples_with_conn(&conn, kinds)
 }
 
-#[allow(dead_code)]
 pub fn fetch_latest_metric_samples_with_conn(
     conn: &Connection,
     kinds: Option<&[MetricKind]>,
@@ -253,6 +976,766 @@ pub fn fetch_latest_metric_samples_with_conn(
     Ok(samples)
 }
 
+/// Per-source statistics for one metric kind.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SourceCatalog {
+    pub source: String,
+    pub first_seen: f64,
+    pub last_seen: f64,
+    pub rows: u64,
+}
+
+/// Description of one metric kind stored in the database.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct KindCatalog {
+    pub kind: String,
+    /// `None` for kinds written by a newer version of symmetri
+    pub description: Option<&'static str>,
+    pub unit: Option<String>,
+    pub counter: bool,
+    /// Stored as 0/1 on/off values
+    pub state: bool,
+    /// Readings are names in `label` rather than numbers
+    pub categorical: bool,
+    /// Seconds between samples when last collected in a loop
+    pub interval: Option<f64>,
+    pub first_seen: f64,
+    pub last_seen: f64,
+    pub rows: u64,
+    pub sources: Vec<SourceCatalog>,
+}
+
+#[allow(dead_code)]
+pub fn catalog(db_path: &Path) -> Result<Vec<KindCatalog>> {
+    let conn = open_connection(db_path, OpenFlags::default())?;
+    catalog_with_conn(&conn)
+}
+
+/// Every kind present in the database or its monthly archives with its unit, sources,
+/// time span and row count.
+pub fn catalog_with_conn(conn: &Connection) -> Result<Vec<KindCatalog>> {
+    let mut rows = Vec::new();
+    for archive in monthly_archives(conn, None) {
+        rows.extend(with_archive(conn, &archive, |table| {
+            catalog_rows_from(conn, table)
+        })?);
+    }
+    rows.extend(catalog_rows_from(conn, "metric_samples")?);
+    Ok(merge_catalog(rows, &collection_intervals(conn)?))
+}
+
+/// Catalog of per-source rows (kind, span and count, unit), oldest store first: sources
+/// seen in several stores are merged, and the unit of the last one wins.
+pub(crate) fn merge_catalog(
+    rows: impl IntoIterator<Item = CatalogRow>,
+    intervals: &BTreeMap<String, f64>,
+) -> Vec<KindCatalog> {
+    let mut merged: BTreeMap<(String, String), (SourceCatalog, Option<String>)> = BTreeMap::new();
+    for (kind, source, unit) in rows {
+        match merged.entry((kind, source.source.clone())) {
+            std::collections::btree_map::Entry::Occupied(mut entry) => {
+                let (seen, seen_unit) = entry.get_mut();
+                seen.first_seen = seen.first_seen.min(source.first_seen);
+                seen.last_seen = seen.last_seen.max(source.last_seen);
+                seen.rows += source.rows;
+                if unit.is_some() {
+                    *seen_unit = unit;
+                }
+            }
+            std::collections::btree_map::Entry::Vacant(entry) => {
+                entry.insert((source, unit));
+            }
+        }
+    }
+
+    let mut catalog: Vec<KindCatalog> = Vec::new();
+    for ((kind, _), (source, unit)) in merged {
+        match catalog.last_mut() {
+            Some(entry) if entry.kind == kind => {
+                entry.first_seen = entry.first_seen.min(source.first_seen);
+                entry.last_seen = entry.last_seen.max(source.last_seen);
+                entry.rows += source.rows;
+                entry.unit = entry.unit.take().or(unit);
+                entry.sources.push(source);
+            }
+            _ => {
+                let known = MetricKind::from_str(&kind).ok();
+                catalog.push(KindCatalog {
+                    description: known.as_ref().map(MetricKind::description),
+                    counter: known.as_ref().is_some_and(MetricKind::is_counter),
+                    state: known.as_ref().is_some_and(MetricKind::is_state),
+                    categorical: known.as_ref().is_some_and(MetricKind::is_categorical),
+                    interval: intervals.get(&kind).copied(),
+                    kind,
+                    unit,
+                    first_seen: source.first_seen,
+                    last_seen: source.last_seen,
+                    rows: source.rows,
+                    sources: vec![source],
+                });
+            }
+        }
+    }
+    catalog
+}
+
+pub(crate) type CatalogRow = (String, SourceCatalog, Option<String>);
+
+fn catalog_rows_from(conn: &Connection, table: &str) -> Result<Vec<CatalogRow>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT kind, source, MIN(ts), MAX(ts), COUNT(*), MAX(unit) \
+         FROM {table} GROUP BY kind, source ORDER BY kind, source"
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            SourceCatalog {
+                source: row.get(1)?,
+                first_seen: row.get(2)?,
+                last_seen: row.get(3)?,
+                rows: row.get::<_, i64>(4)? as u64,
+            },
+            row.get::<_, Option<String>>(5)?,
+        ))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Log a raised alert at `ts`, delivered or held back.
+pub fn record_alert(conn: &Connection, alert: &Alert, ts: f64, sent: bool) -> Result<()> {
+    conn.execute(
+        "INSERT INTO alert_log (ts, title, severity, message, sent) VALUES (?, ?, ?, ?, ?)",
+        params![
+            ts,
+            alert.title,
+            alert.severity.as_str(),
+            alert.message,
+            sent
+        ],
+    )?;
+    Ok(())
+}
+
+/// When an alert titled `title` was last delivered.
+pub fn last_alert_sent(conn: &Connection, title: &str) -> Result<Option<f64>> {
+    Ok(conn.query_row(
+        "SELECT MAX(ts) FROM alert_log WHERE title = ? AND sent = 1",
+        [title],
+        |row| row.get(0),
+    )?)
+}
+
+/// Number of alerts delivered from `since` on.
+pub fn alerts_sent_since(conn: &Connection, since: f64) -> Result<usize> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM alert_log WHERE ts >= ? AND sent = 1",
+        [since],
+        |row| row.get(0),
+    )?;
+    Ok(count as usize)
+}
+
+/// Alerts held back after `since`, oldest first, only those titled `title` when given.
+pub fn held_alerts_since(
+    conn: &Connection,
+    since: Option<f64>,
+    title: Option<&str>,
+) -> Result<Vec<Alert>> {
+    let mut stmt = conn.prepare(
+        "SELECT severity, title, message FROM alert_log \
+         WHERE sent = 0 AND ts > ?1 AND (?2 IS NULL OR title = ?2) ORDER BY ts",
+    )?;
+    let rows = stmt.query_map(params![since.unwrap_or(f64::NEG_INFINITY), title], |row| {
+        Ok(Alert {
+            severity: Severity::from_label(&row.get::<_, String>(0)?),
+            title: row.get(1)?,
+            message: row.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Queue a batch for `sink`, dropping its oldest batches beyond `max_pending`.
+pub fn enqueue_push(
+    conn: &Connection,
+    sink: &str,
+    payload: &str,
+    max_pending: usize,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO push_outbox (sink, payload) VALUES (?, ?)",
+        params![sink, payload],
+    )?;
+    conn.execute(
+        "DELETE FROM push_outbox WHERE sink = ?1 AND id NOT IN \
+         (SELECT id FROM push_outbox WHERE sink = ?1 ORDER BY id DESC LIMIT ?2)",
+        params![sink, max_pending.max(1) as i64],
+    )?;
+    Ok(())
+}
+
+/// Batches queued for `sink`, oldest first.
+pub fn pending_pushes(conn: &Connection, sink: &str) -> Result<Vec<(i64, String)>> {
+    let mut stmt =
+        conn.prepare("SELECT id, payload FROM push_outbox WHERE sink = ? ORDER BY id")?;
+    let rows = stmt.query_map([sink], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+pub fn delete_push(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM push_outbox WHERE id = ?", [id])?;
+    Ok(())
+}
+
+/// Bytes received and sent per interface from `since` on, as the growth of the
+/// `network_bytes` counters (a drop, from a counter reset, counts as nothing). Readings from
+/// up to `seed_seconds` before `since` seed the counters, so the traffic between the last
+/// reading before `since` and the first one after it counts too. Only the samples of `host`
+/// when given.
+pub fn network_transfer_since(
+    conn: &Connection,
+    since: f64,
+    seed_seconds: f64,
+    host: Option<&str>,
+) -> Result<BTreeMap<String, (f64, f64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT source, SUM(MAX(rx, 0)), SUM(MAX(tx, 0)) FROM (
+            SELECT ts, source,
+                rx_bytes - LAG(rx_bytes) OVER counter AS rx,
+                tx_bytes - LAG(tx_bytes) OVER counter AS tx
+            FROM metric_samples
+            WHERE kind = 'network_bytes' AND ts >= ?1 AND (?3 IS NULL OR host = ?3)
+            WINDOW counter AS (PARTITION BY source, COALESCE(host, '') ORDER BY ts)
+        ) WHERE ts >= ?2 GROUP BY source",
+    )?;
+    let rows = stmt.query_map(params![since - seed_seconds, since, host], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            (
+                row.get::<_, Option<f64>>(1)?.unwrap_or(0.0),
+                row.get::<_, Option<f64>>(2)?.unwrap_or(0.0),
+            ),
+        ))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Length of the buckets stored in `metric_rollups`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rollup {
+    Hourly,
+    Daily,
+}
+
+impl Rollup {
+    pub const ALL: [Rollup; 2] = [Rollup::Hourly, Rollup::Daily];
+
+    pub fn seconds(self) -> i64 {
+        match self {
+            Rollup::Hourly => 3600,
+            Rollup::Daily => 86400,
+        }
+    }
+}
+
+/// End of the last bucket stored for `rollup`; raw samples from there on are not rolled up.
+pub fn rolled_until(conn: &Connection, rollup: Rollup) -> Result<Option<f64>> {
+    let last: Option<f64> = conn.query_row(
+        "SELECT MAX(ts) FROM metric_rollups WHERE resolution = ?",
+        [rollup.seconds()],
+        |row| row.get(0),
+    )?;
+    Ok(last.map(|ts| ts + rollup.seconds() as f64))
+}
+
+/// Roll the raw samples of every bucket completed by `now` (and not stored yet) up into
+/// `metric_rollups`, returning the number of rows written. Only the new buckets are read,
+/// so the collector can call it after every run.
+pub fn update_rollups(conn: &mut Connection, now: f64) -> Result<usize> {
+    let tx = conn.transaction()?;
+    let mut written = 0;
+    for rollup in Rollup::ALL {
+        let seconds = rollup.seconds();
+        let start = match rolled_until(&tx, rollup)? {
+            Some(until) => until,
+            None => {
+                let first: Option<f64> =
+                    tx.query_row("SELECT MIN(ts) FROM metric_samples", [], |row| row.get(0))?;
+                match first {
+                    Some(ts) => (ts / seconds as f64).floor() * seconds as f64,
+                    None => continue,
+                }
+            }
+        };
+        let end = (now / seconds as f64).floor() * seconds as f64;
+        if start >= end {
+            continue;
+        }
+        written += tx.execute(
+            "INSERT OR REPLACE INTO metric_rollups \
+             (resolution, ts, kind, source, host, samples, min, avg, max, unit) \
+             SELECT ?1, CAST(ts / ?1 AS INTEGER) * ?1 AS bucket, kind, source, \
+             COALESCE(host, ''), COUNT(value), MIN(value), AVG(value), MAX(value), MAX(unit) \
+             FROM metric_samples WHERE ts >= ?2 AND ts < ?3 AND value IS NOT NULL \
+             GROUP BY bucket, kind, source, COALESCE(host, '')",
+            params![seconds, start, end],
+        )?;
+    }
+    tx.commit()?;
+    Ok(written)
+}
+
+/// Rolled-up buckets of `kinds` from `since` until `until`, one sample per bucket and source
+/// at the bucket's midpoint, valued at the bucket average. The `min`, `max` and `samples`
+/// details keep the rest of the bucket.
+pub fn fetch_rollup_samples(
+    conn: &Connection,
+    rollup: Rollup,
+    since_ts: Option<f64>,
+    until_ts: f64,
+    kinds: &[MetricKind],
+) -> Result<Vec<MetricSample>> {
+    let seconds = rollup.seconds();
+    let mut stmt = conn.prepare(
+        "SELECT ts, source, samples, min, avg, max, unit, host FROM metric_rollups \
+         WHERE resolution = ? AND kind = ? AND ts >= ? AND ts < ? ORDER BY ts",
+    )?;
+    let mut samples = Vec::new();
+    for kind in kinds {
+        // A bucket holding `since` would pull in samples from before it
+        let since = since_ts.map_or(f64::NEG_INFINITY, |ts| {
+            (ts / seconds as f64).ceil() * seconds as f64
+        });
+        let rows = stmt.query_map(params![seconds, kind.as_str(), since, until_ts], |row| {
+            let bucket: f64 = row.get(0)?;
+            let mut sample = MetricSample::new(
+                bucket + seconds as f64 / 2.0,
+                kind.clone(),
+                row.get::<_, String>(1)?,
+                row.get(4)?,
+                row.get::<_, Option<String>>(6)?.as_deref(),
+                serde_json::json!({
+                    "samples": row.get::<_, i64>(2)?,
+                    "min": row.get::<_, Option<f64>>(3)?,
+                    "max": row.get::<_, Option<f64>>(5)?,
+                }),
+            );
+            sample.host = Some(row.get::<_, String>(7)?).filter(|host| !host.is_empty());
+            Ok(sample)
+        })?;
+        for row in rows {
+            samples.push(row?);
+        }
+    }
+    samples.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    Ok(samples)
+}
+
+/// Start and end of the local calendar month `year`-`month`, as timestamps.
+fn month_bounds(year: i32, month: u32) -> Option<(f64, f64)> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = first.checked_add_months(Months::new(1))?;
+    let start = |day: NaiveDate| {
+        Local
+            .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+            .earliest()
+            .map(|dt| dt.timestamp() as f64)
+    };
+    Some((start(first)?, start(next)?))
+}
+
+/// Archive of `year`-`month` next to the database at `db_path`, e.g. `metrics-2025-01.db`.
+fn archive_path(db_path: &Path, year: i32, month: u32) -> PathBuf {
+    let stem = db_path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}-{year:04}-{month:02}");
+    if let Some(extension) = db_path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    db_path.with_file_name(name)
+}
+
+/// File `conn` has open, unless it is an in-memory or temporary database.
+fn database_path(conn: &Connection) -> Option<PathBuf> {
+    conn.path()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Monthly archives next to the database `conn` has open that hold samples from
+/// `since_ts` on, oldest first.
+fn monthly_archives(conn: &Connection, since_ts: Option<f64>) -> Vec<PathBuf> {
+    let Some(db_path) = database_path(conn) else {
+        return Vec::new();
+    };
+    let Some(dir) = db_path.parent().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<(f64, PathBuf)> = dir
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            // `<stem>-YYYY-MM<.ext>`: the year and month are the 7 characters after the stem
+            let stem = db_path.file_stem()?.to_str()?;
+            let name = path.file_name()?.to_str()?;
+            let month = name.strip_prefix(stem)?.strip_prefix('-')?.get(..7)?;
+            let (year, month) = (month.get(..4)?.parse().ok()?, month.get(5..)?.parse().ok()?);
+            if archive_path(&db_path, year, month) != path {
+                return None;
+            }
+            let (start, end) = month_bounds(year, month)?;
+            since_ts
+                .is_none_or(|since| end > since)
+                .then_some((start, path))
+        })
+        .collect();
+    archives.sort_by(|a, b| a.0.total_cmp(&b.0));
+    archives.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Run `read` with the archive at `path` attached; it gets the archive's samples table.
+/// Archives written by an older version are migrated first.
+fn with_archive<T>(
+    conn: &Connection,
+    path: &Path,
+    read: impl FnOnce(&str) -> Result<T>,
+) -> Result<T> {
+    let attach = || conn.execute("ATTACH DATABASE ?1 AS archive", [path.to_string_lossy()]);
+    attach()?;
+    let version: usize = conn.query_row("PRAGMA archive.user_version", [], |row| row.get(0))?;
+    if version < MIGRATIONS.len() {
+        conn.execute_batch("DETACH DATABASE archive")?;
+        init_db_connection(path)?;
+        attach()?;
+    }
+    let result = read("archive.metric_samples");
+    conn.execute_batch("DETACH DATABASE archive")?;
+    result
+}
+
+/// Move samples from before the current local month into monthly archive files next to
+/// the database (`metrics-2025-01.db`), which reads attach again when their timeframe
+/// reaches back that far. Months the rollups have not covered yet stay. Returns how many
+/// samples moved.
+pub fn rotate_archives(conn: &mut Connection, now: f64) -> Result<usize> {
+    let Some(db_path) = database_path(conn) else {
+        return Ok(0);
+    };
+    let today = Local.timestamp_opt(now as i64, 0).single();
+    let Some((current_start, _)) = today.and_then(|dt| month_bounds(dt.year(), dt.month())) else {
+        return Ok(0);
+    };
+    let mut cutoff = current_start;
+    for rollup in Rollup::ALL {
+        cutoff = cutoff.min(rolled_until(conn, rollup)?.unwrap_or(f64::NEG_INFINITY));
+    }
+    let mut moved = 0;
+    loop {
+        let oldest: Option<f64> =
+            conn.query_row("SELECT MIN(ts) FROM metric_samples", [], |row| row.get(0))?;
+        let Some(oldest) = oldest.and_then(|ts| Local.timestamp_opt(ts as i64, 0).single()) else {
+            break;
+        };
+        let (year, month) = (oldest.year(), oldest.month());
+        let Some((start, end)) = month_bounds(year, month).filter(|(_, end)| *end <= cutoff) else {
+            break;
+        };
+        let path = archive_path(&db_path, year, month);
+        init_db_connection(&path)?;
+        conn.execute("ATTACH DATABASE ?1 AS archive", [path.to_string_lossy()])?;
+        let result = archive_month(conn, start, end);
+        conn.execute_batch("DETACH DATABASE archive")?;
+        moved += result?;
+    }
+    Ok(moved)
+}
+
+/// Move the samples in `[start, end)` into the attached `archive`.
+fn archive_month(conn: &mut Connection, start: f64, end: f64) -> Result<usize> {
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO archive.metric_samples \
+         (ts, kind, source, value, unit, details, histogram, label, host, boot, \
+          rx_bytes, tx_bytes, total_bytes, status) \
+         SELECT ts, kind, source, value, unit, details, histogram, label, host, boot, \
+             rx_bytes, tx_bytes, total_bytes, status \
+         FROM metric_samples WHERE ts >= ?1 AND ts < ?2",
+        params![start, end],
+    )?;
+    let moved = tx.execute(
+        "DELETE FROM metric_samples WHERE ts >= ?1 AND ts < ?2",
+        params![start, end],
+    )?;
+    tx.commit()?;
+    Ok(moved)
+}
+
+/// Min/avg/max of one source's values within one bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketStats {
+    pub source: String,
+    /// Bucket start, aligned to local time like `cli_helpers::bucket_start`
+    pub start: i64,
+    pub count: usize,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+impl BucketStats {
+    fn merge(&mut self, other: &BucketStats) {
+        let count = self.count + other.count;
+        self.avg = (self.avg * self.count as f64 + other.avg * other.count as f64) / count as f64;
+        self.count = count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// Which stored rows of a kind [`fetch_bucketed_stats`] and [`fetch_series_spans`] read,
+/// and under which source, mirroring the report's filters.
+#[derive(Debug, Clone, Default)]
+pub struct SampleFilter<'a> {
+    pub since: Option<f64>,
+    /// Inclusive end of the window
+    pub until: Option<f64>,
+    /// Hosts to keep (all when empty); untagged samples are left out when set
+    pub hosts: &'a [String],
+    /// Lists of source patterns (`*` is a wildcard); a source must match one pattern of
+    /// every non-empty list
+    pub sources: Vec<&'a [String]>,
+    /// The one source to keep
+    pub source: Option<&'a str>,
+    /// A source to leave out
+    pub exclude_source: Option<&'a str>,
+    /// Label each row with its device: the recorded `device`, or the text before `:`
+    pub by_device: bool,
+    /// Average the sources of each collection into one row under this source
+    pub merge_sources: Option<&'a str>,
+}
+
+/// Device of a temperature row, as `report::group_temperature_by_device` picks it.
+const DEVICE_SOURCE_SQL: &str = "COALESCE( \
+         CASE WHEN json_valid(details) AND json_type(details, '$.device') = 'text' \
+             THEN json_extract(details, '$.device') END, \
+         CASE WHEN instr(source, ':') > 0 THEN substr(source, 1, instr(source, ':') - 1) \
+             ELSE source END)";
+
+/// `pattern` with `*` wildcards as a GLOB pattern matching the same sources.
+fn source_glob(pattern: &str) -> String {
+    pattern
+        .chars()
+        .map(|c| match c {
+            '?' => "[?]".to_string(),
+            '[' => "[[]".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// `SELECT source, value, ts` over the rows of `kind` in `table` that `filter` keeps.
+fn filtered_rows(
+    table: &str,
+    kind: &MetricKind,
+    filter: &SampleFilter,
+) -> (String, Vec<Box<dyn rusqlite::types::ToSql>>) {
+    let mut conditions = vec!["kind = ?".to_string(), "value IS NOT NULL".to_string()];
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> =
+        vec![Box::new(kind.as_str().to_string())];
+    if let Some(ts) = filter.since {
+        conditions.push("ts >= ?".to_string());
+        params_vec.push(Box::new(ts));
+    }
+    if let Some(ts) = filter.until {
+        conditions.push("ts <= ?".to_string());
+        params_vec.push(Box::new(ts));
+    }
+    if !filter.hosts.is_empty() {
+        let placeholders = filter.hosts.iter().map(|_| "?").collect::<Vec<_>>();
+        conditions.push(format!("host IN ({})", placeholders.join(", ")));
+        for host in filter.hosts {
+            params_vec.push(Box::new(host.clone()));
+        }
+    }
+    for patterns in filter
+        .sources
+        .iter()
+        .filter(|patterns| !patterns.is_empty())
+    {
+        let globs = patterns.iter().map(|_| "source GLOB ?").collect::<Vec<_>>();
+        conditions.push(format!("({})", globs.join(" OR ")));
+        for pattern in patterns.iter() {
+            params_vec.push(Box::new(source_glob(pattern)));
+        }
+    }
+    if let Some(source) = filter.source {
+        conditions.push("source = ?".to_string());
+        params_vec.push(Box::new(source.to_string()));
+    }
+    if let Some(source) = filter.exclude_source {
+        conditions.push("source <> ?".to_string());
+        params_vec.push(Box::new(source.to_string()));
+    }
+    let conditions = conditions.join(" AND ");
+    let sql = match filter.merge_sources {
+        Some(label) => {
+            params_vec.insert(0, Box::new(label.to_string()));
+            format!(
+                "SELECT ? AS source, AVG(value) AS value, ts FROM {table} \
+                 WHERE {conditions} GROUP BY host, ts"
+            )
+        }
+        None => {
+            let source = if filter.by_device {
+                DEVICE_SOURCE_SQL
+            } else {
+                "source"
+            };
+            format!("SELECT {source} AS source, value, ts FROM {table} WHERE {conditions}")
+        }
+    };
+    (sql, params_vec)
+}
+
+/// Per-source stats of `kind` in `bucket_seconds` buckets over the rows `filter` keeps,
+/// computed by SQLite rather than by reading every sample.
+pub fn fetch_bucketed_stats(
+    conn: &Connection,
+    kind: &MetricKind,
+    bucket_seconds: i64,
+    filter: &SampleFilter,
+) -> Result<Vec<BucketStats>> {
+    let mut merged: BTreeMap<(String, i64), BucketStats> = BTreeMap::new();
+    let mut add = |rows: Vec<BucketStats>| {
+        for row in rows {
+            match merged.entry((row.source.clone(), row.start)) {
+                std::collections::btree_map::Entry::Occupied(mut entry) => {
+                    entry.get_mut().merge(&row)
+                }
+                std::collections::btree_map::Entry::Vacant(entry) => {
+                    entry.insert(row);
+                }
+            }
+        }
+    };
+    // A bucket can span the end of an archived month
+    for archive in monthly_archives(conn, filter.since) {
+        add(with_archive(conn, &archive, |table| {
+            bucketed_stats_from(conn, table, kind, bucket_seconds, filter)
+        })?);
+    }
+    add(bucketed_stats_from(
+        conn,
+        "metric_samples",
+        kind,
+        bucket_seconds,
+        filter,
+    )?);
+    Ok(merged.into_values().collect())
+}
+
+fn bucketed_stats_from(
+    conn: &Connection,
+    table: &str,
+    kind: &MetricKind,
+    bucket_seconds: i64,
+    filter: &SampleFilter,
+) -> Result<Vec<BucketStats>> {
+    let (rows_sql, mut params_vec) = filtered_rows(table, kind, filter);
+    params_vec.push(Box::new(bucket_seconds));
+    let mut stmt = conn.prepare(&format!(
+        "WITH filtered AS ({rows_sql}), \
+         local AS ( \
+             SELECT source, value, ts, \
+                 CAST(strftime('%s', ts, 'unixepoch', 'localtime') AS INTEGER) \
+                     - CAST(ts AS INTEGER) AS utc_offset \
+             FROM filtered \
+         ) \
+         SELECT source, \
+             CAST((ts + utc_offset) / ?{n} AS INTEGER) * ?{n} - utc_offset AS bucket, \
+             COUNT(*), MIN(value), AVG(value), MAX(value) \
+         FROM local GROUP BY source, bucket ORDER BY source, bucket",
+        n = params_vec.len()
+    ))?;
+    let to_sql_refs: Vec<&dyn rusqlite::types::ToSql> =
+        params_vec.iter().map(|b| b.as_ref()).collect();
+    let rows = stmt.query_map(rusqlite::params_from_iter(to_sql_refs.iter()), |row| {
+        Ok(BucketStats {
+            source: row.get(0)?,
+            start: row.get(1)?,
+            count: row.get::<_, i64>(2)? as usize,
+            min: row.get(3)?,
+            avg: row.get(4)?,
+            max: row.get(5)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// First and last timestamp and row count of one source's series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesSpan {
+    pub source: String,
+    pub first: f64,
+    pub last: f64,
+    pub count: usize,
+}
+
+/// Span of each source's series of `kind` over the rows `filter` keeps, for callers that
+/// leave the rows themselves to [`fetch_bucketed_stats`].
+pub fn fetch_series_spans(
+    conn: &Connection,
+    kind: &MetricKind,
+    filter: &SampleFilter,
+) -> Result<Vec<SeriesSpan>> {
+    let mut merged: BTreeMap<String, SeriesSpan> = BTreeMap::new();
+    let mut add = |rows: Vec<SeriesSpan>| {
+        for row in rows {
+            match merged.entry(row.source.clone()) {
+                std::collections::btree_map::Entry::Occupied(mut entry) => {
+                    let span = entry.get_mut();
+                    span.first = span.first.min(row.first);
+                    span.last = span.last.max(row.last);
+                    span.count += row.count;
+                }
+                std::collections::btree_map::Entry::Vacant(entry) => {
+                    entry.insert(row);
+                }
+            }
+        }
+    };
+    for archive in monthly_archives(conn, filter.since) {
+        add(with_archive(conn, &archive, |table| {
+            series_spans_from(conn, table, kind, filter)
+        })?);
+    }
+    add(series_spans_from(conn, "metric_samples", kind, filter)?);
+    Ok(merged.into_values().collect())
+}
+
+fn series_spans_from(
+    conn: &Connection,
+    table: &str,
+    kind: &MetricKind,
+    filter: &SampleFilter,
+) -> Result<Vec<SeriesSpan>> {
+    let (rows_sql, params_vec) = filtered_rows(table, kind, filter);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT source, MIN(ts), MAX(ts), COUNT(*) FROM ({rows_sql}) GROUP BY source"
+    ))?;
+    let to_sql_refs: Vec<&dyn rusqlite::types::ToSql> =
+        params_vec.iter().map(|b| b.as_ref()).collect();
+    let rows = stmt.query_map(rusqlite::params_from_iter(to_sql_refs.iter()), |row| {
+        Ok(SeriesSpan {
+            source: row.get(0)?,
+            first: row.get(1)?,
+            last: row.get(2)?,
+            count: row.get::<_, i64>(3)? as usize,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +1756,14 @@ mod tests {
                 value: Some(42.0),
                 unit: Some("%".to_string()),
                 details: json!({"note": "first"}),
+                rx_bytes: None,
+                tx_bytes: None,
+                total_bytes: None,
+                status: None,
+                histogram: None,
+                label: None,
+                host: None,
+                boot: None,
             },
             MetricSample {
                 ts: 2.0,
@@ -281,6 +1772,14 @@ mod tests {
                 value: Some(50.0),
                 unit: Some("%".to_string()),
                 details: serde_json::Value::Null,
+                rx_bytes: None,
+                tx_bytes: None,
+                total_bytes: None,
+                status: None,
+                histogram: None,
+                label: None,
+                host: Some("thinkpad".to_string()),
+                boot: None,
             },
             MetricSample {
                 ts: 2.0,
@@ -288,7 +1787,15 @@ mod tests {
                 source: "eth0".to_string(),
                 value: Some(1000.0),
                 unit: Some("bytes".to_string()),
-                details: json!({"rx_bytes": 750, "tx_bytes": 250}),
+                details: serde_json::Value::Null,
+                rx_bytes: Some(750.0),
+                tx_bytes: Some(250.0),
+                total_bytes: None,
+                status: None,
+                histogram: None,
+                label: None,
+                host: None,
+                boot: None,
             },
         ];
 
@@ -301,6 +1808,12 @@ mod tests {
             fetch_metric_samples(&db_path, Some(1.5), Some(&[MetricKind::CpuUsage])).unwrap();
         assert_eq!(filtered.len(), 1);
         assert_eq!(filtered[0].value, Some(50.0));
+        assert_eq!(filtered[0].host.as_deref(), Some("thinkpad"));
+        let conn = Connection::open(&db_path).unwrap();
+        assert_eq!(
+            last_seen_by_host(&conn).unwrap(),
+            [(None, 2.0), (Some("thinkpad".to_string()), 2.0)]
+        );
 
         let latest = fetch_latest_metric_samples(&db_path, None).unwrap();
         assert_eq!(latest.len(), 2);
@@ -308,6 +1821,518 @@ mod tests {
         assert_eq!(latest[0].value, Some(50.0));
     }
 
+    #[test]
+    fn hot_details_live_in_columns() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        let before = MIGRATIONS.len() - 1;
+        for step in &MIGRATIONS[..before] {
+            conn.execute_batch(step).unwrap();
+        }
+        conn.pragma_update(None, "user_version", before).unwrap();
+        conn.execute_batch(
+            r#"INSERT INTO metric_samples (ts, kind, source, details) VALUES
+                (1.0, 'network_bytes', 'eth0', '{"rx_bytes":750,"tx_bytes":250}'),
+                (1.0, 'battery_percentage', 'BAT0', '{"status":"Charging","cycle":3}'),
+                (1.0, 'cpu_usage', 'cpu', '{}');"#,
+        )
+        .unwrap();
+        drop(conn);
+
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let disk = MetricSample::new(
+            2.0,
+            MetricKind::DiskUsage,
+            "/",
+            Some(40.0),
+            Some("bytes"),
+            json!({"mount": "/"}),
+        )
+        .with_total_bytes(100.5);
+        insert_metric_samples_with_conn(&mut conn, &[disk], &[]).unwrap();
+        let stored: Vec<(Option<String>, SqlValue)> = conn
+            .prepare("SELECT details, COALESCE(rx_bytes, status, total_bytes) FROM metric_samples ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            stored,
+            [
+                (None, SqlValue::Integer(750)),
+                (
+                    Some(r#"{"cycle":3}"#.to_string()),
+                    SqlValue::Text("Charging".into())
+                ),
+                (Some("{}".to_string()), SqlValue::Null),
+                (Some(r#"{"mount":"/"}"#.to_string()), SqlValue::Real(100.5)),
+            ]
+        );
+
+        let samples = fetch_metric_samples_with_conn(&conn, None, None).unwrap();
+        let by_kind = |kind: MetricKind| samples.iter().find(|s| s.kind == kind).unwrap();
+        let network = by_kind(MetricKind::NetworkBytes);
+        assert_eq!(
+            (network.rx_bytes, network.tx_bytes),
+            (Some(750.0), Some(250.0))
+        );
+        assert_eq!(network.details, serde_json::Value::Null);
+        let battery = by_kind(MetricKind::BatteryPercentage);
+        assert_eq!(battery.status.as_deref(), Some("Charging"));
+        assert_eq!(battery.details, json!({"cycle": 3}));
+        let disk = by_kind(MetricKind::DiskUsage);
+        assert_eq!(disk.total_bytes, Some(100.5));
+        assert_eq!(disk.details, json!({"mount": "/"}));
+    }
+
+    #[test]
+    fn legacy_databases_gain_histogram_column() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(SCHEMA)
+            .unwrap();
+
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let histogram = crate::histogram::Histogram::new(vec![5.0, 10.0], vec![3, 1]).unwrap();
+        let sample = MetricSample::new(
+            1.0,
+            MetricKind::Latency,
+            "1.1.1.1",
+            None,
+            Some("ms"),
+            json!({}),
+        )
+        .with_histogram(histogram.clone());
+        insert_metric_samples_with_conn(&mut conn, &[sample], &[]).unwrap();
+        drop(conn);
+
+        let conn = init_db_connection(&db_path).unwrap();
+        let version: usize = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        let stored = fetch_metric_samples_with_conn(&conn, None, None).unwrap();
+        assert_eq!(stored[0].histogram.as_ref(), Some(&histogram));
+        // The median stands in as the scalar value
+        assert!((stored[0].value.unwrap() - 10.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bucketed_stats_match_local_buckets() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut conn = init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let start = 1_700_000_000.0;
+        let samples: Vec<MetricSample> = (0..40)
+            .map(|i| {
+                let source = if i % 2 == 0 { "cpu" } else { "cpu0" };
+                let value = (i * 7 % 13) as f64;
+                MetricSample::new(
+                    start + i as f64 * 1000.5,
+                    MetricKind::CpuUsage,
+                    source,
+                    Some(value),
+                    None,
+                    json!(null),
+                )
+            })
+            .collect();
+        insert_metric_samples_with_conn(&mut conn, &samples, &[]).unwrap();
+
+        let since = start + 5000.0;
+        let stats = fetch_bucketed_stats(
+            &conn,
+            &MetricKind::CpuUsage,
+            3600,
+            &SampleFilter {
+                since: Some(since),
+                ..SampleFilter::default()
+            },
+        )
+        .unwrap();
+        let mut expected: BTreeMap<(String, i64), Vec<f64>> = BTreeMap::new();
+        for sample in samples.iter().filter(|m| m.ts >= since) {
+            let bucket = crate::cli_helpers::bucket_start(sample.ts, 3600).timestamp();
+            expected
+                .entry((sample.source.clone(), bucket))
+                .or_default()
+                .push(sample.value.unwrap());
+        }
+        assert_eq!(stats.len(), expected.len());
+        for (row, ((source, bucket), values)) in stats.iter().zip(&expected) {
+            assert_eq!((&row.source, row.start), (source, *bucket));
+            assert_eq!(row.count, values.len());
+            assert_eq!(
+                row.min,
+                values.iter().copied().fold(f64::INFINITY, f64::min)
+            );
+            assert_eq!(
+                row.max,
+                values.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+            );
+            let avg = values.iter().sum::<f64>() / values.len() as f64;
+            assert!((row.avg - avg).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn backups_restore_into_a_live_database() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut conn = init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let sample = |ts: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::CpuUsage,
+                "cpu",
+                Some(5.0),
+                None,
+                json!(null),
+            )
+        };
+        insert_metric_samples_with_conn(&mut conn, &[sample(1.0)], &[]).unwrap();
+        let backup = tmp.path().join("backup.db");
+        backup_db(&conn, &backup).unwrap();
+        assert!(backup_db(&conn, &backup).is_err());
+
+        insert_metric_samples_with_conn(&mut conn, &[sample(2.0)], &[]).unwrap();
+        restore_db(&mut conn, &backup).unwrap();
+        let restored = fetch_metric_samples_with_conn(&conn, None, None).unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].ts, 1.0);
+
+        let other = tmp.path().join("other.db");
+        Connection::open(&other)
+            .unwrap()
+            .execute_batch("CREATE TABLE notes (text TEXT);")
+            .unwrap();
+        assert!(restore_db(&mut conn, &other).is_err());
+    }
+
+    #[test]
+    fn damaged_databases_are_salvaged_into_a_new_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let note = "x".repeat(500);
+        let samples: Vec<MetricSample> = (0..2000)
+            .map(|i| {
+                MetricSample::new(
+                    i as f64,
+                    MetricKind::CpuUsage,
+                    "cpu",
+                    Some(5.0),
+                    None,
+                    json!({ "note": note }),
+                )
+            })
+            .collect();
+        insert_metric_samples_with_conn(&mut conn, &samples, &[]).unwrap();
+        let pages: u64 = conn
+            .query_row("PRAGMA page_count", [], |row| row.get(0))
+            .unwrap();
+        let page_size: u64 = conn
+            .query_row("PRAGMA page_size", [], |row| row.get(0))
+            .unwrap();
+        drop(conn);
+        assert!(integrity_check(&db_path).unwrap().is_empty());
+
+        // A torn write: a few pages in the middle of the file overwritten with garbage
+        let mut bytes = fs::read(&db_path).unwrap();
+        let start = (pages / 2 * page_size) as usize;
+        bytes[start..start + 4 * page_size as usize].fill(0xAA);
+        fs::write(&db_path, bytes).unwrap();
+        assert!(!integrity_check(&db_path).unwrap().is_empty());
+
+        let recovered = tmp.path().join("recovered.db");
+        let recovery = recover_db(&db_path, &recovered).unwrap();
+        assert!(recovery.rows > 1000 && recovery.rows < 2000);
+        assert!(recovery.unreadable > 0);
+        assert!(recover_db(&db_path, &recovered).is_err());
+        assert!(integrity_check(&recovered).unwrap().is_empty());
+        let conn = init_db_connection(&recovered).unwrap();
+        let salvaged = fetch_metric_samples_with_conn(&conn, None, None).unwrap();
+        assert_eq!(salvaged.len(), recovery.rows);
+        assert_eq!(salvaged[0].details["note"], note.as_str());
+    }
+
+    #[test]
+    fn streamed_samples_follow_the_filters() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut conn = init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let samples: Vec<MetricSample> = (0..5)
+            .flat_map(|i| {
+                let ts = i as f64 * 60.0;
+                [
+                    MetricSample::new(ts, MetricKind::CpuUsage, "cpu", Some(1.0), None, json!({})),
+                    MetricSample::new(
+                        ts,
+                        MetricKind::Temperature,
+                        "cpu",
+                        Some(50.0),
+                        None,
+                        json!({}),
+                    ),
+                ]
+            })
+            .collect();
+        insert_metric_samples_with_conn(&mut conn, &samples, &[]).unwrap();
+
+        let mut streamed = Vec::new();
+        for_each_metric_sample(
+            &conn,
+            Some(60.0),
+            Some(180.0),
+            Some(&[MetricKind::CpuUsage]),
+            |sample| {
+                streamed.push(sample.ts);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(streamed, [60.0, 120.0, 180.0]);
+
+        // An error from the callback stops the stream
+        let mut seen = 0;
+        let stopped = for_each_metric_sample(&conn, None, None, None, |_| {
+            seen += 1;
+            bail!("closed")
+        });
+        assert!(stopped.is_err());
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    fn ad_hoc_queries_are_read_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let samples = [
+            MetricSample::new(
+                1.0,
+                MetricKind::CpuUsage,
+                "cpu",
+                Some(40.0),
+                Some("%"),
+                json!({}),
+            ),
+            MetricSample::new(2.0, MetricKind::CpuUsage, "cpu", None, Some("%"), json!({})),
+        ];
+        insert_metric_samples_with_conn(&mut conn, &samples, &[]).unwrap();
+        drop(conn);
+
+        let result = query_read_only(
+            &db_path,
+            "SELECT ts, value AS usage FROM metric_samples ORDER BY ts",
+        )
+        .unwrap();
+        assert_eq!(result.columns, ["ts", "usage"]);
+        assert_eq!(result.rows, [["1", "40"], ["2", ""]]);
+
+        assert!(query_read_only(&db_path, "DELETE FROM metric_samples").is_err());
+        assert!(query_read_only(&tmp.path().join("missing.db"), "SELECT 1").is_err());
+        let conn = init_db_connection(&db_path).unwrap();
+        assert_eq!(
+            fetch_metric_samples_with_conn(&conn, None, None)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn repeated_inserts_store_each_sample_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut conn = init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let sample = |source: &str, host: Option<&str>| {
+            let mut sample = MetricSample::new(
+                1.0,
+                MetricKind::CpuUsage,
+                source,
+                Some(5.0),
+                None,
+                json!(null),
+            );
+            sample.host = host.map(str::to_string);
+            sample
+        };
+        let batch = [
+            sample("cpu", None),
+            sample("cpu0", None),
+            sample("cpu", Some("desktop")),
+        ];
+        assert_eq!(
+            insert_metric_samples_with_conn(&mut conn, &batch, &[]).unwrap(),
+            3
+        );
+        assert_eq!(
+            insert_metric_samples_with_conn(&mut conn, &batch, &[]).unwrap(),
+            0
+        );
+        assert_eq!(count_metric_samples_with_conn(&conn, None).unwrap(), 3);
+    }
+
+    #[test]
+    fn unchanged_samples_of_skip_repeat_kinds_are_not_stored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut conn = init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let disk = |ts: f64, source: &str, used: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::DiskUsage,
+                source,
+                Some(used),
+                Some("bytes"),
+                serde_json::Value::Null,
+            )
+            .with_total_bytes(100.0)
+        };
+        let skip = [MetricKind::DiskUsage];
+        let cpu = |ts: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::CpuUsage,
+                "cpu",
+                Some(5.0),
+                None,
+                json!(null),
+            )
+        };
+        let first = [disk(1.0, "/", 40.0), disk(1.0, "/home", 40.0), cpu(1.0)];
+        assert_eq!(
+            insert_metric_samples_with_conn(&mut conn, &first, &skip).unwrap(),
+            3
+        );
+        // Repeats of other kinds are still stored
+        let second = [disk(2.0, "/", 40.0), disk(2.0, "/home", 41.0), cpu(2.0)];
+        assert_eq!(
+            insert_metric_samples_with_conn(&mut conn, &second, &skip).unwrap(),
+            2
+        );
+        let mut back = disk(3.0, "/home", 40.0);
+        assert_eq!(
+            insert_metric_samples_with_conn(&mut conn, &[back.clone()], &skip).unwrap(),
+            1
+        );
+        back.ts = 4.0;
+        back.total_bytes = Some(200.0);
+        assert_eq!(
+            insert_metric_samples_with_conn(&mut conn, &[back], &skip).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn catalog_groups_sources_per_kind() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        init_db(&db_path).unwrap();
+        let sample = |ts: f64, kind: MetricKind, source: &str| {
+            MetricSample::new(ts, kind, source, Some(1.0), Some("bytes"), json!(null))
+        };
+        insert_metric_samples(
+            &db_path,
+            &[
+                sample(1.0, MetricKind::NetworkBytes, "wlan0"),
+                sample(5.0, MetricKind::NetworkBytes, "wlan0"),
+                sample(3.0, MetricKind::NetworkBytes, "eth0"),
+                sample(2.0, MetricKind::MemoryUsage, "ram"),
+            ],
+        )
+        .unwrap();
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute(
+            "INSERT INTO metric_samples (ts, kind, source) VALUES (4.0, 'from_the_future', 'x')",
+            [],
+        )
+        .unwrap();
+
+        let catalog = catalog(&db_path).unwrap();
+        let kinds: Vec<&str> = catalog.iter().map(|k| k.kind.as_str()).collect();
+        assert_eq!(
+            kinds,
+            vec!["from_the_future", "memory_usage", "network_bytes"]
+        );
+        let network = &catalog[2];
+        assert!(network.counter);
+        assert_eq!(network.unit.as_deref(), Some("bytes"));
+        assert_eq!(
+            (network.first_seen, network.last_seen, network.rows),
+            (1.0, 5.0, 3)
+        );
+        assert_eq!(network.sources.len(), 2);
+        assert_eq!(network.sources[0].source, "eth0");
+        assert_eq!(catalog[0].description, None);
+        assert_eq!(network.interval, None);
+
+        let mut conn = init_db_connection(&db_path).unwrap();
+        record_collection_intervals(&mut conn, ["network_bytes"], 60.0, 10.0).unwrap();
+        record_collection_intervals(&mut conn, ["network_bytes"], 30.0, 20.0).unwrap();
+        assert_eq!(catalog_with_conn(&conn).unwrap()[2].interval, Some(30.0));
+    }
+
+    #[test]
+    fn rollups_cover_completed_buckets_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut conn = init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let cpu = |ts: f64, value: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::CpuUsage,
+                "cpu",
+                Some(value),
+                Some("%"),
+                json!({}),
+            )
+        };
+        let day = 86400.0;
+        let samples = vec![
+            cpu(day + 60.0, 10.0),
+            cpu(day + 120.0, 30.0),
+            cpu(day + 3700.0, 50.0),
+            cpu(day + 7300.0, 90.0),
+        ];
+        insert_metric_samples_with_conn(&mut conn, &samples, &[]).unwrap();
+
+        // The third hour is still running and the day is not over
+        assert_eq!(update_rollups(&mut conn, day + 7400.0).unwrap(), 2);
+        assert_eq!(update_rollups(&mut conn, day + 7400.0).unwrap(), 0);
+        assert_eq!(
+            rolled_until(&conn, Rollup::Hourly).unwrap(),
+            Some(day + 7200.0)
+        );
+        assert_eq!(rolled_until(&conn, Rollup::Daily).unwrap(), None);
+
+        let hours = fetch_rollup_samples(
+            &conn,
+            Rollup::Hourly,
+            None,
+            day + 7200.0,
+            &[MetricKind::CpuUsage],
+        )
+        .unwrap();
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[0].ts, day + 1800.0);
+        assert_eq!(hours[0].value, Some(20.0));
+        assert_eq!(hours[0].details["max"], 30.0);
+        assert_eq!(hours[0].details["samples"], 2);
+        assert_eq!(hours[0].unit.as_deref(), Some("%"));
+
+        assert_eq!(update_rollups(&mut conn, 2.0 * day).unwrap(), 2);
+        let days = fetch_rollup_samples(
+            &conn,
+            Rollup::Daily,
+            None,
+            2.0 * day,
+            &[MetricKind::CpuUsage],
+        )
+        .unwrap();
+        assert_eq!(days[0].value, Some(45.0));
+    }
+
     #[test]
     fn battery_metrics_roundtrip() {
         let tmp = tempfile::tempdir().unwrap();
@@ -321,7 +2346,15 @@ mod tests {
                 source: "BAT0".to_string(),
                 value: Some(75.0),
                 unit: Some("%".to_string()),
-                details: json!({"status": "Discharging"}),
+                details: serde_json::Value::Null,
+                rx_bytes: None,
+                tx_bytes: None,
+                total_bytes: None,
+                status: Some("Discharging".to_string()),
+                histogram: None,
+                label: None,
+                host: None,
+                boot: None,
             },
             MetricSample {
                 ts: 10.0,
@@ -329,7 +2362,15 @@ mod tests {
                 source: "BAT0".to_string(),
                 value: Some(87.5),
                 unit: Some("%".to_string()),
-                details: json!({"status": "Discharging"}),
+                details: serde_json::Value::Null,
+                rx_bytes: None,
+                tx_bytes: None,
+                total_bytes: None,
+                status: Some("Discharging".to_string()),
+                histogram: None,
+                label: None,
+                host: None,
+                boot: None,
             },
         ];
 
@@ -342,4 +2383,98 @@ mod tests {
         assert_eq!(stored.value, Some(75.0));
         assert_eq!(stored.kind, MetricKind::BatteryPercentage);
     }
+
+    #[test]
+    fn system_info_is_stored_when_it_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let conn = init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let mut info = SystemInfo {
+            hostname: Some("thinkpad".to_string()),
+            kernel: Some("6.8.0".to_string()),
+            memory_bytes: Some(16 << 30),
+            ..SystemInfo::default()
+        };
+        assert!(system_snapshots(&conn, None).unwrap().is_empty());
+        assert!(record_system_info(&conn, &info, 10.0).unwrap());
+        assert!(!record_system_info(&conn, &info, 20.0).unwrap());
+        info.kernel = Some("6.9.1".to_string());
+        assert!(record_system_info(&conn, &info, 30.0).unwrap());
+        assert!(record_system_info(&conn, &SystemInfo::default(), 50.0).unwrap());
+
+        let all = system_snapshots(&conn, None).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].info.memory_bytes, Some(16 << 30));
+        // The snapshot taken before the window still describes its start
+        let window: Vec<f64> = system_snapshots(&conn, Some(40.0))
+            .unwrap()
+            .iter()
+            .map(|s| s.ts)
+            .collect();
+        assert_eq!(window, [30.0, 50.0]);
+        assert_eq!(system_snapshots(&conn, Some(5.0)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn past_months_rotate_into_archives_that_reads_attach() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("metrics.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let (january, _) = month_bounds(2025, 1).unwrap();
+        let (march, _) = month_bounds(2025, 3).unwrap();
+        let cpu = |ts: f64, value: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::CpuUsage,
+                "cpu",
+                Some(value),
+                Some("%"),
+                json!({}),
+            )
+        };
+        let samples = vec![cpu(january + 3600.0, 10.0), cpu(march + 3600.0, 30.0)];
+        insert_metric_samples_with_conn(&mut conn, &samples, &[]).unwrap();
+
+        // Nothing moves until the rollups have covered the month
+        let now = march + 10.0 * 86400.0;
+        assert_eq!(rotate_archives(&mut conn, now).unwrap(), 0);
+        update_rollups(&mut conn, now).unwrap();
+        assert_eq!(rotate_archives(&mut conn, now).unwrap(), 1);
+        assert_eq!(rotate_archives(&mut conn, now).unwrap(), 0);
+        assert!(tmp.path().join("metrics-2025-01.db").exists());
+        let hot: i64 = conn
+            .query_row("SELECT COUNT(*) FROM metric_samples", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(hot, 1);
+
+        let all = fetch_metric_samples_with_conn(&conn, None, None).unwrap();
+        let values: Vec<_> = all.iter().map(|s| s.value).collect();
+        assert_eq!(values, [Some(10.0), Some(30.0)]);
+        assert_eq!(
+            fetch_metric_samples_with_conn(&conn, Some(march), None)
+                .unwrap()
+                .len(),
+            1
+        );
+        let buckets = fetch_bucketed_stats(
+            &conn,
+            &MetricKind::CpuUsage,
+            86400,
+            &SampleFilter::default(),
+        )
+        .unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].avg, 10.0);
+
+        assert_eq!(count_metric_samples_with_conn(&conn, None).unwrap(), 2);
+        assert_eq!(
+            count_metric_samples_with_conn(&conn, Some(march)).unwrap(),
+            1
+        );
+        let catalog = catalog_with_conn(&conn).unwrap();
+        assert_eq!(catalog.len(), 1);
+        assert_eq!(catalog[0].rows, 2);
+        assert_eq!(catalog[0].first_seen, january + 3600.0);
+        assert_eq!(catalog[0].sources[0].last_seen, march + 3600.0);
+        assert_eq!(last_seen_by_host(&conn).unwrap(), [(None, march + 3600.0)]);
+    }
 }
@@ -0,0 +1,361 @@
+//! Metrics defined in the config as arithmetic over collected kinds, e.g.
+//! `mem_pressure = "memory_usage / memory_usage.total_bytes * 100"`.
+
+use std::collections::BTreeMap;
+
+use ordered_float::OrderedFloat;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// A metric kind in an expression: `power_draw`, `power_draw[amdgpu]` (one source) or
+/// `memory_usage.total_bytes` (a number from the sample details).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub kind: MetricKind,
+    pub source: Option<String>,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Term(Term),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ExprError {
+    #[error("unexpected `{0}` at position {1}")]
+    Unexpected(char, usize),
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("unknown metric kind `{0}`")]
+    UnknownKind(String),
+    #[error("invalid number `{0}`")]
+    InvalidNumber(String),
+}
+
+/// A named derived metric, evaluated at report time or stored at collection time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedMetric {
+    pub name: String,
+    pub expr: Expr,
+    pub unit: Option<String>,
+    /// Store the computed value with each collection instead of computing it in reports
+    pub materialize: bool,
+}
+
+struct Parser<'a> {
+    chars: Vec<(usize, char)>,
+    pos: usize,
+    raw: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        while let Some((_, ch)) = self.chars.get(self.pos) {
+            if !ch.is_whitespace() {
+                return Some(*ch);
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn offset(&self) -> usize {
+        self.chars
+            .get(self.pos)
+            .map_or(self.raw.len(), |(idx, _)| *idx)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ExprError> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(ch) => Err(ExprError::Unexpected(ch, self.offset())),
+            None => Err(ExprError::UnexpectedEnd),
+        }
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> &str {
+        let start = self.offset();
+        while self.chars.get(self.pos).is_some_and(|(_, ch)| keep(*ch)) {
+            self.pos += 1;
+        }
+        &self.raw[start..self.offset()]
+    }
+
+    fn expr(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.product()?;
+        while let Some(op) = match self.peek() {
+            Some('+') => Some(Op::Add),
+            Some('-') => Some(Op::Sub),
+            _ => None,
+        } {
+            self.pos += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.product()?));
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.factor()?;
+        while let Some(op) = match self.peek() {
+            Some('*') => Some(Op::Mul),
+            Some('/') => Some(Op::Div),
+            _ => None,
+        } {
+            self.pos += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.factor()?));
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ExprError> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.factor()?)))
+            }
+            Some('(') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some(ch) if ch.is_ascii_digit() || ch == '.' => {
+                let text = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                text.parse()
+                    .map(Expr::Number)
+                    .map_err(|_| ExprError::InvalidNumber(text.to_string()))
+            }
+            Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => self.term().map(Expr::Term),
+            Some(ch) => Err(ExprError::Unexpected(ch, self.offset())),
+            None => Err(ExprError::UnexpectedEnd),
+        }
+    }
+
+    fn term(&mut self) -> Result<Term, ExprError> {
+        let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let name = self.take_while(is_ident).to_string();
+        let kind = MetricKind::from_label(&name).ok_or(ExprError::UnknownKind(name))?;
+        let mut source = None;
+        if self.chars.get(self.pos).is_some_and(|(_, ch)| *ch == '[') {
+            self.pos += 1;
+            source = Some(self.take_while(|c| c != ']').to_string());
+            self.expect(']')?;
+        }
+        let mut detail = None;
+        if self.chars.get(self.pos).is_some_and(|(_, ch)| *ch == '.') {
+            self.pos += 1;
+            let key = self.take_while(is_ident);
+            if key.is_empty() {
+                return match self.peek() {
+                    Some(ch) => Err(ExprError::Unexpected(ch, self.offset())),
+                    None => Err(ExprError::UnexpectedEnd),
+                };
+            }
+            detail = Some(key.to_string());
+        }
+        Ok(Term {
+            kind,
+            source,
+            detail,
+        })
+    }
+}
+
+impl std::str::FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: raw.char_indices().collect(),
+            pos: 0,
+            raw,
+        };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            Some(ch) => Err(ExprError::Unexpected(ch, parser.offset())),
+            None => Ok(expr),
+        }
+    }
+}
+
+impl Expr {
+    fn terms<'a>(&'a self, out: &mut Vec<&'a Term>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Term(term) => out.push(term),
+            Expr::Neg(inner) => inner.terms(out),
+            Expr::Binary(left, _, right) => {
+                left.terms(out);
+                right.terms(out);
+            }
+        }
+    }
+
+    /// Metric kinds the expression reads.
+    pub fn kinds(&self) -> Vec<MetricKind> {
+        let mut terms = Vec::new();
+        self.terms(&mut terms);
+        let mut kinds: Vec<MetricKind> = terms.into_iter().map(|t| t.kind.clone()).collect();
+        kinds.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        kinds.dedup();
+        kinds
+    }
+
+    fn eval(&self, lookup: &impl Fn(&Term) -> Option<f64>) -> Option<f64> {
+        match self {
+            Expr::Number(value) => Some(*value),
+            Expr::Term(term) => lookup(term),
+            Expr::Neg(inner) => inner.eval(lookup).map(|v| -v),
+            Expr::Binary(left, op, right) => {
+                let (l, r) = (left.eval(lookup)?, right.eval(lookup)?);
+                let value = match op {
+                    Op::Add => l + r,
+                    Op::Sub => l - r,
+                    Op::Mul => l * r,
+                    Op::Div => l / r,
+                };
+                value.is_finite().then_some(value)
+            }
+        }
+    }
+}
+
+/// Value of a term within one collection: the named source, or the sum over all sources.
+fn term_value(samples: &[&MetricSample], term: &Term) -> Option<f64> {
+    let values: Vec<f64> = samples
+        .iter()
+        .filter(|s| s.kind == term.kind)
+        .filter(|s| {
+            term.source
+                .as_ref()
+                .is_none_or(|source| &s.source == source)
+        })
+        .filter_map(|s| match &term.detail {
+            Some(key) => s.detail_number(key),
+            None => s.value,
+        })
+        .collect();
+    (!values.is_empty()).then(|| values.iter().sum())
+}
+
+/// Evaluate each metric for every collection timestamp where all of its terms have values.
+pub fn evaluate<'a>(
+    metrics: impl IntoIterator<Item = &'a DerivedMetric>,
+    samples: &[MetricSample],
+) -> Vec<MetricSample> {
+    let mut by_ts: BTreeMap<OrderedFloat<f64>, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in samples {
+        by_ts
+            .entry(OrderedFloat(sample.ts))
+            .or_default()
+            .push(sample);
+    }
+
+    let mut derived = Vec::new();
+    for metric in metrics {
+        let kinds = metric.expr.kinds();
+        for (ts, group) in &by_ts {
+            if !group.iter().any(|s| kinds.contains(&s.kind)) {
+                continue;
+            }
+            if let Some(value) = metric.expr.eval(&|term| term_value(group, term)) {
+                derived.push(MetricSample::new(
+                    ts.into_inner(),
+                    MetricKind::Derived,
+                    metric.name.clone(),
+                    Some(value),
+                    metric.unit.as_deref(),
+                    serde_json::Value::Null,
+                ));
+            }
+        }
+    }
+    derived
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample(kind: MetricKind, source: &str, ts: f64, value: f64) -> MetricSample {
+        MetricSample::new(ts, kind, source, Some(value), None, json!({}))
+    }
+
+    #[test]
+    fn expressions_parse_with_precedence() {
+        let expr: Expr = "-(power_draw[BAT0] - 2) * 3 + memory_usage.total_bytes / 4"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.kinds(),
+            vec![MetricKind::MemoryUsage, MetricKind::PowerDraw]
+        );
+        let Expr::Binary(left, Op::Add, _) = &expr else {
+            panic!("expected a sum: {expr:?}");
+        };
+        assert!(matches!(**left, Expr::Binary(_, Op::Mul, _)));
+
+        assert_eq!("cpu_usage +".parse::<Expr>(), Err(ExprError::UnexpectedEnd));
+        assert_eq!(
+            "gpu_power".parse::<Expr>(),
+            Err(ExprError::UnknownKind("gpu_power".to_string()))
+        );
+        assert_eq!(
+            "cpu_usage cpu_usage".parse::<Expr>(),
+            Err(ExprError::Unexpected('c', 10))
+        );
+    }
+
+    #[test]
+    fn derived_values_follow_collection_timestamps() {
+        let mut memory = sample(MetricKind::MemoryUsage, "memory", 0.0, 1024.0);
+        memory.total_bytes = Some(4096.0);
+        let samples = vec![
+            memory,
+            sample(MetricKind::PowerDraw, "BAT0", 0.0, 12.0),
+            sample(MetricKind::PowerDraw, "amdgpu", 0.0, 5.0),
+            sample(MetricKind::PowerDraw, "BAT0", 60.0, 10.0),
+        ];
+        let metrics = [
+            DerivedMetric {
+                name: "mem_pressure".to_string(),
+                expr: "memory_usage / memory_usage.total_bytes * 100"
+                    .parse()
+                    .unwrap(),
+                unit: Some("%".to_string()),
+                materialize: false,
+            },
+            DerivedMetric {
+                name: "rest_power".to_string(),
+                expr: "power_draw - power_draw[amdgpu] * 2".parse().unwrap(),
+                unit: Some("W".to_string()),
+                materialize: false,
+            },
+        ];
+
+        let derived = evaluate(&metrics, &samples);
+        // The second collection has no amdgpu reading and no memory sample.
+        assert_eq!(derived.len(), 2);
+        assert_eq!(derived[0].source, "mem_pressure");
+        assert_eq!(derived[0].value, Some(25.0));
+        assert_eq!(derived[0].unit.as_deref(), Some("%"));
+        assert_eq!(derived[1].kind, MetricKind::Derived);
+        assert_eq!(derived[1].value, Some(7.0));
+    }
+}
@@ -0,0 +1,327 @@
+//! `symmetri doctor`: which collector groups produce samples on this machine, and the
+//! sysfs/procfs paths behind each one, so an empty report can be traced to a missing
+//! driver, a permission or a `[collect] disabled` entry.
+
+use std::ffi::CString;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::metrics::{self, Collector, MetricSample};
+use crate::sysfs::{
+    ac_online_samples, charger_power_samples, create_battery_metrics, find_battery_paths,
+    find_peripheral_battery_paths, peripheral_battery_metric, read_battery,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathState {
+    Readable,
+    Missing,
+    /// Present but not readable by the current user
+    Denied,
+}
+
+impl PathState {
+    pub fn label(self) -> &'static str {
+        match self {
+            PathState::Readable => "ok",
+            PathState::Missing => "missing",
+            PathState::Denied => "permission denied",
+        }
+    }
+}
+
+/// One path a collector reads and what it provides.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub provides: String,
+    pub state: PathState,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    pub collector: Collector,
+    /// Not listed in `[collect] disabled`
+    pub enabled: bool,
+    /// Samples a trial collection produced, whether or not the group is enabled
+    pub samples: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl Diagnosis {
+    /// Why the group will or won't be collected, in a few words.
+    pub fn verdict(&self) -> String {
+        if !self.enabled {
+            return "disabled in config".to_string();
+        }
+        if self.samples > 0 {
+            return "collected".to_string();
+        }
+        if self
+            .findings
+            .iter()
+            .any(|finding| finding.state == PathState::Denied)
+        {
+            "nothing collected (permission denied)".to_string()
+        } else {
+            "nothing collected (not present)".to_string()
+        }
+    }
+}
+
+/// Whether `path` exists and can be read, without reading it: debugfs and sockets
+/// don't take kindly to a plain read.
+pub fn probe(path: &Path) -> PathState {
+    match fs::metadata(path) {
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => return PathState::Denied,
+        Err(_) => return PathState::Missing,
+        Ok(_) => {}
+    }
+    let Ok(raw) = CString::new(path.as_os_str().as_bytes()) else {
+        return PathState::Missing;
+    };
+    // SAFETY: `raw` is a valid NUL-terminated path for the duration of the call.
+    if unsafe { libc::access(raw.as_ptr(), libc::R_OK) } == 0 {
+        PathState::Readable
+    } else {
+        PathState::Denied
+    }
+}
+
+fn finding(path: impl Into<PathBuf>, provides: impl Into<String>) -> Finding {
+    let path = path.into();
+    let state = probe(&path);
+    Finding {
+        path,
+        provides: provides.into(),
+        state,
+    }
+}
+
+/// The first of `candidates` that exists, else the first one, so the finding reports
+/// it as missing.
+fn first_present(dir: &Path, candidates: &[&str]) -> PathBuf {
+    candidates
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| dir.join(candidates[0]))
+}
+
+fn entries(dir: &Path, keep: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| keep(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// hwmon attribute files such as `temp1_input` or `power1_input` across all chips.
+fn hwmon_inputs(prefix: &str) -> Vec<PathBuf> {
+    entries(Path::new("/sys/class/hwmon"), |_| true)
+        .iter()
+        .flat_map(|chip| {
+            entries(chip, |file| {
+                file.starts_with(prefix) && file.ends_with("_input")
+            })
+        })
+        .collect()
+}
+
+fn findings(collector: Collector, power_supply: &Path) -> Vec<Finding> {
+    match collector {
+        Collector::Battery => {
+            let batteries = find_battery_paths(power_supply);
+            let mut found = vec![finding(power_supply, "power supplies")];
+            found.extend(batteries.iter().map(|battery| {
+                finding(
+                    first_present(battery, &["energy_now", "charge_now"]),
+                    format!("{} charge level", name(battery)),
+                )
+            }));
+            found
+        }
+        Collector::Peripherals => find_peripheral_battery_paths(power_supply)
+            .iter()
+            .map(|device| finding(device.join("capacity"), format!("{} level", name(device))))
+            .collect(),
+        Collector::Cpu => {
+            let cpu0 = Path::new("/sys/devices/system/cpu/cpu0");
+            vec![
+                finding("/proc/stat", "usage, context switches and interrupts"),
+                finding(
+                    cpu0.join("cpufreq/scaling_cur_freq"),
+                    "frequency and governor",
+                ),
+                finding(
+                    cpu0.join("thermal_throttle/core_throttle_count"),
+                    "throttle events",
+                ),
+                finding(cpu0.join("cpuidle"), "idle state residency"),
+            ]
+        }
+        Collector::Gpu => {
+            let drm = Path::new("/sys/class/drm");
+            let cards = entries(drm, |entry| {
+                entry.starts_with("card") && !entry.contains('-')
+            });
+            let mut found = vec![finding(drm, "DRM cards")];
+            for card in &cards {
+                found.push(finding(
+                    first_present(&card.join("device"), &["gpu_busy_percent", "busy_percent"]),
+                    format!("{} usage", name(card)),
+                ));
+                found.push(finding(
+                    first_present(card, &["gt_cur_freq_mhz", "device/pp_dpm_sclk"]),
+                    format!("{} frequency", name(card)),
+                ));
+            }
+            found
+        }
+        Collector::Memory => vec![finding("/proc/meminfo", "memory and swap usage")],
+        Collector::Network => vec![
+            finding("/proc/net/dev", "interface byte counters"),
+            finding("/sys/class/net", "link state"),
+            finding("/proc/net/tcp", "TCP connections"),
+        ],
+        Collector::Disk => vec![finding("/", "usage of the root filesystem")],
+        Collector::Temperature => {
+            let mut found = vec![finding("/sys/class/thermal", "thermal zones")];
+            let sensors = hwmon_inputs("temp");
+            match sensors.first() {
+                Some(first) => found.push(finding(
+                    first,
+                    format!("{} hwmon temperature sensors", sensors.len()),
+                )),
+                None => found.push(finding("/sys/class/hwmon", "hwmon temperature sensors")),
+            }
+            found
+        }
+        Collector::Power => {
+            let sensors = hwmon_inputs("power");
+            let mut found = vec![match sensors.first() {
+                Some(first) => finding(first, format!("{} hwmon power sensors", sensors.len())),
+                None => finding("/sys/class/hwmon", "hwmon power sensors"),
+            }];
+            found.push(finding("/sys/class/leds", "keyboard backlight"));
+            found.push(finding("/sys/class/wakeup", "wakeup sources"));
+            found.push(finding(
+                "/sys/kernel/debug/wakeup_sources",
+                "wakeup sources by name (root only)",
+            ));
+            found
+        }
+        Collector::Containers => vec![
+            finding("/sys/fs/cgroup", "container cgroups"),
+            finding("/var/run/docker.sock", "Docker container names"),
+        ],
+        Collector::Systemd => vec![
+            finding("/run/systemd/system", "systemd as init"),
+            finding("/proc/acpi/button/lid", "lid state"),
+        ],
+        Collector::Stats => Vec::new(),
+    }
+}
+
+/// Samples of one collection with every group enabled, including the disabled ones,
+/// by the group that produced them. Self-metrics are left out.
+pub fn trial_collection(config: &Config) -> Vec<(Collector, Vec<MetricSample>)> {
+    let power_supply = Path::new("/sys/class/power_supply");
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    Collector::all()
+        .filter(|collector| *collector != Collector::Stats)
+        .map(|collector| {
+            let samples = match collector {
+                Collector::Battery => {
+                    let mut samples: Vec<MetricSample> = find_battery_paths(power_supply)
+                        .iter()
+                        .flat_map(|path| create_battery_metrics(&read_battery(path), ts))
+                        .collect();
+                    samples.extend(ac_online_samples(power_supply, ts));
+                    samples.extend(charger_power_samples(power_supply, ts));
+                    samples
+                }
+                Collector::Peripherals => find_peripheral_battery_paths(power_supply)
+                    .iter()
+                    .filter_map(|path| peripheral_battery_metric(path, ts))
+                    .collect(),
+                _ => metrics::collect_metrics(
+                    ts,
+                    |group| group == collector,
+                    &config.network.interfaces,
+                    &mut Vec::new(),
+                ),
+            };
+            (collector, samples)
+        })
+        .collect()
+}
+
+/// Run every collector group once, including the disabled ones, and probe the paths
+/// they read.
+pub fn diagnose(config: &Config) -> Vec<Diagnosis> {
+    let power_supply = Path::new("/sys/class/power_supply");
+    let trial = trial_collection(config);
+    Collector::all()
+        .map(|collector| {
+            let samples = match collector {
+                // Self-metrics come from the other groups' timings on every run
+                Collector::Stats => 1,
+                _ => trial
+                    .iter()
+                    .filter(|(group, _)| *group == collector)
+                    .map(|(_, samples)| samples.len())
+                    .sum(),
+            };
+            Diagnosis {
+                collector,
+                enabled: config.collect.is_enabled(collector),
+                samples,
+                findings: findings(collector, power_supply),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_tells_missing_from_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("energy_now");
+        fs::write(&file, "42000000\n").unwrap();
+        assert_eq!(probe(&file), PathState::Readable);
+        assert_eq!(probe(&dir.path().join("charge_now")), PathState::Missing);
+        assert_eq!(
+            first_present(dir.path(), &["charge_now", "energy_now"]),
+            file
+        );
+
+        let diagnosis = Diagnosis {
+            collector: Collector::Gpu,
+            enabled: true,
+            samples: 0,
+            findings: vec![finding(dir.path().join("card0"), "card0 usage")],
+        };
+        assert_eq!(diagnosis.verdict(), "nothing collected (not present)");
+    }
+}
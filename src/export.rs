@@ -0,0 +1,135 @@
+//! `symmetri export`: stored samples in formats other tools ingest, such as InfluxDB line
+//! protocol.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::metrics::MetricSample;
+
+/// Measurement every sample is written under in line protocol.
+const INFLUX_MEASUREMENT: &str = "symmetri";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// InfluxDB line protocol, with kind, source and host as tags
+    Influx,
+    /// One JSON sample per line
+    Json,
+}
+
+/// Escape a tag key/value or field key: commas, spaces and equals signs are significant.
+fn escape_key(raw: &str) -> String {
+    let mut escaped = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        if matches!(c, ',' | ' ' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn string_field(raw: &str) -> String {
+    format!("\"{}\"", raw.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The sample as one line of InfluxDB line protocol. The value, the label and the numeric
+/// details become fields; samples with none of them are skipped.
+pub fn influx_line(sample: &MetricSample) -> Option<String> {
+    let mut tags = format!(
+        "{INFLUX_MEASUREMENT},kind={}",
+        escape_key(sample.kind.as_str())
+    );
+    // Empty tag values are invalid line protocol
+    if !sample.source.is_empty() {
+        tags.push_str(&format!(",source={}", escape_key(&sample.source)));
+    }
+    if let Some(host) = sample.host.as_deref().filter(|host| !host.is_empty()) {
+        tags.push_str(&format!(",host={}", escape_key(host)));
+    }
+
+    let mut fields = Vec::new();
+    if let Some(value) = sample.value.filter(|v| v.is_finite()) {
+        fields.push(format!("value={value}"));
+    }
+    if let Some(label) = &sample.label {
+        fields.push(format!("label={}", string_field(label)));
+    }
+    let bytes = [
+        ("rx_bytes", sample.rx_bytes),
+        ("tx_bytes", sample.tx_bytes),
+        ("total_bytes", sample.total_bytes),
+    ];
+    for (key, value) in bytes {
+        if let Some(number) = value.filter(|v| v.is_finite()) {
+            fields.push(format!("{key}={number}"));
+        }
+    }
+    if let Value::Object(details) = &sample.details {
+        for (key, value) in details {
+            if let Some(number) = value.as_f64().filter(|v| v.is_finite()) {
+                fields.push(format!("{}={number}", escape_key(key)));
+            }
+        }
+    }
+    if fields.is_empty() {
+        return None;
+    }
+    let ns = (sample.ts * 1e9).round() as i64;
+    Some(format!("{tags} {} {ns}", fields.join(",")))
+}
+
+pub fn write_samples(
+    out: &mut impl Write,
+    samples: &[MetricSample],
+    format: ExportFormat,
+) -> Result<()> {
+    for sample in samples {
+        match format {
+            ExportFormat::Influx => {
+                if let Some(line) = influx_line(sample) {
+                    writeln!(out, "{line}")?;
+                }
+            }
+            ExportFormat::Json => writeln!(out, "{}", serde_json::to_string(sample)?)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::MetricKind;
+    use serde_json::json;
+
+    #[test]
+    fn samples_become_line_protocol() {
+        let mut sample = MetricSample::new(
+            1_700_000_000.5,
+            MetricKind::Temperature,
+            "coretemp:Package id 0",
+            Some(61.5),
+            Some("C"),
+            json!({ "crit_c": 100.0, "chip": "coretemp" }),
+        );
+        sample.host = Some("thinkpad".to_string());
+        assert_eq!(
+            influx_line(&sample).unwrap(),
+            "symmetri,kind=temperature,source=coretemp:Package\\ id\\ 0,host=thinkpad \
+             value=61.5,crit_c=100 1700000000500000000"
+        );
+
+        let mut status =
+            MetricSample::new(2.0, MetricKind::BatteryStatus, "", None, None, Value::Null);
+        assert_eq!(influx_line(&status), None);
+        status.label = Some("Not \"charging\"".to_string());
+        assert_eq!(
+            influx_line(&status).unwrap(),
+            "symmetri,kind=battery_status label=\"Not \\\"charging\\\"\" 2000000000"
+        );
+    }
+}
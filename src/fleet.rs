@@ -0,0 +1,161 @@
+//! `symmetri fleet`: one health row per machine, for keeping an eye on a handful of
+//! personal machines collecting into their own databases or a shared one.
+
+use std::collections::BTreeMap;
+
+use crate::aggregate::aggregate_multi_device_metrics;
+use crate::cli_helpers::average_rates;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::report::average_for_kind;
+
+/// Window the temperature and power columns cover.
+pub const FLEET_WINDOW_SECONDS: f64 = 7.0 * 86400.0;
+
+/// Kinds the fleet summary reads; the battery energy ones feed the discharge fallback.
+pub const FLEET_KINDS: [MetricKind; 7] = [
+    MetricKind::BatteryHealth,
+    MetricKind::BatteryEnergyNow,
+    MetricKind::BatteryEnergyFull,
+    MetricKind::BatteryEnergyFullDesign,
+    MetricKind::DiskUsage,
+    MetricKind::Temperature,
+    MetricKind::PowerDraw,
+];
+
+/// Health of one machine.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FleetRow {
+    pub host: String,
+    /// Newest sample of any kind
+    pub last_seen: Option<f64>,
+    /// Latest health of the (combined) battery
+    pub battery_health: Option<f64>,
+    /// Mountpoint with the highest used share in its latest sample, and that share
+    pub fullest_mount: Option<(String, f64)>,
+    pub max_temp_c: Option<f64>,
+    /// Average power draw, from hwmon/battery power readings or else the discharge rate
+    pub avg_power_w: Option<f64>,
+}
+
+fn latest<'a>(samples: &'a [MetricSample], kind: &MetricKind) -> Option<&'a MetricSample> {
+    samples
+        .iter()
+        .filter(|m| &m.kind == kind && m.value.is_some())
+        .max_by(|a, b| a.ts.total_cmp(&b.ts))
+}
+
+/// One row per host tagged in a database, given the newest sample of each host. Samples
+/// stored before hosts were tagged count as `untagged_host`, the machine they came from.
+pub fn fleet_rows(
+    untagged_host: &str,
+    last_seen: &[(Option<String>, f64)],
+    samples: &[MetricSample],
+) -> Vec<FleetRow> {
+    let name = |host: &Option<String>| host.clone().unwrap_or_else(|| untagged_host.to_string());
+    let mut hosts: BTreeMap<String, (f64, Vec<MetricSample>)> = BTreeMap::new();
+    for (host, ts) in last_seen {
+        let entry = hosts.entry(name(host)).or_insert((*ts, Vec::new()));
+        entry.0 = entry.0.max(*ts);
+    }
+    for sample in samples {
+        if let Some((_, host_samples)) = hosts.get_mut(&name(&sample.host)) {
+            host_samples.push(sample.clone());
+        }
+    }
+    hosts
+        .into_iter()
+        .map(|(host, (last_seen, samples))| fleet_row(host, Some(last_seen), &samples))
+        .collect()
+}
+
+/// Summarize `samples`, which should cover [`FLEET_WINDOW_SECONDS`], for `host`.
+fn fleet_row(host: String, last_seen: Option<f64>, samples: &[MetricSample]) -> FleetRow {
+    let aggregated = aggregate_multi_device_metrics(samples);
+    let mut disks: Vec<&MetricSample> = Vec::new();
+    for sample in samples.iter().filter(|m| m.kind == MetricKind::DiskUsage) {
+        match disks.iter_mut().find(|d| d.source == sample.source) {
+            Some(disk) if disk.ts < sample.ts => *disk = sample,
+            Some(_) => {}
+            None => disks.push(sample),
+        }
+    }
+    let fullest_mount = disks
+        .into_iter()
+        .filter_map(|disk| {
+            let total = disk.total_bytes.filter(|t| *t > 0.0)?;
+            Some((disk.source.clone(), disk.value? / total * 100.0))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    FleetRow {
+        host,
+        last_seen,
+        battery_health: latest(&aggregated, &MetricKind::BatteryHealth).and_then(|m| m.value),
+        fullest_mount,
+        max_temp_c: average_for_kind(samples, MetricKind::Temperature).max(),
+        avg_power_w: average_for_kind(&aggregated, MetricKind::PowerDraw)
+            .average()
+            .or_else(|| average_rates(&aggregated).discharge_w),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn fleet_row_picks_the_fullest_mount_and_hottest_sensor() {
+        let sample = |ts: f64, kind: MetricKind, source: &str, value: f64, total: f64| {
+            MetricSample::new(ts, kind, source, Some(value), None, Value::Null)
+                .with_total_bytes(total)
+        };
+        let samples = vec![
+            sample(1.0, MetricKind::DiskUsage, "/", 90.0, 100.0),
+            sample(2.0, MetricKind::DiskUsage, "/", 40.0, 100.0),
+            sample(2.0, MetricKind::DiskUsage, "/home", 300.0, 400.0),
+            sample(1.0, MetricKind::Temperature, "acpitz", 48.0, 0.0),
+            sample(
+                2.0,
+                MetricKind::Temperature,
+                "coretemp:Package id 0",
+                81.5,
+                0.0,
+            ),
+            sample(1.0, MetricKind::BatteryHealth, "BAT0", 91.0, 0.0),
+            sample(2.0, MetricKind::BatteryHealth, "BAT0", 90.0, 0.0),
+            sample(1.0, MetricKind::PowerDraw, "BAT0", 6.0, 0.0),
+            sample(2.0, MetricKind::PowerDraw, "BAT0", 8.0, 0.0),
+        ];
+
+        let row = fleet_row("thinkpad".to_string(), Some(2.0), &samples);
+        assert_eq!(row.fullest_mount, Some(("/home".to_string(), 75.0)));
+        assert_eq!(row.max_temp_c, Some(81.5));
+        assert_eq!(row.battery_health, Some(90.0));
+        assert_eq!(row.avg_power_w, Some(7.0));
+        assert_eq!(
+            fleet_row("empty".to_string(), None, &[]).fullest_mount,
+            None
+        );
+
+        let mut desktop = sample(3.0, MetricKind::Temperature, "k10temp:Tctl", 60.0, 0.0);
+        desktop.host = Some("desktop".to_string());
+        let mut laptop = samples[4].clone();
+        laptop.host = Some("thinkpad".to_string());
+        let last_seen = [
+            (None, 2.0),
+            (Some("desktop".to_string()), 3.0),
+            (Some("thinkpad".to_string()), 5.0),
+        ];
+        // Rows from before tagging belong to the machine the database came from
+        let rows = fleet_rows(
+            "thinkpad",
+            &last_seen,
+            &[samples[3].clone(), desktop, laptop],
+        );
+        let hosts: Vec<&str> = rows.iter().map(|row| row.host.as_str()).collect();
+        assert_eq!(hosts, ["desktop", "thinkpad"]);
+        assert_eq!(rows[0].max_temp_c, Some(60.0));
+        assert_eq!(rows[1].max_temp_c, Some(81.5));
+        assert_eq!(rows[1].last_seen, Some(5.0));
+    }
+}
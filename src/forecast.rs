@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+const SECONDS_PER_DAY: f64 = 24.0 * 3600.0;
+/// History a trend needs before it is extrapolated: a burst of collections seconds apart
+/// says nothing about growth per day.
+const MIN_FORECAST_SAMPLES: usize = 3;
+const MIN_FORECAST_SPAN_SECONDS: f64 = SECONDS_PER_DAY;
+
+/// Least-squares line through `(x, y)` points, returned as `(slope, intercept)`.
+pub fn linear_fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance += (x - mean_x).powi(2);
+    }
+    if variance == 0.0 {
+        return None;
+    }
+    let slope = covariance / variance;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskForecast {
+    pub source: String,
+    pub first_ts: f64,
+    pub last_ts: f64,
+    pub used_bytes: f64,
+    pub total_bytes: Option<f64>,
+    pub growth_bytes_per_day: f64,
+    intercept: f64,
+}
+
+impl DiskForecast {
+    /// Fitted used bytes at `ts`.
+    pub fn used_at(&self, ts: f64) -> f64 {
+        self.intercept + self.growth_bytes_per_day / SECONDS_PER_DAY * ts
+    }
+
+    /// Days from the latest sample until the filesystem is full, if it is growing.
+    pub fn days_until_full(&self) -> Option<f64> {
+        let total = self.total_bytes?;
+        if self.growth_bytes_per_day <= 0.0 {
+            return None;
+        }
+        Some(((total - self.used_at(self.last_ts)) / self.growth_bytes_per_day).max(0.0))
+    }
+
+    /// Timestamp where the fitted trend reaches the filesystem size.
+    pub fn full_ts(&self) -> Option<f64> {
+        self.days_until_full()
+            .map(|days| self.last_ts + days * SECONDS_PER_DAY)
+    }
+}
+
+/// Fit a growth trend to disk usage for every mountpoint with enough history: at least
+/// [`MIN_FORECAST_SAMPLES`] samples spread over [`MIN_FORECAST_SPAN_SECONDS`].
+pub fn disk_forecasts(metrics: &[MetricSample]) -> Vec<DiskForecast> {
+    let mut by_source: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::DiskUsage && m.value.is_some())
+    {
+        by_source.entry(&sample.source).or_default().push(sample);
+    }
+
+    let mut forecasts = Vec::new();
+    for (source, mut samples) in by_source {
+        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+        let points: Vec<(f64, f64)> = samples
+            .iter()
+            .filter_map(|s| s.value.map(|v| (s.ts, v)))
+            .collect();
+        let span = samples[samples.len() - 1].ts - samples[0].ts;
+        if points.len() < MIN_FORECAST_SAMPLES || span < MIN_FORECAST_SPAN_SECONDS {
+            continue;
+        }
+        let Some((slope, intercept)) = linear_fit(&points) else {
+            continue;
+        };
+        let latest = samples.last().unwrap();
+        forecasts.push(DiskForecast {
+            source: source.to_string(),
+            first_ts: samples[0].ts,
+            last_ts: latest.ts,
+            used_bytes: latest.value.unwrap_or_default(),
+            total_bytes: latest.total_bytes,
+            growth_bytes_per_day: slope * SECONDS_PER_DAY,
+            intercept,
+        });
+    }
+    forecasts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn disk_sample(ts: f64, used: f64, total: f64) -> MetricSample {
+        MetricSample::new(
+            ts,
+            MetricKind::DiskUsage,
+            "/",
+            Some(used),
+            Some("bytes"),
+            Value::Null,
+        )
+        .with_total_bytes(total)
+    }
+
+    #[test]
+    fn linear_fit_recovers_line() {
+        let (slope, intercept) = linear_fit(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)]).unwrap();
+        assert!((slope - 2.0).abs() < 1e-9);
+        assert!((intercept - 1.0).abs() < 1e-9);
+        assert!(linear_fit(&[(1.0, 1.0), (1.0, 2.0)]).is_none());
+    }
+
+    #[test]
+    fn growing_disk_predicts_days_until_full() {
+        let day = SECONDS_PER_DAY;
+        let metrics = vec![
+            disk_sample(0.0, 50.0, 100.0),
+            disk_sample(day, 51.0, 100.0),
+            disk_sample(2.0 * day, 52.0, 100.0),
+        ];
+        let forecast = &disk_forecasts(&metrics)[0];
+        assert!((forecast.growth_bytes_per_day - 1.0).abs() < 1e-9);
+        assert!((forecast.days_until_full().unwrap() - 48.0).abs() < 1e-6);
+        assert!((forecast.full_ts().unwrap() - 50.0 * day).abs() < 1e-3);
+    }
+
+    #[test]
+    fn shrinking_disk_has_no_full_date() {
+        let metrics = vec![
+            disk_sample(0.0, 60.0, 100.0),
+            disk_sample(SECONDS_PER_DAY / 2.0, 58.0, 100.0),
+            disk_sample(SECONDS_PER_DAY, 55.0, 100.0),
+        ];
+        let forecast = &disk_forecasts(&metrics)[0];
+        assert!(forecast.growth_bytes_per_day < 0.0);
+        assert!(forecast.days_until_full().is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::io::Write;
 use std::path::Path;
 
 use anyhow::Result;
@@ -7,10 +8,14 @@ use log::{info, warn};
 use ordered_float::OrderedFloat;
 use plotters::coord::Shift;
 use plotters::prelude::*;
-use plotters::series::LineSeries;
+use plotters::series::{AreaSeries, LineSeries};
 
-use crate::cli::ReportPreset;
+use crate::cli_helpers::{container_cpu_percent, counter_rate_samples};
+use crate::correlate::{series_by_timestamp, Reduce};
+use crate::forecast::disk_forecasts;
+use crate::inline_image::{self, encode_png, InlineProtocol};
 use crate::metrics::{MetricKind, MetricSample};
+use crate::report::ReportPreset;
 use crate::timeframe::Timeframe;
 
 struct MetricSeries {
@@ -20,72 +25,232 @@ struct MetricSeries {
 
 type SeriesPoints = Vec<(DateTime<Utc>, f64)>;
 
+/// A labelled vertical line, e.g. where the power profile changed.
+#[derive(Clone)]
+struct ChartMarker {
+    ts: DateTime<Utc>,
+    label: String,
+}
+
+/// Spread (min, max) of the values averaged into each point of `chart.series[series]`.
+struct SeriesBand {
+    series: usize,
+    points: Vec<(DateTime<Utc>, (f64, f64))>,
+}
+
+#[derive(Default)]
 struct ChartSpec {
     title: String,
     y_desc: String,
     series: Vec<MetricSeries>,
+    bands: Vec<SeriesBand>,
+    markers: Vec<ChartMarker>,
+    style: ChartStyle,
 }
 
-pub fn render_plot(
+/// How report charts are drawn.
+#[derive(Debug, Clone, Default)]
+pub struct PlotOptions {
+    /// Kinds drawn as step lines: each value is held until the next sample
+    pub step_kinds: Vec<MetricKind>,
+    /// Shade the min–max range around series that average several sources
+    pub bands: bool,
+    /// Draw one charge and health line per battery instead of the combined battery
+    pub per_battery: bool,
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum ChartStyle {
+    #[default]
+    Lines,
+    /// Filled areas stacked on top of each other
+    Stacked,
+    /// One on/off strip per series
+    States,
+}
+
+/// `-` as the graph path writes PNG bytes to stdout instead of a file.
+pub fn is_stdout(output: &Path) -> bool {
+    output == Path::new("-")
+}
+
+/// Draw into an in-memory RGB buffer.
+fn draw_rgb<F>(size: (u32, u32), draw: F) -> Result<Vec<u8>>
+where
+    F: for<'a> FnOnce(&DrawingArea<BitMapBackend<'a>, Shift>) -> Result<()>,
+{
+    let mut buffer = vec![0u8; size.0 as usize * size.1 as usize * 3];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, size).into_drawing_area();
+        draw(&root)?;
+        root.present()?;
+    }
+    Ok(buffer)
+}
+
+fn draw_image<F>(output: &Path, size: (u32, u32), draw: F) -> Result<()>
+where
+    F: for<'a> FnOnce(&DrawingArea<BitMapBackend<'a>, Shift>) -> Result<()>,
+{
+    if is_stdout(output) {
+        let png = encode_png(&draw_rgb(size, draw)?, size)?;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&png)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+
+    let root = BitMapBackend::new(output, size).into_drawing_area();
+    draw(&root)?;
+    root.present()?;
+    info!("Saved plot to {}", output.display());
+    Ok(())
+}
+
+fn chart_grid_size(charts: &[ChartSpec]) -> (u32, u32) {
+    let rows = charts.len().max(1) as u32;
+    (1280, (rows * 260).max(260))
+}
+
+fn draw_chart_grid(root: &DrawingArea<BitMapBackend, Shift>, charts: &[ChartSpec]) -> Result<()> {
+    root.fill(&WHITE)?;
+    let areas = root.split_evenly((charts.len().max(1), 1));
+    for (area, chart) in areas.into_iter().zip(charts.iter()) {
+        plot_chart(area, chart)?;
+    }
+    Ok(())
+}
+
+/// Render the report charts and show them in the terminal with an inline image protocol.
+pub fn display_plot_inline(
     metrics: &[MetricSample],
     presets: &[ReportPreset],
     timeframe: &Timeframe,
-    output: &Path,
+    options: &PlotOptions,
+    protocol: InlineProtocol,
 ) -> Result<()> {
-    let charts = build_charts(metrics, presets, timeframe);
+    let charts = build_charts(metrics, presets, timeframe, options);
     if charts.is_empty() {
         warn!("No values available to plot for selected presets");
         return Ok(());
     }
+    let size = chart_grid_size(&charts);
+    let rgb = draw_rgb(size, |root| draw_chart_grid(root, &charts))?;
+    inline_image::display(&mut std::io::stdout().lock(), protocol, &rgb, size)
+}
 
-    let rows = charts.len().max(1);
-    let height = (rows as u32 * 260).max(260);
-    let root = BitMapBackend::new(output, (1280, height)).into_drawing_area();
-    root.fill(&WHITE)?;
-    let areas = root.split_evenly((rows, 1));
-
-    for (area, chart) in areas.into_iter().zip(charts.iter()) {
-        plot_chart(area, chart)?;
+pub fn render_plot(
+    metrics: &[MetricSample],
+    presets: &[ReportPreset],
+    timeframe: &Timeframe,
+    options: &PlotOptions,
+    output: &Path,
+) -> Result<()> {
+    let charts = build_charts(metrics, presets, timeframe, options);
+    if charts.is_empty() {
+        warn!("No values available to plot for selected presets");
+        return Ok(());
     }
 
-    root.present()?;
-    info!("Saved plot to {}", output.display());
-    Ok(())
+    draw_image(output, chart_grid_size(&charts), |root| {
+        draw_chart_grid(root, &charts)
+    })
 }
 
 fn build_charts(
     metrics: &[MetricSample],
     presets: &[ReportPreset],
     timeframe: &Timeframe,
+    options: &PlotOptions,
 ) -> Vec<ChartSpec> {
     let mut charts = Vec::new();
     let label = timeframe.label.replace('_', " ");
+    let held = |kind: MetricKind, points: SeriesPoints| {
+        if options.step_kinds.contains(&kind) {
+            step_points(&points)
+        } else {
+            points
+        }
+    };
+    // Band of the first series of a chart built with `aggregate_metric_series`
+    let band = |kind: MetricKind, metrics: &[MetricSample], map_value: fn(f64) -> f64| {
+        if !options.bands {
+            return Vec::new();
+        }
+        let mut points = spread_band(metrics, kind.clone(), map_value);
+        if options.step_kinds.contains(&kind) {
+            points = step_points(&points);
+        }
+        if points.is_empty() {
+            Vec::new()
+        } else {
+            vec![SeriesBand { series: 0, points }]
+        }
+    };
+    let held_by_source = |kind: MetricKind, series: Vec<MetricSeries>| -> Vec<MetricSeries> {
+        series
+            .into_iter()
+            .map(|s| MetricSeries {
+                label: s.label,
+                points: held(kind.clone(), s.points),
+            })
+            .collect()
+    };
 
     if presets.contains(&ReportPreset::Battery) {
+        let profile_markers = profile_change_markers(metrics);
         let mut series = Vec::new();
-        let percent_points = metric_series(metrics, MetricKind::BatteryPercentage);
-        if !percent_points.is_empty() {
-            series.push(MetricSeries {
-                label: "Charge %".to_string(),
-                points: percent_points,
-            });
-        }
-        let health_points = metric_series(metrics, MetricKind::BatteryHealth);
-        if !health_points.is_empty() {
-            series.push(MetricSeries {
-                label: "Health %".to_string(),
-                points: health_points,
-            });
+        if options.per_battery {
+            for (kind, label) in [
+                (MetricKind::BatteryPercentage, "charge %"),
+                (MetricKind::BatteryHealth, "health %"),
+            ] {
+                let by_battery = aggregate_metric_series_by_source(metrics, kind.clone(), |v, _| v);
+                series.extend(
+                    held_by_source(kind, by_battery)
+                        .into_iter()
+                        .map(|s| MetricSeries {
+                            label: format!("{} {label}", s.label),
+                            points: s.points,
+                        }),
+                );
+            }
+        } else {
+            let percent_points = held(
+                MetricKind::BatteryPercentage,
+                metric_series(metrics, MetricKind::BatteryPercentage),
+            );
+            if !percent_points.is_empty() {
+                series.push(MetricSeries {
+                    label: "Charge %".to_string(),
+                    points: percent_points,
+                });
+            }
+            let health_points = held(
+                MetricKind::BatteryHealth,
+                metric_series(metrics, MetricKind::BatteryHealth),
+            );
+            if !health_points.is_empty() {
+                series.push(MetricSeries {
+                    label: "Health %".to_string(),
+                    points: health_points,
+                });
+            }
         }
         if !series.is_empty() {
             charts.push(ChartSpec {
                 title: format!("Battery ({label})"),
                 y_desc: "Percent".to_string(),
                 series,
+                markers: profile_markers.clone(),
+                ..Default::default()
             });
         }
 
-        let power_draw = aggregate_metric_series(metrics, MetricKind::PowerDraw, |v, _| v);
+        let power_draw = held(
+            MetricKind::PowerDraw,
+            aggregate_metric_series(metrics, MetricKind::PowerDraw, |v, _| v),
+        );
         if !power_draw.is_empty() {
             charts.push(ChartSpec {
                 title: format!("Power draw ({label})"),
@@ -94,52 +259,132 @@ fn build_charts(
                     label: "Discharge".to_string(),
                     points: power_draw,
                 }],
+                bands: band(MetricKind::PowerDraw, metrics, |v| v),
+                markers: profile_markers,
+                ..Default::default()
             });
         }
     }
 
     if presets.contains(&ReportPreset::Cpu) {
-        let usage = aggregate_metric_series_by_source(metrics, MetricKind::CpuUsage, |v, _| v);
+        let usage = held_by_source(
+            MetricKind::CpuUsage,
+            aggregate_metric_series_by_source(metrics, MetricKind::CpuUsage, |v, _| v),
+        );
         if !usage.is_empty() {
             charts.push(ChartSpec {
                 title: format!("CPU usage ({label})"),
                 y_desc: "Percent".to_string(),
                 series: usage,
+                ..Default::default()
             });
         }
-        let freq = aggregate_metric_series_by_source(metrics, MetricKind::CpuFrequency, |v, _| v);
+        let freq = held_by_source(
+            MetricKind::CpuFrequency,
+            aggregate_metric_series_by_source(metrics, MetricKind::CpuFrequency, |v, _| v),
+        );
         if !freq.is_empty() {
             charts.push(ChartSpec {
                 title: format!("CPU frequency ({label})"),
                 y_desc: "MHz".to_string(),
                 series: freq,
+                ..Default::default()
+            });
+        }
+        let mut scheduler = Vec::new();
+        for (kind, label) in [
+            (MetricKind::ContextSwitches, "Context switches"),
+            (MetricKind::Interrupts, "Interrupts"),
+        ] {
+            let points = aggregate_metric_series(
+                &counter_rate_samples(metrics, kind.clone()),
+                kind,
+                |v, _| v,
+            );
+            if !points.is_empty() {
+                scheduler.push(MetricSeries {
+                    label: label.to_string(),
+                    points,
+                });
+            }
+        }
+        if !scheduler.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("Scheduler activity ({label})"),
+                y_desc: "Per second".to_string(),
+                series: scheduler,
+                ..Default::default()
+            });
+        }
+        let mut waits = Vec::new();
+        for (kind, label) in [
+            (MetricKind::CpuIowait, "iowait"),
+            (MetricKind::CpuSteal, "steal"),
+        ] {
+            let points = held(
+                kind.clone(),
+                aggregate_metric_series(metrics, kind, |v, _| v),
+            );
+            if !points.is_empty() {
+                waits.push(MetricSeries {
+                    label: label.to_string(),
+                    points,
+                });
+            }
+        }
+        if !waits.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("CPU iowait and steal ({label})"),
+                y_desc: "Percent".to_string(),
+                series: waits,
+                ..Default::default()
+            });
+        }
+        let throttle = throttle_event_series(metrics, timeframe);
+        if !throttle.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("CPU throttle events ({label})"),
+                y_desc: "Events".to_string(),
+                series: throttle,
+                ..Default::default()
             });
         }
     }
 
     if presets.contains(&ReportPreset::Gpu) {
-        let usage = aggregate_metric_series_by_source(metrics, MetricKind::GpuUsage, |v, _| v);
+        let usage = held_by_source(
+            MetricKind::GpuUsage,
+            aggregate_metric_series_by_source(metrics, MetricKind::GpuUsage, |v, _| v),
+        );
         if !usage.is_empty() {
             charts.push(ChartSpec {
                 title: format!("GPU usage ({label})"),
                 y_desc: "Percent".to_string(),
                 series: usage,
+                ..Default::default()
             });
         }
-        let freq = aggregate_metric_series_by_source(metrics, MetricKind::GpuFrequency, |v, _| v);
+        let freq = held_by_source(
+            MetricKind::GpuFrequency,
+            aggregate_metric_series_by_source(metrics, MetricKind::GpuFrequency, |v, _| v),
+        );
         if !freq.is_empty() {
             charts.push(ChartSpec {
                 title: format!("GPU frequency ({label})"),
                 y_desc: "MHz".to_string(),
                 series: freq,
+                ..Default::default()
             });
         }
     }
 
     if presets.contains(&ReportPreset::Memory) {
-        let memory = aggregate_metric_series(metrics, MetricKind::MemoryUsage, |used, _| {
-            bytes_to_gib(used)
-        });
+        let memory = held(
+            MetricKind::MemoryUsage,
+            aggregate_metric_series(metrics, MetricKind::MemoryUsage, |used, _| {
+                bytes_to_gib(used)
+            }),
+        );
         if !memory.is_empty() {
             charts.push(ChartSpec {
                 title: format!("Memory usage ({label})"),
@@ -148,21 +393,36 @@ fn build_charts(
                     label: "Used".to_string(),
                     points: memory,
                 }],
+                bands: band(MetricKind::MemoryUsage, metrics, bytes_to_gib),
+                ..Default::default()
+            });
+        }
+        let composition = memory_composition_series(metrics);
+        if !composition.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("Memory composition ({label})"),
+                y_desc: "GiB".to_string(),
+                series: composition,
+                style: ChartStyle::Stacked,
+                ..Default::default()
             });
         }
     }
 
     if presets.contains(&ReportPreset::Disk) {
-        let disk =
-            aggregate_metric_series(metrics, MetricKind::DiskUsage, |used, _| bytes_to_gib(used));
+        let mut disk = held_by_source(
+            MetricKind::DiskUsage,
+            aggregate_metric_series_by_source(metrics, MetricKind::DiskUsage, |used, _| {
+                bytes_to_gib(used)
+            }),
+        );
         if !disk.is_empty() {
+            disk.extend(disk_forecast_series(metrics));
             charts.push(ChartSpec {
                 title: format!("Disk usage ({label})"),
                 y_desc: "GiB".to_string(),
-                series: vec![MetricSeries {
-                    label: "Used".to_string(),
-                    points: disk,
-                }],
+                series: disk,
+                ..Default::default()
             });
         }
     }
@@ -187,28 +447,315 @@ fn build_charts(
                 title: format!("Network data transferred ({label})"),
                 y_desc: "MiB".to_string(),
                 series,
+                ..Default::default()
+            });
+        }
+    }
+
+    if presets.contains(&ReportPreset::Containers) {
+        let cpu = aggregate_metric_series_by_source(
+            &container_cpu_percent(metrics),
+            MetricKind::ContainerCpu,
+            |v, _| v,
+        );
+        if !cpu.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("Container CPU ({label})"),
+                y_desc: "Percent of one core".to_string(),
+                series: cpu,
+                ..Default::default()
+            });
+        }
+        let memory = held_by_source(
+            MetricKind::ContainerMemory,
+            aggregate_metric_series_by_source(metrics, MetricKind::ContainerMemory, |used, _| {
+                bytes_to_gib(used)
+            }),
+        );
+        if !memory.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("Container memory ({label})"),
+                y_desc: "GiB".to_string(),
+                series: memory,
+                ..Default::default()
+            });
+        }
+    }
+
+    if presets.contains(&ReportPreset::System) {
+        let failed = held(
+            MetricKind::SystemHealth,
+            metric_series(metrics, MetricKind::SystemHealth),
+        );
+        if !failed.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("Failed systemd units ({label})"),
+                y_desc: "Units".to_string(),
+                series: vec![MetricSeries {
+                    label: "Failed".to_string(),
+                    points: failed,
+                }],
+                ..Default::default()
             });
         }
     }
 
     if presets.contains(&ReportPreset::Temperature) {
-        let temps = aggregate_metric_series_by_source(metrics, MetricKind::Temperature, |v, _| v);
+        let temps = held_by_source(
+            MetricKind::Temperature,
+            aggregate_metric_series_by_source(metrics, MetricKind::Temperature, |v, _| v),
+        );
         if !temps.is_empty() {
             charts.push(ChartSpec {
                 title: format!("Temperature ({label})"),
                 y_desc: "Celsius".to_string(),
                 series: temps,
+                ..Default::default()
+            });
+        }
+    }
+
+    if presets.contains(&ReportPreset::Derived) {
+        let derived = held_by_source(
+            MetricKind::Derived,
+            aggregate_metric_series_by_source(metrics, MetricKind::Derived, |v, _| v),
+        );
+        if !derived.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("Derived metrics ({label})"),
+                y_desc: "Value".to_string(),
+                series: derived,
+                ..Default::default()
+            });
+        }
+    }
+
+    if presets.contains(&ReportPreset::Collector) {
+        let durations =
+            aggregate_metric_series_by_source(metrics, MetricKind::CollectorStats, |v, _| v);
+        if !durations.is_empty() {
+            charts.push(ChartSpec {
+                title: format!("Collection time ({label})"),
+                y_desc: "ms".to_string(),
+                series: durations,
+                ..Default::default()
             });
         }
     }
 
+    let states = state_series(metrics);
+    if !states.is_empty() {
+        charts.push(ChartSpec {
+            title: format!("States ({label})"),
+            y_desc: String::new(),
+            series: states,
+            style: ChartStyle::States,
+            ..Default::default()
+        });
+    }
+
     charts
 }
 
+/// One value per timestamp for an overlay axis: counters become summed per-second rates,
+/// gauges are averaged over sources. Returns the points and the axis label.
+fn overlay_series(metrics: &[MetricSample], kind: &MetricKind) -> (SeriesPoints, String) {
+    let (samples, reduce) = if kind.is_counter() {
+        (counter_rate_samples(metrics, kind.clone()), Reduce::Sum)
+    } else {
+        (metrics.to_vec(), Reduce::Mean)
+    };
+    let unit = samples
+        .iter()
+        .find(|m| &m.kind == kind)
+        .and_then(|m| m.unit.clone())
+        .map(|unit| {
+            if kind.is_counter() {
+                format!("{kind} ({unit}/s)")
+            } else {
+                format!("{kind} ({unit})")
+            }
+        })
+        .unwrap_or_else(|| kind.to_string());
+    let points = series_by_timestamp(&samples, kind.clone(), reduce)
+        .into_iter()
+        .filter_map(|(ts, value)| ts_to_datetime(ts.into_inner()).map(|dt| (dt, value)))
+        .collect();
+    (points, unit)
+}
+
+fn padded_range(points: &[(DateTime<Utc>, f64)]) -> std::ops::Range<f64> {
+    padded_values(points.iter().map(|(_, v)| *v))
+}
+
+fn padded_values(values: impl Iterator<Item = f64> + Clone) -> std::ops::Range<f64> {
+    let mut min_y = values.clone().fold(f64::INFINITY, f64::min);
+    let mut max_y = values.fold(f64::NEG_INFINITY, f64::max);
+    if (max_y - min_y).abs() < 1e-6 {
+        min_y -= 1.0;
+        max_y += 1.0;
+    }
+    let padding = (max_y - min_y) * 0.05;
+    (min_y - padding)..(max_y + padding)
+}
+
+/// Two kinds in one chart, the first on the left y-axis and the second on the right.
+pub fn render_overlay(
+    metrics: &[MetricSample],
+    kinds: (&MetricKind, &MetricKind),
+    timeframe: &Timeframe,
+    step_kinds: &[MetricKind],
+    output: &Path,
+) -> Result<()> {
+    let held = |kind: &MetricKind, (points, desc): (SeriesPoints, String)| {
+        if step_kinds.contains(kind) {
+            (step_points(&points), desc)
+        } else {
+            (points, desc)
+        }
+    };
+    let (left, left_desc) = held(kinds.0, overlay_series(metrics, kinds.0));
+    let (right, right_desc) = held(kinds.1, overlay_series(metrics, kinds.1));
+    if left.is_empty() || right.is_empty() {
+        let missing = if left.is_empty() { kinds.0 } else { kinds.1 };
+        return Err(anyhow::anyhow!(
+            "No {missing} samples in the selected window"
+        ));
+    }
+    let timestamps = left.iter().chain(right.iter()).map(|(ts, _)| *ts);
+    let min_ts = timestamps.clone().min().unwrap();
+    let max_ts = timestamps.max().unwrap();
+
+    draw_image(output, (1280, 480), |root| {
+        root.fill(&WHITE)?;
+        let title = format!(
+            "{} vs {} ({})",
+            kinds.0,
+            kinds.1,
+            timeframe.label.replace('_', " ")
+        );
+        let mut chart_ctx = ChartBuilder::on(root)
+            .caption(title, ("sans-serif", 20).into_font())
+            .margin(12)
+            .x_label_area_size(36)
+            .y_label_area_size(70)
+            .right_y_label_area_size(70)
+            .build_cartesian_2d(min_ts..max_ts, padded_range(&left))?
+            .set_secondary_coord(min_ts..max_ts, padded_range(&right));
+
+        chart_ctx
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(6)
+            .x_desc("Time")
+            .y_desc(left_desc.as_str())
+            .light_line_style(WHITE.mix(0.15))
+            .draw()?;
+        chart_ctx
+            .configure_secondary_axes()
+            .y_labels(6)
+            .y_desc(right_desc.as_str())
+            .draw()?;
+
+        let left_color = Palette99::pick(0).to_rgba();
+        let right_color = Palette99::pick(1).to_rgba();
+        chart_ctx
+            .draw_series(LineSeries::new(left, &left_color))?
+            .label(left_desc)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], left_color));
+        chart_ctx
+            .draw_secondary_series(LineSeries::new(right, &right_color))?
+            .label(right_desc)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], right_color));
+        chart_ctx
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        Ok(())
+    })
+}
+
+/// Scatter plot of `(x, y)` points from `symmetri correlate`, with the least-squares line
+/// when there is one.
+pub fn render_scatter(
+    metrics: &[MetricSample],
+    kinds: (&MetricKind, &MetricKind),
+    points: &[(f64, f64)],
+    fit: Option<(f64, f64)>,
+    timeframe: &Timeframe,
+    output: &Path,
+) -> Result<()> {
+    if points.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No overlapping {} and {} samples in the selected window",
+            kinds.0,
+            kinds.1
+        ));
+    }
+    let (_, x_desc) = overlay_series(metrics, kinds.0);
+    let (_, y_desc) = overlay_series(metrics, kinds.1);
+    let x_range = padded_values(points.iter().map(|(x, _)| *x));
+    let y_range = padded_values(points.iter().map(|(_, y)| *y));
+
+    draw_image(output, (960, 720), |root| {
+        root.fill(&WHITE)?;
+        let title = format!(
+            "{} vs {} ({})",
+            kinds.1,
+            kinds.0,
+            timeframe.label.replace('_', " ")
+        );
+        let mut chart_ctx = ChartBuilder::on(root)
+            .caption(title, ("sans-serif", 20).into_font())
+            .margin(12)
+            .x_label_area_size(40)
+            .y_label_area_size(70)
+            .build_cartesian_2d(x_range.clone(), y_range)?;
+
+        chart_ctx
+            .configure_mesh()
+            .x_labels(8)
+            .y_labels(8)
+            .x_desc(x_desc.as_str())
+            .y_desc(y_desc.as_str())
+            .light_line_style(WHITE.mix(0.15))
+            .draw()?;
+
+        let color = Palette99::pick(0).to_rgba();
+        chart_ctx.draw_series(
+            points
+                .iter()
+                .map(|(x, y)| Circle::new((*x, *y), 3, color.mix(0.6).filled())),
+        )?;
+        if let Some((slope, intercept)) = fit {
+            let line = [x_range.start, x_range.end].map(|x| (x, slope * x + intercept));
+            chart_ctx.draw_series(LineSeries::new(line, &Palette99::pick(1).to_rgba()))?;
+        }
+        Ok(())
+    })
+}
+
 fn plot_chart(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Result<()> {
+    if chart.style == ChartStyle::States {
+        return plot_state_strips(area, chart);
+    }
+    let stacked = chart.style == ChartStyle::Stacked;
+    let layers = if stacked {
+        stack_series(&chart.series)
+    } else {
+        chart.series.iter().map(|s| s.points.clone()).collect()
+    };
     let mut all_points: Vec<(DateTime<Utc>, f64)> = Vec::new();
-    for series in &chart.series {
-        all_points.extend_from_slice(&series.points);
+    for points in &layers {
+        all_points.extend_from_slice(points);
+    }
+    for band in &chart.bands {
+        all_points.extend(
+            band.points
+                .iter()
+                .flat_map(|(ts, (min, max))| [(*ts, *min), (*ts, *max)]),
+        );
     }
 
     let Some(min_ts) = all_points.iter().map(|(ts, _)| *ts).min() else {
@@ -218,7 +765,7 @@ fn plot_chart(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Res
         return Ok(());
     };
 
-    let mut min_y = f64::INFINITY;
+    let mut min_y = if stacked { 0.0 } else { f64::INFINITY };
     let mut max_y = f64::NEG_INFINITY;
     for (_, value) in &all_points {
         min_y = min_y.min(*value);
@@ -248,12 +795,53 @@ fn plot_chart(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Res
         .light_line_style(WHITE.mix(0.15))
         .draw()?;
 
-    for (idx, series) in chart.series.iter().enumerate() {
-        let color = Palette99::pick(idx).to_rgba();
-        chart_ctx
-            .draw_series(LineSeries::new(series.points.clone(), &color))?
-            .label(series.label.clone())
-            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], color));
+    if stacked {
+        // Top layer first, so each lower layer paints over the part it covers.
+        for (idx, (series, points)) in chart.series.iter().zip(&layers).enumerate().rev() {
+            let color = Palette99::pick(idx).to_rgba();
+            chart_ctx
+                .draw_series(AreaSeries::new(points.clone(), 0.0, color))?
+                .label(series.label.clone())
+                .legend(move |(x, y)| {
+                    Rectangle::new([(x, y - 4), (x + 10, y + 4)], color.filled())
+                });
+        }
+    } else {
+        for band in &chart.bands {
+            // Upper edge left to right, then the lower edge back
+            let outline: Vec<(DateTime<Utc>, f64)> = band
+                .points
+                .iter()
+                .map(|(ts, (_, max))| (*ts, *max))
+                .chain(band.points.iter().rev().map(|(ts, (min, _))| (*ts, *min)))
+                .collect();
+            let color = Palette99::pick(band.series).mix(0.2);
+            chart_ctx.draw_series(std::iter::once(Polygon::new(outline, color.filled())))?;
+        }
+        for (idx, series) in chart.series.iter().enumerate() {
+            let color = Palette99::pick(idx).to_rgba();
+            chart_ctx
+                .draw_series(LineSeries::new(series.points.clone(), &color))?
+                .label(series.label.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], color));
+        }
+    }
+
+    let marker_color = BLACK.mix(0.45);
+    for marker in chart
+        .markers
+        .iter()
+        .filter(|m| (min_ts..=max_ts).contains(&m.ts))
+    {
+        chart_ctx.draw_series(std::iter::once(PathElement::new(
+            vec![(marker.ts, y_min), (marker.ts, y_max)],
+            marker_color,
+        )))?;
+        chart_ctx.draw_series(std::iter::once(Text::new(
+            marker.label.clone(),
+            (marker.ts, y_max),
+            ("sans-serif", 12).into_font().color(&marker_color),
+        )))?;
     }
 
     chart_ctx
@@ -265,6 +853,179 @@ fn plot_chart(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Res
     Ok(())
 }
 
+/// On/off series (`kind:source`) for every state kind in the report.
+fn state_series(metrics: &[MetricSample]) -> Vec<MetricSeries> {
+    let mut grouped: BTreeMap<String, SeriesPoints> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind.is_state()) {
+        if let (Some(value), Some(ts)) = (sample.value, ts_to_datetime(sample.ts)) {
+            grouped
+                .entry(format!("{}:{}", sample.kind.as_str(), sample.source))
+                .or_default()
+                .push((ts, value));
+        }
+    }
+    grouped
+        .into_iter()
+        .map(|(label, mut points)| {
+            points.sort_by_key(|(ts, _)| *ts);
+            MetricSeries { label, points }
+        })
+        .collect()
+}
+
+/// A timeline strip per series: filled where the state is on, left empty where it is off.
+/// Each sample's state lasts until the next sample.
+fn plot_state_strips(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Result<()> {
+    let timestamps = chart
+        .series
+        .iter()
+        .flat_map(|s| s.points.iter().map(|(ts, _)| *ts));
+    let (Some(min_ts), Some(max_ts)) = (timestamps.clone().min(), timestamps.max()) else {
+        return Ok(());
+    };
+    let rows = chart.series.len();
+    let labels: Vec<String> = chart.series.iter().map(|s| s.label.clone()).collect();
+
+    let mut chart_ctx = ChartBuilder::on(&area)
+        .caption(&chart.title, ("sans-serif", 20).into_font())
+        .margin(12)
+        .x_label_area_size(36)
+        .y_label_area_size(140)
+        .build_cartesian_2d(min_ts..max_ts, 0.0..rows as f64)?;
+    chart_ctx
+        .configure_mesh()
+        .x_labels(5)
+        .y_labels(rows * 2 + 1)
+        .y_label_formatter(&|y| {
+            // Label the middle of each strip only
+            let idx = (y - 0.5).round();
+            if (y - 0.5 - idx).abs() > 0.01 || idx < 0.0 {
+                return String::new();
+            }
+            labels
+                .get(rows.saturating_sub(idx as usize + 1))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .x_desc("Time")
+        .disable_y_mesh()
+        .light_line_style(WHITE.mix(0.15))
+        .draw()?;
+
+    for (idx, series) in chart.series.iter().enumerate() {
+        let color = Palette99::pick(idx).to_rgba();
+        // The first series is drawn at the top
+        let row = (rows - idx - 1) as f64;
+        let spans = series
+            .points
+            .windows(2)
+            .filter(|pair| pair[0].1 > 0.5)
+            .map(|pair| {
+                Rectangle::new(
+                    [(pair[0].0, row + 0.15), (pair[1].0, row + 0.85)],
+                    color.filled(),
+                )
+            });
+        chart_ctx.draw_series(spans)?;
+        chart_ctx.draw_series(std::iter::once(Rectangle::new(
+            [(min_ts, row + 0.15), (max_ts, row + 0.85)],
+            color.stroke_width(1),
+        )))?;
+    }
+    Ok(())
+}
+
+/// Step-line form of a series: each value holds until the next sample, where the line
+/// jumps vertically, instead of implying smooth change between sparse samples.
+fn step_points<T: Copy>(points: &[(DateTime<Utc>, T)]) -> Vec<(DateTime<Utc>, T)> {
+    let mut stepped = Vec::with_capacity(points.len() * 2);
+    for (idx, point) in points.iter().enumerate() {
+        if let Some(previous) = idx.checked_sub(1).map(|prev| points[prev]) {
+            stepped.push((point.0, previous.1));
+        }
+        stepped.push(*point);
+    }
+    stepped
+}
+
+/// Running totals of the series at each timestamp, bottom layer first.
+fn stack_series(series: &[MetricSeries]) -> Vec<SeriesPoints> {
+    let mut totals: BTreeMap<DateTime<Utc>, f64> = BTreeMap::new();
+    series
+        .iter()
+        .map(|layer| {
+            layer
+                .points
+                .iter()
+                .map(|(ts, value)| {
+                    let total = totals.entry(*ts).or_default();
+                    *total += value;
+                    (*ts, *total)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// What the used memory consists of, from the `/proc/meminfo` breakdown in the details;
+/// samples collected before the breakdown existed are skipped.
+fn memory_composition_series(metrics: &[MetricSample]) -> Vec<MetricSeries> {
+    let labels = ["Applications", "Slab", "Buffers", "Page cache"];
+    let mut layers: Vec<MetricSeries> = labels
+        .iter()
+        .map(|label| MetricSeries {
+            label: label.to_string(),
+            points: Vec::new(),
+        })
+        .collect();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::MemoryUsage) {
+        let field = |key| sample.detail_number(key);
+        let (Some(total), Some(free), Some(buffers), Some(cached), Some(slab), Some(ts)) = (
+            sample.total_bytes,
+            field("free_bytes"),
+            field("buffers_bytes"),
+            field("cached_bytes"),
+            field("slab_bytes"),
+            ts_to_datetime(sample.ts),
+        ) else {
+            continue;
+        };
+        let applications = (total - free - buffers - cached - slab).max(0.0);
+        for (layer, bytes) in layers.iter_mut().zip([applications, slab, buffers, cached]) {
+            layer.points.push((ts, bytes_to_gib(bytes)));
+        }
+    }
+    layers.retain(|layer| !layer.points.is_empty());
+    layers
+}
+
+/// One marker per power profile switch, labelled with the new profile.
+fn profile_change_markers(metrics: &[MetricSample]) -> Vec<ChartMarker> {
+    let mut samples: Vec<&MetricSample> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::PowerProfile)
+        .collect();
+    samples.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+
+    let mut markers = Vec::new();
+    let mut previous: Option<&str> = None;
+    for sample in samples {
+        let Some(profile) = sample.details.get("profile").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        if previous.is_some_and(|p| p != profile) {
+            if let Some(ts) = ts_to_datetime(sample.ts) {
+                markers.push(ChartMarker {
+                    ts,
+                    label: profile.to_string(),
+                });
+            }
+        }
+        previous = Some(profile);
+    }
+    markers
+}
+
 fn metric_series(metrics: &[MetricSample], kind: MetricKind) -> Vec<(DateTime<Utc>, f64)> {
     metrics
         .iter()
@@ -277,11 +1038,11 @@ fn metric_series(metrics: &[MetricSample], kind: MetricKind) -> Vec<(DateTime<Ut
         .collect()
 }
 
-fn aggregate_metric_series<F>(
+fn values_by_timestamp<F>(
     metrics: &[MetricSample],
     kind: MetricKind,
     mut map_value: F,
-) -> Vec<(DateTime<Utc>, f64)>
+) -> BTreeMap<OrderedFloat<f64>, Vec<f64>>
 where
     F: FnMut(f64, &MetricSample) -> f64,
 {
@@ -294,8 +1055,18 @@ where
                 .push(map_value(value, sample));
         }
     }
-
     grouped
+}
+
+fn aggregate_metric_series<F>(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    map_value: F,
+) -> Vec<(DateTime<Utc>, f64)>
+where
+    F: FnMut(f64, &MetricSample) -> f64,
+{
+    values_by_timestamp(metrics, kind, map_value)
         .into_iter()
         .filter_map(|(ts, values)| {
             if values.is_empty() {
@@ -307,6 +1078,27 @@ where
         .collect()
 }
 
+/// Min and max of the values `aggregate_metric_series` averages at each timestamp; empty
+/// when no timestamp has more than one value, since there is no spread to show.
+fn spread_band(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    map_value: fn(f64) -> f64,
+) -> Vec<(DateTime<Utc>, (f64, f64))> {
+    let grouped = values_by_timestamp(metrics, kind, |v, _| map_value(v));
+    if grouped.values().all(|values| values.len() < 2) {
+        return Vec::new();
+    }
+    grouped
+        .into_iter()
+        .filter_map(|(ts, values)| {
+            let min = values.iter().copied().reduce(f64::min)?;
+            let max = values.iter().copied().reduce(f64::max)?;
+            ts_to_datetime(ts.into_inner()).map(|dt| (dt, (min, max)))
+        })
+        .collect()
+}
+
 fn aggregate_metric_series_by_source<F>(
     metrics: &[MetricSample],
     kind: MetricKind,
@@ -349,6 +1141,74 @@ where
     series
 }
 
+/// Trend line per mountpoint, projected ahead by the observed span or until the disk is full.
+fn disk_forecast_series(metrics: &[MetricSample]) -> Vec<MetricSeries> {
+    disk_forecasts(metrics)
+        .into_iter()
+        .filter_map(|forecast| {
+            let horizon = forecast.last_ts + (forecast.last_ts - forecast.first_ts);
+            let end_ts = forecast
+                .full_ts()
+                .map(|full| full.min(horizon))
+                .unwrap_or(horizon);
+            let points = [forecast.first_ts, end_ts]
+                .into_iter()
+                .filter_map(|ts| {
+                    ts_to_datetime(ts).map(|dt| (dt, bytes_to_gib(forecast.used_at(ts))))
+                })
+                .collect::<Vec<_>>();
+            (points.len() == 2).then(|| MetricSeries {
+                label: format!("{} forecast", forecast.source),
+                points,
+            })
+        })
+        .collect()
+}
+
+/// Throttle events per bucket, summed over cores and over packages.
+fn throttle_event_series(metrics: &[MetricSample], timeframe: &Timeframe) -> Vec<MetricSeries> {
+    use crate::cli_helpers::{bucket_counter_deltas, bucket_span_seconds};
+
+    let timestamps: Vec<f64> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::CpuThrottle)
+        .map(|m| m.ts)
+        .collect();
+    let span = timestamps
+        .iter()
+        .copied()
+        .reduce(f64::max)
+        .zip(timestamps.iter().copied().reduce(f64::min))
+        .map(|(max, min)| max - min);
+    let bucket_seconds = bucket_span_seconds(timeframe, span);
+    let deltas = bucket_counter_deltas(metrics, MetricKind::CpuThrottle, bucket_seconds);
+
+    let mut series = Vec::new();
+    for (label, prefix) in [("Core", "cpu"), ("Package", "package")] {
+        let mut totals: BTreeMap<i64, f64> = BTreeMap::new();
+        for (_, buckets) in deltas
+            .iter()
+            .filter(|(source, _)| source.starts_with(prefix))
+        {
+            for (bucket, delta) in buckets {
+                *totals.entry(bucket.timestamp()).or_default() += delta;
+            }
+        }
+        if totals.values().all(|total| *total == 0.0) {
+            continue;
+        }
+        let points = totals
+            .into_iter()
+            .filter_map(|(ts, total)| ts_to_datetime(ts as f64).map(|dt| (dt, total)))
+            .collect();
+        series.push(MetricSeries {
+            label: label.to_string(),
+            points,
+        });
+    }
+    series
+}
+
 fn network_bucket_series(
     metrics: &[MetricSample],
     timeframe: &Timeframe,
@@ -377,14 +1237,8 @@ fn network_bucket_series(
                 continue;
             }
 
-            let rx_delta = counter_delta(
-                detail_number(prev, "rx_bytes"),
-                detail_number(next, "rx_bytes"),
-            );
-            let tx_delta = counter_delta(
-                detail_number(prev, "tx_bytes"),
-                detail_number(next, "tx_bytes"),
-            );
+            let rx_delta = counter_delta(prev.rx_bytes, next.rx_bytes);
+            let tx_delta = counter_delta(prev.tx_bytes, next.tx_bytes);
 
             if rx_delta > 0.0 || tx_delta > 0.0 {
                 all_deltas.push((next.ts, rx_delta, tx_delta));
@@ -449,13 +1303,6 @@ fn counter_delta(previous: Option<f64>, current: Option<f64>) -> f64 {
     }
 }
 
-fn detail_number(sample: &MetricSample, key: &str) -> Option<f64> {
-    sample
-        .details
-        .get(key)
-        .and_then(|v| v.as_f64().or_else(|| v.as_i64().map(|i| i as f64)))
-}
-
 fn bytes_to_gib(used: f64) -> f64 {
     used / (1024.0 * 1024.0 * 1024.0)
 }
@@ -478,6 +1325,14 @@ mod tests {
             value: Some(value),
             unit: None,
             details: serde_json::Value::Null,
+            rx_bytes: None,
+            tx_bytes: None,
+            total_bytes: None,
+            status: None,
+            histogram: None,
+            label: None,
+            host: None,
+            boot: None,
         }
     }
 
@@ -496,4 +1351,88 @@ mod tests {
         assert_eq!(cpu0.points.len(), 2);
         assert_eq!(cpu1.points.len(), 1);
     }
+
+    #[test]
+    fn profile_switches_become_markers() {
+        let profile = |ts: f64, name: &str| MetricSample {
+            details: serde_json::json!({ "profile": name }),
+            ..metric_sample("power-profiles-daemon", ts, 1.0, MetricKind::PowerProfile)
+        };
+        let metrics = vec![
+            profile(120.0, "performance"),
+            profile(0.0, "balanced"),
+            profile(60.0, "balanced"),
+            profile(180.0, "performance"),
+        ];
+
+        let markers = profile_change_markers(&metrics);
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].label, "performance");
+        assert_eq!(markers[0].ts, ts_to_datetime(120.0).unwrap());
+    }
+
+    #[test]
+    fn step_points_hold_values_until_the_next_sample() {
+        let at = |ts: f64| ts_to_datetime(ts).unwrap();
+        let stepped = step_points(&[(at(0.0), 1.0), (at(60.0), 5.0), (at(120.0), 2.0)]);
+        assert_eq!(
+            stepped,
+            [
+                (at(0.0), 1.0),
+                (at(60.0), 1.0),
+                (at(60.0), 5.0),
+                (at(120.0), 5.0),
+                (at(120.0), 2.0),
+            ]
+        );
+        assert!(step_points::<f64>(&[]).is_empty());
+    }
+
+    #[test]
+    fn spread_band_covers_averaged_sources() {
+        let metrics = vec![
+            metric_sample("BAT0", 0.0, 6.0, MetricKind::PowerDraw),
+            metric_sample("amdgpu", 0.0, 2.0, MetricKind::PowerDraw),
+            metric_sample("BAT0", 60.0, 5.0, MetricKind::PowerDraw),
+        ];
+        let band = spread_band(&metrics, MetricKind::PowerDraw, |v| v);
+        let spreads: Vec<(f64, f64)> = band.iter().map(|(_, spread)| *spread).collect();
+        assert_eq!(spreads, [(2.0, 6.0), (5.0, 5.0)]);
+        assert_eq!(
+            aggregate_metric_series(&metrics, MetricKind::PowerDraw, |v, _| v)[0].1,
+            4.0
+        );
+
+        // A single source has no spread to show
+        assert!(spread_band(&metrics[2..], MetricKind::PowerDraw, |v| v).is_empty());
+    }
+
+    #[test]
+    fn memory_composition_stacks_up_to_used_and_cache() {
+        let gib = 1024.0 * 1024.0 * 1024.0;
+        let metrics = vec![
+            MetricSample {
+                total_bytes: Some(16.0 * gib),
+                details: serde_json::json!({
+                    "free_bytes": 4.0 * gib,
+                    "buffers_bytes": 0.5 * gib,
+                    "cached_bytes": 6.0 * gib,
+                    "slab_bytes": 1.0 * gib,
+                }),
+                ..metric_sample("memory", 0.0, 8.0 * gib, MetricKind::MemoryUsage)
+            },
+            // Collected before the breakdown was stored
+            metric_sample("memory", 60.0, 8.0 * gib, MetricKind::MemoryUsage),
+        ];
+
+        let series = memory_composition_series(&metrics);
+        let labels: Vec<&str> = series.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["Applications", "Slab", "Buffers", "Page cache"]);
+        assert_eq!(series[0].points.len(), 1);
+        assert_eq!(series[0].points[0].1, 4.5);
+
+        let stacked = stack_series(&series);
+        let tops: Vec<f64> = stacked.iter().map(|layer| layer[0].1).collect();
+        assert_eq!(tops, [4.5, 5.5, 6.0, 12.0]);
+    }
 }
@@ -0,0 +1,100 @@
+//! Pre-bucketed distributions (e.g. from latency probes) stored next to the scalar value.
+
+use serde::{Deserialize, Serialize};
+
+/// Non-cumulative bucket counts: `counts[i]` observations fell in `(bounds[i - 1], bounds[i]]`,
+/// with an optional extra count for everything above the last bound.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// Ascending upper bounds of the buckets
+    pub bounds: Vec<f64>,
+    pub counts: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum HistogramError {
+    #[error("bucket bounds must be ascending")]
+    UnsortedBounds,
+    #[error("expected {0} or {} bucket counts, got {1}", .0 + 1)]
+    CountMismatch(usize, usize),
+}
+
+impl Histogram {
+    pub fn new(bounds: Vec<f64>, counts: Vec<u64>) -> Result<Self, HistogramError> {
+        if bounds.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(HistogramError::UnsortedBounds);
+        }
+        if counts.len() != bounds.len() && counts.len() != bounds.len() + 1 {
+            return Err(HistogramError::CountMismatch(bounds.len(), counts.len()));
+        }
+        Ok(Histogram { bounds, counts })
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Add the counts of a histogram with the same buckets; returns false when they differ.
+    pub fn merge(&mut self, other: &Histogram) -> bool {
+        if self.bounds != other.bounds || self.counts.len() != other.counts.len() {
+            return false;
+        }
+        for (count, extra) in self.counts.iter_mut().zip(&other.counts) {
+            *count += extra;
+        }
+        true
+    }
+
+    /// Estimate the `q` quantile (0..=1) by interpolating linearly inside the bucket that
+    /// holds it. The first bucket starts at zero; observations above the last bound are
+    /// reported as the last bound since nothing more is known about them.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let rank = q.clamp(0.0, 1.0) * total as f64;
+        let mut seen = 0.0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            let count = *count as f64;
+            let Some(upper) = self.bounds.get(idx) else {
+                return self.bounds.last().copied();
+            };
+            if count > 0.0 && seen + count >= rank {
+                let lower = if idx == 0 {
+                    0.0_f64.min(*upper)
+                } else {
+                    self.bounds[idx - 1]
+                };
+                return Some(lower + (upper - lower) * ((rank - seen) / count));
+            }
+            seen += count;
+        }
+        self.bounds.last().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_interpolate_within_buckets() {
+        let mut hist = Histogram::new(vec![10.0, 20.0, 50.0], vec![50, 30, 20]).unwrap();
+        assert_eq!(hist.quantile(0.5), Some(10.0));
+        assert_eq!(hist.quantile(0.65), Some(15.0));
+        assert_eq!(hist.quantile(0.9), Some(35.0));
+
+        assert!(hist.merge(&Histogram::new(vec![10.0, 20.0, 50.0], vec![0, 0, 100]).unwrap()));
+        assert_eq!(hist.total(), 200);
+        assert_eq!(hist.quantile(0.25), Some(10.0));
+        assert!(!hist.merge(&Histogram::new(vec![1.0], vec![1]).unwrap()));
+
+        let overflow = Histogram::new(vec![1.0, 2.0], vec![1, 1, 8]).unwrap();
+        assert_eq!(overflow.quantile(0.99), Some(2.0));
+        assert_eq!(
+            Histogram::new(vec![2.0, 1.0], vec![1, 1]),
+            Err(HistogramError::UnsortedBounds)
+        );
+    }
+}
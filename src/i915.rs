@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Engine class of an i915 sysfs engine directory (`rcs0`, `vcs1`, ...), as named in fdinfo.
+fn engine_class(engine: &str) -> Option<&'static str> {
+    let prefix = engine.trim_end_matches(|c: char| c.is_ascii_digit());
+    match prefix {
+        "rcs" => Some("render"),
+        "bcs" => Some("copy"),
+        "vcs" => Some("video"),
+        "vecs" => Some("video-enhance"),
+        "ccs" => Some("compute"),
+        _ => None,
+    }
+}
+
+/// An i915 DRM client as listed in `/proc/<pid>/fdinfo/<fd>`.
+#[derive(Debug, PartialEq)]
+struct DrmClient {
+    pdev: String,
+    client_id: String,
+    /// Cumulative busy nanoseconds per engine class
+    engines: BTreeMap<String, u64>,
+}
+
+fn parse_drm_fdinfo(content: &str) -> Option<DrmClient> {
+    let mut driver = None;
+    let mut pdev = None;
+    let mut client_id = None;
+    let mut engines = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "drm-driver" => driver = Some(value),
+            "drm-pdev" => pdev = Some(value.to_string()),
+            "drm-client-id" => client_id = Some(value.to_string()),
+            _ => {
+                let Some(class) = key.strip_prefix("drm-engine-") else {
+                    continue;
+                };
+                if class.starts_with("capacity-") {
+                    continue;
+                }
+                if let Some(ns) = value.strip_suffix(" ns").and_then(|v| v.parse().ok()) {
+                    engines.insert(class.to_string(), ns);
+                }
+            }
+        }
+    }
+    (driver == Some("i915")).then_some(DrmClient {
+        pdev: pdev?,
+        client_id: client_id?,
+        engines,
+    })
+}
+
+/// Engine busy time of every visible i915 DRM client, keyed by PCI device and client id.
+/// A file descriptor can be shared between processes, so clients are keyed by id.
+type ClientBusy = BTreeMap<(String, String), BTreeMap<String, u64>>;
+
+fn drm_clients(proc_root: &Path) -> ClientBusy {
+    let mut clients = ClientBusy::new();
+    for process in fs::read_dir(proc_root).into_iter().flatten().flatten() {
+        let Ok(fds) = fs::read_dir(process.path().join("fdinfo")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Some(client) = fs::read_to_string(fd.path())
+                .ok()
+                .and_then(|content| parse_drm_fdinfo(&content))
+            else {
+                continue;
+            };
+            clients.insert((client.pdev, client.client_id), client.engines);
+        }
+    }
+    clients
+}
+
+/// Busy nanoseconds per (PCI device, engine class) between two reads, over the clients
+/// present in both so that clients opening or closing in between do not skew it.
+fn busy_deltas(first: &ClientBusy, second: &ClientBusy) -> BTreeMap<(String, String), u64> {
+    let mut busy = BTreeMap::new();
+    for (key, engines) in second {
+        let Some(before) = first.get(key) else {
+            continue;
+        };
+        for (class, ns) in engines {
+            let delta = ns.saturating_sub(before.get(class).copied().unwrap_or(*ns));
+            *busy.entry((key.0.clone(), class.clone())).or_default() += delta;
+        }
+    }
+    busy
+}
+
+/// An i915 card with the engine instances it exposes under `engine/`.
+struct Card {
+    name: String,
+    pdev: String,
+    /// Instances per engine class
+    engines: BTreeMap<&'static str, usize>,
+}
+
+fn i915_cards(drm_root: &Path) -> Vec<Card> {
+    let mut cards = Vec::new();
+    for entry in fs::read_dir(drm_root).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with("card") || name.contains('-') {
+            continue;
+        }
+        let device = entry.path().join("device");
+        let driver = fs::read_link(device.join("driver")).ok();
+        if driver.as_deref().and_then(Path::file_name) != Some("i915".as_ref()) {
+            continue;
+        }
+        let Some(pdev) = fs::canonicalize(&device)
+            .ok()
+            .and_then(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+        else {
+            continue;
+        };
+        let mut engines = BTreeMap::new();
+        for engine in fs::read_dir(entry.path().join("engine"))
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            if let Some(class) = engine_class(&engine.file_name().to_string_lossy()) {
+                *engines.entry(class).or_default() += 1;
+            }
+        }
+        cards.push(Card {
+            name,
+            pdev,
+            engines,
+        });
+    }
+    cards
+}
+
+fn engine_usage_samples(
+    ts: f64,
+    cards: &[Card],
+    busy: &BTreeMap<(String, String), u64>,
+    elapsed: Duration,
+) -> Vec<MetricSample> {
+    let elapsed_ns = elapsed.as_nanos() as f64;
+    let mut samples = Vec::new();
+    for card in cards {
+        for (class, instances) in &card.engines {
+            let key = (card.pdev.clone(), class.to_string());
+            let delta = busy.get(&key).copied().unwrap_or(0);
+            let usage = delta as f64 / (elapsed_ns * *instances as f64) * 100.0;
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::GpuUsage,
+                format!("{}:{class}", card.name),
+                Some(usage.clamp(0.0, 100.0)),
+                Some("%"),
+                json!({ "engine": class, "instances": instances }),
+            ));
+        }
+    }
+    samples
+}
+
+/// Per-engine busyness of Intel GPUs (render, video, copy, ...), which i915 only exposes per
+/// DRM client in fdinfo. Clients of other users are only visible when running as root.
+pub fn i915_engine_samples(ts: f64) -> Vec<MetricSample> {
+    let cards = i915_cards(Path::new("/sys/class/drm"));
+    if cards.is_empty() {
+        return Vec::new();
+    }
+    let proc_root = Path::new("/proc");
+    let start = Instant::now();
+    let first = drm_clients(proc_root);
+    thread::sleep(Duration::from_millis(100));
+    let second = drm_clients(proc_root);
+    let elapsed = start.elapsed();
+    engine_usage_samples(ts, &cards, &busy_deltas(&first, &second), elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fdinfo_busy_time_becomes_engine_usage() {
+        let fdinfo = |render: u64| {
+            format!(
+                "pos:\t0\nflags:\t02100002\ndrm-driver:\ti915\ndrm-pdev:\t0000:00:02.0\n\
+                 drm-client-id:\t42\ndrm-engine-render:\t{render} ns\ndrm-engine-copy:\t0 ns\n\
+                 drm-engine-video:\t500 ns\ndrm-engine-capacity-video:\t2\n"
+            )
+        };
+        let client = parse_drm_fdinfo(&fdinfo(1_000)).unwrap();
+        assert_eq!(client.client_id, "42");
+        assert_eq!(client.engines.get("video"), Some(&500));
+        assert_eq!(client.engines.len(), 3);
+        assert!(
+            parse_drm_fdinfo("drm-driver:\tamdgpu\ndrm-pdev:\tx\ndrm-client-id:\t1\n").is_none()
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        let fd_dir = tmp.path().join("100").join("fdinfo");
+        fs::create_dir_all(&fd_dir).unwrap();
+        // The same client shared by two descriptors counts once
+        fs::write(fd_dir.join("5"), fdinfo(1_000)).unwrap();
+        fs::write(fd_dir.join("6"), fdinfo(1_000)).unwrap();
+        let first = drm_clients(tmp.path());
+        fs::write(fd_dir.join("5"), fdinfo(26_000)).unwrap();
+        fs::write(fd_dir.join("6"), fdinfo(26_000)).unwrap();
+        fs::write(fd_dir.join("7"), fdinfo(900_000).replace("\t42", "\t43")).unwrap();
+        let second = drm_clients(tmp.path());
+
+        let card = Card {
+            name: "card1".to_string(),
+            pdev: "0000:00:02.0".to_string(),
+            engines: BTreeMap::from([("render", 1), ("video", 2)]),
+        };
+        // Client 43 opened between the reads and is left out
+        let busy = busy_deltas(&first, &second);
+        let samples = engine_usage_samples(1.0, &[card], &busy, Duration::from_micros(100));
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].source, "card1:render");
+        assert_eq!(samples[0].value, Some(25.0));
+        assert_eq!(samples[1].source, "card1:video");
+        assert_eq!(samples[1].value, Some(0.0));
+        assert_eq!(engine_class("vecs0"), Some("video-enhance"));
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use anyhow::Result;
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+
+/// Terminal graphics protocols that can show a chart inline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlineProtocol {
+    Kitty,
+    Iterm,
+    Sixel,
+}
+
+const KITTY_CHUNK: usize = 4096;
+const SIXEL_TERMS: &[&str] = &["foot", "foot-extra", "mlterm", "yaft-256color", "contour"];
+
+fn protocol_from_env<F>(var: F) -> Option<InlineProtocol>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let term = var("TERM").unwrap_or_default();
+    let term_program = var("TERM_PROGRAM").unwrap_or_default();
+    if var("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || term_program == "ghostty"
+    {
+        return Some(InlineProtocol::Kitty);
+    }
+    if term_program == "iTerm.app"
+        || term_program == "WezTerm"
+        || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        return Some(InlineProtocol::Iterm);
+    }
+    if term.contains("sixel") || SIXEL_TERMS.contains(&term.as_str()) {
+        return Some(InlineProtocol::Sixel);
+    }
+    None
+}
+
+/// Inline protocol supported by the current terminal, guessed from the environment.
+pub fn detect_protocol() -> Option<InlineProtocol> {
+    protocol_from_env(|name| std::env::var(name).ok())
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn encode_png(rgb: &[u8], size: (u32, u32)) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).write_image(rgb, size.0, size.1, ColorType::Rgb8)?;
+    Ok(png)
+}
+
+fn write_kitty(out: &mut impl Write, png: &[u8]) -> Result<()> {
+    let encoded = base64(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(idx + 1 < chunks.len());
+        if idx == 0 {
+            write!(out, "\x1b_Gf=100,a=T,m={more};")?;
+        } else {
+            write!(out, "\x1b_Gm={more};")?;
+        }
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_iterm(out: &mut impl Write, png: &[u8]) -> Result<()> {
+    write!(
+        out,
+        "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        base64(png)
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Map a channel to one of six levels of the 6x6x6 color cube.
+fn cube_level(channel: u8) -> usize {
+    (channel as usize * 5 + 127) / 255
+}
+
+fn write_sixel(out: &mut impl Write, rgb: &[u8], size: (u32, u32)) -> Result<()> {
+    let (width, height) = (size.0 as usize, size.1 as usize);
+    let indices: Vec<usize> = rgb
+        .chunks_exact(3)
+        .map(|px| cube_level(px[0]) * 36 + cube_level(px[1]) * 6 + cube_level(px[2]))
+        .collect();
+
+    write!(out, "\x1bPq\"1;1;{width};{height}")?;
+    let mut used = vec![false; 216];
+    for idx in &indices {
+        used[*idx] = true;
+    }
+    for (idx, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let percent = |level: usize| level * 100 / 5;
+        write!(
+            out,
+            "#{idx};2;{};{};{}",
+            percent(idx / 36),
+            percent(idx / 6 % 6),
+            percent(idx % 6)
+        )?;
+    }
+
+    for band_top in (0..height).step_by(6) {
+        let rows = (height - band_top).min(6);
+        let mut columns: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for row in 0..rows {
+            let offset = (band_top + row) * width;
+            for x in 0..width {
+                columns
+                    .entry(indices[offset + x])
+                    .or_insert_with(|| vec![0; width])[x] |= 1 << row;
+            }
+        }
+        for (idx, bits) in columns {
+            write!(out, "#{idx}")?;
+            let mut x = 0;
+            while x < width {
+                let run = bits[x..].iter().take_while(|b| **b == bits[x]).count();
+                let ch = (63 + bits[x]) as char;
+                if run > 3 {
+                    write!(out, "!{run}{ch}")?;
+                } else {
+                    for _ in 0..run {
+                        write!(out, "{ch}")?;
+                    }
+                }
+                x += run;
+            }
+            write!(out, "$")?;
+        }
+        write!(out, "-")?;
+    }
+    writeln!(out, "\x1b\\")?;
+    Ok(())
+}
+
+/// Write an RGB image to the terminal using the given protocol.
+pub fn display(
+    out: &mut impl Write,
+    protocol: InlineProtocol,
+    rgb: &[u8],
+    size: (u32, u32),
+) -> Result<()> {
+    match protocol {
+        InlineProtocol::Kitty => write_kitty(out, &encode_png(rgb, size)?)?,
+        InlineProtocol::Iterm => write_iterm(out, &encode_png(rgb, size)?)?,
+        InlineProtocol::Sixel => write_sixel(out, rgb, size)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn protocols_are_detected_from_environment() {
+        assert_eq!(
+            protocol_from_env(env(&[("TERM", "xterm-kitty")])),
+            Some(InlineProtocol::Kitty)
+        );
+        assert_eq!(
+            protocol_from_env(env(&[
+                ("TERM", "xterm-256color"),
+                ("TERM_PROGRAM", "WezTerm")
+            ])),
+            Some(InlineProtocol::Iterm)
+        );
+        assert_eq!(
+            protocol_from_env(env(&[("TERM", "foot")])),
+            Some(InlineProtocol::Sixel)
+        );
+        assert_eq!(protocol_from_env(env(&[("TERM", "xterm-256color")])), None);
+    }
+
+    #[test]
+    fn base64_pads_partial_groups() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+
+    #[test]
+    fn sixel_encodes_bands_with_run_lengths() {
+        // 5x1 image: four white pixels then one black.
+        let mut rgb = vec![255u8; 4 * 3];
+        rgb.extend_from_slice(&[0, 0, 0]);
+        let mut out = Vec::new();
+        write_sixel(&mut out, &rgb, (5, 1)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("\x1bPq\"1;1;5;1#0;2;0;0;0#215;2;100;100;100"));
+        assert!(text.contains("#0!4?@$"));
+        assert!(text.contains("#215!4@?$"));
+        assert!(text.ends_with("-\x1b\\\n"));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::forecast::linear_fit;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// A collection gap this long is treated as a reboot or suspend boundary.
+const SESSION_GAP_SECONDS: f64 = 30.0 * 60.0;
+const MIN_SESSION_SECONDS: f64 = 2.0 * 3600.0;
+const MIN_SESSION_SAMPLES: usize = 6;
+/// Share of consecutive samples that must not decrease.
+const MIN_MONOTONIC_RATIO: f64 = 0.7;
+const MIN_R_SQUARED: f64 = 0.8;
+const MIN_GROWTH_BYTES: f64 = 256.0 * 1024.0 * 1024.0;
+/// Growth must also exceed this share of total memory.
+const MIN_GROWTH_RATIO: f64 = 0.05;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeakPeriod {
+    pub start_ts: f64,
+    pub end_ts: f64,
+    pub growth_bytes: f64,
+    pub bytes_per_hour: f64,
+    pub monotonic_ratio: f64,
+}
+
+/// Split time-ordered samples wherever consecutive timestamps are further apart than `max_gap`.
+pub fn split_at_gaps<'a>(samples: &[&'a MetricSample], max_gap: f64) -> Vec<Vec<&'a MetricSample>> {
+    let mut sessions: Vec<Vec<&MetricSample>> = Vec::new();
+    for sample in samples {
+        match sessions.last_mut() {
+            Some(current) if sample.ts - current.last().unwrap().ts <= max_gap => {
+                current.push(sample)
+            }
+            _ => sessions.push(vec![sample]),
+        }
+    }
+    sessions
+}
+
+fn r_squared(points: &[(f64, f64)], slope: f64, intercept: f64) -> f64 {
+    let mean = points.iter().map(|(_, y)| y).sum::<f64>() / points.len() as f64;
+    let total: f64 = points.iter().map(|(_, y)| (y - mean).powi(2)).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let residual: f64 = points
+        .iter()
+        .map(|(x, y)| (y - (slope * x + intercept)).powi(2))
+        .sum();
+    1.0 - residual / total
+}
+
+fn analyze_session(session: &[&MetricSample]) -> Option<LeakPeriod> {
+    let first = session.first()?;
+    let last = session.last()?;
+    if session.len() < MIN_SESSION_SAMPLES || last.ts - first.ts < MIN_SESSION_SECONDS {
+        return None;
+    }
+    let points: Vec<(f64, f64)> = session
+        .iter()
+        .filter_map(|s| s.value.map(|v| (s.ts - first.ts, v)))
+        .collect();
+    let (slope, intercept) = linear_fit(&points)?;
+    if slope <= 0.0 || r_squared(&points, slope, intercept) < MIN_R_SQUARED {
+        return None;
+    }
+
+    let non_decreasing = points.windows(2).filter(|w| w[1].1 >= w[0].1).count();
+    let monotonic_ratio = non_decreasing as f64 / (points.len() - 1) as f64;
+    let duration = last.ts - first.ts;
+    let growth_bytes = slope * duration;
+    let total_bytes = last.total_bytes.unwrap_or(0.0);
+    if monotonic_ratio < MIN_MONOTONIC_RATIO
+        || growth_bytes < MIN_GROWTH_BYTES
+        || growth_bytes < total_bytes * MIN_GROWTH_RATIO
+    {
+        return None;
+    }
+
+    Some(LeakPeriod {
+        start_ts: first.ts,
+        end_ts: last.ts,
+        growth_bytes,
+        bytes_per_hour: slope * 3600.0,
+        monotonic_ratio,
+    })
+}
+
+/// Split time-ordered samples wherever the recorded kernel boot changes; samples stored
+/// before boots were recorded stay with their neighbours.
+fn split_at_boots<'a>(samples: &[&'a MetricSample]) -> Vec<Vec<&'a MetricSample>> {
+    let mut boots: Vec<(Option<&str>, Vec<&MetricSample>)> = Vec::new();
+    for sample in samples {
+        let boot = sample.boot.as_deref();
+        match boots.last_mut() {
+            Some((current, group))
+                if boot.is_none() || current.is_none_or(|current| Some(current) == boot) =>
+            {
+                *current = current.or(boot);
+                group.push(sample);
+            }
+            _ => boots.push((boot, vec![sample])),
+        }
+    }
+    boots.into_iter().map(|(_, group)| group).collect()
+}
+
+/// Flag uninterrupted collection sessions where used memory grows steadily. A reboot
+/// always ends a session, however short the gap around it.
+pub fn detect_memory_leaks(metrics: &[MetricSample]) -> Vec<LeakPeriod> {
+    let mut memory: Vec<&MetricSample> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::MemoryUsage && m.value.is_some())
+        .collect();
+    memory.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+
+    split_at_boots(&memory)
+        .iter()
+        .flat_map(|boot| split_at_gaps(boot, SESSION_GAP_SECONDS))
+        .filter_map(|session| analyze_session(&session))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+    fn memory_sample(ts: f64, used_gib: f64) -> MetricSample {
+        MetricSample::new(
+            ts,
+            MetricKind::MemoryUsage,
+            "memory",
+            Some(used_gib * GIB),
+            Some("bytes"),
+            Value::Null,
+        )
+        .with_total_bytes(16.0 * GIB)
+    }
+
+    #[test]
+    fn steady_growth_is_flagged_per_session() {
+        let mut metrics: Vec<MetricSample> = (0..12)
+            .map(|i| memory_sample(i as f64 * 900.0, 4.0 + i as f64 * 0.2))
+            .collect();
+        // After a long gap (reboot), usage is flat again.
+        let restart = 20.0 * 3600.0;
+        metrics.extend((0..12).map(|i| memory_sample(restart + i as f64 * 900.0, 3.0)));
+
+        let leaks = detect_memory_leaks(&metrics);
+        assert_eq!(leaks.len(), 1);
+        let leak = &leaks[0];
+        assert_eq!(leak.start_ts, 0.0);
+        assert_eq!(leak.end_ts, 11.0 * 900.0);
+        assert!((leak.growth_bytes / GIB - 2.2).abs() < 1e-6);
+        assert_eq!(leak.monotonic_ratio, 1.0);
+    }
+
+    #[test]
+    fn a_quick_reboot_ends_the_session() {
+        let boot = |ts: f64, used_gib: f64, boot: &str| {
+            let mut sample = memory_sample(ts, used_gib);
+            sample.boot = Some(boot.to_string());
+            sample
+        };
+        // Growth in the first boot, then a reboot between two regular collections
+        let mut metrics: Vec<MetricSample> = (0..12)
+            .map(|i| boot(i as f64 * 900.0, 4.0 + i as f64 * 0.2, "a"))
+            .collect();
+        metrics.extend((12..24).map(|i| boot(i as f64 * 900.0, 1.0 + i as f64 * 0.01, "b")));
+
+        let leaks = detect_memory_leaks(&metrics);
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].end_ts, 11.0 * 900.0);
+    }
+
+    #[test]
+    fn noisy_or_small_growth_is_ignored() {
+        let sawtooth: Vec<MetricSample> = (0..12)
+            .map(|i| memory_sample(i as f64 * 900.0, if i % 2 == 0 { 4.0 } else { 6.0 }))
+            .collect();
+        assert!(detect_memory_leaks(&sawtooth).is_empty());
+
+        let tiny: Vec<MetricSample> = (0..12)
+            .map(|i| memory_sample(i as f64 * 900.0, 4.0 + i as f64 * 0.001))
+            .collect();
+        assert!(detect_memory_leaks(&tiny).is_empty());
+    }
+}
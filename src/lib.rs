@@ -1,13 +1,45 @@
 mod aggregate;
+mod alert;
+mod battery_analysis;
+mod check;
 mod cli_helpers;
 mod collector;
+mod config;
+mod containers;
+mod correlate;
+mod data_cap;
 mod db;
+mod derived;
+mod doctor;
+mod export;
+mod fleet;
+mod forecast;
 mod graph;
+mod histogram;
+mod i915;
+mod inline_image;
+mod leak;
+mod live;
 mod metrics;
+mod overview;
+#[cfg(feature = "postgres")]
+mod pg;
+mod power_profile;
+mod push;
+mod schema;
+mod setup;
+mod status;
+mod storage;
 mod sysfs;
+mod system_info;
+mod systemd;
 mod timeframe;
+mod transform;
 
 pub mod cli;
+pub mod report;
 
-pub use collector::{collect_loop, collect_once, default_db_path, resolve_db_path};
+pub use collector::{collect_live, collect_loop, collect_once, default_db_path, resolve_db_path};
+pub use histogram::Histogram;
+pub use metrics::{MetricKind, MetricSample};
 pub use timeframe::{build_timeframe, since_timestamp, Timeframe, TimeframeError};
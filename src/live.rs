@@ -0,0 +1,85 @@
+//! `symmetri live`: collect in-process every few seconds and redraw the key readings in
+//! place, for watching the machine without a collector or database.
+
+use crate::config::Panel;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Panels the live view has, with the kind each shows, in the default display order.
+const LIVE_PANELS: [(Panel, MetricKind); 5] = [
+    (Panel::Cpu, MetricKind::CpuUsage),
+    (Panel::Memory, MetricKind::MemoryUsage),
+    (Panel::Power, MetricKind::PowerDraw),
+    (Panel::Battery, MetricKind::BatteryPercentage),
+    (Panel::Temperature, MetricKind::Temperature),
+];
+
+/// Seconds between refreshes when `--interval` is not given.
+pub const DEFAULT_LIVE_SECONDS: u64 = 2;
+
+/// The samples of one collection the live view shows, grouped by kind in the order of
+/// `panels` (of [`LIVE_PANELS`] when empty) and by source within a kind.
+pub fn live_rows(samples: Vec<MetricSample>, panels: &[Panel]) -> Vec<MetricSample> {
+    let kinds: Vec<&MetricKind> = if panels.is_empty() {
+        LIVE_PANELS.iter().map(|(_, kind)| kind).collect()
+    } else {
+        panels
+            .iter()
+            .filter_map(|panel| LIVE_PANELS.iter().find(|(live, _)| live == panel))
+            .map(|(_, kind)| kind)
+            .collect()
+    };
+    let mut rows: Vec<(usize, MetricSample)> = samples
+        .into_iter()
+        .filter_map(|sample| {
+            let order = kinds.iter().position(|kind| **kind == sample.kind)?;
+            Some((order, sample))
+        })
+        .collect();
+    rows.sort_by(|(a_order, a), (b_order, b)| {
+        a_order.cmp(b_order).then_with(|| a.source.cmp(&b.source))
+    });
+    rows.into_iter().map(|(_, sample)| sample).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rows_follow_the_kind_order() {
+        let sample = |kind: MetricKind, source: &str| {
+            MetricSample::new(0.0, kind, source, Some(1.0), None, json!({}))
+        };
+        let samples = || {
+            vec![
+                sample(MetricKind::Temperature, "coretemp"),
+                sample(MetricKind::NetworkBytes, "wlan0"),
+                sample(MetricKind::CpuUsage, "cpu1"),
+                sample(MetricKind::CpuUsage, "cpu"),
+                sample(MetricKind::PowerDraw, "BAT0"),
+            ]
+        };
+        let order = |panels: &[Panel]| -> Vec<(String, String)> {
+            live_rows(samples(), panels)
+                .into_iter()
+                .map(|s| (s.kind.as_str().to_string(), s.source))
+                .collect()
+        };
+        let pair = |kind: &str, source: &str| (kind.to_string(), source.to_string());
+        assert_eq!(
+            order(&[]),
+            [
+                pair("cpu_usage", "cpu"),
+                pair("cpu_usage", "cpu1"),
+                pair("power_draw", "BAT0"),
+                pair("temperature", "coretemp"),
+            ]
+        );
+        // Configured panels pick and order the rows; network has no live panel
+        assert_eq!(
+            order(&[Panel::Temperature, Panel::Network, Panel::Power]),
+            [pair("temperature", "coretemp"), pair("power_draw", "BAT0")]
+        );
+    }
+}
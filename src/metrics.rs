@@ -2,15 +2,22 @@ use std::collections::BTreeMap;
 use std::ffi::CString;
 use std::fs;
 use std::os::unix::ffi::OsStrExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use log::warn;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
 
+use crate::config::InterfaceFilter;
+use crate::histogram::Histogram;
+
 #[derive(
     Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Display, EnumString, EnumIter,
 )]
@@ -31,6 +38,35 @@ pub enum MetricKind {
     BatteryEnergyNow,
     BatteryEnergyFull,
     BatteryEnergyFullDesign,
+    PeripheralBattery,
+    NetworkWakeups,
+    CpuThrottle,
+    ContainerCpu,
+    ContainerMemory,
+    ContextSwitches,
+    Interrupts,
+    TcpConnections,
+    SystemHealth,
+    UserIdle,
+    LidClosed,
+    CpuIdleResidency,
+    PowerProfile,
+    Derived,
+    Latency,
+    AcOnline,
+    LinkUp,
+    ScreenOn,
+    KeyboardBacklight,
+    CpuGovernor,
+    BatteryStatus,
+    WakeupEvents,
+    CpuIowait,
+    CpuSteal,
+    CollectorStats,
+    CollectorTimeout,
+    ChargerPower,
+    Voltage,
+    Current,
 }
 
 impl MetricKind {
@@ -51,15 +87,170 @@ impl MetricKind {
             MetricKind::BatteryEnergyNow => "battery_energy_now",
             MetricKind::BatteryEnergyFull => "battery_energy_full",
             MetricKind::BatteryEnergyFullDesign => "battery_energy_full_design",
+            MetricKind::PeripheralBattery => "peripheral_battery",
+            MetricKind::NetworkWakeups => "network_wakeups",
+            MetricKind::CpuThrottle => "cpu_throttle",
+            MetricKind::ContainerCpu => "container_cpu",
+            MetricKind::ContainerMemory => "container_memory",
+            MetricKind::ContextSwitches => "context_switches",
+            MetricKind::Interrupts => "interrupts",
+            MetricKind::TcpConnections => "tcp_connections",
+            MetricKind::SystemHealth => "system_health",
+            MetricKind::UserIdle => "user_idle",
+            MetricKind::LidClosed => "lid_closed",
+            MetricKind::CpuIdleResidency => "cpu_idle_residency",
+            MetricKind::PowerProfile => "power_profile",
+            MetricKind::Derived => "derived",
+            MetricKind::Latency => "latency",
+            MetricKind::AcOnline => "ac_online",
+            MetricKind::LinkUp => "link_up",
+            MetricKind::ScreenOn => "screen_on",
+            MetricKind::KeyboardBacklight => "keyboard_backlight",
+            MetricKind::CpuGovernor => "cpu_governor",
+            MetricKind::BatteryStatus => "battery_status",
+            MetricKind::WakeupEvents => "wakeup_events",
+            MetricKind::CpuIowait => "cpu_iowait",
+            MetricKind::CpuSteal => "cpu_steal",
+            MetricKind::CollectorStats => "collector_stats",
+            MetricKind::CollectorTimeout => "collector_timeout",
+            MetricKind::ChargerPower => "charger_power",
+            MetricKind::Voltage => "voltage",
+            MetricKind::Current => "current",
         }
     }
 
-    #[allow(dead_code)]
+    /// One-line human description, used by `symmetri catalog`.
+    pub fn description(&self) -> &'static str {
+        match self {
+            MetricKind::CpuUsage => "CPU utilization",
+            MetricKind::CpuFrequency => "Current CPU core frequency",
+            MetricKind::GpuUsage => "GPU busy percentage",
+            MetricKind::GpuFrequency => "Current GPU frequency",
+            MetricKind::NetworkBytes => "Cumulative bytes received and sent per interface",
+            MetricKind::MemoryUsage => "Used memory",
+            MetricKind::DiskUsage => "Used disk space per mountpoint",
+            MetricKind::Temperature => "Thermal zone and hwmon temperatures",
+            MetricKind::PowerDraw => "Power draw reported by hwmon sensors",
+            MetricKind::BatteryPercentage => "Battery state of charge",
+            MetricKind::BatteryCapacity => "Battery capacity",
+            MetricKind::BatteryHealth => "Full charge capacity relative to design capacity",
+            MetricKind::BatteryEnergyNow => "Energy currently stored in the battery",
+            MetricKind::BatteryEnergyFull => "Energy stored when fully charged",
+            MetricKind::BatteryEnergyFullDesign => "Design full-charge energy",
+            MetricKind::PeripheralBattery => "Charge of Bluetooth/HID device batteries",
+            MetricKind::NetworkWakeups => "Cumulative wakeups triggered by network devices",
+            MetricKind::CpuThrottle => "Cumulative thermal throttle events per core and package",
+            MetricKind::ContainerCpu => "Cumulative CPU time per container",
+            MetricKind::ContainerMemory => "Memory used per container",
+            MetricKind::ContextSwitches => "Cumulative context switches",
+            MetricKind::Interrupts => "Cumulative interrupts serviced",
+            MetricKind::TcpConnections => "TCP sockets per connection state",
+            MetricKind::SystemHealth => "Failed systemd units and overall system state",
+            MetricKind::UserIdle => "Whether logind reports all sessions idle",
+            MetricKind::LidClosed => "Whether the laptop lid is closed",
+            MetricKind::CpuIdleResidency => "Cumulative time spent in each CPU idle state",
+            MetricKind::PowerProfile => {
+                "Active platform power profile (power-saver, balanced, performance)"
+            }
+            MetricKind::Derived => {
+                "Metrics computed from other kinds; the source is the metric name"
+            }
+            MetricKind::Latency => "Probe latency in milliseconds, with its bucketed distribution",
+            MetricKind::AcOnline => "Whether the AC adapter is plugged in",
+            MetricKind::LinkUp => "Whether a network interface reports its link as up",
+            MetricKind::ScreenOn => "Whether a display output is powered on (DPMS)",
+            MetricKind::KeyboardBacklight => {
+                "Keyboard backlight brightness as a share of its maximum"
+            }
+            MetricKind::CpuGovernor => "Scaling governor used by most CPU cores (label)",
+            MetricKind::BatteryStatus => "Battery charging status reported by the kernel (label)",
+            MetricKind::WakeupEvents => "Cumulative events signalled per kernel wakeup source",
+            MetricKind::CpuIowait => "Share of CPU time idle while waiting for I/O",
+            MetricKind::CpuSteal => "Share of CPU time taken by the hypervisor for other guests",
+            MetricKind::CollectorStats => {
+                "Time each collector group took per run; `total` also counts samples written"
+            }
+            MetricKind::CollectorTimeout => {
+                "Seconds waited on a collector group that hung and was skipped"
+            }
+            MetricKind::ChargerPower => {
+                "Wattage negotiated by each online charger (USB-PD voltage x current limit)"
+            }
+            MetricKind::Voltage => "Voltage rails reported by hwmon sensors (`in*_input`)",
+            MetricKind::Current => "Current reported by hwmon sensors (`curr*_input`)",
+        }
+    }
+
+    /// Kinds stored as ever-increasing counters; charts and overlays show their rate.
+    pub fn is_counter(&self) -> bool {
+        matches!(
+            self,
+            MetricKind::NetworkBytes
+                | MetricKind::NetworkWakeups
+                | MetricKind::CpuThrottle
+                | MetricKind::ContainerCpu
+                | MetricKind::ContextSwitches
+                | MetricKind::Interrupts
+                | MetricKind::CpuIdleResidency
+                | MetricKind::WakeupEvents
+        )
+    }
+
+    /// Level, capacity and energy of a system battery, reported per battery in sysfs and
+    /// merged into one combined battery by default.
+    pub fn is_battery(&self) -> bool {
+        matches!(
+            self,
+            MetricKind::BatteryPercentage
+                | MetricKind::BatteryCapacity
+                | MetricKind::BatteryHealth
+                | MetricKind::BatteryEnergyNow
+                | MetricKind::BatteryEnergyFull
+                | MetricKind::BatteryEnergyFullDesign
+        )
+    }
+
+    /// Kinds stored as 0/1 states; reports show the share of time spent on instead of
+    /// min/avg/max.
+    pub fn is_state(&self) -> bool {
+        matches!(
+            self,
+            MetricKind::UserIdle
+                | MetricKind::LidClosed
+                | MetricKind::AcOnline
+                | MetricKind::LinkUp
+                | MetricKind::ScreenOn
+        )
+    }
+
+    /// Kinds whose reading is a category in `label`; reports show the time spent in each.
+    pub fn is_categorical(&self) -> bool {
+        matches!(
+            self,
+            MetricKind::CpuGovernor | MetricKind::BatteryStatus | MetricKind::PowerProfile
+        )
+    }
+
+    /// Gauges whose reports only read the value and source, so long reports can use the
+    /// hourly or daily averages in `metric_rollups` instead of every raw row.
+    pub fn reads_from_rollups(&self) -> bool {
+        matches!(
+            self,
+            MetricKind::CpuUsage
+                | MetricKind::GpuFrequency
+                | MetricKind::Temperature
+                | MetricKind::PowerDraw
+                | MetricKind::CpuIowait
+                | MetricKind::CpuSteal
+                | MetricKind::Voltage
+                | MetricKind::Current
+        )
+    }
+
     pub fn from_label(raw: &str) -> Option<Self> {
         raw.parse().ok()
     }
 
-    #[allow(dead_code)]
     pub fn all_kinds() -> impl Iterator<Item = MetricKind> {
         <MetricKind as IntoEnumIterator>::iter()
     }
@@ -72,8 +263,33 @@ pub struct MetricSample {
     pub source: String,
     pub value: Option<f64>,
     pub unit: Option<String>,
+    /// Readings beyond the value that no other field covers (`device`, `free_bytes`, ...)
     #[serde(default, skip_serializing_if = "Value::is_null")]
     pub details: Value,
+    /// Counter of bytes received, for network interfaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rx_bytes: Option<f64>,
+    /// Counter of bytes sent, for network interfaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_bytes: Option<f64>,
+    /// Capacity a memory or disk usage reading is out of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_bytes: Option<f64>,
+    /// Charge state of a battery or peripheral (`Charging`, `Discharging`, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Bucketed distribution behind the value, for kinds such as probe latencies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<Histogram>,
+    /// Category for kinds whose reading is a name (governor, power profile, battery status)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Machine that collected the sample; `None` for rows stored before hosts were tagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Kernel `boot_id` of the run that collected the sample; `None` before boots were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot: Option<String>,
 }
 
 impl MetricSample {
@@ -92,8 +308,57 @@ impl MetricSample {
             value,
             unit: unit.map(|u| u.to_string()),
             details,
+            rx_bytes: None,
+            tx_bytes: None,
+            total_bytes: None,
+            status: None,
+            histogram: None,
+            label: None,
+            host: None,
+            boot: None,
         }
     }
+
+    /// Record the charge state, e.g. `Discharging`.
+    pub fn with_status<S: Into<String>>(mut self, status: S) -> Self {
+        self.status = Some(status.into());
+        self
+    }
+
+    /// Number recorded under `key`, in a byte field or in `details` (e.g. `free_bytes`).
+    pub fn detail_number(&self, key: &str) -> Option<f64> {
+        match key {
+            "rx_bytes" => self.rx_bytes,
+            "tx_bytes" => self.tx_bytes,
+            "total_bytes" => self.total_bytes,
+            _ => self.details.get(key).and_then(|v| v.as_f64()),
+        }
+    }
+
+    /// Record the capacity a usage reading is out of.
+    pub fn with_total_bytes(mut self, total: f64) -> Self {
+        self.total_bytes = Some(total);
+        self
+    }
+
+    /// Record cumulative received and transmitted bytes for a network sample.
+    pub fn with_network_bytes(mut self, rx: Option<f64>, tx: Option<f64>) -> Self {
+        self.rx_bytes = rx;
+        self.tx_bytes = tx;
+        self
+    }
+
+    pub fn with_label<S: Into<String>>(mut self, label: S) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Attach a distribution; the value becomes its median when none was given.
+    pub fn with_histogram(mut self, histogram: Histogram) -> Self {
+        self.value = self.value.or_else(|| histogram.quantile(0.5));
+        self.histogram = Some(histogram);
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -109,6 +374,19 @@ struct CpuTimes {
     steal: u64,
 }
 
+impl CpuTimes {
+    fn total(&self) -> u64 {
+        self.user
+            + self.nice
+            + self.system
+            + self.idle
+            + self.iowait
+            + self.irq
+            + self.softirq
+            + self.steal
+    }
+}
+
 fn read_cpu_times() -> Option<Vec<CpuTimes>> {
     let content = fs::read_to_string("/proc/stat").ok()?;
     let mut times = Vec::new();
@@ -154,6 +432,13 @@ fn cpu_usage_samples(ts: f64) -> Vec<MetricSample> {
         Some(v) => v,
         None => return Vec::new(),
     };
+    cpu_time_samples(first, second, ts)
+}
+
+/// Usage per CPU line between two `/proc/stat` reads, plus the iowait and steal shares of
+/// the all-CPU line. Usage counts iowait as idle, so I/O-bound or oversubscribed VMs look
+/// idle without the last two.
+fn cpu_time_samples(first: Vec<CpuTimes>, second: Vec<CpuTimes>, ts: f64) -> Vec<MetricSample> {
     let mut second_map: BTreeMap<String, CpuTimes> = BTreeMap::new();
     for entry in second {
         second_map.insert(entry.label.clone(), entry);
@@ -162,49 +447,104 @@ fn cpu_usage_samples(ts: f64) -> Vec<MetricSample> {
     let mut samples = Vec::new();
     for prev in first {
         if let Some(next) = second_map.get(&prev.label) {
-            let prev_total = prev.user
-                + prev.nice
-                + prev.system
-                + prev.idle
-                + prev.iowait
-                + prev.irq
-                + prev.softirq
-                + prev.steal;
-            let next_total = next.user
-                + next.nice
-                + next.system
-                + next.idle
-                + next.iowait
-                + next.irq
-                + next.softirq
-                + next.steal;
             let prev_idle = prev.idle + prev.iowait;
             let next_idle = next.idle + next.iowait;
-            let delta_total = next_total.saturating_sub(prev_total);
+            let delta_total = next.total().saturating_sub(prev.total());
             let delta_idle = next_idle.saturating_sub(prev_idle);
             if delta_total == 0 {
                 continue;
             }
+            let share = |delta: u64| delta as f64 / delta_total as f64 * 100.0;
             let busy = delta_total.saturating_sub(delta_idle);
-            let usage = (busy as f64 / delta_total as f64) * 100.0;
             samples.push(MetricSample::new(
                 ts,
                 MetricKind::CpuUsage,
                 prev.label.clone(),
-                Some(usage),
+                Some(share(busy)),
                 Some("%"),
                 Value::Null,
             ));
+            if prev.label == "cpu" {
+                for (kind, delta) in [
+                    (
+                        MetricKind::CpuIowait,
+                        next.iowait.saturating_sub(prev.iowait),
+                    ),
+                    (MetricKind::CpuSteal, next.steal.saturating_sub(prev.steal)),
+                ] {
+                    samples.push(MetricSample::new(
+                        ts,
+                        kind,
+                        "cpu",
+                        Some(share(delta)),
+                        Some("%"),
+                        Value::Null,
+                    ));
+                }
+            }
+        }
+    }
+    samples
+}
+
+/// Cumulative context switches (`ctxt`) and interrupts (`intr`, first field is the total).
+fn parse_proc_stat_counters(content: &str, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let kind = match parts.next() {
+            Some("ctxt") => MetricKind::ContextSwitches,
+            Some("intr") => MetricKind::Interrupts,
+            _ => continue,
+        };
+        if let Some(total) = parts.next().and_then(|v| v.parse::<f64>().ok()) {
+            samples.push(MetricSample::new(
+                ts,
+                kind,
+                "system",
+                Some(total),
+                Some("count"),
+                Value::Null,
+            ));
         }
     }
     samples
 }
 
-fn read_numeric(path: &Path) -> Option<f64> {
+fn scheduler_samples(ts: f64) -> Vec<MetricSample> {
+    match fs::read_to_string("/proc/stat") {
+        Ok(content) => parse_proc_stat_counters(&content, ts),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub(crate) fn read_numeric(path: &Path) -> Option<f64> {
     let raw = fs::read_to_string(path).ok()?;
     raw.trim().parse::<f64>().ok()
 }
 
+/// Scaling governor and energy performance preference (intel_pstate/amd-pstate) of a core.
+fn cpufreq_policy_details(cpufreq: &Path) -> Value {
+    let read = |file: &str| {
+        fs::read_to_string(cpufreq.join(file))
+            .ok()
+            .map(|raw| raw.trim().to_string())
+            .filter(|raw| !raw.is_empty())
+    };
+    let mut details = serde_json::Map::new();
+    if let Some(governor) = read("scaling_governor") {
+        details.insert("governor".to_string(), json!(governor));
+    }
+    if let Some(epp) = read("energy_performance_preference") {
+        details.insert("epp".to_string(), json!(epp));
+    }
+    if details.is_empty() {
+        Value::Null
+    } else {
+        Value::Object(details)
+    }
+}
+
 fn cpu_frequency_samples(ts: f64) -> Vec<MetricSample> {
     let root = Path::new("/sys/devices/system/cpu");
     let entries = match fs::read_dir(root) {
@@ -220,8 +560,8 @@ fn cpu_frequency_samples(ts: f64) -> Vec<MetricSample> {
         if !name.starts_with("cpu") || name.len() < 4 {
             continue;
         }
-        let freq_path = entry.path().join("cpufreq").join("scaling_cur_freq");
-        if let Some(khz) = read_numeric(&freq_path) {
+        let cpufreq = entry.path().join("cpufreq");
+        if let Some(khz) = read_numeric(&cpufreq.join("scaling_cur_freq")) {
             let mhz = khz / 1000.0;
             samples.push(MetricSample::new(
                 ts,
@@ -229,41 +569,218 @@ fn cpu_frequency_samples(ts: f64) -> Vec<MetricSample> {
                 name,
                 Some(mhz),
                 Some("MHz"),
+                cpufreq_policy_details(&cpufreq),
+            ));
+        }
+    }
+    samples.extend(cpu_governor_sample(&samples, ts));
+    samples
+}
+
+/// The governor most cores run, so governor switches can be reported as time in state.
+fn cpu_governor_sample(frequency: &[MetricSample], ts: f64) -> Option<MetricSample> {
+    let mut cores: BTreeMap<&str, usize> = BTreeMap::new();
+    for sample in frequency {
+        if let Some(governor) = sample.details.get("governor").and_then(Value::as_str) {
+            *cores.entry(governor).or_default() += 1;
+        }
+    }
+    let (governor, _) = cores.iter().max_by_key(|(_, count)| **count)?;
+    Some(
+        MetricSample::new(
+            ts,
+            MetricKind::CpuGovernor,
+            "cpu",
+            None,
+            None,
+            json!({ "cores": cores }),
+        )
+        .with_label(*governor),
+    )
+}
+
+fn cpu_throttle_samples(ts: f64) -> Vec<MetricSample> {
+    read_cpu_throttle(Path::new("/sys/devices/system/cpu"), ts)
+}
+
+/// Cumulative thermal throttle counts per core (`cpuN`) and per package (`packageN`).
+fn read_cpu_throttle(root: &Path, ts: f64) -> Vec<MetricSample> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut samples = Vec::new();
+    let mut packages: BTreeMap<u64, f64> = BTreeMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_cpu = name
+            .strip_prefix("cpu")
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if !is_cpu {
+            continue;
+        }
+        let throttle = entry.path().join("thermal_throttle");
+        if let Some(count) = read_numeric(&throttle.join("core_throttle_count")) {
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::CpuThrottle,
+                name,
+                Some(count),
+                Some("count"),
                 Value::Null,
             ));
         }
+        if let Some(count) = read_numeric(&throttle.join("package_throttle_count")) {
+            let package = read_numeric(&entry.path().join("topology").join("physical_package_id"))
+                .unwrap_or(0.0) as u64;
+            packages.insert(package, count);
+        }
+    }
+    for (package, count) in packages {
+        samples.push(MetricSample::new(
+            ts,
+            MetricKind::CpuThrottle,
+            format!("package{package}"),
+            Some(count),
+            Some("count"),
+            Value::Null,
+        ));
     }
+    samples.sort_by(|a, b| a.source.cmp(&b.source));
     samples
 }
 
-fn parse_meminfo() -> Option<(f64, f64)> {
-    let content = fs::read_to_string("/proc/meminfo").ok()?;
-    let mut total_kb = None;
-    let mut available_kb = None;
+fn cpu_idle_samples(ts: f64) -> Vec<MetricSample> {
+    read_cpu_idle(Path::new("/sys/devices/system/cpu"), ts)
+}
+
+#[derive(Default)]
+struct IdleStateTotals {
+    index: u64,
+    time_usec: f64,
+    usage: f64,
+    cpus: u64,
+}
+
+/// Cumulative time spent in each cpuidle state (`POLL`, `C1E`, `C10`, ...), summed over
+/// CPUs so residency can be expressed as a share of total CPU time.
+fn read_cpu_idle(root: &Path, ts: f64) -> Vec<MetricSample> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut states: BTreeMap<String, IdleStateTotals> = BTreeMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_cpu = name
+            .strip_prefix("cpu")
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if !is_cpu {
+            continue;
+        }
+        let Ok(idle_states) = fs::read_dir(entry.path().join("cpuidle")) else {
+            continue;
+        };
+        for state in idle_states.flatten() {
+            let Some(index) = state
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix("state")
+                .and_then(|n| n.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            let Ok(state_name) = fs::read_to_string(state.path().join("name")) else {
+                continue;
+            };
+            let Some(time_usec) = read_numeric(&state.path().join("time")) else {
+                continue;
+            };
+            let totals = states.entry(state_name.trim().to_string()).or_default();
+            totals.index = index;
+            totals.time_usec += time_usec;
+            totals.usage += read_numeric(&state.path().join("usage")).unwrap_or(0.0);
+            totals.cpus += 1;
+        }
+    }
+    let mut states: Vec<(String, IdleStateTotals)> = states.into_iter().collect();
+    states.sort_by_key(|(_, totals)| totals.index);
+    states
+        .into_iter()
+        .map(|(name, totals)| {
+            MetricSample::new(
+                ts,
+                MetricKind::CpuIdleResidency,
+                name,
+                Some(totals.time_usec),
+                Some("usec"),
+                json!({ "index": totals.index, "cpus": totals.cpus, "usage": totals.usage }),
+            )
+        })
+        .collect()
+}
+
+/// Fields of `/proc/meminfo`, in bytes.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct MemInfo {
+    total: f64,
+    available: f64,
+    free: f64,
+    buffers: f64,
+    /// Page cache, including shmem/tmpfs
+    cached: f64,
+    dirty: f64,
+    slab: f64,
+    shmem: f64,
+}
+
+fn parse_meminfo(content: &str) -> Option<MemInfo> {
+    let mut info = MemInfo::default();
+    let (mut has_total, mut has_available) = (false, false);
     for line in content.lines() {
         let mut parts = line.split_whitespace();
-        match parts.next()? {
-            "MemTotal:" => total_kb = parts.next()?.parse::<f64>().ok(),
-            "MemAvailable:" => available_kb = parts.next()?.parse::<f64>().ok(),
+        let (Some(key), Some(Ok(kb))) = (parts.next(), parts.next().map(str::parse::<f64>)) else {
+            continue;
+        };
+        let field = match key {
+            "MemTotal:" => {
+                has_total = true;
+                &mut info.total
+            }
+            "MemAvailable:" => {
+                has_available = true;
+                &mut info.available
+            }
+            "MemFree:" => &mut info.free,
+            "Buffers:" => &mut info.buffers,
+            "Cached:" => &mut info.cached,
+            "Dirty:" => &mut info.dirty,
+            "Slab:" => &mut info.slab,
+            "Shmem:" => &mut info.shmem,
             _ => continue,
-        }
-    }
-    match (total_kb, available_kb) {
-        (Some(total), Some(avail)) => Some((total * 1024.0, avail * 1024.0)),
-        _ => None,
+        };
+        *field = kb * 1024.0;
     }
+    (has_total && has_available).then_some(info)
 }
 
 fn memory_samples(ts: f64) -> Vec<MetricSample> {
-    let (total, available) = match parse_meminfo() {
-        Some(v) => v,
-        None => return Vec::new(),
+    let Some(info) = fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|content| parse_meminfo(&content))
+    else {
+        return Vec::new();
     };
-    let used = (total - available).max(0.0);
+    let used = (info.total - info.available).max(0.0);
     let details = json!({
-        "total_bytes": total,
-        "available_bytes": available,
-        "used_bytes": used
+        "available_bytes": info.available,
+        "used_bytes": used,
+        "free_bytes": info.free,
+        "buffers_bytes": info.buffers,
+        "cached_bytes": info.cached,
+        "dirty_bytes": info.dirty,
+        "slab_bytes": info.slab,
+        "shmem_bytes": info.shmem
     });
     vec![MetricSample::new(
         ts,
@@ -272,7 +789,8 @@ fn memory_samples(ts: f64) -> Vec<MetricSample> {
         Some(used),
         Some("bytes"),
         details,
-    )]
+    )
+    .with_total_bytes(info.total)]
 }
 
 fn network_samples(ts: f64) -> Vec<MetricSample> {
@@ -304,19 +822,290 @@ fn network_samples(ts: f64) -> Vec<MetricSample> {
             (Some(rx), Some(tx)) => Some(rx + tx),
             _ => None,
         };
-        let details = json!({
-            "rx_bytes": rx_bytes,
-            "tx_bytes": tx_bytes
+        samples.push(
+            MetricSample::new(
+                ts,
+                MetricKind::NetworkBytes,
+                iface,
+                total,
+                Some("bytes"),
+                Value::Null,
+            )
+            .with_network_bytes(rx_bytes, tx_bytes),
+        );
+    }
+    samples
+}
+
+/// Name prefixes of container veth pairs and bridges.
+const VIRTUAL_INTERFACE_PREFIXES: [&str; 3] = ["veth", "docker", "br-"];
+
+/// Loopback, container veths and bridges, tun/tap devices and anything else the kernel
+/// registers under `/sys/devices/virtual` (ifb, dummy, ...), whose traffic is already
+/// counted on a physical interface (or never leaves the machine).
+fn is_virtual_interface(net_root: &Path, iface: &str) -> bool {
+    iface == "lo"
+        || VIRTUAL_INTERFACE_PREFIXES
+            .iter()
+            .any(|prefix| iface.starts_with(prefix))
+        || net_root.join(iface).join("tun_flags").exists()
+        || fs::canonicalize(net_root.join(iface))
+            .is_ok_and(|path| path.components().any(|part| part.as_os_str() == "virtual"))
+}
+
+fn state_sample(ts: f64, kind: MetricKind, source: String, on: bool) -> MetricSample {
+    MetricSample::new(
+        ts,
+        kind,
+        source,
+        Some(f64::from(u8::from(on))),
+        Some("bool"),
+        Value::Null,
+    )
+}
+
+/// Link state per interface from `operstate`; interfaces in `unknown`/`dormant` states
+/// (loopback, some tunnels) have no meaningful link and are skipped, as are the interfaces
+/// `interfaces` leaves out.
+fn link_state_samples(net_root: &Path, ts: f64, interfaces: &InterfaceFilter) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for entry in fs::read_dir(net_root).into_iter().flatten().flatten() {
+        let iface = entry.file_name().to_string_lossy().to_string();
+        if !interfaces.includes(&iface, is_virtual_interface(net_root, &iface)) {
+            continue;
+        }
+        let up = match fs::read_to_string(entry.path().join("operstate")).as_deref() {
+            Ok(state) if state.trim() == "up" => true,
+            Ok(state) if matches!(state.trim(), "down" | "lowerlayerdown") => false,
+            _ => continue,
+        };
+        samples.push(state_sample(ts, MetricKind::LinkUp, iface, up));
+    }
+    samples
+}
+
+/// DPMS state of every enabled display connector (`card0-eDP-1`, ...).
+fn screen_state_samples(drm_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for entry in fs::read_dir(drm_root).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((_, connector)) = name.split_once('-') else {
+            continue;
+        };
+        let read = |file: &str| fs::read_to_string(entry.path().join(file)).ok();
+        if read("enabled").as_deref().map(str::trim) != Some("enabled") {
+            continue;
+        }
+        let Some(dpms) = read("dpms") else {
+            continue;
+        };
+        samples.push(state_sample(
+            ts,
+            MetricKind::ScreenOn,
+            connector.to_string(),
+            dpms.trim() == "On",
+        ));
+    }
+    samples
+}
+
+/// Keyboard backlight LEDs (`tpacpi::kbd_backlight`, `asus::kbd_backlight`, ...).
+fn keyboard_backlight_samples(leds_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for entry in fs::read_dir(leds_root).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.ends_with("kbd_backlight") {
+            continue;
+        }
+        let read = |file: &str| read_numeric(&entry.path().join(file));
+        let (Some(brightness), Some(max)) = (read("brightness"), read("max_brightness")) else {
+            continue;
+        };
+        let percent = (max > 0.0).then(|| brightness / max * 100.0);
+        samples.push(MetricSample::new(
+            ts,
+            MetricKind::KeyboardBacklight,
+            name,
+            percent,
+            Some("%"),
+            json!({ "brightness": brightness as u64, "max_brightness": max as u64 }),
+        ));
+    }
+    samples
+}
+
+/// Counters of one kernel wakeup source.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct WakeupCounts {
+    events: f64,
+    wakeups: f64,
+    active_ms: f64,
+}
+
+impl WakeupCounts {
+    fn add(&mut self, other: WakeupCounts) {
+        self.events += other.events;
+        self.wakeups += other.wakeups;
+        self.active_ms += other.active_ms;
+    }
+}
+
+/// Wakeup sources from `/sys/class/wakeup` (kernel 5.4+). Several devices can register
+/// sources with the same name; their counters are added up.
+fn read_wakeup_class(root: &Path) -> BTreeMap<String, WakeupCounts> {
+    let mut sources: BTreeMap<String, WakeupCounts> = BTreeMap::new();
+    for entry in fs::read_dir(root).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let Ok(name) = fs::read_to_string(path.join("name")) else {
+            continue;
+        };
+        let read = |file: &str| read_numeric(&path.join(file)).unwrap_or(0.0);
+        sources
+            .entry(name.trim().to_string())
+            .or_default()
+            .add(WakeupCounts {
+                events: read("event_count"),
+                wakeups: read("wakeup_count"),
+                active_ms: read("total_time_ms"),
+            });
+    }
+    sources
+}
+
+/// The same counters from the debugfs table (`name active_count event_count wakeup_count
+/// expire_count active_since total_time ...`), for older kernels; needs root.
+fn parse_debugfs_wakeup_sources(content: &str) -> BTreeMap<String, WakeupCounts> {
+    let mut sources: BTreeMap<String, WakeupCounts> = BTreeMap::new();
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // Names may contain spaces, so the numeric columns are counted from the end
+        if fields.len() < 10 {
+            continue;
+        }
+        let numbers = &fields[fields.len() - 9..];
+        let name = fields[..fields.len() - 9].join(" ");
+        let number = |idx: usize| numbers[idx].parse::<f64>().unwrap_or(0.0);
+        sources.entry(name).or_default().add(WakeupCounts {
+            events: number(1),
+            wakeups: number(2),
+            active_ms: number(5),
         });
+    }
+    sources
+}
+
+/// Event counters of every wakeup source that has fired since boot; reports rank them by
+/// their increase, powertop style.
+fn wakeup_source_samples(class_root: &Path, debugfs: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut sources = read_wakeup_class(class_root);
+    if sources.is_empty() {
+        if let Ok(content) = fs::read_to_string(debugfs) {
+            sources = parse_debugfs_wakeup_sources(&content);
+        }
+    }
+    sources
+        .into_iter()
+        .filter(|(_, counts)| counts.events > 0.0)
+        .map(|(name, counts)| {
+            MetricSample::new(
+                ts,
+                MetricKind::WakeupEvents,
+                name,
+                Some(counts.events),
+                Some("count"),
+                json!({ "wakeup_count": counts.wakeups, "active_ms": counts.active_ms }),
+            )
+        })
+        .collect()
+}
+
+fn tcp_state_name(code: &str) -> &'static str {
+    match code {
+        "01" => "established",
+        "02" => "syn_sent",
+        "03" => "syn_recv",
+        "04" => "fin_wait1",
+        "05" => "fin_wait2",
+        "06" => "time_wait",
+        "07" => "close",
+        "08" => "close_wait",
+        "09" => "last_ack",
+        "0A" => "listen",
+        "0B" => "closing",
+        _ => "other",
+    }
+}
+
+/// Count sockets per state (`st` column) across `/proc/net/tcp`-style tables.
+fn count_tcp_states(tables: &[String]) -> BTreeMap<&'static str, usize> {
+    let mut counts: BTreeMap<&'static str, usize> =
+        ["established", "time_wait", "close_wait", "listen"]
+            .into_iter()
+            .map(|state| (state, 0))
+            .collect();
+    for table in tables {
+        for line in table.lines().skip(1) {
+            if let Some(code) = line.split_whitespace().nth(3) {
+                *counts.entry(tcp_state_name(code)).or_default() += 1;
+            }
+        }
+    }
+    counts
+}
+
+fn tcp_connection_samples(ts: f64) -> Vec<MetricSample> {
+    let tables: Vec<String> = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .collect();
+    if tables.is_empty() {
+        return Vec::new();
+    }
+    count_tcp_states(&tables)
+        .into_iter()
+        .map(|(state, count)| {
+            MetricSample::new(
+                ts,
+                MetricKind::TcpConnections,
+                state,
+                Some(count as f64),
+                Some("count"),
+                Value::Null,
+            )
+        })
+        .collect()
+}
+
+/// Cumulative wakeup events per interface, for drivers exposing `device/power/wakeup_count`.
+fn network_wakeup_samples(ts: f64) -> Vec<MetricSample> {
+    read_network_wakeups(Path::new("/sys/class/net"), ts)
+}
+
+fn read_network_wakeups(root: &Path, ts: f64) -> Vec<MetricSample> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut samples = Vec::new();
+    for entry in entries.flatten() {
+        let iface = entry.file_name().to_string_lossy().to_string();
+        let power = entry.path().join("device").join("power");
+        let Some(count) = read_numeric(&power.join("wakeup_count")) else {
+            continue;
+        };
+        let enabled = fs::read_to_string(power.join("wakeup"))
+            .ok()
+            .map(|s| s.trim() == "enabled");
         samples.push(MetricSample::new(
             ts,
-            MetricKind::NetworkBytes,
+            MetricKind::NetworkWakeups,
             iface,
-            total,
-            Some("bytes"),
-            details,
+            Some(count),
+            Some("count"),
+            json!({ "wakeup_enabled": enabled }),
         ));
     }
+    samples.sort_by(|a, b| a.source.cmp(&b.source));
     samples
 }
 
@@ -337,7 +1126,6 @@ fn disk_samples(ts: f64) -> Vec<MetricSample> {
     let available = block_size * stat.f_bavail;
     let used = total.saturating_sub(free);
     let details = json!({
-        "total_bytes": total as f64,
         "available_bytes": available as f64,
         "free_bytes": free as f64
     });
@@ -348,30 +1136,128 @@ fn disk_samples(ts: f64) -> Vec<MetricSample> {
         Some(used as f64),
         Some("bytes"),
         details,
-    )]
+    )
+    .with_total_bytes(total as f64)]
 }
 
 fn temperature_samples(ts: f64) -> Vec<MetricSample> {
-    let mut samples = Vec::new();
+    let mut samples = thermal_zone_samples(Path::new("/sys/class/thermal"), ts);
+    samples.extend(hwmon_temperature_samples(Path::new("/sys/class/hwmon"), ts));
+    samples
+}
 
-    // Read from /sys/class/thermal
-    let thermal_root = Path::new("/sys/class/thermal");
-    if let Ok(entries) = fs::read_dir(thermal_root) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if !entry
-                .file_name()
-                .to_string_lossy()
-                .starts_with("thermal_zone")
-            {
+/// Thermal zones named by their `type`. Types shared by several zones (firmware often
+/// exposes a handful of `acpitz` zones) get the zone appended, e.g. `acpitz:thermal_zone1`,
+/// so the zones are not merged into one series.
+fn thermal_zone_samples(thermal_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut zones: Vec<(String, String, f64)> = Vec::new();
+    for entry in fs::read_dir(thermal_root).into_iter().flatten().flatten() {
+        let zone = entry.file_name().to_string_lossy().to_string();
+        if !zone.starts_with("thermal_zone") {
+            continue;
+        }
+        let path = entry.path();
+        let zone_type = fs::read_to_string(path.join("type"))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| zone.clone());
+        let Some(temp_mc) = read_numeric(&path.join("temp")) else {
+            continue;
+        };
+        zones.push((zone, zone_type, temp_mc / 1000.0));
+    }
+    zones.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut type_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, zone_type, _) in &zones {
+        *type_counts.entry(zone_type).or_default() += 1;
+    }
+    zones
+        .iter()
+        .map(|(zone, zone_type, temp_c)| {
+            let source = if type_counts[zone_type.as_str()] > 1 {
+                format!("{zone_type}:{zone}")
+            } else {
+                zone_type.clone()
+            };
+            MetricSample::new(
+                ts,
+                MetricKind::Temperature,
+                source,
+                Some(*temp_c),
+                Some("C"),
+                json!({ "zone": zone, "device": zone_type }),
+            )
+        })
+        .collect()
+}
+
+/// Name used to prefix hwmon sensor sources. NVMe drives all report the chip name
+/// `nvme`, so they are named after their controller (`nvme0`) to keep drives apart.
+fn hwmon_chip_label(hwmon_path: &Path, name: &str) -> String {
+    if name == "nvme" {
+        if let Some(device) = fs::read_link(hwmon_path.join("device"))
+            .ok()
+            .and_then(|target| target.file_name().map(|f| f.to_string_lossy().to_string()))
+            .filter(|device| device.starts_with("nvme"))
+        {
+            return device;
+        }
+    }
+    name.to_string()
+}
+
+/// Every hwmon directory under `hwmon_root` with its chip `name` and the label its
+/// sources start with. Labels shared by several chips (two amdgpu cards) get the device
+/// appended, e.g. `amdgpu-0000:03:00.0`, so their sensors are not stored as one series.
+fn hwmon_chips(hwmon_root: &Path) -> Vec<(PathBuf, String, String)> {
+    let mut chips: Vec<(PathBuf, String, String)> = fs::read_dir(hwmon_root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| {
+            let hwmon_path = entry.path();
+            let name = fs::read_to_string(hwmon_path.join("name"))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|_| entry.file_name().to_string_lossy().to_string());
+            let chip = hwmon_chip_label(&hwmon_path, &name);
+            (hwmon_path, name, chip)
+        })
+        .collect();
+    chips.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut label_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (_, _, chip) in &chips {
+        *label_counts.entry(chip.clone()).or_default() += 1;
+    }
+    for (hwmon_path, _, chip) in &mut chips {
+        if label_counts[chip.as_str()] > 1 {
+            // The PCI or platform device, or the hwmon node when there is none
+            let device = fs::read_link(hwmon_path.join("device"))
+                .ok()
+                .and_then(|target| target.file_name().map(|f| f.to_string_lossy().to_string()))
+                .or_else(|| Some(hwmon_path.file_name()?.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            *chip = format!("{chip}-{device}");
+        }
+    }
+    chips
+}
+
+fn hwmon_temperature_samples(hwmon_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for (hwmon_path, name, chip) in hwmon_chips(hwmon_root) {
+        let sensor_entries = match fs::read_dir(&hwmon_path) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for sensor in sensor_entries.flatten() {
+            let fname = sensor.file_name().to_string_lossy().to_string();
+            if !fname.starts_with("temp") || !fname.ends_with("_input") {
                 continue;
             }
-            let label = fs::read_to_string(path.join("type"))
-                .ok()
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
-            let temp_mc = match fs::read_to_string(path.join("temp"))
+            let temp_mc = match fs::read_to_string(sensor.path())
                 .ok()
                 .and_then(|s| s.trim().parse::<f64>().ok())
             {
@@ -379,65 +1265,27 @@ fn temperature_samples(ts: f64) -> Vec<MetricSample> {
                 None => continue,
             };
             let temp_c = temp_mc / 1000.0;
+
+            // Try to get a label for this sensor
+            let label_file = fname.replace("_input", "_label");
+            let label = fs::read_to_string(hwmon_path.join(&label_file))
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| fname.trim_end_matches("_input").to_string());
+
+            let source = format!("{chip}:{label}");
             samples.push(MetricSample::new(
                 ts,
                 MetricKind::Temperature,
-                label,
+                source,
                 Some(temp_c),
                 Some("C"),
-                Value::Null,
+                json!({ "chip": name, "device": chip }),
             ));
         }
     }
-
-    // Read from /sys/class/hwmon
-    let hwmon_root = Path::new("/sys/class/hwmon");
-    if let Ok(entries) = fs::read_dir(hwmon_root) {
-        for entry in entries.flatten() {
-            let hwmon_path = entry.path();
-            let name = fs::read_to_string(hwmon_path.join("name"))
-                .ok()
-                .map(|s| s.trim().to_string())
-                .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
-            let sensor_entries = match fs::read_dir(&hwmon_path) {
-                Ok(entries) => entries,
-                Err(_) => continue,
-            };
-            for sensor in sensor_entries.flatten() {
-                let fname = sensor.file_name().to_string_lossy().to_string();
-                if !fname.starts_with("temp") || !fname.ends_with("_input") {
-                    continue;
-                }
-                let temp_mc = match fs::read_to_string(sensor.path())
-                    .ok()
-                    .and_then(|s| s.trim().parse::<f64>().ok())
-                {
-                    Some(v) => v,
-                    None => continue,
-                };
-                let temp_c = temp_mc / 1000.0;
-
-                // Try to get a label for this sensor
-                let label_file = fname.replace("_input", "_label");
-                let label = fs::read_to_string(hwmon_path.join(&label_file))
-                    .ok()
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .unwrap_or_else(|| fname.trim_end_matches("_input").to_string());
-
-                let source = format!("{name}:{label}");
-                samples.push(MetricSample::new(
-                    ts,
-                    MetricKind::Temperature,
-                    source,
-                    Some(temp_c),
-                    Some("C"),
-                    Value::Null,
-                ));
-            }
-        }
-    }
-
+    samples.sort_by(|a, b| a.source.cmp(&b.source));
     samples
 }
 
@@ -461,6 +1309,40 @@ fn parse_pp_dpm_sclk(path: &Path) -> Option<f64> {
     None
 }
 
+/// Power, power cap, fan and hottest-sensor readings of an amdgpu card from its hwmon
+/// directory, so reports can tell a GPU held back by its power limit from a thermally
+/// limited one. `Null` when the card has no hwmon directory (i915, or not amdgpu).
+fn gpu_limit_details(device: &Path) -> Value {
+    let Some(hwmon) = fs::read_dir(device.join("hwmon"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .next()
+    else {
+        return Value::Null;
+    };
+    let watts = |file: &str| read_numeric(&hwmon.join(file)).map(|uw| uw / 1_000_000.0);
+    let celsius = |file: String| read_numeric(&hwmon.join(file)).map(|mc| mc / 1000.0);
+    // Junction (temp2) is what amdgpu throttles on; older cards only have the edge sensor
+    let (temp_c, temp_limit_c) = ["temp2", "temp1"]
+        .iter()
+        .find_map(|channel| {
+            let temp = celsius(format!("{channel}_input"))?;
+            Some((Some(temp), celsius(format!("{channel}_crit"))))
+        })
+        .unwrap_or_default();
+    let fan_pwm_percent = read_numeric(&hwmon.join("pwm1")).map(|pwm| pwm / 255.0 * 100.0);
+    json!({
+        "power_w": watts("power1_average").or_else(|| watts("power1_input")),
+        "power_cap_w": watts("power1_cap"),
+        "fan_pwm_percent": fan_pwm_percent,
+        "fan_rpm": read_numeric(&hwmon.join("fan1_input")),
+        "temp_c": temp_c,
+        "temp_limit_c": temp_limit_c,
+    })
+}
+
 fn gpu_samples(ts: f64) -> Vec<MetricSample> {
     let root = Path::new("/sys/class/drm");
     let entries = match fs::read_dir(root) {
@@ -484,7 +1366,7 @@ fn gpu_samples(ts: f64) -> Vec<MetricSample> {
                 name.clone(),
                 Some(value),
                 Some("%"),
-                Value::Null,
+                gpu_limit_details(&device),
             ));
         }
 
@@ -506,17 +1388,16 @@ fn gpu_samples(ts: f64) -> Vec<MetricSample> {
 
 fn power_samples(ts: f64) -> Vec<MetricSample> {
     let root = Path::new("/sys/class/hwmon");
-    let entries = match fs::read_dir(root) {
-        Ok(entries) => entries,
-        Err(_) => return Vec::new(),
-    };
+    let mut samples = hwmon_power_samples(root, ts);
+    samples.extend(energy_power_samples(root, ts));
+    samples.extend(hwmon_rail_samples(root, ts));
+    samples
+}
+
+/// Power channels (`power*_input`, microwatts) of hwmon chips, e.g. `amdgpu:power1`.
+fn hwmon_power_samples(hwmon_root: &Path, ts: f64) -> Vec<MetricSample> {
     let mut samples = Vec::new();
-    for entry in entries.flatten() {
-        let hwmon_path = entry.path();
-        let name = fs::read_to_string(hwmon_path.join("name"))
-            .ok()
-            .map(|s| s.trim().to_string())
-            .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
+    for (hwmon_path, _, chip) in hwmon_chips(hwmon_root) {
         let sensor_entries = match fs::read_dir(&hwmon_path) {
             Ok(entries) => entries,
             Err(_) => continue,
@@ -534,7 +1415,7 @@ fn power_samples(ts: f64) -> Vec<MetricSample> {
                 None => continue,
             };
             let watts = raw_value / 1_000_000.0;
-            let source = format!("{name}:{}", fname.trim_end_matches("_input"));
+            let source = format!("{chip}:{}", fname.trim_end_matches("_input"));
             samples.push(MetricSample::new(
                 ts,
                 MetricKind::PowerDraw,
@@ -548,19 +1429,857 @@ fn power_samples(ts: f64) -> Vec<MetricSample> {
     samples
 }
 
-pub fn collect_metrics(ts: f64) -> Vec<MetricSample> {
-    let cpu_usage_handle = thread::spawn(move || cpu_usage_samples(ts));
+/// Voltage (`in*_input`, millivolts) and current (`curr*_input`, milliamps) channels of
+/// hwmon chips, as motherboard Super I/O chips expose for their supply rails. Sources are
+/// `chip:label`, e.g. `nct6798:Vcore` or `nct6798:in0` when the channel is unlabelled.
+fn hwmon_rail_samples(hwmon_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for (hwmon_path, name, chip) in hwmon_chips(hwmon_root) {
+        for sensor in fs::read_dir(&hwmon_path).into_iter().flatten().flatten() {
+            let fname = sensor.file_name().to_string_lossy().to_string();
+            let Some(channel) = fname.strip_suffix("_input") else {
+                continue;
+            };
+            let (kind, unit) = match channel.trim_end_matches(|c: char| c.is_ascii_digit()) {
+                "in" => (MetricKind::Voltage, "V"),
+                "curr" => (MetricKind::Current, "A"),
+                _ => continue,
+            };
+            let Some(milli) = read_numeric(&sensor.path()) else {
+                continue;
+            };
+            let label = fs::read_to_string(hwmon_path.join(format!("{channel}_label")))
+                .map(|s| s.trim().to_string())
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| channel.to_string());
+            samples.push(MetricSample::new(
+                ts,
+                kind,
+                format!("{chip}:{label}"),
+                Some(milli / 1000.0),
+                Some(unit),
+                json!({ "chip": name }),
+            ));
+        }
+    }
+    samples.sort_by(|a, b| a.source.cmp(&b.source));
+    samples
+}
+
+/// Cumulative energy counters in microjoules per hwmon sensor (`amd_energy:Esocket0`,
+/// `zenpower:energy1`). Per-core counters of amd_energy are skipped; the socket total
+/// covers them.
+fn hwmon_energy_counters(root: &Path) -> BTreeMap<String, f64> {
+    let mut counters = BTreeMap::new();
+    for (hwmon_path, _, chip) in hwmon_chips(root) {
+        for sensor in fs::read_dir(&hwmon_path).into_iter().flatten().flatten() {
+            let fname = sensor.file_name().to_string_lossy().to_string();
+            let Some(channel) = fname
+                .strip_suffix("_input")
+                .filter(|c| c.starts_with("energy"))
+            else {
+                continue;
+            };
+            let label = fs::read_to_string(hwmon_path.join(format!("{channel}_label")))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|_| channel.to_string());
+            if label.starts_with("Ecore") {
+                continue;
+            }
+            if let Some(microjoules) = read_numeric(&sensor.path()) {
+                counters.insert(format!("{chip}:{label}"), microjoules);
+            }
+        }
+    }
+    counters
+}
+
+fn energy_deltas_to_watts(
+    first: &BTreeMap<String, f64>,
+    second: &BTreeMap<String, f64>,
+    elapsed: Duration,
+    ts: f64,
+) -> Vec<MetricSample> {
+    let seconds = elapsed.as_secs_f64();
+    second
+        .iter()
+        .filter_map(|(source, after)| {
+            let before = first.get(source)?;
+            // A counter that went backwards wrapped around or was reset
+            let joules = (after >= before).then(|| (after - before) / 1_000_000.0)?;
+            Some(MetricSample::new(
+                ts,
+                MetricKind::PowerDraw,
+                source.clone(),
+                Some(joules / seconds),
+                Some("W"),
+                json!({ "energy_counter": true }),
+            ))
+        })
+        .collect()
+}
+
+/// Power from hwmon drivers that only expose energy counters (amd_energy, zenpower on
+/// Ryzen), averaged over a short window.
+fn energy_power_samples(root: &Path, ts: f64) -> Vec<MetricSample> {
+    let first = hwmon_energy_counters(root);
+    if first.is_empty() {
+        return Vec::new();
+    }
+    let start = Instant::now();
+    thread::sleep(Duration::from_millis(200));
+    let second = hwmon_energy_counters(root);
+    energy_deltas_to_watts(&first, &second, start.elapsed(), ts)
+}
+
+/// Groups of collectors that can be turned off with `[collect] disabled = [...]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Display, EnumIter)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Collector {
+    Battery,
+    Peripherals,
+    Cpu,
+    Gpu,
+    Memory,
+    Network,
+    Disk,
+    Temperature,
+    Power,
+    Containers,
+    Systemd,
+    /// Self-metrics: time spent per collector group and samples written
+    Stats,
+}
+
+impl Collector {
+    pub fn all() -> impl Iterator<Item = Collector> {
+        <Collector as IntoEnumIterator>::iter()
+    }
+}
+
+/// Wall time one collector group took during a run and the samples it produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectorTiming {
+    pub collector: Collector,
+    pub elapsed: Duration,
+    pub samples: usize,
+    /// The group missed its deadline and was skipped for this run
+    pub timed_out: bool,
+}
+
+/// How long one collector group may take before the run goes on without it.
+pub const COLLECTOR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Groups that missed their deadline, with a flag their thread sets once it returns.
+static OVERDUE: Mutex<Vec<(Collector, Arc<AtomicBool>)>> = Mutex::new(Vec::new());
+
+/// Run `collect` for one group on its own thread, recording its timing.
+pub fn timed(
+    timings: &mut Vec<CollectorTiming>,
+    collector: Collector,
+    collect: impl FnOnce() -> Vec<MetricSample> + Send + 'static,
+) -> Vec<MetricSample> {
+    timed_with_deadline(timings, collector, COLLECTOR_TIMEOUT, collect)
+}
+
+/// A group still running after `deadline` (a sysfs read stuck on a flaky NVMe or a dead
+/// hwmon chip) is skipped for this run and left to finish in the background. Until it
+/// returns, later runs skip the group without starting another thread.
+fn timed_with_deadline(
+    timings: &mut Vec<CollectorTiming>,
+    collector: Collector,
+    deadline: Duration,
+    collect: impl FnOnce() -> Vec<MetricSample> + Send + 'static,
+) -> Vec<MetricSample> {
+    let start = Instant::now();
+    let mut overdue = OVERDUE.lock().unwrap_or_else(|err| err.into_inner());
+    overdue.retain(|(_, done)| !done.load(Ordering::Acquire));
+    let result = if overdue.iter().any(|(c, _)| *c == collector) {
+        warn!("Skipping {collector} collector: its previous run has not returned yet");
+        None
+    } else {
+        drop(overdue);
+        let done = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let finished = Arc::clone(&done);
+        thread::spawn(move || {
+            let samples = collect();
+            finished.store(true, Ordering::Release);
+            let _ = tx.send(samples);
+        });
+        match rx.recv_timeout(deadline) {
+            Ok(samples) => Some(samples),
+            Err(RecvTimeoutError::Disconnected) => {
+                warn!("The {collector} collector panicked");
+                Some(Vec::new())
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                warn!("The {collector} collector took over {deadline:?}; skipping it this run");
+                OVERDUE
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .push((collector, done));
+                None
+            }
+        }
+    };
+    timings.push(CollectorTiming {
+        collector,
+        elapsed: start.elapsed(),
+        samples: result.as_ref().map_or(0, Vec::len),
+        timed_out: result.is_none(),
+    });
+    result.unwrap_or_default()
+}
+
+pub fn collect_metrics(
+    ts: f64,
+    enabled: impl Fn(Collector) -> bool,
+    interfaces: &InterfaceFilter,
+    timings: &mut Vec<CollectorTiming>,
+) -> Vec<MetricSample> {
+    let interfaces = interfaces.clone();
+    let cpu_usage_handle = enabled(Collector::Cpu).then(|| {
+        thread::spawn(move || {
+            let start = Instant::now();
+            let samples = cpu_usage_samples(ts);
+            (samples, start.elapsed())
+        })
+    });
 
     let mut metrics = Vec::new();
-    metrics.extend(cpu_frequency_samples(ts));
-    metrics.extend(memory_samples(ts));
-    metrics.extend(network_samples(ts));
-    metrics.extend(disk_samples(ts));
-    metrics.extend(temperature_samples(ts));
-    metrics.extend(gpu_samples(ts));
-    metrics.extend(power_samples(ts));
-    if let Ok(cpu_samples) = cpu_usage_handle.join() {
+    if enabled(Collector::Cpu) {
+        metrics.extend(timed(timings, Collector::Cpu, move || {
+            let mut samples = cpu_frequency_samples(ts);
+            samples.extend(cpu_throttle_samples(ts));
+            samples.extend(cpu_idle_samples(ts));
+            samples.extend(scheduler_samples(ts));
+            samples
+        }));
+    }
+    if enabled(Collector::Memory) {
+        metrics.extend(timed(timings, Collector::Memory, move || {
+            memory_samples(ts)
+        }));
+    }
+    if enabled(Collector::Network) {
+        metrics.extend(timed(timings, Collector::Network, move || {
+            let net_root = Path::new("/sys/class/net");
+            let mut samples = network_samples(ts);
+            samples.extend(network_wakeup_samples(ts));
+            samples.retain(|s| {
+                interfaces.includes(&s.source, is_virtual_interface(net_root, &s.source))
+            });
+            samples.extend(link_state_samples(net_root, ts, &interfaces));
+            samples.extend(tcp_connection_samples(ts));
+            samples
+        }));
+    }
+    if enabled(Collector::Disk) {
+        metrics.extend(timed(timings, Collector::Disk, move || disk_samples(ts)));
+    }
+    if enabled(Collector::Temperature) {
+        metrics.extend(timed(timings, Collector::Temperature, move || {
+            temperature_samples(ts)
+        }));
+    }
+    if enabled(Collector::Gpu) {
+        metrics.extend(timed(timings, Collector::Gpu, move || {
+            let mut samples = gpu_samples(ts);
+            samples.extend(crate::i915::i915_engine_samples(ts));
+            samples
+        }));
+    }
+    if enabled(Collector::Power) {
+        metrics.extend(timed(timings, Collector::Power, move || {
+            let mut samples = power_samples(ts);
+            samples.extend(crate::power_profile::power_profile_samples(ts));
+            samples.extend(screen_state_samples(Path::new("/sys/class/drm"), ts));
+            samples.extend(keyboard_backlight_samples(Path::new("/sys/class/leds"), ts));
+            samples.extend(wakeup_source_samples(
+                Path::new("/sys/class/wakeup"),
+                Path::new("/sys/kernel/debug/wakeup_sources"),
+                ts,
+            ));
+            samples
+        }));
+    }
+    if enabled(Collector::Containers) {
+        metrics.extend(timed(timings, Collector::Containers, move || {
+            crate::containers::container_samples(ts)
+        }));
+    }
+    if enabled(Collector::Systemd) {
+        metrics.extend(timed(timings, Collector::Systemd, move || {
+            let mut samples = crate::systemd::system_health_samples(ts);
+            samples.extend(crate::systemd::logind_samples(ts));
+            samples
+        }));
+    }
+    if let Some(Ok((cpu_samples, elapsed))) = cpu_usage_handle.map(|handle| handle.join()) {
+        // CPU usage sleeps between two reads on its own thread; count it with the CPU group.
+        if let Some(cpu) = timings.iter_mut().find(|t| t.collector == Collector::Cpu) {
+            cpu.elapsed += elapsed;
+            cpu.samples += cpu_samples.len();
+        }
         metrics.extend(cpu_samples);
     }
     metrics
 }
+
+/// A `collector_timeout` sample per group that missed its deadline, so a hung collector
+/// shows up in the database even with the `stats` self-metrics disabled.
+pub fn collector_timeout_samples(ts: f64, timings: &[CollectorTiming]) -> Vec<MetricSample> {
+    timings
+        .iter()
+        .filter(|timing| timing.timed_out)
+        .map(|timing| {
+            MetricSample::new(
+                ts,
+                MetricKind::CollectorTimeout,
+                timing.collector.to_string(),
+                Some(timing.elapsed.as_secs_f64()),
+                Some("s"),
+                json!({}),
+            )
+        })
+        .collect()
+}
+
+/// User plus system CPU time this process has used so far.
+pub fn process_cpu_time() -> Duration {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return Duration::ZERO;
+    }
+    let micros = |tv: libc::timeval| tv.tv_sec as u64 * 1_000_000 + tv.tv_usec as u64;
+    Duration::from_micros(micros(usage.ru_utime) + micros(usage.ru_stime))
+}
+
+/// Self-metrics of one collection run: a sample per collector group with its wall time,
+/// and a `total` one with the run's wall and CPU time and the number of samples written.
+pub fn collector_stats_samples(
+    ts: f64,
+    timings: &[CollectorTiming],
+    total: Duration,
+    cpu: Duration,
+    written: usize,
+) -> Vec<MetricSample> {
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut samples: Vec<MetricSample> = timings
+        .iter()
+        .map(|timing| {
+            MetricSample::new(
+                ts,
+                MetricKind::CollectorStats,
+                timing.collector.to_string(),
+                Some(ms(timing.elapsed)),
+                Some("ms"),
+                json!({ "samples": timing.samples }),
+            )
+        })
+        .collect();
+    samples.push(MetricSample::new(
+        ts,
+        MetricKind::CollectorStats,
+        "total",
+        Some(ms(total)),
+        Some("ms"),
+        json!({ "samples": written, "cpu_ms": ms(cpu) }),
+    ));
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hung_collector_is_skipped_until_it_returns() {
+        let (release, hold) = mpsc::channel::<()>();
+        let mut timings = Vec::new();
+        let deadline = Duration::from_millis(50);
+        let samples =
+            timed_with_deadline(&mut timings, Collector::Containers, deadline, move || {
+                hold.recv().ok();
+                vec![MetricSample::new(
+                    0.0,
+                    MetricKind::ContainerCpu,
+                    "a",
+                    Some(1.0),
+                    None,
+                    json!({}),
+                )]
+            });
+        assert!(samples.is_empty());
+        assert!(timings[0].timed_out);
+
+        // Still hung: skipped without waiting on a second thread
+        let started = Instant::now();
+        timed_with_deadline(&mut timings, Collector::Containers, deadline, Vec::new);
+        assert!(started.elapsed() < deadline);
+        let timeouts = collector_timeout_samples(5.0, &timings);
+        assert_eq!(timeouts.len(), 2);
+        assert_eq!(timeouts[0].kind, MetricKind::CollectorTimeout);
+        assert_eq!(timeouts[0].source, "containers");
+
+        release.send(()).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let samples = timed_with_deadline(&mut timings, Collector::Containers, deadline, || {
+            vec![MetricSample::new(
+                0.0,
+                MetricKind::ContainerCpu,
+                "a",
+                Some(1.0),
+                None,
+                json!({}),
+            )]
+        });
+        assert_eq!(samples.len(), 1);
+        assert!(!timings[2].timed_out);
+    }
+
+    #[test]
+    fn link_and_screen_states_are_booleans() {
+        let tmp = tempfile::tempdir().unwrap();
+        let net = tmp.path().join("net");
+        for (iface, state) in [("lo", "unknown"), ("wlan0", "up"), ("eth0", "down")] {
+            fs::create_dir_all(net.join(iface)).unwrap();
+            fs::write(net.join(iface).join("operstate"), format!("{state}\n")).unwrap();
+        }
+        // Registered under /sys/devices/virtual like the real ifb devices
+        let ifb = tmp.path().join("devices/virtual/net/ifb0");
+        fs::create_dir_all(&ifb).unwrap();
+        fs::write(ifb.join("operstate"), "down\n").unwrap();
+        std::os::unix::fs::symlink(&ifb, net.join("ifb0")).unwrap();
+        let mut links = link_state_samples(&net, 1.0, &InterfaceFilter::default());
+        links.sort_by(|a, b| a.source.cmp(&b.source));
+        let links: Vec<(&str, Option<f64>)> =
+            links.iter().map(|s| (s.source.as_str(), s.value)).collect();
+        assert_eq!(links, [("eth0", Some(0.0)), ("wlan0", Some(1.0))]);
+
+        let drm = tmp.path().join("drm");
+        for (name, enabled, dpms) in [
+            ("card0-eDP-1", "enabled", "Off"),
+            ("card0-HDMI-A-1", "disabled", "Off"),
+        ] {
+            fs::create_dir_all(drm.join(name)).unwrap();
+            fs::write(drm.join(name).join("enabled"), enabled).unwrap();
+            fs::write(drm.join(name).join("dpms"), dpms).unwrap();
+        }
+        fs::create_dir_all(drm.join("card0")).unwrap();
+        let screens = screen_state_samples(&drm, 1.0);
+        assert_eq!(screens.len(), 1);
+        assert_eq!(screens[0].source, "eDP-1");
+        assert_eq!(screens[0].value, Some(0.0));
+        assert!(screens[0].kind.is_state());
+    }
+
+    #[test]
+    fn keyboard_backlight_is_a_share_of_max() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (name, brightness) in [("tpacpi::kbd_backlight", "1"), ("input3::capslock", "1")] {
+            let led = tmp.path().join(name);
+            fs::create_dir_all(&led).unwrap();
+            fs::write(led.join("brightness"), brightness).unwrap();
+            fs::write(led.join("max_brightness"), "2\n").unwrap();
+        }
+        let samples = keyboard_backlight_samples(tmp.path(), 1.0);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].source, "tpacpi::kbd_backlight");
+        assert_eq!(samples[0].value, Some(50.0));
+        assert_eq!(samples[0].details["max_brightness"], 2);
+    }
+
+    #[test]
+    fn energy_counters_become_watts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let hwmon = tmp.path().join("hwmon3");
+        fs::create_dir_all(&hwmon).unwrap();
+        fs::write(hwmon.join("name"), "amd_energy\n").unwrap();
+        for (channel, label, value) in [
+            ("energy1", "Ecore000", "5000000"),
+            ("energy17", "Esocket0", "90000000"),
+        ] {
+            fs::write(hwmon.join(format!("{channel}_label")), label).unwrap();
+            fs::write(hwmon.join(format!("{channel}_input")), value).unwrap();
+        }
+        let first = hwmon_energy_counters(tmp.path());
+        assert_eq!(first.keys().collect::<Vec<_>>(), ["amd_energy:Esocket0"]);
+
+        fs::write(hwmon.join("energy17_input"), "93000000").unwrap();
+        let second = hwmon_energy_counters(tmp.path());
+        let samples = energy_deltas_to_watts(&first, &second, Duration::from_millis(200), 1.0);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].kind, MetricKind::PowerDraw);
+        assert!((samples[0].value.unwrap() - 15.0).abs() < 1e-9);
+
+        // Wrapped counters produce no sample
+        assert!(energy_deltas_to_watts(&second, &first, Duration::from_secs(1), 1.0).is_empty());
+    }
+
+    #[test]
+    fn meminfo_breakdown_is_read_in_bytes() {
+        let content = "MemTotal:       16000000 kB\nMemFree:         2000000 kB\nMemAvailable:    9000000 kB\nBuffers:          100000 kB\nCached:          6000000 kB\nSwapCached:            0 kB\nDirty:              2048 kB\nShmem:            500000 kB\nSlab:             400000 kB\n";
+        let info = parse_meminfo(content).unwrap();
+        assert_eq!(info.total, 16_000_000.0 * 1024.0);
+        assert_eq!(info.cached, 6_000_000.0 * 1024.0);
+        assert_eq!(info.dirty, 2048.0 * 1024.0);
+        assert_eq!(info.slab, 400_000.0 * 1024.0);
+        assert!(parse_meminfo("MemTotal: 1 kB\n").is_none());
+    }
+
+    #[test]
+    fn network_wakeups_read_driver_counters() {
+        let tmp = tempfile::tempdir().unwrap();
+        let wlan = tmp.path().join("wlan0").join("device").join("power");
+        fs::create_dir_all(&wlan).unwrap();
+        fs::write(wlan.join("wakeup_count"), "17\n").unwrap();
+        fs::write(wlan.join("wakeup"), "enabled\n").unwrap();
+        fs::create_dir_all(tmp.path().join("lo")).unwrap();
+
+        let samples = read_network_wakeups(tmp.path(), 1.0);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].source, "wlan0");
+        assert_eq!(samples[0].value, Some(17.0));
+        assert_eq!(samples[0].details["wakeup_enabled"], true);
+    }
+
+    #[test]
+    fn iowait_and_steal_are_split_out_of_cpu_time() {
+        let times = |label: &str, idle: u64, iowait: u64, steal: u64| CpuTimes {
+            label: label.to_string(),
+            user: 100,
+            nice: 0,
+            system: 0,
+            idle,
+            iowait,
+            irq: 0,
+            softirq: 0,
+            steal,
+        };
+        let first = vec![times("cpu", 0, 0, 0), times("cpu0", 0, 0, 0)];
+        let second = vec![times("cpu", 50, 30, 20), times("cpu0", 100, 0, 0)];
+
+        let samples = cpu_time_samples(first, second, 1.0);
+        let values: Vec<(&str, &str, f64)> = samples
+            .iter()
+            .map(|s| (s.kind.as_str(), s.source.as_str(), s.value.unwrap()))
+            .collect();
+        // Iowait still counts as idle for usage; steal counts as busy
+        assert_eq!(
+            values,
+            [
+                ("cpu_usage", "cpu", 20.0),
+                ("cpu_iowait", "cpu", 30.0),
+                ("cpu_steal", "cpu", 20.0),
+                ("cpu_usage", "cpu0", 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn proc_stat_counters_read_ctxt_and_intr_totals() {
+        let content = "cpu  1 2 3 4 5 6 7 8 0 0\nintr 9876 12 0 34\nctxt 5555\nbtime 1700000000\n";
+        let samples = parse_proc_stat_counters(content, 1.0);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].kind, MetricKind::Interrupts);
+        assert_eq!(samples[0].value, Some(9876.0));
+        assert_eq!(samples[1].kind, MetricKind::ContextSwitches);
+        assert_eq!(samples[1].value, Some(5555.0));
+    }
+
+    #[test]
+    fn tcp_states_are_counted_across_tables() {
+        let header = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode";
+        let tcp = format!(
+            "{header}\n   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1\n   1: 0100007F:9C40 0100007F:0CEA 01 00000000:00000000 00:00000000 00000000  1000        0 2\n"
+        );
+        let tcp6 = format!(
+            "{header}\n   0: 00000000000000000000000000000000:0016 00000000000000000000000000000000:0000 06 00000000:00000000 00:00000000 00000000     0        0 3\n"
+        );
+        let counts = count_tcp_states(&[tcp, tcp6]);
+        assert_eq!(counts["listen"], 1);
+        assert_eq!(counts["established"], 1);
+        assert_eq!(counts["time_wait"], 1);
+        assert_eq!(counts["close_wait"], 0);
+        assert!(!counts.contains_key("syn_sent"));
+    }
+
+    #[test]
+    fn cpu_throttle_counts_cores_and_packages() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (cpu, core_count) in [("cpu0", "3"), ("cpu1", "5")] {
+            let throttle = tmp.path().join(cpu).join("thermal_throttle");
+            fs::create_dir_all(&throttle).unwrap();
+            fs::write(throttle.join("core_throttle_count"), core_count).unwrap();
+            fs::write(throttle.join("package_throttle_count"), "11\n").unwrap();
+        }
+        fs::create_dir_all(tmp.path().join("cpufreq")).unwrap();
+
+        let samples = read_cpu_throttle(tmp.path(), 1.0);
+        let values: Vec<(&str, Option<f64>)> = samples
+            .iter()
+            .map(|s| (s.source.as_str(), s.value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("cpu0", Some(3.0)),
+                ("cpu1", Some(5.0)),
+                ("package0", Some(11.0))
+            ]
+        );
+    }
+
+    #[test]
+    fn cpufreq_policy_reads_governor_and_epp() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(cpufreq_policy_details(tmp.path()), Value::Null);
+        fs::write(tmp.path().join("scaling_governor"), "powersave\n").unwrap();
+        fs::write(
+            tmp.path().join("energy_performance_preference"),
+            "balance_power\n",
+        )
+        .unwrap();
+        assert_eq!(
+            cpufreq_policy_details(tmp.path()),
+            json!({ "governor": "powersave", "epp": "balance_power" })
+        );
+    }
+
+    #[test]
+    fn cpu_idle_states_are_summed_over_cpus() {
+        let tmp = tempfile::tempdir().unwrap();
+        for cpu in ["cpu0", "cpu1"] {
+            for (state, name, time) in [("state0", "POLL", "10"), ("state2", "C10", "5000")] {
+                let dir = tmp.path().join(cpu).join("cpuidle").join(state);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("name"), format!("{name}\n")).unwrap();
+                fs::write(dir.join("time"), time).unwrap();
+                fs::write(dir.join("usage"), "3").unwrap();
+            }
+        }
+
+        let samples = read_cpu_idle(tmp.path(), 1.0);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].source, "POLL");
+        assert_eq!(samples[1].source, "C10");
+        assert_eq!(samples[1].value, Some(10_000.0));
+        assert_eq!(samples[1].details["cpus"], 2);
+        assert_eq!(samples[1].details["usage"], 6.0);
+    }
+
+    #[test]
+    fn nvme_temperatures_are_named_after_controller() {
+        let tmp = tempfile::tempdir().unwrap();
+        let hwmon_root = tmp.path().join("hwmon");
+        for (hwmon, controller, temp) in
+            [("hwmon1", "nvme0", "35850"), ("hwmon2", "nvme1", "41850")]
+        {
+            let controller_dir = tmp.path().join(controller);
+            fs::create_dir_all(&controller_dir).unwrap();
+            let dir = hwmon_root.join(hwmon);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("name"), "nvme\n").unwrap();
+            fs::write(dir.join("temp1_input"), temp).unwrap();
+            fs::write(dir.join("temp1_label"), "Composite\n").unwrap();
+            std::os::unix::fs::symlink(&controller_dir, dir.join("device")).unwrap();
+        }
+        let cpu = hwmon_root.join("hwmon0");
+        fs::create_dir_all(&cpu).unwrap();
+        fs::write(cpu.join("name"), "coretemp\n").unwrap();
+        fs::write(cpu.join("temp1_input"), "52000").unwrap();
+
+        let samples = hwmon_temperature_samples(&hwmon_root, 1.0);
+        let sources: Vec<&str> = samples.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            vec!["coretemp:temp1", "nvme0:Composite", "nvme1:Composite"]
+        );
+        assert_eq!(samples[1].value, Some(35.85));
+        assert_eq!(samples[1].details["chip"], "nvme");
+        assert_eq!(samples[2].details["device"], "nvme1");
+    }
+
+    #[test]
+    fn chips_sharing_a_name_are_told_apart_by_device() {
+        let tmp = tempfile::tempdir().unwrap();
+        let hwmon_root = tmp.path().join("hwmon");
+        for (hwmon, device, temp) in [
+            ("hwmon3", "0000:03:00.0", "62000"),
+            ("hwmon4", "0000:0c:00.0", "48000"),
+        ] {
+            let device_dir = tmp.path().join(device);
+            fs::create_dir_all(&device_dir).unwrap();
+            let dir = hwmon_root.join(hwmon);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("name"), "amdgpu\n").unwrap();
+            fs::write(dir.join("temp1_input"), temp).unwrap();
+            fs::write(dir.join("temp1_label"), "edge\n").unwrap();
+            fs::write(dir.join("power1_input"), "25000000").unwrap();
+            std::os::unix::fs::symlink(&device_dir, dir.join("device")).unwrap();
+        }
+        let cpu = hwmon_root.join("hwmon0");
+        fs::create_dir_all(&cpu).unwrap();
+        fs::write(cpu.join("name"), "k10temp\n").unwrap();
+        fs::write(cpu.join("temp1_input"), "52000").unwrap();
+
+        let temperatures = hwmon_temperature_samples(&hwmon_root, 1.0);
+        let sources: Vec<&str> = temperatures.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            [
+                "amdgpu-0000:03:00.0:edge",
+                "amdgpu-0000:0c:00.0:edge",
+                "k10temp:temp1"
+            ]
+        );
+        assert_eq!(temperatures[1].details["device"], "amdgpu-0000:0c:00.0");
+        assert_eq!(temperatures[1].details["chip"], "amdgpu");
+        let power = hwmon_power_samples(&hwmon_root, 1.0);
+        let sources: Vec<&str> = power.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            ["amdgpu-0000:03:00.0:power1", "amdgpu-0000:0c:00.0:power1"]
+        );
+    }
+
+    #[test]
+    fn amdgpu_power_cap_and_fan_are_read_from_hwmon() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(gpu_limit_details(tmp.path()), Value::Null);
+        let hwmon = tmp.path().join("hwmon").join("hwmon4");
+        fs::create_dir_all(&hwmon).unwrap();
+        for (file, value) in [
+            ("power1_average", "180000000"),
+            ("power1_cap", "186000000"),
+            ("pwm1", "102"),
+            ("fan1_input", "1450"),
+            ("temp1_input", "62000"),
+            ("temp2_input", "84000"),
+            ("temp2_crit", "110000"),
+        ] {
+            fs::write(hwmon.join(file), format!("{value}\n")).unwrap();
+        }
+        let details = gpu_limit_details(tmp.path());
+        assert_eq!(details["power_w"], 180.0);
+        assert_eq!(details["power_cap_w"], 186.0);
+        assert_eq!(details["fan_pwm_percent"], 40.0);
+        assert_eq!(details["fan_rpm"], 1450.0);
+        assert_eq!(details["temp_c"], 84.0);
+        assert_eq!(details["temp_limit_c"], 110.0);
+    }
+
+    #[test]
+    fn hwmon_rails_are_read_as_voltage_and_current() {
+        let tmp = tempfile::tempdir().unwrap();
+        let chip = tmp.path().join("hwmon3");
+        fs::create_dir_all(&chip).unwrap();
+        fs::write(chip.join("name"), "nct6798\n").unwrap();
+        fs::write(chip.join("in0_input"), "1104\n").unwrap();
+        fs::write(chip.join("in0_label"), "Vcore\n").unwrap();
+        fs::write(chip.join("in1_input"), "12096\n").unwrap();
+        fs::write(chip.join("curr1_input"), "2500\n").unwrap();
+        fs::write(chip.join("intrusion0_alarm"), "0\n").unwrap();
+        fs::write(chip.join("temp1_input"), "40000\n").unwrap();
+
+        let samples = hwmon_rail_samples(tmp.path(), 1.0);
+        let rails: Vec<(&str, &MetricKind, f64)> = samples
+            .iter()
+            .map(|s| (s.source.as_str(), &s.kind, s.value.unwrap()))
+            .collect();
+        assert_eq!(
+            rails,
+            [
+                ("nct6798:Vcore", &MetricKind::Voltage, 1.104),
+                ("nct6798:curr1", &MetricKind::Current, 2.5),
+                ("nct6798:in1", &MetricKind::Voltage, 12.096),
+            ]
+        );
+    }
+
+    #[test]
+    fn wakeup_sources_are_read_from_sysfs_or_debugfs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let class = tmp.path().join("wakeup");
+        for (dir, name, events) in [
+            ("wakeup0", "alarmtimer", "3"),
+            ("wakeup1", "alarmtimer", "2"),
+            ("wakeup2", "PNP0C0D:00", "0"),
+            ("wakeup3", "rtc0", "7"),
+        ] {
+            let path = class.join(dir);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("name"), format!("{name}\n")).unwrap();
+            fs::write(path.join("event_count"), events).unwrap();
+            fs::write(path.join("total_time_ms"), "10").unwrap();
+        }
+        let debugfs = tmp.path().join("wakeup_sources");
+        let samples = wakeup_source_samples(&class, &debugfs, 1.0);
+        let counts: Vec<(&str, f64)> = samples
+            .iter()
+            .map(|s| (s.source.as_str(), s.value.unwrap()))
+            .collect();
+        assert_eq!(counts, [("alarmtimer", 5.0), ("rtc0", 7.0)]);
+        assert_eq!(samples[0].details["active_ms"], 20.0);
+
+        fs::write(
+            &debugfs,
+            "name\t\tactive_count\tevent_count\twakeup_count\texpire_count\tactive_since\t\
+             total_time\tmax_time\tlast_change\tprevent_suspend_time\n\
+             iwlwifi wake\t4\t12\t1\t0\t0\t35\t20\t1000\t0\n\
+             deleted\t0\t0\t0\t0\t0\t0\t0\t0\t0\n",
+        )
+        .unwrap();
+        let samples = wakeup_source_samples(&tmp.path().join("missing"), &debugfs, 1.0);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].source, "iwlwifi wake");
+        assert_eq!(samples[0].value, Some(12.0));
+        assert_eq!(samples[0].details["wakeup_count"], 1.0);
+    }
+
+    #[test]
+    fn virtual_interfaces_are_recognized() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("tun0")).unwrap();
+        fs::write(tmp.path().join("tun0").join("tun_flags"), "0x1001\n").unwrap();
+        fs::create_dir_all(tmp.path().join("wlan0")).unwrap();
+        for iface in ["lo", "veth3f2a1b", "docker0", "br-5d1e2f", "tun0"] {
+            assert!(is_virtual_interface(tmp.path(), iface), "{iface}");
+        }
+        assert!(!is_virtual_interface(tmp.path(), "wlan0"));
+        assert!(!is_virtual_interface(tmp.path(), "enp3s0"));
+    }
+
+    #[test]
+    fn shared_thermal_zone_types_are_told_apart() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (zone, zone_type, temp) in [
+            ("thermal_zone0", "acpitz", "27800"),
+            ("thermal_zone1", "acpitz", "29800"),
+            ("thermal_zone2", "x86_pkg_temp", "45000"),
+        ] {
+            let dir = tmp.path().join(zone);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("type"), format!("{zone_type}\n")).unwrap();
+            fs::write(dir.join("temp"), temp).unwrap();
+        }
+        fs::create_dir_all(tmp.path().join("cooling_device0")).unwrap();
+
+        let samples = thermal_zone_samples(tmp.path(), 1.0);
+        let sources: Vec<&str> = samples.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            [
+                "acpitz:thermal_zone0",
+                "acpitz:thermal_zone1",
+                "x86_pkg_temp"
+            ]
+        );
+        assert_eq!(samples[1].value, Some(29.8));
+        assert_eq!(samples[1].details["device"], "acpitz");
+    }
+}
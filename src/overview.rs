@@ -0,0 +1,218 @@
+//! `symmetri overview`: a handful of key metrics over the last day, week and month side by
+//! side, as a morning glance instead of three reports.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::aggregate::aggregate_multi_device_metrics;
+use crate::cli_helpers::average_rates;
+use crate::db::{self, Rollup};
+use crate::metrics::{MetricKind, MetricSample};
+use crate::report::{average_for_kind, NumberStats};
+use crate::storage::Storage;
+
+const DAY_SECONDS: f64 = 24.0 * 3600.0;
+
+/// Column label, length and rollup read of each window, shortest first.
+pub const WINDOWS: [(&str, f64, Rollup); 3] = [
+    ("24h", DAY_SECONDS, Rollup::Hourly),
+    ("7d", 7.0 * DAY_SECONDS, Rollup::Daily),
+    ("30d", 30.0 * DAY_SECONDS, Rollup::Daily),
+];
+
+/// Kinds the overview reads; the battery ones feed the discharge fallback and the health.
+pub const OVERVIEW_KINDS: [MetricKind; 7] = [
+    MetricKind::PowerDraw,
+    MetricKind::CpuUsage,
+    MetricKind::NetworkBytes,
+    MetricKind::BatteryEnergyNow,
+    MetricKind::BatteryEnergyFull,
+    MetricKind::BatteryEnergyFullDesign,
+    MetricKind::BatteryHealth,
+];
+
+/// Key metrics over one window.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OverviewColumn {
+    pub label: &'static str,
+    /// Average power draw, from hwmon/battery power readings or else the discharge rate
+    pub avg_power_w: Option<f64>,
+    /// Average overall CPU usage
+    pub avg_cpu_percent: Option<f64>,
+    /// Bytes received and sent over all interfaces
+    pub transferred_bytes: Option<f64>,
+    /// Lowest health of the (combined) battery
+    pub min_battery_health: Option<f64>,
+}
+
+/// Samples of [`OVERVIEW_KINDS`] from `since` on, per-core CPU usage dropped as it is read.
+/// A local database serves the `rollup` buckets as far as they reach and raw samples after
+/// the last complete one; a server serves raw samples throughout. The discharge fallback
+/// needs the battery status of raw energy readings, so those are read under the buckets
+/// only when the buckets hold no power draw.
+fn window_samples(store: &dyn Storage, rollup: Rollup, since: f64) -> Result<Vec<MetricSample>> {
+    let mut samples = Vec::new();
+    let mut keep = |sample: MetricSample| {
+        if sample.kind != MetricKind::CpuUsage || sample.source == "cpu" {
+            samples.push(sample);
+        }
+        Ok(())
+    };
+    let rolled = match store.sqlite() {
+        Some(conn) => db::rolled_until(conn, rollup)?
+            .filter(|&rolled| rolled > since)
+            .map(|rolled| (conn, rolled)),
+        None => None,
+    };
+    let mut raw_since = since;
+    if let Some((conn, rolled)) = rolled {
+        let buckets = db::fetch_rollup_samples(conn, rollup, Some(since), rolled, &OVERVIEW_KINDS)?;
+        let has_power = buckets.iter().any(|m| m.kind == MetricKind::PowerDraw);
+        buckets.into_iter().try_for_each(&mut keep)?;
+        if !has_power {
+            let energy = [MetricKind::BatteryEnergyNow];
+            store.for_each_sample(Some(since), Some(rolled), Some(&energy), &mut keep)?;
+        }
+        raw_since = rolled;
+    }
+    store.for_each_sample(Some(raw_since), None, Some(&OVERVIEW_KINDS), &mut keep)?;
+    samples.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    Ok(samples)
+}
+
+/// Growth of each interface's cumulative byte counter between consecutive readings, a
+/// rolled-up bucket counting as its lowest then its highest reading. Drops (counter resets)
+/// count as nothing.
+fn transferred_bytes(samples: &[MetricSample]) -> Option<f64> {
+    let mut last: BTreeMap<&str, f64> = BTreeMap::new();
+    let mut total = None;
+    for sample in samples
+        .iter()
+        .filter(|m| m.kind == MetricKind::NetworkBytes)
+    {
+        let readings = match (sample.detail_number("min"), sample.detail_number("max")) {
+            (Some(min), Some(max)) => vec![min, max],
+            _ => sample.value.into_iter().collect(),
+        };
+        for reading in readings {
+            if let Some(previous) = last.insert(&sample.source, reading) {
+                *total.get_or_insert(0.0) += (reading - previous).max(0.0);
+            }
+        }
+    }
+    total.filter(|&bytes| bytes > 0.0)
+}
+
+fn column(label: &'static str, samples: &[MetricSample]) -> OverviewColumn {
+    let aggregated = aggregate_multi_device_metrics(samples);
+    let avg_power_w = average_for_kind(&aggregated, MetricKind::PowerDraw)
+        .average()
+        .or_else(|| average_rates(&aggregated).discharge_w);
+    let mut cpu = NumberStats::default();
+    for sample in samples
+        .iter()
+        .filter(|m| m.kind == MetricKind::CpuUsage && m.source == "cpu")
+    {
+        cpu.record_opt(sample.value);
+    }
+    OverviewColumn {
+        label,
+        avg_power_w,
+        avg_cpu_percent: cpu.average(),
+        transferred_bytes: transferred_bytes(samples),
+        min_battery_health: average_for_kind(&aggregated, MetricKind::BatteryHealth).min(),
+    }
+}
+
+/// One column per window in [`WINDOWS`], each ending at `now`.
+pub fn overview(store: &dyn Storage, now: f64) -> Result<Vec<OverviewColumn>> {
+    WINDOWS
+        .iter()
+        .map(|&(label, seconds, rollup)| {
+            let samples = window_samples(store, rollup, now - seconds)?;
+            Ok(column(label, &samples))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage;
+    use serde_json::Value;
+
+    const NOW: f64 = 100.0 * DAY_SECONDS;
+
+    fn sample(days_ago: f64, kind: MetricKind, source: &str, value: f64) -> MetricSample {
+        MetricSample::new(
+            NOW - days_ago * DAY_SECONDS,
+            kind,
+            source,
+            Some(value),
+            None,
+            Value::Null,
+        )
+        .with_network_bytes(Some(value), Some(0.0))
+    }
+
+    #[test]
+    fn windows_only_see_their_own_samples() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = storage::open(&tmp.path().join("metrics.db")).unwrap();
+        let samples = vec![
+            sample(20.0, MetricKind::BatteryHealth, "BAT0", 80.0),
+            sample(20.0, MetricKind::PowerDraw, "BAT0", 12.0),
+            sample(3.0, MetricKind::CpuUsage, "cpu", 40.0),
+            sample(3.0, MetricKind::CpuUsage, "cpu0", 90.0),
+            sample(0.5, MetricKind::CpuUsage, "cpu", 10.0),
+            sample(0.5, MetricKind::PowerDraw, "BAT0", 6.0),
+            sample(0.5, MetricKind::BatteryHealth, "BAT0", 82.0),
+            sample(0.5, MetricKind::NetworkBytes, "wlan0", 1000.0),
+            sample(0.1, MetricKind::NetworkBytes, "wlan0", 4000.0),
+        ];
+        store.insert_samples(&samples, &[]).unwrap();
+
+        let columns = overview(store.as_ref(), NOW).unwrap();
+        let labels: Vec<&str> = columns.iter().map(|c| c.label).collect();
+        assert_eq!(labels, ["24h", "7d", "30d"]);
+        assert_eq!(columns[0].avg_cpu_percent, Some(10.0));
+        assert_eq!(columns[1].avg_cpu_percent, Some(25.0));
+        assert_eq!(columns[0].avg_power_w, Some(6.0));
+        assert_eq!(columns[2].avg_power_w, Some(9.0));
+        assert_eq!(columns[0].transferred_bytes, Some(3000.0));
+        assert_eq!(columns[1].min_battery_health, Some(82.0));
+        assert_eq!(columns[2].min_battery_health, Some(80.0));
+    }
+
+    #[test]
+    fn rolled_up_days_are_read_from_the_rollups() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = storage::open(&tmp.path().join("metrics.db")).unwrap();
+        let samples = vec![
+            sample(20.5, MetricKind::CpuUsage, "cpu", 80.0),
+            sample(3.5, MetricKind::CpuUsage, "cpu", 40.0),
+            sample(3.5, MetricKind::CpuUsage, "cpu0", 95.0),
+            sample(3.6, MetricKind::NetworkBytes, "wlan0", 1000.0),
+            sample(3.4, MetricKind::NetworkBytes, "wlan0", 2000.0),
+            sample(0.25, MetricKind::CpuUsage, "cpu", 10.0),
+            sample(0.25, MetricKind::NetworkBytes, "wlan0", 5000.0),
+        ];
+        store.insert_samples(&samples, &[]).unwrap();
+        let conn = store.sqlite_mut().unwrap();
+        db::update_rollups(conn, NOW - 0.5 * DAY_SECONDS).unwrap();
+        // Only the rollups know about the days before the last one now
+        conn.execute(
+            "DELETE FROM metric_samples WHERE ts < ?",
+            [NOW - DAY_SECONDS],
+        )
+        .unwrap();
+
+        let columns = overview(store.as_ref(), NOW).unwrap();
+        assert_eq!(columns[0].avg_cpu_percent, Some(10.0));
+        assert_eq!(columns[0].transferred_bytes, None);
+        assert_eq!(columns[1].avg_cpu_percent, Some(25.0));
+        assert_eq!(columns[1].transferred_bytes, Some(4000.0));
+        assert_eq!(columns[2].avg_cpu_percent, Some(130.0 / 3.0));
+    }
+}
@@ -0,0 +1,473 @@
+//! [`Storage`] on a PostgreSQL server, so a fleet of machines can collect into one central
+//! store. Timestamps are `timestamptz`, and on TimescaleDB `metric_samples` becomes a
+//! hypertable. Only samples, collection intervals and machine snapshots live on the server;
+//! rollups, archives, the push outbox and alerts are features of the local database.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use postgres::fallible_iterator::FallibleIterator;
+use postgres::types::ToSql;
+use postgres::{Client, NoTls, Row};
+use rusqlite::Connection;
+
+use crate::db::{self, CatalogRow, KindCatalog, SourceCatalog};
+use crate::metrics::{MetricKind, MetricSample};
+use crate::storage::Storage;
+use crate::system_info::{SystemInfo, SystemSnapshot};
+
+/// Untagged samples are stored with an empty host, so the unique key covers them.
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS metric_samples (
+    ts TIMESTAMPTZ NOT NULL,
+    kind TEXT NOT NULL,
+    source TEXT NOT NULL DEFAULT '',
+    value DOUBLE PRECISION,
+    unit TEXT,
+    details JSONB,
+    histogram JSONB,
+    label TEXT,
+    host TEXT NOT NULL DEFAULT '',
+    boot TEXT,
+    rx_bytes DOUBLE PRECISION,
+    tx_bytes DOUBLE PRECISION,
+    total_bytes DOUBLE PRECISION,
+    status TEXT,
+    UNIQUE (ts, kind, source, host)
+);
+CREATE INDEX IF NOT EXISTS idx_metric_samples_kind_ts ON metric_samples (kind, ts);
+CREATE TABLE IF NOT EXISTS collection_intervals (
+    kind TEXT PRIMARY KEY,
+    interval DOUBLE PRECISION NOT NULL,
+    updated DOUBLE PRECISION NOT NULL
+);
+CREATE TABLE IF NOT EXISTS system_info (
+    ts DOUBLE PRECISION NOT NULL,
+    hostname TEXT,
+    kernel TEXT,
+    cpu_model TEXT,
+    memory_bytes BIGINT,
+    battery_model TEXT,
+    battery_serial TEXT
+);
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'timescaledb') THEN
+        PERFORM create_hypertable('metric_samples', 'ts', if_not_exists => TRUE);
+    END IF;
+END
+$$;
+"#;
+
+const SAMPLE_COLUMNS: &str = "EXTRACT(EPOCH FROM ts)::float8 AS ts, kind, source, value, unit, \
+     details::text AS details, histogram::text AS histogram, label, NULLIF(host, '') AS host, \
+     boot, rx_bytes, tx_bytes, total_bytes, status";
+
+/// Samples in a window, of some kinds: `$1` since, `$2` until, `$3` kinds (all when null).
+const SAMPLE_FILTER: &str = "($1::float8 IS NULL OR ts >= to_timestamp($1)) \
+     AND ($2::float8 IS NULL OR ts <= to_timestamp($2)) \
+     AND ($3::text[] IS NULL OR kind = ANY($3))";
+
+const SYSTEM_INFO_COLUMNS: &str =
+    "ts, hostname, kernel, cpu_model, memory_bytes, battery_model, battery_serial";
+
+pub struct PostgresStorage {
+    client: RefCell<Client>,
+    dbname: String,
+}
+
+impl PostgresStorage {
+    /// Connect to the server at the `postgres://` URL `location` and create the tables.
+    pub fn connect(location: &Path) -> Result<Self> {
+        let url = location
+            .to_str()
+            .ok_or_else(|| anyhow!("PostgreSQL URL is not valid UTF-8"))?;
+        let mut config = postgres::Config::from_str(url)?;
+        // `CREATE ... IF NOT EXISTS` reports every existing table as a notice
+        config.notice_callback(|_| {});
+        let mut client = config.connect(NoTls)?;
+        client.batch_execute(SCHEMA)?;
+        Ok(Self {
+            client: RefCell::new(client),
+            dbname: config.get_dbname().unwrap_or("postgres").to_string(),
+        })
+    }
+}
+
+fn kind_names(kinds: Option<&[MetricKind]>) -> Option<Vec<&str>> {
+    kinds.map(|kinds| kinds.iter().map(MetricKind::as_str).collect())
+}
+
+fn sample_from_row(row: &Row) -> Result<MetricSample> {
+    let kind: String = row.try_get("kind")?;
+    let json = |column: &str| -> Result<Option<String>> { Ok(row.try_get(column)?) };
+    Ok(MetricSample {
+        ts: row.try_get("ts")?,
+        kind: MetricKind::from_str(&kind)
+            .map_err(|_| anyhow!("invalid metric kind `{kind}` in database row"))?,
+        source: row.try_get("source")?,
+        value: row.try_get("value")?,
+        unit: row.try_get("unit")?,
+        details: match json("details")? {
+            Some(text) => serde_json::from_str(&text).unwrap_or(serde_json::Value::Null),
+            None => serde_json::Value::Null,
+        },
+        rx_bytes: row.try_get("rx_bytes")?,
+        tx_bytes: row.try_get("tx_bytes")?,
+        total_bytes: row.try_get("total_bytes")?,
+        status: row.try_get("status")?,
+        histogram: json("histogram")?.and_then(|text| serde_json::from_str(&text).ok()),
+        label: row.try_get("label")?,
+        host: row.try_get("host")?,
+        boot: row.try_get("boot")?,
+    })
+}
+
+fn snapshot_from_row(row: &Row) -> Result<SystemSnapshot> {
+    Ok(SystemSnapshot {
+        ts: row.try_get("ts")?,
+        info: SystemInfo {
+            hostname: row.try_get("hostname")?,
+            kernel: row.try_get("kernel")?,
+            cpu_model: row.try_get("cpu_model")?,
+            memory_bytes: row
+                .try_get::<_, Option<i64>>("memory_bytes")?
+                .map(|bytes| bytes as u64),
+            battery_model: row.try_get("battery_model")?,
+            battery_serial: row.try_get("battery_serial")?,
+        },
+    })
+}
+
+/// Hot fields and the rest of the details of `sample`, split as for the local database.
+type SplitSample = ([Option<f64>; 3], Option<String>, Option<serde_json::Value>);
+
+fn split_sample(sample: &MetricSample) -> SplitSample {
+    let ([rx_bytes, tx_bytes, total_bytes, status], details) = db::split_details(sample);
+    (
+        [rx_bytes, tx_bytes, total_bytes].map(db::sql_as_number),
+        db::sql_as_text(status),
+        details.and_then(|text| serde_json::from_str(&text).ok()),
+    )
+}
+
+impl Storage for PostgresStorage {
+    fn insert_samples(
+        &mut self,
+        samples: &[MetricSample],
+        skip_repeats: &[MetricKind],
+    ) -> Result<usize> {
+        let client = self.client.get_mut();
+        let mut tx = client.transaction()?;
+        let insert = tx.prepare(
+            "INSERT INTO metric_samples (
+                ts, kind, source, value, unit, details, histogram, label, host, boot,
+                rx_bytes, tx_bytes, total_bytes, status
+            ) VALUES (
+                to_timestamp($1), $2, $3, $4, $5, CAST($6 AS TEXT)::jsonb,
+                CAST($7 AS TEXT)::jsonb, $8, $9, $10, $11, $12, $13, $14
+            ) ON CONFLICT DO NOTHING",
+        )?;
+        let previous = tx.prepare(
+            "SELECT value, label, details::text, rx_bytes, tx_bytes, total_bytes, status \
+             FROM metric_samples WHERE kind = $1 AND source = $2 AND host = $3 \
+             ORDER BY ts DESC LIMIT 1",
+        )?;
+        let mut stored = 0;
+        for sample in samples {
+            let (hot, status, details) = split_sample(sample);
+            let host = sample.host.as_deref().unwrap_or("");
+            if skip_repeats.contains(&sample.kind) {
+                let last =
+                    tx.query_opt(&previous, &[&sample.kind.as_str(), &sample.source, &host])?;
+                let repeated = last.is_some_and(|row| {
+                    let stored_details = row
+                        .get::<_, Option<String>>(2)
+                        .and_then(|text| serde_json::from_str(&text).ok());
+                    row.get::<_, Option<f64>>(0) == sample.value
+                        && row.get::<_, Option<String>>(1) == sample.label
+                        && stored_details == details
+                        && [3, 4, 5].map(|i| row.get::<_, Option<f64>>(i)) == hot
+                        && row.get::<_, Option<String>>(6) == status
+                });
+                if repeated {
+                    continue;
+                }
+            }
+            let histogram = sample
+                .histogram
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?;
+            let details = details.map(|details| details.to_string());
+            let [rx_bytes, tx_bytes, total_bytes] = hot;
+            stored += tx.execute(
+                &insert,
+                &[
+                    &sample.ts,
+                    &sample.kind.as_str(),
+                    &sample.source,
+                    &sample.value,
+                    &sample.unit,
+                    &details,
+                    &histogram,
+                    &sample.label,
+                    &host,
+                    &sample.boot,
+                    &rx_bytes,
+                    &tx_bytes,
+                    &total_bytes,
+                    &status,
+                ],
+            )? as usize;
+        }
+        tx.commit()?;
+        Ok(stored)
+    }
+
+    fn record_collection_intervals(
+        &mut self,
+        kinds: &[&str],
+        interval: f64,
+        ts: f64,
+    ) -> Result<()> {
+        let client = self.client.get_mut();
+        let mut tx = client.transaction()?;
+        for kind in kinds {
+            tx.execute(
+                "INSERT INTO collection_intervals (kind, interval, updated) VALUES ($1, $2, $3) \
+                 ON CONFLICT (kind) DO UPDATE \
+                 SET interval = excluded.interval, updated = excluded.updated",
+                &[kind, &interval, &ts],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn record_system_info(&mut self, info: &SystemInfo, ts: f64) -> Result<bool> {
+        let client = self.client.get_mut();
+        let latest = client
+            .query_opt(
+                &format!("SELECT {SYSTEM_INFO_COLUMNS} FROM system_info ORDER BY ts DESC LIMIT 1"),
+                &[],
+            )?
+            .map(|row| snapshot_from_row(&row))
+            .transpose()?;
+        if latest.is_some_and(|snapshot| &snapshot.info == info) {
+            return Ok(false);
+        }
+        client.execute(
+            &format!(
+                "INSERT INTO system_info ({SYSTEM_INFO_COLUMNS}) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            ),
+            &[
+                &ts,
+                &info.hostname,
+                &info.kernel,
+                &info.cpu_model,
+                &info.memory_bytes.map(|bytes| bytes as i64),
+                &info.battery_model,
+                &info.battery_serial,
+            ],
+        )?;
+        Ok(true)
+    }
+
+    fn fetch_samples(
+        &self,
+        since_ts: Option<f64>,
+        until_ts: Option<f64>,
+        kinds: Option<&[MetricKind]>,
+    ) -> Result<Vec<MetricSample>> {
+        let mut samples = Vec::new();
+        self.for_each_sample(since_ts, until_ts, kinds, &mut |sample| {
+            samples.push(sample);
+            Ok(())
+        })?;
+        Ok(samples)
+    }
+
+    fn for_each_sample(
+        &self,
+        since_ts: Option<f64>,
+        until_ts: Option<f64>,
+        kinds: Option<&[MetricKind]>,
+        each: &mut dyn FnMut(MetricSample) -> Result<()>,
+    ) -> Result<()> {
+        let kinds = kind_names(kinds);
+        let params: [&(dyn ToSql + Sync); 3] = [&since_ts, &until_ts, &kinds];
+        let mut client = self.client.borrow_mut();
+        let mut rows = client.query_raw(
+            &format!(
+                "SELECT {SAMPLE_COLUMNS} FROM metric_samples WHERE {SAMPLE_FILTER} ORDER BY ts"
+            ),
+            params,
+        )?;
+        while let Some(row) = rows.next()? {
+            each(sample_from_row(&row)?)?;
+        }
+        Ok(())
+    }
+
+    fn fetch_latest(&self, kinds: Option<&[MetricKind]>) -> Result<Vec<MetricSample>> {
+        let kinds = kind_names(kinds);
+        let rows = self.client.borrow_mut().query(
+            &format!(
+                "SELECT * FROM ( \
+                     SELECT DISTINCT ON (kind, source, host) {SAMPLE_COLUMNS} \
+                     FROM metric_samples WHERE $1::text[] IS NULL OR kind = ANY($1) \
+                     ORDER BY kind, source, host, metric_samples.ts DESC \
+                 ) latest ORDER BY ts"
+            ),
+            &[&kinds],
+        )?;
+        rows.iter().map(sample_from_row).collect()
+    }
+
+    fn count_samples(&self, since_ts: Option<f64>) -> Result<usize> {
+        let row = self.client.borrow_mut().query_one(
+            "SELECT COUNT(*) FROM metric_samples \
+             WHERE $1::float8 IS NULL OR ts >= to_timestamp($1)",
+            &[&since_ts],
+        )?;
+        Ok(row.try_get::<_, i64>(0)? as usize)
+    }
+
+    fn collection_intervals(&self) -> Result<BTreeMap<String, f64>> {
+        let rows = self
+            .client
+            .borrow_mut()
+            .query("SELECT kind, interval FROM collection_intervals", &[])?;
+        rows.iter()
+            .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+            .collect()
+    }
+
+    fn system_snapshots(&self, since_ts: Option<f64>) -> Result<Vec<SystemSnapshot>> {
+        let since = since_ts.unwrap_or(f64::NEG_INFINITY);
+        let rows = self.client.borrow_mut().query(
+            &format!(
+                "SELECT {SYSTEM_INFO_COLUMNS} FROM system_info \
+                 WHERE ts >= (SELECT COALESCE(MAX(ts), $1) FROM system_info WHERE ts <= $1) \
+                 ORDER BY ts"
+            ),
+            &[&since],
+        )?;
+        rows.iter().map(snapshot_from_row).collect()
+    }
+
+    fn last_seen_by_host(&self) -> Result<Vec<(Option<String>, f64)>> {
+        let rows = self.client.borrow_mut().query(
+            "SELECT NULLIF(host, ''), EXTRACT(EPOCH FROM MAX(ts))::float8 \
+             FROM metric_samples GROUP BY host ORDER BY host",
+            &[],
+        )?;
+        rows.iter()
+            .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+            .collect()
+    }
+
+    fn catalog(&self) -> Result<Vec<KindCatalog>> {
+        let rows = self.client.borrow_mut().query(
+            "SELECT kind, source, EXTRACT(EPOCH FROM MIN(ts))::float8, \
+                 EXTRACT(EPOCH FROM MAX(ts))::float8, COUNT(*), MAX(unit) \
+             FROM metric_samples GROUP BY kind, source ORDER BY kind, source",
+            &[],
+        )?;
+        let rows = rows
+            .iter()
+            .map(|row| -> Result<CatalogRow> {
+                Ok((
+                    row.try_get(0)?,
+                    SourceCatalog {
+                        source: row.try_get(1)?,
+                        first_seen: row.try_get(2)?,
+                        last_seen: row.try_get(3)?,
+                        rows: row.try_get::<_, i64>(4)? as u64,
+                    },
+                    row.try_get(5)?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(db::merge_catalog(rows, &self.collection_intervals()?))
+    }
+
+    fn database_host(&self) -> Result<String> {
+        Ok(self
+            .system_snapshots(None)?
+            .pop()
+            .and_then(|snapshot| snapshot.info.hostname)
+            .unwrap_or_else(|| self.dbname.clone()))
+    }
+
+    fn sqlite(&self) -> Option<&Connection> {
+        None
+    }
+
+    fn sqlite_mut(&mut self) -> Option<&mut Connection> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs against the server in `SYMMETRI_TEST_POSTGRES` (a `postgres://` URL of a
+    /// scratch database) and is skipped without one.
+    #[test]
+    fn server_store_reads_back_what_it_writes() {
+        let Ok(url) = std::env::var("SYMMETRI_TEST_POSTGRES") else {
+            return;
+        };
+        let mut store = PostgresStorage::connect(Path::new(&url)).unwrap();
+        store
+            .client
+            .get_mut()
+            .batch_execute("TRUNCATE metric_samples, collection_intervals, system_info")
+            .unwrap();
+        let sample = |ts: f64, value: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::DiskUsage,
+                "/",
+                Some(value),
+                Some("bytes"),
+                serde_json::json!({"mount": "/"}),
+            )
+            .with_total_bytes(100.0)
+        };
+        let skip = [MetricKind::DiskUsage];
+        let written = store
+            .insert_samples(&[sample(1.0, 40.0), sample(2.0, 40.0)], &skip)
+            .unwrap();
+        assert_eq!(written, 1);
+        // Already stored
+        assert_eq!(store.insert_samples(&[sample(3.0, 50.0)], &[]).unwrap(), 1);
+        assert_eq!(store.insert_samples(&[sample(3.0, 50.0)], &[]).unwrap(), 0);
+        store
+            .record_collection_intervals(&["disk_usage"], 60.0, 3.0)
+            .unwrap();
+        let info = SystemInfo {
+            hostname: Some("thinkpad".to_string()),
+            ..SystemInfo::default()
+        };
+        assert!(store.record_system_info(&info, 1.0).unwrap());
+        assert!(!store.record_system_info(&info, 2.0).unwrap());
+
+        let samples = store.fetch_samples(Some(2.0), None, Some(&skip)).unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].total_bytes, Some(100.0));
+        assert_eq!(samples[0].details, serde_json::json!({"mount": "/"}));
+        assert_eq!(store.fetch_latest(None).unwrap()[0].value, Some(50.0));
+        assert_eq!(store.count_samples(None).unwrap(), 2);
+        assert_eq!(store.collection_intervals().unwrap()["disk_usage"], 60.0);
+        assert_eq!(store.last_seen_by_host().unwrap(), [(None, 3.0)]);
+        assert_eq!(store.catalog().unwrap()[0].interval, Some(60.0));
+        assert_eq!(store.database_host().unwrap(), "thinkpad");
+    }
+}
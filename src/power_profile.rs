@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use log::debug;
+use serde_json::json;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+const PLATFORM_PROFILE_PATH: &str = "/sys/firmware/acpi/platform_profile";
+
+/// Bus names of power-profiles-daemon; releases since 0.20 prefer the UPower one.
+const PPD_SERVICES: [(&str, &str); 2] = [
+    (
+        "org.freedesktop.UPower.PowerProfiles",
+        "/org/freedesktop/UPower/PowerProfiles",
+    ),
+    ("net.hadess.PowerProfiles", "/net/hadess/PowerProfiles"),
+];
+
+/// Position of a profile between power saving (0) and performance (2), so the categorical
+/// value can still be plotted; unknown vendor profiles have no level.
+fn profile_level(profile: &str) -> Option<f64> {
+    match profile {
+        "power-saver" | "low-power" | "quiet" | "cool" => Some(0.0),
+        "balanced" => Some(1.0),
+        "balanced-performance" => Some(1.5),
+        "performance" => Some(2.0),
+        _ => None,
+    }
+}
+
+fn power_profile_sample(ts: f64, source: &str, profile: &str) -> MetricSample {
+    MetricSample::new(
+        ts,
+        MetricKind::PowerProfile,
+        source,
+        profile_level(profile),
+        Some("profile"),
+        json!({ "profile": profile }),
+    )
+    .with_label(profile)
+}
+
+#[cfg(feature = "systemd")]
+fn query_daemon() -> Result<String> {
+    use zbus::blocking::{Connection, Proxy};
+
+    let connection = Connection::system()?;
+    let mut last_err = None;
+    for (service, path) in PPD_SERVICES {
+        let profile = Proxy::new(&connection, service, path, service)
+            .and_then(|proxy| proxy.get_property::<String>("ActiveProfile"));
+        match profile {
+            Ok(profile) => return Ok(profile),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.map_or_else(|| anyhow::anyhow!("no power-profiles-daemon"), Into::into))
+}
+
+#[cfg(not(feature = "systemd"))]
+fn query_daemon() -> Result<String> {
+    use std::process::Command;
+
+    let mut last_err = String::new();
+    for (service, path) in PPD_SERVICES {
+        let output = Command::new("busctl")
+            .args(["get-property", service, path, service, "ActiveProfile"])
+            .output()?;
+        if output.status.success() {
+            return parse_busctl_string(&String::from_utf8_lossy(&output.stdout))
+                .ok_or_else(|| anyhow::anyhow!("unexpected busctl output"));
+        }
+        last_err = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    }
+    Err(anyhow::anyhow!("busctl failed: {last_err}"))
+}
+
+/// A string from `busctl get-property` output (`s "balanced"`).
+#[cfg_attr(feature = "systemd", allow(dead_code))]
+fn parse_busctl_string(output: &str) -> Option<String> {
+    let value = output.trim().strip_prefix("s ")?;
+    Some(value.trim_matches('"').to_string())
+}
+
+fn read_platform_profile(path: &Path) -> Option<String> {
+    let profile = fs::read_to_string(path).ok()?.trim().to_string();
+    (!profile.is_empty()).then_some(profile)
+}
+
+/// The active power profile from power-profiles-daemon, falling back to the ACPI
+/// platform profile when the daemon is not running.
+pub fn power_profile_samples(ts: f64) -> Vec<MetricSample> {
+    match query_daemon() {
+        Ok(profile) => return vec![power_profile_sample(ts, "power-profiles-daemon", &profile)],
+        Err(err) => debug!("power-profiles-daemon unavailable: {err}"),
+    }
+    read_platform_profile(Path::new(PLATFORM_PROFILE_PATH))
+        .map(|profile| power_profile_sample(ts, "platform_profile", &profile))
+        .into_iter()
+        .collect()
+}
+
+/// Whether the active profile is a power saving one (`power-saver`, `low-power`, ...).
+pub fn power_saver_active(ts: f64) -> bool {
+    power_profile_samples(ts)
+        .iter()
+        .any(|sample| sample.value == Some(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_are_stored_with_a_level() {
+        assert_eq!(
+            parse_busctl_string("s \"power-saver\"\n").as_deref(),
+            Some("power-saver")
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("platform_profile");
+        fs::write(&path, "balanced-performance\n").unwrap();
+        let profile = read_platform_profile(&path).unwrap();
+
+        let sample = power_profile_sample(1.0, "platform_profile", &profile);
+        assert_eq!(sample.kind, MetricKind::PowerProfile);
+        assert_eq!(sample.value, Some(1.5));
+        assert_eq!(sample.details["profile"], "balanced-performance");
+        assert_eq!(sample.label.as_deref(), Some("balanced-performance"));
+        assert_eq!(
+            power_profile_sample(1.0, "platform_profile", "custom").value,
+            None
+        );
+    }
+}
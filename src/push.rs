@@ -0,0 +1,338 @@
+//! Pushing collected samples to remote sinks (an InfluxDB write endpoint, or a Prometheus
+//! remote-write one with the `remote-write` feature) over plain HTTP.
+//! Each batch goes through an outbox table first, so batches collected while the sink is
+//! unreachable are sent in order once it is back instead of leaving a gap.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::Connection;
+
+use crate::config::InfluxConfig;
+use crate::db;
+use crate::export::influx_line;
+use crate::metrics::MetricSample;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Host, port and path of an `http://` URL.
+fn split_url(url: &str) -> Result<(String, u16, String)> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| anyhow!("only http:// URLs are supported, got `{url}`"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().context("invalid port")?),
+        None => (authority, 80),
+    };
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// POST `body` to `url`, failing unless the response status is 2xx.
+pub fn http_post(url: &str, headers: &[(&str, String)], body: &[u8]) -> Result<()> {
+    let (host, port, path) = split_url(url)?;
+    let addr = (host.as_str(), port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("cannot resolve {host}"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+    let mut request = format!(
+        "POST {path} HTTP/1.0\r\nHost: {host}\r\nContent-Length: {}\r\n",
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+    let mut request = request.into_bytes();
+    request.extend_from_slice(body);
+    stream.write_all(&request)?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow!("malformed HTTP response from {host}"))?;
+    if !(200..300).contains(&status) {
+        let first_line = response.lines().next().unwrap_or_default();
+        bail!("{url} answered {first_line}");
+    }
+    Ok(())
+}
+
+/// Queue `payload` for `sink` and send everything pending in order, stopping at the first
+/// failure. Returns how many batches were sent; the rest stay queued for the next run.
+pub fn send_through_outbox(
+    conn: &Connection,
+    sink: &str,
+    payload: &str,
+    max_pending: usize,
+    send: impl Fn(&str) -> Result<()>,
+) -> Result<usize> {
+    db::enqueue_push(conn, sink, payload, max_pending)?;
+    let mut sent = 0;
+    for (id, batch) in db::pending_pushes(conn, sink)? {
+        if let Err(err) = send(&batch) {
+            let pending = db::pending_pushes(conn, sink)?.len();
+            return Err(err.context(format!("{pending} batches queued for {sink}")));
+        }
+        db::delete_push(conn, id)?;
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+/// Push `samples` to the configured InfluxDB write endpoint as line protocol.
+pub fn push_influx(
+    conn: &Connection,
+    config: &InfluxConfig,
+    samples: &[MetricSample],
+) -> Result<()> {
+    let Some(url) = &config.url else {
+        return Ok(());
+    };
+    let lines: Vec<String> = samples.iter().filter_map(influx_line).collect();
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let mut headers = vec![("Content-Type", "text/plain; charset=utf-8".to_string())];
+    if let Some(token) = &config.token {
+        headers.push(("Authorization", format!("Token {token}")));
+    }
+    send_through_outbox(
+        conn,
+        "influx",
+        &lines.join("\n"),
+        config.max_pending,
+        |batch| http_post(url, &headers, batch.as_bytes()),
+    )?;
+    Ok(())
+}
+
+#[cfg(feature = "remote-write")]
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Append protobuf field `field` holding length-delimited `bytes`.
+#[cfg(feature = "remote-write")]
+fn put_bytes(out: &mut Vec<u8>, field: u8, bytes: &[u8]) {
+    out.push(field << 3 | 2);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Label names and values of one remote-write time series.
+#[cfg(feature = "remote-write")]
+type SeriesLabels<'a> = Vec<(&'a str, String)>;
+
+/// A remote-write `WriteRequest`: one time series per kind, source and host, named
+/// `symmetri_<kind>`, holding its samples with a finite value.
+#[cfg(feature = "remote-write")]
+fn write_request(samples: &[MetricSample]) -> Vec<u8> {
+    use std::collections::BTreeMap;
+
+    let mut series: BTreeMap<SeriesLabels, Vec<(f64, i64)>> = BTreeMap::new();
+    for sample in samples {
+        let Some(value) = sample.value.filter(|v| v.is_finite()) else {
+            continue;
+        };
+        // Labels must be sorted by name
+        let mut labels = vec![("__name__", format!("symmetri_{}", sample.kind.as_str()))];
+        if let Some(host) = sample.host.as_deref().filter(|host| !host.is_empty()) {
+            labels.push(("host", host.to_string()));
+        }
+        if !sample.source.is_empty() {
+            labels.push(("source", sample.source.clone()));
+        }
+        let ms = (sample.ts * 1000.0).round() as i64;
+        series.entry(labels).or_default().push((value, ms));
+    }
+
+    let mut request = Vec::new();
+    for (labels, mut points) in series {
+        points.sort_by_key(|(_, ms)| *ms);
+        let mut timeseries = Vec::new();
+        for (name, value) in labels {
+            let mut label = Vec::new();
+            put_bytes(&mut label, 1, name.as_bytes());
+            put_bytes(&mut label, 2, value.as_bytes());
+            put_bytes(&mut timeseries, 1, &label);
+        }
+        for (value, ms) in points {
+            let mut point = vec![1 << 3 | 1];
+            point.extend_from_slice(&value.to_le_bytes());
+            point.push(2 << 3);
+            put_varint(&mut point, ms as u64);
+            put_bytes(&mut timeseries, 2, &point);
+        }
+        put_bytes(&mut request, 1, &timeseries);
+    }
+    request
+}
+
+/// `data` as a snappy block made of literals only. Remote write requires snappy framing but
+/// not actual compression, and the payloads are small.
+#[cfg(feature = "remote-write")]
+fn snappy_literals(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 65536 * 3 + 8);
+    put_varint(&mut out, data.len() as u64);
+    for chunk in data.chunks(65536) {
+        // Tag 61: literal whose length minus one follows in two little-endian bytes
+        out.push(61 << 2);
+        out.extend_from_slice(&((chunk.len() - 1) as u16).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out
+}
+
+/// Push `samples` to the configured Prometheus remote-write endpoint. The outbox holds the
+/// samples as JSON; they are encoded when sent.
+#[cfg(feature = "remote-write")]
+pub fn push_remote_write(
+    conn: &Connection,
+    config: &crate::config::RemoteWriteConfig,
+    samples: &[MetricSample],
+) -> Result<()> {
+    let Some(url) = &config.url else {
+        return Ok(());
+    };
+    if !samples.iter().any(|m| m.value.is_some_and(f64::is_finite)) {
+        return Ok(());
+    }
+    let mut headers = vec![
+        ("Content-Type", "application/x-protobuf".to_string()),
+        ("Content-Encoding", "snappy".to_string()),
+        ("X-Prometheus-Remote-Write-Version", "0.1.0".to_string()),
+    ];
+    if let Some(token) = &config.token {
+        headers.push(("Authorization", format!("Bearer {token}")));
+    }
+    send_through_outbox(
+        conn,
+        "remote_write",
+        &serde_json::to_string(samples)?,
+        config.max_pending,
+        |batch| {
+            let samples: Vec<MetricSample> = serde_json::from_str(batch)?;
+            http_post(url, &headers, &snappy_literals(&write_request(&samples)))
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn batches_wait_in_the_outbox_until_the_sink_answers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let conn = db::init_db_connection(&tmp.path().join("metrics.db")).unwrap();
+        let down = |_: &str| -> Result<()> { bail!("connection refused") };
+        assert!(send_through_outbox(&conn, "influx", "a", 2, down).is_err());
+        assert!(send_through_outbox(&conn, "influx", "b", 2, down).is_err());
+        // The oldest batch is dropped past the limit
+        assert!(send_through_outbox(&conn, "influx", "c", 2, down).is_err());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/api/v2/write?bucket=x",
+            listener.local_addr().unwrap()
+        );
+        let server = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                let mut buf = [0u8; 1024];
+                // Every body in this test is a single byte
+                while !request.contains("\r\n\r\n") || request.ends_with("\r\n\r\n") {
+                    let read = stream.read(&mut buf).unwrap();
+                    request.push_str(&String::from_utf8_lossy(&buf[..read]));
+                }
+                bodies.push(request.split("\r\n\r\n").nth(1).unwrap().to_string());
+                stream
+                    .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                    .unwrap();
+            }
+            bodies
+        });
+        let sent = send_through_outbox(&conn, "influx", "d", 2, |batch| {
+            http_post(&url, &[], batch.as_bytes())
+        })
+        .unwrap();
+        assert_eq!(sent, 2);
+        assert_eq!(server.join().unwrap(), ["c", "d"]);
+        assert!(db::pending_pushes(&conn, "influx").unwrap().is_empty());
+        assert!(split_url("https://example.com").is_err());
+    }
+
+    #[cfg(feature = "remote-write")]
+    #[test]
+    fn samples_become_a_remote_write_request() {
+        use crate::metrics::MetricKind;
+        use serde_json::Value;
+
+        let sample = |ts: f64, value: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::Temperature,
+                "cpu",
+                Some(value),
+                None,
+                Value::Null,
+            )
+        };
+        let request = write_request(&[sample(3.0, 2.0), sample(2.0, 1.0)]);
+
+        let label = |name: &str, value: &str| {
+            let mut label = Vec::new();
+            put_bytes(&mut label, 1, name.as_bytes());
+            put_bytes(&mut label, 2, value.as_bytes());
+            label
+        };
+        let mut timeseries = Vec::new();
+        put_bytes(
+            &mut timeseries,
+            1,
+            &label("__name__", "symmetri_temperature"),
+        );
+        put_bytes(&mut timeseries, 1, &label("source", "cpu"));
+        // Oldest first: 1.0 at 2000 ms, then 2.0 at 3000 ms
+        put_bytes(
+            &mut timeseries,
+            2,
+            &[&[0x09][..], &1.0f64.to_le_bytes(), &[0x10, 0xD0, 0x0F]].concat(),
+        );
+        put_bytes(
+            &mut timeseries,
+            2,
+            &[&[0x09][..], &2.0f64.to_le_bytes(), &[0x10, 0xB8, 0x17]].concat(),
+        );
+        let mut expected = Vec::new();
+        put_bytes(&mut expected, 1, &timeseries);
+        assert_eq!(request, expected);
+
+        assert_eq!(
+            snappy_literals(b"abc"),
+            [3, 61 << 2, 2, 0, b'a', b'b', b'c']
+        );
+    }
+}
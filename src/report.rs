@@ -0,0 +1,3660 @@
+//! Report computation shared by every front end: the CLI tables, graphs and anything
+//! else that renders a timeframe report builds a [`ReportResult`] with [`ReportBuilder`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use clap::ValueEnum;
+use ordered_float::OrderedFloat;
+use rusqlite::Connection;
+use serde::Deserialize;
+
+use crate::battery_analysis::{
+    battery_sessions, charge_by_charger, charge_curves, charge_extremes, discharge_by_activity,
+    discharge_by_keyboard_backlight, discharge_intervals, energy_used, last_discharge,
+    rate_sessions, soc_decile_rates, Direction,
+};
+pub use crate::battery_analysis::{
+    ActivityDrain, BacklightDrain, BatterySession, ChargeCurve, ChargeExtremes, ChargerRate,
+    DecileRate, LastDischarge, CALIBRATION_INTERVAL_DAYS,
+};
+pub use crate::cli_helpers::SourceCounterBuckets;
+use crate::cli_helpers::{
+    average_rates, bucket_counter_deltas, bucket_span_seconds, bucket_start, container_cpu_percent,
+    counter_delta, counter_rate_samples, cpu_idle_residency_percent, estimate_runtime_hours,
+    is_charging, is_discharging,
+};
+pub use crate::correlate::ThrottleSummary;
+use crate::correlate::{series_by_timestamp, thermal_throttle_summary, Reduce};
+use crate::db;
+use crate::derived;
+pub use crate::derived::DerivedMetric;
+use crate::forecast::disk_forecasts;
+pub use crate::forecast::DiskForecast;
+use crate::histogram::Histogram;
+use crate::leak::detect_memory_leaks;
+pub use crate::leak::LeakPeriod;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::storage::Storage;
+use crate::system_info::SystemSnapshot;
+use crate::timeframe::Timeframe;
+
+/// Default temperature (C) above which frequency drops count as likely thermal throttling.
+pub const DEFAULT_THROTTLE_TEMP_C: f64 = 85.0;
+
+/// Default horizon (days) within which a filesystem predicted to fill is highlighted.
+pub const DEFAULT_DISK_FULL_DAYS: f64 = 30.0;
+
+/// Timeframes longer than this read hourly rollups for [`MetricKind::reads_from_rollups`]
+/// kinds, and those longer than the daily one read daily rollups.
+const HOURLY_ROLLUP_AFTER_SECONDS: f64 = 7.0 * 86400.0;
+const DAILY_ROLLUP_AFTER_SECONDS: f64 = 90.0 * 86400.0;
+
+/// Samples of `kinds` from `since` to `until`: rolled-up buckets as far as they reach,
+/// then the raw samples collected after the last complete bucket.
+fn fetch_with_rollups(
+    conn: &Connection,
+    rollup: db::Rollup,
+    since: Option<f64>,
+    until: Option<f64>,
+    kinds: &[MetricKind],
+) -> anyhow::Result<Vec<MetricSample>> {
+    if kinds.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rolled) = db::rolled_until(conn, rollup)? else {
+        return db::fetch_metric_samples_between(conn, since, until, Some(kinds));
+    };
+    let rolled = until.map_or(rolled, |until| rolled.min(until));
+    let mut samples = db::fetch_rollup_samples(conn, rollup, since, rolled, kinds)?;
+    let recent = since.map_or(rolled, |since| since.max(rolled));
+    samples.extend(db::fetch_metric_samples_between(
+        conn,
+        Some(recent),
+        until,
+        Some(kinds),
+    )?);
+    Ok(samples)
+}
+
+/// Rollup read for a timeframe of `seconds` (`None` for all time), if any.
+fn rollup_for(seconds: Option<f64>) -> Option<db::Rollup> {
+    match seconds {
+        None => Some(db::Rollup::Daily),
+        Some(s) if s > DAILY_ROLLUP_AFTER_SECONDS => Some(db::Rollup::Daily),
+        Some(s) if s > HOURLY_ROLLUP_AFTER_SECONDS => Some(db::Rollup::Hourly),
+        Some(_) => None,
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPreset {
+    All,
+    Battery,
+    Cpu,
+    Gpu,
+    Memory,
+    Network,
+    Temperature,
+    Disk,
+    Containers,
+    System,
+    Derived,
+    Collector,
+}
+
+/// How CPU usage and frequency break down in tables and graphs; both the total and
+/// every core are shown when unset.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CpuDetail {
+    /// The `cpu` total only, with core frequencies averaged into it
+    Aggregate,
+    /// `cpu0`..`cpuN` only
+    PerCore,
+}
+
+/// Kinds of report blocks, printed per preset in this order by default.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportSection {
+    /// System info snapshots of the machines the samples came from
+    Machine,
+    /// Whole-timeframe figures: battery summary, states, time in state, distributions, boots
+    Summary,
+    /// Per-window stats tables
+    Buckets,
+    /// Where the energy went: discharge by charge level/activity/backlight, charging
+    /// curve, peripherals, wakeup sources
+    Breakdowns,
+    /// Findings: thermal throttling, memory leaks, disk forecasts, undersampled series
+    Events,
+}
+
+pub fn preset_kinds(preset: ReportPreset) -> &'static [MetricKind] {
+    match preset {
+        ReportPreset::Battery => &[
+            MetricKind::BatteryPercentage,
+            MetricKind::BatteryCapacity,
+            MetricKind::BatteryHealth,
+            MetricKind::BatteryEnergyNow,
+            MetricKind::BatteryEnergyFull,
+            MetricKind::BatteryEnergyFullDesign,
+            MetricKind::PeripheralBattery,
+            MetricKind::PowerDraw,
+            MetricKind::UserIdle,
+            MetricKind::LidClosed,
+            MetricKind::PowerProfile,
+            MetricKind::AcOnline,
+            MetricKind::ChargerPower,
+            MetricKind::ScreenOn,
+            MetricKind::KeyboardBacklight,
+            MetricKind::BatteryStatus,
+            MetricKind::WakeupEvents,
+        ],
+        ReportPreset::Cpu => &[
+            MetricKind::CpuUsage,
+            MetricKind::CpuFrequency,
+            MetricKind::CpuThrottle,
+            MetricKind::CpuIdleResidency,
+            MetricKind::ContextSwitches,
+            MetricKind::Interrupts,
+            MetricKind::CpuGovernor,
+            MetricKind::CpuIowait,
+            MetricKind::CpuSteal,
+        ],
+        ReportPreset::Gpu => &[MetricKind::GpuUsage, MetricKind::GpuFrequency],
+        ReportPreset::Memory => &[MetricKind::MemoryUsage],
+        ReportPreset::Network => &[
+            MetricKind::NetworkBytes,
+            MetricKind::NetworkWakeups,
+            MetricKind::TcpConnections,
+            MetricKind::Latency,
+            MetricKind::LinkUp,
+        ],
+        ReportPreset::Temperature => &[
+            MetricKind::Temperature,
+            MetricKind::Voltage,
+            MetricKind::Current,
+        ],
+        ReportPreset::Disk => &[MetricKind::DiskUsage],
+        ReportPreset::Containers => &[MetricKind::ContainerCpu, MetricKind::ContainerMemory],
+        ReportPreset::System => &[MetricKind::SystemHealth],
+        ReportPreset::Derived => &[MetricKind::Derived],
+        ReportPreset::Collector => &[MetricKind::CollectorStats, MetricKind::CollectorTimeout],
+        ReportPreset::All => &[],
+    }
+}
+
+pub fn normalize_presets(mut presets: Vec<ReportPreset>) -> Vec<ReportPreset> {
+    if presets.is_empty() {
+        return vec![ReportPreset::Battery];
+    }
+    if presets.contains(&ReportPreset::All) {
+        return vec![
+            ReportPreset::Battery,
+            ReportPreset::Cpu,
+            ReportPreset::Gpu,
+            ReportPreset::Memory,
+            ReportPreset::Network,
+            ReportPreset::Temperature,
+            ReportPreset::Disk,
+            ReportPreset::Containers,
+            ReportPreset::System,
+            ReportPreset::Derived,
+            ReportPreset::Collector,
+        ];
+    }
+    presets.sort();
+    presets.dedup();
+    presets
+}
+
+pub fn metric_kinds_for_presets(presets: &[ReportPreset]) -> Vec<MetricKind> {
+    let mut kinds = Vec::new();
+    for preset in presets {
+        kinds.extend_from_slice(preset_kinds(*preset));
+    }
+    kinds.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    kinds.dedup();
+    kinds
+}
+
+pub fn has_data_for_preset(preset: ReportPreset, metrics: &[MetricSample]) -> bool {
+    if preset == ReportPreset::All {
+        return !metrics.is_empty();
+    }
+    let kinds = preset_kinds(preset);
+    metrics.iter().any(|m| kinds.contains(&m.kind))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("No records available; collect data first.")]
+    NoRecords,
+    #[error(
+        "No records for the selected presets in {0}; try a broader timeframe or enable those collectors."
+    )]
+    NoDataForPresets(String),
+    #[error(transparent)]
+    Database(#[from] anyhow::Error),
+}
+
+/// Kinds compared with the period before the timeframe.
+const COMPARED_KINDS: [MetricKind; 4] = [
+    MetricKind::PowerDraw,
+    MetricKind::BatteryEnergyNow,
+    MetricKind::BatteryPercentage,
+    MetricKind::Temperature,
+];
+
+#[derive(Debug, Clone, Copy)]
+struct ComparisonPeriod {
+    since: f64,
+    until: f64,
+    label: &'static str,
+}
+
+/// Averages of the period before the timeframe, keyed by the timeframe bucket each lines
+/// up with, for the delta columns of the bucket tables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviousPeriod {
+    /// `last week` or `prev 30d`
+    pub label: &'static str,
+    /// Average discharge power per bucket, as in the battery table
+    pub discharge_w: TimeBuckets<f64>,
+    /// Average temperature per sensor and bucket
+    pub temperature: BTreeMap<String, TimeBuckets<f64>>,
+}
+
+/// Kinds whose only use in a report is their per-source bucket table. When SQLite computes
+/// those tables, only the span of each series is read instead of its rows.
+const SQL_BUCKETED_KINDS: [MetricKind; 6] = [
+    MetricKind::CpuUsage,
+    MetricKind::GpuFrequency,
+    MetricKind::TcpConnections,
+    MetricKind::ContainerMemory,
+    MetricKind::Voltage,
+    MetricKind::Current,
+];
+
+/// What [`ReportBuilder`] reads from one database: raw samples, collection intervals per
+/// kind name, machine snapshots and the spans of the series left to SQLite.
+type FetchedSamples = (
+    Vec<MetricSample>,
+    BTreeMap<String, f64>,
+    Vec<SystemSnapshot>,
+    Vec<(MetricKind, db::SeriesSpan)>,
+);
+
+/// Describes a report (timeframe, presets, filters) and computes it into a [`ReportResult`].
+#[derive(Debug, Clone)]
+pub struct ReportBuilder {
+    timeframe: Timeframe,
+    presets: Vec<ReportPreset>,
+    source_filters: Vec<String>,
+    hosts: Vec<String>,
+    temperature_sensors: Vec<String>,
+    temp_by_device: bool,
+    cpu_detail: Option<CpuDetail>,
+    temperature_top: Option<usize>,
+    throttle_temp_c: f64,
+    disk_full_days: f64,
+    bucket_seconds: Option<i64>,
+    derived: Vec<DerivedMetric>,
+    intervals: BTreeMap<String, f64>,
+    sections: Vec<ReportSection>,
+    per_battery: bool,
+    sessions: bool,
+    histograms: Vec<MetricKind>,
+    daily: bool,
+    keep_samples: bool,
+}
+
+impl ReportBuilder {
+    /// A battery report over `timeframe`, with every source included.
+    pub fn new(timeframe: Timeframe) -> Self {
+        Self {
+            timeframe,
+            presets: Vec::new(),
+            source_filters: Vec::new(),
+            hosts: Vec::new(),
+            temperature_sensors: Vec::new(),
+            temp_by_device: false,
+            cpu_detail: None,
+            temperature_top: None,
+            throttle_temp_c: DEFAULT_THROTTLE_TEMP_C,
+            disk_full_days: DEFAULT_DISK_FULL_DAYS,
+            bucket_seconds: None,
+            derived: Vec::new(),
+            intervals: BTreeMap::new(),
+            sections: Vec::new(),
+            per_battery: false,
+            sessions: false,
+            histograms: Vec::new(),
+            daily: false,
+            keep_samples: true,
+        }
+    }
+
+    /// Sections to compute; empty means battery, and `All` expands to every preset.
+    pub fn presets(mut self, presets: impl IntoIterator<Item = ReportPreset>) -> Self {
+        self.presets = presets.into_iter().collect();
+        self
+    }
+
+    /// Keep only samples whose source matches one of these names (all when empty).
+    pub fn source_filters(mut self, filters: impl IntoIterator<Item = String>) -> Self {
+        self.source_filters = filters.into_iter().collect();
+        self
+    }
+
+    /// Keep only samples collected on one of these hosts (all when empty).
+    pub fn hosts(mut self, hosts: impl IntoIterator<Item = String>) -> Self {
+        self.hosts = hosts.into_iter().collect();
+        self
+    }
+
+    /// Keep only temperature sources matching one of these patterns (`*` is a wildcard, e.g.
+    /// `coretemp:*`); other kinds are unaffected. All sensors are kept when empty.
+    pub fn temperature_sensors(mut self, patterns: impl IntoIterator<Item = String>) -> Self {
+        self.temperature_sensors = patterns.into_iter().collect();
+        self
+    }
+
+    /// Merge temperature sensors per device (`nvme0`, `coretemp`, ...).
+    pub fn temp_by_device(mut self, enabled: bool) -> Self {
+        self.temp_by_device = enabled;
+        self
+    }
+
+    /// Keep only the `count` temperature sensors with the highest peak in the timeframe.
+    pub fn temperature_top(mut self, count: Option<usize>) -> Self {
+        self.temperature_top = count;
+        self
+    }
+
+    /// Show only the CPU total or only the cores (both when `None`).
+    pub fn cpu_detail(mut self, detail: Option<CpuDetail>) -> Self {
+        self.cpu_detail = detail;
+        self
+    }
+
+    /// Keep each battery's samples apart instead of merging them into one combined battery,
+    /// and report every pack in [`ReportResult::battery_packs`].
+    pub fn per_battery(mut self, enabled: bool) -> Self {
+        self.per_battery = enabled;
+        self
+    }
+
+    /// List every charge and discharge session in [`BatteryReport::sessions`].
+    pub fn sessions(mut self, enabled: bool) -> Self {
+        self.sessions = enabled;
+        self
+    }
+
+    /// Bin the values of these kinds per source into [`ReportResult::histograms`].
+    pub fn histograms(mut self, kinds: impl IntoIterator<Item = MetricKind>) -> Self {
+        self.histograms = kinds.into_iter().collect();
+        self
+    }
+
+    /// Summarize every calendar day into [`ReportResult::daily`], fetching the kinds it
+    /// needs whatever the presets.
+    pub fn daily(mut self, enabled: bool) -> Self {
+        self.daily = enabled;
+        self
+    }
+
+    pub fn throttle_temp(mut self, celsius: f64) -> Self {
+        self.throttle_temp_c = celsius;
+        self
+    }
+
+    /// Flag mountpoints predicted to fill within this many days.
+    pub fn disk_full_days(mut self, days: f64) -> Self {
+        self.disk_full_days = days;
+        self
+    }
+
+    /// Width of the bucket tables, instead of one picked from the window length.
+    pub fn bucket_seconds(mut self, seconds: Option<i64>) -> Self {
+        self.bucket_seconds = seconds;
+        self
+    }
+
+    /// Derived metric definitions; those not materialized at collection time are computed
+    /// from the fetched samples for the `derived` preset.
+    pub fn derived(mut self, metrics: impl IntoIterator<Item = DerivedMetric>) -> Self {
+        self.derived = metrics.into_iter().collect();
+        self
+    }
+
+    /// Collection interval in seconds per kind name, used to flag undersampled series.
+    /// [`ReportBuilder::build`] reads the intervals recorded in the database instead.
+    pub fn collection_intervals(mut self, intervals: BTreeMap<String, f64>) -> Self {
+        self.intervals = intervals;
+        self
+    }
+
+    /// Sections that will be printed (all when empty). Per-window buckets are only computed
+    /// when [`ReportSection::Buckets`] is among them.
+    pub fn sections(mut self, sections: impl IntoIterator<Item = ReportSection>) -> Self {
+        self.sections = sections.into_iter().collect();
+        self
+    }
+
+    /// Keep every sample of the selected presets in [`ReportResult::samples`], as graphs
+    /// need. When off, [`ReportBuilder::build`] leaves the kinds that only feed bucket
+    /// tables to SQLite and does not read their rows. On by default.
+    pub fn keep_samples(mut self, enabled: bool) -> Self {
+        self.keep_samples = enabled;
+        self
+    }
+
+    fn computed_derived(&self, presets: &[ReportPreset]) -> Vec<&DerivedMetric> {
+        if !presets.contains(&ReportPreset::Derived) {
+            return Vec::new();
+        }
+        self.derived.iter().filter(|m| !m.materialize).collect()
+    }
+
+    /// Fetch the timeframe's samples for the selected presets and compute the report.
+    pub fn build(&self, store: &dyn Storage) -> Result<ReportResult, ReportError> {
+        if store.count_samples(None)? == 0 {
+            return Err(ReportError::NoRecords);
+        }
+        let (raw, intervals, machines, spans) = self.fetch(store, true)?;
+        let mut report = self.compute(&raw, &intervals, machines, &spans, store.sqlite())?;
+        if let Some(period) = self.comparison_period() {
+            let previous = self.fetch_previous(store, period)?;
+            report.previous = self.previous_period(&previous, period, report.bucket_seconds);
+        }
+        Ok(report)
+    }
+
+    /// Fetch the timeframe's samples from several databases (per-year or per-host files)
+    /// and compute one report over all of them. Each database comes with the host its
+    /// untagged samples are labelled with, see [`db::database_host`].
+    pub fn build_merged(
+        &self,
+        databases: &[(Box<dyn Storage>, String)],
+    ) -> Result<ReportResult, ReportError> {
+        if let [(store, _)] = databases {
+            return self.build(store.as_ref());
+        }
+        let mut raw = Vec::new();
+        let mut intervals = BTreeMap::new();
+        let mut machines = Vec::new();
+        let mut previous = Vec::new();
+        for (store, host) in databases {
+            let store = store.as_ref();
+            if store.count_samples(None)? == 0 {
+                continue;
+            }
+            let (samples, kind_intervals, snapshots, _) = self.fetch(store, false)?;
+            let tag = |mut sample: MetricSample| {
+                sample.host.get_or_insert_with(|| host.clone());
+                sample
+            };
+            raw.extend(samples.into_iter().map(tag));
+            if let Some(period) = self.comparison_period() {
+                previous.extend(self.fetch_previous(store, period)?.into_iter().map(tag));
+            }
+            for (kind, interval) in kind_intervals {
+                intervals.entry(kind).or_insert(interval);
+            }
+            machines.extend(snapshots);
+        }
+        if raw.is_empty() {
+            return Err(ReportError::NoRecords);
+        }
+        raw.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+        machines.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+        let mut report = self.compute(&raw, &intervals, machines, &[], None)?;
+        if let Some(period) = self.comparison_period() {
+            previous.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+            report.previous = self.previous_period(&previous, period, report.bucket_seconds);
+        }
+        Ok(report)
+    }
+
+    /// The period before the timeframe that its buckets are compared with: the week before
+    /// a 7-day timeframe or the 30 days before a 30-day one, when bucket tables are printed.
+    fn comparison_period(&self) -> Option<ComparisonPeriod> {
+        if !(self.sections.is_empty() || self.sections.contains(&ReportSection::Buckets)) {
+            return None;
+        }
+        let seconds = self.timeframe.seconds?;
+        let label = if seconds == 7.0 * 86400.0 {
+            "last week"
+        } else if seconds == 30.0 * 86400.0 {
+            "prev 30d"
+        } else {
+            return None;
+        };
+        let since = self.timeframe.since_timestamp(None)?;
+        Some(ComparisonPeriod {
+            since: since - seconds,
+            until: since,
+            label,
+        })
+    }
+
+    /// Power and temperature samples of the comparison period, read like [`Self::fetch`].
+    fn fetch_previous(
+        &self,
+        store: &dyn Storage,
+        period: ComparisonPeriod,
+    ) -> Result<Vec<MetricSample>, ReportError> {
+        let (since, until) = (Some(period.since), Some(period.until));
+        let mut samples = match (rollup_for(self.timeframe.seconds), store.sqlite()) {
+            (Some(rollup), Some(conn)) => {
+                let (rolled, raw_kinds): (Vec<MetricKind>, Vec<MetricKind>) = COMPARED_KINDS
+                    .iter()
+                    .cloned()
+                    .partition(MetricKind::reads_from_rollups);
+                let mut samples = fetch_with_rollups(conn, rollup, since, until, &rolled)?;
+                samples.extend(db::fetch_metric_samples_between(
+                    conn,
+                    since,
+                    until,
+                    Some(&raw_kinds),
+                )?);
+                samples
+            }
+            _ => store.fetch_samples(since, until, Some(&COMPARED_KINDS))?,
+        };
+        samples.retain(|sample| sample.ts < period.until);
+        samples.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+        Ok(samples)
+    }
+
+    /// Bucket averages of the comparison period under the report's filters, keyed by the
+    /// bucket of the timeframe they line up with.
+    fn previous_period(
+        &self,
+        raw: &[MetricSample],
+        period: ComparisonPeriod,
+        bucket_seconds: i64,
+    ) -> Option<PreviousPeriod> {
+        let shift = period.until - period.since;
+        let raw = filter_metrics_by_host(raw, &self.hosts);
+        let shifted: Vec<MetricSample> = crate::aggregate::aggregate_multi_device_metrics(&raw)
+            .into_iter()
+            .map(|mut sample| {
+                sample.ts += shift;
+                sample
+            })
+            .collect();
+        let samples =
+            self.filter_temperatures(filter_metrics_by_source(&shifted, &self.source_filters));
+        if samples.is_empty() {
+            return None;
+        }
+        let battery: Vec<MetricSample> = samples
+            .iter()
+            .filter(|m| m.kind.is_battery())
+            .cloned()
+            .collect();
+        let (discharge_rates, _) = battery_rate_buckets(&battery, bucket_seconds);
+        let mut discharge_w: TimeBuckets<f64> =
+            bucket_stats_for_kind(&samples, MetricKind::PowerDraw, bucket_seconds)
+                .into_iter()
+                .filter_map(|(bucket, stats)| Some((bucket, stats.average()?)))
+                .collect();
+        // Energy-based rates win over power_draw, as in the battery table
+        for (bucket, stats) in discharge_rates {
+            if let Some(avg) = stats.average() {
+                discharge_w.insert(bucket, avg);
+            }
+        }
+        let temperature =
+            bucket_stats_for_kind_by_source(&samples, MetricKind::Temperature, bucket_seconds)
+                .into_iter()
+                .map(|(source, buckets)| {
+                    let averages = buckets
+                        .into_iter()
+                        .filter_map(|(bucket, stats)| Some((bucket, stats.average()?)))
+                        .collect();
+                    (source, averages)
+                })
+                .collect();
+        Some(PreviousPeriod {
+            label: period.label,
+            discharge_w,
+            temperature,
+        })
+    }
+
+    /// `temperature_sensors` and `temp_by_device` applied to the temperature samples.
+    fn filter_temperatures(&self, mut samples: Vec<MetricSample>) -> Vec<MetricSample> {
+        if !self.temperature_sensors.is_empty() {
+            samples.retain(|m| {
+                m.kind != MetricKind::Temperature
+                    || self
+                        .temperature_sensors
+                        .iter()
+                        .any(|pattern| source_matches(pattern, &m.source))
+            });
+        }
+        if self.temp_by_device {
+            samples = group_temperature_by_device(samples);
+        }
+        samples
+    }
+
+    /// Raw samples of the selected presets in the timeframe (bucket averages from the
+    /// rollups of a local database for long ones), the collection intervals and the machine
+    /// snapshots. With `sql_buckets`, kinds that only feed bucket tables SQLite computes are
+    /// not read; their series spans come instead.
+    fn fetch(&self, store: &dyn Storage, sql_buckets: bool) -> Result<FetchedSamples, ReportError> {
+        let conn = store.sqlite();
+        let presets = normalize_presets(self.presets.clone());
+        let mut kinds = metric_kinds_for_presets(&presets);
+        let derived_kinds: Vec<MetricKind> = self
+            .computed_derived(&presets)
+            .into_iter()
+            .flat_map(|metric| metric.expr.kinds())
+            .collect();
+        kinds.extend(derived_kinds.iter().cloned());
+        if self.daily {
+            kinds.extend(DAILY_KINDS.iter().cloned());
+        }
+        kinds.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        kinds.dedup();
+        let since = self.timeframe.since_timestamp(None);
+        let until = self.timeframe.until;
+        let (in_sql, kinds): (Vec<MetricKind>, Vec<MetricKind>) =
+            kinds.into_iter().partition(|kind| {
+                sql_buckets
+                    && conn.is_some()
+                    && !self.keep_samples
+                    && SQL_BUCKETED_KINDS.contains(kind)
+                    && !self.histograms.contains(kind)
+                    && !derived_kinds.contains(kind)
+            });
+        let mut spans = Vec::new();
+        if let Some(conn) = conn {
+            for kind in in_sql {
+                let filter = self.sample_filter(&kind);
+                for span in db::fetch_series_spans(conn, &kind, &filter)? {
+                    spans.push((kind.clone(), span));
+                }
+            }
+        }
+        let mut intervals = store.collection_intervals()?;
+        let raw = match (rollup_for(self.timeframe.seconds), conn) {
+            (Some(rollup), Some(conn)) => {
+                let (rolled, raw_kinds): (Vec<MetricKind>, Vec<MetricKind>) =
+                    kinds.into_iter().partition(MetricKind::reads_from_rollups);
+                let mut raw = fetch_with_rollups(conn, rollup, since, until, &rolled)?;
+                raw.extend(db::fetch_metric_samples_between(
+                    conn,
+                    since,
+                    until,
+                    Some(&raw_kinds),
+                )?);
+                raw.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+                // Bucket averages are sparse by design, not undersampled
+                for kind in &rolled {
+                    intervals.remove(kind.as_str());
+                }
+                raw
+            }
+            _ => store.fetch_samples(since, until, Some(&kinds))?,
+        };
+        let mut machines = store.system_snapshots(since)?;
+        if let Some(until) = until {
+            machines.retain(|snapshot| snapshot.ts <= until);
+        }
+        Ok((raw, intervals, machines, spans))
+    }
+
+    /// Compute the report from already fetched (raw, per-device) samples.
+    pub fn build_from_samples(&self, raw: &[MetricSample]) -> Result<ReportResult, ReportError> {
+        self.compute(raw, &self.intervals, Vec::new(), &[], None)
+    }
+
+    /// The report's host, source, temperature and CPU filters over the stored rows of
+    /// `kind`, for SQLite to apply. `temperature_top` is left to the caller.
+    fn sample_filter(&self, kind: &MetricKind) -> db::SampleFilter<'_> {
+        let mut filter = db::SampleFilter {
+            since: self.timeframe.since_timestamp(None),
+            until: self.timeframe.until,
+            hosts: &self.hosts,
+            sources: vec![&self.source_filters],
+            ..db::SampleFilter::default()
+        };
+        match (kind, self.cpu_detail) {
+            (MetricKind::Temperature, _) => {
+                filter.sources.push(&self.temperature_sensors);
+                filter.by_device = self.temp_by_device;
+            }
+            (MetricKind::CpuUsage, Some(CpuDetail::Aggregate)) => filter.source = Some("cpu"),
+            (MetricKind::CpuUsage, Some(CpuDetail::PerCore)) => filter.exclude_source = Some("cpu"),
+            (MetricKind::CpuFrequency, Some(CpuDetail::Aggregate)) => {
+                filter.merge_sources = Some("cpu")
+            }
+            _ => {}
+        }
+        filter
+    }
+
+    /// `conn` is given when the samples come straight from that database; per-source bucket
+    /// tables are then computed in SQL instead of from `raw`, which lacks the kinds whose
+    /// series are only described by `spans`.
+    fn compute(
+        &self,
+        raw: &[MetricSample],
+        intervals: &BTreeMap<String, f64>,
+        machines: Vec<SystemSnapshot>,
+        spans: &[(MetricKind, db::SeriesSpan)],
+        conn: Option<&Connection>,
+    ) -> Result<ReportResult, ReportError> {
+        let presets = normalize_presets(self.presets.clone());
+        let raw = filter_metrics_by_host(raw, &self.hosts);
+        let mut aggregated = if self.per_battery {
+            raw.clone()
+        } else {
+            crate::aggregate::aggregate_multi_device_metrics(&raw)
+        };
+        let computed = derived::evaluate(self.computed_derived(&presets), &aggregated);
+        aggregated.extend(computed);
+        let mut samples =
+            self.filter_temperatures(filter_metrics_by_source(&aggregated, &self.source_filters));
+        if let Some(count) = self.temperature_top {
+            let hottest: Vec<String> = temperature_sensor_stats(&samples)
+                .into_iter()
+                .take(count)
+                .map(|(source, _)| source)
+                .collect();
+            samples.retain(|m| m.kind != MetricKind::Temperature || hottest.contains(&m.source));
+        }
+        if let Some(detail) = self.cpu_detail {
+            samples = apply_cpu_detail(samples, detail);
+        }
+        let spans_cover = |preset: ReportPreset| {
+            spans
+                .iter()
+                .any(|(kind, _)| preset == ReportPreset::All || preset_kinds(preset).contains(kind))
+        };
+        if !presets
+            .iter()
+            .any(|preset| has_data_for_preset(*preset, &samples) || spans_cover(*preset))
+        {
+            return Err(ReportError::NoDataForPresets(
+                self.timeframe.label.replace('_', " "),
+            ));
+        }
+
+        let bucket_seconds = self.bucket_seconds.unwrap_or_else(|| {
+            bucket_span_seconds(&self.timeframe, series_span_seconds(&samples, spans))
+        });
+        let wants = |preset: ReportPreset| presets.contains(&preset);
+        // Bucket tables left out of the output are computed from no samples, which is free.
+        let wants_buckets =
+            self.sections.is_empty() || self.sections.contains(&ReportSection::Buckets);
+        let bucketed: &[MetricSample] = if wants_buckets { &samples } else { &[] };
+        let by_source = |kind: MetricKind| -> Result<SourceBuckets, ReportError> {
+            match conn.filter(|_| wants_buckets) {
+                Some(conn) => {
+                    let filter = self.sample_filter(&kind);
+                    let mut buckets = sql_source_buckets(conn, &kind, bucket_seconds, &filter)?;
+                    if let (MetricKind::Temperature, Some(count)) = (&kind, self.temperature_top) {
+                        keep_hottest(&mut buckets, count);
+                    }
+                    Ok(buckets)
+                }
+                None => Ok(bucket_stats_for_kind_by_source(
+                    bucketed,
+                    kind,
+                    bucket_seconds,
+                )),
+            }
+        };
+        // The combined battery is still reported next to the packs
+        let combined = if self.per_battery && wants(ReportPreset::Battery) {
+            crate::aggregate::aggregate_multi_device_metrics(&samples)
+        } else {
+            Vec::new()
+        };
+        let (battery_samples, battery_bucketed) = match (self.per_battery, bucketed.is_empty()) {
+            (false, _) => (&samples[..], bucketed),
+            (true, false) => (&combined[..], &combined[..]),
+            (true, true) => (&combined[..], &[][..]),
+        };
+        Ok(ReportResult {
+            battery: wants(ReportPreset::Battery).then(|| {
+                BatteryReport::compute(
+                    battery_samples,
+                    battery_bucketed,
+                    bucket_seconds,
+                    self.sessions,
+                )
+            }),
+            battery_packs: if self.per_battery && wants(ReportPreset::Battery) {
+                battery_packs(&samples, !bucketed.is_empty(), bucket_seconds)
+            } else {
+                Vec::new()
+            },
+            cpu: wants(ReportPreset::Cpu)
+                .then(|| {
+                    CpuReport::compute(
+                        &samples,
+                        bucketed,
+                        bucket_seconds,
+                        self.throttle_temp_c,
+                        &by_source,
+                    )
+                })
+                .transpose()?,
+            gpu: wants(ReportPreset::Gpu)
+                .then(|| {
+                    Ok::<_, ReportError>(GpuReport {
+                        usage: by_source(MetricKind::GpuUsage)?,
+                        frequency: by_source(MetricKind::GpuFrequency)?,
+                        limits: gpu_limits(&samples),
+                    })
+                })
+                .transpose()?,
+            memory: wants(ReportPreset::Memory).then(|| MemoryReport {
+                buckets: bucket_usage_stats(bucketed, MetricKind::MemoryUsage, bucket_seconds),
+                leaks: detect_memory_leaks(&samples),
+            }),
+            disk: wants(ReportPreset::Disk).then(|| {
+                let forecasts = disk_forecasts(&samples);
+                DiskReport {
+                    buckets: bucket_usage_stats(bucketed, MetricKind::DiskUsage, bucket_seconds),
+                    mounts: disk_mounts(&samples, &forecasts, self.disk_full_days),
+                    forecasts,
+                }
+            }),
+            network: wants(ReportPreset::Network)
+                .then(|| {
+                    Ok::<_, ReportError>(NetworkReport {
+                        totals: bucket_network_totals(bucketed, bucket_seconds),
+                        wakeups: bucket_counter_deltas(
+                            bucketed,
+                            MetricKind::NetworkWakeups,
+                            bucket_seconds,
+                        ),
+                        tcp: by_source(MetricKind::TcpConnections)?,
+                    })
+                })
+                .transpose()?,
+            containers: wants(ReportPreset::Containers)
+                .then(|| {
+                    Ok::<_, ReportError>(ContainerReport {
+                        cpu: bucket_stats_for_kind_by_source(
+                            &container_cpu_percent(bucketed),
+                            MetricKind::ContainerCpu,
+                            bucket_seconds,
+                        ),
+                        memory: by_source(MetricKind::ContainerMemory)?,
+                    })
+                })
+                .transpose()?,
+            system: wants(ReportPreset::System).then(|| SystemReport {
+                health: bucket_system_health(bucketed, bucket_seconds),
+            }),
+            temperature: wants(ReportPreset::Temperature)
+                .then(|| {
+                    Ok::<_, ReportError>(TemperatureReport {
+                        sensors: temperature_sensor_stats(&samples),
+                        buckets: by_source(MetricKind::Temperature)?,
+                        voltage: by_source(MetricKind::Voltage)?,
+                        current: by_source(MetricKind::Current)?,
+                    })
+                })
+                .transpose()?,
+            derived: wants(ReportPreset::Derived).then(|| DerivedReport {
+                buckets: bucket_stats_for_kind_by_source(
+                    bucketed,
+                    MetricKind::Derived,
+                    bucket_seconds,
+                ),
+                units: samples
+                    .iter()
+                    .filter(|m| m.kind == MetricKind::Derived)
+                    .filter_map(|m| Some((m.source.clone(), m.unit.clone()?)))
+                    .collect(),
+            }),
+            collector: wants(ReportPreset::Collector)
+                .then(|| {
+                    Ok::<_, ReportError>(CollectorReport {
+                        costs: collector_costs(&samples),
+                        buckets: by_source(MetricKind::CollectorStats)?,
+                    })
+                })
+                .transpose()?,
+            distributions: distribution_summaries(&samples),
+            histograms: self
+                .histograms
+                .iter()
+                .flat_map(|kind| value_histograms(&samples, kind))
+                .collect(),
+            daily: if self.daily {
+                daily_summaries(&samples)
+            } else {
+                Vec::new()
+            },
+            previous: None,
+            states: state_summaries(&samples),
+            categories: category_summaries(&samples),
+            undersampled: undersampled_series(&samples, spans, intervals),
+            boots: boot_summaries(&raw),
+            machines,
+            timeframe: self.timeframe.clone(),
+            presets,
+            bucket_seconds,
+            record_count: raw.len() + spans.iter().map(|(_, span)| span.count).sum::<usize>(),
+            samples,
+        })
+    }
+}
+
+/// A computed report. Sections are `Some` exactly when their preset was selected.
+#[derive(Debug, Clone)]
+pub struct ReportResult {
+    pub timeframe: Timeframe,
+    pub presets: Vec<ReportPreset>,
+    /// Width of each summary window
+    pub bucket_seconds: i64,
+    /// Raw rows in the timeframe, before multi-device aggregation, fetched or counted by
+    /// SQLite
+    pub record_count: usize,
+    /// Aggregated and filtered series, as plotted in graphs
+    pub samples: Vec<MetricSample>,
+    pub battery: Option<BatteryReport>,
+    /// Each battery on its own, only filled with [`ReportBuilder::per_battery`]
+    pub battery_packs: Vec<BatteryPack>,
+    pub cpu: Option<CpuReport>,
+    pub gpu: Option<GpuReport>,
+    pub memory: Option<MemoryReport>,
+    pub disk: Option<DiskReport>,
+    pub network: Option<NetworkReport>,
+    pub containers: Option<ContainerReport>,
+    pub system: Option<SystemReport>,
+    pub temperature: Option<TemperatureReport>,
+    pub derived: Option<DerivedReport>,
+    pub collector: Option<CollectorReport>,
+    /// Percentiles of every histogram-carrying series in the report
+    pub distributions: Vec<DistributionSummary>,
+    /// Value distributions of the kinds given to [`ReportBuilder::histograms`]
+    pub histograms: Vec<ValueHistogram>,
+    /// One row per calendar day, oldest first; only filled with [`ReportBuilder::daily`]
+    pub daily: Vec<DailySummary>,
+    /// Bucket averages of the week or 30 days before a 7- or 30-day timeframe
+    pub previous: Option<PreviousPeriod>,
+    /// Share of time each on/off series spent on
+    pub states: Vec<StateSummary>,
+    /// Time each categorical series spent per label
+    pub categories: Vec<CategorySummary>,
+    /// Series with far fewer samples than their collection interval implies, whose
+    /// aggregates rest on little data
+    pub undersampled: Vec<SamplingSummary>,
+    /// Kernel boots seen in the timeframe, oldest first
+    pub boots: Vec<BootSummary>,
+    /// Machine identity snapshots in effect during the timeframe, oldest first
+    pub machines: Vec<SystemSnapshot>,
+}
+
+pub type TimeBuckets<T> = BTreeMap<DateTime<Local>, T>;
+
+#[derive(Debug, Clone)]
+pub struct BatteryReport {
+    /// Battery level, capacity and energy samples
+    pub samples: Vec<MetricSample>,
+    pub avg_discharge_w: Option<f64>,
+    pub avg_charge_w: Option<f64>,
+    pub est_runtime_hours: Option<f64>,
+    pub power_draw: TimeBuckets<NumberStats>,
+    pub discharge_rates: TimeBuckets<NumberStats>,
+    pub charge_rates: TimeBuckets<NumberStats>,
+    pub discharge_deciles: Vec<DecileRate>,
+    pub activity: Vec<ActivityDrain>,
+    pub keyboard_backlight: Vec<BacklightDrain>,
+    pub charge_curves: Vec<ChargeCurve>,
+    pub charge_deciles: Vec<DecileRate>,
+    /// Charging split by negotiated charger wattage, strongest charger first
+    pub chargers: Vec<ChargerRate>,
+    pub peripherals: Vec<PeripheralSummary>,
+    /// Busiest wakeup sources, most events first
+    pub wakeup_sources: Vec<WakeupSourceSummary>,
+    /// Charge and discharge sessions, oldest first; only filled with
+    /// [`ReportBuilder::sessions`]
+    pub sessions: Vec<BatterySession>,
+    /// When the machine was last unplugged and how that run went
+    pub last_discharge: Option<LastDischarge>,
+    /// When the battery was last full and last empty
+    pub charge_extremes: ChargeExtremes,
+}
+
+impl BatteryReport {
+    /// `bucketed` is `metrics`, or empty when the per-window tables are not wanted.
+    fn compute(
+        metrics: &[MetricSample],
+        bucketed: &[MetricSample],
+        bucket_seconds: i64,
+        sessions: bool,
+    ) -> Self {
+        let samples: Vec<MetricSample> = metrics
+            .iter()
+            .filter(|m| m.kind.is_battery())
+            .cloned()
+            .collect();
+        let rates = average_rates(&samples);
+        let avg_discharge_w = average_for_kind(metrics, MetricKind::PowerDraw)
+            .average()
+            .or(rates.discharge_w);
+        let (discharge_rates, charge_rates) = if bucketed.is_empty() {
+            Default::default()
+        } else {
+            battery_rate_buckets(&samples, bucket_seconds)
+        };
+        Self {
+            avg_discharge_w,
+            avg_charge_w: rates.charge_w,
+            est_runtime_hours: estimate_runtime_hours(avg_discharge_w, &samples),
+            power_draw: bucket_stats_for_kind(bucketed, MetricKind::PowerDraw, bucket_seconds),
+            discharge_rates,
+            charge_rates,
+            discharge_deciles: soc_decile_rates(&rate_sessions(&samples, Direction::Discharging)),
+            activity: discharge_by_activity(metrics),
+            keyboard_backlight: discharge_by_keyboard_backlight(metrics),
+            charge_curves: charge_curves(&samples),
+            charge_deciles: soc_decile_rates(&rate_sessions(&samples, Direction::Charging)),
+            chargers: charge_by_charger(metrics),
+            peripherals: peripheral_summaries(metrics),
+            wakeup_sources: top_wakeup_sources(metrics, TOP_WAKEUP_SOURCES),
+            sessions: if sessions {
+                battery_sessions(&samples)
+            } else {
+                Vec::new()
+            },
+            last_discharge: last_discharge(&samples),
+            charge_extremes: charge_extremes(&samples),
+            samples,
+        }
+    }
+}
+
+/// One battery of a multi-battery machine (e.g. the internal and hot-swap packs of a ThinkPad).
+#[derive(Debug, Clone)]
+pub struct BatteryPack {
+    pub source: String,
+    /// This pack's level, capacity and energy samples
+    pub samples: Vec<MetricSample>,
+    pub latest_percent: Option<f64>,
+    pub health: Option<f64>,
+    pub energy_full_wh: Option<f64>,
+    pub energy_full_design_wh: Option<f64>,
+    pub avg_discharge_w: Option<f64>,
+    pub avg_charge_w: Option<f64>,
+    pub discharge_rates: TimeBuckets<NumberStats>,
+    pub charge_rates: TimeBuckets<NumberStats>,
+}
+
+fn battery_packs(metrics: &[MetricSample], buckets: bool, bucket_seconds: i64) -> Vec<BatteryPack> {
+    let mut by_source: BTreeMap<&str, Vec<MetricSample>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind.is_battery()) {
+        by_source
+            .entry(&sample.source)
+            .or_default()
+            .push(sample.clone());
+    }
+    by_source
+        .into_iter()
+        .map(|(source, samples)| {
+            let latest = |kind: MetricKind| {
+                samples
+                    .iter()
+                    .rev()
+                    .find(|m| m.kind == kind && m.value.is_some())
+                    .and_then(|m| m.value)
+            };
+            let energy_full_wh = latest(MetricKind::BatteryEnergyFull);
+            let energy_full_design_wh = latest(MetricKind::BatteryEnergyFullDesign);
+            let health = latest(MetricKind::BatteryHealth).or_else(|| {
+                let (full, design) = (energy_full_wh?, energy_full_design_wh?);
+                (design > 0.0).then(|| full / design * 100.0)
+            });
+            let rates = average_rates(&samples);
+            let (discharge_rates, charge_rates) = if buckets {
+                battery_rate_buckets(&samples, bucket_seconds)
+            } else {
+                Default::default()
+            };
+            BatteryPack {
+                source: source.to_string(),
+                latest_percent: latest(MetricKind::BatteryPercentage),
+                health,
+                energy_full_wh,
+                energy_full_design_wh,
+                avg_discharge_w: rates.discharge_w,
+                avg_charge_w: rates.charge_w,
+                discharge_rates,
+                charge_rates,
+                samples,
+            }
+        })
+        .collect()
+}
+
+/// Latest state of a Bluetooth/HID peripheral battery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeripheralSummary {
+    pub device: String,
+    pub latest_percent: Option<f64>,
+    pub min_percent: Option<f64>,
+    pub status: Option<String>,
+    pub last_seen: f64,
+}
+
+fn peripheral_summaries(metrics: &[MetricSample]) -> Vec<PeripheralSummary> {
+    let mut by_device: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::PeripheralBattery)
+    {
+        by_device.entry(&sample.source).or_default().push(sample);
+    }
+    by_device
+        .into_iter()
+        .filter_map(|(device, samples)| {
+            let latest = samples
+                .iter()
+                .max_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap())?;
+            let mut stats = NumberStats::default();
+            for sample in &samples {
+                stats.record_opt(sample.value);
+            }
+            Some(PeripheralSummary {
+                device: device.to_string(),
+                latest_percent: latest.value,
+                min_percent: stats.min(),
+                status: latest.status.as_deref().map(str::to_string),
+                last_seen: latest.ts,
+            })
+        })
+        .collect()
+}
+
+const TOP_WAKEUP_SOURCES: usize = 10;
+
+/// Events one kernel wakeup source signalled in the timeframe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WakeupSourceSummary {
+    pub source: String,
+    pub events: f64,
+    /// Events per hour over the span the source was sampled
+    pub per_hour: Option<f64>,
+}
+
+/// Wakeup sources ranked by the increase of their event counters, skipping counter resets
+/// across reboots.
+fn top_wakeup_sources(metrics: &[MetricSample], limit: usize) -> Vec<WakeupSourceSummary> {
+    let mut summaries: Vec<WakeupSourceSummary> =
+        series_by_source(metrics, |m| m.kind == MetricKind::WakeupEvents)
+            .into_iter()
+            .filter_map(|((_, source), points)| {
+                let events: f64 = points
+                    .windows(2)
+                    .map(|w| counter_delta(w[0].value, w[1].value))
+                    .sum();
+                let span = points.last()?.ts - points.first()?.ts;
+                (events > 0.0).then(|| WakeupSourceSummary {
+                    source: source.to_string(),
+                    events,
+                    per_hour: (span > 0.0).then(|| events / span * 3600.0),
+                })
+            })
+            .collect();
+    summaries.sort_by(|a, b| b.events.total_cmp(&a.events));
+    summaries.truncate(limit);
+    summaries
+}
+
+#[derive(Debug, Clone)]
+pub struct CpuReport {
+    pub usage: SourceBuckets,
+    pub frequency: SourceBuckets,
+    pub governors: GovernorBuckets,
+    pub throttle_events: SourceCounterBuckets,
+    /// Context switches per second
+    pub context_switches: TimeBuckets<NumberStats>,
+    /// Interrupts per second
+    pub interrupts: TimeBuckets<NumberStats>,
+    /// Share of CPU time waiting for I/O
+    pub iowait: TimeBuckets<NumberStats>,
+    /// Share of CPU time stolen by the hypervisor
+    pub steal: TimeBuckets<NumberStats>,
+    /// Share of CPU time per cpuidle state, as `%` samples
+    pub idle_residency: Vec<MetricSample>,
+    pub thermal_throttle: Option<ThrottleSummary>,
+}
+
+impl CpuReport {
+    fn compute(
+        metrics: &[MetricSample],
+        bucketed: &[MetricSample],
+        bucket_seconds: i64,
+        throttle_temp_c: f64,
+        by_source: &dyn Fn(MetricKind) -> Result<SourceBuckets, ReportError>,
+    ) -> Result<Self, ReportError> {
+        Ok(Self {
+            usage: by_source(MetricKind::CpuUsage)?,
+            frequency: by_source(MetricKind::CpuFrequency)?,
+            governors: bucket_governors(bucketed, bucket_seconds),
+            throttle_events: bucket_counter_deltas(
+                bucketed,
+                MetricKind::CpuThrottle,
+                bucket_seconds,
+            ),
+            context_switches: bucket_stats_for_kind(
+                &counter_rate_samples(bucketed, MetricKind::ContextSwitches),
+                MetricKind::ContextSwitches,
+                bucket_seconds,
+            ),
+            interrupts: bucket_stats_for_kind(
+                &counter_rate_samples(bucketed, MetricKind::Interrupts),
+                MetricKind::Interrupts,
+                bucket_seconds,
+            ),
+            iowait: bucket_stats_for_kind(bucketed, MetricKind::CpuIowait, bucket_seconds),
+            steal: bucket_stats_for_kind(bucketed, MetricKind::CpuSteal, bucket_seconds),
+            idle_residency: cpu_idle_residency_percent(bucketed),
+            thermal_throttle: thermal_throttle_summary(metrics, throttle_temp_c),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GpuReport {
+    pub usage: SourceBuckets,
+    pub frequency: SourceBuckets,
+    /// Cards reporting power cap or temperature limits (amdgpu)
+    pub limits: Vec<GpuLimitSummary>,
+}
+
+/// Power draw at or above this share of the cap counts as running at the power limit.
+const GPU_POWER_LIMIT_RATIO: f64 = 0.95;
+/// Temperatures within this many degrees of the critical one count as thermally limited.
+const GPU_THERMAL_MARGIN_C: f64 = 5.0;
+
+/// How often a GPU ran into its power cap or close to its thermal limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuLimitSummary {
+    pub card: String,
+    /// Usage samples carrying limit readings
+    pub samples: usize,
+    pub power_limited: usize,
+    pub thermal_limited: usize,
+    pub avg_fan_percent: Option<f64>,
+    pub max_temp_c: Option<f64>,
+}
+
+impl GpuLimitSummary {
+    pub fn power_limited_percent(&self) -> f64 {
+        self.power_limited as f64 / self.samples as f64 * 100.0
+    }
+
+    pub fn thermal_limited_percent(&self) -> f64 {
+        self.thermal_limited as f64 / self.samples as f64 * 100.0
+    }
+}
+
+fn gpu_limits(metrics: &[MetricSample]) -> Vec<GpuLimitSummary> {
+    let mut by_card: BTreeMap<&str, (GpuLimitSummary, NumberStats, NumberStats)> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::GpuUsage) {
+        let detail = |key: &str| sample.details.get(key).and_then(|v| v.as_f64());
+        let (power, cap) = (detail("power_w"), detail("power_cap_w"));
+        let (temp, temp_limit) = (detail("temp_c"), detail("temp_limit_c"));
+        if cap.is_none() && temp_limit.is_none() {
+            continue;
+        }
+        let (summary, fan, temps) = by_card.entry(&sample.source).or_insert_with(|| {
+            let summary = GpuLimitSummary {
+                card: sample.source.clone(),
+                samples: 0,
+                power_limited: 0,
+                thermal_limited: 0,
+                avg_fan_percent: None,
+                max_temp_c: None,
+            };
+            (summary, NumberStats::default(), NumberStats::default())
+        });
+        summary.samples += 1;
+        if let (Some(power), Some(cap)) = (power, cap) {
+            summary.power_limited += usize::from(power >= cap * GPU_POWER_LIMIT_RATIO);
+        }
+        if let (Some(temp), Some(limit)) = (temp, temp_limit) {
+            summary.thermal_limited += usize::from(temp >= limit - GPU_THERMAL_MARGIN_C);
+        }
+        fan.record_opt(detail("fan_pwm_percent"));
+        temps.record_opt(temp);
+    }
+    by_card
+        .into_values()
+        .map(|(summary, fan, temps)| GpuLimitSummary {
+            avg_fan_percent: fan.average(),
+            max_temp_c: temps.max(),
+            ..summary
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryReport {
+    pub buckets: TimeBuckets<UsageStats>,
+    pub leaks: Vec<LeakPeriod>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiskReport {
+    pub buckets: TimeBuckets<UsageStats>,
+    pub mounts: Vec<DiskMount>,
+    pub forecasts: Vec<DiskForecast>,
+}
+
+/// Latest usage of one mountpoint and its growth over the timeframe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskMount {
+    pub source: String,
+    pub used_bytes: f64,
+    pub total_bytes: Option<f64>,
+    /// Fitted trend; `None` without a day of history
+    pub growth_bytes_per_day: Option<f64>,
+    pub days_until_full: Option<f64>,
+    /// Predicted to fill within [`ReportBuilder::disk_full_days`]
+    pub filling: bool,
+}
+
+impl DiskMount {
+    pub fn used_percent(&self) -> Option<f64> {
+        self.total_bytes
+            .filter(|total| *total > 0.0)
+            .map(|total| self.used_bytes / total * 100.0)
+    }
+}
+
+/// One [`DiskMount`] per disk usage source, from its newest sample and its forecast.
+fn disk_mounts(
+    metrics: &[MetricSample],
+    forecasts: &[DiskForecast],
+    full_days: f64,
+) -> Vec<DiskMount> {
+    let mut latest: BTreeMap<&str, &MetricSample> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::DiskUsage && m.value.is_some())
+    {
+        if latest
+            .get(sample.source.as_str())
+            .is_none_or(|seen| seen.ts <= sample.ts)
+        {
+            latest.insert(&sample.source, sample);
+        }
+    }
+    latest
+        .into_iter()
+        .map(|(source, sample)| {
+            let forecast = forecasts.iter().find(|f| f.source == source);
+            let days_until_full = forecast.and_then(DiskForecast::days_until_full);
+            DiskMount {
+                source: source.to_string(),
+                used_bytes: sample.value.unwrap_or_default(),
+                total_bytes: sample.total_bytes,
+                growth_bytes_per_day: forecast.map(|f| f.growth_bytes_per_day),
+                days_until_full,
+                filling: days_until_full.is_some_and(|days| days <= full_days),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkReport {
+    pub totals: TimeBuckets<TransferStats>,
+    pub wakeups: SourceCounterBuckets,
+    /// Socket counts per TCP state
+    pub tcp: SourceBuckets,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContainerReport {
+    /// CPU usage in percent of one core
+    pub cpu: SourceBuckets,
+    pub memory: SourceBuckets,
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemReport {
+    pub health: TimeBuckets<HealthBucket>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TemperatureReport {
+    /// Whole-timeframe stats per sensor, hottest peak first
+    pub sensors: Vec<(String, NumberStats)>,
+    pub buckets: SourceBuckets,
+    /// hwmon voltage rails, in volts
+    pub voltage: SourceBuckets,
+    /// hwmon current sensors, in amps
+    pub current: SourceBuckets,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectorReport {
+    /// Cost per collector group, most expensive first, with the whole run last
+    pub costs: Vec<CollectorCost>,
+    /// Milliseconds per collector group
+    pub buckets: SourceBuckets,
+}
+
+/// What one collector group cost over the timeframe.
+#[derive(Debug, Clone)]
+pub struct CollectorCost {
+    /// Collector group, or `total` for whole runs
+    pub collector: String,
+    /// Milliseconds per run
+    pub duration: NumberStats,
+    /// Samples produced per run (written, for `total`)
+    pub samples: NumberStats,
+    /// CPU milliseconds per run; only recorded for `total`
+    pub cpu: NumberStats,
+}
+
+fn collector_costs(metrics: &[MetricSample]) -> Vec<CollectorCost> {
+    let mut costs: BTreeMap<&str, CollectorCost> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::CollectorStats)
+    {
+        let cost = costs
+            .entry(&sample.source)
+            .or_insert_with(|| CollectorCost {
+                collector: sample.source.clone(),
+                duration: NumberStats::default(),
+                samples: NumberStats::default(),
+                cpu: NumberStats::default(),
+            });
+        cost.duration.record_opt(sample.value);
+        cost.samples
+            .record_opt(sample.details.get("samples").and_then(|v| v.as_f64()));
+        cost.cpu
+            .record_opt(sample.details.get("cpu_ms").and_then(|v| v.as_f64()));
+    }
+    let mut costs: Vec<CollectorCost> = costs.into_values().collect();
+    costs.sort_by(|a, b| {
+        (a.collector == "total")
+            .cmp(&(b.collector == "total"))
+            .then_with(|| {
+                let avg = |cost: &CollectorCost| cost.duration.average().unwrap_or(0.0);
+                avg(b).total_cmp(&avg(a))
+            })
+    });
+    costs
+}
+
+#[derive(Debug, Clone)]
+pub struct DerivedReport {
+    /// Buckets per derived metric name
+    pub buckets: SourceBuckets,
+    pub units: BTreeMap<String, String>,
+}
+
+/// Time one on/off series (AC plugged in, link up, lid closed, ...) spent in each state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateSummary {
+    pub kind: MetricKind,
+    pub source: String,
+    pub on_seconds: f64,
+    /// Time covered by samples, excluding gaps in collection
+    pub observed_seconds: f64,
+    pub transitions: usize,
+    /// State at the last sample
+    pub latest: bool,
+}
+
+impl StateSummary {
+    pub fn on_percent(&self) -> Option<f64> {
+        (self.observed_seconds > 0.0).then(|| self.on_seconds / self.observed_seconds * 100.0)
+    }
+}
+
+/// Samples of each (kind, source) series matching `keep`, oldest first.
+fn series_by_source(
+    metrics: &[MetricSample],
+    keep: impl Fn(&MetricSample) -> bool,
+) -> BTreeMap<(&str, &str), Vec<&MetricSample>> {
+    let mut series: BTreeMap<(&str, &str), Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| keep(m)) {
+        series
+            .entry((sample.kind.as_str(), sample.source.as_str()))
+            .or_default()
+            .push(sample);
+    }
+    for points in series.values_mut() {
+        points.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    }
+    series
+}
+
+/// Consecutive sample pairs with the seconds between them. Each interval counts toward the
+/// earlier sample; intervals over three times the typical spacing are gaps in collection
+/// (suspend, collector stopped) where the state is unknown, and are reported as `None`.
+fn timed_intervals<'a>(
+    points: &[&'a MetricSample],
+) -> Vec<(&'a MetricSample, &'a MetricSample, Option<f64>)> {
+    let mut gaps: Vec<f64> = points.windows(2).map(|w| w[1].ts - w[0].ts).collect();
+    gaps.sort_by(f64::total_cmp);
+    let max_gap = gaps.get(gaps.len() / 2).map_or(0.0, |median| median * 3.0);
+    points
+        .windows(2)
+        .map(|w| {
+            let dt = w[1].ts - w[0].ts;
+            (w[0], w[1], (dt <= max_gap).then_some(dt))
+        })
+        .collect()
+}
+
+pub(crate) fn state_summaries(metrics: &[MetricSample]) -> Vec<StateSummary> {
+    let is_on = |sample: &MetricSample| sample.value.is_some_and(|v| v > 0.5);
+    series_by_source(metrics, |m| m.kind.is_state() && m.value.is_some())
+        .into_values()
+        .filter_map(|points| {
+            let (mut on_seconds, mut observed_seconds, mut transitions) = (0.0, 0.0, 0);
+            for (prev, next, dt) in timed_intervals(&points) {
+                if is_on(prev) != is_on(next) {
+                    transitions += 1;
+                }
+                if let Some(dt) = dt {
+                    observed_seconds += dt;
+                    if is_on(prev) {
+                        on_seconds += dt;
+                    }
+                }
+            }
+            let latest = *points.last()?;
+            Some(StateSummary {
+                kind: latest.kind.clone(),
+                source: latest.source.clone(),
+                on_seconds,
+                observed_seconds,
+                transitions,
+                latest: is_on(latest),
+            })
+        })
+        .collect()
+}
+
+/// Time one categorical series (governor, power profile, battery status) spent per label.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorySummary {
+    pub kind: MetricKind,
+    pub source: String,
+    /// Seconds per label, excluding gaps in collection
+    pub seconds: BTreeMap<String, f64>,
+    pub transitions: usize,
+    /// Label at the last sample
+    pub latest: String,
+}
+
+impl CategorySummary {
+    pub fn observed_seconds(&self) -> f64 {
+        self.seconds.values().sum()
+    }
+}
+
+pub(crate) fn category_summaries(metrics: &[MetricSample]) -> Vec<CategorySummary> {
+    series_by_source(metrics, |m| m.kind.is_categorical() && m.label.is_some())
+        .into_values()
+        .filter_map(|points| {
+            let mut seconds: BTreeMap<String, f64> = BTreeMap::new();
+            let mut transitions = 0;
+            for (prev, next, dt) in timed_intervals(&points) {
+                if prev.label != next.label {
+                    transitions += 1;
+                }
+                if let (Some(dt), Some(label)) = (dt, &prev.label) {
+                    *seconds.entry(label.clone()).or_default() += dt;
+                }
+            }
+            let latest = *points.last()?;
+            Some(CategorySummary {
+                kind: latest.kind.clone(),
+                source: latest.source.clone(),
+                seconds,
+                transitions,
+                latest: latest.label.clone()?,
+            })
+        })
+        .collect()
+}
+
+/// Share of the expected samples a series needs before its aggregates are trusted.
+const MIN_SAMPLING_COVERAGE: f64 = 0.5;
+
+/// Samples stored for one series against what its collection interval implies over the
+/// span it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplingSummary {
+    pub kind: MetricKind,
+    pub source: String,
+    /// Configured seconds between samples
+    pub interval: f64,
+    pub samples: usize,
+    pub expected: usize,
+}
+
+impl SamplingSummary {
+    pub fn coverage(&self) -> f64 {
+        self.samples as f64 / self.expected.max(1) as f64
+    }
+}
+
+/// Series whose kind has a recorded collection interval but that hold less than
+/// `MIN_SAMPLING_COVERAGE` of the samples it implies, e.g. a collector that keeps timing
+/// out or a machine that was mostly suspended. `spans` describe series whose rows were not
+/// fetched.
+pub(crate) fn undersampled_series(
+    metrics: &[MetricSample],
+    spans: &[(MetricKind, db::SeriesSpan)],
+    intervals: &BTreeMap<String, f64>,
+) -> Vec<SamplingSummary> {
+    let mut series: BTreeMap<(&str, &str), (&MetricKind, f64, f64, usize)> =
+        series_by_source(metrics, |m| intervals.contains_key(m.kind.as_str()))
+            .into_iter()
+            .filter_map(|(key, points)| {
+                let (first, last) = (*points.first()?, *points.last()?);
+                Some((key, (&first.kind, first.ts, last.ts, points.len())))
+            })
+            .collect();
+    for (kind, span) in spans {
+        let key = (kind.as_str(), span.source.as_str());
+        series.insert(key, (kind, span.first, span.last, span.count));
+    }
+    series
+        .into_iter()
+        .filter_map(|((_, source), (kind, first, last, samples))| {
+            let interval = *intervals.get(kind.as_str())?;
+            if interval <= 0.0 {
+                return None;
+            }
+            let summary = SamplingSummary {
+                kind: kind.clone(),
+                source: source.to_string(),
+                interval,
+                samples,
+                expected: ((last - first) / interval).floor() as usize + 1,
+            };
+            (summary.coverage() < MIN_SAMPLING_COVERAGE).then_some(summary)
+        })
+        .collect()
+}
+
+/// Collection runs of one kernel boot within the timeframe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootSummary {
+    /// Kernel `boot_id`
+    pub boot: String,
+    /// First and last collection of the boot
+    pub start_ts: f64,
+    pub end_ts: f64,
+    pub runs: usize,
+    /// Breaks between runs of over three typical intervals; within one boot these are
+    /// suspends (or a stopped collector), never reboots
+    pub suspends: usize,
+    pub suspended_seconds: f64,
+}
+
+impl BootSummary {
+    /// Span of the boot without its suspends.
+    pub fn awake_seconds(&self) -> f64 {
+        (self.end_ts - self.start_ts - self.suspended_seconds).max(0.0)
+    }
+}
+
+/// One summary per boot recorded on the samples, oldest first; samples stored before boots
+/// were recorded are left out.
+pub(crate) fn boot_summaries(metrics: &[MetricSample]) -> Vec<BootSummary> {
+    let mut runs: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for sample in metrics {
+        if let Some(boot) = &sample.boot {
+            runs.entry(boot).or_default().push(sample.ts);
+        }
+    }
+    let mut spacings = Vec::new();
+    for timestamps in runs.values_mut() {
+        timestamps.sort_by(f64::total_cmp);
+        timestamps.dedup();
+        spacings.extend(timestamps.windows(2).map(|w| w[1] - w[0]));
+    }
+    spacings.sort_by(f64::total_cmp);
+    let max_gap = spacings
+        .get(spacings.len() / 2)
+        .map_or(0.0, |median| median * 3.0);
+    let mut boots: Vec<BootSummary> = runs
+        .into_iter()
+        .filter_map(|(boot, timestamps)| {
+            let suspends: Vec<f64> = timestamps
+                .windows(2)
+                .map(|w| w[1] - w[0])
+                .filter(|dt| *dt > max_gap)
+                .collect();
+            Some(BootSummary {
+                boot: boot.to_string(),
+                start_ts: *timestamps.first()?,
+                end_ts: *timestamps.last()?,
+                runs: timestamps.len(),
+                suspends: suspends.len(),
+                suspended_seconds: suspends.iter().sum(),
+            })
+        })
+        .collect();
+    boots.sort_by(|a, b| a.start_ts.total_cmp(&b.start_ts));
+    boots
+}
+
+/// Percentiles over all histograms of one series in the timeframe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistributionSummary {
+    pub kind: MetricKind,
+    pub source: String,
+    pub unit: Option<String>,
+    /// Samples whose histograms were merged
+    pub samples: usize,
+    pub observations: u64,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+/// Bins of a histogram table; percentages always span 0–100.
+pub const VALUE_HISTOGRAM_BINS: usize = 10;
+
+/// How the values of one series spread over the timeframe, in equal-width bins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueHistogram {
+    pub kind: MetricKind,
+    pub source: String,
+    pub unit: Option<String>,
+    /// `(lower, upper, samples)`; the last bin includes its upper bound
+    pub bins: Vec<(f64, f64, usize)>,
+}
+
+impl ValueHistogram {
+    pub fn total(&self) -> usize {
+        self.bins.iter().map(|(_, _, count)| count).sum()
+    }
+}
+
+/// One [`ValueHistogram`] per source of `kind` with values.
+pub fn value_histograms(metrics: &[MetricSample], kind: &MetricKind) -> Vec<ValueHistogram> {
+    let mut by_source: BTreeMap<&str, (Option<&str>, Vec<f64>)> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| &m.kind == kind) {
+        if let Some(value) = sample.value.filter(|v| v.is_finite()) {
+            let entry = by_source.entry(&sample.source).or_default();
+            entry.0 = entry.0.or(sample.unit.as_deref());
+            entry.1.push(value);
+        }
+    }
+    by_source
+        .into_iter()
+        .map(|(source, (unit, values))| {
+            let (low, high) = if unit == Some("%") {
+                (0.0, 100.0)
+            } else {
+                let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+                let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                (low, high)
+            };
+            let bins = if high > low { VALUE_HISTOGRAM_BINS } else { 1 };
+            let width = (high - low) / bins as f64;
+            let mut counts = vec![0; bins];
+            for value in values {
+                let index = if width > 0.0 {
+                    ((value - low) / width)
+                        .floor()
+                        .clamp(0.0, (bins - 1) as f64) as usize
+                } else {
+                    0
+                };
+                counts[index] += 1;
+            }
+            ValueHistogram {
+                kind: kind.clone(),
+                source: source.to_string(),
+                unit: unit.map(str::to_string),
+                bins: counts
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, count)| {
+                        let lower = low + width * i as f64;
+                        (lower, if width > 0.0 { lower + width } else { high }, count)
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Merge the histograms per kind and source. When the bucket layout changes mid-window the
+/// newer layout wins, since percentiles cannot be read across different buckets.
+pub(crate) fn distribution_summaries(metrics: &[MetricSample]) -> Vec<DistributionSummary> {
+    let mut merged: BTreeMap<(&str, &str), (&MetricSample, Histogram, usize)> = BTreeMap::new();
+    for sample in metrics {
+        let Some(histogram) = &sample.histogram else {
+            continue;
+        };
+        let key = (sample.kind.as_str(), sample.source.as_str());
+        match merged.get_mut(&key) {
+            Some((latest, total, count)) => {
+                if !total.merge(histogram) {
+                    *total = histogram.clone();
+                    *count = 0;
+                }
+                *latest = sample;
+                *count += 1;
+            }
+            None => {
+                merged.insert(key, (sample, histogram.clone(), 1));
+            }
+        }
+    }
+    merged
+        .into_values()
+        .map(|(latest, histogram, samples)| DistributionSummary {
+            kind: latest.kind.clone(),
+            source: latest.source.clone(),
+            unit: latest.unit.clone(),
+            samples,
+            observations: histogram.total(),
+            p50: histogram.quantile(0.5),
+            p90: histogram.quantile(0.9),
+            p99: histogram.quantile(0.99),
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct NumberStats {
+    total: f64,
+    pub count: usize,
+    min: f64,
+    max: f64,
+}
+
+impl NumberStats {
+    pub(crate) fn record(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.total += value;
+        self.count += 1;
+    }
+
+    pub(crate) fn record_opt(&mut self, value: Option<f64>) {
+        if let Some(v) = value {
+            self.record(v);
+        }
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.total / self.count as f64)
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct UsageStats {
+    pub used: NumberStats,
+    pub percent: NumberStats,
+}
+
+impl UsageStats {
+    pub(crate) fn record(&mut self, used: Option<f64>, total: Option<f64>) {
+        if let Some(used_bytes) = used {
+            self.used.record(used_bytes);
+            if let Some(total_bytes) = total {
+                if total_bytes > 0.0 {
+                    self.percent.record((used_bytes / total_bytes) * 100.0);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TransferStats {
+    pub rx_total: f64,
+    pub tx_total: f64,
+}
+
+impl TransferStats {
+    pub(crate) fn record(&mut self, rx: f64, tx: f64) {
+        self.rx_total += rx;
+        self.tx_total += tx;
+    }
+}
+
+pub(crate) fn average_for_kind(metrics: &[MetricSample], kind: MetricKind) -> NumberStats {
+    let mut stats = NumberStats::default();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        stats.record_opt(sample.value);
+    }
+    stats
+}
+
+pub fn filter_metrics_by_source(
+    metrics: &[MetricSample],
+    sensor_filters: &[String],
+) -> Vec<MetricSample> {
+    if sensor_filters.is_empty() {
+        return metrics.to_vec();
+    }
+    metrics
+        .iter()
+        .filter(|m| sensor_filters.iter().any(|f| source_matches(f, &m.source)))
+        .cloned()
+        .collect()
+}
+
+/// Keep the samples collected on one of `hosts` (all when empty); untagged samples are
+/// dropped by a filter since their host is unknown.
+pub fn filter_metrics_by_host(metrics: &[MetricSample], hosts: &[String]) -> Vec<MetricSample> {
+    if hosts.is_empty() {
+        return metrics.to_vec();
+    }
+    metrics
+        .iter()
+        .filter(|m| m.host.as_ref().is_some_and(|host| hosts.contains(host)))
+        .cloned()
+        .collect()
+}
+
+/// Whether `source` matches `pattern`, where each `*` stands for any run of characters.
+pub fn source_matches(pattern: &str, source: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = source.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Device part of a temperature source: the recorded hwmon device, or the text before `:`.
+fn temperature_device(sample: &MetricSample) -> String {
+    sample
+        .details
+        .get("device")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            sample
+                .source
+                .split_once(':')
+                .map(|(device, _)| device.to_string())
+                .unwrap_or_else(|| sample.source.clone())
+        })
+}
+
+pub fn group_temperature_by_device(mut metrics: Vec<MetricSample>) -> Vec<MetricSample> {
+    for sample in metrics
+        .iter_mut()
+        .filter(|m| m.kind == MetricKind::Temperature)
+    {
+        sample.source = temperature_device(sample);
+    }
+    metrics
+}
+
+/// Stats of every temperature source over all of `metrics`, highest peak first.
+pub fn temperature_sensor_stats(metrics: &[MetricSample]) -> Vec<(String, NumberStats)> {
+    let mut sensors: BTreeMap<&str, NumberStats> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::Temperature) {
+        sensors
+            .entry(&sample.source)
+            .or_default()
+            .record_opt(sample.value);
+    }
+    let mut sensors: Vec<(String, NumberStats)> = sensors
+        .into_iter()
+        .filter(|(_, stats)| stats.count > 0)
+        .map(|(source, stats)| (source.to_string(), stats))
+        .collect();
+    sensors.sort_by(|a, b| {
+        b.1.max()
+            .unwrap_or(f64::MIN)
+            .total_cmp(&a.1.max().unwrap_or(f64::MIN))
+    });
+    sensors
+}
+
+/// Drop the CPU usage series `detail` hides. In aggregate mode the per-core frequencies
+/// of each collection are averaged into one `cpu` sample.
+pub fn apply_cpu_detail(metrics: Vec<MetricSample>, detail: CpuDetail) -> Vec<MetricSample> {
+    let mut frequencies: BTreeMap<(Option<String>, u64), (MetricSample, f64, usize)> =
+        BTreeMap::new();
+    let mut kept: Vec<MetricSample> = metrics
+        .into_iter()
+        .filter_map(|sample| match (detail, &sample.kind) {
+            (CpuDetail::Aggregate, MetricKind::CpuUsage) => {
+                (sample.source == "cpu").then_some(sample)
+            }
+            (CpuDetail::PerCore, MetricKind::CpuUsage) => {
+                (sample.source != "cpu").then_some(sample)
+            }
+            (CpuDetail::Aggregate, MetricKind::CpuFrequency) => {
+                if let Some(value) = sample.value {
+                    let entry = frequencies
+                        .entry((sample.host.clone(), sample.ts.to_bits()))
+                        .or_insert_with(|| (sample.clone(), 0.0, 0));
+                    entry.1 += value;
+                    entry.2 += 1;
+                }
+                None
+            }
+            _ => Some(sample),
+        })
+        .collect();
+    kept.extend(frequencies.into_values().map(|(mut sample, sum, cores)| {
+        sample.source = "cpu".to_string();
+        sample.value = Some(sum / cores as f64);
+        sample.details = serde_json::json!({ "cores": cores });
+        sample
+    }));
+    kept.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    kept
+}
+
+pub type SourceBuckets = BTreeMap<String, BTreeMap<DateTime<Local>, NumberStats>>;
+
+pub(crate) fn bucket_stats_for_kind_by_source(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    bucket_seconds: i64,
+) -> SourceBuckets {
+    let mut buckets: SourceBuckets = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        if let Some(value) = sample.value {
+            let bucket = bucket_start(sample.ts, bucket_seconds);
+            buckets
+                .entry(sample.source.clone())
+                .or_default()
+                .entry(bucket)
+                .or_default()
+                .record(value);
+        }
+    }
+    buckets
+}
+
+/// Keep the `count` sources with the highest peak, as [`temperature_sensor_stats`] ranks
+/// them.
+fn keep_hottest(buckets: &mut SourceBuckets, count: usize) {
+    let mut peaks: Vec<(String, f64)> = buckets
+        .iter()
+        .filter_map(|(source, buckets)| {
+            let peak = buckets
+                .values()
+                .filter_map(NumberStats::max)
+                .reduce(f64::max)?;
+            Some((source.clone(), peak))
+        })
+        .collect();
+    peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let hottest: Vec<String> = peaks
+        .into_iter()
+        .take(count)
+        .map(|(source, _)| source)
+        .collect();
+    buckets.retain(|source, _| hottest.contains(source));
+}
+
+/// Seconds between the first and last sample of `metrics` and the series only described
+/// by `spans`.
+fn series_span_seconds(
+    metrics: &[MetricSample],
+    spans: &[(MetricKind, db::SeriesSpan)],
+) -> Option<f64> {
+    let first = spans.iter().map(|(_, span)| span.first);
+    let last = spans.iter().map(|(_, span)| span.last);
+    let first = metrics.iter().map(|s| s.ts).chain(first).reduce(f64::min)?;
+    let last = metrics.iter().map(|s| s.ts).chain(last).reduce(f64::max)?;
+    Some(last - first)
+}
+
+/// [`bucket_stats_for_kind_by_source`] over the stored rows of `kind` that `filter` keeps,
+/// computed by SQLite.
+fn sql_source_buckets(
+    conn: &Connection,
+    kind: &MetricKind,
+    bucket_seconds: i64,
+    filter: &db::SampleFilter,
+) -> anyhow::Result<SourceBuckets> {
+    let mut buckets: SourceBuckets = BTreeMap::new();
+    for stats in db::fetch_bucketed_stats(conn, kind, bucket_seconds, filter)? {
+        let Some(start) = Local.timestamp_opt(stats.start, 0).single() else {
+            continue;
+        };
+        buckets.entry(stats.source).or_default().insert(
+            start,
+            NumberStats {
+                total: stats.avg * stats.count as f64,
+                count: stats.count,
+                min: stats.min,
+                max: stats.max,
+            },
+        );
+    }
+    Ok(buckets)
+}
+
+pub(crate) fn bucket_stats_for_kind(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Local>, NumberStats> {
+    let mut buckets: BTreeMap<DateTime<Local>, NumberStats> = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        if let Some(value) = sample.value {
+            let bucket = bucket_start(sample.ts, bucket_seconds);
+            buckets.entry(bucket).or_default().record(value);
+        }
+    }
+    buckets
+}
+
+#[cfg(test)]
+fn usage_stats_for_kind(metrics: &[MetricSample], kind: MetricKind) -> UsageStats {
+    let mut stats = UsageStats::default();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        let total = sample.total_bytes;
+        stats.record(sample.value, total);
+    }
+    stats
+}
+
+pub(crate) fn bucket_usage_stats(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Local>, UsageStats> {
+    let mut buckets: BTreeMap<DateTime<Local>, UsageStats> = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        let bucket = bucket_start(sample.ts, bucket_seconds);
+        let total = sample.total_bytes;
+        buckets
+            .entry(bucket)
+            .or_default()
+            .record(sample.value, total);
+    }
+    buckets
+}
+
+#[cfg(test)]
+struct NetworkRateSample {
+    rx_rate: Option<f64>,
+    tx_rate: Option<f64>,
+}
+
+#[cfg(test)]
+fn rate_from_counters(previous: Option<f64>, current: Option<f64>, dt: f64) -> Option<f64> {
+    match (previous, current) {
+        (Some(prev), Some(next)) if next >= prev && dt > 0.0 => Some((next - prev) / dt),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn compute_network_rates(metrics: &[MetricSample]) -> Vec<NetworkRateSample> {
+    let mut by_iface: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|s| s.kind == MetricKind::NetworkBytes)
+    {
+        by_iface.entry(&sample.source).or_default().push(sample);
+    }
+
+    let mut rates = Vec::new();
+    for (_iface, mut samples) in by_iface {
+        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+        for window in samples.windows(2) {
+            let prev = window[0];
+            let next = window[1];
+            let dt = next.ts - prev.ts;
+            if dt <= 0.0 {
+                continue;
+            }
+            let rx_rate = rate_from_counters(prev.rx_bytes, next.rx_bytes, dt);
+            let tx_rate = rate_from_counters(prev.tx_bytes, next.tx_bytes, dt);
+            if rx_rate.is_none() && tx_rate.is_none() {
+                continue;
+            }
+            rates.push(NetworkRateSample { rx_rate, tx_rate });
+        }
+    }
+    rates
+}
+
+fn compute_counter_delta(prev: Option<f64>, next: Option<f64>) -> f64 {
+    match (prev, next) {
+        (Some(prev_val), Some(next_val)) if next_val >= prev_val => next_val - prev_val,
+        _ => 0.0,
+    }
+}
+
+/// Per-interface counter deltas between consecutive samples, as `(iface, ts, rx, tx)`.
+/// Kinds [`daily_summaries`] reads.
+const DAILY_KINDS: [MetricKind; 7] = [
+    MetricKind::ScreenOn,
+    MetricKind::UserIdle,
+    MetricKind::BatteryEnergyNow,
+    MetricKind::BatteryPercentage,
+    MetricKind::PowerDraw,
+    MetricKind::Temperature,
+    MetricKind::NetworkBytes,
+];
+
+/// Consecutive samples further apart than this are a gap (suspend, shutdown) and add no
+/// screen time or energy.
+const DAILY_GAP_SECONDS: f64 = 10.0 * 60.0;
+
+/// One local calendar day of the window.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DailySummary {
+    pub day: NaiveDate,
+    /// Time with a display on, or with the user active when no display state is collected
+    pub screen_on_seconds: Option<f64>,
+    /// Battery energy drained, or energy drawn per `power_draw` without battery readings
+    pub energy_wh: Option<f64>,
+    /// Of the hottest sensor at each collection
+    pub avg_temp: Option<f64>,
+    pub max_temp: Option<f64>,
+    /// Bytes received and sent over all interfaces
+    pub transferred_bytes: Option<f64>,
+}
+
+fn local_day(ts: f64) -> Option<NaiveDate> {
+    Local
+        .timestamp_opt(ts as i64, 0)
+        .single()
+        .map(|dt| dt.date_naive())
+}
+
+/// Seconds per day during which `on` holds for a series reduced to one value per
+/// collection, each interval counted from its first sample.
+fn daily_on_seconds(
+    series: &BTreeMap<OrderedFloat<f64>, f64>,
+    on: impl Fn(f64) -> bool,
+) -> BTreeMap<NaiveDate, f64> {
+    let points: Vec<(f64, f64)> = series.iter().map(|(ts, v)| (ts.0, *v)).collect();
+    let mut days: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for pair in points.windows(2) {
+        let ((ts, value), (next_ts, _)) = (pair[0], pair[1]);
+        let Some(day) = local_day(ts) else {
+            continue;
+        };
+        let seconds = days.entry(day).or_default();
+        if next_ts - ts <= DAILY_GAP_SECONDS && on(value) {
+            *seconds += next_ts - ts;
+        }
+    }
+    days
+}
+
+fn day_of(days: &mut BTreeMap<NaiveDate, DailySummary>, ts: f64) -> Option<&mut DailySummary> {
+    local_day(ts).and_then(|date| days.get_mut(&date))
+}
+
+/// Screen time, energy, temperature and traffic per local calendar day, oldest first.
+pub fn daily_summaries(metrics: &[MetricSample]) -> Vec<DailySummary> {
+    // Every collected day gets a row, even one without any of the columns
+    let mut days: BTreeMap<NaiveDate, DailySummary> = metrics
+        .iter()
+        .filter_map(|m| local_day(m.ts))
+        .map(|day| {
+            let summary = DailySummary {
+                day,
+                ..Default::default()
+            };
+            (day, summary)
+        })
+        .collect();
+
+    let screen = series_by_timestamp(metrics, MetricKind::ScreenOn, Reduce::Max);
+    let screen_time = if screen.is_empty() {
+        let idle = series_by_timestamp(metrics, MetricKind::UserIdle, Reduce::Max);
+        daily_on_seconds(&idle, |idle| idle < 0.5)
+    } else {
+        daily_on_seconds(&screen, |on| on > 0.5)
+    };
+    for (date, seconds) in screen_time {
+        if let Some(summary) = days.get_mut(&date) {
+            summary.screen_on_seconds = Some(seconds);
+        }
+    }
+
+    if metrics
+        .iter()
+        .any(|m| m.kind == MetricKind::BatteryEnergyNow)
+    {
+        for (prev, next) in discharge_intervals(metrics) {
+            if next.ts - prev.ts > DAILY_GAP_SECONDS {
+                continue;
+            }
+            if let Some(summary) = day_of(&mut days, prev.ts) {
+                *summary.energy_wh.get_or_insert(0.0) += energy_used(prev, next);
+            }
+        }
+    } else {
+        let power = series_by_timestamp(metrics, MetricKind::PowerDraw, Reduce::Sum);
+        let points: Vec<(f64, f64)> = power.iter().map(|(ts, v)| (ts.0, *v)).collect();
+        for pair in points.windows(2) {
+            let ((ts, watts), (next_ts, _)) = (pair[0], pair[1]);
+            if next_ts - ts > DAILY_GAP_SECONDS {
+                continue;
+            }
+            if let Some(summary) = day_of(&mut days, ts) {
+                *summary.energy_wh.get_or_insert(0.0) += watts * (next_ts - ts) / 3600.0;
+            }
+        }
+    }
+
+    let mut temperatures: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+    for (ts, hottest) in series_by_timestamp(metrics, MetricKind::Temperature, Reduce::Max) {
+        if let Some(date) = local_day(ts.0) {
+            temperatures.entry(date).or_default().push(hottest);
+        }
+    }
+    for (date, values) in temperatures {
+        if let Some(summary) = days.get_mut(&date) {
+            summary.avg_temp = Some(values.iter().sum::<f64>() / values.len() as f64);
+            summary.max_temp = values.iter().copied().reduce(f64::max);
+        }
+    }
+
+    for (_, ts, rx, tx) in network_counter_deltas(metrics) {
+        // A delta belongs to the interval ending at `ts`
+        if let Some(summary) = day_of(&mut days, ts) {
+            *summary.transferred_bytes.get_or_insert(0.0) += rx + tx;
+        }
+    }
+    days.into_values().collect()
+}
+
+pub(crate) fn network_counter_deltas(metrics: &[MetricSample]) -> Vec<(&str, f64, f64, f64)> {
+    let mut by_iface: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|s| s.kind == MetricKind::NetworkBytes)
+    {
+        by_iface.entry(&sample.source).or_default().push(sample);
+    }
+
+    let mut deltas = Vec::new();
+    for (iface, mut samples) in by_iface {
+        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+        for window in samples.windows(2) {
+            let prev = window[0];
+            let next = window[1];
+            let dt = next.ts - prev.ts;
+            if dt <= 0.0 {
+                continue;
+            }
+
+            let rx_delta = compute_counter_delta(prev.rx_bytes, next.rx_bytes);
+            let tx_delta = compute_counter_delta(prev.tx_bytes, next.tx_bytes);
+
+            if rx_delta > 0.0 || tx_delta > 0.0 {
+                deltas.push((iface, next.ts, rx_delta, tx_delta));
+            }
+        }
+    }
+    deltas
+}
+
+pub(crate) fn bucket_network_totals(
+    metrics: &[MetricSample],
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Local>, TransferStats> {
+    let mut buckets: BTreeMap<DateTime<Local>, TransferStats> = BTreeMap::new();
+    for (_iface, ts, rx_delta, tx_delta) in network_counter_deltas(metrics) {
+        let bucket = bucket_start(ts, bucket_seconds);
+        buckets
+            .entry(bucket)
+            .or_default()
+            .record(rx_delta, tx_delta);
+    }
+    buckets
+}
+
+pub(crate) fn battery_rate_buckets(
+    battery_metrics: &[MetricSample],
+    bucket_seconds: i64,
+) -> (
+    BTreeMap<DateTime<Local>, NumberStats>,
+    BTreeMap<DateTime<Local>, NumberStats>,
+) {
+    const MAX_GAP_HOURS: f64 = 5.0 / 60.0;
+
+    let mut discharge = BTreeMap::new();
+    let mut charge = BTreeMap::new();
+
+    let energy_now_samples: Vec<_> = battery_metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::BatteryEnergyNow && m.value.is_some())
+        .collect();
+
+    let mut iter = energy_now_samples.iter();
+    let mut previous = match iter.next() {
+        Some(sample) => sample,
+        None => return (discharge, charge),
+    };
+
+    for current in iter {
+        if current.ts < previous.ts {
+            previous = current;
+            continue;
+        }
+        let (Some(prev_now), Some(curr_now)) = (previous.value, current.value) else {
+            previous = current;
+            continue;
+        };
+        let dt_hours = (current.ts - previous.ts) / 3600.0;
+        if dt_hours <= 0.0 || dt_hours > MAX_GAP_HOURS {
+            previous = current;
+            continue;
+        }
+        let bucket = bucket_start(current.ts, bucket_seconds);
+        if curr_now > prev_now && is_charging(previous) && is_charging(current) {
+            charge
+                .entry(bucket)
+                .or_default()
+                .record((curr_now - prev_now) / dt_hours);
+        } else if curr_now < prev_now && is_discharging(previous) && is_discharging(current) {
+            discharge
+                .entry(bucket)
+                .or_default()
+                .record((prev_now - curr_now) / dt_hours);
+        }
+        previous = current;
+    }
+
+    (discharge, charge)
+}
+
+/// Number of samples per governor label, by source and bucket.
+pub type GovernorBuckets = BTreeMap<String, BTreeMap<DateTime<Local>, BTreeMap<String, usize>>>;
+
+/// `powersave (balance_power)` from the governor and EPP recorded with a frequency sample.
+fn governor_label(sample: &MetricSample) -> Option<String> {
+    let governor = sample.details.get("governor")?.as_str()?;
+    Some(match sample.details.get("epp").and_then(|v| v.as_str()) {
+        Some(epp) => format!("{governor} ({epp})"),
+        None => governor.to_string(),
+    })
+}
+
+fn bucket_governors(metrics: &[MetricSample], bucket_seconds: i64) -> GovernorBuckets {
+    let mut buckets: GovernorBuckets = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|s| s.kind == MetricKind::CpuFrequency)
+    {
+        if let Some(label) = governor_label(sample) {
+            *buckets
+                .entry(sample.source.clone())
+                .or_default()
+                .entry(bucket_start(sample.ts, bucket_seconds))
+                .or_default()
+                .entry(label)
+                .or_default() += 1;
+        }
+    }
+    buckets
+}
+
+/// Most frequent governor label in a bucket; ties go to the alphabetically first one.
+pub fn dominant_governor(counts: &BTreeMap<String, usize>) -> Option<&str> {
+    counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(label, _)| label.as_str())
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct HealthBucket {
+    pub samples: usize,
+    pub degraded: usize,
+    pub peak_failed: f64,
+    pub failed_units: BTreeSet<String>,
+}
+
+pub(crate) fn bucket_system_health(
+    metrics: &[MetricSample],
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Local>, HealthBucket> {
+    let mut buckets: BTreeMap<DateTime<Local>, HealthBucket> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::SystemHealth)
+    {
+        let bucket = buckets
+            .entry(bucket_start(sample.ts, bucket_seconds))
+            .or_default();
+        bucket.samples += 1;
+        if sample.details.get("system_state").and_then(|v| v.as_str()) != Some("running") {
+            bucket.degraded += 1;
+        }
+        bucket.peak_failed = bucket.peak_failed.max(sample.value.unwrap_or_default());
+        if let Some(units) = sample
+            .details
+            .get("failed_units")
+            .and_then(|v| v.as_array())
+        {
+            bucket
+                .failed_units
+                .extend(units.iter().filter_map(|u| u.as_str()).map(str::to_string));
+        }
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metric_sample_with_source(
+        kind: MetricKind,
+        source: &str,
+        ts: f64,
+        value: Option<f64>,
+        details: serde_json::Value,
+    ) -> MetricSample {
+        MetricSample {
+            ts,
+            kind,
+            source: source.to_string(),
+            value,
+            unit: None,
+            details,
+            rx_bytes: None,
+            tx_bytes: None,
+            total_bytes: None,
+            status: None,
+            histogram: None,
+            label: None,
+            host: None,
+            boot: None,
+        }
+    }
+
+    fn metric_sample(
+        kind: MetricKind,
+        ts: f64,
+        value: Option<f64>,
+        details: serde_json::Value,
+    ) -> MetricSample {
+        metric_sample_with_source(kind, "test", ts, value, details)
+    }
+
+    fn battery_metric(ts: f64, kind: MetricKind, value: f64, status: &str) -> MetricSample {
+        MetricSample {
+            ts,
+            kind: kind.clone(),
+            source: "BAT0".to_string(),
+            value: Some(value),
+            unit: match kind {
+                MetricKind::BatteryPercentage
+                | MetricKind::BatteryCapacity
+                | MetricKind::BatteryHealth => Some("%".to_string()),
+                _ => Some("Wh".to_string()),
+            },
+            details: serde_json::Value::Null,
+            rx_bytes: None,
+            tx_bytes: None,
+            total_bytes: None,
+            status: Some(status.to_string()),
+            histogram: None,
+            label: None,
+            host: None,
+            boot: None,
+        }
+    }
+
+    #[test]
+    fn dominant_governor_per_bucket() {
+        let freq = |ts: f64, governor: &str| {
+            metric_sample_with_source(
+                MetricKind::CpuFrequency,
+                "cpu0",
+                ts,
+                Some(2000.0),
+                json!({ "governor": governor, "epp": "balance_power" }),
+            )
+        };
+        let metrics = vec![
+            freq(0.0, "powersave"),
+            freq(60.0, "performance"),
+            freq(120.0, "performance"),
+            metric_sample_with_source(
+                MetricKind::CpuFrequency,
+                "cpu1",
+                0.0,
+                Some(1.0),
+                json!(null),
+            ),
+        ];
+        let buckets = bucket_governors(&metrics, 3600);
+        assert!(!buckets.contains_key("cpu1"));
+        let counts = buckets["cpu0"].values().next().unwrap();
+        assert_eq!(
+            dominant_governor(counts),
+            Some("performance (balance_power)")
+        );
+    }
+
+    #[test]
+    fn network_rates_compute_per_second() {
+        let metrics = vec![
+            metric_sample(
+                MetricKind::NetworkBytes,
+                0.0,
+                Some(1000.0),
+                serde_json::Value::Null,
+            )
+            .with_network_bytes(Some(1_000.0), Some(500.0)),
+            metric_sample(
+                MetricKind::NetworkBytes,
+                10.0,
+                Some(4000.0),
+                serde_json::Value::Null,
+            )
+            .with_network_bytes(Some(3_000.0), Some(1_500.0)),
+        ];
+
+        let rates = compute_network_rates(&metrics);
+        assert_eq!(rates.len(), 1);
+        let rate = &rates[0];
+        assert!((rate.rx_rate.unwrap() - 200.0).abs() < 1e-6);
+        assert!((rate.tx_rate.unwrap() - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn network_totals_compute_transferred_bytes() {
+        let metrics = vec![
+            metric_sample(
+                MetricKind::NetworkBytes,
+                0.0,
+                Some(1000.0),
+                serde_json::Value::Null,
+            )
+            .with_network_bytes(Some(1_000.0), Some(500.0)),
+            metric_sample(
+                MetricKind::NetworkBytes,
+                10.0,
+                Some(4000.0),
+                serde_json::Value::Null,
+            )
+            .with_network_bytes(Some(3_000.0), Some(1_500.0)),
+            metric_sample(
+                MetricKind::NetworkBytes,
+                20.0,
+                Some(8000.0),
+                serde_json::Value::Null,
+            )
+            .with_network_bytes(Some(7_000.0), Some(3_000.0)),
+        ];
+
+        let totals = bucket_network_totals(&metrics, 60);
+        assert!(!totals.is_empty());
+
+        // Sum up all totals across buckets
+        let total_rx: f64 = totals.values().map(|s| s.rx_total).sum();
+        let total_tx: f64 = totals.values().map(|s| s.tx_total).sum();
+
+        // Total RX: (3000-1000) + (7000-3000) = 2000 + 4000 = 6000
+        assert!((total_rx - 6000.0).abs() < 1e-6);
+        // Total TX: (1500-500) + (3000-1500) = 1000 + 1500 = 2500
+        assert!((total_tx - 2500.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bucket_stats_are_kept_per_source() {
+        let metrics = vec![
+            metric_sample_with_source(MetricKind::Temperature, "cpu0", 0.0, Some(30.0), json!({})),
+            metric_sample_with_source(MetricKind::Temperature, "cpu1", 0.0, Some(40.0), json!({})),
+            metric_sample_with_source(MetricKind::Temperature, "cpu0", 60.0, Some(50.0), json!({})),
+        ];
+
+        let buckets = bucket_stats_for_kind_by_source(&metrics, MetricKind::Temperature, 60);
+        assert_eq!(buckets.len(), 2);
+        let cpu0_count: usize = buckets
+            .get("cpu0")
+            .unwrap()
+            .values()
+            .map(|stats| stats.count)
+            .sum();
+        let cpu1_count: usize = buckets
+            .get("cpu1")
+            .unwrap()
+            .values()
+            .map(|stats| stats.count)
+            .sum();
+        assert_eq!(cpu0_count, 2);
+        assert_eq!(cpu1_count, 1);
+    }
+
+    #[test]
+    fn temperatures_group_by_device() {
+        let metrics = vec![
+            metric_sample_with_source(
+                MetricKind::Temperature,
+                "nvme0:Composite",
+                0.0,
+                Some(40.0),
+                json!({"device": "nvme0"}),
+            ),
+            metric_sample_with_source(
+                MetricKind::Temperature,
+                "nvme0:Sensor 1",
+                0.0,
+                Some(50.0),
+                json!({"device": "nvme0"}),
+            ),
+            metric_sample_with_source(
+                MetricKind::Temperature,
+                "coretemp:Core 0",
+                0.0,
+                Some(60.0),
+                json!({}),
+            ),
+            metric_sample_with_source(
+                MetricKind::Temperature,
+                "acpitz",
+                0.0,
+                Some(30.0),
+                json!({}),
+            ),
+        ];
+
+        let grouped = group_temperature_by_device(metrics);
+        let buckets = bucket_stats_for_kind_by_source(&grouped, MetricKind::Temperature, 60);
+        let sources: Vec<&String> = buckets.keys().collect();
+        assert_eq!(sources, vec!["acpitz", "coretemp", "nvme0"]);
+        let nvme = buckets["nvme0"].values().next().unwrap();
+        assert_eq!(nvme.count, 2);
+        assert_eq!(nvme.max(), Some(50.0));
+    }
+
+    #[test]
+    fn metrics_can_be_filtered_by_source() {
+        let metrics = vec![
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu0", 0.0, Some(10.0), json!({})),
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu1", 0.0, Some(20.0), json!({})),
+        ];
+
+        let filtered = filter_metrics_by_source(&metrics, &["cpu1".to_string()]);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].source, "cpu1");
+    }
+
+    #[test]
+    fn temperature_allowlist_accepts_wildcards() {
+        assert!(source_matches("coretemp:*", "coretemp:Package id 0"));
+        assert!(source_matches("*:Composite", "nvme0:Composite"));
+        assert!(source_matches("nvme*:*", "nvme1:Sensor 2"));
+        assert!(!source_matches("acpitz", "acpitz:thermal_zone1"));
+        assert!(!source_matches("ab*b", "ab"));
+
+        let timeframe = crate::timeframe::build_timeframe(0, 0, 0, true).unwrap();
+        let temp = |source: &str| {
+            metric_sample_with_source(MetricKind::Temperature, source, 0.0, Some(40.0), json!({}))
+        };
+        let samples = vec![
+            temp("coretemp:Core 0"),
+            temp("acpitz:thermal_zone0"),
+            temp("iwlwifi_1"),
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu", 0.0, Some(5.0), json!({})),
+        ];
+        let report = ReportBuilder::new(timeframe)
+            .presets([ReportPreset::Temperature, ReportPreset::Cpu])
+            .temperature_sensors(["coretemp:*".to_string()])
+            .build_from_samples(&samples)
+            .unwrap();
+        let sources: Vec<&str> = report.samples.iter().map(|m| m.source.as_str()).collect();
+        assert_eq!(sources, ["coretemp:Core 0", "cpu"]);
+    }
+
+    #[test]
+    fn daily_summaries_split_by_local_day() {
+        let noon = Local
+            .with_ymd_and_hms(2026, 10, 12, 12, 0, 0)
+            .unwrap()
+            .timestamp() as f64;
+        let mut metrics = Vec::new();
+        for (offset, on) in [(0.0, 1.0), (300.0, 1.0), (600.0, 0.0), (900.0, 1.0)] {
+            metrics.push(metric_sample(
+                MetricKind::ScreenOn,
+                noon + offset,
+                Some(on),
+                json!({}),
+            ));
+        }
+        for (offset, energy, rx) in [(0.0, 50.0, 0.0), (300.0, 49.0, 1000.0)] {
+            metrics.push(
+                metric_sample(
+                    MetricKind::BatteryEnergyNow,
+                    noon + offset,
+                    Some(energy),
+                    serde_json::Value::Null,
+                )
+                .with_status("Discharging"),
+            );
+            metrics.push(
+                metric_sample_with_source(
+                    MetricKind::NetworkBytes,
+                    "eth0",
+                    noon + offset,
+                    Some(rx),
+                    serde_json::Value::Null,
+                )
+                .with_network_bytes(Some(rx), Some(0.0)),
+            );
+        }
+        for (offset, cpu, nvme) in [
+            (0.0, 50.0, 40.0),
+            (300.0, 70.0, 45.0),
+            (86_400.0, 60.0, 30.0),
+        ] {
+            metrics.push(metric_sample_with_source(
+                MetricKind::Temperature,
+                "coretemp",
+                noon + offset,
+                Some(cpu),
+                json!({}),
+            ));
+            metrics.push(metric_sample_with_source(
+                MetricKind::Temperature,
+                "nvme0",
+                noon + offset,
+                Some(nvme),
+                json!({}),
+            ));
+        }
+
+        let days = daily_summaries(&metrics);
+        assert_eq!(days.len(), 2);
+        let first = &days[0];
+        assert_eq!(first.day, NaiveDate::from_ymd_opt(2026, 10, 12).unwrap());
+        assert_eq!(first.screen_on_seconds, Some(600.0));
+        assert_eq!(first.energy_wh, Some(1.0));
+        assert_eq!((first.avg_temp, first.max_temp), (Some(60.0), Some(70.0)));
+        assert_eq!(first.transferred_bytes, Some(1000.0));
+        let second = &days[1];
+        assert_eq!((second.screen_on_seconds, second.energy_wh), (None, None));
+        assert_eq!(second.max_temp, Some(60.0));
+    }
+
+    #[test]
+    fn previous_week_lines_up_with_this_week() {
+        let week = crate::timeframe::build_timeframe(0, 7, 0, false).unwrap();
+        let builder = ReportBuilder::new(week);
+        let period = builder.comparison_period().unwrap();
+        assert_eq!(period.label, "last week");
+        assert_eq!(period.until - period.since, 7.0 * 86400.0);
+        let day = crate::timeframe::build_timeframe(0, 1, 0, false).unwrap();
+        assert!(ReportBuilder::new(day).comparison_period().is_none());
+
+        let bucket = 3600;
+        let last_week = period.since + 60.0;
+        let samples = vec![
+            metric_sample_with_source(
+                MetricKind::Temperature,
+                "coretemp",
+                last_week,
+                Some(50.0),
+                json!({}),
+            ),
+            metric_sample_with_source(
+                MetricKind::PowerDraw,
+                "BAT0",
+                last_week,
+                Some(6.0),
+                json!({}),
+            ),
+        ];
+        let previous = builder.previous_period(&samples, period, bucket).unwrap();
+        let key = bucket_start(last_week + 7.0 * 86400.0, bucket);
+        assert_eq!(previous.discharge_w.get(&key), Some(&6.0));
+        assert_eq!(previous.temperature["coretemp"].get(&key), Some(&50.0));
+    }
+
+    #[test]
+    fn value_histograms_show_both_modes() {
+        let power = |value: f64| {
+            let mut sample = metric_sample_with_source(
+                MetricKind::PowerDraw,
+                "BAT0",
+                0.0,
+                Some(value),
+                json!({}),
+            );
+            sample.unit = Some("W".to_string());
+            sample
+        };
+        // Idle around 4 W and compiling around 24 W: the average of 14 W never happens
+        let samples: Vec<MetricSample> = [4.0, 4.5, 5.0, 23.0, 24.0, 24.0].map(power).to_vec();
+        let histograms = value_histograms(&samples, &MetricKind::PowerDraw);
+        let bins = &histograms[0].bins;
+        assert_eq!(bins.len(), VALUE_HISTOGRAM_BINS);
+        assert_eq!((bins[0].0, bins[9].1), (4.0, 24.0));
+        assert_eq!(bins[0].2, 3);
+        assert_eq!(bins[9].2, 3);
+        assert_eq!(bins[5].2, 0);
+        assert_eq!(histograms[0].total(), 6);
+
+        let usage =
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu", 0.0, Some(100.0), json!({}));
+        let usage = MetricSample {
+            unit: Some("%".to_string()),
+            ..usage
+        };
+        let bins = &value_histograms(&[usage], &MetricKind::CpuUsage)[0].bins;
+        assert_eq!((bins[0].0, bins[9].1, bins[9].2), (0.0, 100.0, 1));
+    }
+
+    #[test]
+    fn disk_mounts_flag_filesystems_filling_soon() {
+        let day = 24.0 * 3600.0;
+        let gib = 1024.0 * 1024.0 * 1024.0;
+        let disk = |source: &str, ts: f64, used: f64| {
+            metric_sample_with_source(
+                MetricKind::DiskUsage,
+                source,
+                ts,
+                Some(used * gib),
+                serde_json::Value::Null,
+            )
+            .with_total_bytes(100.0 * gib)
+        };
+        let samples = vec![
+            disk("/", 0.0, 50.0),
+            disk("/home", 0.0, 80.0),
+            disk("/", day / 2.0, 50.25),
+            disk("/home", day / 2.0, 85.0),
+            disk("/", day, 50.5),
+            disk("/home", day, 90.0),
+            disk("/boot", day, 20.0),
+        ];
+        let forecasts = disk_forecasts(&samples);
+        let mounts = disk_mounts(&samples, &forecasts, 7.0);
+        let summary: Vec<(&str, Option<f64>, bool)> = mounts
+            .iter()
+            .map(|m| (m.source.as_str(), m.used_percent(), m.filling))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("/", Some(50.5), false),
+                ("/boot", Some(20.0), false),
+                ("/home", Some(90.0), true),
+            ]
+        );
+        assert_eq!(mounts[1].growth_bytes_per_day, None);
+        assert_eq!(mounts[2].days_until_full, Some(1.0));
+    }
+
+    #[test]
+    fn disk_forecasts_need_a_day_of_history() {
+        let gib = 1024.0 * 1024.0 * 1024.0;
+        // Three collections two seconds apart, growing by a few hundred MiB
+        let samples: Vec<MetricSample> = [(0.0, 50.0), (2.0, 50.3), (4.0, 50.6)]
+            .into_iter()
+            .map(|(ts, used)| {
+                metric_sample_with_source(
+                    MetricKind::DiskUsage,
+                    "/",
+                    ts,
+                    Some(used * gib),
+                    serde_json::Value::Null,
+                )
+                .with_total_bytes(100.0 * gib)
+            })
+            .collect();
+        let timeframe = crate::timeframe::build_timeframe(0, 0, 0, true).unwrap();
+        let report = ReportBuilder::new(timeframe)
+            .presets([ReportPreset::Disk])
+            .build_from_samples(&samples)
+            .unwrap();
+        let disk = report.disk.unwrap();
+        assert!(disk.forecasts.is_empty());
+        let mount = &disk.mounts[0];
+        assert_eq!(
+            (mount.growth_bytes_per_day, mount.days_until_full),
+            (None, None)
+        );
+        assert!(!mount.filling);
+    }
+
+    #[test]
+    fn top_keeps_the_hottest_sensors() {
+        let timeframe = crate::timeframe::build_timeframe(0, 0, 0, true).unwrap();
+        let temp = |source: &str, ts: f64, value: f64| {
+            metric_sample_with_source(MetricKind::Temperature, source, ts, Some(value), json!({}))
+        };
+        let samples = vec![
+            temp("acpitz", 0.0, 40.0),
+            temp("coretemp:Package id 0", 0.0, 55.0),
+            temp("nvme0:Composite", 0.0, 45.0),
+            temp("acpitz", 60.0, 41.0),
+            temp("coretemp:Package id 0", 60.0, 80.0),
+            temp("nvme0:Composite", 60.0, 47.0),
+        ];
+        let report = ReportBuilder::new(timeframe)
+            .presets([ReportPreset::Temperature])
+            .temperature_top(Some(2))
+            .build_from_samples(&samples)
+            .unwrap();
+        let temperature = report.temperature.unwrap();
+        let sensors: Vec<(&str, Option<f64>)> = temperature
+            .sensors
+            .iter()
+            .map(|(source, stats)| (source.as_str(), stats.max()))
+            .collect();
+        assert_eq!(
+            sensors,
+            [
+                ("coretemp:Package id 0", Some(80.0)),
+                ("nvme0:Composite", Some(47.0))
+            ]
+        );
+        assert!(!temperature.buckets.contains_key("acpitz"));
+    }
+
+    #[test]
+    fn cpu_detail_picks_the_total_or_the_cores() {
+        let samples = vec![
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu", 0.0, Some(20.0), json!({})),
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu0", 0.0, Some(10.0), json!({})),
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu1", 0.0, Some(30.0), json!({})),
+            metric_sample_with_source(
+                MetricKind::CpuFrequency,
+                "cpu0",
+                0.0,
+                Some(800.0),
+                json!({}),
+            ),
+            metric_sample_with_source(
+                MetricKind::CpuFrequency,
+                "cpu1",
+                0.0,
+                Some(2400.0),
+                json!({}),
+            ),
+        ];
+        let sources = |samples: &[MetricSample]| -> Vec<(MetricKind, String, Option<f64>)> {
+            samples
+                .iter()
+                .map(|m| (m.kind.clone(), m.source.clone(), m.value))
+                .collect()
+        };
+
+        let aggregate = apply_cpu_detail(samples.clone(), CpuDetail::Aggregate);
+        assert_eq!(
+            sources(&aggregate),
+            [
+                (MetricKind::CpuUsage, "cpu".to_string(), Some(20.0)),
+                (MetricKind::CpuFrequency, "cpu".to_string(), Some(1600.0)),
+            ]
+        );
+        let per_core = apply_cpu_detail(samples, CpuDetail::PerCore);
+        assert_eq!(per_core.len(), 4);
+        assert!(per_core.iter().all(|m| m.source != "cpu"));
+    }
+
+    #[test]
+    fn usage_stats_compute_percentage() {
+        let metrics = vec![metric_sample(
+            MetricKind::MemoryUsage,
+            0.0,
+            Some(2048.0),
+            serde_json::Value::Null,
+        )
+        .with_total_bytes(4096.0)];
+
+        let stats = usage_stats_for_kind(&metrics, MetricKind::MemoryUsage);
+        assert_eq!(stats.used.count, 1);
+        assert!((stats.used.average().unwrap() - 2048.0).abs() < 1e-6);
+        assert!((stats.percent.average().unwrap() - 50.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn battery_rate_buckets_capture_charging_segments() {
+        let metrics = vec![
+            battery_metric(0.0, MetricKind::BatteryEnergyNow, 10.0, "Charging"),
+            battery_metric(300.0, MetricKind::BatteryEnergyNow, 11.0, "Charging"),
+            battery_metric(600.0, MetricKind::BatteryEnergyNow, 12.5, "Charging"),
+        ];
+
+        let (discharge, charge) = battery_rate_buckets(&metrics, 300);
+
+        assert!(discharge.is_empty());
+        let first_bucket = bucket_start(metrics[1].ts, 300);
+        let second_bucket = bucket_start(metrics[2].ts, 300);
+        let first_rate = charge
+            .get(&first_bucket)
+            .and_then(NumberStats::average)
+            .unwrap();
+        let second_rate = charge
+            .get(&second_bucket)
+            .and_then(NumberStats::average)
+            .unwrap();
+
+        assert!((first_rate - 12.0).abs() < 1e-6);
+        assert!((second_rate - 18.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn battery_rate_buckets_ignores_mixed_mode_segments() {
+        let metrics = vec![
+            battery_metric(0.0, MetricKind::BatteryEnergyNow, 50.0, "Discharging"),
+            battery_metric(300.0, MetricKind::BatteryEnergyNow, 49.0, "Discharging"),
+            battery_metric(600.0, MetricKind::BatteryEnergyNow, 50.0, "Charging"),
+            battery_metric(900.0, MetricKind::BatteryEnergyNow, 50.5, "Charging"),
+        ];
+
+        let (discharge, charge) = battery_rate_buckets(&metrics, 600);
+
+        let discharge_bucket = bucket_start(metrics[1].ts, 600);
+        let charge_bucket = bucket_start(metrics[3].ts, 600);
+        assert!(discharge.contains_key(&discharge_bucket));
+        assert!(charge.contains_key(&charge_bucket));
+    }
+
+    #[test]
+    fn gpu_limits_tell_power_from_thermal_limits() {
+        let gpu = |power: f64, temp: f64| {
+            metric_sample_with_source(
+                MetricKind::GpuUsage,
+                "card1",
+                0.0,
+                Some(99.0),
+                json!({
+                    "power_w": power, "power_cap_w": 186.0, "fan_pwm_percent": 50.0,
+                    "temp_c": temp, "temp_limit_c": 110.0,
+                }),
+            )
+        };
+        let mut metrics = vec![
+            gpu(185.0, 80.0),
+            gpu(150.0, 106.0),
+            gpu(120.0, 70.0),
+            gpu(184.0, 108.0),
+        ];
+        // i915 cards carry no limit readings and are left out
+        metrics.push(metric_sample_with_source(
+            MetricKind::GpuUsage,
+            "card0:render",
+            0.0,
+            Some(10.0),
+            json!({ "engine": "render" }),
+        ));
+
+        let limits = gpu_limits(&metrics);
+        assert_eq!(limits.len(), 1);
+        assert_eq!(limits[0].card, "card1");
+        assert_eq!(limits[0].samples, 4);
+        assert_eq!(limits[0].power_limited_percent(), 50.0);
+        assert_eq!(limits[0].thermal_limited_percent(), 50.0);
+        assert_eq!(limits[0].avg_fan_percent, Some(50.0));
+        assert_eq!(limits[0].max_temp_c, Some(108.0));
+    }
+
+    #[test]
+    fn per_battery_reports_each_pack_next_to_the_combined_battery() {
+        let pack = |source: &str, ts: f64, kind: MetricKind, value: f64| MetricSample {
+            source: source.to_string(),
+            ..battery_metric(ts, kind, value, "Discharging")
+        };
+        let mut samples = Vec::new();
+        for (ts, bat0, bat1) in [(0.0, 20.0, 40.0), (60.0, 19.9, 40.0)] {
+            samples.push(pack("BAT0", ts, MetricKind::BatteryEnergyNow, bat0));
+            samples.push(pack("BAT0", ts, MetricKind::BatteryEnergyFull, 40.0));
+            samples.push(pack("BAT0", ts, MetricKind::BatteryEnergyFullDesign, 50.0));
+            samples.push(pack("BAT1", ts, MetricKind::BatteryEnergyNow, bat1));
+            samples.push(pack("BAT1", ts, MetricKind::BatteryEnergyFull, 40.0));
+            samples.push(pack("BAT1", ts, MetricKind::BatteryHealth, 95.0));
+        }
+        let timeframe = crate::timeframe::build_timeframe(0, 0, 0, true).unwrap();
+        let report = ReportBuilder::new(timeframe.clone())
+            .per_battery(true)
+            .build_from_samples(&samples)
+            .unwrap();
+
+        let packs = &report.battery_packs;
+        assert_eq!(packs.len(), 2);
+        assert_eq!(packs[0].source, "BAT0");
+        assert_eq!(packs[0].health, Some(80.0));
+        assert!((packs[0].avg_discharge_w.unwrap() - 6.0).abs() < 1e-6);
+        assert_eq!(packs[1].health, Some(95.0));
+        assert_eq!(packs[1].avg_discharge_w, None);
+        // The combined battery and the graph series are unchanged by the split
+        let combined = report.battery.unwrap();
+        assert!(combined.samples.iter().all(|m| m.source == "BAT0+BAT1"));
+        assert!(report.samples.iter().any(|m| m.source == "BAT1"));
+
+        let merged = ReportBuilder::new(timeframe)
+            .build_from_samples(&samples)
+            .unwrap();
+        assert!(merged.battery_packs.is_empty());
+    }
+
+    #[test]
+    fn state_uptime_skips_collection_gaps() {
+        let state = |ts: f64, value: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::AcOnline,
+                "AC",
+                Some(value),
+                Some("bool"),
+                json!({}),
+            )
+        };
+        let metrics = vec![
+            state(0.0, 1.0),
+            state(60.0, 1.0),
+            state(120.0, 0.0),
+            state(180.0, 0.0),
+            // Suspended for an hour: not counted
+            state(3_780.0, 1.0),
+            state(3_840.0, 1.0),
+            MetricSample::new(0.0, MetricKind::CpuUsage, "cpu", Some(0.0), None, json!({})),
+        ];
+
+        let summaries = state_summaries(&metrics);
+        assert_eq!(summaries.len(), 1);
+        let ac = &summaries[0];
+        assert_eq!(ac.observed_seconds, 240.0);
+        assert_eq!(ac.on_seconds, 180.0);
+        assert_eq!(ac.on_percent(), Some(75.0));
+        assert_eq!(ac.transitions, 2);
+        assert!(ac.latest);
+    }
+
+    #[test]
+    fn categories_report_time_per_label() {
+        let governor = |ts: f64, label: &str| {
+            MetricSample::new(ts, MetricKind::CpuGovernor, "cpu", None, None, json!({}))
+                .with_label(label)
+        };
+        let metrics = vec![
+            governor(0.0, "powersave"),
+            governor(60.0, "performance"),
+            governor(120.0, "performance"),
+            governor(180.0, "powersave"),
+        ];
+
+        let summaries = category_summaries(&metrics);
+        assert_eq!(summaries.len(), 1);
+        let cpu = &summaries[0];
+        assert_eq!(cpu.seconds.get("performance"), Some(&120.0));
+        assert_eq!(cpu.seconds.get("powersave"), Some(&60.0));
+        assert_eq!(cpu.observed_seconds(), 180.0);
+        assert_eq!(cpu.transitions, 2);
+        assert_eq!(cpu.latest, "powersave");
+    }
+
+    #[test]
+    fn distributions_merge_histograms_per_source() {
+        let probe = |ts: f64, source: &str, bounds: Vec<f64>, counts: Vec<u64>| {
+            MetricSample::new(ts, MetricKind::Latency, source, None, Some("ms"), json!({}))
+                .with_histogram(Histogram::new(bounds, counts).unwrap())
+        };
+        let metrics = vec![
+            probe(0.0, "gateway", vec![10.0, 20.0], vec![4, 0]),
+            probe(60.0, "gateway", vec![10.0, 20.0], vec![0, 4]),
+            probe(0.0, "remote", vec![50.0], vec![1]),
+            // A new bucket layout replaces the older samples
+            probe(60.0, "remote", vec![100.0], vec![2]),
+        ];
+
+        let summaries = distribution_summaries(&metrics);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].source, "gateway");
+        assert_eq!(summaries[0].samples, 2);
+        assert_eq!(summaries[0].observations, 8);
+        assert_eq!(summaries[0].p50, Some(10.0));
+        assert_eq!(summaries[0].p90, Some(18.0));
+        assert_eq!(summaries[1].samples, 1);
+        assert_eq!(summaries[1].p50, Some(50.0));
+    }
+
+    #[test]
+    fn wakeup_sources_are_ranked_by_event_increase() {
+        let wakeup = |source: &str, ts: f64, count: f64| {
+            metric_sample_with_source(MetricKind::WakeupEvents, source, ts, Some(count), json!({}))
+        };
+        let metrics = vec![
+            wakeup("rtc0", 0.0, 10.0),
+            wakeup("rtc0", 1800.0, 12.0),
+            wakeup("xhci_hcd", 0.0, 100.0),
+            wakeup("xhci_hcd", 1800.0, 130.0),
+            // Counter reset by a reboot
+            wakeup("xhci_hcd", 3600.0, 5.0),
+            wakeup("idle", 0.0, 3.0),
+            wakeup("idle", 3600.0, 3.0),
+        ];
+        let top = top_wakeup_sources(&metrics, 10);
+        let ranked: Vec<(&str, f64)> = top.iter().map(|w| (w.source.as_str(), w.events)).collect();
+        assert_eq!(ranked, [("xhci_hcd", 30.0), ("rtc0", 2.0)]);
+        assert_eq!(top[0].per_hour, Some(30.0));
+        assert_eq!(top_wakeup_sources(&metrics, 1).len(), 1);
+    }
+
+    #[test]
+    fn collector_costs_rank_groups_before_the_total() {
+        use crate::metrics::{collector_stats_samples, Collector, CollectorTiming};
+        use std::time::Duration;
+
+        let run = |ts: f64, disk_ms: u64| {
+            let timings = [
+                CollectorTiming {
+                    collector: Collector::Cpu,
+                    elapsed: Duration::from_millis(100),
+                    samples: 9,
+                    timed_out: false,
+                },
+                CollectorTiming {
+                    collector: Collector::Disk,
+                    elapsed: Duration::from_millis(disk_ms),
+                    samples: 2,
+                    timed_out: false,
+                },
+            ];
+            let total = Duration::from_millis(110 + disk_ms);
+            collector_stats_samples(ts, &timings, total, Duration::from_millis(4), 11)
+        };
+        let mut metrics = run(0.0, 2);
+        metrics.extend(run(60.0, 398));
+        assert_eq!(metrics.len(), 6);
+
+        let costs = collector_costs(&metrics);
+        let order: Vec<&str> = costs.iter().map(|c| c.collector.as_str()).collect();
+        assert_eq!(order, ["disk", "cpu", "total"]);
+        assert_eq!(costs[0].duration.average(), Some(200.0));
+        assert_eq!(costs[0].duration.max(), Some(398.0));
+        assert_eq!(costs[1].samples.average(), Some(9.0));
+        assert_eq!(costs[2].samples.average(), Some(11.0));
+        assert_eq!(costs[2].cpu.average(), Some(4.0));
+        assert_eq!(costs[1].cpu.count, 0);
+    }
+
+    #[test]
+    fn sparse_series_are_flagged_against_their_interval() {
+        let sample = |ts: f64, kind: MetricKind, source: &str| {
+            metric_sample_with_source(kind, source, ts, Some(1.0), json!({}))
+        };
+        let mut metrics: Vec<MetricSample> = (0..10)
+            .map(|i| sample(i as f64 * 60.0, MetricKind::CpuUsage, "cpu"))
+            .collect();
+        // 3 of the 10 samples a 60 s interval implies over 9 minutes
+        metrics.extend([0.0, 240.0, 540.0].map(|ts| sample(ts, MetricKind::DiskUsage, "/")));
+        metrics.push(sample(0.0, MetricKind::Temperature, "acpitz"));
+        metrics.push(sample(540.0, MetricKind::Temperature, "acpitz"));
+        let intervals = BTreeMap::from([
+            ("cpu_usage".to_string(), 60.0),
+            ("disk_usage".to_string(), 60.0),
+        ]);
+
+        let flagged = undersampled_series(&metrics, &[], &intervals);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].source, "/");
+        assert_eq!((flagged[0].samples, flagged[0].expected), (3, 10));
+        assert!((flagged[0].coverage() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn boots_count_their_suspends() {
+        let sample = |ts: f64, boot: Option<&str>| {
+            let mut sample = metric_sample(MetricKind::CpuUsage, ts, Some(5.0), json!({}));
+            sample.boot = boot.map(str::to_string);
+            sample
+        };
+        // Every 5 minutes, with an hour asleep in the first boot; the reboot itself is quick
+        let mut metrics: Vec<MetricSample> = [0.0, 300.0, 600.0, 4_200.0, 4_500.0]
+            .map(|ts| sample(ts, Some("a")))
+            .into();
+        metrics.extend([4_800.0, 5_100.0].map(|ts| sample(ts, Some("b"))));
+        metrics.push(sample(5_400.0, None));
+
+        let boots = boot_summaries(&metrics);
+        assert_eq!(boots.len(), 2);
+        assert_eq!((boots[0].boot.as_str(), boots[0].runs), ("a", 5));
+        assert_eq!(
+            (boots[0].suspends, boots[0].suspended_seconds),
+            (1, 3_600.0)
+        );
+        assert_eq!(boots[0].awake_seconds(), 900.0);
+        assert_eq!((boots[1].start_ts, boots[1].suspends), (4_800.0, 0));
+    }
+
+    #[test]
+    fn builder_computes_selected_sections() {
+        let timeframe = crate::timeframe::build_timeframe(0, 0, 0, true).unwrap();
+        let samples = vec![
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu0", 0.0, Some(10.0), json!({})),
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu1", 60.0, Some(30.0), json!({})),
+            metric_sample(
+                MetricKind::MemoryUsage,
+                60.0,
+                Some(1024.0),
+                serde_json::Value::Null,
+            )
+            .with_total_bytes(4096.0),
+        ];
+
+        let report = ReportBuilder::new(timeframe.clone())
+            .presets([ReportPreset::Cpu, ReportPreset::Memory])
+            .source_filters(["cpu1".to_string(), "test".to_string()])
+            .build_from_samples(&samples)
+            .unwrap();
+        assert_eq!(report.record_count, 3);
+        assert_eq!(report.samples.len(), 2);
+        assert!(report.battery.is_none() && report.gpu.is_none());
+        let cpu = report.cpu.unwrap();
+        assert_eq!(cpu.usage.keys().collect::<Vec<_>>(), vec!["cpu1"]);
+        let memory = report.memory.unwrap().buckets;
+        let stats = memory.values().next().unwrap();
+        assert_eq!(stats.percent.average(), Some(25.0));
+
+        let summary_only = ReportBuilder::new(timeframe.clone())
+            .presets([ReportPreset::Cpu, ReportPreset::Memory])
+            .sections([ReportSection::Summary])
+            .build_from_samples(&samples)
+            .unwrap();
+        assert!(summary_only.cpu.unwrap().usage.is_empty());
+        assert!(summary_only.memory.unwrap().buckets.is_empty());
+
+        let missing = ReportBuilder::new(timeframe).build_from_samples(&samples);
+        assert!(matches!(missing, Err(ReportError::NoDataForPresets(_))));
+    }
+
+    #[test]
+    fn sql_buckets_apply_the_report_filters() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = crate::storage::open(&tmp.path().join("metrics.db")).unwrap();
+        let start = 1_700_000_000.0;
+        let mut samples = Vec::new();
+        for i in 0..12 {
+            let ts = start + i as f64 * 600.0;
+            let step = i as f64;
+            for (host, offset) in [("laptop", 0.0), ("desktop", 50.0)] {
+                let mut add = |kind: MetricKind, source: &str, value: f64, details| {
+                    let mut sample =
+                        metric_sample_with_source(kind, source, ts, Some(value + offset), details);
+                    sample.host = Some(host.to_string());
+                    samples.push(sample);
+                };
+                add(MetricKind::CpuUsage, "cpu", step, json!({}));
+                add(MetricKind::CpuUsage, "cpu0", 2.0 * step, json!({}));
+                add(MetricKind::CpuFrequency, "cpu0", 1000.0 + step, json!({}));
+                add(MetricKind::CpuFrequency, "cpu1", 3000.0, json!({}));
+                let coretemp = json!({"device": "coretemp"});
+                add(
+                    MetricKind::Temperature,
+                    "coretemp:Core 0",
+                    40.0 + step,
+                    coretemp.clone(),
+                );
+                add(MetricKind::Temperature, "coretemp:Core 1", 45.0, coretemp);
+                add(
+                    MetricKind::Temperature,
+                    "acpitz:temp1",
+                    90.0 - step,
+                    json!({}),
+                );
+                add(MetricKind::Temperature, "nvme0:Composite", 35.0, json!({}));
+                add(MetricKind::Voltage, "in0", 1.0 + step, json!({}));
+                add(MetricKind::Voltage, "vbat", 3.0, json!({}));
+            }
+        }
+        store.insert_samples(&samples, &[]).unwrap();
+        let timeframe = Timeframe {
+            label: "test".to_string(),
+            seconds: Some(3600.0),
+            hours: 1,
+            days: 0,
+            months: 0,
+            until: Some(start + 5400.0),
+        };
+        let in_window: Vec<MetricSample> = samples
+            .iter()
+            .filter(|m| m.ts >= start + 1800.0 && m.ts <= start + 5400.0)
+            .cloned()
+            .collect();
+
+        let summary = |buckets: &SourceBuckets| -> Vec<(String, i64, usize, String)> {
+            let mut rows = Vec::new();
+            for (source, buckets) in buckets {
+                for (bucket, stats) in buckets {
+                    let stats_text = format!(
+                        "{:.6}/{}/{}",
+                        stats.average().unwrap(),
+                        stats.min,
+                        stats.max
+                    );
+                    rows.push((source.clone(), bucket.timestamp(), stats.count, stats_text));
+                }
+            }
+            rows
+        };
+        for (detail, by_device) in [
+            (None, false),
+            (Some(CpuDetail::Aggregate), false),
+            (Some(CpuDetail::PerCore), true),
+        ] {
+            let builder = ReportBuilder::new(timeframe.clone())
+                .presets([ReportPreset::Cpu, ReportPreset::Temperature])
+                .hosts(["desktop".to_string()])
+                .source_filters(["cpu*", "*:*", "in*"].map(String::from))
+                .temperature_sensors(["coretemp:*", "acpitz:*"].map(String::from))
+                .temperature_top(Some(2))
+                .temp_by_device(by_device)
+                .cpu_detail(detail)
+                .bucket_seconds(Some(1800));
+            let from_db = builder
+                .clone()
+                .keep_samples(false)
+                .build(store.as_ref())
+                .unwrap();
+            let from_rows = builder.build_from_samples(&in_window).unwrap();
+
+            let (db_cpu, rows_cpu) = (from_db.cpu.unwrap(), from_rows.cpu.unwrap());
+            assert!(!db_cpu.usage.is_empty());
+            assert_eq!(summary(&db_cpu.usage), summary(&rows_cpu.usage));
+            assert_eq!(summary(&db_cpu.frequency), summary(&rows_cpu.frequency));
+            let (db_temp, rows_temp) =
+                (from_db.temperature.unwrap(), from_rows.temperature.unwrap());
+            assert_eq!(db_temp.buckets.len(), 2);
+            assert_eq!(summary(&db_temp.buckets), summary(&rows_temp.buckets));
+            assert_eq!(summary(&db_temp.voltage), summary(&rows_temp.voltage));
+            assert_eq!(
+                db_temp.voltage.keys().collect::<Vec<_>>(),
+                vec!["in0"],
+                "{detail:?}"
+            );
+            // Rows of the kinds only bucketed stay in the database
+            assert!(from_db
+                .samples
+                .iter()
+                .all(|m| m.kind != MetricKind::CpuUsage && m.kind != MetricKind::Voltage));
+        }
+    }
+
+    #[test]
+    fn merged_reports_label_each_database_with_its_host() {
+        let tmp = tempfile::tempdir().unwrap();
+        let timeframe = crate::timeframe::build_timeframe(0, 0, 0, true).unwrap();
+        let mut databases = Vec::new();
+        for (name, value) in [("laptop", 10.0), ("desktop", 30.0)] {
+            let path = tmp.path().join(format!("{name}.db"));
+            let mut store = crate::storage::open(&path).unwrap();
+            let sample = metric_sample_with_source(
+                MetricKind::CpuUsage,
+                "cpu",
+                value,
+                Some(value),
+                json!({}),
+            );
+            store.insert_samples(&[sample], &[]).unwrap();
+            let host = store.database_host().unwrap();
+            databases.push((store, host));
+        }
+
+        let report = ReportBuilder::new(timeframe.clone())
+            .presets([ReportPreset::Cpu])
+            .build_merged(&databases)
+            .unwrap();
+        assert_eq!(report.record_count, 2);
+        let hosts: Vec<_> = report.samples.iter().map(|s| s.host.as_deref()).collect();
+        assert_eq!(hosts, [Some("laptop"), Some("desktop")]);
+
+        let desktop = ReportBuilder::new(timeframe)
+            .presets([ReportPreset::Cpu])
+            .hosts(["desktop".to_string()])
+            .build_merged(&databases)
+            .unwrap();
+        assert_eq!(desktop.samples.len(), 1);
+        assert_eq!(desktop.samples[0].value, Some(30.0));
+    }
+}
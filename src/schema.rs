@@ -0,0 +1,110 @@
+//! JSON Schemas of the JSON symmetri prints, so downstream tools can validate against them
+//! and notice breaking changes.
+
+use clap::ValueEnum;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde_json::{json, Value};
+
+use crate::db::KindCatalog;
+use crate::status::StatusJson;
+
+/// Bumped whenever a field is removed, renamed or changes type in any published schema;
+/// new optional fields keep the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON outputs with a published schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SchemaName {
+    /// `symmetri catalog`
+    Catalog,
+    /// `symmetri status --format json`
+    Status,
+}
+
+impl SchemaName {
+    fn as_str(self) -> &'static str {
+        match self {
+            SchemaName::Catalog => "catalog",
+            SchemaName::Status => "status",
+        }
+    }
+}
+
+pub fn schema(name: SchemaName) -> RootSchema {
+    let mut schema = match name {
+        SchemaName::Catalog => schema_for!(Vec<KindCatalog>),
+        SchemaName::Status => schema_for!(StatusJson),
+    };
+    schema.schema.metadata().id = Some(format!(
+        "urn:symmetri:schema:{}:v{SCHEMA_VERSION}",
+        name.as_str()
+    ));
+    schema
+}
+
+/// Every schema keyed by name, with the version they share.
+pub fn all_schemas() -> Value {
+    let schemas: serde_json::Map<String, Value> = SchemaName::value_variants()
+        .iter()
+        .map(|name| (name.as_str().to_string(), json!(schema(*name))))
+        .collect();
+    json!({ "version": SCHEMA_VERSION, "schemas": schemas })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Field lists pinned per schema version: a failure here means a breaking change,
+    /// which needs `SCHEMA_VERSION` bumped along with this list.
+    #[test]
+    fn published_fields_are_stable() {
+        let properties = |name: SchemaName, path: &str| -> Vec<String> {
+            let schema = json!(schema(name));
+            let mut keys: Vec<String> = schema
+                .pointer(path)
+                .and_then(Value::as_object)
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(SCHEMA_VERSION, 1);
+        assert_eq!(
+            properties(SchemaName::Catalog, "/definitions/KindCatalog/properties"),
+            [
+                "categorical",
+                "counter",
+                "description",
+                "first_seen",
+                "interval",
+                "kind",
+                "last_seen",
+                "rows",
+                "sources",
+                "state",
+                "unit"
+            ]
+        );
+        assert_eq!(
+            properties(SchemaName::Status, "/properties"),
+            [
+                "charging",
+                "class",
+                "metric",
+                "percentage",
+                "text",
+                "tooltip",
+                "value"
+            ]
+        );
+        let all = all_schemas();
+        assert_eq!(
+            all["schemas"]["status"]["$id"],
+            "urn:symmetri:schema:status:v1"
+        );
+    }
+}
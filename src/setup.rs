@@ -0,0 +1,441 @@
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+use crate::config;
+use crate::metrics::Collector;
+use crate::sysfs::{find_battery_paths, find_peripheral_battery_paths};
+
+const DEFAULT_INTERVAL_SECONDS: u64 = 300;
+
+/// Hardware and services found on this machine, used to suggest collectors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Detected {
+    pub batteries: Vec<String>,
+    pub peripherals: Vec<String>,
+    pub gpus: Vec<String>,
+    pub sensors: Vec<String>,
+    pub interfaces: Vec<String>,
+    pub containers: bool,
+    pub systemd: bool,
+}
+
+impl Detected {
+    /// Whether a collector is worth enabling by default.
+    fn suggests(&self, collector: Collector) -> bool {
+        match collector {
+            Collector::Battery => !self.batteries.is_empty(),
+            Collector::Peripherals => !self.peripherals.is_empty(),
+            Collector::Gpu => !self.gpus.is_empty(),
+            Collector::Temperature => !self.sensors.is_empty(),
+            Collector::Network => !self.interfaces.is_empty(),
+            Collector::Containers => self.containers,
+            Collector::Systemd => self.systemd,
+            Collector::Cpu
+            | Collector::Memory
+            | Collector::Disk
+            | Collector::Power
+            | Collector::Stats => true,
+        }
+    }
+}
+
+fn file_names(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .collect()
+}
+
+fn dir_entries(dir: &Path, keep: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|name| keep(name))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+fn sensor_names() -> Vec<String> {
+    let mut sensors: Vec<String> = dir_entries(Path::new("/sys/class/hwmon"), |_| true)
+        .into_iter()
+        .filter_map(|hwmon| {
+            fs::read_to_string(Path::new("/sys/class/hwmon").join(hwmon).join("name")).ok()
+        })
+        .chain(
+            dir_entries(Path::new("/sys/class/thermal"), |n| {
+                n.starts_with("thermal_zone")
+            })
+            .into_iter()
+            .filter_map(|zone| {
+                fs::read_to_string(Path::new("/sys/class/thermal").join(zone).join("type")).ok()
+            }),
+        )
+        .map(|name| name.trim().to_string())
+        .collect();
+    sensors.sort();
+    sensors.dedup();
+    sensors
+}
+
+pub fn detect() -> Detected {
+    let power_supply = Path::new("/sys/class/power_supply");
+    Detected {
+        batteries: file_names(&find_battery_paths(power_supply)),
+        peripherals: file_names(&find_peripheral_battery_paths(power_supply)),
+        gpus: dir_entries(Path::new("/sys/class/drm"), |n| {
+            n.starts_with("card") && !n.contains('-')
+        }),
+        sensors: sensor_names(),
+        interfaces: dir_entries(Path::new("/sys/class/net"), |n| n != "lo"),
+        containers: [
+            "/var/run/docker.sock",
+            "/var/lib/docker",
+            "/var/lib/containers",
+        ]
+        .iter()
+        .any(|p| Path::new(p).exists()),
+        systemd: Path::new("/run/systemd/system").exists(),
+    }
+}
+
+/// Where the wizard writes its results.
+#[derive(Debug, Clone)]
+pub struct SetupPaths {
+    pub config: PathBuf,
+    /// Whether `config` was given explicitly and must be passed to the collector
+    pub explicit_config: bool,
+    pub unit_dir: PathBuf,
+    pub exe: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupOutcome {
+    pub disabled: Vec<Collector>,
+    pub interval_seconds: u64,
+    pub config_written: bool,
+    pub units_written: bool,
+}
+
+fn read_answer(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+fn ask_yes_no(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: bool,
+) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    loop {
+        write!(output, "{question} {hint} ")?;
+        output.flush()?;
+        let Some(answer) = read_answer(input)? else {
+            writeln!(output)?;
+            return Ok(default);
+        };
+        match answer.to_ascii_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => writeln!(output, "Please answer y or n.")?,
+        }
+    }
+}
+
+fn ask_interval(input: &mut impl BufRead, output: &mut impl Write) -> Result<u64> {
+    loop {
+        write!(
+            output,
+            "Collection interval in seconds [{DEFAULT_INTERVAL_SECONDS}] "
+        )?;
+        output.flush()?;
+        let Some(answer) = read_answer(input)? else {
+            writeln!(output)?;
+            return Ok(DEFAULT_INTERVAL_SECONDS);
+        };
+        if answer.is_empty() {
+            return Ok(DEFAULT_INTERVAL_SECONDS);
+        }
+        match answer.parse::<u64>() {
+            Ok(seconds) if seconds >= 10 => return Ok(seconds),
+            _ => writeln!(output, "Please enter a number of seconds (at least 10).")?,
+        }
+    }
+}
+
+fn describe(items: &[String]) -> String {
+    if items.is_empty() {
+        "none found".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn yes_no(found: bool) -> &'static str {
+    if found {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+pub fn render_config(disabled: &[Collector]) -> String {
+    let disabled: Vec<String> = disabled.iter().map(|c| format!("\"{c}\"")).collect();
+    format!(
+        "# Written by `symmetri setup`; see the README for all options.\n\n[collect]\ndisabled = [{}]\n",
+        disabled.join(", ")
+    )
+}
+
+fn collect_command(paths: &SetupPaths) -> String {
+    let mut command = format!("{} collect", paths.exe.display());
+    if paths.explicit_config {
+        command.push_str(&format!(" --config {}", paths.config.display()));
+    }
+    command
+}
+
+pub fn render_service(paths: &SetupPaths) -> String {
+    format!(
+        "[Unit]\nDescription=Symmetri metrics collector\nConditionPathExists=/sys\n\n[Service]\nType=oneshot\nExecStart={}\n",
+        collect_command(paths)
+    )
+}
+
+pub fn render_timer(interval_seconds: u64) -> String {
+    format!(
+        "[Unit]\nDescription=Collect system metrics periodically\n\n[Timer]\nOnBootSec=1min\nOnUnitActiveSec={interval_seconds}s\nAccuracySec=1s\nUnit=symmetri.service\n\n[Install]\nWantedBy=timers.target\n"
+    )
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Ask which collectors to enable and how often to collect, then write the config and,
+/// optionally, systemd user units. Unanswered questions (EOF) take their defaults.
+pub fn run_wizard(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    detected: &Detected,
+    paths: &SetupPaths,
+) -> Result<SetupOutcome> {
+    writeln!(output, "Detected on this machine:")?;
+    writeln!(output, "  Batteries:    {}", describe(&detected.batteries))?;
+    writeln!(
+        output,
+        "  Peripherals:  {}",
+        describe(&detected.peripherals)
+    )?;
+    writeln!(output, "  GPUs:         {}", describe(&detected.gpus))?;
+    writeln!(output, "  Sensors:      {}", describe(&detected.sensors))?;
+    writeln!(output, "  Interfaces:   {}", describe(&detected.interfaces))?;
+    writeln!(output, "  Containers:   {}", yes_no(detected.containers))?;
+    writeln!(output, "  systemd:      {}", yes_no(detected.systemd))?;
+    writeln!(output)?;
+
+    let mut disabled = Vec::new();
+    for collector in Collector::all() {
+        let question = format!("Enable the {collector} collector?");
+        if !ask_yes_no(input, output, &question, detected.suggests(collector))? {
+            disabled.push(collector);
+        }
+    }
+    let interval_seconds = ask_interval(input, output)?;
+
+    let config_text = render_config(&disabled);
+    let config_written = if paths.config.exists() {
+        let question = format!("{} exists; overwrite it?", paths.config.display());
+        ask_yes_no(input, output, &question, false)?
+    } else {
+        ask_yes_no(
+            input,
+            output,
+            &format!("Write config to {}?", paths.config.display()),
+            true,
+        )?
+    };
+    if config_written {
+        write_file(&paths.config, &config_text)?;
+        writeln!(output, "Wrote {}", paths.config.display())?;
+    } else {
+        writeln!(
+            output,
+            "Config not written; add this to it yourself:\n\n{config_text}"
+        )?;
+    }
+
+    let units_written = detected.systemd
+        && ask_yes_no(
+            input,
+            output,
+            "Install a systemd user timer for periodic collection?",
+            false,
+        )?;
+    if units_written {
+        write_file(
+            &paths.unit_dir.join("symmetri.service"),
+            &render_service(paths),
+        )?;
+        write_file(
+            &paths.unit_dir.join("symmetri.timer"),
+            &render_timer(interval_seconds),
+        )?;
+        writeln!(
+            output,
+            "Wrote symmetri.service and symmetri.timer to {}",
+            paths.unit_dir.display()
+        )?;
+    } else {
+        writeln!(
+            output,
+            "To collect in the foreground instead: {} --interval {interval_seconds}",
+            collect_command(paths)
+        )?;
+    }
+
+    Ok(SetupOutcome {
+        disabled,
+        interval_seconds,
+        config_written,
+        units_written,
+    })
+}
+
+fn systemctl_user(args: &[&str]) -> Result<()> {
+    let status = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("systemctl --user {} failed ({status})", args.join(" "));
+    }
+    Ok(())
+}
+
+/// Interactive `symmetri setup` on stdin/stdout.
+pub fn run(config_path: Option<&Path>) -> Result<()> {
+    let (config, explicit_config) = config::resolve_config_path(config_path);
+    let paths = SetupPaths {
+        config,
+        explicit_config,
+        unit_dir: dirs::config_dir()
+            .context("no config directory for systemd user units")?
+            .join("systemd")
+            .join("user"),
+        exe: std::env::current_exe().context("failed to locate the symmetri binary")?,
+    };
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    let outcome = run_wizard(&mut stdin.lock(), &mut stdout, &detect(), &paths)?;
+
+    if outcome.units_written {
+        let enabled = systemctl_user(&["daemon-reload"])
+            .and_then(|_| systemctl_user(&["enable", "--now", "symmetri.timer"]));
+        match enabled {
+            Ok(()) => println!("Enabled symmetri.timer; the first sample is taken within a minute."),
+            Err(err) => println!(
+                "Could not enable the timer ({err}); run `systemctl --user enable --now symmetri.timer`."
+            ),
+        }
+    }
+    println!("Once some samples are in, run `symmetri report` to see them.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected() -> Detected {
+        Detected {
+            batteries: vec!["BAT0".to_string()],
+            interfaces: vec!["wlan0".to_string()],
+            sensors: vec!["coretemp".to_string()],
+            systemd: true,
+            ..Detected::default()
+        }
+    }
+
+    fn paths(root: &Path) -> SetupPaths {
+        SetupPaths {
+            config: root.join("config.toml"),
+            explicit_config: false,
+            unit_dir: root.join("systemd/user"),
+            exe: PathBuf::from("/usr/bin/symmetri"),
+        }
+    }
+
+    #[test]
+    fn defaults_follow_detected_hardware() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = paths(tmp.path());
+        // Accept every default, then override the interval and install the timer.
+        let answers = format!("{}60\n\ny\n", "\n".repeat(Collector::all().count()));
+        let mut output = Vec::new();
+        let outcome =
+            run_wizard(&mut answers.as_bytes(), &mut output, &detected(), &paths).unwrap();
+
+        assert_eq!(
+            outcome.disabled,
+            vec![
+                Collector::Peripherals,
+                Collector::Gpu,
+                Collector::Containers
+            ]
+        );
+        assert_eq!(outcome.interval_seconds, 60);
+        let config = config::parse_config(&fs::read_to_string(&paths.config).unwrap()).unwrap();
+        assert!(!config.collect.is_enabled(Collector::Gpu));
+        assert!(config.collect.is_enabled(Collector::Battery));
+        let timer = fs::read_to_string(paths.unit_dir.join("symmetri.timer")).unwrap();
+        assert!(timer.contains("OnUnitActiveSec=60s"));
+        let service = fs::read_to_string(paths.unit_dir.join("symmetri.service")).unwrap();
+        assert!(service.contains("ExecStart=/usr/bin/symmetri collect\n"));
+    }
+
+    #[test]
+    fn existing_config_is_kept_unless_confirmed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = paths(tmp.path());
+        fs::write(&paths.config, "[report]\ngraph = true\n").unwrap();
+        // Disable battery, keep the rest, invalid interval then default; EOF afterwards.
+        let answers = format!(
+            "maybe\nn\n{}abc\n\n",
+            "\n".repeat(Collector::all().count() - 1)
+        );
+        let mut output = Vec::new();
+        let outcome =
+            run_wizard(&mut answers.as_bytes(), &mut output, &detected(), &paths).unwrap();
+
+        assert_eq!(outcome.disabled[0], Collector::Battery);
+        assert_eq!(outcome.interval_seconds, DEFAULT_INTERVAL_SECONDS);
+        assert!(!outcome.config_written);
+        assert!(!outcome.units_written);
+        assert_eq!(
+            fs::read_to_string(&paths.config).unwrap(),
+            "[report]\ngraph = true\n"
+        );
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains("Please answer y or n."));
+        assert!(text.contains("disabled = [\"battery\""));
+    }
+}
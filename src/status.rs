@@ -0,0 +1,731 @@
+//! One-line output of the latest samples for status bars (i3blocks, polybar), with click
+//! actions that cycle the shown metric or open a report.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::ValueEnum;
+use log::warn;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cli_helpers::average_rates;
+use crate::config::StatusConfig;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::storage::{self, Storage};
+
+/// Samples older than this are not shown, so a stopped collector does not leave stale values.
+const MAX_SAMPLE_AGE_SECS: f64 = 30.0 * 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusMetric {
+    Battery,
+    Power,
+    Cpu,
+    Temperature,
+    Memory,
+}
+
+impl StatusMetric {
+    pub const ALL: [StatusMetric; 5] = [
+        StatusMetric::Battery,
+        StatusMetric::Power,
+        StatusMetric::Cpu,
+        StatusMetric::Temperature,
+        StatusMetric::Memory,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            StatusMetric::Battery => "BAT",
+            StatusMetric::Power => "PWR",
+            StatusMetric::Cpu => "CPU",
+            StatusMetric::Temperature => "TEMP",
+            StatusMetric::Memory => "MEM",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            StatusMetric::Battery => "Battery level",
+            StatusMetric::Power => "Power draw",
+            StatusMetric::Cpu => "CPU usage",
+            StatusMetric::Temperature => "Hottest temperature sensor",
+            StatusMetric::Memory => "Used memory",
+        }
+    }
+
+    fn kinds(self) -> &'static [MetricKind] {
+        match self {
+            StatusMetric::Battery => &[MetricKind::BatteryPercentage, MetricKind::BatteryStatus],
+            StatusMetric::Power => &[MetricKind::PowerDraw],
+            StatusMetric::Cpu => &[MetricKind::CpuUsage],
+            StatusMetric::Temperature => &[MetricKind::Temperature],
+            StatusMetric::Memory => &[MetricKind::MemoryUsage],
+        }
+    }
+
+    /// Warning and critical thresholds; battery levels are critical when low.
+    fn urgency(self, value: f64) -> Urgency {
+        let (warning, critical) = match self {
+            StatusMetric::Battery => {
+                return if value <= 10.0 {
+                    Urgency::Critical
+                } else if value <= 25.0 {
+                    Urgency::Warning
+                } else {
+                    Urgency::Normal
+                };
+            }
+            StatusMetric::Power => (25.0, 45.0),
+            StatusMetric::Cpu => (75.0, 95.0),
+            StatusMetric::Temperature => (75.0, 90.0),
+            StatusMetric::Memory => (80.0, 95.0),
+        };
+        if value >= critical {
+            Urgency::Critical
+        } else if value >= warning {
+            Urgency::Warning
+        } else {
+            Urgency::Normal
+        }
+    }
+}
+
+/// Output protocol of the status line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StatusFormat {
+    /// Just the text
+    #[default]
+    Plain,
+    /// Full text, short text and color lines
+    I3blocks,
+    /// Text wrapped in polybar color tags when above a threshold
+    Polybar,
+    /// Text wrapped in tmux style escapes, for `status-right`
+    Tmux,
+    /// JSON object, read as is by waybar custom modules
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl Urgency {
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Urgency::Normal => None,
+            Urgency::Warning => Some("#FFB52A"),
+            Urgency::Critical => Some("#FF5555"),
+        }
+    }
+}
+
+/// Latest reading of a status metric.
+#[derive(Debug, Clone, PartialEq)]
+struct StatusValue {
+    metric: StatusMetric,
+    value: f64,
+    charging: bool,
+}
+
+impl StatusValue {
+    fn text(&self) -> String {
+        match self.metric {
+            StatusMetric::Battery => {
+                let sign = if self.charging { "+" } else { "" };
+                format!("{:.0}%{sign}", self.value)
+            }
+            StatusMetric::Power => format!("{:.1}W", self.value),
+            StatusMetric::Cpu | StatusMetric::Memory => format!("{:.0}%", self.value),
+            StatusMetric::Temperature => format!("{:.0}°C", self.value),
+        }
+    }
+
+    fn urgency(&self) -> Urgency {
+        if self.metric == StatusMetric::Battery && self.charging {
+            return Urgency::Normal;
+        }
+        self.metric.urgency(self.value)
+    }
+}
+
+/// Reading of `metric` at the newest timestamp among `samples`: the averaged battery level
+/// and power draw, the overall CPU usage, the hottest sensor and the used memory share.
+fn status_value(metric: StatusMetric, samples: &[MetricSample]) -> Option<StatusValue> {
+    let value_kind = &metric.kinds()[0];
+    let relevant: Vec<&MetricSample> = samples
+        .iter()
+        .filter(|s| &s.kind == value_kind && s.value.is_some())
+        .filter(|s| metric != StatusMetric::Cpu || s.source == "cpu")
+        .collect();
+    let latest = relevant
+        .iter()
+        .map(|s| s.ts)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let at_latest: Vec<&MetricSample> = relevant.into_iter().filter(|s| s.ts == latest).collect();
+    if at_latest.is_empty() {
+        return None;
+    }
+    let values = at_latest.iter().filter_map(|s| match metric {
+        StatusMetric::Memory => {
+            let total = s.total_bytes?;
+            let used = s.value?;
+            (total > 0.0).then(|| used / total * 100.0)
+        }
+        _ => s.value,
+    });
+    let value = match metric {
+        StatusMetric::Temperature => values.fold(f64::NEG_INFINITY, f64::max),
+        _ => {
+            let values: Vec<f64> = values.collect();
+            if values.is_empty() {
+                return None;
+            }
+            values.iter().sum::<f64>() / values.len() as f64
+        }
+    };
+    if !value.is_finite() {
+        return None;
+    }
+    let charging = metric == StatusMetric::Battery
+        && samples
+            .iter()
+            .filter(|s| s.kind == MetricKind::BatteryStatus)
+            .max_by(|a, b| a.ts.total_cmp(&b.ts))
+            .and_then(|s| s.label.as_deref())
+            == Some("Charging");
+    Some(StatusValue {
+        metric,
+        value,
+        charging,
+    })
+}
+
+/// Battery readings the time-remaining estimate averages its rate over.
+pub const BATTERY_RATE_WINDOW_SECS: f64 = 30.0 * 60.0;
+
+/// Time the batteries need at their recent rate: until empty while discharging, until
+/// full while charging.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryEta {
+    pub hours: f64,
+    pub charging: bool,
+}
+
+/// Estimate from the battery samples of the last `BATTERY_RATE_WINDOW_SECS`, all packs
+/// combined; `None` without a steady charge or discharge to extrapolate.
+pub fn battery_eta(samples: &[MetricSample]) -> Option<BatteryEta> {
+    let mut combined = crate::aggregate::aggregate_multi_device_metrics(samples);
+    combined.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    let rates = average_rates(&combined);
+    let latest = |kind: MetricKind| {
+        combined
+            .iter()
+            .rev()
+            .find(|s| s.kind == kind && s.value.is_some())
+    };
+    let now = latest(MetricKind::BatteryEnergyNow)?;
+    let energy = now.value?;
+    let (hours, charging) = match now.status.as_deref() {
+        Some("Charging") => {
+            let full = latest(MetricKind::BatteryEnergyFull)?.value?;
+            ((full - energy) / rates.charge_w?, true)
+        }
+        Some("Discharging") => (energy / rates.discharge_w?, false),
+        _ => return None,
+    };
+    (hours.is_finite() && hours >= 0.0).then_some(BatteryEta { hours, charging })
+}
+
+/// `--format json` output. The `text`, `tooltip`, `class` and `percentage` fields follow
+/// the waybar custom module protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct StatusJson {
+    /// Shown text, e.g. `BAT 73%+`
+    pub text: String,
+    pub tooltip: String,
+    /// Threshold the value crossed, usable as a CSS class
+    pub class: Urgency,
+    /// Rounded value of percentage metrics (battery, CPU, memory)
+    pub percentage: Option<u8>,
+    pub metric: StatusMetric,
+    /// `None` when no recent sample exists
+    pub value: Option<f64>,
+    /// Whether the battery is charging
+    pub charging: bool,
+}
+
+/// Render the status of `metric` in the given protocol. Persistent i3blocks blocks read one
+/// line per update, so `single_line` drops the short text and color lines.
+fn render(
+    metric: StatusMetric,
+    value: Option<&StatusValue>,
+    format: StatusFormat,
+    single_line: bool,
+) -> String {
+    let short = value.map_or_else(|| "n/a".to_string(), StatusValue::text);
+    let full = format!("{} {short}", metric.label());
+    let color = value.and_then(|v| v.urgency().color());
+    if format == StatusFormat::Json {
+        let percent = matches!(
+            metric,
+            StatusMetric::Battery | StatusMetric::Cpu | StatusMetric::Memory
+        );
+        let json = StatusJson {
+            text: full,
+            tooltip: metric.description().to_string(),
+            class: value.map_or(Urgency::Normal, StatusValue::urgency),
+            percentage: value
+                .filter(|_| percent)
+                .map(|v| v.value.round().clamp(0.0, 100.0) as u8),
+            metric,
+            value: value.map(|v| v.value),
+            charging: value.is_some_and(|v| v.charging),
+        };
+        return serde_json::to_string(&json).unwrap_or_default();
+    }
+    match (format, color) {
+        (StatusFormat::I3blocks, Some(color)) if !single_line => {
+            format!("{full}\n{short}\n{color}")
+        }
+        (StatusFormat::I3blocks, None) if !single_line => format!("{full}\n{short}"),
+        (StatusFormat::Polybar, Some(color)) => format!("%{{F{color}}}{full}%{{F-}}"),
+        (StatusFormat::Tmux, Some(color)) => format!("#[fg={color}]{full}#[fg=default]"),
+        _ => full,
+    }
+}
+
+/// `--plain` text: the degree sign is the only non-ASCII character a status line holds.
+fn ascii_only(line: &str) -> String {
+    line.replace('°', "")
+}
+
+/// What a mouse button does on the status block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClickAction {
+    Next,
+    Previous,
+    Open,
+}
+
+/// Map an i3blocks/polybar button (`1`..`5` or `left`, `right`, `up`, `down`) to an action.
+/// Left click and scrolling down cycle forward, scrolling up cycles back, right click opens
+/// the report; the middle button does nothing.
+fn click_action(button: &str) -> Option<ClickAction> {
+    match button.trim().to_ascii_lowercase().as_str() {
+        "1" | "left" | "5" | "down" => Some(ClickAction::Next),
+        "4" | "up" => Some(ClickAction::Previous),
+        "3" | "right" => Some(ClickAction::Open),
+        _ => None,
+    }
+}
+
+/// Button of a click event sent on stdin by persistent i3blocks blocks: a JSON object with
+/// a `button` field, or a bare button number.
+fn parse_click_event(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(serde_json::Value::Object(event)) => event.get("button").map(|b| match b {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }),
+        _ => Some(line.to_string()),
+    }
+}
+
+fn cycle(metrics: &[StatusMetric], current: StatusMetric, step: isize) -> StatusMetric {
+    let len = metrics.len() as isize;
+    let idx = metrics.iter().position(|m| *m == current).unwrap_or(0) as isize;
+    metrics[(idx + step).rem_euclid(len) as usize]
+}
+
+/// File remembering the metric the bar shows between invocations.
+fn state_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("symmetri")
+        .join("status-metric")
+}
+
+fn load_metric(path: &Path, metrics: &[StatusMetric]) -> StatusMetric {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| StatusMetric::from_str(raw.trim(), true).ok())
+        .filter(|metric| metrics.contains(metric))
+        .unwrap_or(metrics[0])
+}
+
+fn save_metric(path: &Path, metric: StatusMetric) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let name = metric.to_possible_value().map(|v| v.get_name().to_string());
+    fs::write(path, name.unwrap_or_default())
+        .with_context(|| format!("failed to save status state to {}", path.display()))
+}
+
+/// Start the configured report command without waiting for it.
+fn open_report(command: Option<&[String]>) {
+    let Some((program, args)) = command.and_then(|c| c.split_first()) else {
+        warn!("right click ignored: set status.open_command in the config");
+        return;
+    };
+    let spawned = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(err) = spawned {
+        warn!("failed to run status.open_command {program}: {err}");
+    }
+}
+
+pub struct StatusOptions {
+    /// Show this metric instead of the remembered one
+    pub metric: Option<StatusMetric>,
+    pub format: StatusFormat,
+    /// Button pressed, from `--click` or the i3blocks environment
+    pub click: Option<String>,
+    /// ASCII text without color tags or escapes, whatever the format (JSON aside)
+    pub plain: bool,
+    /// Keep running, printing a line every this many seconds and reading clicks on stdin
+    pub watch: Option<u64>,
+}
+
+struct StatusBar<'a> {
+    store: Box<dyn Storage>,
+    config: &'a StatusConfig,
+    metrics: Vec<StatusMetric>,
+    state: PathBuf,
+}
+
+impl StatusBar<'_> {
+    fn current(&self) -> StatusMetric {
+        load_metric(&self.state, &self.metrics)
+    }
+
+    fn handle_click(&self, button: &str) -> Result<()> {
+        let step = match click_action(button) {
+            Some(ClickAction::Next) => 1,
+            Some(ClickAction::Previous) => -1,
+            Some(ClickAction::Open) => {
+                open_report(self.config.open_command.as_deref());
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        save_metric(&self.state, cycle(&self.metrics, self.current(), step))
+    }
+
+    fn line(
+        &self,
+        metric: StatusMetric,
+        format: StatusFormat,
+        single_line: bool,
+    ) -> Result<String> {
+        let since = Utc::now().timestamp() as f64 - MAX_SAMPLE_AGE_SECS;
+        let samples = self
+            .store
+            .fetch_samples(Some(since), None, Some(metric.kinds()))?;
+        Ok(render(
+            metric,
+            status_value(metric, &samples).as_ref(),
+            format,
+            single_line,
+        ))
+    }
+
+    /// Line for `--metric`, the metric chosen by clicking, or every `tmux_metrics` entry
+    /// side by side for tmux, where clicks do not reach the command.
+    fn output(&self, options: &StatusOptions, single_line: bool) -> Result<String> {
+        let shown = match (options.metric, options.format) {
+            (Some(metric), _) => vec![metric],
+            (None, StatusFormat::Tmux) if self.config.tmux_metrics.is_empty() => {
+                vec![StatusMetric::Battery, StatusMetric::Temperature]
+            }
+            (None, StatusFormat::Tmux) => self.config.tmux_metrics.clone(),
+            (None, _) => vec![self.current()],
+        };
+        let format = match options.format {
+            StatusFormat::Json => StatusFormat::Json,
+            _ if options.plain => StatusFormat::Plain,
+            format => format,
+        };
+        let lines = shown
+            .into_iter()
+            .map(|metric| self.line(metric, format, single_line))
+            .collect::<Result<Vec<_>>>()?;
+        let line = lines.join(" ");
+        Ok(if options.plain {
+            ascii_only(&line)
+        } else {
+            line
+        })
+    }
+}
+
+/// Print the status line, handling a click first when one is given.
+pub fn run(db_path: &Path, config: &StatusConfig, options: StatusOptions) -> Result<()> {
+    let metrics = if config.metrics.is_empty() {
+        StatusMetric::ALL.to_vec()
+    } else {
+        config.metrics.clone()
+    };
+    let bar = StatusBar {
+        store: storage::open(db_path)?,
+        config,
+        metrics,
+        state: state_path(),
+    };
+    let click = options
+        .click
+        .clone()
+        .or_else(|| std::env::var("BLOCK_BUTTON").ok())
+        .or_else(|| std::env::var("button").ok());
+    if let Some(button) = click {
+        bar.handle_click(&button)?;
+    }
+    let Some(interval) = options.watch else {
+        println!("{}", bar.output(&options, false)?);
+        return Ok(());
+    };
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if let Some(button) = parse_click_event(&line) {
+                if tx.send(button).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    let mut stdout = io::stdout();
+    loop {
+        writeln!(stdout, "{}", bar.output(&options, true)?)?;
+        stdout.flush()?;
+        match rx.recv_timeout(Duration::from_secs(interval.max(1))) {
+            Ok(button) => bar.handle_click(&button)?,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            // stdin closed: keep refreshing on the timer alone
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                thread::sleep(Duration::from_secs(interval.max(1)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample(ts: f64, kind: MetricKind, source: &str, value: f64) -> MetricSample {
+        MetricSample::new(ts, kind, source, Some(value), None, json!({}))
+    }
+
+    #[test]
+    fn latest_values_render_for_each_protocol() {
+        let mut status = sample(20.0, MetricKind::BatteryStatus, "BAT0", 0.0);
+        status.label = Some("Discharging".to_string());
+        let samples = vec![
+            sample(10.0, MetricKind::BatteryPercentage, "BAT0", 90.0),
+            sample(20.0, MetricKind::BatteryPercentage, "BAT0", 10.0),
+            sample(20.0, MetricKind::BatteryPercentage, "BAT1", 20.0),
+            status,
+            sample(20.0, MetricKind::Temperature, "acpitz", 40.0),
+            sample(20.0, MetricKind::Temperature, "coretemp:Package id 0", 71.4),
+            sample(20.0, MetricKind::CpuUsage, "cpu0", 100.0),
+            sample(20.0, MetricKind::CpuUsage, "cpu", 12.0),
+        ];
+
+        let battery = status_value(StatusMetric::Battery, &samples).unwrap();
+        assert_eq!(battery.value, 15.0);
+        assert!(!battery.charging);
+        assert_eq!(
+            render(
+                StatusMetric::Battery,
+                Some(&battery),
+                StatusFormat::I3blocks,
+                false
+            ),
+            "BAT 15%\n15%\n#FFB52A"
+        );
+        assert_eq!(
+            render(
+                StatusMetric::Battery,
+                Some(&battery),
+                StatusFormat::Polybar,
+                false
+            ),
+            "%{F#FFB52A}BAT 15%%{F-}"
+        );
+
+        let temp = status_value(StatusMetric::Temperature, &samples).unwrap();
+        assert_eq!(
+            render(
+                StatusMetric::Temperature,
+                Some(&temp),
+                StatusFormat::I3blocks,
+                true
+            ),
+            "TEMP 71°C"
+        );
+        assert_eq!(
+            render(
+                StatusMetric::Battery,
+                Some(&battery),
+                StatusFormat::Tmux,
+                true
+            ),
+            "#[fg=#FFB52A]BAT 15%#[fg=default]"
+        );
+        assert_eq!(
+            render(
+                StatusMetric::Temperature,
+                Some(&temp),
+                StatusFormat::Tmux,
+                true
+            ),
+            "TEMP 71°C"
+        );
+        assert_eq!(
+            ascii_only(&render(
+                StatusMetric::Temperature,
+                Some(&temp),
+                StatusFormat::Plain,
+                true
+            )),
+            "TEMP 71C"
+        );
+        let cpu = status_value(StatusMetric::Cpu, &samples).unwrap();
+        assert_eq!(cpu.text(), "12%");
+        assert_eq!(status_value(StatusMetric::Memory, &samples), None);
+        assert_eq!(
+            render(StatusMetric::Memory, None, StatusFormat::Plain, false),
+            "MEM n/a"
+        );
+        let json: serde_json::Value = serde_json::from_str(&render(
+            StatusMetric::Battery,
+            Some(&battery),
+            StatusFormat::Json,
+            true,
+        ))
+        .unwrap();
+        assert_eq!(json["text"], "BAT 15%");
+        assert_eq!(json["class"], "warning");
+        assert_eq!(json["percentage"], 15);
+    }
+
+    #[test]
+    fn battery_eta_follows_the_recent_rate() {
+        let battery = |ts: f64, kind: MetricKind, source: &str, wh: f64, status: &str| {
+            MetricSample::new(
+                ts,
+                kind,
+                source,
+                Some(wh),
+                Some("Wh"),
+                serde_json::Value::Null,
+            )
+            .with_status(status)
+        };
+        // Two packs losing 1 Wh each per 5 minutes: 24 W over 40 Wh left
+        let mut samples = Vec::new();
+        for (i, ts) in [0.0, 300.0, 600.0].into_iter().enumerate() {
+            for source in ["BAT0", "BAT1"] {
+                let now = 22.0 - i as f64;
+                samples.push(battery(
+                    ts,
+                    MetricKind::BatteryEnergyNow,
+                    source,
+                    now,
+                    "Discharging",
+                ));
+                samples.push(battery(
+                    ts,
+                    MetricKind::BatteryEnergyFull,
+                    source,
+                    50.0,
+                    "Discharging",
+                ));
+            }
+        }
+        let eta = battery_eta(&samples).unwrap();
+        assert!(!eta.charging);
+        assert!((eta.hours - 40.0 / 24.0).abs() < 1e-9);
+
+        let charging: Vec<MetricSample> = [(0.0, 40.0), (300.0, 45.0)]
+            .into_iter()
+            .flat_map(|(ts, wh)| {
+                [
+                    battery(ts, MetricKind::BatteryEnergyNow, "BAT0", wh, "Charging"),
+                    battery(ts, MetricKind::BatteryEnergyFull, "BAT0", 50.0, "Charging"),
+                ]
+            })
+            .collect();
+        let eta = battery_eta(&charging).unwrap();
+        assert!(eta.charging);
+        assert!((eta.hours - 5.0 / 60.0).abs() < 1e-9);
+        assert_eq!(battery_eta(&charging[..2]), None);
+    }
+
+    #[test]
+    fn clicks_cycle_the_remembered_metric() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = tmp.path().join("symmetri").join("status-metric");
+        let metrics = [
+            StatusMetric::Battery,
+            StatusMetric::Cpu,
+            StatusMetric::Memory,
+        ];
+        assert_eq!(load_metric(&state, &metrics), StatusMetric::Battery);
+
+        assert_eq!(click_action("1"), Some(ClickAction::Next));
+        assert_eq!(click_action("up"), Some(ClickAction::Previous));
+        assert_eq!(click_action("3"), Some(ClickAction::Open));
+        assert_eq!(click_action("2"), None);
+        assert_eq!(
+            cycle(&metrics, StatusMetric::Battery, -1),
+            StatusMetric::Memory
+        );
+        assert_eq!(
+            cycle(&metrics, StatusMetric::Memory, 1),
+            StatusMetric::Battery
+        );
+
+        save_metric(&state, StatusMetric::Cpu).unwrap();
+        assert_eq!(load_metric(&state, &metrics), StatusMetric::Cpu);
+        // A metric dropped from the configured list falls back to the first one
+        assert_eq!(
+            load_metric(&state, &[StatusMetric::Power, StatusMetric::Battery]),
+            StatusMetric::Power
+        );
+
+        assert_eq!(
+            parse_click_event(r#"{"name":"symmetri","button":4,"x":10}"#).as_deref(),
+            Some("4")
+        );
+        assert_eq!(parse_click_event("3\n").as_deref(), Some("3"));
+        assert_eq!(parse_click_event(""), None);
+    }
+}
@@ -0,0 +1,246 @@
+//! Where samples are stored: the local SQLite database of `db.rs`, or with the `postgres`
+//! feature a PostgreSQL/TimescaleDB server that several machines collect into. `--db` and
+//! `SYMMETRI_DB` pick the store, a `postgres://` or `postgresql://` URL meaning the server.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::db::{self, KindCatalog};
+use crate::metrics::{MetricKind, MetricSample};
+use crate::system_info::{SystemInfo, SystemSnapshot};
+
+/// Whether `location` names a PostgreSQL server rather than a database file.
+pub fn is_remote(location: &Path) -> bool {
+    location
+        .to_str()
+        .is_some_and(|url| url.starts_with("postgres://") || url.starts_with("postgresql://"))
+}
+
+/// Open the store at `location`, creating its tables when missing.
+pub fn open(location: &Path) -> Result<Box<dyn Storage>> {
+    if !is_remote(location) {
+        return Ok(Box::new(SqliteStorage::open(location)?));
+    }
+    #[cfg(feature = "postgres")]
+    return Ok(Box::new(crate::pg::PostgresStorage::connect(location)?));
+    #[cfg(not(feature = "postgres"))]
+    anyhow::bail!(
+        "--db is a PostgreSQL URL, but symmetri was built without the `postgres` feature"
+    );
+}
+
+/// Sample storage the collector writes to and the read commands query. Rollups, monthly
+/// archives, the push outbox and the alert log only exist in the local database, reached
+/// through [`Storage::sqlite`].
+pub trait Storage {
+    /// Store `samples` like [`db::insert_metric_samples_with_conn`]; returns how many were
+    /// written.
+    fn insert_samples(
+        &mut self,
+        samples: &[MetricSample],
+        skip_repeats: &[MetricKind],
+    ) -> Result<usize>;
+
+    /// Remember the interval `kinds` are being collected at, replacing earlier values.
+    fn record_collection_intervals(&mut self, kinds: &[&str], interval: f64, ts: f64)
+        -> Result<()>;
+
+    /// Store the machine identity unless it matches the latest snapshot; returns whether a
+    /// row was written.
+    fn record_system_info(&mut self, info: &SystemInfo, ts: f64) -> Result<bool>;
+
+    /// Samples from `since_ts` up to and including `until_ts` (of `kinds`, when given),
+    /// oldest first.
+    fn fetch_samples(
+        &self,
+        since_ts: Option<f64>,
+        until_ts: Option<f64>,
+        kinds: Option<&[MetricKind]>,
+    ) -> Result<Vec<MetricSample>>;
+
+    /// Call `each` with the samples [`Storage::fetch_samples`] would return, without
+    /// holding them all in memory.
+    fn for_each_sample(
+        &self,
+        since_ts: Option<f64>,
+        until_ts: Option<f64>,
+        kinds: Option<&[MetricKind]>,
+        each: &mut dyn FnMut(MetricSample) -> Result<()>,
+    ) -> Result<()>;
+
+    /// Newest sample of each kind and source (of `kinds`, when given).
+    fn fetch_latest(&self, kinds: Option<&[MetricKind]>) -> Result<Vec<MetricSample>>;
+
+    /// Number of samples since `since_ts`.
+    fn count_samples(&self, since_ts: Option<f64>) -> Result<usize>;
+
+    /// Last recorded collection interval in seconds per kind.
+    fn collection_intervals(&self) -> Result<BTreeMap<String, f64>>;
+
+    /// Machine snapshots in effect from `since_ts` on, oldest first.
+    fn system_snapshots(&self, since_ts: Option<f64>) -> Result<Vec<SystemSnapshot>>;
+
+    /// Timestamp of the newest sample of each host, untagged samples under `None`.
+    fn last_seen_by_host(&self) -> Result<Vec<(Option<String>, f64)>>;
+
+    /// Every stored kind with its unit, sources, time span and row count.
+    fn catalog(&self) -> Result<Vec<KindCatalog>>;
+
+    /// Host the samples without a host tag came from, see [`db::database_host`].
+    fn database_host(&self) -> Result<String>;
+
+    /// The local database behind the store, `None` for a server.
+    fn sqlite(&self) -> Option<&Connection>;
+
+    /// Like [`Storage::sqlite`], for writing.
+    fn sqlite_mut(&mut self) -> Option<&mut Connection>;
+}
+
+/// The local SQLite database, including its monthly archives.
+pub struct SqliteStorage {
+    conn: Connection,
+    path: PathBuf,
+}
+
+impl SqliteStorage {
+    pub fn open(db_path: &Path) -> Result<Self> {
+        Ok(Self {
+            conn: db::init_db_connection(db_path)?,
+            path: db_path.to_path_buf(),
+        })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn insert_samples(
+        &mut self,
+        samples: &[MetricSample],
+        skip_repeats: &[MetricKind],
+    ) -> Result<usize> {
+        db::insert_metric_samples_with_conn(&mut self.conn, samples, skip_repeats)
+    }
+
+    fn record_collection_intervals(
+        &mut self,
+        kinds: &[&str],
+        interval: f64,
+        ts: f64,
+    ) -> Result<()> {
+        db::record_collection_intervals(&mut self.conn, kinds.iter().copied(), interval, ts)
+    }
+
+    fn record_system_info(&mut self, info: &SystemInfo, ts: f64) -> Result<bool> {
+        db::record_system_info(&self.conn, info, ts)
+    }
+
+    fn fetch_samples(
+        &self,
+        since_ts: Option<f64>,
+        until_ts: Option<f64>,
+        kinds: Option<&[MetricKind]>,
+    ) -> Result<Vec<MetricSample>> {
+        db::fetch_metric_samples_between(&self.conn, since_ts, until_ts, kinds)
+    }
+
+    fn for_each_sample(
+        &self,
+        since_ts: Option<f64>,
+        until_ts: Option<f64>,
+        kinds: Option<&[MetricKind]>,
+        each: &mut dyn FnMut(MetricSample) -> Result<()>,
+    ) -> Result<()> {
+        db::for_each_metric_sample(&self.conn, since_ts, until_ts, kinds, each)
+    }
+
+    fn fetch_latest(&self, kinds: Option<&[MetricKind]>) -> Result<Vec<MetricSample>> {
+        db::fetch_latest_metric_samples_with_conn(&self.conn, kinds)
+    }
+
+    fn count_samples(&self, since_ts: Option<f64>) -> Result<usize> {
+        db::count_metric_samples_with_conn(&self.conn, since_ts)
+    }
+
+    fn collection_intervals(&self) -> Result<BTreeMap<String, f64>> {
+        db::collection_intervals(&self.conn)
+    }
+
+    fn system_snapshots(&self, since_ts: Option<f64>) -> Result<Vec<SystemSnapshot>> {
+        db::system_snapshots(&self.conn, since_ts)
+    }
+
+    fn last_seen_by_host(&self) -> Result<Vec<(Option<String>, f64)>> {
+        db::last_seen_by_host(&self.conn)
+    }
+
+    fn catalog(&self) -> Result<Vec<KindCatalog>> {
+        db::catalog_with_conn(&self.conn)
+    }
+
+    fn database_host(&self) -> Result<String> {
+        db::database_host(&self.conn, &self.path)
+    }
+
+    fn sqlite(&self) -> Option<&Connection> {
+        Some(&self.conn)
+    }
+
+    fn sqlite_mut(&mut self) -> Option<&mut Connection> {
+        Some(&mut self.conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postgres_urls_open_the_server_store() {
+        assert!(is_remote(Path::new(
+            "postgres://collector@metrics/symmetri"
+        )));
+        assert!(is_remote(Path::new("postgresql:///symmetri")));
+        assert!(!is_remote(Path::new("/var/lib/symmetri/metrics.db")));
+        assert!(!is_remote(Path::new("postgres.db")));
+        #[cfg(not(feature = "postgres"))]
+        {
+            let err = open(Path::new("postgres://localhost/symmetri"))
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("`postgres` feature"));
+        }
+    }
+
+    #[test]
+    fn sqlite_store_reads_back_what_it_writes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut store = open(&tmp.path().join("metrics.db")).unwrap();
+        let sample = |ts: f64, value: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::CpuUsage,
+                "cpu",
+                Some(value),
+                Some("%"),
+                serde_json::Value::Null,
+            )
+        };
+        let written = store
+            .insert_samples(&[sample(1.0, 10.0), sample(2.0, 20.0)], &[])
+            .unwrap();
+        assert_eq!(written, 2);
+        store
+            .record_collection_intervals(&["cpu_usage"], 60.0, 2.0)
+            .unwrap();
+        assert_eq!(store.count_samples(Some(2.0)).unwrap(), 1);
+        assert_eq!(store.fetch_samples(None, Some(1.0), None).unwrap().len(), 1);
+        assert_eq!(store.fetch_latest(None).unwrap()[0].value, Some(20.0));
+        assert_eq!(store.collection_intervals().unwrap()["cpu_usage"], 60.0);
+        assert_eq!(store.catalog().unwrap()[0].rows, 2);
+        assert_eq!(store.last_seen_by_host().unwrap(), [(None, 2.0)]);
+        assert_eq!(store.database_host().unwrap(), "metrics");
+        assert!(store.sqlite().is_some());
+    }
+}
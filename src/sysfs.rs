@@ -25,9 +25,6 @@ pub fn create_battery_metrics(reading: &BatteryReading, ts: f64) -> Vec<MetricSa
         .unwrap_or_else(|| reading.path.to_string_lossy().to_string());
 
     let mut metrics = Vec::new();
-    let details = json!({
-        "status": reading.status
-    });
 
     if let Some(percentage) = reading.percentage {
         metrics.push(MetricSample::new(
@@ -36,7 +33,7 @@ pub fn create_battery_metrics(reading: &BatteryReading, ts: f64) -> Vec<MetricSa
             &source,
             Some(percentage),
             Some("%"),
-            details.clone(),
+            serde_json::Value::Null,
         ));
     }
 
@@ -47,7 +44,7 @@ pub fn create_battery_metrics(reading: &BatteryReading, ts: f64) -> Vec<MetricSa
             &source,
             Some(capacity),
             Some("%"),
-            details.clone(),
+            serde_json::Value::Null,
         ));
     }
 
@@ -58,7 +55,7 @@ pub fn create_battery_metrics(reading: &BatteryReading, ts: f64) -> Vec<MetricSa
             &source,
             Some(health),
             Some("%"),
-            details.clone(),
+            serde_json::Value::Null,
         ));
     }
 
@@ -69,7 +66,7 @@ pub fn create_battery_metrics(reading: &BatteryReading, ts: f64) -> Vec<MetricSa
             &source,
             Some(energy),
             Some("Wh"),
-            details.clone(),
+            serde_json::Value::Null,
         ));
     }
 
@@ -80,7 +77,7 @@ pub fn create_battery_metrics(reading: &BatteryReading, ts: f64) -> Vec<MetricSa
             &source,
             Some(energy),
             Some("Wh"),
-            details.clone(),
+            serde_json::Value::Null,
         ));
     }
 
@@ -91,10 +88,27 @@ pub fn create_battery_metrics(reading: &BatteryReading, ts: f64) -> Vec<MetricSa
             &source,
             Some(energy),
             Some("Wh"),
-            details.clone(),
+            serde_json::Value::Null,
         ));
     }
 
+    for metric in &mut metrics {
+        metric.status = reading.status.clone();
+    }
+    if let Some(status) = reading.status.as_deref().filter(|_| !metrics.is_empty()) {
+        metrics.push(
+            MetricSample::new(
+                ts,
+                MetricKind::BatteryStatus,
+                &source,
+                None,
+                None,
+                serde_json::Value::Null,
+            )
+            .with_label(status),
+        );
+    }
+
     metrics
 }
 
@@ -195,6 +209,125 @@ pub fn find_battery_paths(sysfs_root: &Path) -> Vec<PathBuf> {
     batteries
 }
 
+/// Batteries of connected peripherals (Bluetooth/USB HID devices), which the kernel
+/// exposes next to the system batteries with `scope=Device` or an `hid-` prefix.
+pub fn find_peripheral_battery_paths(sysfs_root: &Path) -> Vec<PathBuf> {
+    let mut batteries = Vec::new();
+    let entries = match fs::read_dir(sysfs_root) {
+        Ok(entries) => entries,
+        Err(_) => return batteries,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("BAT") {
+            continue;
+        }
+        let is_battery = read_str(&path.join("type"))
+            .map(|t| t.eq_ignore_ascii_case("battery"))
+            .unwrap_or(false);
+        let is_device_scope = read_str(&path.join("scope"))
+            .map(|s| s.eq_ignore_ascii_case("device"))
+            .unwrap_or(false);
+        if is_battery && (is_device_scope || name.starts_with("hid-")) {
+            batteries.push(path);
+        }
+    }
+    batteries.sort();
+    batteries
+}
+
+pub fn peripheral_battery_metric(path: &Path, ts: f64) -> Option<MetricSample> {
+    let uevent = parse_uevent(path);
+    let capacity = float_from_uevent(&uevent, &["POWER_SUPPLY_CAPACITY"])
+        .or_else(|| read_float(&path.join("capacity")))?;
+    let dir_name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string());
+    let model = uevent
+        .get("POWER_SUPPLY_MODEL_NAME")
+        .cloned()
+        .or_else(|| read_str(&path.join("model_name")));
+    let status = uevent
+        .get("POWER_SUPPLY_STATUS")
+        .cloned()
+        .or_else(|| read_str(&path.join("status")));
+    let mut sample = MetricSample::new(
+        ts,
+        MetricKind::PeripheralBattery,
+        model.unwrap_or_else(|| dir_name.clone()),
+        Some(capacity),
+        Some("%"),
+        json!({ "device": dir_name }),
+    );
+    sample.status = status;
+    Some(sample)
+}
+
+/// Whether each AC adapter (`type=Mains`) is plugged in.
+pub fn ac_online_samples(sysfs_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for entry in fs::read_dir(sysfs_root).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let is_mains =
+            read_str(&path.join("type")).is_some_and(|t| t.eq_ignore_ascii_case("mains"));
+        let Some(online) = read_float(&path.join("online")).filter(|_| is_mains) else {
+            continue;
+        };
+        samples.push(MetricSample::new(
+            ts,
+            MetricKind::AcOnline,
+            entry.file_name().to_string_lossy(),
+            Some(if online > 0.0 { 1.0 } else { 0.0 }),
+            Some("bool"),
+            serde_json::Value::Null,
+        ));
+    }
+    samples
+}
+
+/// The active entry of a `usb_type` list, which brackets it: `C [PD] PD_PPS`.
+fn active_usb_type(raw: &str) -> Option<&str> {
+    raw.split_whitespace()
+        .find_map(|entry| entry.strip_prefix('[')?.strip_suffix(']'))
+}
+
+/// Negotiated wattage of each online charger (Mains or USB supply exposing `voltage_max`
+/// and `current_max`), e.g. 20V x 3.25A for a 65W USB-PD brick.
+pub fn charger_power_samples(sysfs_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for entry in fs::read_dir(sysfs_root).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let is_charger = read_str(&path.join("type"))
+            .is_some_and(|t| t.eq_ignore_ascii_case("mains") || t.starts_with("USB"));
+        if !is_charger || read_float(&path.join("online")).is_none_or(|online| online <= 0.0) {
+            continue;
+        }
+        let (Some(voltage_uv), Some(current_ua)) = (
+            read_float(&path.join("voltage_max")),
+            read_float(&path.join("current_max")),
+        ) else {
+            continue;
+        };
+        let (volts, amps) = (voltage_uv / 1_000_000.0, current_ua / 1_000_000.0);
+        let usb_type = read_str(&path.join("usb_type"));
+        samples.push(MetricSample::new(
+            ts,
+            MetricKind::ChargerPower,
+            entry.file_name().to_string_lossy(),
+            Some(volts * amps),
+            Some("W"),
+            serde_json::json!({
+                "usb_type": usb_type.as_deref().and_then(active_usb_type),
+                "voltage_max_v": volts,
+                "current_max_a": amps,
+            }),
+        ));
+    }
+    samples
+}
+
 pub fn read_battery(path: &Path) -> BatteryReading {
     let uevent = parse_uevent(path);
 
@@ -299,9 +432,64 @@ mod tests {
         let ac = tmp.path().join("AC");
         fs::create_dir(&ac).unwrap();
         write(&ac.join("type"), "Mains\n");
+        write(&ac.join("online"), "1\n");
 
         let paths = find_battery_paths(tmp.path());
         assert_eq!(paths, vec![bat0]);
+
+        let ac_samples = ac_online_samples(tmp.path(), 1.0);
+        assert_eq!(ac_samples.len(), 1);
+        assert_eq!(ac_samples[0].kind, MetricKind::AcOnline);
+        assert_eq!(ac_samples[0].source, "AC");
+        assert_eq!(ac_samples[0].value, Some(1.0));
+        // No negotiated limits, no charger wattage
+        assert!(charger_power_samples(tmp.path(), 1.0).is_empty());
+
+        let usb = tmp.path().join("ucsi-source-psy-USBC000:001");
+        fs::create_dir(&usb).unwrap();
+        write(&usb.join("type"), "USB\n");
+        write(&usb.join("online"), "1\n");
+        write(&usb.join("usb_type"), "C [PD] PD_PPS\n");
+        write(&usb.join("voltage_max"), "20000000\n");
+        write(&usb.join("current_max"), "3250000\n");
+        let chargers = charger_power_samples(tmp.path(), 1.0);
+        assert_eq!(chargers.len(), 1);
+        assert_eq!(chargers[0].kind, MetricKind::ChargerPower);
+        assert!((chargers[0].value.unwrap() - 65.0).abs() < 1e-9);
+        assert_eq!(chargers[0].details["usb_type"], "PD");
+    }
+
+    #[test]
+    fn peripheral_batteries_are_tagged_by_model() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bat0 = tmp.path().join("BAT0");
+        fs::create_dir(&bat0).unwrap();
+        write(&bat0.join("type"), "Battery\n");
+
+        let mouse = tmp.path().join("hid-aa:bb:cc:dd:ee:ff-battery");
+        fs::create_dir(&mouse).unwrap();
+        write(&mouse.join("type"), "Battery\n");
+        write(&mouse.join("capacity"), "64\n");
+        write(&mouse.join("model_name"), "MX Master 3\n");
+        write(&mouse.join("status"), "Discharging\n");
+
+        let headset = tmp.path().join("headset_battery");
+        fs::create_dir(&headset).unwrap();
+        write(&headset.join("type"), "Battery\n");
+        write(&headset.join("scope"), "Device\n");
+        write(&headset.join("capacity"), "90\n");
+
+        let paths = find_peripheral_battery_paths(tmp.path());
+        assert_eq!(paths, vec![headset.clone(), mouse.clone()]);
+
+        let sample = peripheral_battery_metric(&mouse, 1.0).unwrap();
+        assert_eq!(sample.kind, MetricKind::PeripheralBattery);
+        assert_eq!(sample.source, "MX Master 3");
+        assert_eq!(sample.value, Some(64.0));
+        assert_eq!(sample.status.as_deref(), Some("Discharging"));
+
+        let fallback = peripheral_battery_metric(&headset, 1.0).unwrap();
+        assert_eq!(fallback.source, "headset_battery");
     }
 
     #[test]
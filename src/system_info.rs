@@ -0,0 +1,187 @@
+//! Identity of the machine collecting (host, kernel, CPU, RAM, battery), stored when the
+//! collector starts so reports can tell which machine and kernel produced the data.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::sysfs::find_battery_paths;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SystemInfo {
+    pub hostname: Option<String>,
+    pub kernel: Option<String>,
+    pub cpu_model: Option<String>,
+    pub memory_bytes: Option<u64>,
+    /// Model of each battery, comma separated when there are several
+    pub battery_model: Option<String>,
+    pub battery_serial: Option<String>,
+}
+
+/// A stored snapshot and the time the collector first saw it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SystemSnapshot {
+    pub ts: f64,
+    pub info: SystemInfo,
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    let raw = fs::read_to_string(path).ok()?;
+    let value = raw.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn cpu_model(cpuinfo: &str) -> Option<String> {
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        // `Hardware`/`Model` name the SoC or board on ARM, which lacks `model name`
+        matches!(key.trim(), "model name" | "Hardware" | "Model")
+            .then(|| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+fn memory_total_bytes(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Join one value per battery, or `None` when no battery reports it.
+fn join_batteries(values: &[Option<String>]) -> Option<String> {
+    if values.iter().all(Option::is_none) {
+        return None;
+    }
+    let values: Vec<&str> = values.iter().map(|v| v.as_deref().unwrap_or("?")).collect();
+    Some(values.join(", "))
+}
+
+/// Name that tags this machine's samples: the hostname, else the systemd machine id.
+pub fn local_host(proc_root: &Path, machine_id: &Path) -> Option<String> {
+    read_trimmed(&proc_root.join("sys/kernel/hostname")).or_else(|| read_trimmed(machine_id))
+}
+
+/// Kernel `boot_id`, which changes on every boot but survives suspend and resume.
+pub fn boot_id(proc_root: &Path) -> Option<String> {
+    read_trimmed(&proc_root.join("sys/kernel/random/boot_id"))
+}
+
+/// Read the snapshot from `/proc` and the batteries under `power_supply_root`, which is
+/// `None` when the battery collector is disabled.
+pub fn read_system_info(proc_root: &Path, power_supply_root: Option<&Path>) -> SystemInfo {
+    let mut batteries = power_supply_root
+        .map(find_battery_paths)
+        .unwrap_or_default();
+    // A stable order, so an unchanged machine compares equal to its last snapshot
+    batteries.sort();
+    let field = |name: &str| -> Vec<Option<String>> {
+        batteries
+            .iter()
+            .map(|path| read_trimmed(&path.join(name)))
+            .collect()
+    };
+    let models: Vec<Option<String>> = batteries
+        .iter()
+        .map(|path| {
+            let manufacturer = read_trimmed(&path.join("manufacturer"));
+            let model = read_trimmed(&path.join("model_name"));
+            match (manufacturer, model) {
+                (Some(manufacturer), Some(model)) => Some(format!("{manufacturer} {model}")),
+                (manufacturer, model) => model.or(manufacturer),
+            }
+        })
+        .collect();
+    SystemInfo {
+        hostname: read_trimmed(&proc_root.join("sys/kernel/hostname")),
+        kernel: read_trimmed(&proc_root.join("sys/kernel/osrelease")),
+        cpu_model: fs::read_to_string(proc_root.join("cpuinfo"))
+            .ok()
+            .and_then(|content| cpu_model(&content)),
+        memory_bytes: fs::read_to_string(proc_root.join("meminfo"))
+            .ok()
+            .and_then(|content| memory_total_bytes(&content)),
+        battery_model: join_batteries(&models),
+        battery_serial: join_batteries(&field("serial_number")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_proc_and_battery_identity() {
+        let tmp = tempfile::tempdir().unwrap();
+        let proc_root = tmp.path().join("proc");
+        fs::create_dir_all(proc_root.join("sys/kernel")).unwrap();
+        fs::write(proc_root.join("sys/kernel/hostname"), "thinkpad\n").unwrap();
+        fs::write(proc_root.join("sys/kernel/osrelease"), "6.9.1-arch1-1\n").unwrap();
+        fs::write(
+            proc_root.join("cpuinfo"),
+            "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel name\t: Intel(R) Core(TM) i7-8550U\n",
+        )
+        .unwrap();
+        fs::write(
+            proc_root.join("meminfo"),
+            "MemTotal:       16303772 kB\nMemFree: 1 kB\n",
+        )
+        .unwrap();
+
+        let supply = tmp.path().join("power_supply");
+        for (name, serial) in [("BAT0", Some(" 1234")), ("BAT1", None)] {
+            let bat = supply.join(name);
+            fs::create_dir_all(&bat).unwrap();
+            fs::write(bat.join("type"), "Battery\n").unwrap();
+            fs::write(bat.join("model_name"), "01AV431\n").unwrap();
+            if let Some(serial) = serial {
+                fs::write(bat.join("manufacturer"), "SMP\n").unwrap();
+                fs::write(bat.join("serial_number"), serial).unwrap();
+            }
+        }
+
+        let info = read_system_info(&proc_root, Some(&supply));
+        assert_eq!(info.hostname.as_deref(), Some("thinkpad"));
+        assert_eq!(info.kernel.as_deref(), Some("6.9.1-arch1-1"));
+        assert_eq!(
+            info.cpu_model.as_deref(),
+            Some("Intel(R) Core(TM) i7-8550U")
+        );
+        assert_eq!(info.memory_bytes, Some(16303772 * 1024));
+        assert_eq!(info.battery_model.as_deref(), Some("SMP 01AV431, 01AV431"));
+        assert_eq!(info.battery_serial.as_deref(), Some("1234, ?"));
+
+        assert_eq!(
+            local_host(&proc_root, &tmp.path().join("machine-id")).as_deref(),
+            Some("thinkpad")
+        );
+        fs::write(
+            tmp.path().join("machine-id"),
+            "b08dfa6083e7567a1921a715000001fb\n",
+        )
+        .unwrap();
+        assert_eq!(
+            local_host(&tmp.path().join("gone"), &tmp.path().join("machine-id")).as_deref(),
+            Some("b08dfa6083e7567a1921a715000001fb")
+        );
+
+        assert_eq!(boot_id(&proc_root), None);
+        fs::create_dir_all(proc_root.join("sys/kernel/random")).unwrap();
+        fs::write(
+            proc_root.join("sys/kernel/random/boot_id"),
+            "5c9f0e7a-1b2d-4e3f-8a6b-0c1d2e3f4a5b\n",
+        )
+        .unwrap();
+        assert_eq!(
+            boot_id(&proc_root).as_deref(),
+            Some("5c9f0e7a-1b2d-4e3f-8a6b-0c1d2e3f4a5b")
+        );
+
+        let without_battery = read_system_info(&proc_root, None);
+        assert_eq!(without_battery.battery_model, None);
+        assert_eq!(
+            cpu_model("Hardware\t: BCM2835\n").as_deref(),
+            Some("BCM2835")
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use log::debug;
+use serde_json::json;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Failed unit names stored per sample; the count is always exact.
+const MAX_LISTED_UNITS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SystemdState {
+    /// `running`, `degraded`, `starting`, ...
+    system_state: String,
+    failed_units: Vec<String>,
+}
+
+fn system_health_sample(ts: f64, state: &SystemdState) -> MetricSample {
+    let listed: Vec<&String> = state.failed_units.iter().take(MAX_LISTED_UNITS).collect();
+    MetricSample::new(
+        ts,
+        MetricKind::SystemHealth,
+        "systemd",
+        Some(state.failed_units.len() as f64),
+        Some("units"),
+        json!({ "system_state": state.system_state, "failed_units": listed }),
+    )
+}
+
+#[cfg(feature = "systemd")]
+fn query_state() -> Result<SystemdState> {
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    type Unit = (
+        String,
+        String,
+        String,
+        String,
+        String,
+        String,
+        OwnedObjectPath,
+        u32,
+        String,
+        OwnedObjectPath,
+    );
+
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.systemd1",
+        "/org/freedesktop/systemd1",
+        "org.freedesktop.systemd1.Manager",
+    )?;
+    let system_state: String = manager.get_property("SystemState")?;
+    let units: Vec<Unit> = manager.call("ListUnitsFiltered", &(vec!["failed"],))?;
+    let mut failed_units: Vec<String> = units.into_iter().map(|unit| unit.0).collect();
+    failed_units.sort();
+    Ok(SystemdState {
+        system_state,
+        failed_units,
+    })
+}
+
+#[cfg(not(feature = "systemd"))]
+fn query_state() -> Result<SystemdState> {
+    use std::process::Command;
+
+    // `is-system-running` exits non-zero when degraded but still prints the state.
+    let state = Command::new("systemctl")
+        .arg("is-system-running")
+        .output()?;
+    let system_state = String::from_utf8_lossy(&state.stdout).trim().to_string();
+    // `offline` means systemd is not the init system (e.g. inside a container).
+    if system_state.is_empty() || system_state == "offline" {
+        return Err(anyhow::anyhow!("systemd is not running ({system_state})"));
+    }
+    let units = Command::new("systemctl")
+        .args(["list-units", "--state=failed", "--no-legend", "--plain"])
+        .output()?;
+    Ok(SystemdState {
+        system_state,
+        failed_units: parse_failed_units(&String::from_utf8_lossy(&units.stdout)),
+    })
+}
+
+/// Unit names from `systemctl list-units --no-legend --plain` output.
+#[cfg_attr(feature = "systemd", allow(dead_code))]
+fn parse_failed_units(output: &str) -> Vec<String> {
+    let mut units: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect();
+    units.sort();
+    units
+}
+
+/// Number of failed units and the overall system state; nothing when systemd is unavailable.
+pub fn system_health_samples(ts: f64) -> Vec<MetricSample> {
+    match query_state() {
+        Ok(state) => vec![system_health_sample(ts, &state)],
+        Err(err) => {
+            debug!("systemd state unavailable: {err}");
+            Vec::new()
+        }
+    }
+}
+
+const ACPI_LID_DIR: &str = "/proc/acpi/button/lid";
+
+/// Session activity as seen by logind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LogindState {
+    /// All sessions idle (set by the desktop or `swayidle idlehint`)
+    idle: Option<bool>,
+    lid_closed: Option<bool>,
+}
+
+fn logind_state_samples(ts: f64, state: LogindState) -> Vec<MetricSample> {
+    [
+        (MetricKind::UserIdle, state.idle),
+        (MetricKind::LidClosed, state.lid_closed),
+    ]
+    .into_iter()
+    .filter_map(|(kind, flag)| {
+        flag.map(|flag| {
+            MetricSample::new(
+                ts,
+                kind,
+                "logind",
+                Some(f64::from(u8::from(flag))),
+                Some("bool"),
+                serde_json::Value::Null,
+            )
+        })
+    })
+    .collect()
+}
+
+#[cfg(feature = "systemd")]
+fn query_logind() -> Result<LogindState> {
+    use zbus::blocking::{Connection, Proxy};
+
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    Ok(LogindState {
+        idle: Some(manager.get_property("IdleHint")?),
+        lid_closed: Some(manager.get_property("LidClosed")?),
+    })
+}
+
+#[cfg(not(feature = "systemd"))]
+fn query_logind() -> Result<LogindState> {
+    use std::process::Command;
+
+    let output = Command::new("busctl")
+        .args([
+            "get-property",
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+            "IdleHint",
+            "LidClosed",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "busctl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let values: Vec<Option<bool>> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(parse_busctl_bool)
+        .collect();
+    Ok(LogindState {
+        idle: values.first().copied().flatten(),
+        lid_closed: values.get(1).copied().flatten(),
+    })
+}
+
+/// A boolean from `busctl get-property` output (`b true`).
+#[cfg_attr(feature = "systemd", allow(dead_code))]
+fn parse_busctl_bool(line: &str) -> Option<bool> {
+    match line.trim().strip_prefix("b ")? {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// `/proc/acpi/button/lid/*/state` contains e.g. `state:      closed`.
+fn parse_acpi_lid_state(content: &str) -> Option<bool> {
+    match content.strip_prefix("state:")?.trim() {
+        "closed" => Some(true),
+        "open" => Some(false),
+        _ => None,
+    }
+}
+
+fn acpi_lid_closed() -> Option<bool> {
+    std::fs::read_dir(ACPI_LID_DIR)
+        .ok()?
+        .flatten()
+        .find_map(|entry| std::fs::read_to_string(entry.path().join("state")).ok())
+        .and_then(|content| parse_acpi_lid_state(&content))
+}
+
+/// Idle hint and lid state; logind reports an always-open lid on machines without one,
+/// so the lid is only recorded when ACPI exposes a lid switch.
+pub fn logind_samples(ts: f64) -> Vec<MetricSample> {
+    let acpi_lid = acpi_lid_closed();
+    let mut state = query_logind().unwrap_or_else(|err| {
+        debug!("logind state unavailable: {err}");
+        LogindState {
+            idle: None,
+            lid_closed: None,
+        }
+    });
+    state.lid_closed = acpi_lid.map(|acpi| state.lid_closed.unwrap_or(acpi));
+    logind_state_samples(ts, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_units_become_health_sample() {
+        let output = "nginx.service loaded failed failed A high performance web server\nbackup.timer  loaded failed failed Nightly backup\n";
+        let state = SystemdState {
+            system_state: "degraded".to_string(),
+            failed_units: parse_failed_units(output),
+        };
+        let sample = system_health_sample(5.0, &state);
+        assert_eq!(sample.kind, MetricKind::SystemHealth);
+        assert_eq!(sample.value, Some(2.0));
+        assert_eq!(sample.details["system_state"], "degraded");
+        assert_eq!(
+            sample.details["failed_units"],
+            json!(["backup.timer", "nginx.service"])
+        );
+    }
+
+    #[test]
+    fn logind_flags_become_boolean_samples() {
+        assert_eq!(parse_busctl_bool("b true"), Some(true));
+        assert_eq!(parse_busctl_bool("s \"x\""), None);
+        assert_eq!(parse_acpi_lid_state("state:      closed\n"), Some(true));
+
+        let samples = logind_state_samples(
+            1.0,
+            LogindState {
+                idle: Some(false),
+                lid_closed: None,
+            },
+        );
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].kind, MetricKind::UserIdle);
+        assert_eq!(samples[0].value, Some(0.0));
+    }
+}
@@ -1,5 +1,7 @@
 use std::time::SystemTime;
 
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
 const SECONDS_PER_HOUR: u64 = 3600;
 const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
 const APPROX_DAYS_PER_MONTH: u64 = 30;
@@ -11,11 +13,17 @@ pub struct Timeframe {
     pub hours: u64,
     pub days: u64,
     pub months: u64,
+    /// Absolute end of the window; `None` for a window ending now
+    pub until: Option<f64>,
 }
 
 impl Timeframe {
+    /// Start of the window: `seconds` before `until`, or before `now` for a trailing one.
     pub fn since_timestamp(&self, now: Option<SystemTime>) -> Option<f64> {
         let seconds = self.seconds?;
+        if let Some(until) = self.until {
+            return Some(until - seconds);
+        }
         let reference = now.unwrap_or_else(SystemTime::now);
         let reference_secs = reference
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -31,6 +39,12 @@ pub enum TimeframeError {
     InvalidHours,
     #[error("{0} must be zero or greater")]
     NegativeValue(&'static str),
+    #[error("invalid window `{0}`; expected e.g. 6h, 7d, 2w, 3mo or all")]
+    InvalidWindow(String),
+    #[error("invalid time `{0}`; expected RFC3339 (2024-05-07T09:00:00+02:00), YYYY-MM-DD HH:MM or YYYY-MM-DD")]
+    InvalidTimestamp(String),
+    #[error("the window ends before it starts")]
+    EmptyWindow,
 }
 
 fn validate_non_negative(value: i64, name: &'static str) -> Result<(), TimeframeError> {
@@ -65,6 +79,7 @@ pub fn build_timeframe(
             hours: 0,
             days: 0,
             months: 0,
+            until: None,
         });
     }
 
@@ -82,6 +97,7 @@ pub fn build_timeframe(
             hours: 0,
             days: 0,
             months: months_u,
+            until: None,
         });
     }
 
@@ -95,6 +111,7 @@ pub fn build_timeframe(
             hours: 0,
             days: days_u,
             months: 0,
+            until: None,
         });
     }
 
@@ -115,9 +132,109 @@ pub fn build_timeframe(
         hours: hours_u,
         days: 0,
         months: 0,
+        until: None,
     })
 }
 
+/// Parse a compact window such as `24h`, `7d`, `2w`, `3mo` or `all`.
+pub fn parse_window(raw: &str) -> Result<Timeframe, TimeframeError> {
+    let raw = raw.trim();
+    if raw == "all" {
+        return build_timeframe(0, 0, 0, true);
+    }
+    let invalid = || TimeframeError::InvalidWindow(raw.to_string());
+    let split = raw
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (number, unit) = raw.split_at(split);
+    let value: i64 = number.parse().map_err(|_| invalid())?;
+    if value == 0 {
+        return Err(invalid());
+    }
+    match unit {
+        "h" => build_timeframe(value, 0, 0, false),
+        "d" => build_timeframe(0, value, 0, false),
+        "w" => build_timeframe(0, value * 7, 0, false),
+        "mo" => build_timeframe(0, 0, value, false),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse an absolute time: RFC3339, or `YYYY-MM-DD[ HH:MM[:SS]]` in local time. A bare
+/// date is its midnight, or the next midnight when `end_of_day` is set, so a date given as
+/// the end of a window includes that day.
+pub fn parse_timestamp(raw: &str, end_of_day: bool) -> Result<f64, TimeframeError> {
+    let raw = raw.trim();
+    let invalid = || TimeframeError::InvalidTimestamp(raw.to_string());
+    if let Ok(time) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(time.timestamp() as f64);
+    }
+    let naive = match NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        Ok(date) => {
+            let date = if end_of_day {
+                date.succ_opt().ok_or_else(invalid)?
+            } else {
+                date
+            };
+            date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?
+        }
+        Err(_) => [
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%dT%H:%M",
+        ]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+        .ok_or_else(invalid)?,
+    };
+    let local = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(invalid)?;
+    Ok(local.timestamp() as f64)
+}
+
+fn time_label(ts: f64) -> String {
+    Local
+        .timestamp_opt(ts as i64, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d_%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Pin `timeframe` to absolute bounds: from `since` (replacing its length) and up to
+/// `until` (instead of `now`). Either bound may be missing.
+pub fn bound_timeframe(
+    timeframe: Timeframe,
+    since: Option<f64>,
+    until: Option<f64>,
+    now: f64,
+) -> Result<Timeframe, TimeframeError> {
+    let end = until.unwrap_or(now);
+    let mut timeframe = match since {
+        Some(since) if since >= end => return Err(TimeframeError::EmptyWindow),
+        Some(since) => Timeframe {
+            label: format!("since_{}", time_label(since)),
+            seconds: Some(end - since),
+            hours: 0,
+            days: 0,
+            months: 0,
+            until: None,
+        },
+        None => timeframe,
+    };
+    if let Some(until) = until {
+        let label = match since {
+            Some(since) => time_label(since),
+            None => timeframe.label,
+        };
+        timeframe.label = format!("{label}_to_{}", time_label(until));
+        timeframe.until = Some(until);
+    }
+    Ok(timeframe)
+}
+
 #[allow(dead_code)]
 pub fn timeframe_seconds(timeframe: &Timeframe) -> Option<f64> {
     timeframe.seconds
@@ -170,6 +287,54 @@ mod tests {
         assert_eq!(timeframe.label, "all");
     }
 
+    #[test]
+    fn compact_windows_parse() {
+        assert_eq!(parse_window("24h").unwrap().label, "last_24_hours");
+        assert_eq!(parse_window("2w").unwrap().days, 14);
+        assert_eq!(parse_window("3mo").unwrap().months, 3);
+        assert_eq!(parse_window("all").unwrap().seconds, None);
+        for raw in ["", "h", "0d", "5m", "12"] {
+            assert!(matches!(
+                parse_window(raw),
+                Err(TimeframeError::InvalidWindow(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn absolute_bounds_pin_the_window() {
+        let since = parse_timestamp("2024-05-07T09:00:00Z", false).unwrap();
+        let until = parse_timestamp("2024-05-07T17:00:00+00:00", true).unwrap();
+        assert_eq!(until - since, 8.0 * 3600.0);
+        let trailing = build_timeframe(6, 0, 0, false).unwrap();
+        let office = bound_timeframe(trailing.clone(), Some(since), Some(until), 2e9).unwrap();
+        assert_eq!(office.seconds, Some(8.0 * 3600.0));
+        assert_eq!(office.until, Some(until));
+        assert_eq!(office.since_timestamp(None), Some(since));
+
+        // A window length counts back from `until`
+        let before = bound_timeframe(trailing.clone(), None, Some(until), 2e9).unwrap();
+        assert_eq!(before.since_timestamp(None), Some(until - 6.0 * 3600.0));
+        assert!(before.label.starts_with("last_6_hours_to_"));
+        let open = bound_timeframe(trailing.clone(), Some(since), None, since + 60.0).unwrap();
+        assert_eq!((open.seconds, open.until), (Some(60.0), None));
+        assert!(matches!(
+            bound_timeframe(trailing, Some(until), Some(since), 2e9),
+            Err(TimeframeError::EmptyWindow)
+        ));
+
+        let day = parse_timestamp("2024-05-07", false).unwrap();
+        assert_eq!(parse_timestamp("2024-05-07", true).unwrap() - day, 86400.0);
+        assert_eq!(
+            parse_timestamp("2024-05-07 09:30", false).unwrap() - day,
+            9.5 * 3600.0
+        );
+        assert!(matches!(
+            parse_timestamp("tuesday", false),
+            Err(TimeframeError::InvalidTimestamp(_))
+        ));
+    }
+
     #[test]
     fn invalid_inputs_raise() {
         assert!(matches!(
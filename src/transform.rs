@@ -0,0 +1,134 @@
+//! Config rules that fix up samples before they are stored, for sensors that report in the
+//! wrong unit or with a constant offset on specific hardware.
+
+use serde::{Deserialize, Deserializer};
+
+use crate::metrics::{MetricKind, MetricSample};
+
+/// One `[[transform]]` rule. Rules run in config order and every matching rule applies, so
+/// a later rule sees the source name given by an earlier `rename`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Transform {
+    #[serde(deserialize_with = "deserialize_kind")]
+    pub kind: MetricKind,
+    /// Only samples from this source; every source of the kind when unset
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Multiply the value, e.g. `0.001` for a sensor reporting milliwatts as watts
+    #[serde(default)]
+    pub scale: Option<f64>,
+    /// Added after scaling
+    #[serde(default)]
+    pub offset: Option<f64>,
+    /// Store the sample under this source name instead
+    #[serde(default)]
+    pub rename: Option<String>,
+    /// Replace the stored unit
+    #[serde(default)]
+    pub unit: Option<String>,
+    /// Do not store matching samples at all
+    #[serde(default)]
+    pub drop: bool,
+}
+
+fn deserialize_kind<'de, D>(deserializer: D) -> Result<MetricKind, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    MetricKind::from_label(&raw)
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown metric kind `{raw}`")))
+}
+
+impl Transform {
+    fn matches(&self, sample: &MetricSample) -> bool {
+        sample.kind == self.kind
+            && self
+                .source
+                .as_ref()
+                .is_none_or(|source| &sample.source == source)
+    }
+
+    /// Apply the rule to a matching sample; `None` when it is dropped.
+    fn apply(&self, mut sample: MetricSample) -> Option<MetricSample> {
+        if self.drop {
+            return None;
+        }
+        if let Some(value) = sample.value.as_mut() {
+            *value = *value * self.scale.unwrap_or(1.0) + self.offset.unwrap_or(0.0);
+        }
+        if let Some(source) = &self.rename {
+            sample.source = source.clone();
+        }
+        if let Some(unit) = &self.unit {
+            sample.unit = Some(unit.clone());
+        }
+        Some(sample)
+    }
+}
+
+/// Run the samples of one collection through the configured rules.
+pub fn apply_transforms(transforms: &[Transform], samples: Vec<MetricSample>) -> Vec<MetricSample> {
+    if transforms.is_empty() {
+        return samples;
+    }
+    samples
+        .into_iter()
+        .filter_map(|sample| {
+            transforms.iter().try_fold(sample, |sample, rule| {
+                if rule.matches(&sample) {
+                    rule.apply(sample)
+                } else {
+                    Some(sample)
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample(kind: MetricKind, source: &str, value: f64) -> MetricSample {
+        MetricSample::new(0.0, kind, source, Some(value), Some("C"), json!({}))
+    }
+
+    #[test]
+    fn rules_scale_rename_and_drop_in_order() {
+        let config = crate::config::parse_config(
+            r#"
+            [[transform]]
+            kind = "temperature"
+            source = "acpitz"
+            scale = 0.1
+            offset = -2
+            rename = "board"
+
+            [[transform]]
+            kind = "temperature"
+            source = "board"
+            unit = "celsius"
+
+            [[transform]]
+            kind = "power_draw"
+            drop = true
+            "#,
+        )
+        .unwrap();
+
+        let samples = vec![
+            sample(MetricKind::Temperature, "acpitz", 450.0),
+            sample(MetricKind::Temperature, "coretemp", 50.0),
+            sample(MetricKind::PowerDraw, "BAT0", 9.0),
+        ];
+        let out = apply_transforms(&config.transforms, samples);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].source, "board");
+        assert_eq!(out[0].value, Some(43.0));
+        assert_eq!(out[0].unit.as_deref(), Some("celsius"));
+        assert_eq!(out[1].source, "coretemp");
+        assert_eq!(out[1].value, Some(50.0));
+    }
+}